@@ -1,10 +1,18 @@
 //! Execution context for agent sessions.
 
-use std::{collections::HashMap, path::PathBuf};
+use std::{
+    any::{Any, TypeId},
+    collections::HashMap,
+    fmt,
+    path::PathBuf,
+    sync::{Arc, Mutex, PoisonError},
+};
 
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
 
+use crate::secrets::{REDACTED, Redactor, SecretValue};
+
 /// Generic execution context for agent sessions.
 ///
 /// Unlike vibe-kanban's Task/Project model, this is fully generic
@@ -17,6 +25,19 @@ pub struct ExecutionContext {
     /// Arbitrary metadata for app-specific needs.
     #[serde(default)]
     pub metadata: HashMap<String, Value>,
+
+    /// Secrets injected into the child environment.
+    ///
+    /// Values are redacted when serialized and from session output.
+    #[serde(default)]
+    pub secrets: HashMap<String, SecretValue>,
+
+    /// State executors keep across the processes of a session, e.g. its
+    /// follow-ups and resumes.
+    ///
+    /// Not serialized; the session manager passes in each session's state.
+    #[serde(skip)]
+    pub session_state: SessionState,
 }
 
 impl ExecutionContext {
@@ -26,6 +47,8 @@ impl ExecutionContext {
         Self {
             working_dir,
             metadata: HashMap::new(),
+            secrets: HashMap::new(),
+            session_state: SessionState::default(),
         }
     }
 
@@ -35,6 +58,8 @@ impl ExecutionContext {
         Self {
             working_dir,
             metadata,
+            secrets: HashMap::new(),
+            session_state: SessionState::default(),
         }
     }
 
@@ -48,4 +73,54 @@ impl ExecutionContext {
     pub fn set_metadata(&mut self, key: impl Into<String>, value: Value) {
         self.metadata.insert(key.into(), value);
     }
+
+    /// Set a secret to be exposed to the child as an environment variable.
+    pub fn set_secret(&mut self, key: impl Into<String>, value: impl Into<String>) {
+        self.secrets.insert(key.into(), SecretValue::new(value));
+    }
+
+    /// Environment variables to inject into the child process.
+    ///
+    /// Secrets lost to a serialization round-trip are skipped.
+    pub fn secret_env(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.secrets
+            .iter()
+            .filter(|(_, v)| v.expose() != REDACTED)
+            .map(|(k, v)| (k.as_str(), v.expose()))
+    }
+
+    /// Build a redactor for this context's secrets.
+    #[must_use]
+    pub fn redactor(&self) -> Redactor {
+        Redactor::new(self.secrets.values().cloned())
+    }
+}
+
+/// Values kept across the processes of a session, one per type, e.g. the
+/// tool calls a user chose to always allow.
+///
+/// Clones share the values.
+#[derive(Clone, Default)]
+pub struct SessionState(Arc<Mutex<HashMap<TypeId, Arc<dyn Any + Send + Sync>>>>);
+
+impl SessionState {
+    /// The value of type `T`, inserting `T::default()` if there is none yet.
+    #[must_use]
+    pub fn get_or_default<T: Any + Default + Send + Sync>(&self) -> Arc<T> {
+        let value = Arc::clone(
+            self.0
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner)
+                .entry(TypeId::of::<T>())
+                .or_insert_with(|| Arc::new(T::default())),
+        );
+        // Only ever inserted under its own type's ID.
+        value.downcast().unwrap_or_default()
+    }
+}
+
+impl fmt::Debug for SessionState {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SessionState").finish_non_exhaustive()
+    }
 }
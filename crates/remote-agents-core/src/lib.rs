@@ -4,14 +4,17 @@
 //! - `MsgStore` - Broadcast + history for reconnection support
 //! - `LogMsg` - Typed log message enum
 //! - `ExecutionContext` - Generic context for session execution
+//! - `Redactor` - Secret redaction for output and storage
 //! - Storage and Executor traits
 
 pub mod context;
 pub mod log_msg;
 pub mod msg_store;
+pub mod secrets;
 pub mod traits;
 
-pub use context::ExecutionContext;
+pub use context::{ExecutionContext, SessionState};
 pub use log_msg::LogMsg;
 pub use msg_store::MsgStore;
-pub use traits::{Executor, SessionStorage};
+pub use secrets::{Redactor, SecretValue, StreamRedactor};
+pub use traits::{AuditStorage, EventStorage, Executor, OutputBlobStore, SessionStorage};
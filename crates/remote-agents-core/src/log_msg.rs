@@ -10,6 +10,8 @@ pub const EV_JSON_PATCH: &str = "json_patch";
 pub const EV_SESSION_ID: &str = "session_id";
 pub const EV_READY: &str = "ready";
 pub const EV_FINISHED: &str = "finished";
+pub const EV_ASSISTANT_TEXT: &str = "assistant_text";
+pub const EV_STALLED: &str = "stalled";
 
 /// Typed log message for agent output.
 #[derive(Clone, Debug, Serialize, Deserialize)]
@@ -26,6 +28,10 @@ pub enum LogMsg {
     Ready,
     /// Agent has finished.
     Finished,
+    /// Incremental assistant text, streamed ahead of the complete message.
+    AssistantText(String),
+    /// The agent has produced no output for this many seconds.
+    Stalled { silent_secs: u64 },
 }
 
 impl LogMsg {
@@ -39,6 +45,8 @@ impl LogMsg {
             Self::SessionId(_) => EV_SESSION_ID,
             Self::Ready => EV_READY,
             Self::Finished => EV_FINISHED,
+            Self::AssistantText(_) => EV_ASSISTANT_TEXT,
+            Self::Stalled { .. } => EV_STALLED,
         }
     }
 
@@ -56,6 +64,8 @@ impl LogMsg {
             Self::SessionId(s) => EV_SESSION_ID.len() + s.len() + OVERHEAD,
             Self::Ready => EV_READY.len() + OVERHEAD,
             Self::Finished => EV_FINISHED.len() + OVERHEAD,
+            Self::AssistantText(s) => EV_ASSISTANT_TEXT.len() + s.len() + OVERHEAD,
+            Self::Stalled { .. } => EV_STALLED.len() + 20 + OVERHEAD,
         }
     }
 
@@ -75,6 +85,10 @@ impl LogMsg {
             Self::SessionId(s) => Event::default().event(EV_SESSION_ID).data(s.clone()),
             Self::Ready => Event::default().event(EV_READY).data(""),
             Self::Finished => Event::default().event(EV_FINISHED).data(""),
+            Self::AssistantText(s) => Event::default().event(EV_ASSISTANT_TEXT).data(s.clone()),
+            Self::Stalled { silent_secs } => Event::default()
+                .event(EV_STALLED)
+                .data(silent_secs.to_string()),
         }
     }
 
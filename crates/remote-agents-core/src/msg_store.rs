@@ -9,7 +9,7 @@ use futures::{StreamExt, future};
 use tokio::sync::broadcast;
 use tokio_stream::wrappers::BroadcastStream;
 
-use crate::LogMsg;
+use crate::{LogMsg, Redactor};
 
 /// Default history size limit (100 MB).
 const HISTORY_BYTES: usize = 100_000 * 1024;
@@ -32,6 +32,7 @@ struct Inner {
 pub struct MsgStore {
     inner: RwLock<Inner>,
     sender: broadcast::Sender<LogMsg>,
+    redactor: Redactor,
 }
 
 impl Default for MsgStore {
@@ -44,6 +45,12 @@ impl MsgStore {
     /// Create a new message store.
     #[must_use]
     pub fn new() -> Self {
+        Self::with_redactor(Redactor::default())
+    }
+
+    /// Create a message store that redacts secrets from every pushed message.
+    #[must_use]
+    pub fn with_redactor(redactor: Redactor) -> Self {
         let (sender, _) = broadcast::channel(10000);
         Self {
             inner: RwLock::new(Inner {
@@ -51,11 +58,19 @@ impl MsgStore {
                 total_bytes: 0,
             }),
             sender,
+            redactor,
         }
     }
 
+    /// Get the redactor applied to pushed messages.
+    #[must_use]
+    pub const fn redactor(&self) -> &Redactor {
+        &self.redactor
+    }
+
     /// Push a message to both live listeners and history.
     pub fn push(&self, msg: LogMsg) {
+        let msg = self.redactor.redact_msg(msg);
         let _ = self.sender.send(msg.clone()); // live listeners
         let bytes = msg.approx_bytes();
 
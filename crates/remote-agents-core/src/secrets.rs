@@ -0,0 +1,269 @@
+//! Secret values and output redaction.
+
+use std::{
+    borrow::Cow,
+    fmt::{self, Write as _},
+};
+
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::LogMsg;
+
+/// Placeholder substituted for secret values.
+pub const REDACTED: &str = "[REDACTED]";
+
+/// A secret value that never appears in `Debug` output or serialized form.
+///
+/// Secrets do not survive a serialization round-trip: a deserialized secret
+/// holds the `REDACTED` placeholder, so apps must re-supply secrets when
+/// reusing a stored context.
+#[derive(Clone, PartialEq, Eq)]
+pub struct SecretValue(String);
+
+impl SecretValue {
+    /// Wrap a secret value.
+    #[must_use]
+    pub fn new(value: impl Into<String>) -> Self {
+        Self(value.into())
+    }
+
+    /// Get the underlying secret value.
+    #[must_use]
+    pub fn expose(&self) -> &str {
+        &self.0
+    }
+}
+
+impl fmt::Debug for SecretValue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(REDACTED)
+    }
+}
+
+impl Serialize for SecretValue {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(REDACTED)
+    }
+}
+
+impl<'de> Deserialize<'de> for SecretValue {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        String::deserialize(deserializer).map(Self)
+    }
+}
+
+/// Replaces known secret values in text with `REDACTED`.
+///
+/// Secrets are also replaced as they appear escaped in JSON strings, e.g.
+/// in a tool call's input, with `\"`, `\\`, and `\u` escapes.
+#[derive(Debug, Clone, Default)]
+pub struct Redactor {
+    secrets: Vec<SecretValue>,
+}
+
+impl Redactor {
+    /// Create a redactor for the given secret values.
+    #[must_use]
+    pub fn new(secrets: impl IntoIterator<Item = SecretValue>) -> Self {
+        let mut secrets: Vec<SecretValue> = secrets
+            .into_iter()
+            .filter(|s| !s.expose().is_empty() && s.expose() != REDACTED)
+            .flat_map(|s| json_escaped(s.expose()).into_iter().chain([s]))
+            .collect();
+        // Longest first so overlapping secrets are fully replaced.
+        secrets.sort_by_key(|s| std::cmp::Reverse(s.expose().len()));
+        secrets.dedup();
+        Self { secrets }
+    }
+
+    /// A redactor for text streamed in deltas.
+    #[must_use]
+    pub fn streaming(&self) -> StreamRedactor {
+        StreamRedactor {
+            redactor: self.clone(),
+            held: String::new(),
+        }
+    }
+
+    /// Whether there is nothing to redact.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.secrets.is_empty()
+    }
+
+    /// Redact secret values from text.
+    #[must_use]
+    pub fn redact<'a>(&self, text: &'a str) -> Cow<'a, str> {
+        let mut result = Cow::Borrowed(text);
+        for secret in &self.secrets {
+            if result.contains(secret.expose()) {
+                result = Cow::Owned(result.replace(secret.expose(), REDACTED));
+            }
+        }
+        result
+    }
+
+    /// Redact secret values from a log message.
+    #[must_use]
+    pub fn redact_msg(&self, msg: LogMsg) -> LogMsg {
+        if self.is_empty() {
+            return msg;
+        }
+        match msg {
+            LogMsg::Stdout(s) => LogMsg::Stdout(self.redact(&s).into_owned()),
+            LogMsg::Stderr(s) => LogMsg::Stderr(self.redact(&s).into_owned()),
+            LogMsg::AssistantText(s) => LogMsg::AssistantText(self.redact(&s).into_owned()),
+            LogMsg::JsonPatch(patch) => {
+                let Ok(json) = serde_json::to_string(&patch) else {
+                    return LogMsg::JsonPatch(patch);
+                };
+                match self.redact(&json) {
+                    Cow::Borrowed(_) => LogMsg::JsonPatch(patch),
+                    Cow::Owned(redacted) => serde_json::from_str(&redacted)
+                        .map_or(LogMsg::JsonPatch(patch), LogMsg::JsonPatch),
+                }
+            }
+            other => other,
+        }
+    }
+}
+
+/// Redacts secrets from text streamed in deltas, e.g. `AssistantText`,
+/// including secrets split across deltas.
+///
+/// The end of each delta, up to one byte shorter than the longest secret,
+/// is held back until the next delta or `flush`, in case it starts a
+/// secret.
+#[derive(Debug, Clone)]
+pub struct StreamRedactor {
+    redactor: Redactor,
+    held: String,
+}
+
+impl StreamRedactor {
+    /// Redact `delta`, returning the text that can be sent on, which may be
+    /// empty.
+    pub fn push(&mut self, delta: &str) -> String {
+        let mut text = std::mem::take(&mut self.held);
+        text.push_str(delta);
+        let longest = self
+            .redactor
+            .secrets
+            .first()
+            .map_or(0, |s| s.expose().len());
+        let mut cut = text.len().saturating_sub(longest.saturating_sub(1));
+        while !text.is_char_boundary(cut) {
+            cut -= 1;
+        }
+        // A secret starting before the cut is sent whole, to be replaced.
+        while let Some(end) = self
+            .redactor
+            .secrets
+            .iter()
+            .flat_map(|s| text.match_indices(s.expose()))
+            .map(|(start, secret)| (start, start + secret.len()))
+            .filter(|&(start, end)| start < cut && end > cut)
+            .map(|(_, end)| end)
+            .max()
+        {
+            cut = end;
+        }
+        self.held = text.split_off(cut);
+        self.redactor.redact(&text).into_owned()
+    }
+
+    /// Redact and return the text held back, e.g. once the stream ends.
+    pub fn flush(&mut self) -> String {
+        let held = std::mem::take(&mut self.held);
+        self.redactor.redact(&held).into_owned()
+    }
+}
+
+/// The forms of `secret` inside a JSON string other than itself: as
+/// `serde_json` escapes it, and with every non-ASCII character escaped too,
+/// as e.g. Python's `json.dumps` writes it.
+fn json_escaped(secret: &str) -> Vec<SecretValue> {
+    let Ok(quoted) = serde_json::to_string(secret) else {
+        return Vec::new();
+    };
+    let escaped = &quoted[1..quoted.len() - 1];
+    let mut ascii = String::with_capacity(escaped.len());
+    for c in escaped.chars() {
+        if c.is_ascii() {
+            ascii.push(c);
+            continue;
+        }
+        for unit in c.encode_utf16(&mut [0; 2]) {
+            let _ = write!(ascii, "\\u{unit:04x}");
+        }
+    }
+    [escaped.to_string(), ascii]
+        .into_iter()
+        .filter(|form| form != secret)
+        .map(SecretValue)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ExecutionContext;
+
+    #[test]
+    fn test_redact_output() {
+        let redactor = Redactor::new([SecretValue::new("sk-123"), SecretValue::new("sk-123456")]);
+        assert_eq!(redactor.redact("key=sk-123456;"), "key=[REDACTED];");
+        assert!(matches!(redactor.redact("nothing here"), Cow::Borrowed(_)));
+
+        let LogMsg::Stdout(s) = redactor.redact_msg(LogMsg::Stdout("using sk-123".into())) else {
+            panic!("Wrong message type");
+        };
+        assert_eq!(s, "using [REDACTED]");
+    }
+
+    #[test]
+    fn test_redact_escaped() {
+        let redactor = Redactor::new([SecretValue::new(r#"pa"ss\wörd"#)]);
+        let input = serde_json::json!({ "command": r#"login --password 'pa"ss\wörd'"# });
+        let patch: json_patch::Patch = serde_json::from_value(serde_json::json!([
+            { "op": "add", "path": "/entries/0", "value": input },
+        ]))
+        .unwrap();
+        let LogMsg::JsonPatch(patch) = redactor.redact_msg(LogMsg::JsonPatch(patch)) else {
+            panic!("Wrong message type");
+        };
+        let json = serde_json::to_string(&patch).unwrap();
+        assert!(json.contains("login --password '[REDACTED]'"), "{json}");
+
+        // As e.g. Python writes it, with non-ASCII characters escaped.
+        let ascii = r#"{"password": "pa\"ss\\w\u00f6rd"}"#;
+        assert_eq!(redactor.redact(ascii), r#"{"password": "[REDACTED]"}"#);
+    }
+
+    #[test]
+    fn test_redact_split_across_deltas() {
+        let redactor = Redactor::new([SecretValue::new("sk-123"), SecretValue::new("sk-123456")]);
+        let mut stream = redactor.streaming();
+        let deltas = ["The key is sk-1", "23", "456, and é", "lse sk-12", "3."];
+        let mut sent: Vec<String> = deltas.iter().map(|delta| stream.push(delta)).collect();
+        sent.push(stream.flush());
+        for text in &sent {
+            assert!(!text.contains("23") && !text.ends_with("sk-"), "{sent:?}");
+        }
+        assert_eq!(sent.concat(), "The key is [REDACTED], and élse [REDACTED].");
+    }
+
+    #[test]
+    fn test_secrets_not_serialized() {
+        let mut ctx = ExecutionContext::new("/tmp".into());
+        ctx.set_secret("API_KEY", "sk-123");
+
+        let json = serde_json::to_string(&ctx).unwrap();
+        assert!(!json.contains("sk-123"));
+        assert!(!format!("{ctx:?}").contains("sk-123"));
+
+        let parsed: ExecutionContext = serde_json::from_str(&json).unwrap();
+        assert_eq!(parsed.secret_env().count(), 0);
+        assert!(parsed.redactor().is_empty());
+    }
+}
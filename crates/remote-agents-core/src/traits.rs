@@ -3,15 +3,24 @@
 use std::path::PathBuf;
 
 use async_trait::async_trait;
+use futures::{StreamExt, future::BoxFuture, stream::BoxStream};
 use serde::{Deserialize, Serialize};
+use serde_json::Value;
 use thiserror::Error;
+use tokio::io::{AsyncBufReadExt, AsyncRead, BufReader};
 use uuid::Uuid;
 
-use crate::ExecutionContext;
+use crate::{ExecutionContext, LogMsg};
 
 /// Session identifier.
 pub type SessionId = Uuid;
 
+/// Name of a run grouping related sessions (e.g. plan, implement, review).
+pub type RunId = String;
+
+/// Identifier of the tenant (user, team, ...) that owns a session.
+pub type TenantId = String;
+
 /// Session status.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 #[serde(rename_all = "snake_case")]
@@ -28,6 +37,14 @@ pub enum SessionStatus {
     Cancelled,
 }
 
+impl SessionStatus {
+    /// Whether the session has finished and will not change status again.
+    #[must_use]
+    pub const fn is_terminal(self) -> bool {
+        matches!(self, Self::Completed | Self::Failed | Self::Cancelled)
+    }
+}
+
 /// Session filter for queries.
 #[derive(Debug, Clone, Default)]
 pub struct SessionFilter {
@@ -35,10 +52,75 @@ pub struct SessionFilter {
     pub status: Option<SessionStatus>,
     /// Filter by working directory.
     pub working_dir: Option<PathBuf>,
+    /// Only sessions created at or after this timestamp (Unix epoch seconds).
+    pub created_after: Option<i64>,
+    /// Only sessions created before this timestamp (Unix epoch seconds).
+    pub created_before: Option<i64>,
+    /// Case-insensitive text matched against the prompt and metadata values.
+    pub query: Option<String>,
+    /// Filter by run.
+    pub run_id: Option<RunId>,
+    /// Filter by tenant.
+    pub tenant_id: Option<TenantId>,
+    /// Include soft-deleted sessions, which are otherwise left out.
+    pub include_deleted: bool,
     /// Limit results.
     pub limit: Option<usize>,
 }
 
+impl SessionFilter {
+    /// Check whether a session matches this filter (ignoring `limit`).
+    #[must_use]
+    pub fn matches(&self, session: &Session) -> bool {
+        if session.deleted && !self.include_deleted {
+            return false;
+        }
+        if self.status.is_some_and(|status| session.status != status) {
+            return false;
+        }
+        if let Some(ref working_dir) = self.working_dir {
+            if session.context.working_dir != *working_dir {
+                return false;
+            }
+        }
+        if self
+            .run_id
+            .as_ref()
+            .is_some_and(|run_id| session.run_id.as_ref() != Some(run_id))
+        {
+            return false;
+        }
+        if self
+            .tenant_id
+            .as_ref()
+            .is_some_and(|tenant_id| session.tenant_id.as_ref() != Some(tenant_id))
+        {
+            return false;
+        }
+        if self.created_after.is_some_and(|t| session.created_at < t) {
+            return false;
+        }
+        if self.created_before.is_some_and(|t| session.created_at >= t) {
+            return false;
+        }
+        if let Some(ref query) = self.query {
+            let query = query.to_lowercase();
+            let in_prompt = session
+                .prompt
+                .as_ref()
+                .is_some_and(|p| p.to_lowercase().contains(&query));
+            let in_metadata = session.context.metadata.values().any(|v| {
+                let text = v.as_str().map_or_else(|| v.to_string(), str::to_string);
+                text.to_lowercase().contains(&query)
+            });
+            if !in_prompt && !in_metadata {
+                return false;
+            }
+        }
+        true
+    }
+}
+
 /// Persisted session data.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Session {
@@ -46,14 +128,36 @@ pub struct Session {
     pub id: SessionId,
     /// Execution context.
     pub context: ExecutionContext,
+    /// Prompt the session was started with.
+    pub prompt: Option<String>,
     /// Current status.
     pub status: SessionStatus,
     /// Agent session ID (for follow-up).
     pub agent_session_id: Option<String>,
+    /// Session this one was continued from, if it is a follow-up.
+    pub parent_session_id: Option<SessionId>,
+    /// Run the session belongs to, if any.
+    #[serde(default)]
+    pub run_id: Option<RunId>,
+    /// Tenant owning the session, if any.
+    #[serde(default)]
+    pub tenant_id: Option<TenantId>,
+    /// Exit code of the agent process, once it has exited.
+    pub exit_code: Option<i32>,
+    /// Why the session ended with its status, if not self-explanatory.
+    #[serde(default)]
+    pub status_reason: Option<String>,
+    /// Whether the session has been soft-deleted.
+    #[serde(default)]
+    pub deleted: bool,
     /// Creation timestamp (Unix epoch seconds).
     pub created_at: i64,
     /// Last update timestamp.
     pub updated_at: i64,
+    /// Version of the session's status and context, incremented by each
+    /// `update_status` and `update_context`.
+    #[serde(default)]
+    pub version: u64,
 }
 
 /// Storage error.
@@ -61,6 +165,8 @@ pub struct Session {
 pub enum StorageError {
     #[error("Session not found: {0}")]
     NotFound(SessionId),
+    #[error("Session {0} was modified concurrently")]
+    Conflict(SessionId),
     #[error("Storage error: {0}")]
     Internal(String),
 }
@@ -75,7 +181,26 @@ pub trait SessionStorage: Send + Sync {
     async fn get(&self, id: SessionId) -> Result<Option<Session>, StorageError>;
 
     /// Update session status.
-    async fn update_status(&self, id: SessionId, status: SessionStatus) -> Result<(), StorageError>;
+    ///
+    /// With `expected_version`, the update only applies if the session is
+    /// still at that version (see `Session::version`), and otherwise fails
+    /// with `StorageError::Conflict`.
+    async fn update_status(
+        &self,
+        id: SessionId,
+        status: SessionStatus,
+        expected_version: Option<u64>,
+    ) -> Result<(), StorageError>;
+
+    /// Replace the session's execution context.
+    ///
+    /// `expected_version` behaves as for `update_status`.
+    async fn update_context(
+        &self,
+        id: SessionId,
+        ctx: &ExecutionContext,
+        expected_version: Option<u64>,
+    ) -> Result<(), StorageError>;
 
     /// Set agent session ID (for follow-up support).
     async fn set_agent_session_id(
@@ -84,6 +209,52 @@ pub trait SessionStorage: Send + Sync {
         agent_session_id: String,
     ) -> Result<(), StorageError>;
 
+    /// Record the exit code of the agent process.
+    async fn set_exit_code(&self, id: SessionId, exit_code: i32) -> Result<(), StorageError>;
+
+    /// Record why the session ended with its status (e.g. a limit was exceeded).
+    async fn set_status_reason(&self, id: SessionId, reason: String) -> Result<(), StorageError>;
+
+    /// Set the prompt the session was started with.
+    async fn set_prompt(&self, id: SessionId, prompt: String) -> Result<(), StorageError>;
+
+    /// Record the session this one was continued from.
+    async fn set_parent_session_id(
+        &self,
+        id: SessionId,
+        parent_session_id: SessionId,
+    ) -> Result<(), StorageError>;
+
+    /// Add the session to a run.
+    async fn set_run_id(&self, id: SessionId, run_id: RunId) -> Result<(), StorageError>;
+
+    /// Assign the session to a tenant.
+    async fn set_tenant_id(&self, id: SessionId, tenant_id: TenantId) -> Result<(), StorageError>;
+
+    /// Mark a session deleted, leaving it out of `list` results unless
+    /// `SessionFilter::include_deleted` is set. Its data is kept, so it can
+    /// be brought back with `restore`.
+    async fn soft_delete(&self, id: SessionId) -> Result<(), StorageError>;
+
+    /// Undo a `soft_delete`.
+    async fn restore(&self, id: SessionId) -> Result<(), StorageError>;
+
+    /// Get the direct follow-ups of a session, oldest first.
+    async fn get_children(&self, id: SessionId) -> Result<Vec<Session>, StorageError>;
+
+    /// Get the lineage of a session, from the original session down to `id`.
+    async fn get_chain(&self, id: SessionId) -> Result<Vec<Session>, StorageError> {
+        let mut chain = Vec::new();
+        let mut next = Some(id);
+        while let Some(current) = next {
+            let session = self.get(current).await?.ok_or(StorageError::NotFound(current))?;
+            next = session.parent_session_id;
+            chain.push(session);
+        }
+        chain.reverse();
+        Ok(chain)
+    }
+
     /// List sessions with optional filter.
     async fn list(&self, filter: SessionFilter) -> Result<Vec<Session>, StorageError>;
 
@@ -92,14 +263,457 @@ pub trait SessionStorage: Send + Sync {
 
     /// Get session output.
     async fn get_output(&self, id: SessionId) -> Result<Vec<u8>, StorageError>;
+
+    /// Get up to `len` bytes of session output starting at byte `offset`.
+    ///
+    /// Reading at or past the end returns the bytes available, possibly
+    /// none. The default implementation reads the whole output.
+    async fn get_output_range(
+        &self,
+        id: SessionId,
+        offset: u64,
+        len: usize,
+    ) -> Result<Vec<u8>, StorageError> {
+        let output = self.get_output(id).await?;
+        let start = usize::try_from(offset).map_or(output.len(), |o| o.min(output.len()));
+        let end = start.saturating_add(len).min(output.len());
+        Ok(output[start..end].to_vec())
+    }
+
+    /// Get the length of session output in bytes.
+    ///
+    /// The default implementation reads the whole output.
+    async fn output_len(&self, id: SessionId) -> Result<u64, StorageError> {
+        let output = self.get_output(id).await?;
+        Ok(u64::try_from(output.len()).unwrap_or(u64::MAX))
+    }
 }
 
+/// Sequence number of a persisted event within a session (starting at 0).
+pub type EventSeq = u64;
+
+/// Persisted log event.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StoredEvent {
+    /// Session the event belongs to.
+    pub session_id: SessionId,
+    /// Position of the event within the session.
+    pub seq: EventSeq,
+    /// Timestamp the event was recorded (Unix epoch seconds).
+    pub timestamp: i64,
+    /// Milliseconds past `timestamp` the event was recorded, for timing
+    /// events within a second, e.g. for playback. 0 for events stored
+    /// without it.
+    #[serde(default)]
+    pub subsec_millis: u32,
+    /// The event itself.
+    pub msg: LogMsg,
+}
+
+/// Trait for structured event storage backends.
+///
+/// Unlike `SessionStorage::append_output`, events keep their `LogMsg` type
+/// so a session can be replayed exactly as it was streamed.
+#[async_trait]
+pub trait EventStorage: Send + Sync {
+    /// Append an event, returning its sequence number.
+    async fn append_event(&self, id: SessionId, msg: &LogMsg) -> Result<EventSeq, StorageError>;
+
+    /// Get events with a sequence number of at least `from`, up to `limit` events.
+    async fn get_events(
+        &self,
+        id: SessionId,
+        from: EventSeq,
+        limit: Option<usize>,
+    ) -> Result<Vec<StoredEvent>, StorageError>;
+
+    /// Number of events stored for a session.
+    async fn event_count(&self, id: SessionId) -> Result<u64, StorageError>;
+}
+
+/// Outcome of an approval decision for a tool call.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ApprovalOutcome {
+    /// Approved by an approval handler.
+    Approved,
+    /// Approved without consulting an approval handler.
+    AutoApproved,
+    /// Denied by an approval handler.
+    Denied,
+    /// No decision was made in time.
+    TimedOut,
+}
+
+/// Audit record of a single tool invocation and its approval decision.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ToolCallRecord {
+    /// Session the tool call belongs to.
+    pub session_id: SessionId,
+    /// Agent-assigned tool call identifier.
+    pub tool_call_id: String,
+    /// Name of the tool invoked.
+    pub tool_name: String,
+    /// Input the tool was invoked with (after any approval edits).
+    pub input: Value,
+    /// Approval outcome.
+    pub outcome: ApprovalOutcome,
+    /// Who or what made the decision (user name, handler name, ...).
+    pub decided_by: Option<String>,
+    /// Reason given for the decision, if any.
+    pub reason: Option<String>,
+    /// When approval was requested (Unix epoch seconds).
+    pub requested_at: i64,
+    /// When the decision was made (Unix epoch seconds).
+    pub decided_at: i64,
+    /// Hex SHA-256 of the input as the agent requested it, if recorded.
+    #[serde(default)]
+    pub input_hash: Option<String>,
+    /// How long the decision took in milliseconds, if recorded.
+    #[serde(default)]
+    pub latency_ms: Option<u64>,
+}
+
+/// Filter for tool call audit queries.
+#[derive(Debug, Clone, Default)]
+pub struct ToolCallFilter {
+    /// Filter by session.
+    pub session_id: Option<SessionId>,
+    /// Filter by tool name.
+    pub tool_name: Option<String>,
+    /// Filter by approval outcome.
+    pub outcome: Option<ApprovalOutcome>,
+    /// Only calls decided at or after this timestamp (Unix epoch seconds).
+    pub decided_after: Option<i64>,
+    /// Only calls decided before this timestamp (Unix epoch seconds).
+    pub decided_before: Option<i64>,
+    /// Limit results.
+    pub limit: Option<usize>,
+}
+
+impl ToolCallFilter {
+    /// Check whether a record matches this filter (ignoring `limit`).
+    #[must_use]
+    pub fn matches(&self, record: &ToolCallRecord) -> bool {
+        self.session_id.is_none_or(|id| record.session_id == id)
+            && self
+                .tool_name
+                .as_ref()
+                .is_none_or(|name| record.tool_name == *name)
+            && self.outcome.is_none_or(|outcome| record.outcome == outcome)
+            && self.decided_after.is_none_or(|t| record.decided_at >= t)
+            && self.decided_before.is_none_or(|t| record.decided_at < t)
+    }
+}
+
+/// Audit record of a hook invoked by an agent (e.g. `PreToolUse`).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HookEventRecord {
+    /// Session the hook ran in.
+    pub session_id: SessionId,
+    /// Hook event name (e.g. `PreToolUse`).
+    pub event: String,
+    /// Tool the hook ran for, if any.
+    pub tool_name: Option<String>,
+    /// Agent-assigned tool call identifier, if any.
+    pub tool_call_id: Option<String>,
+    /// Input the hook was invoked with.
+    pub input: Value,
+    /// Output the hook returned, if any.
+    pub output: Option<Value>,
+    /// When the hook ran (Unix epoch seconds).
+    pub timestamp: i64,
+}
+
+/// Filter for hook event audit queries.
+#[derive(Debug, Clone, Default)]
+pub struct HookEventFilter {
+    /// Filter by session.
+    pub session_id: Option<SessionId>,
+    /// Filter by hook event name.
+    pub event: Option<String>,
+    /// Filter by tool name.
+    pub tool_name: Option<String>,
+    /// Only events at or after this timestamp (Unix epoch seconds).
+    pub after: Option<i64>,
+    /// Only events before this timestamp (Unix epoch seconds).
+    pub before: Option<i64>,
+    /// Limit results.
+    pub limit: Option<usize>,
+}
+
+impl HookEventFilter {
+    /// Check whether a record matches this filter (ignoring `limit`).
+    #[must_use]
+    pub fn matches(&self, record: &HookEventRecord) -> bool {
+        self.session_id.is_none_or(|id| record.session_id == id)
+            && self.event.as_ref().is_none_or(|event| record.event == *event)
+            && self
+                .tool_name
+                .as_ref()
+                .is_none_or(|name| record.tool_name.as_ref() == Some(name))
+            && self.after.is_none_or(|t| record.timestamp >= t)
+            && self.before.is_none_or(|t| record.timestamp < t)
+    }
+}
+
+/// Trait for approval and hook audit storage backends.
+///
+/// Audit records are kept apart from session output so they can be
+/// queried directly for compliance review.
+#[async_trait]
+pub trait AuditStorage: Send + Sync {
+    /// Record a tool invocation and its approval decision.
+    async fn record_tool_call(&self, record: ToolCallRecord) -> Result<(), StorageError>;
+
+    /// Get all recorded tool calls for a session, oldest first.
+    async fn get_tool_calls(&self, id: SessionId) -> Result<Vec<ToolCallRecord>, StorageError>;
+
+    /// Query tool calls across sessions, in order of decision time.
+    async fn query_tool_calls(
+        &self,
+        filter: ToolCallFilter,
+    ) -> Result<Vec<ToolCallRecord>, StorageError>;
+
+    /// Record a hook invocation.
+    async fn record_hook_event(&self, record: HookEventRecord) -> Result<(), StorageError>;
+
+    /// Query hook events across sessions, oldest first.
+    async fn query_hook_events(
+        &self,
+        filter: HookEventFilter,
+    ) -> Result<Vec<HookEventRecord>, StorageError>;
+}
+
+/// Stream of chunks of a stored blob.
+pub type BlobStream = BoxStream<'static, Result<Vec<u8>, StorageError>>;
+
+/// Trait for stores holding large session output outside the primary database.
+///
+/// Storage backends configured with a blob store keep only a key per
+/// output blob and read the data back from here.
+#[async_trait]
+pub trait OutputBlobStore: Send + Sync {
+    /// Store a blob, replacing any blob with the same key.
+    async fn put(&self, key: &str, data: Vec<u8>) -> Result<(), StorageError>;
+
+    /// Get a whole blob.
+    async fn get(&self, key: &str) -> Result<Vec<u8>, StorageError>;
+
+    /// Stream a blob in chunks.
+    async fn stream(&self, key: &str) -> Result<BlobStream, StorageError>;
+
+    /// Delete a blob. Deleting a missing blob is not an error.
+    async fn delete(&self, key: &str) -> Result<(), StorageError>;
+}
+
+/// Stream of normalized events produced by a spawned agent.
+pub type EventStream = BoxStream<'static, Result<LogMsg, ExecutorError>>;
+
+/// Token counts reported by an agent.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TokenUsage {
+    /// Input tokens billed at the full rate.
+    pub input_tokens: u64,
+    /// Output tokens.
+    pub output_tokens: u64,
+    /// Input tokens written to the prompt cache.
+    pub cache_creation_input_tokens: u64,
+    /// Input tokens read from the prompt cache.
+    pub cache_read_input_tokens: u64,
+}
+
+impl TokenUsage {
+    /// Add another usage to this one.
+    pub const fn add(&mut self, other: &Self) {
+        self.input_tokens += other.input_tokens;
+        self.output_tokens += other.output_tokens;
+        self.cache_creation_input_tokens += other.cache_creation_input_tokens;
+        self.cache_read_input_tokens += other.cache_read_input_tokens;
+    }
+}
+
+/// Resource usage reported by an executor while its agent runs.
+#[derive(Debug, Clone, PartialEq)]
+pub enum UsageEvent {
+    /// The agent completed a turn.
+    TurnCompleted,
+    /// The agent invoked a tool.
+    ToolCall { tool_name: String },
+    /// Tokens used by a turn.
+    Tokens(TokenUsage),
+    /// Estimated cost so far, in US dollars.
+    Cost { total_usd: f64 },
+}
+
+/// Stream of usage events produced by a spawned agent.
+pub type UsageStream = BoxStream<'static, UsageEvent>;
+
+/// Future resolving, once a process has exited, to a new process that
+/// continues the same session, or `None` if the session is done.
+pub type Continuation = BoxFuture<'static, Option<SpawnedProcess>>;
+
+/// How an agent reported that its run ended.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AgentOutcome {
+    /// Whether the agent finished the task rather than stopping on an
+    /// error, e.g. running out of turns.
+    pub success: bool,
+    /// Why the run failed.
+    pub reason: Option<String>,
+}
+
+/// Future resolving to the agent's reported outcome, or `None` if it
+/// reported none. Polled once the process has exited.
+pub type OutcomeFuture = BoxFuture<'static, Option<AgentOutcome>>;
+
 /// Spawned process handle.
 pub struct SpawnedProcess {
     /// Child process handle.
     pub child: command_group::AsyncGroupChild,
-    /// Receiver for graceful interrupt requests.
-    pub interrupt_rx: Option<tokio::sync::oneshot::Receiver<()>>,
+    /// Sender for graceful interrupt requests.
+    ///
+    /// The executor holds the receiving end and asks the agent to stop
+    /// (e.g. via its control protocol) when a value is sent.
+    pub interrupt_tx: Option<tokio::sync::oneshot::Sender<()>>,
+    /// Normalized output events.
+    ///
+    /// When `None`, consumers fall back to the child's raw stdout/stderr.
+    pub events: Option<EventStream>,
+    /// Sender for raw input to the agent.
+    ///
+    /// Executors that own the child's stdin (e.g. for a control protocol)
+    /// set this to accept client input. When `None`, consumers may write to
+    /// the child's piped stdin directly.
+    pub input_tx: Option<tokio::sync::mpsc::UnboundedSender<Vec<u8>>>,
+    /// Usage events, for enforcing turn, tool call, and cost limits and
+    /// for recording the session's token usage and cost.
+    pub usage: Option<UsageStream>,
+    /// Sender for permission mode changes while the agent runs, by the
+    /// agent's name for the mode (e.g. `plan` or `acceptEdits`).
+    pub permission_mode_tx: Option<tokio::sync::mpsc::UnboundedSender<String>>,
+    /// Receiver for the executor asking for the session to be stopped, with
+    /// the reason (e.g. a tool call denied after the agent started it).
+    pub abort_rx: Option<tokio::sync::oneshot::Receiver<String>>,
+    /// Awaited after the process exits, for executors that continue a
+    /// session in a new process (e.g. resuming after a rate limit).
+    pub continuation: Option<Continuation>,
+    /// The outcome the agent reported, for executors whose agent can fail
+    /// its task yet exit cleanly.
+    pub outcome: Option<OutcomeFuture>,
+}
+
+impl SpawnedProcess {
+    /// Create a handle whose events are the child's raw stdout/stderr lines.
+    #[must_use]
+    pub fn from_child(mut child: command_group::AsyncGroupChild) -> Self {
+        let events = raw_output_events(&mut child);
+        Self {
+            child,
+            interrupt_tx: None,
+            events: Some(events),
+            input_tx: None,
+            usage: None,
+            permission_mode_tx: None,
+            abort_rx: None,
+            continuation: None,
+            outcome: None,
+        }
+    }
+
+    /// Set the graceful interrupt sender.
+    #[must_use]
+    pub fn with_interrupt(mut self, interrupt_tx: tokio::sync::oneshot::Sender<()>) -> Self {
+        self.interrupt_tx = Some(interrupt_tx);
+        self
+    }
+
+    /// Set the normalized event stream.
+    #[must_use]
+    pub fn with_events(mut self, events: EventStream) -> Self {
+        self.events = Some(events);
+        self
+    }
+
+    /// Set the input sender.
+    #[must_use]
+    pub fn with_input(mut self, input_tx: tokio::sync::mpsc::UnboundedSender<Vec<u8>>) -> Self {
+        self.input_tx = Some(input_tx);
+        self
+    }
+
+    /// Set the usage event stream.
+    #[must_use]
+    pub fn with_usage(mut self, usage: UsageStream) -> Self {
+        self.usage = Some(usage);
+        self
+    }
+
+    /// Set the permission mode sender.
+    #[must_use]
+    pub fn with_permission_mode(
+        mut self,
+        permission_mode_tx: tokio::sync::mpsc::UnboundedSender<String>,
+    ) -> Self {
+        self.permission_mode_tx = Some(permission_mode_tx);
+        self
+    }
+
+    /// Set the abort request receiver.
+    #[must_use]
+    pub fn with_abort(mut self, abort_rx: tokio::sync::oneshot::Receiver<String>) -> Self {
+        self.abort_rx = Some(abort_rx);
+        self
+    }
+
+    /// Set the continuation awaited after the process exits.
+    #[must_use]
+    pub fn with_continuation(mut self, continuation: Continuation) -> Self {
+        self.continuation = Some(continuation);
+        self
+    }
+
+    /// Set the future resolving to the agent's reported outcome.
+    #[must_use]
+    pub fn with_outcome(mut self, outcome: OutcomeFuture) -> Self {
+        self.outcome = Some(outcome);
+        self
+    }
+}
+
+/// Stream a child's stdout and stderr lines as `LogMsg` events.
+///
+/// Takes the child's piped stdout/stderr; pipes that were not configured
+/// (or were already taken) are skipped.
+pub fn raw_output_events(child: &mut command_group::AsyncGroupChild) -> EventStream {
+    fn lines<R>(reader: R, wrap: fn(String) -> LogMsg) -> EventStream
+    where
+        R: AsyncRead + Send + Unpin + 'static,
+    {
+        let lines = BufReader::new(reader).lines();
+        futures::stream::unfold(lines, move |mut lines| async move {
+            match lines.next_line().await {
+                Ok(Some(line)) => Some((Ok(wrap(line + "\n")), lines)),
+                Ok(None) => None,
+                Err(e) => Some((Err(ExecutorError::Io(e)), lines)),
+            }
+        })
+        .boxed()
+    }
+
+    let stdout = child
+        .inner()
+        .stdout
+        .take()
+        .map_or_else(|| futures::stream::empty().boxed(), |s| lines(s, LogMsg::Stdout));
+    let stderr = child
+        .inner()
+        .stderr
+        .take()
+        .map_or_else(|| futures::stream::empty().boxed(), |s| lines(s, LogMsg::Stderr));
+
+    futures::stream::select(stdout, stderr).boxed()
 }
 
 /// Executor error.
@@ -113,9 +727,51 @@ pub enum ExecutorError {
     Io(#[from] std::io::Error),
     #[error("Command build error: {0}")]
     CommandBuild(String),
+    #[error("No executor registered for: {0}")]
+    NotRegistered(String),
+}
+
+impl ExecutorError {
+    /// Whether the error may succeed if the spawn is retried.
+    ///
+    /// Covers executables that are not on PATH yet and temporary resource
+    /// exhaustion (e.g. `EAGAIN`).
+    #[must_use]
+    pub fn is_transient(&self) -> bool {
+        match self {
+            Self::ExecutableNotFound(_) => true,
+            Self::Io(e) => matches!(
+                e.kind(),
+                std::io::ErrorKind::WouldBlock
+                    | std::io::ErrorKind::Interrupted
+                    | std::io::ErrorKind::ResourceBusy
+                    | std::io::ErrorKind::TimedOut
+            ),
+            Self::SpawnFailed(_) | Self::CommandBuild(_) | Self::NotRegistered(_) => false,
+        }
+    }
+}
+
+/// Command an executor would run for a session, from `Executor::preview`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CommandPreview {
+    /// Resolved path of the executable.
+    pub program: PathBuf,
+    /// Arguments passed to the executable.
+    pub args: Vec<String>,
+    /// Shell-quoted command line.
+    pub command_line: String,
+    /// Working directory the command runs in.
+    pub working_dir: PathBuf,
+    /// Names of environment variables set for the command (values hidden).
+    pub env: Vec<String>,
 }
 
 /// Trait for agent executors.
+///
+/// Executors should populate `SpawnedProcess::events` with output normalized
+/// to `LogMsg`, so callers can forward it without knowing the agent's
+/// stdout format.
 #[async_trait]
 pub trait Executor: Send + Sync {
     /// Spawn a new agent session.
@@ -132,4 +788,92 @@ pub trait Executor: Send + Sync {
         prompt: &str,
         session_id: &str,
     ) -> Result<SpawnedProcess, ExecutorError>;
+
+    /// Reattach to an agent session after the host process restarted.
+    ///
+    /// Returns `None` if this executor cannot resume sessions (the default).
+    async fn resume(
+        &self,
+        _ctx: &ExecutionContext,
+        _session_id: &str,
+    ) -> Result<Option<SpawnedProcess>, ExecutorError> {
+        Ok(None)
+    }
+
+    /// Resolve and build the command `spawn` would run, without running it.
+    ///
+    /// Returns `None` if this executor cannot preview commands (the default).
+    async fn preview(
+        &self,
+        _ctx: &ExecutionContext,
+        _prompt: &str,
+    ) -> Result<Option<CommandPreview>, ExecutorError> {
+        Ok(None)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn session(prompt: &str, created_at: i64) -> Session {
+        let mut context = ExecutionContext::new(PathBuf::from("/tmp"));
+        context.set_metadata("ticket", Value::String("RATE-42".to_string()));
+        Session {
+            id: Uuid::new_v4(),
+            context,
+            prompt: Some(prompt.to_string()),
+            status: SessionStatus::Completed,
+            agent_session_id: None,
+            parent_session_id: None,
+            run_id: None,
+            tenant_id: None,
+            exit_code: None,
+            status_reason: None,
+            deleted: false,
+            created_at,
+            updated_at: created_at,
+            version: 0,
+        }
+    }
+
+    #[test]
+    fn test_filter_time_range() {
+        let filter = SessionFilter {
+            created_after: Some(100),
+            created_before: Some(200),
+            ..Default::default()
+        };
+        assert!(filter.matches(&session("a", 100)));
+        assert!(filter.matches(&session("a", 199)));
+        assert!(!filter.matches(&session("a", 99)));
+        assert!(!filter.matches(&session("a", 200)));
+    }
+
+    #[test]
+    fn test_filter_query() {
+        let s = session("Fix the Rate Limiting bug", 0);
+        let matches = |q: &str| {
+            SessionFilter {
+                query: Some(q.to_string()),
+                ..Default::default()
+            }
+            .matches(&s)
+        };
+        assert!(matches("rate limiting"));
+        assert!(matches("rate-42"));
+        assert!(!matches("pagination"));
+    }
+
+    #[test]
+    fn test_filter_run_id() {
+        let mut s = session("a", 0);
+        let filter = SessionFilter {
+            run_id: Some("release-1.2".to_string()),
+            ..Default::default()
+        };
+        assert!(!filter.matches(&s));
+        s.run_id = Some("release-1.2".to_string());
+        assert!(filter.matches(&s));
+    }
 }
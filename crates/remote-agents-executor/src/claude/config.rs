@@ -0,0 +1,247 @@
+//! Model and thinking configuration for Claude sessions.
+
+use std::collections::BTreeMap;
+
+use super::mcp::{McpServerConfig, mcp_config_json};
+use super::settings::ClaudeSettings;
+use super::types::PermissionMode;
+
+/// Model, thinking, tool, and output settings for a Claude session.
+///
+/// The model and thinking budget are sent over the control protocol by
+/// `ProtocolPeer::initialize`, so they can be changed mid-session; the rest
+/// are CLI flags added by `CommandBuilder::claude_config`. Turn and budget
+/// limits are enforced by `ClaudeClient`, and `ClaudeExecutor` writes the
+/// settings to a per-session file.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ClaudeConfig {
+    /// Model to use, e.g. `sonnet` or a full model name.
+    pub model: Option<String>,
+    /// Model to fall back to when the primary model is overloaded.
+    pub fallback_model: Option<String>,
+    /// Maximum tokens the model may spend thinking per turn.
+    pub max_thinking_tokens: Option<u32>,
+    /// Emit verbose output, including full turn-by-turn messages.
+    pub verbose: bool,
+    /// Stream partial assistant messages as they are generated.
+    pub include_partial_messages: bool,
+    /// Permission mode to start in, e.g. `Plan` to review a plan first.
+    pub permission_mode: Option<PermissionMode>,
+    /// MCP servers to give the agent, keyed by server name.
+    pub mcp_servers: BTreeMap<String, McpServerConfig>,
+    /// Tool rules the agent may use without asking, e.g. `Read` or
+    /// `Bash(git diff:*)`.
+    pub allowed_tools: Vec<String>,
+    /// Tool rules the agent may never use, e.g. `Bash` or `WebFetch`.
+    pub disallowed_tools: Vec<String>,
+    /// Interrupt the agent once it starts more turns than this.
+    pub max_turns: Option<u32>,
+    /// Budget in US dollars. The CLI only reports cost with a result, so
+    /// this is checked when a run ends rather than mid-turn.
+    pub max_budget_usd: Option<f64>,
+    /// Hooks, permissions, and environment for this session only.
+    pub settings: ClaudeSettings,
+}
+
+impl ClaudeConfig {
+    /// Create a configuration that leaves everything at the CLI defaults.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the model.
+    #[must_use]
+    pub fn with_model(mut self, model: impl Into<String>) -> Self {
+        self.model = Some(model.into());
+        self
+    }
+
+    /// Set the fallback model.
+    #[must_use]
+    pub fn with_fallback_model(mut self, model: impl Into<String>) -> Self {
+        self.fallback_model = Some(model.into());
+        self
+    }
+
+    /// Set the thinking token budget.
+    #[must_use]
+    pub const fn with_max_thinking_tokens(mut self, tokens: u32) -> Self {
+        self.max_thinking_tokens = Some(tokens);
+        self
+    }
+
+    /// Enable or disable verbose output.
+    #[must_use]
+    pub const fn with_verbose(mut self, verbose: bool) -> Self {
+        self.verbose = verbose;
+        self
+    }
+
+    /// Enable or disable streaming of partial assistant messages.
+    #[must_use]
+    pub const fn with_partial_messages(mut self, include: bool) -> Self {
+        self.include_partial_messages = include;
+        self
+    }
+
+    /// Set the permission mode to start in.
+    #[must_use]
+    pub const fn with_permission_mode(mut self, mode: PermissionMode) -> Self {
+        self.permission_mode = Some(mode);
+        self
+    }
+
+    /// Add an MCP server, replacing any with the same name.
+    #[must_use]
+    pub fn with_mcp_server(mut self, name: impl Into<String>, server: McpServerConfig) -> Self {
+        self.mcp_servers.insert(name.into(), server);
+        self
+    }
+
+    /// Allow a tool rule without asking.
+    #[must_use]
+    pub fn with_allowed_tool(mut self, rule: impl Into<String>) -> Self {
+        self.allowed_tools.push(rule.into());
+        self
+    }
+
+    /// Block a tool rule.
+    #[must_use]
+    pub fn with_disallowed_tool(mut self, rule: impl Into<String>) -> Self {
+        self.disallowed_tools.push(rule.into());
+        self
+    }
+
+    /// Interrupt the agent once it starts more than `max` turns.
+    #[must_use]
+    pub const fn with_max_turns(mut self, max: u32) -> Self {
+        self.max_turns = Some(max);
+        self
+    }
+
+    /// Report the session once its cost exceeds `max_usd`.
+    #[must_use]
+    pub const fn with_max_budget_usd(mut self, max_usd: f64) -> Self {
+        self.max_budget_usd = Some(max_usd);
+        self
+    }
+
+    /// Set per-session settings.
+    #[must_use]
+    pub fn with_settings(mut self, settings: ClaudeSettings) -> Self {
+        self.settings = settings;
+        self
+    }
+
+    /// Whether the tool lists decide a call to `tool_name`: `Some(false)` if
+    /// it is disallowed, `Some(true)` if allowed, `None` otherwise.
+    ///
+    /// Only whole-tool rules (`Bash`, or `mcp__server` for all of a server's
+    /// tools) are checked here; rules with a specifier such as
+    /// `Bash(rm:*)` are left to the CLI.
+    #[must_use]
+    pub fn tool_permission(&self, tool_name: &str) -> Option<bool> {
+        let matches = |rules: &[String]| rules.iter().any(|rule| rule_matches(rule, tool_name));
+        if matches(&self.disallowed_tools) {
+            Some(false)
+        } else if matches(&self.allowed_tools) {
+            Some(true)
+        } else {
+            None
+        }
+    }
+
+    /// CLI flags for the settings the control protocol cannot change.
+    #[must_use]
+    pub fn cli_args(&self) -> Vec<String> {
+        let mut args = Vec::new();
+        if let Some(model) = &self.fallback_model {
+            args.extend(["--fallback-model".to_string(), model.clone()]);
+        }
+        if self.verbose {
+            args.push("--verbose".to_string());
+        }
+        if self.include_partial_messages {
+            args.push("--include-partial-messages".to_string());
+        }
+        if let Some(mode) = self.permission_mode {
+            args.extend(["--permission-mode".to_string(), mode.as_str().to_string()]);
+        }
+        if !self.allowed_tools.is_empty() {
+            args.extend(["--allowedTools".to_string(), self.allowed_tools.join(",")]);
+        }
+        if !self.disallowed_tools.is_empty() {
+            args.extend([
+                "--disallowedTools".to_string(),
+                self.disallowed_tools.join(","),
+            ]);
+        }
+        if !self.mcp_servers.is_empty() {
+            args.extend([
+                "--mcp-config".to_string(),
+                mcp_config_json(&self.mcp_servers),
+            ]);
+        }
+        args
+    }
+}
+
+/// Whether a whole-tool rule covers `tool_name`.
+fn rule_matches(rule: &str, tool_name: &str) -> bool {
+    rule == tool_name
+        || (rule.starts_with("mcp__")
+            && tool_name
+                .strip_prefix(rule)
+                .is_some_and(|rest| rest.starts_with("__")))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::CommandBuilder;
+
+    #[test]
+    fn test_cli_args() {
+        let config = ClaudeConfig::new()
+            .with_model("opus")
+            .with_fallback_model("sonnet")
+            .with_verbose(true);
+        let parts = CommandBuilder::new("claude -p")
+            .claude_config(&config)
+            .build_initial()
+            .unwrap();
+        assert_eq!(
+            parts.args,
+            ["-p", "--fallback-model", "sonnet", "--verbose"]
+        );
+
+        assert!(ClaudeConfig::new().cli_args().is_empty());
+    }
+
+    #[test]
+    fn test_tool_permission() {
+        let config = ClaudeConfig::new()
+            .with_allowed_tool("Read")
+            .with_allowed_tool("Bash(git diff:*)")
+            .with_disallowed_tool("Bash")
+            .with_disallowed_tool("mcp__github");
+        assert_eq!(config.tool_permission("Read"), Some(true));
+        assert_eq!(config.tool_permission("Bash"), Some(false));
+        assert_eq!(
+            config.tool_permission("mcp__github__create_issue"),
+            Some(false)
+        );
+        assert_eq!(config.tool_permission("mcp__githubx__search"), None);
+        assert_eq!(config.tool_permission("Edit"), None);
+        assert_eq!(
+            config.cli_args(),
+            [
+                "--allowedTools",
+                "Read,Bash(git diff:*)",
+                "--disallowedTools",
+                "Bash,mcp__github"
+            ]
+        );
+    }
+}
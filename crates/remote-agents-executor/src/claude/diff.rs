@@ -0,0 +1,150 @@
+//! Diffs of the file changes made by Claude's edit tools.
+
+use std::fmt::Write as _;
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+/// A change to one file, as a unified diff.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FileDiff {
+    pub path: String,
+    /// The change with `---`/`+++` headers and `@@` hunks.
+    pub unified_diff: String,
+}
+
+impl FileDiff {
+    /// The diff of a successful edit tool call.
+    ///
+    /// Uses the patch the CLI reports with the tool's result when given, as
+    /// its hunks carry the file's line numbers. Otherwise the diff is built
+    /// from the tool's input alone, so its hunks count lines from the start
+    /// of the replaced text rather than the file.
+    #[must_use]
+    pub fn from_tool_call(tool_name: &str, input: &Value, result: Option<&Value>) -> Option<Self> {
+        let path = input.get("file_path").and_then(Value::as_str)?;
+        let reported = result
+            .and_then(|result| result.get("structuredPatch"))
+            .and_then(Value::as_array)
+            .filter(|hunks| !hunks.is_empty());
+        if let Some(hunks) = reported {
+            return Some(Self::from_structured_patch(path, hunks));
+        }
+
+        let text = |value: &Value, key: &str| {
+            value
+                .get(key)
+                .and_then(Value::as_str)
+                .unwrap_or_default()
+                .to_string()
+        };
+        let replacements: Vec<(String, String)> = match tool_name {
+            "Edit" => vec![(text(input, "old_string"), text(input, "new_string"))],
+            "MultiEdit" => input
+                .get("edits")
+                .and_then(Value::as_array)
+                .into_iter()
+                .flatten()
+                .map(|edit| (text(edit, "old_string"), text(edit, "new_string")))
+                .collect(),
+            "Write" => vec![(String::new(), text(input, "content"))],
+            _ => return None,
+        };
+        if replacements.is_empty() {
+            return None;
+        }
+        let mut unified_diff = headers(path);
+        for (old, new) in &replacements {
+            push_replacement(&mut unified_diff, old, new);
+        }
+        Some(Self {
+            path: path.to_string(),
+            unified_diff,
+        })
+    }
+
+    /// Render a patch as reported by the CLI: hunks with `oldStart`,
+    /// `oldLines`, `newStart`, `newLines`, and prefixed `lines`.
+    fn from_structured_patch(path: &str, hunks: &[Value]) -> Self {
+        let number = |hunk: &Value, key: &str| hunk.get(key).and_then(Value::as_u64).unwrap_or(0);
+        let mut unified_diff = headers(path);
+        for hunk in hunks {
+            let _ = writeln!(
+                unified_diff,
+                "@@ -{},{} +{},{} @@",
+                number(hunk, "oldStart"),
+                number(hunk, "oldLines"),
+                number(hunk, "newStart"),
+                number(hunk, "newLines"),
+            );
+            let lines = hunk.get("lines").and_then(Value::as_array);
+            for line in lines.into_iter().flatten().filter_map(Value::as_str) {
+                unified_diff.push_str(line);
+                unified_diff.push('\n');
+            }
+        }
+        Self {
+            path: path.to_string(),
+            unified_diff,
+        }
+    }
+}
+
+fn headers(path: &str) -> String {
+    format!("--- a/{path}\n+++ b/{path}\n")
+}
+
+/// Append a hunk replacing `old` with `new`.
+fn push_replacement(diff: &mut String, old: &str, new: &str) {
+    let old: Vec<&str> = old.lines().collect();
+    let new: Vec<&str> = new.lines().collect();
+    let start = |lines: &[&str]| usize::from(!lines.is_empty());
+    let _ = writeln!(
+        diff,
+        "@@ -{},{} +{},{} @@",
+        start(&old),
+        old.len(),
+        start(&new),
+        new.len()
+    );
+    for line in old {
+        let _ = writeln!(diff, "-{line}");
+    }
+    for line in new {
+        let _ = writeln!(diff, "+{line}");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn test_diff_from_input_and_patch() {
+        let input = json!({ "file_path": "src/lib.rs", "old_string": "a\nb", "new_string": "c" });
+        assert_eq!(
+            FileDiff::from_tool_call("Edit", &input, None),
+            Some(FileDiff {
+                path: "src/lib.rs".into(),
+                unified_diff: "--- a/src/lib.rs\n+++ b/src/lib.rs\n@@ -1,2 +1,1 @@\n-a\n-b\n+c\n"
+                    .into(),
+            })
+        );
+
+        let result = json!({ "structuredPatch": [{
+            "oldStart": 10, "oldLines": 2, "newStart": 10, "newLines": 1,
+            "lines": [" x", "-a", "-b", "+c"],
+        }]});
+        let diff = FileDiff::from_tool_call("Edit", &input, Some(&result)).unwrap();
+        assert!(
+            diff.unified_diff
+                .ends_with("@@ -10,2 +10,1 @@\n x\n-a\n-b\n+c\n")
+        );
+
+        let input = json!({ "file_path": "new.txt", "content": "hi" });
+        let diff = FileDiff::from_tool_call("Write", &input, None).unwrap();
+        assert!(diff.unified_diff.ends_with("@@ -0,0 +1,1 @@\n+hi\n"));
+    }
+}
@@ -0,0 +1,521 @@
+//! `Executor` implementation running the Claude CLI.
+
+use std::{process::Stdio, sync::Arc, time::Duration};
+
+use async_trait::async_trait;
+use command_group::AsyncCommandGroup;
+use futures::{FutureExt, StreamExt};
+use remote_agents_core::{
+    ExecutionContext, LogMsg,
+    traits::{CommandPreview, Continuation, EventStream, Executor, ExecutorError, SpawnedProcess},
+};
+use tokio::{
+    io::{AsyncBufReadExt, BufReader, DuplexStream, Lines},
+    sync::{OnceCell, broadcast, mpsc, oneshot},
+};
+
+use super::client::{ClaudeClient, LimitExceeded, LogWriter};
+use super::config::ClaudeConfig;
+use super::hooks::HookRegistry;
+use super::installation::{ClaudeInstallation, ClaudeVersion, MIN_CLAUDE_VERSION};
+use super::mcp::McpServerConfig;
+use super::protocol::{ProtocolError, ProtocolPeer};
+use super::retry::{RateLimitPolicy, is_rate_limited};
+use super::sdk_mcp::SdkMcpServer;
+use super::settings::SettingsFile;
+use super::stderr::stderr_events;
+use super::types::ClaudeMessage;
+use crate::approvals::{ApprovalHandler, ApprovalMemory};
+use crate::command::{CommandBuilder, CommandParts};
+
+/// Flags for driving the CLI over the SDK control protocol.
+const PROTOCOL_PARAMS: [&str; 5] = [
+    "-p",
+    "--output-format=stream-json",
+    "--input-format=stream-json",
+    "--permission-prompt-tool",
+    "stdio",
+];
+
+/// Buffer between the protocol reader and the event stream.
+const OUTPUT_BUFFER_SIZE: usize = 64 * 1024;
+
+/// Runs sessions with the Claude CLI over its SDK control protocol.
+///
+/// Each session is one CLI process: the prompt is sent once the protocol is
+/// initialized, and the process exits after the final result. Follow-ups
+/// start a new process with `--resume`.
+///
+/// To run a slash command, pass `slash_command_prompt` as the prompt; the
+/// `init` message in the output lists the commands available.
+///
+/// With a `RateLimitPolicy`, a run ending in a rate limit or overload error
+/// is resumed in a new process after a backoff, continuing the same session.
+#[derive(Clone)]
+pub struct ClaudeExecutor {
+    command: CommandBuilder,
+    config: ClaudeConfig,
+    approval_handler: Option<Arc<dyn ApprovalHandler>>,
+    hooks: HookRegistry,
+    sdk_mcp_servers: Vec<SdkMcpServer>,
+    minimum_version: ClaudeVersion,
+    rate_limit_policy: Option<RateLimitPolicy>,
+    /// Located on first spawn, then reused.
+    installation: OnceCell<ClaudeInstallation>,
+}
+
+impl Default for ClaudeExecutor {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ClaudeExecutor {
+    /// Create an executor running `claude` from PATH, auto-approving tools.
+    #[must_use]
+    pub fn new() -> Self {
+        Self {
+            command: CommandBuilder::new("claude").params(PROTOCOL_PARAMS),
+            config: ClaudeConfig::default(),
+            approval_handler: None,
+            hooks: HookRegistry::default(),
+            sdk_mcp_servers: Vec::new(),
+            minimum_version: MIN_CLAUDE_VERSION,
+            rate_limit_policy: None,
+            installation: OnceCell::new(),
+        }
+    }
+
+    /// Run a different executable, e.g. an absolute path to `claude`.
+    #[must_use]
+    pub fn with_executable(mut self, executable: impl Into<String>) -> Self {
+        self.command = self.command.override_base(executable);
+        self
+    }
+
+    /// Set the model, tool, and output configuration.
+    ///
+    /// Verbose output is always enabled, as stream-json output requires it.
+    #[must_use]
+    pub fn with_config(mut self, config: ClaudeConfig) -> Self {
+        self.config = config;
+        self
+    }
+
+    /// Ask `handler` before tools run, instead of auto-approving.
+    #[must_use]
+    pub fn with_approval_handler(mut self, handler: Arc<dyn ApprovalHandler>) -> Self {
+        self.approval_handler = Some(handler);
+        self
+    }
+
+    /// Run `hooks` for Claude's hook events.
+    #[must_use]
+    pub fn with_hooks(mut self, hooks: HookRegistry) -> Self {
+        self.hooks = hooks;
+        self
+    }
+
+    /// Give the agent the in-process MCP server `server`, whose tools run
+    /// as Rust callbacks.
+    #[must_use]
+    pub fn with_sdk_mcp_server(mut self, server: SdkMcpServer) -> Self {
+        self.sdk_mcp_servers.push(server);
+        self
+    }
+
+    /// Require at least `version` of the CLI instead of `MIN_CLAUDE_VERSION`.
+    #[must_use]
+    pub const fn with_minimum_version(mut self, version: ClaudeVersion) -> Self {
+        self.minimum_version = version;
+        self
+    }
+
+    /// Resume sessions stopped by rate limits according to `policy`.
+    #[must_use]
+    pub fn with_rate_limit_policy(mut self, policy: RateLimitPolicy) -> Self {
+        self.rate_limit_policy = Some(policy);
+        self
+    }
+
+    /// The session config, with the verbose output stream-json needs and
+    /// the in-process MCP servers.
+    fn session_config(&self) -> ClaudeConfig {
+        self.sdk_mcp_servers
+            .iter()
+            .fold(self.config.clone(), |config, server| {
+                config.with_mcp_server(server.name(), McpServerConfig::sdk(server.name()))
+            })
+            .with_verbose(true)
+    }
+
+    /// Build the command, resuming `resume_id` if given.
+    fn command_parts(
+        &self,
+        config: &ClaudeConfig,
+        resume_id: Option<&str>,
+    ) -> Result<CommandParts, ExecutorError> {
+        let builder = self.command.clone().claude_config(config);
+        let parts = resume_id.map_or_else(
+            || builder.build_initial(),
+            |id| builder.build_follow_up(&["--resume".to_string(), id.to_string()]),
+        )?;
+        Ok(parts)
+    }
+
+    /// Start the CLI, initialize the protocol, and send the prompt.
+    ///
+    /// `resumes` counts the session's rate limit resumes so far.
+    async fn start(
+        &self,
+        ctx: &ExecutionContext,
+        prompt: &str,
+        resume_id: Option<&str>,
+        resumes: u32,
+    ) -> Result<SpawnedProcess, ExecutorError> {
+        let config = self.session_config();
+        let mut parts = self.command_parts(&config, resume_id)?;
+        let settings = if config.settings.is_empty() {
+            None
+        } else {
+            let file = config.settings.write_temp().await?;
+            parts
+                .args
+                .extend(settings_args(file.path().display().to_string()));
+            Some(file)
+        };
+        let installation = self
+            .installation
+            .get_or_try_init(|| ClaudeInstallation::locate(&parts.program, self.minimum_version))
+            .await?;
+
+        let mut command = tokio::process::Command::new(&installation.path);
+        command
+            .args(&parts.args)
+            .current_dir(&ctx.working_dir)
+            .envs(ctx.secret_env())
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .kill_on_drop(true);
+        let mut child = command.group_spawn()?;
+        let stdin = child.inner().stdin.take();
+        let stdout = child.inner().stdout.take();
+        let (Some(stdin), Some(stdout)) = (stdin, stdout) else {
+            return Err(ExecutorError::SpawnFailed(
+                "Claude stdio not piped".to_string(),
+            ));
+        };
+
+        let (output_reader, output_writer) = tokio::io::duplex(OUTPUT_BUFFER_SIZE);
+        // "Always allow" decisions last for the session, not just this process.
+        let approval_handler = self.approval_handler.clone().map(|handler| {
+            let grants = ctx.session_state.get_or_default();
+            Arc::new(ApprovalMemory::with_grants(handler, grants)) as Arc<dyn ApprovalHandler>
+        });
+        let client = ClaudeClient::with_sdk_mcp_servers(
+            LogWriter::new(output_writer),
+            approval_handler,
+            self.hooks.clone(),
+            config.clone(),
+            self.sdk_mcp_servers.clone(),
+        );
+        let usage = client.usage_events();
+        let limits = client.subscribe_limits();
+        let result = client.take_result();
+        let (interrupt_tx, interrupt_rx) = oneshot::channel();
+        let peer = ProtocolPeer::spawn(stdin, stdout, client, interrupt_rx);
+        peer.initialize(&config).await.map_err(protocol_error)?;
+        peer.send_user_message(prompt.to_string())
+            .await
+            .map_err(protocol_error)?;
+        let (permission_mode_tx, permission_mode_rx) = mpsc::unbounded_channel();
+        peer.forward_permission_modes(permission_mode_rx);
+
+        let stderr = child
+            .inner()
+            .stderr
+            .take()
+            .map_or_else(|| futures::stream::empty().boxed(), stderr_events);
+        let (rate_limit, continuation) = self
+            .rate_limit_policy
+            .as_ref()
+            .filter(|policy| resumes < policy.max_resumes)
+            .map(|policy| {
+                let delay = policy.backoff(resumes + 1);
+                let (resume_tx, resume_rx) = oneshot::channel();
+                let continuation = self.resume_after(ctx.clone(), resume_rx, delay, resumes + 1);
+                (RateLimitWatch { delay, resume_tx }, continuation)
+            })
+            .unzip();
+        let output = output_events(output_reader, rate_limit)
+            .chain(limit_events(limits))
+            .chain(remove_on_end(settings));
+        let events = futures::stream::select(output, stderr).boxed();
+        let mut process = SpawnedProcess::from_child(child)
+            .with_events(events)
+            .with_interrupt(interrupt_tx)
+            .with_usage(usage)
+            .with_permission_mode(permission_mode_tx);
+        if let Some(result) = result {
+            process = process.with_outcome(
+                async move { result.await.ok().map(|result| result.outcome()) }.boxed(),
+            );
+        }
+        process.continuation = continuation;
+        Ok(process)
+    }
+
+    /// Resume the session once `resume_rx` reports that it was rate
+    /// limited, after `delay`.
+    fn resume_after(
+        &self,
+        ctx: ExecutionContext,
+        resume_rx: oneshot::Receiver<String>,
+        delay: Duration,
+        resumes: u32,
+    ) -> Continuation {
+        let executor = self.clone();
+        async move {
+            let session_id = resume_rx.await.ok()?;
+            tokio::time::sleep(delay).await;
+            let prompt = executor.rate_limit_policy.as_ref()?.prompt.clone();
+            executor
+                .start(&ctx, &prompt, Some(&session_id), resumes)
+                .await
+                .inspect_err(|e| {
+                    tracing::warn!("Failed to resume Claude session {session_id}: {e}");
+                })
+                .ok()
+        }
+        .boxed()
+    }
+}
+
+#[async_trait]
+impl Executor for ClaudeExecutor {
+    async fn spawn(
+        &self,
+        ctx: &ExecutionContext,
+        prompt: &str,
+    ) -> Result<SpawnedProcess, ExecutorError> {
+        self.start(ctx, prompt, None, 0).await
+    }
+
+    async fn spawn_follow_up(
+        &self,
+        ctx: &ExecutionContext,
+        prompt: &str,
+        session_id: &str,
+    ) -> Result<SpawnedProcess, ExecutorError> {
+        self.start(ctx, prompt, Some(session_id), 0).await
+    }
+
+    async fn preview(
+        &self,
+        ctx: &ExecutionContext,
+        _prompt: &str,
+    ) -> Result<Option<CommandPreview>, ExecutorError> {
+        let config = self.session_config();
+        let mut parts = self.command_parts(&config, None)?;
+        if !config.settings.is_empty() {
+            // Shown inline, as no file is written for a preview.
+            parts.args.extend(settings_args(config.settings.to_json()));
+        }
+        let preview = parts.into_preview(ctx).await?;
+        Ok(Some(preview))
+    }
+}
+
+/// Reports a run ending in a rate limit, so the session can be resumed.
+struct RateLimitWatch {
+    /// Delay before the session is resumed.
+    delay: Duration,
+    /// Receives the ID of the session to resume.
+    resume_tx: oneshot::Sender<String>,
+}
+
+/// Parsing state of the output stream.
+struct OutputState {
+    lines: Lines<BufReader<DuplexStream>>,
+    session_id: Option<String>,
+    rate_limit: Option<RateLimitWatch>,
+}
+
+impl OutputState {
+    /// Events for one line of output.
+    fn on_line(&mut self, line: String) -> Vec<LogMsg> {
+        match ClaudeMessage::parse(&line) {
+            Some(ClaudeMessage::System {
+                session_id: Some(id),
+                ..
+            }) => {
+                self.session_id = Some(id.clone());
+                vec![LogMsg::SessionId(id), LogMsg::Stdout(line + "\n")]
+            }
+            Some(message @ ClaudeMessage::StreamEvent { .. }) => message
+                .text_delta()
+                .map(|text| LogMsg::AssistantText(text.to_string()))
+                .into_iter()
+                .collect(),
+            Some(ClaudeMessage::Result(result)) if is_rate_limited(&result) => {
+                let mut msgs = vec![LogMsg::Stdout(line + "\n")];
+                let session_id = result.session_id.or_else(|| self.session_id.clone());
+                let watch = self.rate_limit.take_if(|_| session_id.is_some());
+                if let (Some(watch), Some(id)) = (watch, session_id) {
+                    if watch.resume_tx.send(id).is_ok() {
+                        msgs.push(LogMsg::Stderr(format!(
+                            "Claude is rate limited; resuming the session in {}s\n",
+                            watch.delay.as_secs()
+                        )));
+                    }
+                }
+                msgs
+            }
+            _ => vec![LogMsg::Stdout(line + "\n")],
+        }
+    }
+}
+
+/// Stream the CLI's stdout lines as `Stdout` events, preceded by a
+/// `SessionId` event for the `init` message so follow-ups can resume it.
+///
+/// Partial messages become `AssistantText` deltas instead of output lines.
+/// A rate limited run is reported to `rate_limit`, if given.
+fn output_events(output: DuplexStream, rate_limit: Option<RateLimitWatch>) -> EventStream {
+    let state = OutputState {
+        lines: BufReader::new(output).lines(),
+        session_id: None,
+        rate_limit,
+    };
+    futures::stream::unfold(state, |mut state| async move {
+        let events = match state.lines.next_line().await {
+            Ok(Some(line)) => state.on_line(line).into_iter().map(Ok).collect(),
+            Ok(None) => return None,
+            Err(e) => vec![Err(ExecutorError::Io(e))],
+        };
+        Some((futures::stream::iter(events), state))
+    })
+    .flatten()
+    .boxed()
+}
+
+/// A `Stderr` event explaining the interrupt, if the client exceeded a
+/// limit. Read once the output has ended, when the client is gone.
+fn limit_events(mut limits: broadcast::Receiver<LimitExceeded>) -> EventStream {
+    futures::stream::once(async move { limits.try_recv().ok() })
+        .filter_map(futures::future::ready)
+        .map(|limit| Ok(LogMsg::Stderr(format!("Stopping Claude: {limit}\n"))))
+        .boxed()
+}
+
+/// An empty stream that removes the session's settings file once the
+/// output before it has ended.
+fn remove_on_end(settings: Option<SettingsFile>) -> EventStream {
+    futures::stream::once(async move { drop(settings) })
+        .filter_map(|()| futures::future::ready(None))
+        .boxed()
+}
+
+/// `--settings` with a settings file path or inline JSON.
+fn settings_args(settings: String) -> [String; 2] {
+    ["--settings".to_string(), settings]
+}
+
+#[allow(clippy::needless_pass_by_value)]
+fn protocol_error(e: ProtocolError) -> ExecutorError {
+    ExecutorError::SpawnFailed(format!("Failed to start Claude session: {e}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use tokio::io::AsyncWriteExt;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_output_events_report_session_id() {
+        let (reader, mut writer) = tokio::io::duplex(1024);
+        writer
+            .write_all(b"{\"type\":\"system\",\"subtype\":\"init\",\"session_id\":\"s1\"}\n")
+            .await
+            .unwrap();
+        drop(writer);
+
+        let events: Vec<_> = output_events(reader, None)
+            .map(Result::unwrap)
+            .collect()
+            .await;
+        assert!(matches!(&events[0], LogMsg::SessionId(id) if id == "s1"));
+        assert!(matches!(&events[1], LogMsg::Stdout(line) if line.contains("init")));
+        assert_eq!(events.len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_output_events_report_rate_limit() {
+        let (reader, mut writer) = tokio::io::duplex(1024);
+        let result = serde_json::json!({
+            "type": "result",
+            "subtype": "success",
+            "is_error": true,
+            "result": "API Error: 529 {\"type\":\"overloaded_error\"}",
+            "session_id": "s1",
+        });
+        writer
+            .write_all(format!("{result}\n").as_bytes())
+            .await
+            .unwrap();
+        drop(writer);
+
+        let (resume_tx, resume_rx) = oneshot::channel();
+        let watch = RateLimitWatch {
+            delay: Duration::from_secs(30),
+            resume_tx,
+        };
+        let events: Vec<_> = output_events(reader, Some(watch))
+            .map(Result::unwrap)
+            .collect()
+            .await;
+        assert!(
+            matches!(&events[1], LogMsg::Stderr(e) if e.contains("resuming the session in 30s"))
+        );
+        assert_eq!(resume_rx.await.unwrap(), "s1");
+    }
+
+    #[tokio::test]
+    async fn test_output_events_stream_text_deltas() {
+        let (reader, mut writer) = tokio::io::duplex(1024);
+        for delta in ["Hel", "lo"] {
+            let line = serde_json::json!({
+                "type": "stream_event",
+                "event": {
+                    "type": "content_block_delta",
+                    "index": 0,
+                    "delta": { "type": "text_delta", "text": delta },
+                },
+            });
+            writer
+                .write_all(format!("{line}\n").as_bytes())
+                .await
+                .unwrap();
+        }
+        writer
+            .write_all(b"{\"type\":\"stream_event\",\"event\":{\"type\":\"message_stop\"}}\n")
+            .await
+            .unwrap();
+        drop(writer);
+
+        let events: Vec<_> = output_events(reader, None)
+            .map(Result::unwrap)
+            .collect()
+            .await;
+        let text: Vec<_> = events
+            .iter()
+            .map(|msg| match msg {
+                LogMsg::AssistantText(text) => text.as_str(),
+                other => panic!("unexpected event {other:?}"),
+            })
+            .collect();
+        assert_eq!(text, ["Hel", "lo"]);
+    }
+}
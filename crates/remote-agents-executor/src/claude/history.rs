@@ -0,0 +1,164 @@
+//! Reading the session transcripts Claude keeps on disk.
+
+use std::{
+    io,
+    path::{Path, PathBuf},
+};
+
+use serde::Deserialize;
+
+use super::types::{ClaudeMessage, MessageContent, UserMessage};
+use crate::normalized::{NormalizedEntry, Normalizer};
+
+/// Fields of a transcript line beyond the message itself.
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct TranscriptLine {
+    #[serde(default)]
+    session_id: Option<String>,
+    #[serde(default)]
+    cwd: Option<String>,
+    /// Part of a subagent's conversation rather than the main one.
+    #[serde(default)]
+    is_sidechain: bool,
+    /// Injected by the CLI, e.g. a slash command caveat.
+    #[serde(default)]
+    is_meta: bool,
+}
+
+/// A past session read from a transcript under `~/.claude/projects`.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ClaudeTranscript {
+    /// Claude's ID for the session, which `--resume` continues.
+    pub session_id: Option<String>,
+    /// Directory the session ran in.
+    pub cwd: Option<PathBuf>,
+    /// Messages of the main conversation, in order.
+    pub messages: Vec<ClaudeMessage>,
+}
+
+impl ClaudeTranscript {
+    /// Parse a transcript. Summaries, subagent turns, and lines injected by
+    /// the CLI are skipped.
+    #[must_use]
+    pub fn parse(jsonl: &str) -> Self {
+        let mut transcript = Self::default();
+        for line in jsonl.lines() {
+            let Ok(fields) = serde_json::from_str::<TranscriptLine>(line) else {
+                continue;
+            };
+            if fields.is_sidechain || fields.is_meta {
+                continue;
+            }
+            let Some(message) = ClaudeMessage::parse(line) else {
+                continue;
+            };
+            transcript.session_id = transcript.session_id.or(fields.session_id);
+            transcript.cwd = transcript.cwd.or_else(|| fields.cwd.map(PathBuf::from));
+            transcript.messages.push(message);
+        }
+        transcript
+    }
+
+    /// Read and parse the transcript at `path`.
+    ///
+    /// # Errors
+    /// Returns error if the file cannot be read.
+    pub async fn read(path: &Path) -> io::Result<Self> {
+        let jsonl = tokio::fs::read_to_string(path).await?;
+        Ok(Self::parse(&jsonl))
+    }
+
+    /// The first prompt of the session.
+    #[must_use]
+    pub fn prompt(&self) -> Option<&str> {
+        self.messages.iter().find_map(|message| match message {
+            ClaudeMessage::User {
+                message:
+                    UserMessage {
+                        content: MessageContent::Text(text),
+                    },
+                ..
+            } => Some(text.as_str()),
+            _ => None,
+        })
+    }
+
+    /// The conversation as normalized entries.
+    #[must_use]
+    pub fn entries(&self) -> Vec<NormalizedEntry> {
+        let mut normalizer = Normalizer::new();
+        self.messages
+            .iter()
+            .flat_map(|message| normalizer.claude(message))
+            .collect()
+    }
+}
+
+/// Where Claude keeps transcripts: `~/.claude/projects`, with a directory
+/// per working directory.
+#[must_use]
+pub fn default_projects_dir() -> Option<PathBuf> {
+    dirs::home_dir().map(|home| home.join(".claude").join("projects"))
+}
+
+/// Paths of the transcripts in each project directory under `projects_dir`.
+///
+/// # Errors
+/// Returns error if a directory cannot be read.
+pub async fn list_transcripts(projects_dir: &Path) -> io::Result<Vec<PathBuf>> {
+    let mut transcripts = Vec::new();
+    let mut projects = tokio::fs::read_dir(projects_dir).await?;
+    while let Some(project) = projects.next_entry().await? {
+        if !project.file_type().await?.is_dir() {
+            continue;
+        }
+        let mut files = tokio::fs::read_dir(project.path()).await?;
+        while let Some(file) = files.next_entry().await? {
+            let path = file.path();
+            if path.extension().is_some_and(|ext| ext == "jsonl") {
+                transcripts.push(path);
+            }
+        }
+    }
+    transcripts.sort();
+    Ok(transcripts)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_transcript() {
+        let jsonl = [
+            r#"{"type":"summary","summary":"Fix the build","leafUuid":"u2"}"#,
+            r#"{"type":"user","sessionId":"s1","cwd":"/src/app","isMeta":true,
+                "message":{"role":"user","content":"Caveat: ..."}}"#,
+            r#"{"type":"user","sessionId":"s1","cwd":"/src/app",
+                "message":{"role":"user","content":"fix the build"}}"#,
+            r#"{"type":"assistant","sessionId":"s1","cwd":"/src/app","isSidechain":true,
+                "message":{"role":"assistant","content":[{"type":"text","text":"sub"}]}}"#,
+            r#"{"type":"assistant","sessionId":"s1","cwd":"/src/app",
+                "message":{"role":"assistant","content":[{"type":"text","text":"Done"}]}}"#,
+        ]
+        .map(|line| line.replace('\n', ""))
+        .join("\n");
+
+        let transcript = ClaudeTranscript::parse(&jsonl);
+        assert_eq!(transcript.session_id.as_deref(), Some("s1"));
+        assert_eq!(transcript.cwd, Some(PathBuf::from("/src/app")));
+        assert_eq!(transcript.prompt(), Some("fix the build"));
+        assert_eq!(
+            transcript.entries(),
+            [
+                NormalizedEntry::UserMessage {
+                    content: "fix the build".into()
+                },
+                NormalizedEntry::AssistantMessage {
+                    content: "Done".into()
+                },
+            ]
+        );
+    }
+}
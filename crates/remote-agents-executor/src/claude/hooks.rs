@@ -0,0 +1,213 @@
+//! Rust callbacks for Claude hook events.
+
+use std::sync::Arc;
+
+use futures::future::BoxFuture;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+/// Events Claude can run hooks for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum HookEvent {
+    /// Before a tool runs; the output can allow, deny, or ask.
+    PreToolUse,
+    /// After a tool runs.
+    PostToolUse,
+    /// When the user submits a prompt, before the model sees it.
+    UserPromptSubmit,
+    /// When Claude sends a notification, e.g. waiting for input.
+    Notification,
+    /// When the agent finishes responding.
+    Stop,
+    /// When a subagent finishes responding.
+    SubagentStop,
+    /// Before the conversation is compacted.
+    PreCompact,
+    /// When a session starts or resumes.
+    SessionStart,
+    /// When a session ends.
+    SessionEnd,
+}
+
+impl HookEvent {
+    /// Name of the event in the hooks configuration.
+    #[must_use]
+    pub const fn as_str(&self) -> &'static str {
+        match self {
+            Self::PreToolUse => "PreToolUse",
+            Self::PostToolUse => "PostToolUse",
+            Self::UserPromptSubmit => "UserPromptSubmit",
+            Self::Notification => "Notification",
+            Self::Stop => "Stop",
+            Self::SubagentStop => "SubagentStop",
+            Self::PreCompact => "PreCompact",
+            Self::SessionStart => "SessionStart",
+            Self::SessionEnd => "SessionEnd",
+        }
+    }
+}
+
+/// Input passed to a hook callback.
+#[derive(Debug, Clone)]
+pub struct HookInput {
+    /// The hook's input, e.g. `tool_name` and `tool_input` for tool hooks.
+    pub input: Value,
+    /// Tool call the hook runs for, for tool hooks.
+    pub tool_use_id: Option<String>,
+}
+
+/// Async hook callback, returning the hook's JSON output (for example a
+/// `hookSpecificOutput` permission decision) or an error message.
+pub type HookCallback =
+    Arc<dyn Fn(HookInput) -> BoxFuture<'static, Result<Value, String>> + Send + Sync>;
+
+#[derive(Clone)]
+struct RegisteredHook {
+    event: HookEvent,
+    matcher: Option<String>,
+    callback: HookCallback,
+}
+
+/// Hook callbacks for a Claude session.
+///
+/// Each registered callback gets a callback ID, sent to Claude by
+/// `ProtocolPeer::initialize`; `ClaudeClient` routes Claude's hook callback
+/// requests back to it by that ID.
+#[derive(Clone, Default)]
+pub struct HookRegistry {
+    hooks: Vec<RegisteredHook>,
+}
+
+impl HookRegistry {
+    /// Create an empty registry.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Register a callback for `event`, returning its callback ID.
+    ///
+    /// For tool events, `matcher` is a pattern of tool names (e.g.
+    /// `Edit|Write`); `None` matches every tool.
+    pub fn register<F, Fut>(
+        &mut self,
+        event: HookEvent,
+        matcher: Option<String>,
+        callback: F,
+    ) -> String
+    where
+        F: Fn(HookInput) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<Value, String>> + Send + 'static,
+    {
+        self.hooks.push(RegisteredHook {
+            event,
+            matcher,
+            callback: Arc::new(move |input| Box::pin(callback(input))),
+        });
+        callback_id(self.hooks.len() - 1)
+    }
+
+    /// Register a callback for `event`; see `register`.
+    #[must_use]
+    pub fn with_hook<F, Fut>(
+        mut self,
+        event: HookEvent,
+        matcher: Option<String>,
+        callback: F,
+    ) -> Self
+    where
+        F: Fn(HookInput) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<Value, String>> + Send + 'static,
+    {
+        self.register(event, matcher, callback);
+        self
+    }
+
+    /// Whether no callback is registered.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.hooks.is_empty()
+    }
+
+    /// The `hooks` field of the initialize request, or `None` without hooks.
+    #[must_use]
+    pub fn initialize_payload(&self) -> Option<Value> {
+        if self.hooks.is_empty() {
+            return None;
+        }
+        let mut payload = serde_json::Map::new();
+        for (index, hook) in self.hooks.iter().enumerate() {
+            let mut entry = serde_json::json!({ "hookCallbackIds": [callback_id(index)] });
+            if let Some(matcher) = &hook.matcher {
+                entry["matcher"] = Value::String(matcher.clone());
+            }
+            let entries = payload
+                .entry(hook.event.as_str())
+                .or_insert_with(|| Value::Array(Vec::new()));
+            if let Value::Array(entries) = entries {
+                entries.push(entry);
+            }
+        }
+        Some(Value::Object(payload))
+    }
+
+    /// Run the callback registered under `callback_id`, or return `None` if
+    /// there is none.
+    pub(crate) async fn call(
+        &self,
+        callback_id: &str,
+        input: HookInput,
+    ) -> Option<Result<Value, String>> {
+        let index = callback_id
+            .strip_prefix(CALLBACK_ID_PREFIX)?
+            .parse::<usize>()
+            .ok()?;
+        let hook = self.hooks.get(index)?;
+        Some((hook.callback)(input).await)
+    }
+}
+
+/// Prefix of the callback IDs this registry hands out.
+const CALLBACK_ID_PREFIX: &str = "hook_";
+
+fn callback_id(index: usize) -> String {
+    format!("{CALLBACK_ID_PREFIX}{index}")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_registry_routes_by_callback_id() {
+        let mut hooks = HookRegistry::new();
+        let pre = hooks.register(
+            HookEvent::PreToolUse,
+            Some("Bash".into()),
+            |input| async move { Ok(serde_json::json!({ "seen": input.tool_use_id })) },
+        );
+        let stop = hooks.register(HookEvent::Stop, None, |_| async { Err("nope".to_string()) });
+
+        assert_eq!(
+            hooks.initialize_payload().unwrap(),
+            serde_json::json!({
+                "PreToolUse": [{ "matcher": "Bash", "hookCallbackIds": [pre] }],
+                "Stop": [{ "hookCallbackIds": [stop] }],
+            })
+        );
+
+        let input = HookInput {
+            input: Value::Null,
+            tool_use_id: Some("t1".into()),
+        };
+        assert_eq!(
+            hooks.call(&pre, input.clone()).await,
+            Some(Ok(serde_json::json!({ "seen": "t1" })))
+        );
+        assert_eq!(
+            hooks.call(&stop, input.clone()).await,
+            Some(Err("nope".into()))
+        );
+        assert_eq!(hooks.call("hook_9", input).await, None);
+    }
+}
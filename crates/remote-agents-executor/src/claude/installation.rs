@@ -0,0 +1,173 @@
+//! Locating the Claude CLI and checking its version.
+
+use std::{fmt, path::PathBuf, str::FromStr, time::Duration};
+
+use remote_agents_core::traits::ExecutorError;
+use remote_agents_pty::resolve_executable_path;
+use thiserror::Error;
+
+/// Oldest CLI version whose control protocol this crate supports.
+pub const MIN_CLAUDE_VERSION: ClaudeVersion = ClaudeVersion::new(2, 0, 0);
+
+/// How long to wait for `claude --version`.
+const VERSION_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Installation error.
+#[derive(Debug, Error)]
+pub enum InstallationError {
+    #[error(
+        "Claude CLI `{0}` not found on PATH; install it with \
+         `npm install -g @anthropic-ai/claude-code` or set the executable path"
+    )]
+    NotFound(String),
+    #[error("Failed to run `{} --version`: {source}", path.display())]
+    VersionCheck {
+        path: PathBuf,
+        source: std::io::Error,
+    },
+    #[error("Unrecognized `claude --version` output: {0}")]
+    UnknownVersion(String),
+    #[error(
+        "Claude CLI {found} is older than the minimum supported version {minimum}; \
+         update it with `claude update`"
+    )]
+    Unsupported {
+        found: ClaudeVersion,
+        minimum: ClaudeVersion,
+    },
+}
+
+impl From<InstallationError> for ExecutorError {
+    fn from(e: InstallationError) -> Self {
+        match e {
+            InstallationError::NotFound(_) => Self::ExecutableNotFound(e.to_string()),
+            _ => Self::SpawnFailed(e.to_string()),
+        }
+    }
+}
+
+/// A Claude CLI version, e.g. `2.0.14`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct ClaudeVersion {
+    pub major: u32,
+    pub minor: u32,
+    pub patch: u32,
+}
+
+impl ClaudeVersion {
+    /// Create a version.
+    #[must_use]
+    pub const fn new(major: u32, minor: u32, patch: u32) -> Self {
+        Self {
+            major,
+            minor,
+            patch,
+        }
+    }
+}
+
+impl fmt::Display for ClaudeVersion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}.{}.{}", self.major, self.minor, self.patch)
+    }
+}
+
+impl FromStr for ClaudeVersion {
+    type Err = InstallationError;
+
+    /// Parse `claude --version` output, e.g. `2.0.14 (Claude Code)`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let unknown = || InstallationError::UnknownVersion(s.trim().to_string());
+        let version = s.split_whitespace().next().ok_or_else(unknown)?;
+        let mut parts = version.split('.').map(str::parse::<u32>);
+        match (parts.next(), parts.next(), parts.next()) {
+            (Some(Ok(major)), Some(Ok(minor)), Some(Ok(patch))) => {
+                Ok(Self::new(major, minor, patch))
+            }
+            _ => Err(unknown()),
+        }
+    }
+}
+
+/// A located Claude CLI of a supported version.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ClaudeInstallation {
+    /// Absolute path of the executable.
+    pub path: PathBuf,
+    pub version: ClaudeVersion,
+}
+
+impl ClaudeInstallation {
+    /// Locate `claude` and check it is at least `MIN_CLAUDE_VERSION`.
+    ///
+    /// # Errors
+    /// Returns error if the CLI is missing, its version cannot be read, or
+    /// it is too old.
+    pub async fn discover() -> Result<Self, InstallationError> {
+        Self::locate("claude", MIN_CLAUDE_VERSION).await
+    }
+
+    /// Locate `program` and check it is at least `minimum`.
+    ///
+    /// Global npm and bun installs are found even when the PATH this process
+    /// started with misses them, using the login shell's PATH.
+    ///
+    /// # Errors
+    /// Returns error if the CLI is missing, its version cannot be read, or
+    /// it is older than `minimum`.
+    pub async fn locate(program: &str, minimum: ClaudeVersion) -> Result<Self, InstallationError> {
+        let path = resolve_executable_path(program)
+            .await
+            .ok_or_else(|| InstallationError::NotFound(program.to_string()))?;
+        let version = read_version(&path).await?;
+        if version < minimum {
+            return Err(InstallationError::Unsupported {
+                found: version,
+                minimum,
+            });
+        }
+        Ok(Self { path, version })
+    }
+}
+
+/// Run `path --version` and parse its output.
+async fn read_version(path: &PathBuf) -> Result<ClaudeVersion, InstallationError> {
+    let check_error = |source| InstallationError::VersionCheck {
+        path: path.clone(),
+        source,
+    };
+    let output = tokio::time::timeout(
+        VERSION_TIMEOUT,
+        tokio::process::Command::new(path)
+            .arg("--version")
+            .kill_on_drop(true)
+            .output(),
+    )
+    .await
+    .map_err(|_| check_error(std::io::ErrorKind::TimedOut.into()))?
+    .map_err(check_error)?;
+    if !output.status.success() {
+        return Err(check_error(std::io::Error::other(format!(
+            "exited with {}",
+            output.status
+        ))));
+    }
+    String::from_utf8_lossy(&output.stdout).parse()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_version() {
+        let version: ClaudeVersion = "2.0.14 (Claude Code)\n".parse().unwrap();
+        assert_eq!(version, ClaudeVersion::new(2, 0, 14));
+        assert!(version >= MIN_CLAUDE_VERSION);
+        assert!(ClaudeVersion::new(1, 0, 128) < MIN_CLAUDE_VERSION);
+        assert!(matches!(
+            "claude".parse::<ClaudeVersion>(),
+            Err(InstallationError::UnknownVersion(_))
+        ));
+    }
+}
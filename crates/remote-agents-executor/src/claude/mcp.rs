@@ -0,0 +1,152 @@
+//! MCP server configuration for Claude sessions.
+
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+
+/// Prefix Claude gives the names of tools provided by MCP servers.
+const MCP_TOOL_PREFIX: &str = "mcp__";
+
+/// How to reach an MCP server, as written to `--mcp-config`.
+///
+/// Values are passed on the agent's command line. Rather than embedding
+/// secrets, reference the session's environment with `${VAR}`, which Claude
+/// expands when it starts the server.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum McpServerConfig {
+    /// A server Claude runs as a subprocess, speaking MCP over stdio.
+    Stdio {
+        command: String,
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        args: Vec<String>,
+        #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+        env: BTreeMap<String, String>,
+    },
+    /// A remote server using streamable HTTP.
+    Http {
+        url: String,
+        #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+        headers: BTreeMap<String, String>,
+    },
+    /// A remote server using server-sent events.
+    Sse {
+        url: String,
+        #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+        headers: BTreeMap<String, String>,
+    },
+    /// A server run in-process, whose messages Claude sends over the
+    /// control protocol; see `SdkMcpServer`.
+    Sdk { name: String },
+}
+
+impl McpServerConfig {
+    /// A stdio server run with `command` and `args`.
+    #[must_use]
+    pub fn stdio<I>(command: impl Into<String>, args: I) -> Self
+    where
+        I: IntoIterator,
+        I::Item: Into<String>,
+    {
+        Self::Stdio {
+            command: command.into(),
+            args: args.into_iter().map(Into::into).collect(),
+            env: BTreeMap::new(),
+        }
+    }
+
+    /// A streamable HTTP server at `url`.
+    #[must_use]
+    pub fn http(url: impl Into<String>) -> Self {
+        Self::Http {
+            url: url.into(),
+            headers: BTreeMap::new(),
+        }
+    }
+
+    /// A server-sent events server at `url`.
+    #[must_use]
+    pub fn sse(url: impl Into<String>) -> Self {
+        Self::Sse {
+            url: url.into(),
+            headers: BTreeMap::new(),
+        }
+    }
+
+    /// An in-process server registered under `name`.
+    #[must_use]
+    pub fn sdk(name: impl Into<String>) -> Self {
+        Self::Sdk { name: name.into() }
+    }
+}
+
+/// The `--mcp-config` payload for a set of servers, keyed by server name.
+#[must_use]
+pub fn mcp_config_json(servers: &BTreeMap<String, McpServerConfig>) -> String {
+    serde_json::json!({ "mcpServers": servers }).to_string()
+}
+
+/// An MCP tool name split into its server and tool, e.g.
+/// `mcp__github__create_issue`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct McpToolName<'a> {
+    /// Name the server was configured under.
+    pub server: &'a str,
+    /// Name of the tool on that server.
+    pub tool: &'a str,
+}
+
+impl<'a> McpToolName<'a> {
+    /// Split a tool name, returning `None` for tools not provided by MCP.
+    #[must_use]
+    pub fn parse(tool_name: &'a str) -> Option<Self> {
+        let (server, tool) = tool_name.strip_prefix(MCP_TOOL_PREFIX)?.split_once("__")?;
+        Some(Self { server, tool })
+    }
+}
+
+/// Connection status of an MCP server, reported in the `init` message.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct McpServerStatus {
+    pub name: String,
+    /// `connected`, `failed`, `pending`, or `needs-auth`.
+    pub status: String,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_mcp_config_json() {
+        let mut servers = BTreeMap::new();
+        servers.insert(
+            "files".to_string(),
+            McpServerConfig::stdio("npx", ["-y", "mcp-files"]),
+        );
+        servers.insert(
+            "docs".to_string(),
+            McpServerConfig::http("https://docs.example.com/mcp"),
+        );
+        let json: serde_json::Value = serde_json::from_str(&mcp_config_json(&servers)).unwrap();
+        assert_eq!(
+            json,
+            serde_json::json!({ "mcpServers": {
+                "docs": { "type": "http", "url": "https://docs.example.com/mcp" },
+                "files": { "type": "stdio", "command": "npx", "args": ["-y", "mcp-files"] },
+            }})
+        );
+    }
+
+    #[test]
+    fn test_parse_mcp_tool_name() {
+        assert_eq!(
+            McpToolName::parse("mcp__github__create_issue"),
+            Some(McpToolName {
+                server: "github",
+                tool: "create_issue",
+            })
+        );
+        assert_eq!(McpToolName::parse("Bash"), None);
+    }
+}
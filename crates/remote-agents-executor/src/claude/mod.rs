@@ -1,9 +1,39 @@
 //! Claude Code executor and SDK protocol.
 
 pub mod client;
+pub mod commands;
+pub mod config;
+pub mod diff;
+pub mod executor;
+pub mod history;
+pub mod hooks;
+pub mod installation;
+pub mod mcp;
+pub mod plan;
 pub mod protocol;
+pub mod retry;
+pub mod rotation;
+pub mod sdk_mcp;
+pub mod settings;
+pub mod stderr;
 pub mod types;
 
-pub use client::ClaudeClient;
+pub use client::{ClaudeClient, ClaudeUsage, LimitExceeded, LogWriter};
+pub use commands::{SlashCommand, slash_command_prompt};
+pub use config::ClaudeConfig;
+pub use diff::FileDiff;
+pub use executor::ClaudeExecutor;
+pub use history::ClaudeTranscript;
+pub use hooks::{HookEvent, HookInput, HookRegistry};
+pub use installation::{ClaudeInstallation, ClaudeVersion, InstallationError};
+pub use mcp::{McpServerConfig, McpToolName};
+pub use plan::{PlanDecision, PlanProposal};
 pub use protocol::ProtocolPeer;
-pub use types::PermissionMode;
+pub use retry::RateLimitPolicy;
+pub use rotation::LogRotation;
+pub use sdk_mcp::SdkMcpServer;
+pub use settings::{ClaudeSettings, SettingsFile};
+pub use stderr::ClaudeFailure;
+pub use types::{
+    ClaudeMessage, CompactTrigger, Compaction, ContentBlock, PermissionMode, SessionResult,
+};
@@ -0,0 +1,82 @@
+//! Plan mode: reviewing the plan Claude proposes before it starts editing.
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use super::types::{
+    PermissionMode, PermissionResult, PermissionUpdate, PermissionUpdateDestination,
+    PermissionUpdateType,
+};
+
+/// Tool Claude calls in plan mode to propose its plan and leave plan mode.
+pub const EXIT_PLAN_MODE_TOOL: &str = "ExitPlanMode";
+
+/// A plan Claude proposed, awaiting `ClaudeClient::resolve_plan`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PlanProposal {
+    /// ID of the `ExitPlanMode` tool call, to resolve the plan by.
+    pub tool_use_id: String,
+    /// The plan, as Markdown.
+    pub plan: String,
+}
+
+impl PlanProposal {
+    /// The plan from an `ExitPlanMode` tool input.
+    #[must_use]
+    pub fn plan_from_input(input: &Value) -> Option<&str> {
+        input.get("plan").and_then(Value::as_str)
+    }
+}
+
+/// How to resolve a proposed plan.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PlanDecision {
+    /// Accept the plan and continue in `mode`, e.g. `AcceptEdits`.
+    Approve { mode: PermissionMode },
+    /// Reject the plan; Claude stays in plan mode and revises it.
+    Reject { feedback: String },
+}
+
+impl PlanDecision {
+    /// Response to the `ExitPlanMode` permission request.
+    pub(crate) fn into_permission_result(self, input: Value) -> PermissionResult {
+        match self {
+            Self::Approve { mode } => PermissionResult::Allow {
+                updated_input: input,
+                updated_permissions: Some(vec![PermissionUpdate {
+                    update_type: PermissionUpdateType::SetMode,
+                    mode: Some(mode),
+                    destination: Some(PermissionUpdateDestination::Session),
+                    rules: None,
+                    behavior: None,
+                    directories: None,
+                }]),
+            },
+            Self::Reject { feedback } => PermissionResult::Deny {
+                message: feedback,
+                interrupt: Some(false),
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_approve_switches_mode() {
+        let decision = PlanDecision::Approve {
+            mode: PermissionMode::AcceptEdits,
+        };
+        let result = serde_json::to_value(decision.into_permission_result(Value::Null)).unwrap();
+        assert_eq!(
+            result["updatedPermissions"],
+            serde_json::json!([{
+                "type": "setMode",
+                "mode": "acceptEdits",
+                "destination": "session",
+            }])
+        );
+    }
+}
@@ -1,15 +1,22 @@
 //! Claude Code control protocol handler.
 
-use std::sync::Arc;
+use std::{
+    collections::HashMap,
+    sync::{Arc, MutexGuard, PoisonError},
+    time::Duration,
+};
 
 use futures::FutureExt;
+use serde_json::Value;
 use tokio::{
     io::{AsyncBufReadExt, AsyncWriteExt, BufReader},
     process::{ChildStdin, ChildStdout},
-    sync::{Mutex, oneshot},
+    sync::{Mutex, mpsc, oneshot},
 };
 
 use super::client::ClaudeClient;
+use super::commands::slash_command_prompt;
+use super::config::ClaudeConfig;
 use super::types::{
     CLIMessage, ControlRequestType, ControlResponseMessage, ControlResponseType,
     Message, PermissionMode, SDKControlRequest, SDKControlRequestType,
@@ -22,18 +29,38 @@ pub enum ProtocolError {
     Io(#[from] std::io::Error),
     #[error("JSON error: {0}")]
     Json(#[from] serde_json::Error),
+    #[error("Control request failed: {0}")]
+    ControlRequestFailed(String),
+    #[error("No response to control request within {0:?}")]
+    Timeout(Duration),
+    #[error("CLI exited before responding to control request")]
+    Closed,
 }
 
+/// How long to wait for the CLI to acknowledge a control request.
+pub const DEFAULT_CONTROL_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Senders for the responses to control requests awaiting one, by request
+/// ID.
+type PendingRequests = HashMap<String, oneshot::Sender<ControlResponseType>>;
+
 /// Handles bidirectional control protocol communication.
 #[derive(Clone)]
 pub struct ProtocolPeer {
-    stdin: Arc<Mutex<ChildStdin>>,
+    /// `None` once closed after the final result.
+    stdin: Arc<Mutex<Option<ChildStdin>>>,
+    /// The client's registered hooks, for `initialize`.
+    hooks: Option<serde_json::Value>,
+    /// ID of the initialize request, to pass its response to the client.
+    initialize_id: Arc<std::sync::Mutex<Option<String>>>,
+    pending: Arc<std::sync::Mutex<PendingRequests>>,
 }
 
 impl ProtocolPeer {
     /// Spawn a new protocol peer.
     ///
     /// This starts a background task to read from stdout and handle control messages.
+    /// Once the final result is read, stdin is closed so the CLI exits.
     #[must_use]
     pub fn spawn(
         stdin: ChildStdin,
@@ -42,7 +69,10 @@ impl ProtocolPeer {
         interrupt_rx: oneshot::Receiver<()>,
     ) -> Self {
         let peer = Self {
-            stdin: Arc::new(Mutex::new(stdin)),
+            stdin: Arc::new(Mutex::new(Some(stdin))),
+            hooks: client.hooks().initialize_payload(),
+            initialize_id: Arc::default(),
+            pending: Arc::default(),
         };
 
         let reader_peer = peer.clone();
@@ -78,15 +108,20 @@ impl ProtocolPeer {
                             }
                             match serde_json::from_str::<CLIMessage>(line) {
                                 Ok(CLIMessage::ControlRequest { request_id, request }) => {
-                                    self.handle_control_request(&client, request_id, request).await;
+                                    self.spawn_control_request(&client, request_id, request);
+                                }
+                                Ok(CLIMessage::ControlResponse { response }) => {
+                                    self.handle_control_response(&client, response);
                                 }
-                                Ok(CLIMessage::ControlResponse { .. }) => {}
                                 Ok(CLIMessage::Result(_)) => {
                                     client.on_non_control(line).await;
+                                    self.stdin.lock().await.take();
                                     break;
                                 }
                                 _ => {
-                                    client.on_non_control(line).await;
+                                    if client.on_non_control(line).await.is_some() {
+                                        self.spawn_interrupt();
+                                    }
                                 }
                             }
                         }
@@ -96,16 +131,41 @@ impl ProtocolPeer {
                         }
                     }
                 }
-                _ = &mut interrupt_rx => {
-                    if let Err(e) = self.interrupt().await {
-                        tracing::debug!("Failed to send interrupt to Claude: {e}");
-                    }
-                }
+                _ = &mut interrupt_rx => self.spawn_interrupt(),
             }
         }
+        // Requests still awaiting a response will not get one.
+        self.pending_requests().clear();
         Ok(())
     }
 
+    /// Interrupt in the background, as the reader must stay free to read
+    /// the acknowledgement.
+    fn spawn_interrupt(&self) {
+        let peer = self.clone();
+        tokio::spawn(async move {
+            if let Err(e) = peer.interrupt().await {
+                tracing::debug!("Failed to interrupt Claude: {e}");
+            }
+        });
+    }
+
+    /// Handle a control request in the background, as answering it may wait
+    /// on a human approval while the reader must stay free to read responses
+    /// to our own requests, e.g. an interrupt.
+    fn spawn_control_request(
+        &self,
+        client: &Arc<ClaudeClient>,
+        request_id: String,
+        request: ControlRequestType,
+    ) {
+        let peer = self.clone();
+        let client = Arc::clone(client);
+        tokio::spawn(async move {
+            peer.handle_control_request(&client, request_id, request).await;
+        });
+    }
+
     async fn handle_control_request(
         &self,
         client: &Arc<ClaudeClient>,
@@ -155,6 +215,88 @@ impl ProtocolPeer {
                     }
                 }
             }
+            ControlRequestType::McpMessage {
+                server_name,
+                message,
+            } => {
+                match client.on_mcp_message(server_name, &message).await {
+                    Ok(response) => {
+                        let response = serde_json::json!({ "mcp_response": response });
+                        if let Err(e) = self.send_hook_response(request_id, response).await {
+                            tracing::error!("Failed to send MCP response: {e}");
+                        }
+                    }
+                    Err(e) => {
+                        tracing::error!("Error in on_mcp_message: {e}");
+                        if let Err(e2) = self.send_error(request_id, e.to_string()).await {
+                            tracing::error!("Failed to send error response: {e2}");
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    fn handle_control_response(&self, client: &ClaudeClient, response: ControlResponseType) {
+        let (ControlResponseType::Success { request_id, .. }
+        | ControlResponseType::Error { request_id, .. }) = &response;
+        let request_id = request_id.clone();
+        let initialized = self
+            .initialize_id
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .take_if(|id| *id == request_id)
+            .is_some();
+        if initialized {
+            if let ControlResponseType::Success {
+                response: Some(response),
+                ..
+            } = &response
+            {
+                client.on_initialized(response);
+            }
+            return;
+        }
+        let pending = self.pending_requests().remove(&request_id);
+        if let Some(tx) = pending {
+            // The caller may have stopped waiting; that is not an error.
+            let _ = tx.send(response);
+        }
+    }
+
+    fn pending_requests(&self) -> MutexGuard<'_, PendingRequests> {
+        self.pending.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Send a control request and wait up to `timeout` for the CLI to
+    /// acknowledge it, returning the response payload.
+    ///
+    /// # Errors
+    /// Returns error if write fails, the CLI rejects the request or exits
+    /// first, or no response arrives within `timeout`.
+    pub async fn send_control_request(
+        &self,
+        request: SDKControlRequestType,
+        timeout: Duration,
+    ) -> Result<Option<Value>, ProtocolError> {
+        let request = SDKControlRequest::new(request);
+        let request_id = request.request_id.clone();
+        let (tx, rx) = oneshot::channel();
+        self.pending_requests().insert(request_id.clone(), tx);
+        if let Err(e) = self.send_json(&request).await {
+            self.pending_requests().remove(&request_id);
+            return Err(e);
+        }
+        match tokio::time::timeout(timeout, rx).await {
+            Ok(Ok(ControlResponseType::Success { response, .. })) => Ok(response),
+            Ok(Ok(ControlResponseType::Error { error, .. })) => Err(
+                ProtocolError::ControlRequestFailed(error.unwrap_or_default()),
+            ),
+            Ok(Err(_)) => Err(ProtocolError::Closed),
+            Err(_) => {
+                self.pending_requests().remove(&request_id);
+                Err(ProtocolError::Timeout(timeout))
+            }
         }
     }
 
@@ -185,6 +327,9 @@ impl ProtocolPeer {
     async fn send_json<T: serde::Serialize>(&self, message: &T) -> Result<(), ProtocolError> {
         let json = serde_json::to_string(message)?;
         let mut stdin = self.stdin.lock().await;
+        let stdin = stdin
+            .as_mut()
+            .ok_or_else(|| std::io::Error::from(std::io::ErrorKind::BrokenPipe))?;
         stdin.write_all(json.as_bytes()).await?;
         stdin.write_all(b"\n").await?;
         stdin.flush().await?;
@@ -200,30 +345,215 @@ impl ProtocolPeer {
         self.send_json(&message).await
     }
 
-    /// Initialize the protocol.
+    /// Send a slash command, e.g. `compact` with optional instructions.
     ///
     /// # Errors
     /// Returns error if write fails.
-    pub async fn initialize(&self, hooks: Option<serde_json::Value>) -> Result<(), ProtocolError> {
-        self.send_json(&SDKControlRequest::new(SDKControlRequestType::Initialize { hooks }))
-            .await
+    pub async fn send_slash_command(&self, name: &str, args: &str) -> Result<(), ProtocolError> {
+        self.send_user_message(slash_command_prompt(name, args)).await
     }
 
-    /// Send interrupt request.
+    /// Compact the conversation now rather than waiting for the CLI to do
+    /// so when the context fills up, keeping long sessions and their
+    /// follow-ups responsive. `instructions`, if not empty, say what the
+    /// summary should keep.
+    ///
+    /// The CLI reports the compaction to `ClaudeClient::subscribe_compactions`
+    /// with a `Manual` trigger.
     ///
     /// # Errors
     /// Returns error if write fails.
-    pub async fn interrupt(&self) -> Result<(), ProtocolError> {
-        self.send_json(&SDKControlRequest::new(SDKControlRequestType::Interrupt {}))
+    pub async fn compact(&self, instructions: &str) -> Result<(), ProtocolError> {
+        self.send_slash_command("compact", instructions).await
+    }
+
+    /// Initialize the protocol with the client's registered hooks, then
+    /// apply the config's model and thinking budget if set.
+    ///
+    /// The CLI's response lists its slash commands, available from
+    /// `ClaudeClient::slash_commands` once it arrives.
+    ///
+    /// # Errors
+    /// Returns error if write fails, or the CLI rejects the model or
+    /// thinking budget.
+    pub async fn initialize(&self, config: &ClaudeConfig) -> Result<(), ProtocolError> {
+        let hooks = self.hooks.clone();
+        let request = SDKControlRequest::new(SDKControlRequestType::Initialize { hooks });
+        *self
+            .initialize_id
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner) = Some(request.request_id.clone());
+        self.send_json(&request).await?;
+        if config.model.is_some() {
+            self.set_model(config.model.clone()).await?;
+        }
+        if config.max_thinking_tokens.is_some() {
+            self.set_max_thinking_tokens(config.max_thinking_tokens).await?;
+        }
+        Ok(())
+    }
+
+    /// Send a control request and wait for its acknowledgement.
+    async fn control(&self, request: SDKControlRequestType) -> Result<(), ProtocolError> {
+        self.send_control_request(request, DEFAULT_CONTROL_TIMEOUT)
             .await
+            .map(drop)
     }
 
-    /// Set permission mode.
+    /// Switch model, or back to the default with `None`.
     ///
     /// # Errors
-    /// Returns error if write fails.
+    /// Returns error if write fails or the CLI does not acknowledge it.
+    pub async fn set_model(&self, model: Option<String>) -> Result<(), ProtocolError> {
+        self.control(SDKControlRequestType::SetModel { model }).await
+    }
+
+    /// Set the thinking token budget, or remove it with `None`.
+    ///
+    /// # Errors
+    /// Returns error if write fails or the CLI does not acknowledge it.
+    pub async fn set_max_thinking_tokens(
+        &self,
+        max_thinking_tokens: Option<u32>,
+    ) -> Result<(), ProtocolError> {
+        self.control(SDKControlRequestType::SetMaxThinkingTokens { max_thinking_tokens })
+            .await
+    }
+
+    /// Send interrupt request and wait for its acknowledgement.
+    ///
+    /// # Errors
+    /// Returns error if write fails or the CLI does not acknowledge it.
+    pub async fn interrupt(&self) -> Result<(), ProtocolError> {
+        self.control(SDKControlRequestType::Interrupt {}).await
+    }
+
+    /// Set permission mode and wait for its acknowledgement.
+    ///
+    /// # Errors
+    /// Returns error if write fails or the CLI does not acknowledge it.
     pub async fn set_permission_mode(&self, mode: PermissionMode) -> Result<(), ProtocolError> {
-        self.send_json(&SDKControlRequest::new(SDKControlRequestType::SetPermissionMode { mode }))
+        self.control(SDKControlRequestType::SetPermissionMode { mode })
             .await
     }
+
+    /// Apply permission mode changes sent by the session manager, by mode
+    /// name, until the sender is dropped.
+    ///
+    /// Pass the receiver of the channel given to
+    /// `SpawnedProcess::with_permission_mode`.
+    pub fn forward_permission_modes(&self, mut modes: mpsc::UnboundedReceiver<String>) {
+        let peer = self.clone();
+        tokio::spawn(async move {
+            while let Some(mode) = modes.recv().await {
+                let result = match mode.parse() {
+                    Ok(mode) => peer.set_permission_mode(mode).await,
+                    Err(e) => {
+                        tracing::warn!("Ignoring permission mode change: {e}");
+                        continue;
+                    }
+                };
+                if let Err(e) = result {
+                    tracing::error!("Failed to set permission mode: {e}");
+                }
+            }
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::process::Stdio;
+
+    use super::super::client::LogWriter;
+    use super::*;
+
+    /// A stand-in for the CLI that rejects every control request.
+    const REJECT_ALL: &str = r#"while read -r line; do
+        id=$(printf '%s' "$line" | sed 's/.*"request_id":"\([^"]*\)".*/\1/')
+        printf '{"type":"control_response","response":'
+        printf '{"subtype":"error","request_id":"%s","error":"denied"}}\n' "$id"
+    done"#;
+
+    /// A stand-in for the CLI that asks to use a tool, then acknowledges
+    /// every control request.
+    const ASK_THEN_ACK: &str = r#"printf '{"type":"control_request","request_id":"c1","request":'
+    printf '{"subtype":"can_use_tool","tool_name":"Bash","input":{},"tool_use_id":"t1"}}\n'
+    while read -r line; do
+        id=$(printf '%s' "$line" | sed 's/.*"request_id":"\([^"]*\)".*/\1/')
+        printf '{"type":"control_response","response":'
+        printf '{"subtype":"success","request_id":"%s"}}\n' "$id"
+    done"#;
+
+    /// Approval handler that never decides, like a human who is away.
+    struct Undecided(tokio::sync::Notify);
+
+    #[async_trait::async_trait]
+    impl crate::approvals::ApprovalHandler for Undecided {
+        async fn request_approval(
+            &self,
+            _tool_name: &str,
+            _tool_input: Value,
+            _tool_call_id: &str,
+        ) -> Result<crate::approvals::ApprovalResult, crate::approvals::ApprovalError> {
+            self.0.notify_one();
+            std::future::pending().await
+        }
+    }
+
+    #[tokio::test]
+    async fn test_interrupt_while_approval_pending() {
+        let mut child = tokio::process::Command::new("sh")
+            .args(["-c", ASK_THEN_ACK])
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .kill_on_drop(true)
+            .spawn()
+            .unwrap();
+        let handler = Arc::new(Undecided(tokio::sync::Notify::new()));
+        let client = ClaudeClient::new(
+            LogWriter::new(tokio::io::sink()),
+            Some(Arc::clone(&handler) as Arc<dyn crate::approvals::ApprovalHandler>),
+        );
+        let (_interrupt_tx, interrupt_rx) = oneshot::channel();
+        let peer = ProtocolPeer::spawn(
+            child.stdin.take().unwrap(),
+            child.stdout.take().unwrap(),
+            client,
+            interrupt_rx,
+        );
+
+        handler.0.notified().await;
+        let interrupted = peer
+            .send_control_request(SDKControlRequestType::Interrupt {}, Duration::from_secs(5))
+            .await;
+        assert!(interrupted.is_ok(), "{interrupted:?}");
+        peer.set_permission_mode(PermissionMode::Plan).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_control_request_acknowledgements() {
+        let mut child = tokio::process::Command::new("sh")
+            .args(["-c", REJECT_ALL])
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .kill_on_drop(true)
+            .spawn()
+            .unwrap();
+        let client = ClaudeClient::new(LogWriter::new(tokio::io::sink()), None);
+        let (_interrupt_tx, interrupt_rx) = oneshot::channel();
+        let peer = ProtocolPeer::spawn(
+            child.stdin.take().unwrap(),
+            child.stdout.take().unwrap(),
+            client,
+            interrupt_rx,
+        );
+
+        let err = peer.set_permission_mode(PermissionMode::Plan).await;
+        assert!(matches!(err, Err(ProtocolError::ControlRequestFailed(e)) if e == "denied"));
+
+        child.kill().await.unwrap();
+        let err = peer.interrupt().await;
+        assert!(matches!(err, Err(ProtocolError::Io(_) | ProtocolError::Closed)));
+    }
 }
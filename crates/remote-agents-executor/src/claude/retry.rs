@@ -0,0 +1,99 @@
+//! Resuming Claude sessions stopped by rate limits.
+
+use std::time::Duration;
+
+use super::stderr::ClaudeFailure;
+use super::types::ResultMessage;
+
+/// Prompt continuing a resumed session, unless the policy sets another.
+pub const DEFAULT_RESUME_PROMPT: &str = "Continue from where you left off.";
+
+/// How `ClaudeExecutor` resumes sessions whose run ended in a rate limit or
+/// overload error.
+///
+/// Instead of ending the session with the error, the executor waits, then
+/// continues it in a new CLI process with `--resume` and `prompt`. Once
+/// `max_resumes` are used, the error ends the session.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RateLimitPolicy {
+    /// Resumes per session before the error is surfaced.
+    pub max_resumes: u32,
+    /// Delay before the first resume.
+    pub initial_backoff: Duration,
+    /// Upper bound on the delay before a resume.
+    pub max_backoff: Duration,
+    /// Factor the delay grows by after each resume.
+    pub multiplier: u32,
+    /// Prompt sent to the resumed session.
+    pub prompt: String,
+}
+
+impl Default for RateLimitPolicy {
+    fn default() -> Self {
+        Self::new(3)
+    }
+}
+
+impl RateLimitPolicy {
+    /// Resume up to `max_resumes` times, backing off from 30s up to 10 minutes.
+    #[must_use]
+    pub fn new(max_resumes: u32) -> Self {
+        Self {
+            max_resumes,
+            initial_backoff: Duration::from_secs(30),
+            max_backoff: Duration::from_secs(600),
+            multiplier: 2,
+            prompt: DEFAULT_RESUME_PROMPT.to_string(),
+        }
+    }
+
+    /// Send `prompt` to resumed sessions.
+    #[must_use]
+    pub fn with_prompt(mut self, prompt: impl Into<String>) -> Self {
+        self.prompt = prompt.into();
+        self
+    }
+
+    /// Delay before the given resume (1 for the first resume).
+    #[must_use]
+    pub fn backoff(&self, resume: u32) -> Duration {
+        let factor = self.multiplier.saturating_pow(resume.saturating_sub(1));
+        self.initial_backoff
+            .saturating_mul(factor)
+            .min(self.max_backoff)
+    }
+}
+
+/// Whether a run ended because the API was rate limited or overloaded.
+pub(crate) fn is_rate_limited(result: &ResultMessage) -> bool {
+    result.is_error
+        && matches!(
+            result.result.as_deref().and_then(ClaudeFailure::classify),
+            Some(ClaudeFailure::RateLimited | ClaudeFailure::Overloaded)
+        )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_backoff_and_detection() {
+        let policy = RateLimitPolicy::new(5);
+        assert_eq!(policy.backoff(1), Duration::from_secs(30));
+        assert_eq!(policy.backoff(3), Duration::from_secs(120));
+        assert_eq!(policy.backoff(10), Duration::from_secs(600));
+
+        let result: ResultMessage = serde_json::from_str(
+            r#"{"subtype":"success","is_error":true,
+                "result":"API Error: 429 {\"type\":\"rate_limit_error\"}"}"#,
+        )
+        .unwrap();
+        assert!(is_rate_limited(&result));
+        let result = ResultMessage {
+            is_error: false,
+            ..result
+        };
+        assert!(!is_rate_limited(&result));
+    }
+}
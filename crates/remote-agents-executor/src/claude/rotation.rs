@@ -0,0 +1,143 @@
+//! Log files rotated by size.
+
+use std::{
+    io,
+    path::{Path, PathBuf},
+};
+
+use tokio::{
+    fs::{File, OpenOptions},
+    io::AsyncWriteExt,
+};
+
+/// When a log file is rotated and how many rotated files are kept.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LogRotation {
+    /// Size in bytes past which the file is rotated before the next write.
+    pub max_bytes: u64,
+    /// Rotated files kept, `{path}.1` being the newest. With 0, the file
+    /// is truncated instead.
+    pub max_files: usize,
+}
+
+impl Default for LogRotation {
+    /// Rotate at 10 MiB, keeping 5 rotated files.
+    fn default() -> Self {
+        Self {
+            max_bytes: 10 * 1024 * 1024,
+            max_files: 5,
+        }
+    }
+}
+
+/// A log file appended to line by line, rotated per its `LogRotation`.
+pub(crate) struct RotatingFile {
+    path: PathBuf,
+    rotation: LogRotation,
+    file: File,
+    size: u64,
+}
+
+impl RotatingFile {
+    /// Open `path` for appending, creating it and its directory if missing.
+    pub(crate) async fn open(path: PathBuf, rotation: LogRotation) -> io::Result<Self> {
+        if let Some(dir) = path.parent() {
+            tokio::fs::create_dir_all(dir).await?;
+        }
+        let file = append(&path).await?;
+        let size = file.metadata().await?.len();
+        Ok(Self {
+            path,
+            rotation,
+            file,
+            size,
+        })
+    }
+
+    /// Append `line`, rotating first if it would take the file past the
+    /// size limit. A line larger than the limit gets a file of its own.
+    pub(crate) async fn write_line(&mut self, line: &[u8]) -> io::Result<()> {
+        let len = line.len() as u64;
+        if self.size > 0 && self.size + len > self.rotation.max_bytes {
+            self.rotate().await?;
+        }
+        self.file.write_all(line).await?;
+        self.file.flush().await?;
+        self.size += len;
+        Ok(())
+    }
+
+    /// Shift `{path}.N` to `{path}.N+1`, dropping the oldest, and start a
+    /// new file at `path`.
+    async fn rotate(&mut self) -> io::Result<()> {
+        let max_files = self.rotation.max_files;
+        if max_files == 0 {
+            self.file.set_len(0).await?;
+            self.size = 0;
+            return Ok(());
+        }
+        remove_if_exists(&rotated(&self.path, max_files)).await?;
+        for n in (1..max_files).rev() {
+            rename_if_exists(&rotated(&self.path, n), &rotated(&self.path, n + 1)).await?;
+        }
+        tokio::fs::rename(&self.path, rotated(&self.path, 1)).await?;
+        self.file = append(&self.path).await?;
+        self.size = 0;
+        Ok(())
+    }
+}
+
+async fn append(path: &Path) -> io::Result<File> {
+    OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .await
+}
+
+/// `{path}.{n}`.
+fn rotated(path: &Path, n: usize) -> PathBuf {
+    let mut name = path.as_os_str().to_owned();
+    name.push(format!(".{n}"));
+    PathBuf::from(name)
+}
+
+async fn remove_if_exists(path: &Path) -> io::Result<()> {
+    match tokio::fs::remove_file(path).await {
+        Err(e) if e.kind() != io::ErrorKind::NotFound => Err(e),
+        _ => Ok(()),
+    }
+}
+
+async fn rename_if_exists(from: &Path, to: &Path) -> io::Result<()> {
+    match tokio::fs::rename(from, to).await {
+        Err(e) if e.kind() != io::ErrorKind::NotFound => Err(e),
+        _ => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_rotation_keeps_max_files() {
+        let dir = std::env::temp_dir().join(format!("log-rotation-{}", uuid::Uuid::new_v4()));
+        let path = dir.join("session.jsonl");
+        let rotation = LogRotation {
+            max_bytes: 8,
+            max_files: 2,
+        };
+        let mut file = RotatingFile::open(path.clone(), rotation).await.unwrap();
+        for line in ["one\n", "two\n", "three\n", "four\n", "five\n"] {
+            file.write_line(line.as_bytes()).await.unwrap();
+        }
+
+        let read = |path: PathBuf| async move { tokio::fs::read_to_string(path).await.unwrap() };
+        assert_eq!(read(path.clone()).await, "five\n");
+        assert_eq!(read(rotated(&path, 1)).await, "four\n");
+        assert_eq!(read(rotated(&path, 2)).await, "three\n");
+        assert!(!rotated(&path, 3).exists());
+        tokio::fs::remove_dir_all(&dir).await.unwrap();
+    }
+}
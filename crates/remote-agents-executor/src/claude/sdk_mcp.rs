@@ -0,0 +1,192 @@
+//! MCP servers implemented in Rust and run in-process.
+
+use std::sync::Arc;
+
+use futures::future::BoxFuture;
+use serde_json::{Value, json};
+
+/// MCP protocol version the in-process servers speak.
+const MCP_PROTOCOL_VERSION: &str = "2024-11-05";
+
+/// JSON-RPC error code for an unsupported method.
+const METHOD_NOT_FOUND: i64 = -32601;
+/// JSON-RPC error code for bad parameters, e.g. an unknown tool.
+const INVALID_PARAMS: i64 = -32602;
+
+/// Async tool implementation, given the call's arguments and returning its
+/// text result or an error message shown to the model.
+pub type McpToolHandler =
+    Arc<dyn Fn(Value) -> BoxFuture<'static, Result<String, String>> + Send + Sync>;
+
+#[derive(Clone)]
+struct McpTool {
+    name: String,
+    description: String,
+    input_schema: Value,
+    handler: McpToolHandler,
+}
+
+/// An MCP server whose tools are Rust callbacks.
+///
+/// Add it with `ClaudeExecutor::with_sdk_mcp_server`. Claude sees it as an
+/// `sdk` server and sends its MCP messages over the control protocol, where
+/// `ClaudeClient` answers them, so no external MCP process is needed.
+#[derive(Clone)]
+pub struct SdkMcpServer {
+    name: String,
+    version: String,
+    tools: Vec<McpTool>,
+}
+
+impl SdkMcpServer {
+    /// Create a server with no tools. Its tools are named
+    /// `mcp__{name}__{tool}` in the session.
+    #[must_use]
+    pub fn new(name: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            version: "1.0.0".to_string(),
+            tools: Vec::new(),
+        }
+    }
+
+    /// Report `version` to Claude instead of `1.0.0`.
+    #[must_use]
+    pub fn with_version(mut self, version: impl Into<String>) -> Self {
+        self.version = version.into();
+        self
+    }
+
+    /// Add a tool taking arguments described by the JSON Schema
+    /// `input_schema`.
+    #[must_use]
+    pub fn with_tool<F, Fut>(
+        mut self,
+        name: impl Into<String>,
+        description: impl Into<String>,
+        input_schema: Value,
+        handler: F,
+    ) -> Self
+    where
+        F: Fn(Value) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<String, String>> + Send + 'static,
+    {
+        self.tools.push(McpTool {
+            name: name.into(),
+            description: description.into(),
+            input_schema,
+            handler: Arc::new(move |input| Box::pin(handler(input))),
+        });
+        self
+    }
+
+    /// Name the server is configured under.
+    #[must_use]
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Answer a JSON-RPC message from Claude.
+    pub async fn handle_message(&self, message: &Value) -> Value {
+        let id = message.get("id").cloned().unwrap_or(Value::Null);
+        let params = message.get("params").unwrap_or(&Value::Null);
+        let result = match message.get("method").and_then(Value::as_str) {
+            Some("initialize") => Ok(json!({
+                "protocolVersion": MCP_PROTOCOL_VERSION,
+                "capabilities": { "tools": {} },
+                "serverInfo": { "name": self.name, "version": self.version },
+            })),
+            Some("tools/list") => Ok(json!({ "tools": self.tools_list() })),
+            Some("tools/call") => self.call_tool(params).await,
+            // Notifications need no answer, but Claude expects a response.
+            Some(method) if method.starts_with("notifications/") => Ok(json!({})),
+            Some(method) => Err((METHOD_NOT_FOUND, format!("Method not found: {method}"))),
+            None => Err((METHOD_NOT_FOUND, "Missing method".to_string())),
+        };
+        match result {
+            Ok(result) => json!({ "jsonrpc": "2.0", "id": id, "result": result }),
+            Err((code, message)) => json!({
+                "jsonrpc": "2.0",
+                "id": id,
+                "error": { "code": code, "message": message },
+            }),
+        }
+    }
+
+    fn tools_list(&self) -> Vec<Value> {
+        self.tools
+            .iter()
+            .map(|tool| {
+                json!({
+                    "name": tool.name,
+                    "description": tool.description,
+                    "inputSchema": tool.input_schema,
+                })
+            })
+            .collect()
+    }
+
+    async fn call_tool(&self, params: &Value) -> Result<Value, (i64, String)> {
+        let name = params
+            .get("name")
+            .and_then(Value::as_str)
+            .unwrap_or_default();
+        let tool = self
+            .tools
+            .iter()
+            .find(|tool| tool.name == name)
+            .ok_or_else(|| (INVALID_PARAMS, format!("Unknown tool: {name}")))?;
+        let arguments = params
+            .get("arguments")
+            .cloned()
+            .unwrap_or_else(|| json!({}));
+        let (text, is_error) = match (tool.handler)(arguments).await {
+            Ok(text) => (text, false),
+            Err(error) => (error, true),
+        };
+        Ok(json!({
+            "content": [{ "type": "text", "text": text }],
+            "isError": is_error,
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_list_and_call_tools() {
+        let server = SdkMcpServer::new("math").with_tool(
+            "add",
+            "Add two numbers",
+            json!({ "type": "object" }),
+            |args: Value| async move {
+                let sum = args["a"]
+                    .as_i64()
+                    .zip(args["b"].as_i64())
+                    .map(|(a, b)| a + b);
+                sum.map(|sum| sum.to_string())
+                    .ok_or_else(|| "a and b must be numbers".into())
+            },
+        );
+
+        let list = json!({ "jsonrpc": "2.0", "id": 1, "method": "tools/list" });
+        let response = server.handle_message(&list).await;
+        assert_eq!(response["result"]["tools"][0]["name"], "add");
+
+        let call = json!({ "jsonrpc": "2.0", "id": 2, "method": "tools/call",
+            "params": { "name": "add", "arguments": { "a": 2, "b": 3 } } });
+        let response = server.handle_message(&call).await;
+        assert_eq!(response["id"], 2);
+        assert_eq!(
+            response["result"],
+            json!({ "content": [{ "type": "text", "text": "5" }], "isError": false })
+        );
+
+        let call = json!({ "jsonrpc": "2.0", "id": 3, "method": "tools/call",
+            "params": { "name": "subtract" } });
+        let response = server.handle_message(&call).await;
+        assert_eq!(response["error"]["code"], INVALID_PARAMS);
+    }
+}
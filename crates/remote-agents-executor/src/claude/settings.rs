@@ -0,0 +1,229 @@
+//! Per-session Claude settings files.
+
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+};
+
+use serde::{Deserialize, Serialize};
+
+use super::hooks::HookEvent;
+use super::types::PermissionMode;
+
+/// Name of the settings file within its temporary directory.
+const SETTINGS_FILE_NAME: &str = "settings.json";
+
+/// Claude settings for one session, passed with `--settings`.
+///
+/// These are layered over the user's and project's settings without
+/// changing them, so per-session hooks, permissions, and environment never
+/// touch `~/.claude`.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ClaudeSettings {
+    #[serde(default, skip_serializing_if = "SettingsPermissions::is_empty")]
+    pub permissions: SettingsPermissions,
+    /// Environment variables for the session and the tools it runs.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub env: BTreeMap<String, String>,
+    /// Shell command hooks, by hook event name.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub hooks: BTreeMap<String, Vec<CommandHookMatcher>>,
+}
+
+/// Permission rules in a settings file, e.g. `Bash(npm test:*)`.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SettingsPermissions {
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub allow: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub ask: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub deny: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub default_mode: Option<PermissionMode>,
+}
+
+impl SettingsPermissions {
+    /// Whether no rule or mode is set.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.allow.is_empty()
+            && self.ask.is_empty()
+            && self.deny.is_empty()
+            && self.default_mode.is_none()
+    }
+}
+
+/// Shell commands run for the tools a matcher selects.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CommandHookMatcher {
+    /// Pattern of tool names, e.g. `Edit|Write`; `None` matches every tool.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub matcher: Option<String>,
+    pub hooks: Vec<CommandHook>,
+}
+
+/// A hook running a shell command.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum CommandHook {
+    Command {
+        command: String,
+        /// Timeout in seconds.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        timeout: Option<u32>,
+    },
+}
+
+impl ClaudeSettings {
+    /// Create empty settings.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Allow a permission rule without asking.
+    #[must_use]
+    pub fn with_allow(mut self, rule: impl Into<String>) -> Self {
+        self.permissions.allow.push(rule.into());
+        self
+    }
+
+    /// Ask before a permission rule.
+    #[must_use]
+    pub fn with_ask(mut self, rule: impl Into<String>) -> Self {
+        self.permissions.ask.push(rule.into());
+        self
+    }
+
+    /// Deny a permission rule.
+    #[must_use]
+    pub fn with_deny(mut self, rule: impl Into<String>) -> Self {
+        self.permissions.deny.push(rule.into());
+        self
+    }
+
+    /// Set the default permission mode.
+    #[must_use]
+    pub const fn with_default_mode(mut self, mode: PermissionMode) -> Self {
+        self.permissions.default_mode = Some(mode);
+        self
+    }
+
+    /// Set an environment variable.
+    #[must_use]
+    pub fn with_env(mut self, key: impl Into<String>, value: impl Into<String>) -> Self {
+        self.env.insert(key.into(), value.into());
+        self
+    }
+
+    /// Run `command` for `event`, for tools matching `matcher`.
+    #[must_use]
+    pub fn with_command_hook(
+        mut self,
+        event: HookEvent,
+        matcher: Option<String>,
+        command: impl Into<String>,
+    ) -> Self {
+        self.hooks
+            .entry(event.as_str().to_string())
+            .or_default()
+            .push(CommandHookMatcher {
+                matcher,
+                hooks: vec![CommandHook::Command {
+                    command: command.into(),
+                    timeout: None,
+                }],
+            });
+        self
+    }
+
+    /// Whether nothing is set.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.permissions.is_empty() && self.env.is_empty() && self.hooks.is_empty()
+    }
+
+    /// The settings as JSON, which `--settings` also accepts inline.
+    #[must_use]
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(self).unwrap_or_else(|_| "{}".to_string())
+    }
+
+    /// Write the settings to a new private temporary directory.
+    ///
+    /// # Errors
+    /// Returns error if the directory or file cannot be created.
+    pub async fn write_temp(&self) -> Result<SettingsFile, std::io::Error> {
+        let dir =
+            std::env::temp_dir().join(format!("remote-agents-claude-{}", uuid::Uuid::new_v4()));
+        let mut builder = tokio::fs::DirBuilder::new();
+        #[cfg(unix)]
+        builder.mode(0o700);
+        builder.create(&dir).await?;
+        let file = SettingsFile { dir };
+        tokio::fs::write(file.path(), self.to_json()).await?;
+        Ok(file)
+    }
+}
+
+/// A settings file in a temporary directory, removed on drop.
+#[derive(Debug)]
+pub struct SettingsFile {
+    dir: PathBuf,
+}
+
+impl SettingsFile {
+    /// Path of the settings file, for `--settings`.
+    #[must_use]
+    pub fn path(&self) -> PathBuf {
+        self.dir.join(SETTINGS_FILE_NAME)
+    }
+
+    /// The temporary directory holding the file.
+    #[must_use]
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+}
+
+impl Drop for SettingsFile {
+    fn drop(&mut self) {
+        if let Err(e) = std::fs::remove_dir_all(&self.dir) {
+            tracing::warn!("Failed to remove settings {}: {e}", self.dir.display());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_write_temp_removed_on_drop() {
+        let settings = ClaudeSettings::new()
+            .with_deny("Bash(rm:*)")
+            .with_env("CI", "1")
+            .with_command_hook(HookEvent::PostToolUse, Some("Edit".into()), "cargo fmt");
+        let file = settings.write_temp().await.unwrap();
+
+        let written: serde_json::Value =
+            serde_json::from_str(&std::fs::read_to_string(file.path()).unwrap()).unwrap();
+        assert_eq!(
+            written,
+            serde_json::json!({
+                "permissions": { "deny": ["Bash(rm:*)"] },
+                "env": { "CI": "1" },
+                "hooks": { "PostToolUse": [{
+                    "matcher": "Edit",
+                    "hooks": [{ "type": "command", "command": "cargo fmt" }],
+                }] },
+            })
+        );
+
+        let dir = file.dir().to_path_buf();
+        drop(file);
+        assert!(!dir.exists());
+    }
+}
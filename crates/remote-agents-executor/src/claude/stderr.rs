@@ -0,0 +1,159 @@
+//! Classifying the Claude CLI's stderr into known failures.
+
+use std::collections::HashSet;
+
+use futures::StreamExt;
+use remote_agents_core::{
+    LogMsg,
+    traits::{EventStream, ExecutorError},
+};
+use tokio::io::{AsyncBufReadExt, AsyncRead, BufReader};
+
+/// A failure recognized in the CLI's stderr.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, thiserror::Error)]
+pub enum ClaudeFailure {
+    #[error("Claude is not authenticated; run `claude` and `/login`, or set ANTHROPIC_API_KEY")]
+    Authentication,
+    #[error("Claude hit a rate or usage limit; retry later")]
+    RateLimited,
+    #[error("The Claude API is overloaded; retry later")]
+    Overloaded,
+    #[error("Claude could not reach the API; check the network and proxy settings")]
+    Network,
+    #[error("The Claude CLI crashed")]
+    Crashed,
+}
+
+impl ClaudeFailure {
+    /// Patterns identifying each failure, matched case-insensitively.
+    const PATTERNS: &[(Self, &[&str])] = &[
+        (
+            Self::Authentication,
+            &[
+                "invalid api key",
+                "authentication_error",
+                "please run /login",
+                "oauth token has expired",
+                "not logged in",
+            ],
+        ),
+        (
+            Self::RateLimited,
+            &["rate_limit_error", "rate limit", "usage limit reached"],
+        ),
+        (Self::Overloaded, &["overloaded_error", "overloaded"]),
+        (
+            Self::Network,
+            &[
+                "econnrefused",
+                "econnreset",
+                "enotfound",
+                "etimedout",
+                "fetch failed",
+            ],
+        ),
+        (
+            Self::Crashed,
+            &[
+                "uncaught exception",
+                "unhandled promise rejection",
+                "fatal error",
+                "segmentation fault",
+            ],
+        ),
+    ];
+
+    /// The failure a stderr line reports, if it matches a known pattern.
+    #[must_use]
+    pub fn classify(line: &str) -> Option<Self> {
+        let line = line.to_ascii_lowercase();
+        Self::PATTERNS
+            .iter()
+            .find(|(_, patterns)| patterns.iter().any(|pattern| line.contains(pattern)))
+            .map(|(failure, _)| *failure)
+    }
+}
+
+/// Stream the CLI's stderr lines as `Stderr` events.
+///
+/// The first line reporting each `ClaudeFailure` is followed by a `Stderr`
+/// event describing the failure, so it is diagnosable from the session log.
+pub fn stderr_events<R>(stderr: R) -> EventStream
+where
+    R: AsyncRead + Send + Unpin + 'static,
+{
+    let lines = BufReader::new(stderr).lines();
+    futures::stream::unfold(
+        (lines, HashSet::new()),
+        |(mut lines, mut reported)| async move {
+            let events = match lines.next_line().await {
+                Ok(Some(line)) => {
+                    let failure = ClaudeFailure::classify(&line).filter(|f| reported.insert(*f));
+                    if let Some(failure) = failure {
+                        tracing::warn!("{failure}: {line}");
+                    }
+                    std::iter::once(line + "\n")
+                        .chain(failure.map(|failure| format!("{failure}\n")))
+                        .map(|line| Ok(LogMsg::Stderr(line)))
+                        .collect::<Vec<_>>()
+                }
+                Ok(None) => return None,
+                Err(e) => vec![Err(ExecutorError::Io(e))],
+            };
+            Some((futures::stream::iter(events), (lines, reported)))
+        },
+    )
+    .flatten()
+    .boxed()
+}
+
+#[cfg(test)]
+mod tests {
+    use tokio::io::AsyncWriteExt;
+
+    use super::*;
+
+    #[test]
+    fn test_classify() {
+        assert_eq!(
+            ClaudeFailure::classify("Invalid API key · Please run /login"),
+            Some(ClaudeFailure::Authentication)
+        );
+        assert_eq!(
+            ClaudeFailure::classify(r#"API Error: 529 {"type":"overloaded_error"}"#),
+            Some(ClaudeFailure::Overloaded)
+        );
+        assert_eq!(
+            ClaudeFailure::classify("Error: connect ECONNREFUSED 127.0.0.1:443"),
+            Some(ClaudeFailure::Network)
+        );
+        assert_eq!(ClaudeFailure::classify("Compacting conversation"), None);
+    }
+
+    #[tokio::test]
+    async fn test_failures_reported_once() {
+        let (reader, mut writer) = tokio::io::duplex(1024);
+        writer
+            .write_all(b"rate_limit_error\nrate_limit_error\n")
+            .await
+            .unwrap();
+        drop(writer);
+
+        let events: Vec<_> = stderr_events(reader).map(Result::unwrap).collect().await;
+        let lines: Vec<_> = events
+            .iter()
+            .map(|msg| match msg {
+                LogMsg::Stderr(line) => line.as_str(),
+                other => panic!("unexpected event {other:?}"),
+            })
+            .collect();
+        assert_eq!(
+            lines,
+            [
+                "rate_limit_error\n",
+                "Claude hit a rate or usage limit; retry later\n",
+                "rate_limit_error\n",
+            ]
+        );
+    }
+}
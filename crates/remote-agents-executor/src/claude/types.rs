@@ -1,8 +1,14 @@
 //! Type definitions for Claude Code control protocol.
 
+use std::time::Duration;
+
+use remote_agents_core::traits::{AgentOutcome, TokenUsage};
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
 
+use super::mcp::{McpServerStatus, McpToolName};
+use super::plan::{EXIT_PLAN_MODE_TOOL, PlanProposal};
+
 /// Top-level message types from CLI stdout.
 #[derive(Debug, Deserialize)]
 #[serde(tag = "type", rename_all = "snake_case")]
@@ -22,6 +28,347 @@ pub enum CLIMessage {
     Other(Value),
 }
 
+/// Typed stream-json message from CLI stdout (other than control traffic).
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ClaudeMessage {
+    /// Session metadata, sent once at startup (`subtype` `init`).
+    System {
+        subtype: String,
+        #[serde(default)]
+        session_id: Option<String>,
+        #[serde(default)]
+        model: Option<String>,
+        #[serde(default)]
+        cwd: Option<String>,
+        #[serde(default)]
+        tools: Vec<String>,
+        #[serde(default)]
+        mcp_servers: Vec<McpServerStatus>,
+        /// Names of the slash commands available, without the leading `/`.
+        #[serde(default)]
+        slash_commands: Vec<String>,
+        /// What a `compact_boundary` message compacted.
+        #[serde(default)]
+        compact_metadata: Option<Compaction>,
+    },
+    /// A turn from the model: text, thinking, and tool calls.
+    Assistant {
+        message: AssistantMessage,
+        #[serde(default)]
+        session_id: Option<String>,
+        /// Tool call this message belongs to, for subagent messages.
+        #[serde(default)]
+        parent_tool_use_id: Option<String>,
+    },
+    /// A user turn, including tool results sent back to the model.
+    User {
+        message: UserMessage,
+        #[serde(default)]
+        session_id: Option<String>,
+        #[serde(default)]
+        parent_tool_use_id: Option<String>,
+        /// Details of a tool result, e.g. the patch an `Edit` applied.
+        #[serde(default, alias = "toolUseResult")]
+        tool_use_result: Option<Value>,
+    },
+    /// Final message of a run.
+    Result(ResultMessage),
+    /// A raw model streaming event, sent with `--include-partial-messages`
+    /// ahead of the complete `assistant` message.
+    StreamEvent {
+        event: StreamEvent,
+        #[serde(default)]
+        session_id: Option<String>,
+        #[serde(default)]
+        parent_tool_use_id: Option<String>,
+    },
+}
+
+impl ClaudeMessage {
+    /// Parse a stdout line, returning `None` for control traffic and
+    /// message types this crate does not model.
+    #[must_use]
+    pub fn parse(line: &str) -> Option<Self> {
+        serde_json::from_str(line).ok()
+    }
+
+    /// Content blocks carried by an assistant or user message.
+    #[must_use]
+    pub fn content(&self) -> &[ContentBlock] {
+        match self {
+            Self::Assistant { message, .. } => &message.content,
+            Self::User { message, .. } => match &message.content {
+                MessageContent::Blocks(blocks) => blocks,
+                MessageContent::Text(_) => &[],
+            },
+            Self::System { .. } | Self::Result(_) | Self::StreamEvent { .. } => &[],
+        }
+    }
+
+    /// The compaction a `compact_boundary` message reports.
+    #[must_use]
+    pub fn compaction(&self) -> Option<&Compaction> {
+        match self {
+            Self::System {
+                subtype,
+                compact_metadata,
+                ..
+            } if subtype == "compact_boundary" => compact_metadata.as_ref(),
+            _ => None,
+        }
+    }
+
+    /// Assistant text streamed by a partial message, excluding subagents.
+    #[must_use]
+    pub fn text_delta(&self) -> Option<&str> {
+        match self {
+            Self::StreamEvent {
+                event:
+                    StreamEvent::ContentBlockDelta {
+                        delta: ContentDelta::TextDelta { text },
+                        ..
+                    },
+                parent_tool_use_id: None,
+                ..
+            } => Some(text),
+            _ => None,
+        }
+    }
+}
+
+/// What started a compaction of the conversation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CompactTrigger {
+    /// The CLI compacted as the context window filled up.
+    Auto,
+    /// The `/compact` command, e.g. from `ProtocolPeer::compact`.
+    Manual,
+}
+
+/// The CLI summarized the conversation so far to free up context.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Compaction {
+    pub trigger: CompactTrigger,
+    /// Tokens in the context before compacting.
+    #[serde(default)]
+    pub pre_tokens: Option<u64>,
+}
+
+/// Model streaming event within a `stream_event` message.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum StreamEvent {
+    /// More content for the block at `index`.
+    ContentBlockDelta { index: u32, delta: ContentDelta },
+    /// Message and block start and stop events, which this crate does not
+    /// model.
+    #[serde(other)]
+    Other,
+}
+
+/// Incremental content of a streamed content block.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ContentDelta {
+    TextDelta {
+        text: String,
+    },
+    ThinkingDelta {
+        thinking: String,
+    },
+    /// Part of a tool call's input JSON.
+    InputJsonDelta {
+        partial_json: String,
+    },
+    #[serde(other)]
+    Other,
+}
+
+/// Model output within an `assistant` message.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AssistantMessage {
+    #[serde(default)]
+    pub id: Option<String>,
+    #[serde(default)]
+    pub model: Option<String>,
+    pub content: Vec<ContentBlock>,
+    #[serde(default)]
+    pub stop_reason: Option<String>,
+    /// Tokens used by this turn, repeated on each message of the turn.
+    #[serde(default)]
+    pub usage: Option<TokenUsage>,
+}
+
+/// Input within a `user` message.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct UserMessage {
+    pub content: MessageContent,
+}
+
+/// Message content: plain text or content blocks.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum MessageContent {
+    Text(String),
+    Blocks(Vec<ContentBlock>),
+}
+
+/// A block of message content.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ContentBlock {
+    Text {
+        text: String,
+    },
+    Thinking {
+        thinking: String,
+    },
+    /// A tool call made by the model.
+    ToolUse {
+        id: String,
+        name: String,
+        input: Value,
+    },
+    /// The outcome of a tool call, sent back to the model.
+    ToolResult {
+        tool_use_id: String,
+        /// A string, or an array of content blocks.
+        #[serde(default)]
+        content: Value,
+        #[serde(default)]
+        is_error: bool,
+    },
+    /// A block type this crate does not model.
+    #[serde(other)]
+    Unknown,
+}
+
+impl ContentBlock {
+    /// Server and tool of a call to an MCP tool.
+    #[must_use]
+    pub fn mcp_tool(&self) -> Option<McpToolName<'_>> {
+        match self {
+            Self::ToolUse { name, .. } => McpToolName::parse(name),
+            _ => None,
+        }
+    }
+
+    /// The plan proposed by an `ExitPlanMode` tool call.
+    #[must_use]
+    pub fn plan(&self) -> Option<&str> {
+        match self {
+            Self::ToolUse { name, input, .. } if name == EXIT_PLAN_MODE_TOOL => {
+                PlanProposal::plan_from_input(input)
+            }
+            _ => None,
+        }
+    }
+
+    /// Text of a tool result, joining the text blocks of array content.
+    #[must_use]
+    pub fn tool_result_text(&self) -> Option<String> {
+        let Self::ToolResult { content, .. } = self else {
+            return None;
+        };
+        Some(match content {
+            Value::String(text) => text.clone(),
+            Value::Array(blocks) => blocks
+                .iter()
+                .filter_map(|block| block.get("text").and_then(Value::as_str))
+                .collect::<Vec<_>>()
+                .join("\n"),
+            _ => String::new(),
+        })
+    }
+}
+
+/// Summary of a finished run.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ResultMessage {
+    /// `success`, or the kind of error (e.g. `error_max_turns`).
+    pub subtype: String,
+    #[serde(default)]
+    pub is_error: bool,
+    #[serde(default)]
+    pub duration_ms: Option<u64>,
+    #[serde(default)]
+    pub duration_api_ms: Option<u64>,
+    #[serde(default)]
+    pub num_turns: Option<u32>,
+    /// Final response text, on success.
+    #[serde(default)]
+    pub result: Option<String>,
+    #[serde(default)]
+    pub session_id: Option<String>,
+    /// Tokens used by the whole run.
+    #[serde(default)]
+    pub usage: Option<TokenUsage>,
+    /// Estimated cost of the session so far, in US dollars.
+    #[serde(default)]
+    pub total_cost_usd: Option<f64>,
+}
+
+/// How a run ended, from its final `result` message.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SessionResult {
+    /// `success`, or the kind of error (e.g. `error_max_turns`).
+    pub subtype: String,
+    pub is_error: bool,
+    pub num_turns: Option<u32>,
+    /// Wall time of the run.
+    pub duration: Option<Duration>,
+    /// Time spent waiting on the API.
+    pub api_duration: Option<Duration>,
+    /// Estimated cost of the session so far, in US dollars.
+    pub cost_usd: Option<f64>,
+    /// The error message, for a failed run that reports one.
+    pub error: Option<String>,
+}
+
+impl SessionResult {
+    /// Whether the run finished its task rather than stopping on an error.
+    #[must_use]
+    pub fn is_success(&self) -> bool {
+        !self.is_error && self.subtype == "success"
+    }
+
+    /// The outcome reported to the session manager.
+    #[must_use]
+    pub fn outcome(&self) -> AgentOutcome {
+        if self.is_success() {
+            return AgentOutcome {
+                success: true,
+                reason: None,
+            };
+        }
+        let reason = match (&self.error, self.subtype.as_str()) {
+            (Some(error), _) => format!("Claude run failed: {error}"),
+            (None, "success") => "Claude run failed".to_string(),
+            (None, subtype) => format!("Claude run ended with {subtype}"),
+        };
+        AgentOutcome {
+            success: false,
+            reason: Some(reason),
+        }
+    }
+}
+
+impl From<&ResultMessage> for SessionResult {
+    fn from(result: &ResultMessage) -> Self {
+        Self {
+            subtype: result.subtype.clone(),
+            is_error: result.is_error,
+            num_turns: result.num_turns,
+            duration: result.duration_ms.map(Duration::from_millis),
+            api_duration: result.duration_api_ms.map(Duration::from_millis),
+            cost_usd: result.total_cost_usd,
+            error: result.result.clone().filter(|_| result.is_error),
+        }
+    }
+}
+
 /// Control request from SDK to CLI (outgoing).
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SDKControlRequest {
@@ -83,6 +430,8 @@ pub enum ControlRequestType {
         #[serde(skip_serializing_if = "Option::is_none")]
         tool_use_id: Option<String>,
     },
+    /// A JSON-RPC message for an in-process MCP server.
+    McpMessage { server_name: String, message: Value },
 }
 
 /// Permission update operation.
@@ -179,6 +528,11 @@ pub enum SDKControlRequestType {
         hooks: Option<Value>,
     },
     Interrupt {},
+    SetModel {
+        #[serde(skip_serializing_if = "Option::is_none")]
+        model: Option<String>,
+    },
+    SetMaxThinkingTokens { max_thinking_tokens: Option<u32> },
 }
 
 /// Permission mode.
@@ -210,6 +564,25 @@ impl std::fmt::Display for PermissionMode {
     }
 }
 
+impl std::str::FromStr for PermissionMode {
+    type Err = UnknownPermissionMode;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "default" => Ok(Self::Default),
+            "acceptEdits" => Ok(Self::AcceptEdits),
+            "plan" => Ok(Self::Plan),
+            "bypassPermissions" => Ok(Self::BypassPermissions),
+            _ => Err(UnknownPermissionMode(s.to_string())),
+        }
+    }
+}
+
+/// Error parsing a `PermissionMode`.
+#[derive(Debug, thiserror::Error)]
+#[error("Unknown permission mode: {0}")]
+pub struct UnknownPermissionMode(pub String);
+
 /// Result of permission check.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(tag = "behavior", rename_all = "camelCase")]
@@ -226,3 +599,76 @@ pub enum PermissionResult {
         interrupt: Option<bool>,
     },
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_stream_json() {
+        let line = r#"{"type":"assistant","session_id":"s1","message":{"id":"m1","content":[
+            {"type":"text","text":"Listing files"},
+            {"type":"tool_use","id":"t1","name":"Bash","input":{"command":"ls"}},
+            {"type":"server_tool_use","id":"t2"}]}}"#;
+        let message = ClaudeMessage::parse(line).unwrap();
+        assert_eq!(
+            message.content()[1],
+            ContentBlock::ToolUse {
+                id: "t1".into(),
+                name: "Bash".into(),
+                input: serde_json::json!({ "command": "ls" }),
+            }
+        );
+        assert_eq!(message.content()[2], ContentBlock::Unknown);
+
+        let line = r#"{"type":"user","message":{"role":"user","content":[{"type":"tool_result",
+            "tool_use_id":"t1","content":[{"type":"text","text":"Cargo.toml"}]}]}}"#;
+        let message = ClaudeMessage::parse(line).unwrap();
+        assert_eq!(
+            message.content()[0].tool_result_text().as_deref(),
+            Some("Cargo.toml")
+        );
+
+        let line = r#"{"type":"result","subtype":"success","is_error":false,"num_turns":2,
+            "result":"Done","session_id":"s1","total_cost_usd":0.25,
+            "usage":{"input_tokens":10,"output_tokens":5,"cache_read_input_tokens":100}}"#;
+        let Some(ClaudeMessage::Result(result)) = ClaudeMessage::parse(line) else {
+            panic!("expected a result message");
+        };
+        assert_eq!(result.result.as_deref(), Some("Done"));
+        assert_eq!(result.total_cost_usd, Some(0.25));
+        assert_eq!(
+            result.usage,
+            Some(TokenUsage {
+                input_tokens: 10,
+                output_tokens: 5,
+                cache_creation_input_tokens: 0,
+                cache_read_input_tokens: 100,
+            })
+        );
+
+        let line = r#"{"type":"stream_event","session_id":"s1","event":{"type":"content_block_delta",
+            "index":0,"delta":{"type":"text_delta","text":"Hel"}}}"#;
+        assert_eq!(
+            ClaudeMessage::parse(line).unwrap().text_delta(),
+            Some("Hel")
+        );
+
+        assert!(ClaudeMessage::parse(r#"{"type":"control_response","response":{}}"#).is_none());
+    }
+
+    #[test]
+    fn test_parse_compact_boundary() {
+        let line = r#"{"type":"system","subtype":"compact_boundary","session_id":"s1",
+            "compact_metadata":{"trigger":"auto","pre_tokens":150000}}"#;
+        assert_eq!(
+            ClaudeMessage::parse(line).unwrap().compaction(),
+            Some(&Compaction {
+                trigger: CompactTrigger::Auto,
+                pre_tokens: Some(150_000),
+            })
+        );
+        let line = r#"{"type":"system","subtype":"init","session_id":"s1"}"#;
+        assert_eq!(ClaudeMessage::parse(line).unwrap().compaction(), None);
+    }
+}
@@ -2,9 +2,15 @@
 
 use std::path::PathBuf;
 
-use remote_agents_pty::resolve_executable_path;
+use remote_agents_core::{
+    ExecutionContext,
+    traits::{CommandPreview, ExecutorError},
+};
+use remote_agents_pty::{resolve_executable_path, shell::UnixShell};
 use thiserror::Error;
 
+use crate::claude::ClaudeConfig;
+
 /// Command build error.
 #[derive(Debug, Error)]
 pub enum CommandBuildError {
@@ -18,6 +24,12 @@ pub enum CommandBuildError {
     InvalidShellParams(String),
 }
 
+impl From<CommandBuildError> for ExecutorError {
+    fn from(e: CommandBuildError) -> Self {
+        Self::CommandBuild(e.to_string())
+    }
+}
+
 /// Parsed command parts (program + args).
 #[derive(Debug, Clone)]
 pub struct CommandParts {
@@ -43,6 +55,27 @@ impl CommandParts {
             .ok_or_else(|| CommandBuildError::InvalidBase(format!("Executable not found: {program}")))?;
         Ok((executable, args))
     }
+
+    /// Resolve the program and describe the command as it would run in `ctx`.
+    ///
+    /// # Errors
+    /// Returns error if executable not found or the command cannot be quoted.
+    pub async fn into_preview(
+        self,
+        ctx: &ExecutionContext,
+    ) -> Result<CommandPreview, CommandBuildError> {
+        let (program, args) = self.into_resolved().await?;
+        let program_str = program.to_string_lossy();
+        let words = std::iter::once(program_str.as_ref()).chain(args.iter().map(String::as_str));
+        let command_line = shlex::try_join(words)?;
+        Ok(CommandPreview {
+            program,
+            args,
+            command_line,
+            working_dir: ctx.working_dir.clone(),
+            env: ctx.secret_env().map(|(key, _)| key.to_string()).collect(),
+        })
+    }
 }
 
 /// Builder for constructing commands.
@@ -52,6 +85,8 @@ pub struct CommandBuilder {
     pub base: String,
     /// Optional parameters to append.
     pub params: Option<Vec<String>>,
+    /// Run the command through the user's login shell.
+    pub login_shell: bool,
 }
 
 impl CommandBuilder {
@@ -61,6 +96,7 @@ impl CommandBuilder {
         Self {
             base: base.into(),
             params: None,
+            login_shell: false,
         }
     }
 
@@ -97,6 +133,26 @@ impl CommandBuilder {
         self
     }
 
+    /// Run the built command through the user's shell from `$SHELL` as a
+    /// login shell, e.g. `zsh -lc '...'`, so its profile sets up PATH.
+    ///
+    /// Use for CLIs installed with node, bun, or nvm on servers where the
+    /// daemon's PATH is minimal. Has no effect on Windows.
+    #[must_use]
+    pub const fn via_login_shell(mut self) -> Self {
+        self.login_shell = true;
+        self
+    }
+
+    /// Add the CLI flags for a Claude session's configuration.
+    ///
+    /// The model and thinking budget are not flags; they are sent by
+    /// `ProtocolPeer::initialize`.
+    #[must_use]
+    pub fn claude_config(self, config: &ClaudeConfig) -> Self {
+        self.extend_params(config.cli_args())
+    }
+
     /// Build command for initial invocation.
     ///
     /// # Errors
@@ -126,6 +182,14 @@ impl CommandBuilder {
             return Err(CommandBuildError::EmptyCommand);
         }
 
+        if self.login_shell && !cfg!(windows) {
+            let command_line = shlex::try_join(parts.iter().map(String::as_str))?;
+            let shell = UnixShell::current_shell();
+            let flag = if shell.login() { "-lc" } else { "-c" };
+            let program = shell.path().to_string_lossy().into_owned();
+            return Ok(CommandParts::new(program, vec![flag.to_string(), command_line]));
+        }
+
         let program = parts.remove(0);
         Ok(CommandParts::new(program, parts))
     }
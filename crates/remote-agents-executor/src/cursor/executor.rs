@@ -0,0 +1,346 @@
+//! `Executor` implementation running cursor-agent headless.
+
+use std::{process::Stdio, sync::Arc};
+
+use async_trait::async_trait;
+use command_group::AsyncCommandGroup;
+use futures::StreamExt;
+use remote_agents_core::{
+    ExecutionContext, LogMsg,
+    traits::{
+        CommandPreview, EventStream, Executor, ExecutorError, SpawnedProcess, UsageEvent,
+        UsageStream, raw_output_events,
+    },
+};
+use remote_agents_pty::resolve_executable_path;
+use tokio::{
+    io::{AsyncBufReadExt, AsyncRead, BufReader},
+    sync::{mpsc, oneshot},
+};
+
+use super::types::CursorEvent;
+use crate::approvals::{ApprovalHandler, ApprovalMemory, ApprovalResult};
+use crate::command::{CommandBuilder, CommandParts};
+
+/// Flags for a headless run printing stream-json events.
+const PRINT_PARAMS: [&str; 3] = ["-p", "--output-format", "stream-json"];
+
+/// Runs sessions with cursor-agent's headless print mode.
+///
+/// Each prompt is one process; follow-ups continue the chat with
+/// `--resume`. Output is cursor-agent's stream-json, one event per
+/// `Stdout` line, like `ClaudeExecutor`'s.
+///
+/// Without an approval handler, every tool call is allowed (`--force`).
+/// With one, cursor-agent refuses commands its own permissions do not
+/// allow, and the handler reviews each tool call as it starts. Print mode
+/// cannot wait for a decision, so a denial stops the session.
+pub struct CursorAgentExecutor {
+    command: CommandBuilder,
+    model: Option<String>,
+    approval_handler: Option<Arc<dyn ApprovalHandler>>,
+}
+
+impl Default for CursorAgentExecutor {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl CursorAgentExecutor {
+    /// Create an executor running `cursor-agent` from PATH, allowing every
+    /// tool call.
+    #[must_use]
+    pub fn new() -> Self {
+        Self {
+            command: CommandBuilder::new("cursor-agent").params(PRINT_PARAMS),
+            model: None,
+            approval_handler: None,
+        }
+    }
+
+    /// Run a different executable, e.g. an absolute path to `cursor-agent`.
+    #[must_use]
+    pub fn with_executable(mut self, executable: impl Into<String>) -> Self {
+        self.command = self.command.override_base(executable);
+        self
+    }
+
+    /// Use `model`, e.g. `gpt-5` or `sonnet-4`.
+    #[must_use]
+    pub fn with_model(mut self, model: impl Into<String>) -> Self {
+        self.model = Some(model.into());
+        self
+    }
+
+    /// Review tool calls with `handler` instead of allowing them all.
+    #[must_use]
+    pub fn with_approval_handler(mut self, handler: Arc<dyn ApprovalHandler>) -> Self {
+        self.approval_handler = Some(handler);
+        self
+    }
+
+    /// Build the command, resuming chat `session_id` if given.
+    fn command_parts(
+        &self,
+        prompt: &str,
+        session_id: Option<&str>,
+    ) -> Result<CommandParts, ExecutorError> {
+        let mut args = Vec::new();
+        if self.approval_handler.is_none() {
+            args.push("--force".to_string());
+        }
+        if let Some(model) = &self.model {
+            args.extend(["--model".to_string(), model.clone()]);
+        }
+        if let Some(id) = session_id {
+            args.extend(["--resume".to_string(), id.to_string()]);
+        }
+        args.extend(["--".to_string(), prompt.to_string()]);
+        Ok(self.command.build_follow_up(&args)?)
+    }
+
+    async fn start(
+        &self,
+        ctx: &ExecutionContext,
+        prompt: &str,
+        session_id: Option<&str>,
+    ) -> Result<SpawnedProcess, ExecutorError> {
+        let parts = self.command_parts(prompt, session_id)?;
+        let program = resolve_executable_path(&parts.program)
+            .await
+            .ok_or_else(|| ExecutorError::ExecutableNotFound(parts.program.clone()))?;
+
+        let mut command = tokio::process::Command::new(program);
+        command
+            .args(&parts.args)
+            .current_dir(&ctx.working_dir)
+            .envs(ctx.secret_env())
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .kill_on_drop(true);
+        let mut child = command.group_spawn()?;
+        let Some(stdout) = child.inner().stdout.take() else {
+            return Err(ExecutorError::SpawnFailed(
+                "cursor-agent stdout not piped".to_string(),
+            ));
+        };
+
+        let (usage_tx, usage_rx) = mpsc::unbounded_channel();
+        let (abort_tx, abort_rx) = oneshot::channel();
+        let output = OutputState {
+            lines: BufReader::new(stdout).lines(),
+            usage_tx,
+            session_reported: false,
+            approval_handler: self
+                .approval_handler
+                .clone()
+                .map(|handler| Arc::new(ApprovalMemory::new(handler)) as Arc<dyn ApprovalHandler>),
+            abort_tx: Some(abort_tx),
+        };
+        let stderr = raw_output_events(&mut child);
+        let events = futures::stream::select(output_events(output), stderr).boxed();
+        Ok(SpawnedProcess::from_child(child)
+            .with_events(events)
+            .with_usage(usage_stream(usage_rx))
+            .with_abort(abort_rx))
+    }
+}
+
+#[async_trait]
+impl Executor for CursorAgentExecutor {
+    async fn spawn(
+        &self,
+        ctx: &ExecutionContext,
+        prompt: &str,
+    ) -> Result<SpawnedProcess, ExecutorError> {
+        self.start(ctx, prompt, None).await
+    }
+
+    async fn spawn_follow_up(
+        &self,
+        ctx: &ExecutionContext,
+        prompt: &str,
+        session_id: &str,
+    ) -> Result<SpawnedProcess, ExecutorError> {
+        self.start(ctx, prompt, Some(session_id)).await
+    }
+
+    async fn preview(
+        &self,
+        ctx: &ExecutionContext,
+        prompt: &str,
+    ) -> Result<Option<CommandPreview>, ExecutorError> {
+        let preview = self.command_parts(prompt, None)?.into_preview(ctx).await?;
+        Ok(Some(preview))
+    }
+}
+
+/// Parsing state of the output stream.
+struct OutputState<R> {
+    lines: tokio::io::Lines<BufReader<R>>,
+    usage_tx: mpsc::UnboundedSender<UsageEvent>,
+    session_reported: bool,
+    approval_handler: Option<Arc<dyn ApprovalHandler>>,
+    /// Taken when a denial stops the session.
+    abort_tx: Option<oneshot::Sender<String>>,
+}
+
+impl<R> OutputState<R> {
+    /// Events for one line: the session ID when first seen, then the line.
+    async fn on_line(&mut self, line: String) -> Vec<LogMsg> {
+        let Some(event) = CursorEvent::parse(&line) else {
+            return vec![LogMsg::Stdout(line + "\n")];
+        };
+        let mut msgs = Vec::new();
+        if let Some(id) = event.session_id().filter(|_| !self.session_reported) {
+            self.session_reported = true;
+            msgs.push(LogMsg::SessionId(id.to_string()));
+        }
+        // The session manager may not read usage; that is not an error.
+        for usage in event.usage_events() {
+            let _ = self.usage_tx.send(usage);
+        }
+        msgs.push(LogMsg::Stdout(line + "\n"));
+        let handler = self.approval_handler.clone();
+        if let Some(reason) = review(handler.as_deref(), &event).await {
+            if let Some(abort_tx) = self.abort_tx.take() {
+                let _ = abort_tx.send(reason);
+            }
+        }
+        msgs
+    }
+}
+
+/// Ask the approval handler about a starting tool call, returning why the
+/// session must stop if it is not approved.
+async fn review(handler: Option<&dyn ApprovalHandler>, event: &CursorEvent) -> Option<String> {
+    let handler = handler?;
+    let (tool_name, args) = event.started_tool()?;
+    let CursorEvent::ToolCall { call_id, .. } = event else {
+        return None;
+    };
+    match handler.request_approval(&tool_name, args, call_id).await {
+        Ok(ApprovalResult::Allow { .. }) => None,
+        Ok(ApprovalResult::Deny { message, .. }) => {
+            Some(format!("Tool call {tool_name} denied: {message}"))
+        }
+        Err(e) => Some(format!("Approval of tool call {tool_name} failed: {e}")),
+    }
+}
+
+/// Stream cursor-agent's stream-json lines as `Stdout` events, reporting
+/// the session ID and usage, and reviewing tool calls.
+fn output_events<R>(state: OutputState<R>) -> EventStream
+where
+    R: AsyncRead + Send + Unpin + 'static,
+{
+    futures::stream::unfold(state, |mut state| async move {
+        let events = match state.lines.next_line().await {
+            Ok(Some(line)) => state.on_line(line).await.into_iter().map(Ok).collect(),
+            Ok(None) => return None,
+            Err(e) => vec![Err(ExecutorError::Io(e))],
+        };
+        Some((futures::stream::iter(events), state))
+    })
+    .flatten()
+    .boxed()
+}
+
+/// Usage events from `rx`, ending when the output stream is done.
+fn usage_stream(rx: mpsc::UnboundedReceiver<UsageEvent>) -> UsageStream {
+    futures::stream::unfold(rx, |mut rx| async move {
+        rx.recv().await.map(|event| (event, rx))
+    })
+    .boxed()
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::Value;
+    use tokio::io::AsyncWriteExt;
+
+    use super::*;
+    use crate::approvals::ApprovalError;
+
+    struct DenyShell;
+
+    #[async_trait]
+    impl ApprovalHandler for DenyShell {
+        async fn request_approval(
+            &self,
+            tool_name: &str,
+            tool_input: Value,
+            _tool_call_id: &str,
+        ) -> Result<ApprovalResult, ApprovalError> {
+            Ok(if tool_name == "shell" {
+                ApprovalResult::Deny {
+                    message: "no shell".into(),
+                    interrupt: None,
+                    decided_by: None,
+                }
+            } else {
+                ApprovalResult::allow(tool_input)
+            })
+        }
+    }
+
+    #[tokio::test]
+    async fn test_denied_tool_call_aborts() {
+        let (reader, mut writer) = tokio::io::duplex(4096);
+        let started = |call_id: &str, tool: &str| {
+            serde_json::json!({
+                "type": "tool_call",
+                "subtype": "started",
+                "call_id": call_id,
+                "tool_call": { tool: { "args": {} } },
+            })
+            .to_string()
+        };
+        let lines = [
+            r#"{"type":"system","subtype":"init","session_id":"chat"}"#.to_string(),
+            started("c1", "readToolCall"),
+            started("c2", "shellToolCall"),
+        ];
+        writer
+            .write_all(format!("{}\n", lines.join("\n")).as_bytes())
+            .await
+            .unwrap();
+        drop(writer);
+
+        let (usage_tx, _usage_rx) = mpsc::unbounded_channel();
+        let (abort_tx, abort_rx) = oneshot::channel();
+        let state = OutputState {
+            lines: BufReader::new(reader).lines(),
+            usage_tx,
+            session_reported: false,
+            approval_handler: Some(Arc::new(DenyShell)),
+            abort_tx: Some(abort_tx),
+        };
+        let events: Vec<_> = output_events(state).map(Result::unwrap).collect().await;
+        assert!(matches!(&events[0], LogMsg::SessionId(id) if id == "chat"));
+        assert_eq!(events.len(), 4);
+        assert_eq!(abort_rx.await.unwrap(), "Tool call shell denied: no shell");
+    }
+
+    #[test]
+    fn test_command_parts() {
+        let parts = CursorAgentExecutor::new()
+            .command_parts("fix it", Some("chat"))
+            .unwrap();
+        assert_eq!(
+            parts.args,
+            [
+                "-p",
+                "--output-format",
+                "stream-json",
+                "--force",
+                "--resume",
+                "chat",
+                "--",
+                "fix it",
+            ]
+        );
+    }
+}
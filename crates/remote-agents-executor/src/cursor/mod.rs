@@ -0,0 +1,7 @@
+//! Cursor agent executor.
+
+pub mod executor;
+pub mod types;
+
+pub use executor::CursorAgentExecutor;
+pub use types::CursorEvent;
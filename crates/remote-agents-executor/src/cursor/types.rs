@@ -0,0 +1,169 @@
+//! Events printed by `cursor-agent -p --output-format stream-json`.
+
+use remote_agents_core::traits::UsageEvent;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+/// Suffix of the keys naming a tool within a `tool_call` event.
+const TOOL_CALL_SUFFIX: &str = "ToolCall";
+
+/// One line of cursor-agent stream-json output.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum CursorEvent {
+    /// Session metadata, sent once at startup (`subtype` `init`).
+    System {
+        #[serde(default)]
+        subtype: Option<String>,
+        #[serde(default)]
+        session_id: Option<String>,
+        #[serde(default)]
+        model: Option<String>,
+    },
+    /// The prompt.
+    User {
+        #[serde(default)]
+        message: Option<CursorMessage>,
+        #[serde(default)]
+        session_id: Option<String>,
+    },
+    /// Text from the model.
+    Assistant {
+        message: CursorMessage,
+        #[serde(default)]
+        session_id: Option<String>,
+    },
+    /// A tool call starting or completing.
+    ToolCall {
+        subtype: ToolCallStatus,
+        call_id: String,
+        /// The tool, keyed by e.g. `shellToolCall`, with its `args` and,
+        /// once completed, its `result`.
+        tool_call: Value,
+        #[serde(default)]
+        session_id: Option<String>,
+    },
+    /// Final message of a run.
+    Result {
+        #[serde(default)]
+        is_error: bool,
+        #[serde(default)]
+        result: Option<String>,
+        #[serde(default)]
+        session_id: Option<String>,
+    },
+}
+
+/// Progress of a tool call.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ToolCallStatus {
+    Started,
+    Completed,
+}
+
+/// A message from the model.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CursorMessage {
+    #[serde(default)]
+    pub content: Vec<Value>,
+}
+
+impl CursorMessage {
+    /// The text blocks of the message, joined.
+    #[must_use]
+    pub fn text(&self) -> String {
+        self.content
+            .iter()
+            .filter_map(|block| block.get("text").and_then(Value::as_str))
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+}
+
+impl CursorEvent {
+    /// Parse a line of output, or `None` if it is not a known event.
+    #[must_use]
+    pub fn parse(line: &str) -> Option<Self> {
+        serde_json::from_str(line).ok()
+    }
+
+    /// ID of the chat, for `--resume`.
+    #[must_use]
+    pub fn session_id(&self) -> Option<&str> {
+        match self {
+            Self::System { session_id, .. }
+            | Self::User { session_id, .. }
+            | Self::Assistant { session_id, .. }
+            | Self::ToolCall { session_id, .. }
+            | Self::Result { session_id, .. } => session_id.as_deref(),
+        }
+    }
+
+    /// The tool name and call of a tool call event, e.g. `shell` and
+    /// `{"args": {"command": "ls"}}`.
+    #[must_use]
+    pub fn tool(&self) -> Option<(&str, &Value)> {
+        let Self::ToolCall { tool_call, .. } = self else {
+            return None;
+        };
+        let (key, call) = tool_call.as_object()?.iter().next()?;
+        Some((key.strip_suffix(TOOL_CALL_SUFFIX).unwrap_or(key), call))
+    }
+
+    /// The tool name and arguments of a starting tool call, e.g. `shell`
+    /// and `{"command": "ls"}`.
+    #[must_use]
+    pub fn started_tool(&self) -> Option<(String, Value)> {
+        let Self::ToolCall {
+            subtype: ToolCallStatus::Started,
+            ..
+        } = self
+        else {
+            return None;
+        };
+        let (name, call) = self.tool()?;
+        let args = call.get("args").cloned().unwrap_or(Value::Null);
+        Some((name.to_string(), args))
+    }
+
+    /// Usage this event reports. cursor-agent reports no tokens or cost.
+    #[must_use]
+    pub fn usage_events(&self) -> Vec<UsageEvent> {
+        match self {
+            Self::Assistant { .. } => vec![UsageEvent::TurnCompleted],
+            Self::ToolCall { .. } => self
+                .started_tool()
+                .map(|(tool_name, _)| UsageEvent::ToolCall { tool_name })
+                .into_iter()
+                .collect(),
+            Self::System { .. } | Self::User { .. } | Self::Result { .. } => Vec::new(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_tool_call() {
+        let line = r#"{"type":"tool_call","subtype":"started","call_id":"c1","session_id":"chat",
+            "tool_call":{"shellToolCall":{"args":{"command":"ls"}}}}"#;
+        let event = CursorEvent::parse(line).unwrap();
+        assert_eq!(event.session_id(), Some("chat"));
+        assert_eq!(
+            event.started_tool(),
+            Some(("shell".to_string(), serde_json::json!({ "command": "ls" })))
+        );
+        assert_eq!(
+            event.usage_events(),
+            vec![UsageEvent::ToolCall {
+                tool_name: "shell".into()
+            }]
+        );
+
+        let completed = line.replace("started", "completed");
+        assert_eq!(CursorEvent::parse(&completed).unwrap().started_tool(), None);
+    }
+}
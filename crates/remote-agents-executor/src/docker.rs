@@ -0,0 +1,329 @@
+//! `Executor` implementation running a command inside a Docker container.
+
+use std::{collections::BTreeMap, path::PathBuf, process::Stdio};
+
+use async_trait::async_trait;
+use command_group::AsyncCommandGroup;
+use remote_agents_core::{
+    ExecutionContext,
+    traits::{CommandPreview, Executor, ExecutorError, SpawnedProcess},
+};
+use remote_agents_pty::resolve_executable_path;
+use tokio::sync::oneshot;
+
+use crate::command::{CommandBuilder, CommandParts};
+
+/// Directory the session's working directory is mounted at by default.
+const DEFAULT_CONTAINER_WORKDIR: &str = "/workspace";
+
+/// Runs sessions with an agent CLI inside a new container.
+///
+/// Each prompt runs `docker run --rm` with the session's working directory
+/// mounted read-write, so the agent can change nothing else on the host.
+/// The prompt is appended to the command as its last argument. Stdout and
+/// stderr lines are streamed as `Stdout` and `Stderr` events.
+///
+/// Environment variables, including the context's secrets, are passed by
+/// name with `-e`, so their values never appear on the command line.
+/// Follow-ups pass the agent session to continue with `--resume`.
+///
+/// Interrupting a session runs `docker rm -f` on its container, as does the
+/// session ending, so a container whose `docker run` was killed does not
+/// keep running.
+pub struct DockerExecutor {
+    docker: String,
+    image: String,
+    command: CommandBuilder,
+    resume_flag: String,
+    container_workdir: String,
+    mounts: Vec<Mount>,
+    env: BTreeMap<String, String>,
+    memory: Option<String>,
+    cpus: Option<String>,
+    network: Option<String>,
+}
+
+/// An extra bind mount.
+struct Mount {
+    host: PathBuf,
+    container: String,
+    read_only: bool,
+}
+
+impl DockerExecutor {
+    /// Create an executor running `command` in containers of `image`.
+    #[must_use]
+    pub fn new(image: impl Into<String>, command: CommandBuilder) -> Self {
+        Self {
+            docker: "docker".to_string(),
+            image: image.into(),
+            command,
+            resume_flag: "--resume".to_string(),
+            container_workdir: DEFAULT_CONTAINER_WORKDIR.to_string(),
+            mounts: Vec::new(),
+            env: BTreeMap::new(),
+            memory: None,
+            cpus: None,
+            network: None,
+        }
+    }
+
+    /// Run a different Docker CLI, e.g. `podman`.
+    #[must_use]
+    pub fn with_docker_executable(mut self, docker: impl Into<String>) -> Self {
+        self.docker = docker.into();
+        self
+    }
+
+    /// Pass the agent session a follow-up continues with `flag` instead of
+    /// `--resume`, e.g. `--session` for opencode.
+    #[must_use]
+    pub fn with_resume_flag(mut self, flag: impl Into<String>) -> Self {
+        self.resume_flag = flag.into();
+        self
+    }
+
+    /// Mount the working directory at `dir` instead of `/workspace`.
+    #[must_use]
+    pub fn with_container_workdir(mut self, dir: impl Into<String>) -> Self {
+        self.container_workdir = dir.into();
+        self
+    }
+
+    /// Bind mount `host` at `container`.
+    #[must_use]
+    pub fn with_mount(
+        mut self,
+        host: impl Into<PathBuf>,
+        container: impl Into<String>,
+        read_only: bool,
+    ) -> Self {
+        self.mounts.push(Mount {
+            host: host.into(),
+            container: container.into(),
+            read_only,
+        });
+        self
+    }
+
+    /// Set an environment variable in the container.
+    #[must_use]
+    pub fn with_env(mut self, key: impl Into<String>, value: impl Into<String>) -> Self {
+        self.env.insert(key.into(), value.into());
+        self
+    }
+
+    /// Limit the container's memory, e.g. `2g`.
+    #[must_use]
+    pub fn with_memory(mut self, memory: impl Into<String>) -> Self {
+        self.memory = Some(memory.into());
+        self
+    }
+
+    /// Limit the container's CPUs, e.g. `1.5`.
+    #[must_use]
+    pub fn with_cpus(mut self, cpus: impl Into<String>) -> Self {
+        self.cpus = Some(cpus.into());
+        self
+    }
+
+    /// Attach the container to `network`, e.g. `none` to cut it off.
+    #[must_use]
+    pub fn with_network(mut self, network: impl Into<String>) -> Self {
+        self.network = Some(network.into());
+        self
+    }
+
+    /// Build the `docker run` command for a container named `name`,
+    /// resuming agent session `session_id` if given.
+    fn command_parts(
+        &self,
+        ctx: &ExecutionContext,
+        prompt: &str,
+        session_id: Option<&str>,
+        name: &str,
+    ) -> Result<CommandParts, ExecutorError> {
+        let mut args: Vec<String> = ["run", "--rm", "-i", "--name", name]
+            .into_iter()
+            .map(str::to_string)
+            .collect();
+        let workdir = format!("{}:{}", ctx.working_dir.display(), self.container_workdir);
+        args.extend([
+            "-v".to_string(),
+            workdir,
+            "-w".to_string(),
+            self.container_workdir.clone(),
+        ]);
+        for mount in &self.mounts {
+            let mut volume = format!("{}:{}", mount.host.display(), mount.container);
+            if mount.read_only {
+                volume.push_str(":ro");
+            }
+            args.extend(["-v".to_string(), volume]);
+        }
+        let env_keys = self.env.keys().map(String::as_str);
+        for key in env_keys.chain(ctx.secret_env().map(|(key, _)| key)) {
+            args.extend(["-e".to_string(), key.to_string()]);
+        }
+        let limits = [
+            ("--memory", &self.memory),
+            ("--cpus", &self.cpus),
+            ("--network", &self.network),
+        ];
+        for (flag, value) in limits {
+            if let Some(value) = value {
+                args.extend([flag.to_string(), value.clone()]);
+            }
+        }
+        args.push(self.image.clone());
+
+        let mut agent_args = Vec::new();
+        if let Some(id) = session_id {
+            agent_args.extend([self.resume_flag.clone(), id.to_string()]);
+        }
+        if !prompt.is_empty() {
+            agent_args.push(prompt.to_string());
+        }
+        let agent = if agent_args.is_empty() {
+            self.command.build_initial()
+        } else {
+            self.command.build_follow_up(&agent_args)
+        }?;
+        args.push(agent.program);
+        args.extend(agent.args);
+        Ok(CommandParts::new(self.docker.clone(), args))
+    }
+
+    async fn start(
+        &self,
+        ctx: &ExecutionContext,
+        prompt: &str,
+        session_id: Option<&str>,
+    ) -> Result<SpawnedProcess, ExecutorError> {
+        let name = format!("remote-agents-{}", uuid::Uuid::new_v4());
+        let parts = self.command_parts(ctx, prompt, session_id, &name)?;
+        let program = resolve_executable_path(&parts.program)
+            .await
+            .ok_or_else(|| ExecutorError::ExecutableNotFound(parts.program.clone()))?;
+
+        let mut command = tokio::process::Command::new(&program);
+        command
+            .args(&parts.args)
+            .current_dir(&ctx.working_dir)
+            .envs(&self.env)
+            .envs(ctx.secret_env())
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .kill_on_drop(true);
+        let child = command.group_spawn()?;
+
+        // The sender is dropped when the session ends. Either way the
+        // container is removed, in case `docker run` was killed without it.
+        let (interrupt_tx, interrupt_rx) = oneshot::channel::<()>();
+        tokio::spawn(async move {
+            let _ = interrupt_rx.await;
+            remove_container(program, &name).await;
+        });
+        Ok(SpawnedProcess::from_child(child).with_interrupt(interrupt_tx))
+    }
+}
+
+#[async_trait]
+impl Executor for DockerExecutor {
+    async fn spawn(
+        &self,
+        ctx: &ExecutionContext,
+        prompt: &str,
+    ) -> Result<SpawnedProcess, ExecutorError> {
+        self.start(ctx, prompt, None).await
+    }
+
+    async fn spawn_follow_up(
+        &self,
+        ctx: &ExecutionContext,
+        prompt: &str,
+        session_id: &str,
+    ) -> Result<SpawnedProcess, ExecutorError> {
+        self.start(ctx, prompt, Some(session_id)).await
+    }
+
+    async fn preview(
+        &self,
+        ctx: &ExecutionContext,
+        prompt: &str,
+    ) -> Result<Option<CommandPreview>, ExecutorError> {
+        let preview = self
+            .command_parts(ctx, prompt, None, "remote-agents-preview")?
+            .into_preview(ctx)
+            .await?;
+        Ok(Some(preview))
+    }
+}
+
+/// Run `docker rm -f` on container `name`, stopping it if it still runs.
+async fn remove_container(docker: PathBuf, name: &str) {
+    let status = tokio::process::Command::new(docker)
+        .args(["rm", "-f", name])
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()
+        .await;
+    match status {
+        Ok(status) if status.success() => {}
+        Ok(status) => tracing::warn!("docker rm -f {name} exited with {status}"),
+        Err(e) => tracing::warn!("Failed to run docker rm -f {name}: {e}"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_command_parts() {
+        let executor = DockerExecutor::new("agents:latest", CommandBuilder::new("claude -p"))
+            .with_mount("/cache", "/root/.cache", true)
+            .with_env("CI", "1")
+            .with_memory("2g")
+            .with_network("none");
+        let ctx = ExecutionContext::new(PathBuf::from("/src/app"));
+        let parts = executor.command_parts(&ctx, "fix it", None, "c1").unwrap();
+        assert_eq!(parts.program, "docker");
+        assert_eq!(
+            parts.args,
+            [
+                "run",
+                "--rm",
+                "-i",
+                "--name",
+                "c1",
+                "-v",
+                "/src/app:/workspace",
+                "-w",
+                "/workspace",
+                "-v",
+                "/cache:/root/.cache:ro",
+                "-e",
+                "CI",
+                "--memory",
+                "2g",
+                "--network",
+                "none",
+                "agents:latest",
+                "claude",
+                "-p",
+                "fix it",
+            ]
+        );
+
+        let parts = executor
+            .command_parts(&ctx, "go on", Some("s1"), "c2")
+            .unwrap();
+        assert_eq!(
+            parts.args[parts.args.len() - 5..],
+            ["claude", "-p", "--resume", "s1", "go on"]
+        );
+    }
+}
@@ -0,0 +1,339 @@
+//! `Executor` implementation running each session as a Kubernetes pod.
+
+use std::{collections::BTreeMap, path::PathBuf, process::Stdio};
+
+use async_trait::async_trait;
+use command_group::AsyncCommandGroup;
+use remote_agents_core::{
+    ExecutionContext,
+    traits::{CommandPreview, Executor, ExecutorError, SpawnedProcess},
+};
+use remote_agents_pty::resolve_executable_path;
+use serde_json::{Value, json};
+use tokio::sync::oneshot;
+
+use crate::command::{CommandBuilder, CommandParts};
+
+/// Runs sessions with an agent CLI in a new pod of a Kubernetes cluster.
+///
+/// Each prompt creates a pod with `kubectl run --attach --rm`, which
+/// streams the container's output and takes its input through the API
+/// server, and deletes the pod once it exits. The prompt is appended to the
+/// command as its last argument. Follow-ups pass the agent session to
+/// continue with `--resume`.
+///
+/// Interrupting a session deletes its pod, as does the session ending, so a
+/// pod whose `kubectl run` was killed does not keep running.
+///
+/// The context's secrets are not forwarded, since kubectl could only pass
+/// them on its command line; reference Kubernetes Secrets with
+/// `with_secret_env` instead.
+pub struct K8sExecutor {
+    kubectl: String,
+    image: String,
+    command: CommandBuilder,
+    resume_flag: String,
+    namespace: Option<String>,
+    service_account: Option<String>,
+    working_dir: Option<String>,
+    env: BTreeMap<String, String>,
+    secret_env: BTreeMap<String, (String, String)>,
+    requests: BTreeMap<String, String>,
+    limits: BTreeMap<String, String>,
+}
+
+impl K8sExecutor {
+    /// Create an executor running `command` in pods of `image`, in the
+    /// current kubectl context and namespace.
+    #[must_use]
+    pub fn new(image: impl Into<String>, command: CommandBuilder) -> Self {
+        Self {
+            kubectl: "kubectl".to_string(),
+            image: image.into(),
+            command,
+            resume_flag: "--resume".to_string(),
+            namespace: None,
+            service_account: None,
+            working_dir: None,
+            env: BTreeMap::new(),
+            secret_env: BTreeMap::new(),
+            requests: BTreeMap::new(),
+            limits: BTreeMap::new(),
+        }
+    }
+
+    /// Run a different executable, e.g. an absolute path to `kubectl`.
+    #[must_use]
+    pub fn with_kubectl_executable(mut self, kubectl: impl Into<String>) -> Self {
+        self.kubectl = kubectl.into();
+        self
+    }
+
+    /// Pass the agent session a follow-up continues with `flag` instead of
+    /// `--resume`, e.g. `--session` for opencode.
+    #[must_use]
+    pub fn with_resume_flag(mut self, flag: impl Into<String>) -> Self {
+        self.resume_flag = flag.into();
+        self
+    }
+
+    /// Create pods in `namespace`.
+    #[must_use]
+    pub fn with_namespace(mut self, namespace: impl Into<String>) -> Self {
+        self.namespace = Some(namespace.into());
+        self
+    }
+
+    /// Run pods as `service_account`.
+    #[must_use]
+    pub fn with_service_account(mut self, service_account: impl Into<String>) -> Self {
+        self.service_account = Some(service_account.into());
+        self
+    }
+
+    /// Run the command in `dir` within the container.
+    #[must_use]
+    pub fn with_working_dir(mut self, dir: impl Into<String>) -> Self {
+        self.working_dir = Some(dir.into());
+        self
+    }
+
+    /// Set an environment variable in the container.
+    #[must_use]
+    pub fn with_env(mut self, key: impl Into<String>, value: impl Into<String>) -> Self {
+        self.env.insert(key.into(), value.into());
+        self
+    }
+
+    /// Set an environment variable from key `secret_key` of Secret
+    /// `secret_name`.
+    #[must_use]
+    pub fn with_secret_env(
+        mut self,
+        key: impl Into<String>,
+        secret_name: impl Into<String>,
+        secret_key: impl Into<String>,
+    ) -> Self {
+        self.secret_env
+            .insert(key.into(), (secret_name.into(), secret_key.into()));
+        self
+    }
+
+    /// Request `quantity` of `resource`, e.g. `cpu` and `500m`.
+    #[must_use]
+    pub fn with_resource_request(
+        mut self,
+        resource: impl Into<String>,
+        quantity: impl Into<String>,
+    ) -> Self {
+        self.requests.insert(resource.into(), quantity.into());
+        self
+    }
+
+    /// Limit `resource` to `quantity`, e.g. `memory` and `2Gi`.
+    #[must_use]
+    pub fn with_resource_limit(
+        mut self,
+        resource: impl Into<String>,
+        quantity: impl Into<String>,
+    ) -> Self {
+        self.limits.insert(resource.into(), quantity.into());
+        self
+    }
+
+    /// Arguments selecting the namespace, if one is set.
+    fn namespace_args(&self) -> Vec<String> {
+        self.namespace
+            .iter()
+            .flat_map(|ns| ["--namespace".to_string(), ns.clone()])
+            .collect()
+    }
+
+    /// The pod spec fields `kubectl run` has no flags for, as an override.
+    fn overrides(&self, name: &str) -> Value {
+        let env: Vec<Value> = self
+            .env
+            .iter()
+            .map(|(key, value)| json!({ "name": key, "value": value }))
+            .chain(self.secret_env.iter().map(|(key, (secret, secret_key))| {
+                json!({
+                    "name": key,
+                    "valueFrom": { "secretKeyRef": { "name": secret, "key": secret_key } },
+                })
+            }))
+            .collect();
+        let mut container = json!({
+            "name": name,
+            "image": self.image,
+            "stdin": true,
+            "stdinOnce": true,
+            "env": env,
+            "resources": { "requests": self.requests, "limits": self.limits },
+        });
+        if let Some(dir) = &self.working_dir {
+            container["workingDir"] = json!(dir);
+        }
+        let mut spec = json!({ "containers": [container] });
+        if let Some(account) = &self.service_account {
+            spec["serviceAccountName"] = json!(account);
+        }
+        json!({ "apiVersion": "v1", "spec": spec })
+    }
+
+    /// Build the `kubectl run` command for a pod named `name`, resuming
+    /// agent session `session_id` if given.
+    fn command_parts(
+        &self,
+        prompt: &str,
+        session_id: Option<&str>,
+        name: &str,
+    ) -> Result<CommandParts, ExecutorError> {
+        let mut args = vec!["run".to_string(), name.to_string()];
+        args.extend(self.namespace_args());
+        args.extend([
+            format!("--image={}", self.image),
+            "--restart=Never".to_string(),
+            "--rm".to_string(),
+            "--attach".to_string(),
+            "-i".to_string(),
+            "--quiet".to_string(),
+            format!("--overrides={}", self.overrides(name)),
+            "--command".to_string(),
+            "--".to_string(),
+        ]);
+
+        let mut agent_args = Vec::new();
+        if let Some(id) = session_id {
+            agent_args.extend([self.resume_flag.clone(), id.to_string()]);
+        }
+        if !prompt.is_empty() {
+            agent_args.push(prompt.to_string());
+        }
+        let agent = if agent_args.is_empty() {
+            self.command.build_initial()
+        } else {
+            self.command.build_follow_up(&agent_args)
+        }?;
+        args.push(agent.program);
+        args.extend(agent.args);
+        Ok(CommandParts::new(self.kubectl.clone(), args))
+    }
+
+    async fn start(
+        &self,
+        ctx: &ExecutionContext,
+        prompt: &str,
+        session_id: Option<&str>,
+    ) -> Result<SpawnedProcess, ExecutorError> {
+        let name = format!("remote-agents-{}", uuid::Uuid::new_v4());
+        let parts = self.command_parts(prompt, session_id, &name)?;
+        let program = resolve_executable_path(&parts.program)
+            .await
+            .ok_or_else(|| ExecutorError::ExecutableNotFound(parts.program.clone()))?;
+
+        let mut command = tokio::process::Command::new(&program);
+        command
+            .args(&parts.args)
+            .current_dir(&ctx.working_dir)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .kill_on_drop(true);
+        let child = command.group_spawn()?;
+
+        // The sender is dropped when the session ends. Either way the pod
+        // is deleted, in case `kubectl run` was killed without deleting it.
+        let (interrupt_tx, interrupt_rx) = oneshot::channel::<()>();
+        let mut delete_args = vec!["delete".to_string(), "pod".to_string(), name];
+        delete_args.extend(self.namespace_args());
+        delete_args.extend(["--wait=false".to_string(), "--ignore-not-found".to_string()]);
+        tokio::spawn(async move {
+            let _ = interrupt_rx.await;
+            delete_pod(program, &delete_args).await;
+        });
+        Ok(SpawnedProcess::from_child(child).with_interrupt(interrupt_tx))
+    }
+}
+
+#[async_trait]
+impl Executor for K8sExecutor {
+    async fn spawn(
+        &self,
+        ctx: &ExecutionContext,
+        prompt: &str,
+    ) -> Result<SpawnedProcess, ExecutorError> {
+        self.start(ctx, prompt, None).await
+    }
+
+    async fn spawn_follow_up(
+        &self,
+        ctx: &ExecutionContext,
+        prompt: &str,
+        session_id: &str,
+    ) -> Result<SpawnedProcess, ExecutorError> {
+        self.start(ctx, prompt, Some(session_id)).await
+    }
+
+    async fn preview(
+        &self,
+        ctx: &ExecutionContext,
+        prompt: &str,
+    ) -> Result<Option<CommandPreview>, ExecutorError> {
+        let preview = self
+            .command_parts(prompt, None, "remote-agents-preview")?
+            .into_preview(ctx)
+            .await?;
+        Ok(Some(preview))
+    }
+}
+
+/// Run `kubectl delete pod` with `args`.
+async fn delete_pod(kubectl: PathBuf, args: &[String]) {
+    let status = tokio::process::Command::new(kubectl)
+        .args(args)
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()
+        .await;
+    match status {
+        Ok(status) if status.success() => {}
+        Ok(status) => tracing::warn!("kubectl {} exited with {status}", args.join(" ")),
+        Err(e) => tracing::warn!("Failed to run kubectl {}: {e}", args.join(" ")),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_command_parts() {
+        let executor = K8sExecutor::new("agents:latest", CommandBuilder::new("claude -p"))
+            .with_namespace("agents")
+            .with_service_account("agent-runner")
+            .with_secret_env("ANTHROPIC_API_KEY", "anthropic", "api-key")
+            .with_resource_limit("memory", "2Gi");
+        let parts = executor.command_parts("fix it", None, "p1").unwrap();
+        assert_eq!(parts.program, "kubectl");
+        assert_eq!(parts.args[..4], ["run", "p1", "--namespace", "agents"]);
+        assert_eq!(
+            parts.args[parts.args.len() - 4..],
+            ["--", "claude", "-p", "fix it"]
+        );
+        let parts = executor.command_parts("go on", Some("s1"), "p2").unwrap();
+        assert_eq!(
+            parts.args[parts.args.len() - 6..],
+            ["--", "claude", "-p", "--resume", "s1", "go on"]
+        );
+
+        let overrides = executor.overrides("p1");
+        assert_eq!(overrides["spec"]["serviceAccountName"], "agent-runner");
+        let container = &overrides["spec"]["containers"][0];
+        assert_eq!(container["resources"]["limits"]["memory"], "2Gi");
+        assert_eq!(
+            container["env"][0]["valueFrom"]["secretKeyRef"],
+            json!({ "name": "anthropic", "key": "api-key" })
+        );
+    }
+}
@@ -1,13 +1,60 @@
 //! Claude Code executor with SDK protocol support.
 //!
 //! Provides:
+//! - `ClaudeExecutor`, an `Executor` running the Claude CLI
+//! - `OpencodeExecutor`, an `Executor` running the opencode CLI
+//! - `CursorAgentExecutor`, an `Executor` running cursor-agent
+//! - `ShellExecutor`, an `Executor` running arbitrary commands
+//! - `DockerExecutor`, an `Executor` running a command in a container
+//! - `StallWatchdog`, an `Executor` wrapper reporting agents that stop
+//!   producing output
+//! - `K8sExecutor`, an `Executor` running a command in a Kubernetes pod
+//!   (feature: kubernetes)
+//! - `NormalizedEntry`, agent output in one schema across executors
 //! - Claude Code SDK protocol types
 //! - Command building utilities
-//! - Approval handler trait
+//! - Approval handler trait, and `ApprovalBroker` queueing approvals for
+//!   any client to decide
+//! - `AuditingApprovalHandler`, recording approval decisions to audit
+//!   storage
+//! - `RiskClassifier`, judging the risk of tool calls from their input
+//! - `WorkingDirPolicy`, allowing file tools inside the working directory
+//! - `StdioApprovalHandler`, asking about tool calls on the terminal
 
 pub mod approvals;
+pub mod audit;
+pub mod broker;
 pub mod claude;
 pub mod command;
+pub mod cursor;
+pub mod docker;
+pub mod normalized;
+pub mod opencode;
+pub mod policy;
+pub mod risk;
+pub mod shell;
+pub mod stdio;
+pub mod watchdog;
 
-pub use approvals::{ApprovalHandler, ApprovalResult, ApprovalStatus};
+#[cfg(feature = "kubernetes")]
+pub mod k8s;
+
+pub use approvals::{
+    ApprovalGrants, ApprovalHandler, ApprovalMemory, ApprovalResult, ApprovalRisk, ApprovalScope, ApprovalStatus,
+    CompositeApprovalHandler, RateLimitedApprovalHandler, TimeoutApprovalHandler,
+};
+pub use audit::AuditingApprovalHandler;
+pub use broker::{ApprovalBroker, PendingApproval};
+pub use claude::ClaudeExecutor;
 pub use command::{CommandBuilder, CommandParts};
+pub use cursor::CursorAgentExecutor;
+pub use docker::DockerExecutor;
+#[cfg(feature = "kubernetes")]
+pub use k8s::K8sExecutor;
+pub use normalized::{NormalizedEntry, Normalizer, ToolKind, ToolStatus};
+pub use opencode::OpencodeExecutor;
+pub use policy::WorkingDirPolicy;
+pub use risk::RiskClassifier;
+pub use shell::ShellExecutor;
+pub use stdio::StdioApprovalHandler;
+pub use watchdog::{PendingApprovals, StallWatchdog};
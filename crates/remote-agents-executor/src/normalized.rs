@@ -0,0 +1,386 @@
+//! Agent output normalized to one schema across executors.
+
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::claude::{
+    ClaudeMessage, ContentBlock, FileDiff,
+    types::{MessageContent, UserMessage},
+};
+use crate::cursor::{CursorEvent, types::ToolCallStatus};
+use crate::opencode::OpencodeEvent;
+
+/// One entry of a conversation, whichever agent produced it.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum NormalizedEntry {
+    /// A prompt from the user.
+    UserMessage { content: String },
+    /// Text from the model.
+    AssistantMessage { content: String },
+    /// A tool call starting or finishing. Updates share the call's `id`.
+    ToolCall {
+        id: Option<String>,
+        name: String,
+        kind: ToolKind,
+        status: ToolStatus,
+        input: Value,
+        /// The result, or the error of a failed call.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        output: Option<Value>,
+    },
+    /// A file changed by a successful edit tool call, following the call.
+    FileDiff { path: String, unified_diff: String },
+    /// The agent or the run failed.
+    Error { message: String },
+    /// Session metadata or lifecycle, e.g. the model in use.
+    System { message: String },
+}
+
+/// What a tool does, for choosing how to display it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ToolKind {
+    Read,
+    Edit,
+    Execute,
+    Search,
+    Fetch,
+    Other,
+}
+
+impl ToolKind {
+    /// Classify a tool by name, e.g. `Bash`, `shell`, or `run_terminal_cmd`.
+    #[must_use]
+    pub fn from_name(name: &str) -> Self {
+        let name: String = name
+            .chars()
+            .filter(char::is_ascii_alphanumeric)
+            .map(|c| c.to_ascii_lowercase())
+            .collect();
+        match name.as_str() {
+            "read" | "view" | "ls" | "list" | "notebookread" => Self::Read,
+            "edit" | "multiedit" | "write" | "notebookedit" | "patch" | "delete" => Self::Edit,
+            "bash" | "shell" | "bashoutput" | "killshell" | "runterminalcmd" => Self::Execute,
+            "grep" | "glob" | "search" | "codebasesearch" => Self::Search,
+            "webfetch" | "websearch" | "fetch" => Self::Fetch,
+            _ => Self::Other,
+        }
+    }
+}
+
+/// Progress of a tool call.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ToolStatus {
+    Started,
+    Succeeded,
+    Failed,
+}
+
+/// Converts each executor's native output to `NormalizedEntry`s.
+///
+/// Keep one per session: Claude reports a tool's result without its name
+/// or input, so the normalizer remembers the calls it has seen started.
+#[derive(Debug, Default)]
+pub struct Normalizer {
+    started_tools: HashMap<String, (String, Value)>,
+}
+
+impl Normalizer {
+    /// Create a normalizer for a new session.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Entries for a Claude stream-json message. Partial messages are
+    /// skipped; the complete message follows them.
+    pub fn claude(&mut self, msg: &ClaudeMessage) -> Vec<NormalizedEntry> {
+        match msg {
+            ClaudeMessage::System { subtype, model, .. } => vec![NormalizedEntry::System {
+                message: system_message(subtype, model.as_deref()),
+            }],
+            ClaudeMessage::User {
+                message:
+                    UserMessage {
+                        content: MessageContent::Text(text),
+                    },
+                ..
+            } => vec![NormalizedEntry::UserMessage {
+                content: text.clone(),
+            }],
+            ClaudeMessage::Assistant { .. } | ClaudeMessage::User { .. } => msg
+                .content()
+                .iter()
+                .flat_map(|block| self.claude_block(block, msg))
+                .collect(),
+            ClaudeMessage::Result(result) if result.is_error => vec![NormalizedEntry::Error {
+                message: result
+                    .result
+                    .clone()
+                    .unwrap_or_else(|| result.subtype.clone()),
+            }],
+            ClaudeMessage::Result(_) | ClaudeMessage::StreamEvent { .. } => Vec::new(),
+        }
+    }
+
+    fn claude_block(&mut self, block: &ContentBlock, msg: &ClaudeMessage) -> Vec<NormalizedEntry> {
+        let from_user = matches!(msg, ClaudeMessage::User { .. });
+        match block {
+            ContentBlock::Text { text } if from_user => vec![NormalizedEntry::UserMessage {
+                content: text.clone(),
+            }],
+            ContentBlock::Text { text } => vec![NormalizedEntry::AssistantMessage {
+                content: text.clone(),
+            }],
+            ContentBlock::ToolUse { id, name, input } => {
+                self.started_tools
+                    .insert(id.clone(), (name.clone(), input.clone()));
+                vec![tool_call(
+                    Some(id.clone()),
+                    name,
+                    ToolStatus::Started,
+                    input.clone(),
+                    None,
+                )]
+            }
+            ContentBlock::ToolResult {
+                tool_use_id,
+                is_error,
+                ..
+            } => {
+                let (name, input) = self
+                    .started_tools
+                    .remove(tool_use_id)
+                    .unwrap_or_else(|| (String::new(), Value::Null));
+                let status = if *is_error {
+                    ToolStatus::Failed
+                } else {
+                    ToolStatus::Succeeded
+                };
+                let output = block.tool_result_text().map(Value::String);
+                let tool_use_result = match msg {
+                    ClaudeMessage::User {
+                        tool_use_result, ..
+                    } => tool_use_result.as_ref(),
+                    _ => None,
+                };
+                let diff = if status == ToolStatus::Succeeded {
+                    FileDiff::from_tool_call(&name, &input, tool_use_result)
+                } else {
+                    None
+                };
+                let diff = diff.map(|diff| NormalizedEntry::FileDiff {
+                    path: diff.path,
+                    unified_diff: diff.unified_diff,
+                });
+                let call = tool_call(Some(tool_use_id.clone()), &name, status, input, output);
+                std::iter::once(call).chain(diff).collect()
+            }
+            ContentBlock::Thinking { .. } | ContentBlock::Unknown => Vec::new(),
+        }
+    }
+
+    /// Entries for an opencode JSON event.
+    #[must_use]
+    pub fn opencode(&self, event: &OpencodeEvent) -> Vec<NormalizedEntry> {
+        match event {
+            OpencodeEvent::Text { part, .. } => vec![NormalizedEntry::AssistantMessage {
+                content: part.text.clone(),
+            }],
+            OpencodeEvent::ToolUse { part, .. } => {
+                let status = match part.state.get("status").and_then(Value::as_str) {
+                    Some("completed") => ToolStatus::Succeeded,
+                    Some("error") => ToolStatus::Failed,
+                    _ => ToolStatus::Started,
+                };
+                let input = part.state.get("input").cloned().unwrap_or(Value::Null);
+                let output = part
+                    .state
+                    .get("output")
+                    .or_else(|| part.state.get("error"))
+                    .cloned();
+                vec![tool_call(
+                    part.call_id.clone(),
+                    &part.tool,
+                    status,
+                    input,
+                    output,
+                )]
+            }
+            OpencodeEvent::Error { .. } => event
+                .error_message()
+                .map(|message| NormalizedEntry::Error { message })
+                .into_iter()
+                .collect(),
+            OpencodeEvent::StepStart { .. } | OpencodeEvent::StepFinish { .. } => Vec::new(),
+        }
+    }
+
+    /// Entries for a cursor-agent stream-json event.
+    #[must_use]
+    pub fn cursor(&self, event: &CursorEvent) -> Vec<NormalizedEntry> {
+        match event {
+            CursorEvent::System { subtype, model, .. } => vec![NormalizedEntry::System {
+                message: system_message(subtype.as_deref().unwrap_or("system"), model.as_deref()),
+            }],
+            CursorEvent::User { message, .. } => message
+                .iter()
+                .map(|message| NormalizedEntry::UserMessage {
+                    content: message.text(),
+                })
+                .collect(),
+            CursorEvent::Assistant { message, .. } => vec![NormalizedEntry::AssistantMessage {
+                content: message.text(),
+            }],
+            CursorEvent::ToolCall {
+                subtype, call_id, ..
+            } => event
+                .tool()
+                .map(|(name, call)| {
+                    let result = call.get("result");
+                    let status = match (subtype, result) {
+                        (ToolCallStatus::Started, _) => ToolStatus::Started,
+                        (ToolCallStatus::Completed, Some(result))
+                            if result.get("error").is_some() =>
+                        {
+                            ToolStatus::Failed
+                        }
+                        (ToolCallStatus::Completed, _) => ToolStatus::Succeeded,
+                    };
+                    let input = call.get("args").cloned().unwrap_or(Value::Null);
+                    tool_call(Some(call_id.clone()), name, status, input, result.cloned())
+                })
+                .into_iter()
+                .collect(),
+            CursorEvent::Result {
+                is_error: true,
+                result,
+                ..
+            } => vec![NormalizedEntry::Error {
+                message: result
+                    .clone()
+                    .unwrap_or_else(|| "cursor-agent run failed".to_string()),
+            }],
+            CursorEvent::Result { .. } => Vec::new(),
+        }
+    }
+}
+
+fn tool_call(
+    id: Option<String>,
+    name: &str,
+    status: ToolStatus,
+    input: Value,
+    output: Option<Value>,
+) -> NormalizedEntry {
+    NormalizedEntry::ToolCall {
+        id,
+        name: name.to_string(),
+        kind: ToolKind::from_name(name),
+        status,
+        input,
+        output,
+    }
+}
+
+/// Describe a system event, e.g. `Session started with model sonnet`.
+fn system_message(subtype: &str, model: Option<&str>) -> String {
+    match (subtype, model) {
+        ("init", Some(model)) => format!("Session started with model {model}"),
+        ("init", None) => "Session started".to_string(),
+        ("compact_boundary", _) => "Conversation compacted".to_string(),
+        (subtype, _) => subtype.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn test_claude_tool_call_completes() {
+        let mut normalizer = Normalizer::new();
+        let call = r#"{"type":"assistant","message":{"content":[{"type":"text","text":"Listing"},
+            {"type":"tool_use","id":"t1","name":"Bash","input":{"command":"ls"}}]}}"#;
+        let result = r#"{"type":"user","message":{"content":[
+            {"type":"tool_result","tool_use_id":"t1","content":"a.rs"}]}}"#;
+
+        let entries = normalizer.claude(&ClaudeMessage::parse(call).unwrap());
+        assert_eq!(
+            entries[0],
+            NormalizedEntry::AssistantMessage {
+                content: "Listing".into()
+            }
+        );
+        assert!(matches!(
+            &entries[1],
+            NormalizedEntry::ToolCall {
+                kind: ToolKind::Execute,
+                status: ToolStatus::Started,
+                ..
+            }
+        ));
+        assert_eq!(
+            normalizer.claude(&ClaudeMessage::parse(result).unwrap()),
+            vec![NormalizedEntry::ToolCall {
+                id: Some("t1".into()),
+                name: "Bash".into(),
+                kind: ToolKind::Execute,
+                status: ToolStatus::Succeeded,
+                input: json!({ "command": "ls" }),
+                output: Some(json!("a.rs")),
+            }]
+        );
+    }
+
+    #[test]
+    fn test_claude_edit_emits_file_diff() {
+        let mut normalizer = Normalizer::new();
+        let call = r#"{"type":"assistant","message":{"content":[{"type":"tool_use","id":"t1",
+            "name":"Edit","input":{"file_path":"a.rs","old_string":"x","new_string":"y"}}]}}"#;
+        let result = r#"{"type":"user","message":{"content":[
+            {"type":"tool_result","tool_use_id":"t1","content":"ok"}]}}"#;
+
+        normalizer.claude(&ClaudeMessage::parse(call).unwrap());
+        let entries = normalizer.claude(&ClaudeMessage::parse(result).unwrap());
+        assert_eq!(
+            entries[1],
+            NormalizedEntry::FileDiff {
+                path: "a.rs".into(),
+                unified_diff: "--- a/a.rs\n+++ b/a.rs\n@@ -1,1 +1,1 @@\n-x\n+y\n".into(),
+            }
+        );
+    }
+
+    #[test]
+    fn test_opencode_and_cursor_tool_calls() {
+        let normalizer = Normalizer::new();
+        let opencode = r#"{"type":"tool_use","part":{"tool":"edit","callID":"c1",
+            "state":{"status":"error","input":{"filePath":"a.rs"},"error":"not found"}}}"#;
+        assert!(matches!(
+            &normalizer.opencode(&OpencodeEvent::parse(opencode).unwrap())[0],
+            NormalizedEntry::ToolCall {
+                kind: ToolKind::Edit,
+                status: ToolStatus::Failed,
+                ..
+            }
+        ));
+
+        let cursor = r#"{"type":"tool_call","subtype":"completed","call_id":"c2","tool_call":
+            {"grepToolCall":{"args":{"pattern":"fn"},"result":{"success":{}}}}}"#;
+        assert!(matches!(
+            &normalizer.cursor(&CursorEvent::parse(cursor).unwrap())[0],
+            NormalizedEntry::ToolCall {
+                kind: ToolKind::Search,
+                status: ToolStatus::Succeeded,
+                ..
+            }
+        ));
+    }
+}
@@ -0,0 +1,277 @@
+//! `Executor` implementation running the opencode CLI.
+
+use std::process::Stdio;
+
+use async_trait::async_trait;
+use command_group::AsyncCommandGroup;
+use futures::StreamExt;
+use remote_agents_core::{
+    ExecutionContext, LogMsg,
+    traits::{
+        CommandPreview, EventStream, Executor, ExecutorError, SpawnedProcess, UsageEvent,
+        UsageStream, raw_output_events,
+    },
+};
+use remote_agents_pty::resolve_executable_path;
+use tokio::{
+    io::{AsyncBufReadExt, AsyncRead, BufReader},
+    sync::mpsc,
+};
+
+use super::types::OpencodeEvent;
+use crate::command::{CommandBuilder, CommandParts};
+
+/// Flags for a headless run printing JSON events.
+const RUN_PARAMS: [&str; 3] = ["run", "--format", "json"];
+
+/// Runs sessions with `opencode run`.
+///
+/// Each prompt is one process, which exits when the agent finishes.
+/// Follow-ups continue the session with `--session`. opencode has no
+/// interrupt request, so interrupting a session signals the process.
+pub struct OpencodeExecutor {
+    command: CommandBuilder,
+    model: Option<String>,
+}
+
+impl Default for OpencodeExecutor {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl OpencodeExecutor {
+    /// Create an executor running `opencode` from PATH with its default
+    /// model.
+    #[must_use]
+    pub fn new() -> Self {
+        Self {
+            command: CommandBuilder::new("opencode").params(RUN_PARAMS),
+            model: None,
+        }
+    }
+
+    /// Run a different executable, e.g. an absolute path to `opencode`.
+    #[must_use]
+    pub fn with_executable(mut self, executable: impl Into<String>) -> Self {
+        self.command = self.command.override_base(executable);
+        self
+    }
+
+    /// Use `model`, as `provider/model`, e.g. `anthropic/claude-sonnet-4`.
+    #[must_use]
+    pub fn with_model(mut self, model: impl Into<String>) -> Self {
+        self.model = Some(model.into());
+        self
+    }
+
+    /// Build the command, continuing `session_id` if given.
+    fn command_parts(
+        &self,
+        prompt: &str,
+        session_id: Option<&str>,
+    ) -> Result<CommandParts, ExecutorError> {
+        let mut args = Vec::new();
+        if let Some(model) = &self.model {
+            args.extend(["--model".to_string(), model.clone()]);
+        }
+        if let Some(id) = session_id {
+            args.extend(["--session".to_string(), id.to_string()]);
+        }
+        // The prompt is positional; `--` keeps a leading `-` from being
+        // read as a flag.
+        args.extend(["--".to_string(), prompt.to_string()]);
+        Ok(self.command.build_follow_up(&args)?)
+    }
+
+    async fn start(
+        &self,
+        ctx: &ExecutionContext,
+        prompt: &str,
+        session_id: Option<&str>,
+    ) -> Result<SpawnedProcess, ExecutorError> {
+        let parts = self.command_parts(prompt, session_id)?;
+        let program = resolve_executable_path(&parts.program)
+            .await
+            .ok_or_else(|| ExecutorError::ExecutableNotFound(parts.program.clone()))?;
+
+        let mut command = tokio::process::Command::new(program);
+        command
+            .args(&parts.args)
+            .current_dir(&ctx.working_dir)
+            .envs(ctx.secret_env())
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .kill_on_drop(true);
+        let mut child = command.group_spawn()?;
+        let Some(stdout) = child.inner().stdout.take() else {
+            return Err(ExecutorError::SpawnFailed(
+                "opencode stdout not piped".to_string(),
+            ));
+        };
+
+        let (usage_tx, usage_rx) = mpsc::unbounded_channel();
+        let stderr = raw_output_events(&mut child);
+        let events = futures::stream::select(output_events(stdout, usage_tx), stderr).boxed();
+        Ok(SpawnedProcess::from_child(child)
+            .with_events(events)
+            .with_usage(usage_stream(usage_rx)))
+    }
+}
+
+#[async_trait]
+impl Executor for OpencodeExecutor {
+    async fn spawn(
+        &self,
+        ctx: &ExecutionContext,
+        prompt: &str,
+    ) -> Result<SpawnedProcess, ExecutorError> {
+        self.start(ctx, prompt, None).await
+    }
+
+    async fn spawn_follow_up(
+        &self,
+        ctx: &ExecutionContext,
+        prompt: &str,
+        session_id: &str,
+    ) -> Result<SpawnedProcess, ExecutorError> {
+        self.start(ctx, prompt, Some(session_id)).await
+    }
+
+    async fn preview(
+        &self,
+        ctx: &ExecutionContext,
+        prompt: &str,
+    ) -> Result<Option<CommandPreview>, ExecutorError> {
+        let preview = self.command_parts(prompt, None)?.into_preview(ctx).await?;
+        Ok(Some(preview))
+    }
+}
+
+/// Parsing state of the output stream.
+struct OutputState<R> {
+    lines: tokio::io::Lines<BufReader<R>>,
+    usage_tx: mpsc::UnboundedSender<UsageEvent>,
+    session_reported: bool,
+    total_cost_usd: f64,
+}
+
+impl<R> OutputState<R> {
+    /// Events for one line: the session ID when first seen, the line, and
+    /// a `Stderr` description of an error event.
+    fn on_line(&mut self, line: String) -> Vec<LogMsg> {
+        let Some(event) = OpencodeEvent::parse(&line) else {
+            return vec![LogMsg::Stdout(line + "\n")];
+        };
+        let mut msgs = Vec::new();
+        if let Some(id) = event.session_id().filter(|_| !self.session_reported) {
+            self.session_reported = true;
+            msgs.push(LogMsg::SessionId(id.to_string()));
+        }
+        // The session manager may not read usage; that is not an error.
+        for usage in event.usage_events() {
+            let _ = self.usage_tx.send(usage);
+        }
+        if let OpencodeEvent::StepFinish { part, .. } = &event {
+            self.total_cost_usd += part.cost;
+            let _ = self.usage_tx.send(UsageEvent::Cost {
+                total_usd: self.total_cost_usd,
+            });
+        }
+        let error = event.error_message();
+        msgs.push(LogMsg::Stdout(line + "\n"));
+        msgs.extend(error.map(|e| LogMsg::Stderr(format!("opencode error: {e}\n"))));
+        msgs
+    }
+}
+
+/// Stream opencode's JSON event lines as `Stdout` events, reporting the
+/// session ID and sending usage to `usage_tx`.
+fn output_events<R>(stdout: R, usage_tx: mpsc::UnboundedSender<UsageEvent>) -> EventStream
+where
+    R: AsyncRead + Send + Unpin + 'static,
+{
+    let state = OutputState {
+        lines: BufReader::new(stdout).lines(),
+        usage_tx,
+        session_reported: false,
+        total_cost_usd: 0.0,
+    };
+    futures::stream::unfold(state, |mut state| async move {
+        let events = match state.lines.next_line().await {
+            Ok(Some(line)) => state.on_line(line).into_iter().map(Ok).collect(),
+            Ok(None) => return None,
+            Err(e) => vec![Err(ExecutorError::Io(e))],
+        };
+        Some((futures::stream::iter(events), state))
+    })
+    .flatten()
+    .boxed()
+}
+
+/// Usage events from `rx`, ending when the output stream is done.
+fn usage_stream(rx: mpsc::UnboundedReceiver<UsageEvent>) -> UsageStream {
+    futures::stream::unfold(rx, |mut rx| async move {
+        rx.recv().await.map(|event| (event, rx))
+    })
+    .boxed()
+}
+
+#[cfg(test)]
+mod tests {
+    use tokio::io::AsyncWriteExt;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_output_events() {
+        let (reader, mut writer) = tokio::io::duplex(4096);
+        let lines = [
+            r#"{"type":"step_start","sessionID":"ses_1","part":{}}"#,
+            r#"{"type":"step_finish","sessionID":"ses_1","part":{"cost":0.5,"tokens":{}}}"#,
+            r#"{"type":"error","sessionID":"ses_1","error":{"data":{"message":"No API key"}}}"#,
+        ];
+        writer
+            .write_all(format!("{}\n", lines.join("\n")).as_bytes())
+            .await
+            .unwrap();
+        drop(writer);
+
+        let (usage_tx, usage_rx) = mpsc::unbounded_channel();
+        let events: Vec<_> = output_events(reader, usage_tx)
+            .map(Result::unwrap)
+            .collect()
+            .await;
+        assert!(matches!(&events[0], LogMsg::SessionId(id) if id == "ses_1"));
+        assert_eq!(events.len(), 5);
+        assert!(matches!(&events[4], LogMsg::Stderr(e) if e == "opencode error: No API key\n"));
+
+        let usage: Vec<_> = usage_stream(usage_rx).collect().await;
+        assert_eq!(usage.len(), 3);
+        assert_eq!(usage[2], UsageEvent::Cost { total_usd: 0.5 });
+    }
+
+    #[test]
+    fn test_command_parts() {
+        let parts = OpencodeExecutor::new()
+            .with_model("anthropic/claude-sonnet-4")
+            .command_parts("-fix it", Some("ses_1"))
+            .unwrap();
+        assert_eq!(parts.program, "opencode");
+        assert_eq!(
+            parts.args,
+            [
+                "run",
+                "--format",
+                "json",
+                "--model",
+                "anthropic/claude-sonnet-4",
+                "--session",
+                "ses_1",
+                "--",
+                "-fix it",
+            ]
+        );
+    }
+}
@@ -0,0 +1,7 @@
+//! opencode executor.
+
+pub mod executor;
+pub mod types;
+
+pub use executor::OpencodeExecutor;
+pub use types::OpencodeEvent;
@@ -0,0 +1,186 @@
+//! Events printed by `opencode run --format json`.
+
+use remote_agents_core::traits::{TokenUsage, UsageEvent};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+/// One line of `opencode run --format json` output.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum OpencodeEvent {
+    /// The model started a step: one request and its tool calls.
+    StepStart {
+        #[serde(rename = "sessionID", default)]
+        session_id: Option<String>,
+    },
+    /// A completed text part.
+    Text {
+        #[serde(rename = "sessionID", default)]
+        session_id: Option<String>,
+        part: TextPart,
+    },
+    /// A tool call that finished, successfully or not.
+    ToolUse {
+        #[serde(rename = "sessionID", default)]
+        session_id: Option<String>,
+        part: ToolPart,
+    },
+    /// A step finished, with its token usage and cost.
+    StepFinish {
+        #[serde(rename = "sessionID", default)]
+        session_id: Option<String>,
+        part: StepFinishPart,
+    },
+    /// The run failed.
+    Error {
+        #[serde(rename = "sessionID", default)]
+        session_id: Option<String>,
+        error: Value,
+    },
+}
+
+impl OpencodeEvent {
+    /// Parse a line of output, or `None` if it is not a known event.
+    #[must_use]
+    pub fn parse(line: &str) -> Option<Self> {
+        serde_json::from_str(line).ok()
+    }
+
+    /// ID of the opencode session, for `--session`.
+    #[must_use]
+    pub fn session_id(&self) -> Option<&str> {
+        match self {
+            Self::StepStart { session_id }
+            | Self::Text { session_id, .. }
+            | Self::ToolUse { session_id, .. }
+            | Self::StepFinish { session_id, .. }
+            | Self::Error { session_id, .. } => session_id.as_deref(),
+        }
+    }
+
+    /// The message of an error event, whose error is e.g.
+    /// `{"name":..,"data":{"message":..}}`.
+    #[must_use]
+    pub fn error_message(&self) -> Option<String> {
+        let Self::Error { error, .. } = self else {
+            return None;
+        };
+        Some(
+            error
+                .pointer("/data/message")
+                .or_else(|| error.get("message"))
+                .and_then(Value::as_str)
+                .map_or_else(|| error.to_string(), str::to_string),
+        )
+    }
+
+    /// Usage this event reports, excluding cost, which is cumulative.
+    #[must_use]
+    pub fn usage_events(&self) -> Vec<UsageEvent> {
+        match self {
+            Self::ToolUse { part, .. } => vec![UsageEvent::ToolCall {
+                tool_name: part.tool.clone(),
+            }],
+            Self::StepFinish { part, .. } => vec![
+                UsageEvent::TurnCompleted,
+                UsageEvent::Tokens(part.tokens.usage()),
+            ],
+            Self::StepStart { .. } | Self::Text { .. } | Self::Error { .. } => Vec::new(),
+        }
+    }
+}
+
+/// Text the model produced.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TextPart {
+    pub text: String,
+}
+
+/// A tool call and its state.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ToolPart {
+    /// Tool name, e.g. `bash` or `edit`.
+    pub tool: String,
+    #[serde(rename = "callID", default)]
+    pub call_id: Option<String>,
+    /// Status, input, and output or error of the call.
+    #[serde(default)]
+    pub state: Value,
+}
+
+/// Usage of a finished step.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct StepFinishPart {
+    #[serde(default)]
+    pub tokens: OpencodeTokens,
+    /// Cost of the step, in US dollars.
+    #[serde(default)]
+    pub cost: f64,
+}
+
+/// Token counts of a step.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct OpencodeTokens {
+    pub input: u64,
+    pub output: u64,
+    pub reasoning: u64,
+    pub cache: OpencodeCacheTokens,
+}
+
+/// Prompt cache token counts of a step.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct OpencodeCacheTokens {
+    pub read: u64,
+    pub write: u64,
+}
+
+impl OpencodeTokens {
+    /// The counts as `TokenUsage`, with reasoning counted as output.
+    #[must_use]
+    pub const fn usage(&self) -> TokenUsage {
+        TokenUsage {
+            input_tokens: self.input,
+            output_tokens: self.output + self.reasoning,
+            cache_creation_input_tokens: self.cache.write,
+            cache_read_input_tokens: self.cache.read,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_events() {
+        let finish = r#"{"type":"step_finish","timestamp":1,"sessionID":"ses_1","part":{
+            "type":"step-finish","cost":0.02,"tokens":{"input":100,"output":20,"reasoning":5,
+            "cache":{"read":50,"write":0}}}}"#;
+        let event = OpencodeEvent::parse(finish).unwrap();
+        assert_eq!(event.session_id(), Some("ses_1"));
+        assert_eq!(
+            event.usage_events(),
+            vec![
+                UsageEvent::TurnCompleted,
+                UsageEvent::Tokens(TokenUsage {
+                    input_tokens: 100,
+                    output_tokens: 25,
+                    cache_creation_input_tokens: 0,
+                    cache_read_input_tokens: 50,
+                }),
+            ]
+        );
+
+        let tool = r#"{"type":"tool_use","sessionID":"ses_1","part":{"type":"tool",
+            "tool":"bash","callID":"c1","state":{"status":"completed"}}}"#;
+        assert_eq!(
+            OpencodeEvent::parse(tool).unwrap().usage_events(),
+            vec![UsageEvent::ToolCall {
+                tool_name: "bash".into()
+            }]
+        );
+        assert!(OpencodeEvent::parse("INFO starting server").is_none());
+    }
+}
@@ -0,0 +1,218 @@
+//! Built-in approval policies.
+
+use std::path::{Component, Path, PathBuf};
+
+use async_trait::async_trait;
+use serde_json::Value;
+
+use crate::{
+    approvals::{ApprovalError, ApprovalHandler, ApprovalResult},
+    normalized::ToolKind,
+};
+
+/// Approval policy keeping file tools inside a session's working directory.
+///
+/// Reads, searches, and edits of paths inside the working directory are
+/// allowed; those outside it are denied, or with `with_escalation` left
+/// undecided for the next handler of a `CompositeApprovalHandler`, e.g. a
+/// human. Paths are resolved through symlinks, so a link inside the
+/// directory pointing out of it counts as outside. A search's `glob`, or a
+/// Glob's `pattern`, that is absolute or climbs out with `..` is checked
+/// like a path; a Grep's `pattern` is a regex and is not.
+/// Other tools are left undecided.
+#[derive(Debug, Clone)]
+pub struct WorkingDirPolicy {
+    working_dir: PathBuf,
+    escalate: bool,
+}
+
+impl WorkingDirPolicy {
+    /// Keep file tools inside `working_dir`, e.g. the session's
+    /// `ExecutionContext::working_dir`.
+    #[must_use]
+    pub fn new(working_dir: impl Into<PathBuf>) -> Self {
+        Self {
+            working_dir: working_dir.into(),
+            escalate: false,
+        }
+    }
+
+    /// Leave calls outside the working directory undecided instead of
+    /// denying them.
+    #[must_use]
+    pub const fn with_escalation(mut self) -> Self {
+        self.escalate = true;
+        self
+    }
+
+    /// Whether `path`, relative to the working directory if not absolute,
+    /// resolves inside it.
+    async fn is_inside(&self, path: &str) -> bool {
+        let Some(working_dir) = resolve(&self.working_dir).await else {
+            return false;
+        };
+        resolve(&working_dir.join(path))
+            .await
+            .is_some_and(|path| path.starts_with(&working_dir))
+    }
+}
+
+#[async_trait]
+impl ApprovalHandler for WorkingDirPolicy {
+    async fn request_approval(
+        &self,
+        tool_name: &str,
+        tool_input: Value,
+        _tool_call_id: &str,
+    ) -> Result<ApprovalResult, ApprovalError> {
+        let kind = ToolKind::from_name(tool_name);
+        if !matches!(kind, ToolKind::Read | ToolKind::Edit | ToolKind::Search) {
+            return Err(ApprovalError::Undecided);
+        }
+        let path = ["file_path", "notebook_path", "path"]
+            .iter()
+            .find_map(|key| tool_input.get(key).and_then(Value::as_str));
+        let mut paths: Vec<&str> = path.into_iter().collect();
+        if matches!(kind, ToolKind::Search) {
+            // Searches default to the working directory, but a glob can
+            // name files elsewhere by itself.
+            let keys: &[&str] = if tool_name.eq_ignore_ascii_case("glob") {
+                &["pattern", "glob"]
+            } else {
+                &["glob"]
+            };
+            let patterns = keys
+                .iter()
+                .filter_map(|key| tool_input.get(key).and_then(Value::as_str))
+                .filter(|pattern| may_leave(pattern));
+            paths.extend(patterns);
+        } else if paths.is_empty() {
+            return Err(ApprovalError::Undecided);
+        }
+        let mut outside = None;
+        for path in paths {
+            if !self.is_inside(path).await {
+                outside = Some(path.to_string());
+                break;
+            }
+        }
+        let Some(path) = outside else {
+            return Ok(ApprovalResult::allow(tool_input));
+        };
+        if self.escalate {
+            return Err(ApprovalError::Undecided);
+        }
+        Ok(ApprovalResult::Deny {
+            message: format!(
+                "{path} is outside the working directory {}",
+                self.working_dir.display()
+            ),
+            interrupt: None,
+            decided_by: None,
+        })
+    }
+}
+
+/// Whether glob `pattern` could match outside the directory it is relative to.
+fn may_leave(pattern: &str) -> bool {
+    let pattern = Path::new(pattern);
+    pattern.is_absolute()
+        || pattern
+            .components()
+            .any(|component| component == Component::ParentDir)
+}
+
+/// `path` with symlinks resolved. Of a path that does not exist yet, e.g. a
+/// file about to be written, the longest existing ancestor is resolved.
+/// `None` if nothing resolves, or the missing part leaves its parent.
+async fn resolve(path: &Path) -> Option<PathBuf> {
+    let mut existing = path.to_path_buf();
+    let mut missing = Vec::new();
+    loop {
+        if let Ok(mut resolved) = tokio::fs::canonicalize(&existing).await {
+            resolved.extend(missing.iter().rev());
+            return Some(resolved);
+        }
+        // `None` for `..`, which cannot be resolved without its parent.
+        missing.push(existing.file_name()?.to_os_string());
+        existing.pop();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_allows_paths_inside_working_dir() {
+        let root = std::env::temp_dir().join(format!("policy-{}", uuid::Uuid::new_v4()));
+        let working_dir = root.join("repo");
+        tokio::fs::create_dir_all(working_dir.join("src"))
+            .await
+            .unwrap();
+        #[cfg(unix)]
+        tokio::fs::symlink(&root, working_dir.join("escape"))
+            .await
+            .unwrap();
+        let policy = WorkingDirPolicy::new(&working_dir);
+        let allowed = |tool: &'static str, path: &str| {
+            let input = json!({ "file_path": path });
+            let policy = policy.clone();
+            async move {
+                matches!(
+                    policy.request_approval(tool, input, "t1").await,
+                    Ok(ApprovalResult::Allow { .. })
+                )
+            }
+        };
+
+        assert!(allowed("Read", "src/lib.rs").await);
+        let inside = working_dir.join("src/new/mod.rs");
+        assert!(allowed("Write", inside.to_str().unwrap()).await);
+        assert!(!allowed("Edit", "../outside.txt").await);
+        assert!(!allowed("Read", "/etc/hosts").await);
+        #[cfg(unix)]
+        assert!(!allowed("Write", "escape/outside.txt").await);
+
+        let escalating = WorkingDirPolicy::new(&working_dir).with_escalation();
+        let result = escalating
+            .request_approval("Read", json!({ "file_path": "/etc/hosts" }), "t2")
+            .await;
+        assert!(matches!(result, Err(ApprovalError::Undecided)));
+        let result = policy
+            .request_approval("Bash", json!({ "command": "ls" }), "t3")
+            .await;
+        assert!(matches!(result, Err(ApprovalError::Undecided)));
+
+        let searched = |input: Value| {
+            let policy = policy.clone();
+            async move {
+                matches!(
+                    policy.request_approval("Glob", input, "t4").await,
+                    Ok(ApprovalResult::Allow { .. })
+                )
+            }
+        };
+        assert!(searched(json!({ "pattern": "src/**/*.rs" })).await);
+        assert!(searched(json!({ "pattern": "fn ..", "glob": "*.rs" })).await);
+        assert!(!searched(json!({ "pattern": "/etc/**/*.conf" })).await);
+        assert!(!searched(json!({ "pattern": "key", "glob": "../**/.env" })).await);
+
+        let grepped = |input: Value| {
+            let policy = policy.clone();
+            async move {
+                matches!(
+                    policy.request_approval("Grep", input, "t5").await,
+                    Ok(ApprovalResult::Allow { .. })
+                )
+            }
+        };
+        assert!(grepped(json!({ "pattern": r"\.\./" })).await);
+        assert!(grepped(json!({ "pattern": "foo/../bar", "glob": "*.rs" })).await);
+        assert!(!grepped(json!({ "pattern": "key", "glob": "/etc/*" })).await);
+        assert!(!grepped(json!({ "pattern": "key", "path": "/etc" })).await);
+        tokio::fs::remove_dir_all(&root).await.unwrap();
+    }
+}
@@ -0,0 +1,557 @@
+//! Risk of tool calls, judged from their input.
+
+use std::path::{Component, Path, PathBuf};
+
+use serde_json::Value;
+
+use crate::{approvals::ApprovalRisk, normalized::ToolKind};
+
+/// Commands that only read or print.
+const READ_ONLY_COMMANDS: &[&str] = &[
+    "cat", "cd", "cut", "date", "df", "diff", "du", "echo", "file", "grep", "head", "jq", "less",
+    "ls", "more", "printf", "pwd", "rg", "sort", "stat", "tail", "tr", "tree", "true", "uniq",
+    "wc", "which", "whoami",
+];
+
+/// Git subcommands that only read.
+const READ_ONLY_GIT: &[&str] = &[
+    "blame",
+    "diff",
+    "log",
+    "ls-files",
+    "rev-parse",
+    "show",
+    "status",
+];
+
+/// Commands that delete data, stop processes, or escalate privileges.
+const DESTRUCTIVE_COMMANDS: &[&str] = &[
+    "chmod", "chown", "dd", "fdisk", "kill", "killall", "pkill", "reboot", "rm", "rmdir", "shred",
+    "shutdown", "su", "sudo", "truncate",
+];
+
+/// Commands that reach the network.
+const NETWORK_COMMANDS: &[&str] = &[
+    "curl", "ftp", "nc", "ncat", "rsync", "scp", "sftp", "ssh", "telnet", "wget",
+];
+
+/// Package managers, which reach the network to install.
+const PACKAGE_MANAGERS: &[&str] = &[
+    "apt", "apt-get", "brew", "cargo", "gem", "npm", "pip", "pip3", "pnpm", "yarn",
+];
+
+/// Commands writing to the paths given as their arguments.
+const WRITING_COMMANDS: &[&str] = &["cp", "ln", "mkdir", "mv", "tee", "touch"];
+
+/// Commands running the command in their arguments, with those of their
+/// options taking a value.
+const WRAPPERS: &[(&str, &[&str])] = &[
+    ("command", &[]),
+    ("env", &["-u", "--unset", "-C", "--chdir"]),
+    ("exec", &["-a"]),
+    ("nice", &["-n", "--adjustment"]),
+    ("nohup", &[]),
+    ("time", &["-f", "--format", "-o", "--output"]),
+    ("timeout", &["-s", "--signal", "-k", "--kill-after"]),
+    (
+        "xargs",
+        &[
+            "-a",
+            "--arg-file",
+            "-d",
+            "--delimiter",
+            "-E",
+            "-I",
+            "-L",
+            "--max-lines",
+            "-n",
+            "--max-args",
+            "-P",
+            "--max-procs",
+            "-s",
+            "--max-chars",
+        ],
+    ),
+];
+
+/// Shells, which run the script given with `-c`.
+const SHELLS: &[&str] = &["ash", "bash", "dash", "ksh", "sh", "zsh"];
+
+/// Git options taking a value, given before the subcommand.
+const GIT_OPTIONS: &[&str] = &[
+    "-C",
+    "-c",
+    "--config-env",
+    "--exec-path",
+    "--git-dir",
+    "--namespace",
+    "--work-tree",
+];
+
+/// Judges how much harm a tool call could do from its name and input.
+///
+/// Reads and searches are low risk, and so are shell commands that only
+/// read, e.g. `ls` or `git status`. Edits, web fetches, and other commands
+/// are medium risk. Destructive commands (e.g. `rm`, `git push --force`,
+/// `git reset --hard`), commands reaching the network, writes outside the
+/// working directory, and unknown tools are high risk.
+///
+/// Without a working directory, absolute paths, relative paths leaving it
+/// with `..`, and paths under `~` count as outside.
+#[derive(Debug, Clone, Default)]
+pub struct RiskClassifier {
+    working_dir: Option<PathBuf>,
+}
+
+impl RiskClassifier {
+    /// Create a classifier with no working directory.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Treat writes outside `working_dir` as high risk.
+    #[must_use]
+    pub fn with_working_dir(mut self, working_dir: impl Into<PathBuf>) -> Self {
+        let working_dir = working_dir.into();
+        self.working_dir = Some(normalize(&working_dir).unwrap_or(working_dir));
+        self
+    }
+
+    /// The risk of calling `tool_name` with `tool_input`.
+    #[must_use]
+    pub fn classify(&self, tool_name: &str, tool_input: &Value) -> ApprovalRisk {
+        match ToolKind::from_name(tool_name) {
+            ToolKind::Read | ToolKind::Search => ApprovalRisk::Low,
+            ToolKind::Fetch => ApprovalRisk::Medium,
+            ToolKind::Edit => {
+                let path = ["file_path", "notebook_path", "path"]
+                    .iter()
+                    .find_map(|key| tool_input.get(key).and_then(Value::as_str));
+                match path {
+                    Some(path) if !self.is_outside(path) => ApprovalRisk::Medium,
+                    _ => ApprovalRisk::High,
+                }
+            }
+            // Without a command, e.g. reading or killing a background shell.
+            ToolKind::Execute => tool_input
+                .get("command")
+                .and_then(Value::as_str)
+                .map_or_else(
+                    || ApprovalRisk::of_tool(tool_name),
+                    |command| self.classify_command(command),
+                ),
+            ToolKind::Other => ApprovalRisk::High,
+        }
+    }
+
+    /// The risk of a shell command line: that of its riskiest command.
+    fn classify_command(&self, command: &str) -> ApprovalRisk {
+        split_commands(command)
+            .iter()
+            .map(|segment| {
+                let words = shlex::split(segment)
+                    .unwrap_or_else(|| segment.split_whitespace().map(str::to_string).collect());
+                self.classify_words(&words)
+            })
+            .max()
+            .unwrap_or(ApprovalRisk::Low)
+    }
+
+    /// The risk of one command, split into words.
+    fn classify_words(&self, words: &[String]) -> ApprovalRisk {
+        // Command substitutions could run anything.
+        if words
+            .iter()
+            .any(|word| word.contains("$(") || word.contains('`'))
+        {
+            return ApprovalRisk::High;
+        }
+        let (words, redirects) = split_redirects(words);
+        let words = unwrap_command(&words);
+        let mut command = words
+            .iter()
+            .map(String::as_str)
+            .skip_while(|word| is_assignment(word));
+        let Some(program) = command.next().map(program_name) else {
+            return ApprovalRisk::Low;
+        };
+        let args: Vec<&str> = command.collect();
+
+        let writes_outside = |written: &[&str]| {
+            written
+                .iter()
+                .copied()
+                .chain(redirects.iter().map(String::as_str))
+                .any(|path| self.is_outside(path))
+        };
+        if SHELLS.contains(&program) {
+            if let Some(script) = shell_script(&args) {
+                let redirected = if writes_outside(&[]) {
+                    ApprovalRisk::High
+                } else if redirects.is_empty() {
+                    ApprovalRisk::Low
+                } else {
+                    ApprovalRisk::Medium
+                };
+                return self.classify_command(script).max(redirected);
+            }
+        }
+
+        let flag = |names: &[&str]| args.iter().any(|arg| names.contains(arg));
+        let destructive = DESTRUCTIVE_COMMANDS.contains(&program)
+            || program.starts_with("mkfs")
+            || (program == "find" && flag(&["-delete", "-exec", "-execdir"]))
+            || (program == "git" && is_destructive_git(&args));
+        let network = NETWORK_COMMANDS.contains(&program)
+            || (program == "git"
+                && git_subcommand(&args)
+                    .is_some_and(|(sub, _)| matches!(sub, "push" | "pull" | "fetch" | "clone")))
+            || (PACKAGE_MANAGERS.contains(&program) && flag(&["install", "add"]));
+        let written: Vec<&str> = match program {
+            "cp" | "mv" | "ln" => args
+                .iter()
+                .rfind(|arg| !arg.starts_with('-'))
+                .into_iter()
+                .copied()
+                .collect(),
+            "sort" => sort_output(&args).into_iter().collect(),
+            _ if WRITING_COMMANDS.contains(&program) => args
+                .iter()
+                .filter(|arg| !arg.starts_with('-'))
+                .copied()
+                .collect(),
+            _ => Vec::new(),
+        };
+        if destructive || network || writes_outside(&written) {
+            return ApprovalRisk::High;
+        }
+
+        // `env` running a command was unwrapped above, so this one prints.
+        let read_only = READ_ONLY_COMMANDS.contains(&program)
+            || program == "env"
+            || program == "find"
+            || (program == "git"
+                && git_subcommand(&args).is_some_and(|(sub, rest)| {
+                    READ_ONLY_GIT.contains(&sub) || (sub == "branch" && rest.is_empty())
+                }));
+        if read_only && written.is_empty() && redirects.is_empty() {
+            ApprovalRisk::Low
+        } else {
+            ApprovalRisk::Medium
+        }
+    }
+
+    /// Whether `path` is outside the working directory.
+    fn is_outside(&self, path: &str) -> bool {
+        if path == "/dev/null" {
+            return false;
+        }
+        if path.starts_with('~') {
+            return true;
+        }
+        let path = Path::new(path);
+        self.working_dir.as_ref().map_or_else(
+            || path.is_absolute() || normalize(path).is_none(),
+            |dir| normalize(&dir.join(path)).is_none_or(|path| !path.starts_with(dir)),
+        )
+    }
+}
+
+/// Whether a git command rewrites or discards history or work.
+fn is_destructive_git(args: &[&str]) -> bool {
+    let Some((subcommand, args)) = git_subcommand(args) else {
+        return false;
+    };
+    let flag = |names: &[&str]| args.iter().any(|arg| names.contains(arg));
+    match subcommand {
+        "push" => {
+            flag(&["-f", "--force", "--force-with-lease", "--delete", "-d"])
+                || args
+                    .iter()
+                    .any(|arg| arg.starts_with('+') || arg.starts_with(':'))
+        }
+        "reset" => flag(&["--hard"]),
+        "checkout" => flag(&["--", ".", "-f", "--force"]),
+        "branch" => flag(&["-d", "-D", "--delete"]),
+        "stash" => flag(&["drop", "clear"]),
+        "clean" | "rebase" | "restore" | "filter-branch" | "filter-repo" => true,
+        _ => false,
+    }
+}
+
+/// Git's subcommand and the arguments after it, past any global options,
+/// e.g. `reset` and `--hard` for `git -C repo reset --hard`.
+fn git_subcommand<'a, 'b>(args: &'b [&'a str]) -> Option<(&'a str, &'b [&'a str])> {
+    let mut index = 0;
+    while let Some(arg) = args.get(index) {
+        if GIT_OPTIONS.contains(arg) {
+            index += 2;
+        } else if arg.starts_with('-') {
+            index += 1;
+        } else {
+            return Some((*arg, &args[index + 1..]));
+        }
+    }
+    None
+}
+
+/// The script a shell runs with `-c`, e.g. `rm -rf /` for
+/// `sh -c 'rm -rf /'`, if any.
+fn shell_script<'a>(args: &[&'a str]) -> Option<&'a str> {
+    let mut args = args.iter();
+    let mut script = false;
+    while let Some(arg) = args.next() {
+        match *arg {
+            "-o" | "+o" | "-O" | "+O" => {
+                args.next();
+            }
+            "--" => return args.next().copied().filter(|_| script),
+            option if option.starts_with("--") || option.starts_with('+') => {}
+            option if option.starts_with('-') => script |= option.contains('c'),
+            operand => return script.then_some(operand),
+        }
+    }
+    None
+}
+
+/// Split a command line into its commands at unquoted `;`, `&`, `|`, `&&`,
+/// `||`, and newlines.
+pub(crate) fn split_commands(command: &str) -> Vec<String> {
+    let mut commands = Vec::new();
+    let mut current = String::new();
+    let mut quote = None;
+    let mut chars = command.chars();
+    while let Some(c) = chars.next() {
+        match (c, quote) {
+            ('\\', q) if q != Some('\'') => {
+                current.push(c);
+                current.extend(chars.next());
+                continue;
+            }
+            ('\'' | '"', None) => quote = Some(c),
+            (c, Some(q)) if c == q => quote = None,
+            (';' | '&' | '|' | '\n', None) => {
+                // `>&` and `&>` redirect rather than separate.
+                if c == '&' && (current.ends_with('>') || chars.as_str().starts_with('>')) {
+                    current.push(c);
+                    continue;
+                }
+                commands.push(std::mem::take(&mut current));
+                continue;
+            }
+            _ => {}
+        }
+        current.push(c);
+    }
+    commands.push(current);
+    commands.retain(|command| !command.trim().is_empty());
+    commands
+}
+
+/// Split words into a command's arguments and the files it redirects
+/// output to.
+fn split_redirects(words: &[String]) -> (Vec<String>, Vec<String>) {
+    let mut args = Vec::new();
+    let mut redirects = Vec::new();
+    let mut words = words.iter();
+    while let Some(word) = words.next() {
+        let operator = word.trim_start_matches(|c: char| c.is_ascii_digit() || c == '&');
+        let Some(target) = operator
+            .strip_prefix(">>")
+            .or_else(|| operator.strip_prefix('>'))
+        else {
+            args.push(word.clone());
+            continue;
+        };
+        // Duplicating a descriptor, e.g. `2>&1`.
+        if target.starts_with('&') {
+            continue;
+        }
+        if target.is_empty() {
+            redirects.extend(words.next().cloned());
+        } else {
+            redirects.push(target.to_string());
+        }
+    }
+    (args, redirects)
+}
+
+/// The command a wrapper like `env`, `nohup`, or `xargs` runs, if `words`
+/// run one, e.g. `rm -rf /` for `env -u HOME rm -rf /` or
+/// `timeout 5 nice rm -rf /`. Otherwise, or for a bare wrapper, e.g. `env`,
+/// which only prints, `words` unchanged.
+fn unwrap_command(words: &[String]) -> Vec<String> {
+    let Some(start) = words.iter().position(|word| !is_assignment(word)) else {
+        return words.to_vec();
+    };
+    let wrapper = program_name(&words[start]);
+    let Some((_, valued)) = WRAPPERS.iter().find(|(name, _)| *name == wrapper) else {
+        return words.to_vec();
+    };
+    let mut rest = words[start + 1..].iter();
+    let mut command = Vec::new();
+    while let Some(word) = rest.next() {
+        match word.as_str() {
+            "--" => break,
+            // Only looks the command up.
+            "-v" | "-V" if wrapper == "command" => return words.to_vec(),
+            option if valued.contains(&option) => {
+                rest.next();
+            }
+            // The command is given as one string.
+            "-S" | "--split-string" if wrapper == "env" => {
+                let split = rest.next().map(|value| {
+                    shlex::split(value)
+                        .unwrap_or_else(|| value.split_whitespace().map(str::to_string).collect())
+                });
+                command.extend(split.into_iter().flatten());
+                break;
+            }
+            option if option.starts_with('-') => {}
+            _ => {
+                command.push(word.clone());
+                break;
+            }
+        }
+    }
+    command.extend(rest.cloned());
+    // `timeout` is given a duration before the command.
+    if wrapper == "timeout" && !command.is_empty() {
+        command.remove(0);
+    }
+    if command.iter().all(|word| is_assignment(word)) {
+        return words.to_vec();
+    }
+    unwrap_command(&command)
+}
+
+/// The file `sort` writes its output to with `-o`, if any.
+fn sort_output<'a>(args: &[&'a str]) -> Option<&'a str> {
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        if *arg == "-o" || *arg == "--output" {
+            return args.next().copied();
+        }
+        if let Some(path) = arg.strip_prefix("--output=") {
+            return Some(path);
+        }
+        if let Some(path) = arg.strip_prefix("-o") {
+            return Some(path);
+        }
+    }
+    None
+}
+
+/// Whether `word` sets an environment variable, e.g. `RUST_LOG=debug`.
+fn is_assignment(word: &str) -> bool {
+    word.split_once('=').is_some_and(|(name, _)| {
+        !name.is_empty() && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+    })
+}
+
+/// A program's name without its directory, e.g. `rm` for `/bin/rm`.
+fn program_name(program: &str) -> &str {
+    program.rsplit('/').next().unwrap_or(program)
+}
+
+/// `path` with `.` and `..` resolved lexically, or `None` if a relative
+/// path leaves its starting point.
+pub(crate) fn normalize(path: &Path) -> Option<PathBuf> {
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            // `/..` is `/`.
+            Component::ParentDir => {
+                if !normalized.pop() && !path.is_absolute() {
+                    return None;
+                }
+            }
+            component => normalized.push(component),
+        }
+    }
+    Some(normalized)
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn test_classify_commands() {
+        let classifier = RiskClassifier::new().with_working_dir("/work/repo");
+        let bash = |command: &str| classifier.classify("Bash", &json!({ "command": command }));
+
+        assert_eq!(bash("ls -la && git status"), ApprovalRisk::Low);
+        assert_eq!(bash("grep -r foo src | head -5"), ApprovalRisk::Low);
+        assert_eq!(bash("cargo test 2>&1"), ApprovalRisk::Medium);
+        assert_eq!(bash("echo hi > notes.txt"), ApprovalRisk::Medium);
+        assert_eq!(bash("echo hi > ../notes.txt"), ApprovalRisk::High);
+        assert_eq!(bash("cargo build; rm -rf target"), ApprovalRisk::High);
+        assert_eq!(bash("git push --force origin main"), ApprovalRisk::High);
+        assert_eq!(bash("git reset --hard HEAD~1"), ApprovalRisk::High);
+        assert_eq!(bash("curl https://example.com"), ApprovalRisk::High);
+        assert_eq!(bash("echo $(cat ~/.ssh/id_rsa)"), ApprovalRisk::High);
+        assert_eq!(bash("echo 'a; rm -rf /'"), ApprovalRisk::Low);
+        assert_eq!(bash("env"), ApprovalRisk::Low);
+        assert_eq!(bash("env > /etc/environment"), ApprovalRisk::High);
+        assert_eq!(bash("env -i PATH=/bin ls"), ApprovalRisk::Low);
+        assert_eq!(bash("env rm -rf /"), ApprovalRisk::High);
+        assert_eq!(bash("env -u HOME FOO=1 cargo test"), ApprovalRisk::Medium);
+        assert_eq!(bash("env -S 'rm -rf /'"), ApprovalRisk::High);
+        assert_eq!(bash("sort -u names.txt"), ApprovalRisk::Low);
+        assert_eq!(bash("sh -c 'rm -rf /'"), ApprovalRisk::High);
+        assert_eq!(bash("bash -lc 'cd src; ls'"), ApprovalRisk::Low);
+        assert_eq!(bash("zsh -o errexit -c 'cargo test'"), ApprovalRisk::Medium);
+        assert_eq!(bash("sh -c 'ls' > ../out.txt"), ApprovalRisk::High);
+        assert_eq!(bash("bash script.sh"), ApprovalRisk::Medium);
+        assert_eq!(bash("nohup rm -rf / &"), ApprovalRisk::High);
+        assert_eq!(bash("nice -n 10 rm -rf /"), ApprovalRisk::High);
+        assert_eq!(bash("timeout -s KILL 5 rm -rf /"), ApprovalRisk::High);
+        assert_eq!(bash("timeout 5 ls"), ApprovalRisk::Low);
+        assert_eq!(bash("exec rm -rf /"), ApprovalRisk::High);
+        assert_eq!(bash("command rm -rf /"), ApprovalRisk::High);
+        assert_eq!(bash("command -v rm"), ApprovalRisk::Medium);
+        assert_eq!(
+            bash("find . -name '*.o' | xargs -0 rm -rf"),
+            ApprovalRisk::High
+        );
+        assert_eq!(bash("xargs -n 1 -I {} rm {}"), ApprovalRisk::High);
+        assert_eq!(bash("xargs -0 sh -c 'rm -rf /'"), ApprovalRisk::High);
+        assert_eq!(bash("xargs -n 1 wc -l"), ApprovalRisk::Low);
+        assert_eq!(bash("git -C repo reset --hard"), ApprovalRisk::High);
+        assert_eq!(bash("git -c x=y push --force"), ApprovalRisk::High);
+        assert_eq!(bash("git -C repo status"), ApprovalRisk::Low);
+        assert_eq!(bash("git -C push status"), ApprovalRisk::Low);
+        assert_eq!(bash("sort -o names.txt names.txt"), ApprovalRisk::Medium);
+        assert_eq!(bash("sort --output=/etc/passwd names"), ApprovalRisk::High);
+
+        let edit = |path: &str| classifier.classify("Write", &json!({ "file_path": path }));
+        assert_eq!(edit("/work/repo/src/lib.rs"), ApprovalRisk::Medium);
+        assert_eq!(edit("src/../README.md"), ApprovalRisk::Medium);
+        assert_eq!(edit("/work/repo/../other/lib.rs"), ApprovalRisk::High);
+        assert_eq!(edit("/etc/hosts"), ApprovalRisk::High);
+
+        let classifier = RiskClassifier::new();
+        let bash = |command: &str| classifier.classify("Bash", &json!({ "command": command }));
+        assert_eq!(bash("echo x > notes.txt"), ApprovalRisk::Medium);
+        assert_eq!(bash("echo x > /etc/passwd"), ApprovalRisk::High);
+        assert_eq!(bash("echo x > ../notes.txt"), ApprovalRisk::High);
+        assert_eq!(bash("cargo test > /dev/null"), ApprovalRisk::Medium);
+        let edit = |path: &str| classifier.classify("Write", &json!({ "file_path": path }));
+        assert_eq!(edit("src/lib.rs"), ApprovalRisk::Medium);
+        assert_eq!(edit("/work/repo/src/lib.rs"), ApprovalRisk::High);
+
+        assert_eq!(
+            classifier.classify("Read", &json!({ "file_path": "/etc/hosts" })),
+            ApprovalRisk::Low
+        );
+        assert_eq!(
+            classifier.classify("mcp__db__query", &json!({})),
+            ApprovalRisk::High
+        );
+    }
+}
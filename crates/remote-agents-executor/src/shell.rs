@@ -0,0 +1,134 @@
+//! `Executor` implementation running arbitrary commands.
+
+use std::process::Stdio;
+
+use async_trait::async_trait;
+use command_group::AsyncCommandGroup;
+use remote_agents_core::{
+    ExecutionContext,
+    traits::{CommandPreview, Executor, ExecutorError, SpawnedProcess},
+};
+use remote_agents_pty::resolve_executable_path;
+
+use crate::command::{CommandBuilder, CommandParts};
+
+/// Runs sessions as a plain command, e.g. a script or build job.
+///
+/// Stdout and stderr lines are streamed as `Stdout` and `Stderr` events,
+/// and client input is written to the command's stdin. The command runs in
+/// its own process group; there is no graceful interrupt request, so
+/// interrupting a session signals the whole group.
+///
+/// The prompt is appended as the last argument unless disabled with
+/// `with_prompt_arg(false)`. Follow-ups run the command again.
+pub struct ShellExecutor {
+    command: CommandBuilder,
+    prompt_arg: bool,
+}
+
+impl ShellExecutor {
+    /// Create an executor running `command`.
+    #[must_use]
+    pub const fn new(command: CommandBuilder) -> Self {
+        Self {
+            command,
+            prompt_arg: true,
+        }
+    }
+
+    /// Set whether a non-empty prompt is appended as the last argument.
+    #[must_use]
+    pub const fn with_prompt_arg(mut self, prompt_arg: bool) -> Self {
+        self.prompt_arg = prompt_arg;
+        self
+    }
+
+    fn command_parts(&self, prompt: &str) -> Result<CommandParts, ExecutorError> {
+        let parts = if self.prompt_arg && !prompt.is_empty() {
+            self.command.build_follow_up(&[prompt.to_string()])
+        } else {
+            self.command.build_initial()
+        }?;
+        Ok(parts)
+    }
+}
+
+#[async_trait]
+impl Executor for ShellExecutor {
+    async fn spawn(
+        &self,
+        ctx: &ExecutionContext,
+        prompt: &str,
+    ) -> Result<SpawnedProcess, ExecutorError> {
+        let parts = self.command_parts(prompt)?;
+        let program = resolve_executable_path(&parts.program)
+            .await
+            .ok_or_else(|| ExecutorError::ExecutableNotFound(parts.program.clone()))?;
+
+        let mut command = tokio::process::Command::new(program);
+        command
+            .args(&parts.args)
+            .current_dir(&ctx.working_dir)
+            .envs(ctx.secret_env())
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .kill_on_drop(true);
+        Ok(SpawnedProcess::from_child(command.group_spawn()?))
+    }
+
+    async fn spawn_follow_up(
+        &self,
+        ctx: &ExecutionContext,
+        prompt: &str,
+        _session_id: &str,
+    ) -> Result<SpawnedProcess, ExecutorError> {
+        self.spawn(ctx, prompt).await
+    }
+
+    async fn preview(
+        &self,
+        ctx: &ExecutionContext,
+        prompt: &str,
+    ) -> Result<Option<CommandPreview>, ExecutorError> {
+        let preview = self.command_parts(prompt)?.into_preview(ctx).await?;
+        Ok(Some(preview))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use futures::StreamExt;
+    use remote_agents_core::LogMsg;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_spawn_streams_output() {
+        let executor = ShellExecutor::new(
+            CommandBuilder::new("sh").params(["-c", "echo \"built $0\"; echo warn >&2"]),
+        );
+        let ctx = ExecutionContext::new(std::env::temp_dir());
+        let mut process = executor.spawn(&ctx, "release").await.unwrap();
+
+        let mut events: Vec<_> = process
+            .events
+            .take()
+            .unwrap()
+            .map(Result::unwrap)
+            .collect()
+            .await;
+        events.sort_by_key(|msg| matches!(msg, LogMsg::Stderr(_)));
+        assert!(matches!(&events[0], LogMsg::Stdout(s) if s == "built release\n"));
+        assert!(matches!(&events[1], LogMsg::Stderr(s) if s == "warn\n"));
+        assert!(process.child.wait().await.unwrap().success());
+    }
+
+    #[test]
+    fn test_prompt_arg() {
+        let executor = ShellExecutor::new(CommandBuilder::new("make test"));
+        assert_eq!(executor.command_parts("").unwrap().args, ["test"]);
+        let executor = executor.with_prompt_arg(false);
+        assert_eq!(executor.command_parts("ignored").unwrap().args, ["test"]);
+    }
+}
@@ -0,0 +1,162 @@
+//! Approvals asked on the terminal.
+
+use async_trait::async_trait;
+use serde_json::Value;
+use tokio::{
+    io::{AsyncBufRead, AsyncBufReadExt, AsyncRead, AsyncWrite, AsyncWriteExt, BufReader},
+    sync::Mutex,
+};
+
+use crate::{
+    approvals::{ApprovalError, ApprovalHandler, ApprovalResult, ApprovalRisk, ApprovalScope},
+    risk::RiskClassifier,
+};
+
+type Reader = Box<dyn AsyncBufRead + Unpin + Send>;
+type Writer = Box<dyn AsyncWrite + Unpin + Send>;
+
+/// Approval handler asking the user of a CLI on its terminal.
+///
+/// Each call is shown on stderr with its risk and command or path, and
+/// answered on stdin: `y` allows it, `n` denies it, and `a` allows it and
+/// every later call of the same tool. Requests are asked one at a time.
+/// Fails with `ApprovalError::ServiceUnavailable` once stdin is closed.
+pub struct StdioApprovalHandler {
+    io: Mutex<(Reader, Writer)>,
+    classifier: RiskClassifier,
+}
+
+impl StdioApprovalHandler {
+    /// Ask on stdin and stderr.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::with_io(tokio::io::stdin(), tokio::io::stderr())
+    }
+
+    /// Read answers from `reader` and write questions to `writer` instead,
+    /// e.g. for a terminal other than the process's own.
+    #[must_use]
+    pub fn with_io(
+        reader: impl AsyncRead + Unpin + Send + 'static,
+        writer: impl AsyncWrite + Unpin + Send + 'static,
+    ) -> Self {
+        Self {
+            io: Mutex::new((Box::new(BufReader::new(reader)), Box::new(writer))),
+            classifier: RiskClassifier::new(),
+        }
+    }
+
+    /// Judge the risk shown with questions with `classifier`.
+    #[must_use]
+    pub fn with_risk_classifier(mut self, classifier: RiskClassifier) -> Self {
+        self.classifier = classifier;
+        self
+    }
+}
+
+impl Default for StdioApprovalHandler {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[async_trait]
+impl ApprovalHandler for StdioApprovalHandler {
+    async fn request_approval(
+        &self,
+        tool_name: &str,
+        tool_input: Value,
+        _tool_call_id: &str,
+    ) -> Result<ApprovalResult, ApprovalError> {
+        let risk = match self.classifier.classify(tool_name, &tool_input) {
+            ApprovalRisk::Low => "low",
+            ApprovalRisk::Medium => "medium",
+            ApprovalRisk::High => "high",
+        };
+        let subject = ["command", "file_path"]
+            .iter()
+            .find_map(|key| tool_input.get(key).and_then(Value::as_str))
+            .map_or_else(|| tool_input.to_string(), str::to_string);
+        let question = format!("\n{tool_name} ({risk} risk): {subject}\n");
+
+        let mut io = self.io.lock().await;
+        let (reader, writer) = &mut *io;
+        let answer = ask(reader, writer, &question)
+            .await
+            .map_err(|e| ApprovalError::RequestFailed(e.to_string()))?
+            .ok_or(ApprovalError::ServiceUnavailable)?;
+        drop(io);
+        Ok(match answer {
+            Answer::Yes => ApprovalResult::allow(tool_input),
+            Answer::Always => ApprovalResult::Allow {
+                updated_input: tool_input,
+                scope: ApprovalScope::Tool,
+                decided_by: None,
+            },
+            Answer::No => ApprovalResult::Deny {
+                message: "Denied by the user".to_string(),
+                interrupt: None,
+                decided_by: None,
+            },
+        })
+    }
+}
+
+enum Answer {
+    Yes,
+    No,
+    Always,
+}
+
+/// Write `question` and read answers until one is valid, or `None` at the
+/// end of input.
+async fn ask(
+    reader: &mut Reader,
+    writer: &mut Writer,
+    question: &str,
+) -> std::io::Result<Option<Answer>> {
+    writer.write_all(question.as_bytes()).await?;
+    loop {
+        writer
+            .write_all(b"Allow? [y]es / [n]o / [a]lways: ")
+            .await?;
+        writer.flush().await?;
+        let mut line = String::new();
+        if reader.read_line(&mut line).await? == 0 {
+            return Ok(None);
+        }
+        match line.trim().to_lowercase().as_str() {
+            "y" | "yes" => return Ok(Some(Answer::Yes)),
+            "n" | "no" => return Ok(Some(Answer::No)),
+            "a" | "always" => return Ok(Some(Answer::Always)),
+            _ => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_answers_read_from_input() {
+        let handler = StdioApprovalHandler::with_io(&b"maybe\na\nn\n"[..], tokio::io::sink());
+        let approve =
+            |command: &str| handler.request_approval("Bash", json!({ "command": command }), "t1");
+
+        let result = approve("cargo test").await.unwrap();
+        assert!(matches!(
+            result,
+            ApprovalResult::Allow {
+                scope: ApprovalScope::Tool,
+                ..
+            }
+        ));
+        let result = approve("rm -rf target").await.unwrap();
+        assert!(matches!(result, ApprovalResult::Deny { .. }));
+        let result = approve("ls").await;
+        assert!(matches!(result, Err(ApprovalError::ServiceUnavailable)));
+    }
+}
@@ -0,0 +1,377 @@
+//! Detecting agents that have stopped producing output.
+
+use std::{
+    sync::{Arc, Mutex, PoisonError},
+    time::Duration,
+};
+
+use async_trait::async_trait;
+use futures::{FutureExt, StreamExt};
+use json_patch::Patch;
+use remote_agents_core::{
+    ExecutionContext, LogMsg,
+    traits::{CommandPreview, EventStream, Executor, ExecutorError, SpawnedProcess},
+};
+use serde_json::Value;
+use tokio::{sync::oneshot, time::Instant};
+
+use crate::approvals::{ApprovalError, ApprovalHandler, ApprovalResult};
+
+/// Sender half of a process's abort channel, shared by whoever may abort it.
+type SharedAbort = Arc<Mutex<Option<oneshot::Sender<String>>>>;
+
+/// Wraps an executor to report sessions whose agent has gone quiet.
+///
+/// A CLI waiting on a prompt nobody will answer produces no output and
+/// never exits. Once a session's process has produced no event for
+/// `window`, a `Stalled` event is emitted, once per silence. With
+/// `with_interrupt`, the session is also stopped and fails as stalled.
+///
+/// An agent waiting on an approval is silent too; see
+/// `with_pending_approvals` to not count that wait.
+#[derive(Clone)]
+pub struct StallWatchdog<E> {
+    inner: E,
+    window: Duration,
+    interrupt: bool,
+    pending: Option<PendingApprovals>,
+}
+
+impl<E: Executor> StallWatchdog<E> {
+    /// Watch the sessions `inner` spawns for `window` of silence.
+    #[must_use]
+    pub const fn new(inner: E, window: Duration) -> Self {
+        Self {
+            inner,
+            window,
+            interrupt: false,
+            pending: None,
+        }
+    }
+
+    /// Stop stalled sessions instead of only reporting them.
+    #[must_use]
+    pub const fn with_interrupt(mut self) -> Self {
+        self.interrupt = true;
+        self
+    }
+
+    /// Pause the silence window while `pending` has approvals waiting, so
+    /// an agent is not reported while a reviewer decides. Give `inner` the
+    /// approval handler from `PendingApprovals::track`.
+    #[must_use]
+    pub fn with_pending_approvals(mut self, pending: PendingApprovals) -> Self {
+        self.pending = Some(pending);
+        self
+    }
+
+    /// The wrapped executor.
+    #[must_use]
+    pub const fn inner(&self) -> &E {
+        &self.inner
+    }
+
+    fn watch(&self, process: SpawnedProcess) -> SpawnedProcess {
+        watch(process, self.window, self.interrupt, self.pending.clone())
+    }
+}
+
+#[async_trait]
+impl<E: Executor> Executor for StallWatchdog<E> {
+    async fn spawn(
+        &self,
+        ctx: &ExecutionContext,
+        prompt: &str,
+    ) -> Result<SpawnedProcess, ExecutorError> {
+        let process = self.inner.spawn(ctx, prompt).await?;
+        Ok(self.watch(process))
+    }
+
+    async fn spawn_follow_up(
+        &self,
+        ctx: &ExecutionContext,
+        prompt: &str,
+        session_id: &str,
+    ) -> Result<SpawnedProcess, ExecutorError> {
+        let process = self.inner.spawn_follow_up(ctx, prompt, session_id).await?;
+        Ok(self.watch(process))
+    }
+
+    async fn resume(
+        &self,
+        ctx: &ExecutionContext,
+        session_id: &str,
+    ) -> Result<Option<SpawnedProcess>, ExecutorError> {
+        let process = self.inner.resume(ctx, session_id).await?;
+        Ok(process.map(|process| self.watch(process)))
+    }
+
+    async fn preview(
+        &self,
+        ctx: &ExecutionContext,
+        prompt: &str,
+    ) -> Result<Option<CommandPreview>, ExecutorError> {
+        self.inner.preview(ctx, prompt).await
+    }
+}
+
+/// Watch `process`, and any process continuing its session, for `window`
+/// of silence.
+fn watch(
+    mut process: SpawnedProcess,
+    window: Duration,
+    interrupt: bool,
+    pending: Option<PendingApprovals>,
+) -> SpawnedProcess {
+    let abort = interrupt.then(|| {
+        let (abort_tx, abort_rx) = oneshot::channel();
+        let abort: SharedAbort = Arc::new(Mutex::new(Some(abort_tx)));
+        // Keep forwarding the executor's own abort requests.
+        if let Some(inner_rx) = process.abort_rx.replace(abort_rx) {
+            let abort = Arc::clone(&abort);
+            tokio::spawn(async move {
+                if let Ok(reason) = inner_rx.await {
+                    send_abort(&abort, reason);
+                }
+            });
+        }
+        abort
+    });
+    process.events = process
+        .events
+        .take()
+        .map(|events| stall_events(events, window, abort, pending.clone()));
+    process.continuation = process.continuation.take().map(|next| {
+        async move {
+            next.await
+                .map(|process| watch(process, window, interrupt, pending))
+        }
+        .boxed()
+    });
+    process
+}
+
+fn send_abort(abort: &SharedAbort, reason: String) {
+    let sender = abort.lock().unwrap_or_else(PoisonError::into_inner).take();
+    if let Some(sender) = sender {
+        let _ = sender.send(reason);
+    }
+}
+
+/// Approvals being waited on, shared by the approval handler asking for
+/// them and the `StallWatchdog` that should not count the wait as silence.
+///
+/// Sessions whose executor shares the handler are all paused while any
+/// of them waits.
+#[derive(Clone, Default)]
+pub struct PendingApprovals(Arc<Mutex<PendingState>>);
+
+#[derive(Default)]
+struct PendingState {
+    /// Approvals being waited on.
+    count: usize,
+    /// When the last wait ended.
+    settled_at: Option<Instant>,
+}
+
+impl PendingApprovals {
+    /// No approvals pending.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Wrap `handler` to count the approvals it is waiting on.
+    #[must_use]
+    pub fn track(&self, handler: Arc<dyn ApprovalHandler>) -> Arc<dyn ApprovalHandler> {
+        Arc::new(TrackedHandler {
+            inner: handler,
+            pending: self.clone(),
+        })
+    }
+
+    /// Whether an approval is being waited on.
+    #[must_use]
+    pub fn is_pending(&self) -> bool {
+        self.lock().count > 0
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, PendingState> {
+        self.0.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    fn wait(&self) -> PendingWait {
+        self.lock().count += 1;
+        PendingWait(self.clone())
+    }
+
+    /// When a silence since `since` should count from instead, if an
+    /// approval was waited on since: now if one still is.
+    fn resumed_after(&self, since: Instant) -> Option<Instant> {
+        let state = self.lock();
+        if state.count > 0 {
+            return Some(Instant::now());
+        }
+        state.settled_at.filter(|settled| *settled > since)
+    }
+}
+
+/// An approval being waited on, until dropped.
+struct PendingWait(PendingApprovals);
+
+impl Drop for PendingWait {
+    fn drop(&mut self) {
+        let mut state = self.0.lock();
+        state.count -= 1;
+        if state.count == 0 {
+            state.settled_at = Some(Instant::now());
+        }
+    }
+}
+
+/// Handler counting the approvals its inner handler is waiting on.
+struct TrackedHandler {
+    inner: Arc<dyn ApprovalHandler>,
+    pending: PendingApprovals,
+}
+
+#[async_trait]
+impl ApprovalHandler for TrackedHandler {
+    async fn request_approval(
+        &self,
+        tool_name: &str,
+        tool_input: Value,
+        tool_call_id: &str,
+    ) -> Result<ApprovalResult, ApprovalError> {
+        let _wait = self.pending.wait();
+        self.inner
+            .request_approval(tool_name, tool_input, tool_call_id)
+            .await
+    }
+
+    async fn request_modified_approval(
+        &self,
+        tool_name: &str,
+        tool_input: Value,
+        tool_call_id: &str,
+        changes: &Patch,
+    ) -> Result<ApprovalResult, ApprovalError> {
+        let _wait = self.pending.wait();
+        self.inner
+            .request_modified_approval(tool_name, tool_input, tool_call_id, changes)
+            .await
+    }
+}
+
+/// State of the watched stream.
+struct StallState {
+    events: EventStream,
+    /// When the current silence started.
+    quiet_since: Instant,
+    /// Whether the current silence was already reported.
+    stalled: bool,
+}
+
+/// `events`, with a `Stalled` event after `window` without one, and an
+/// abort request through `abort` if given. Time `pending` has approvals
+/// waiting does not count.
+fn stall_events(
+    events: EventStream,
+    window: Duration,
+    abort: Option<SharedAbort>,
+    pending: Option<PendingApprovals>,
+) -> EventStream {
+    let state = StallState {
+        events,
+        quiet_since: Instant::now(),
+        stalled: false,
+    };
+    futures::stream::unfold(state, move |mut state| {
+        let abort = abort.clone();
+        let pending = pending.clone();
+        async move {
+            loop {
+                let deadline = state.quiet_since + window;
+                match tokio::time::timeout_at(deadline, state.events.next()).await {
+                    Ok(Some(event)) => {
+                        state.quiet_since = Instant::now();
+                        state.stalled = false;
+                        return Some((event, state));
+                    }
+                    Ok(None) => return None,
+                    // Already reported; keep waiting for output or exit.
+                    Err(_) if state.stalled => state.quiet_since = Instant::now(),
+                    Err(_) => {
+                        let resumed = pending
+                            .as_ref()
+                            .and_then(|pending| pending.resumed_after(state.quiet_since));
+                        if let Some(resumed) = resumed {
+                            state.quiet_since = resumed;
+                            continue;
+                        }
+                        state.stalled = true;
+                        if let Some(abort) = &abort {
+                            send_abort(
+                                abort,
+                                format!("Agent stalled: no output for {}ms", window.as_millis()),
+                            );
+                        }
+                        let silent_secs = window.as_secs();
+                        return Some((Ok(LogMsg::Stalled { silent_secs }), state));
+                    }
+                }
+            }
+        }
+    })
+    .boxed()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_stall_reported_once_per_silence() {
+        let (tx, rx) = futures::channel::mpsc::unbounded();
+        let (abort_tx, abort_rx) = oneshot::channel();
+        let abort = Arc::new(Mutex::new(Some(abort_tx)));
+        let mut events = stall_events(rx.boxed(), Duration::from_millis(20), Some(abort), None);
+
+        tx.unbounded_send(Ok(LogMsg::Ready)).unwrap();
+        assert!(matches!(events.next().await, Some(Ok(LogMsg::Ready))));
+        assert!(matches!(
+            events.next().await,
+            Some(Ok(LogMsg::Stalled { .. }))
+        ));
+        assert_eq!(abort_rx.await.unwrap(), "Agent stalled: no output for 20ms");
+
+        let next = tokio::spawn(async move { (events.next().await, events) });
+        tokio::time::sleep(Duration::from_millis(70)).await;
+        tx.unbounded_send(Ok(LogMsg::Finished)).unwrap();
+        let (event, mut events) = next.await.unwrap();
+        assert!(matches!(event, Some(Ok(LogMsg::Finished))));
+        drop(tx);
+        assert!(events.next().await.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_pending_approval_pauses_window() {
+        let (tx, rx) = futures::channel::mpsc::unbounded();
+        let pending = PendingApprovals::new();
+        let window = Duration::from_millis(20);
+        let mut events = stall_events(rx.boxed(), window, None, Some(pending.clone()));
+
+        let wait = pending.wait();
+        let next = tokio::time::timeout(Duration::from_millis(70), events.next()).await;
+        assert!(next.is_err());
+        let settled = std::time::Instant::now();
+        drop(wait);
+        assert!(!pending.is_pending());
+        assert!(matches!(
+            events.next().await,
+            Some(Ok(LogMsg::Stalled { .. }))
+        ));
+        assert!(settled.elapsed() >= window);
+        drop(tx);
+    }
+}
@@ -0,0 +1,120 @@
+//! Environment of PTY session shells.
+
+use std::{
+    collections::{BTreeMap, HashSet},
+    ffi::OsString,
+    path::PathBuf,
+};
+
+use portable_pty::CommandBuilder;
+
+/// Which of the daemon's environment variables a session inherits.
+#[derive(Debug, Clone)]
+enum Inherit {
+    All,
+    Only(HashSet<String>),
+}
+
+/// Environment of a PTY session's shell, on top of the daemon's own.
+///
+/// By default the shell inherits the daemon's whole environment. Variables
+/// set here override inherited ones, and directories prepended to `PATH`
+/// are searched first. Nothing here changes the daemon's environment.
+#[derive(Debug, Clone)]
+pub struct PtyEnv {
+    inherit: Inherit,
+    denied: HashSet<String>,
+    vars: BTreeMap<String, String>,
+    path_prepend: Vec<PathBuf>,
+}
+
+impl PtyEnv {
+    /// Inherit the daemon's whole environment.
+    #[must_use]
+    pub fn new() -> Self {
+        Self {
+            inherit: Inherit::All,
+            denied: HashSet::new(),
+            vars: BTreeMap::new(),
+            path_prepend: Vec::new(),
+        }
+    }
+
+    /// Inherit only the daemon's `keys`, e.g. `PATH` and `HOME`, instead of
+    /// its whole environment.
+    #[must_use]
+    pub fn inherit_only<I, T>(keys: I) -> Self
+    where
+        I: IntoIterator<Item = T>,
+        T: Into<String>,
+    {
+        Self {
+            inherit: Inherit::Only(keys.into_iter().map(Into::into).collect()),
+            ..Self::new()
+        }
+    }
+
+    /// Do not inherit the daemon's `keys`, e.g. its own credentials.
+    #[must_use]
+    pub fn with_denied<I, T>(mut self, keys: I) -> Self
+    where
+        I: IntoIterator<Item = T>,
+        T: Into<String>,
+    {
+        self.denied.extend(keys.into_iter().map(Into::into));
+        self
+    }
+
+    /// Set `key` to `value`.
+    #[must_use]
+    pub fn with_var(mut self, key: impl Into<String>, value: impl Into<String>) -> Self {
+        self.vars.insert(key.into(), value.into());
+        self
+    }
+
+    /// Search `dir` for programs before the rest of `PATH`. Directories
+    /// prepended later come first.
+    #[must_use]
+    pub fn with_path_prepend(mut self, dir: impl Into<PathBuf>) -> Self {
+        self.path_prepend.insert(0, dir.into());
+        self
+    }
+
+    /// Apply to `cmd`, which starts with the daemon's environment.
+    pub(crate) fn apply(&self, cmd: &mut CommandBuilder) {
+        if let Inherit::Only(keys) = &self.inherit {
+            let kept: Vec<(&String, OsString)> = keys
+                .iter()
+                .filter_map(|key| Some((key, cmd.get_env(key)?.to_os_string())))
+                .collect();
+            cmd.env_clear();
+            for (key, value) in kept {
+                cmd.env(key, value);
+            }
+        }
+        for key in &self.denied {
+            cmd.env_remove(key);
+        }
+        for (key, value) in &self.vars {
+            cmd.env(key, value);
+        }
+        if !self.path_prepend.is_empty() {
+            let path = cmd.get_env("PATH").map(std::env::split_paths);
+            let dirs = self
+                .path_prepend
+                .iter()
+                .cloned()
+                .chain(path.into_iter().flatten());
+            match std::env::join_paths(dirs) {
+                Ok(path) => cmd.env("PATH", path),
+                Err(e) => tracing::warn!("Cannot prepend to PTY session PATH: {e}"),
+            }
+        }
+    }
+}
+
+impl Default for PtyEnv {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -2,10 +2,15 @@
 //!
 //! Provides:
 //! - `PtyService` - Manage PTY sessions
+//! - `PtyEnv` - Environment of a session's shell
+//! - Asciinema recording of sessions
 //! - Shell detection utilities for Unix and Windows
 
+pub mod env;
+mod recording;
 pub mod service;
 pub mod shell;
 
-pub use service::{PtyError, PtyService};
+pub use env::PtyEnv;
+pub use service::{PtyAttachment, PtyError, PtyExit, PtyService, PtySessionInfo};
 pub use shell::{get_interactive_shell, get_shell_command, resolve_executable_path};
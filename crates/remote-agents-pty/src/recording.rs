@@ -0,0 +1,184 @@
+//! Asciinema recordings of PTY sessions.
+
+use std::{
+    fs::File,
+    io::{self, BufWriter, Write},
+    path::Path,
+    sync::mpsc,
+    thread,
+    time::{Instant, SystemTime, UNIX_EPOCH},
+};
+
+use serde_json::{Value, json};
+
+/// Writes a session to an asciinema v2 `.cast` file: a header, then an
+/// event per output chunk and resize, timed from the start.
+///
+/// Events are written on a thread of their own, so recording never blocks
+/// reading the PTY. The file is flushed whenever that thread catches up,
+/// and finished once the recorder is dropped.
+pub struct CastRecorder {
+    started: Instant,
+    events: mpsc::Sender<(f64, CastEvent)>,
+}
+
+enum CastEvent {
+    Output(Vec<u8>),
+    Resize(u16, u16),
+}
+
+struct CastFile {
+    writer: BufWriter<File>,
+    /// Output bytes of a character split across chunks.
+    partial: Vec<u8>,
+}
+
+impl CastRecorder {
+    /// Create the file at `path` for a `cols` by `rows` terminal.
+    ///
+    /// # Errors
+    /// Returns error if the file or its directory cannot be created, the
+    /// header cannot be written, or the writing thread cannot be started.
+    pub fn create(path: &Path, cols: u16, rows: u16) -> io::Result<Self> {
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        let mut writer = BufWriter::new(File::create(path)?);
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |d| d.as_secs());
+        let header = json!({
+            "version": 2,
+            "width": cols,
+            "height": rows,
+            "timestamp": timestamp,
+            "env": { "TERM": "xterm-256color" },
+        });
+        writeln!(writer, "{header}")?;
+        writer.flush()?;
+        let (events, events_rx) = mpsc::channel();
+        let file = CastFile {
+            writer,
+            partial: Vec::new(),
+        };
+        thread::Builder::new()
+            .name("pty-recording".to_string())
+            .spawn(move || file.write_events(&events_rx))?;
+        Ok(Self {
+            started: Instant::now(),
+            events,
+        })
+    }
+
+    /// Record output `data`.
+    pub fn output(&self, data: &[u8]) {
+        self.send(CastEvent::Output(data.to_vec()));
+    }
+
+    /// Record the terminal being resized to `cols` by `rows`.
+    pub fn resize(&self, cols: u16, rows: u16) {
+        self.send(CastEvent::Resize(cols, rows));
+    }
+
+    fn send(&self, event: CastEvent) {
+        // Nothing is listening once a write has failed, which is reported
+        // by the writing thread.
+        let _ = self
+            .events
+            .send((self.started.elapsed().as_secs_f64(), event));
+    }
+}
+
+impl CastFile {
+    /// Write events until the recorder is dropped or a write fails.
+    fn write_events(mut self, events: &mpsc::Receiver<(f64, CastEvent)>) {
+        while let Ok(event) = events.recv() {
+            let written = std::iter::once(event)
+                .chain(events.try_iter())
+                .try_for_each(|(time, event)| self.write_event(time, event))
+                .and_then(|()| self.writer.flush());
+            if let Err(e) = written {
+                tracing::warn!("Failed to write PTY recording: {e}");
+                return;
+            }
+        }
+    }
+
+    fn write_event(&mut self, time: f64, event: CastEvent) -> io::Result<()> {
+        let (code, data) = match event {
+            CastEvent::Output(data) => {
+                let mut bytes = std::mem::take(&mut self.partial);
+                bytes.extend_from_slice(&data);
+                // Keep an incomplete character at the end for the next
+                // chunk.
+                let complete = match std::str::from_utf8(&bytes) {
+                    Err(e) if e.error_len().is_none() => e.valid_up_to(),
+                    _ => bytes.len(),
+                };
+                self.partial = bytes.split_off(complete);
+                if bytes.is_empty() {
+                    return Ok(());
+                }
+                ("o", String::from_utf8_lossy(&bytes).into_owned())
+            }
+            CastEvent::Resize(cols, rows) => ("r", format!("{cols}x{rows}")),
+        };
+        let event = Value::from(vec![json!(time), json!(code), json!(data)]);
+        writeln!(self.writer, "{event}")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+
+    #[test]
+    fn test_cast_written() {
+        let dir = std::env::temp_dir().join(format!("pty-recording-{}", uuid::Uuid::new_v4()));
+        let path = dir.join("session.cast");
+        let recorder = CastRecorder::create(&path, 80, 24).unwrap();
+        recorder.output(b"$ ls\r\n");
+        // "é" split across chunks.
+        recorder.output(b"caf\xc3");
+        recorder.output(b"\xa9\r\n");
+        recorder.resize(100, 30);
+        drop(recorder);
+
+        let mut lines = Vec::new();
+        for _ in 0..100 {
+            let cast = std::fs::read_to_string(&path).unwrap();
+            lines = cast.lines().map(str::to_string).collect();
+            if lines.len() == 5 {
+                break;
+            }
+            thread::sleep(Duration::from_millis(20));
+        }
+        std::fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(lines.len(), 5, "{lines:?}");
+
+        let header: Value = serde_json::from_str(&lines[0]).unwrap();
+        assert_eq!(header["version"], 2);
+        assert_eq!(header["width"], 80);
+        assert_eq!(header["height"], 24);
+        let events: Vec<(f64, String, String)> = lines[1..]
+            .iter()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert!(events.iter().map(|(time, _, _)| *time).is_sorted());
+        let events: Vec<_> = events
+            .iter()
+            .map(|(_, code, data)| (code.as_str(), data.as_str()))
+            .collect();
+        assert_eq!(
+            events,
+            [
+                ("o", "$ ls\r\n"),
+                ("o", "caf"),
+                ("o", "é\r\n"),
+                ("r", "100x30")
+            ]
+        );
+    }
+}
@@ -1,19 +1,20 @@
 //! PTY session management service.
 
 use std::{
-    collections::HashMap,
+    collections::{HashMap, VecDeque},
     io::{Read, Write},
     path::PathBuf,
-    sync::{Arc, Mutex},
+    sync::{Arc, Mutex, PoisonError},
     thread,
+    time::SystemTime,
 };
 
-use portable_pty::{CommandBuilder, NativePtySystem, PtySize, PtySystem};
+use portable_pty::{CommandBuilder, ExitStatus, NativePtySystem, PtySize, PtySystem};
 use thiserror::Error;
-use tokio::sync::mpsc;
+use tokio::sync::{broadcast, mpsc, oneshot};
 use uuid::Uuid;
 
-use crate::shell::get_interactive_shell;
+use crate::{env::PtyEnv, recording::CastRecorder, shell::get_interactive_shell};
 
 /// PTY error types.
 #[derive(Debug, Error)]
@@ -30,28 +31,162 @@ pub enum PtyError {
     SessionClosed,
 }
 
+/// Default bytes of recent output kept per session.
+const DEFAULT_SCROLLBACK_LIMIT: usize = 64 * 1024;
+
+/// Output chunks buffered per output subscriber before it starts lagging.
+const OUTPUT_CHANNEL_CAPACITY: usize = 1024;
+
+/// Exit notifications buffered per subscriber before it starts lagging.
+const EXIT_CHANNEL_CAPACITY: usize = 64;
+
+/// How a PTY session's shell exited.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PtyExit {
+    pub session_id: Uuid,
+    /// Exit code; 1 if the shell was killed by a signal.
+    pub code: u32,
+    /// Name of the signal that killed the shell, if one did.
+    pub signal: Option<String>,
+}
+
+impl PtyExit {
+    /// Whether the shell exited successfully.
+    #[must_use]
+    pub const fn success(&self) -> bool {
+        self.code == 0 && self.signal.is_none()
+    }
+}
+
+/// A PTY session, as listed by `PtyService::list_sessions`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PtySessionInfo {
+    pub id: Uuid,
+    /// Process ID of the shell, if the platform reports one.
+    pub pid: Option<u32>,
+    pub cols: u16,
+    pub rows: u16,
+    pub working_dir: PathBuf,
+    pub created_at: SystemTime,
+    /// When input was last written or output last read, e.g. for closing
+    /// idle sessions.
+    pub last_activity: SystemTime,
+    /// Clients attached with `PtyService::attach`.
+    pub clients: usize,
+}
+
+/// A client attached to a PTY session with `PtyService::attach`.
+///
+/// Dropping it detaches the client. Unless the service keeps detached
+/// sessions alive, the session is closed when its last client detaches.
+pub struct PtyAttachment {
+    /// Recent output, up to the scrollback limit, to show first.
+    pub scrollback: Vec<u8>,
+    /// Output from the end of `scrollback` on.
+    pub output: broadcast::Receiver<Vec<u8>>,
+    session_id: Uuid,
+    sessions: Arc<Mutex<HashMap<Uuid, PtySession>>>,
+    keep_alive: bool,
+}
+
+impl Drop for PtyAttachment {
+    fn drop(&mut self) {
+        let mut sessions = self.sessions.lock().unwrap_or_else(PoisonError::into_inner);
+        let Some(session) = sessions.get_mut(&self.session_id) else {
+            return;
+        };
+        session.clients = session.clients.saturating_sub(1);
+        if session.clients > 0 || self.keep_alive {
+            return;
+        }
+        let session = sessions.remove(&self.session_id);
+        drop(sessions);
+        tracing::debug!(
+            "Closing PTY {} on its last client detaching",
+            self.session_id
+        );
+        drop(session);
+    }
+}
+
 struct PtySession {
     writer: Box<dyn Write + Send>,
     master: Box<dyn portable_pty::MasterPty + Send>,
     _output_handle: thread::JoinHandle<()>,
     closed: bool,
+    pid: Option<u32>,
+    cols: u16,
+    rows: u16,
+    working_dir: PathBuf,
+    created_at: SystemTime,
+    /// Also updated by the output thread.
+    last_activity: Arc<Mutex<SystemTime>>,
+    /// Also written by the output thread.
+    output: Arc<SessionOutput>,
+    clients: usize,
 }
 
 /// PTY session management service.
 #[derive(Clone)]
 pub struct PtyService {
     sessions: Arc<Mutex<HashMap<Uuid, PtySession>>>,
+    exits: broadcast::Sender<PtyExit>,
+    scrollback_limit: usize,
+    keep_alive: bool,
+    recording_dir: Option<PathBuf>,
 }
 
 impl PtyService {
     /// Create a new PTY service.
     #[must_use]
     pub fn new() -> Self {
+        let (exits, _) = broadcast::channel(EXIT_CHANNEL_CAPACITY);
         Self {
             sessions: Arc::new(Mutex::new(HashMap::new())),
+            exits,
+            scrollback_limit: DEFAULT_SCROLLBACK_LIMIT,
+            keep_alive: false,
+            recording_dir: None,
         }
     }
 
+    /// Record each session to `dir` as an asciinema v2 file named after
+    /// the session ID, e.g. `<id>.cast`, for archiving and replay. Applies
+    /// to sessions created afterwards; failing to create the file fails
+    /// creating the session.
+    #[must_use]
+    pub fn with_recording(mut self, dir: impl Into<PathBuf>) -> Self {
+        self.recording_dir = Some(dir.into());
+        self
+    }
+
+    /// Keep sessions running when their last client detaches, like tmux,
+    /// so long-running jobs survive dropped connections. Detached sessions
+    /// stay until closed or their shell exits.
+    #[must_use]
+    pub const fn with_keep_alive(mut self) -> Self {
+        self.keep_alive = true;
+        self
+    }
+
+    /// Keep up to `bytes` of recent output per session for
+    /// `get_scrollback`, instead of 64 KiB. Applies to sessions created
+    /// afterwards.
+    #[must_use]
+    pub const fn with_scrollback_limit(mut self, bytes: usize) -> Self {
+        self.scrollback_limit = bytes;
+        self
+    }
+
+    /// Subscribe to sessions' shells exiting.
+    ///
+    /// A session is removed once its shell exits, after its output
+    /// receiver has been sent all the output.
+    #[must_use]
+    pub fn subscribe_exits(&self) -> broadcast::Receiver<PtyExit> {
+        self.exits.subscribe()
+    }
+
     /// Create a new PTY session.
     ///
     /// Returns the session ID and a receiver for output data.
@@ -63,10 +198,34 @@ impl PtyService {
         working_dir: PathBuf,
         cols: u16,
         rows: u16,
+    ) -> Result<(Uuid, mpsc::UnboundedReceiver<Vec<u8>>), PtyError> {
+        self.create_session_with_env(working_dir, cols, rows, PtyEnv::default())
+            .await
+    }
+
+    /// Create a new PTY session whose shell has environment `env`.
+    ///
+    /// `TERM` and `COLORTERM` are always set for the terminal.
+    ///
+    /// # Errors
+    /// Returns error if PTY creation fails.
+    pub async fn create_session_with_env(
+        &self,
+        working_dir: PathBuf,
+        cols: u16,
+        rows: u16,
+        env: PtyEnv,
     ) -> Result<(Uuid, mpsc::UnboundedReceiver<Vec<u8>>), PtyError> {
         let session_id = Uuid::new_v4();
         let (output_tx, output_rx) = mpsc::unbounded_channel();
         let shell = get_interactive_shell().await;
+        let created_at = SystemTime::now();
+        let last_activity = Arc::new(Mutex::new(created_at));
+        let output_activity = Arc::clone(&last_activity);
+        let session_dir = working_dir.clone();
+        let (exit_tx, exit_rx) = oneshot::channel();
+        let output = Arc::new(self.session_output(session_id, cols, rows)?);
+        let thread_output = Arc::clone(&output);
 
         let result = tokio::task::spawn_blocking(move || {
             let pty_system = NativePtySystem::default();
@@ -82,6 +241,7 @@ impl PtyService {
 
             let mut cmd = CommandBuilder::new(&shell);
             cmd.cwd(&working_dir);
+            env.apply(&mut cmd);
 
             // Configure shell-specific options
             let shell_name = shell.file_name().and_then(|n| n.to_str()).unwrap_or("");
@@ -98,10 +258,11 @@ impl PtyService {
             cmd.env("TERM", "xterm-256color");
             cmd.env("COLORTERM", "truecolor");
 
-            let child = pty_pair
+            let mut child = pty_pair
                 .slave
                 .spawn_command(cmd)
                 .map_err(|e| PtyError::CreateFailed(e.to_string()))?;
+            let pid = child.process_id();
 
             let writer = pty_pair
                 .master
@@ -119,28 +280,44 @@ impl PtyService {
                     match reader.read(&mut buf) {
                         Ok(0) => break,
                         Ok(n) => {
-                            if output_tx.send(buf[..n].to_vec()).is_err() {
-                                break;
-                            }
+                            touch(&output_activity);
+                            thread_output.record(&buf[..n]);
+                            // Keep reading for subscribers and scrollback
+                            // if the receiver is dropped.
+                            let _ = output_tx.send(buf[..n].to_vec());
                         }
                         Err(_) => break,
                     }
                 }
-                drop(child);
+                // The shell has exited, or will on the PTY closing.
+                match child.wait() {
+                    Ok(status) => {
+                        let _ = exit_tx.send(status);
+                    }
+                    Err(e) => tracing::warn!("Failed to wait for PTY {session_id}: {e}"),
+                }
             });
 
-            Ok::<_, PtyError>((pty_pair.master, writer, output_handle))
+            Ok::<_, PtyError>((pty_pair.master, writer, output_handle, pid))
         })
         .await
         .map_err(|e| PtyError::CreateFailed(e.to_string()))??;
 
-        let (master, writer, output_handle) = result;
+        let (master, writer, output_handle, pid) = result;
 
         let session = PtySession {
             writer,
             master,
             _output_handle: output_handle,
             closed: false,
+            pid,
+            cols,
+            rows,
+            working_dir: session_dir,
+            created_at,
+            last_activity,
+            output,
+            clients: 0,
         };
 
         self.sessions
@@ -148,9 +325,51 @@ impl PtyService {
             .map_err(|e| PtyError::CreateFailed(e.to_string()))?
             .insert(session_id, session);
 
+        // After inserting, so the session cannot be removed first.
+        self.watch_exit(session_id, exit_rx);
+
         Ok((session_id, output_rx))
     }
 
+    /// Output state of a new session, recording it if enabled.
+    fn session_output(
+        &self,
+        session_id: Uuid,
+        cols: u16,
+        rows: u16,
+    ) -> Result<SessionOutput, PtyError> {
+        let recorder = self
+            .recording_dir
+            .as_ref()
+            .map(|dir| CastRecorder::create(&dir.join(format!("{session_id}.cast")), cols, rows))
+            .transpose()
+            .map_err(|e| PtyError::CreateFailed(format!("Failed to start recording: {e}")))?;
+        Ok(SessionOutput::new(self.scrollback_limit, recorder))
+    }
+
+    /// Remove session `session_id` and announce its exit once its shell
+    /// exits.
+    fn watch_exit(&self, session_id: Uuid, exit_rx: oneshot::Receiver<ExitStatus>) {
+        let sessions = Arc::clone(&self.sessions);
+        let exits = self.exits.clone();
+        tokio::spawn(async move {
+            let Ok(status) = exit_rx.await else {
+                return;
+            };
+            sessions
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner)
+                .remove(&session_id);
+            tracing::debug!("PTY {session_id} exited with {status}");
+            // No subscribers is not an error.
+            let _ = exits.send(PtyExit {
+                session_id,
+                code: status.exit_code(),
+                signal: status.signal().map(str::to_string),
+            });
+        });
+    }
+
     /// Write data to a PTY session.
     ///
     /// # Errors
@@ -177,6 +396,7 @@ impl PtyService {
             .writer
             .flush()
             .map_err(|e| PtyError::WriteFailed(e.to_string()))?;
+        touch(&session.last_activity);
 
         Ok(())
     }
@@ -186,12 +406,12 @@ impl PtyService {
     /// # Errors
     /// Returns error if session not found or resize fails.
     pub async fn resize(&self, session_id: Uuid, cols: u16, rows: u16) -> Result<(), PtyError> {
-        let sessions = self
+        let mut sessions = self
             .sessions
             .lock()
             .map_err(|e| PtyError::ResizeFailed(e.to_string()))?;
         let session = sessions
-            .get(&session_id)
+            .get_mut(&session_id)
             .ok_or(PtyError::SessionNotFound(session_id))?;
 
         if session.closed {
@@ -207,6 +427,11 @@ impl PtyService {
                 pixel_height: 0,
             })
             .map_err(|e| PtyError::ResizeFailed(e.to_string()))?;
+        session.cols = cols;
+        session.rows = rows;
+        if let Some(recorder) = &session.output.recorder {
+            recorder.resize(cols, rows);
+        }
 
         Ok(())
     }
@@ -235,6 +460,145 @@ impl PtyService {
             .map(|s| s.contains_key(session_id))
             .unwrap_or(false)
     }
+
+    /// Subscribe to a session's output from now on, besides the receiver
+    /// returned on creation, e.g. for a second viewer or a recorder.
+    ///
+    /// A subscriber more than 1024 chunks behind skips the oldest, with a
+    /// `Lagged` error. The receiver closes when the shell exits.
+    ///
+    /// # Errors
+    /// Returns error if session not found.
+    pub fn subscribe_output(
+        &self,
+        session_id: Uuid,
+    ) -> Result<broadcast::Receiver<Vec<u8>>, PtyError> {
+        self.sessions
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .get(&session_id)
+            .map(|session| session.output.subscribers.subscribe())
+            .ok_or(PtyError::SessionNotFound(session_id))
+    }
+
+    /// Attach a client to a session: its recent output, and its output
+    /// from then on, without gaps or repeats between them.
+    ///
+    /// # Errors
+    /// Returns error if session not found.
+    pub fn attach(&self, session_id: Uuid) -> Result<PtyAttachment, PtyError> {
+        let mut sessions = self.sessions.lock().unwrap_or_else(PoisonError::into_inner);
+        let session = sessions
+            .get_mut(&session_id)
+            .ok_or(PtyError::SessionNotFound(session_id))?;
+        session.clients += 1;
+        let output = Arc::clone(&session.output);
+        drop(sessions);
+        let (scrollback, output) = output.attach();
+        Ok(PtyAttachment {
+            scrollback,
+            output,
+            session_id,
+            sessions: Arc::clone(&self.sessions),
+            keep_alive: self.keep_alive,
+        })
+    }
+
+    /// Recent output of a session, up to the scrollback limit, e.g. to
+    /// replay to a client attaching to it. May start mid-way through a
+    /// character or escape sequence.
+    ///
+    /// # Errors
+    /// Returns error if session not found.
+    pub fn get_scrollback(&self, session_id: Uuid) -> Result<Vec<u8>, PtyError> {
+        let output = self
+            .sessions
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .get(&session_id)
+            .map(|session| Arc::clone(&session.output))
+            .ok_or(PtyError::SessionNotFound(session_id))?;
+        Ok(output.attach().0)
+    }
+
+    /// List open sessions, oldest first.
+    #[must_use]
+    pub fn list_sessions(&self) -> Vec<PtySessionInfo> {
+        let mut sessions: Vec<_> = self
+            .sessions
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .iter()
+            .filter(|(_, session)| !session.closed)
+            .map(|(id, session)| PtySessionInfo {
+                id: *id,
+                pid: session.pid,
+                cols: session.cols,
+                rows: session.rows,
+                working_dir: session.working_dir.clone(),
+                created_at: session.created_at,
+                last_activity: *session
+                    .last_activity
+                    .lock()
+                    .unwrap_or_else(PoisonError::into_inner),
+                clients: session.clients,
+            })
+            .collect();
+        sessions.sort_by_key(|session| session.created_at);
+        sessions
+    }
+}
+
+/// A session's recent output and output subscribers.
+struct SessionOutput {
+    scrollback: Mutex<VecDeque<u8>>,
+    limit: usize,
+    subscribers: broadcast::Sender<Vec<u8>>,
+    recorder: Option<CastRecorder>,
+}
+
+impl SessionOutput {
+    fn new(limit: usize, recorder: Option<CastRecorder>) -> Self {
+        Self {
+            scrollback: Mutex::default(),
+            limit,
+            subscribers: broadcast::channel(OUTPUT_CHANNEL_CAPACITY).0,
+            recorder,
+        }
+    }
+
+    /// Record `data`, send it to subscribers, and append it to the
+    /// scrollback, dropping the oldest bytes past the limit.
+    fn record(&self, data: &[u8]) {
+        if let Some(recorder) = &self.recorder {
+            recorder.output(data);
+        }
+        let mut scrollback = self
+            .scrollback
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+        // Sent under the lock, so `attach` sees each chunk exactly once.
+        let _ = self.subscribers.send(data.to_vec());
+        let data = &data[data.len().saturating_sub(self.limit)..];
+        let excess = (scrollback.len() + data.len()).saturating_sub(self.limit);
+        scrollback.drain(..excess);
+        scrollback.extend(data);
+    }
+
+    /// The scrollback, and a subscription to output after it.
+    fn attach(&self) -> (Vec<u8>, broadcast::Receiver<Vec<u8>>) {
+        let scrollback = self
+            .scrollback
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+        let output = self.subscribers.subscribe();
+        (scrollback.iter().copied().collect(), output)
+    }
+}
+
+/// Record activity on a session now.
+fn touch(last_activity: &Mutex<SystemTime>) {
+    *last_activity.lock().unwrap_or_else(PoisonError::into_inner) = SystemTime::now();
 }
 
 impl Default for PtyService {
@@ -242,3 +606,96 @@ impl Default for PtyService {
         Self::new()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+
+    /// Receive output with `recv` until it contains `text`, returning all
+    /// of it.
+    async fn read_until(mut recv: impl AsyncFnMut() -> Option<Vec<u8>>, text: &str) -> String {
+        let mut read = String::new();
+        tokio::time::timeout(Duration::from_secs(10), async {
+            while !read.contains(text) {
+                let data = recv().await.expect("session output closed");
+                read.push_str(&String::from_utf8_lossy(&data));
+            }
+        })
+        .await
+        .unwrap_or_else(|_| panic!("no {text:?} in output: {read:?}"));
+        read
+    }
+
+    #[tokio::test]
+    async fn test_attach_replays_scrollback() {
+        let service = PtyService::new();
+        let (id, mut output) = service
+            .create_session(std::env::temp_dir(), 80, 24)
+            .await
+            .unwrap();
+        // The result is not in the echoed input, only in the output.
+        service.write(id, b"echo $((6 * 7))\n").await.unwrap();
+        read_until(async || output.recv().await, "42").await;
+
+        let mut attachment = service.attach(id).unwrap();
+        assert!(String::from_utf8_lossy(&attachment.scrollback).contains("42"));
+        service.write(id, b"echo $((7 * 8))\n").await.unwrap();
+        let after = read_until(async || attachment.output.recv().await.ok(), "56").await;
+        assert!(!after.contains("42"));
+        service.close_session(id).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_output_sent_to_every_subscriber() {
+        let service = PtyService::new();
+        let (id, mut output) = service
+            .create_session(std::env::temp_dir(), 80, 24)
+            .await
+            .unwrap();
+        let mut first = service.subscribe_output(id).unwrap();
+        let mut second = service.subscribe_output(id).unwrap();
+        service.write(id, b"echo $((6 * 7))\n").await.unwrap();
+
+        read_until(async || output.recv().await, "42").await;
+        read_until(async || first.recv().await.ok(), "42").await;
+        read_until(async || second.recv().await.ok(), "42").await;
+        service.close_session(id).await.unwrap();
+        assert!(matches!(
+            service.subscribe_output(id),
+            Err(PtyError::SessionNotFound(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_detach_closes_session_unless_kept_alive() {
+        let service = PtyService::new();
+        let (id, _output) = service
+            .create_session(std::env::temp_dir(), 80, 24)
+            .await
+            .unwrap();
+        let first = service.attach(id).unwrap();
+        let second = service.attach(id).unwrap();
+        assert_eq!(service.list_sessions()[0].clients, 2);
+        drop(first);
+        assert!(service.session_exists(&id));
+        drop(second);
+        assert!(!service.session_exists(&id));
+
+        let service = PtyService::new().with_keep_alive();
+        let (id, mut output) = service
+            .create_session(std::env::temp_dir(), 80, 24)
+            .await
+            .unwrap();
+        drop(service.attach(id).unwrap());
+        assert!(service.session_exists(&id));
+        assert_eq!(service.list_sessions()[0].clients, 0);
+        // Still running, and reattachable.
+        service.write(id, b"echo $((6 * 7))\n").await.unwrap();
+        read_until(async || output.recv().await, "42").await;
+        let attachment = service.attach(id).unwrap();
+        assert!(String::from_utf8_lossy(&attachment.scrollback).contains("42"));
+        service.close_session(id).await.unwrap();
+    }
+}
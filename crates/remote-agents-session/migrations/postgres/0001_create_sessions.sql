@@ -0,0 +1,67 @@
+CREATE TABLE sessions (
+    id UUID PRIMARY KEY,
+    context JSONB NOT NULL,
+    working_dir TEXT NOT NULL,
+    prompt TEXT,
+    status TEXT NOT NULL,
+    agent_session_id TEXT,
+    parent_session_id UUID,
+    run_id TEXT,
+    exit_code INTEGER,
+    status_reason TEXT,
+    created_at BIGINT NOT NULL,
+    updated_at BIGINT NOT NULL
+);
+
+CREATE INDEX idx_sessions_status ON sessions (status);
+CREATE INDEX idx_sessions_created_at ON sessions (created_at);
+CREATE INDEX idx_sessions_working_dir ON sessions (working_dir);
+CREATE INDEX idx_sessions_parent_session_id ON sessions (parent_session_id);
+CREATE INDEX idx_sessions_run_id ON sessions (run_id);
+
+-- Output is stored as one chunk per append, concatenated on read.
+CREATE TABLE session_output (
+    id BIGSERIAL PRIMARY KEY,
+    session_id UUID NOT NULL,
+    data BYTEA NOT NULL
+);
+
+CREATE INDEX idx_session_output_session_id ON session_output (session_id, id);
+
+CREATE TABLE session_events (
+    session_id UUID NOT NULL,
+    seq BIGINT NOT NULL,
+    timestamp BIGINT NOT NULL,
+    msg JSONB NOT NULL,
+    PRIMARY KEY (session_id, seq)
+);
+
+CREATE TABLE tool_calls (
+    id BIGSERIAL PRIMARY KEY,
+    session_id UUID NOT NULL,
+    tool_call_id TEXT NOT NULL,
+    tool_name TEXT NOT NULL,
+    input JSONB NOT NULL,
+    outcome TEXT NOT NULL,
+    decided_by TEXT,
+    reason TEXT,
+    requested_at BIGINT NOT NULL,
+    decided_at BIGINT NOT NULL
+);
+
+CREATE INDEX idx_tool_calls_session_id ON tool_calls (session_id, id);
+
+-- Publish session changes for `PostgresStorage::watch`.
+CREATE FUNCTION notify_session_change() RETURNS trigger AS $$
+BEGIN
+    PERFORM pg_notify(
+        'remote_agents_sessions',
+        json_build_object('session_id', NEW.id, 'status', NEW.status)::text
+    );
+    RETURN NEW;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER sessions_notify
+    AFTER INSERT OR UPDATE ON sessions
+    FOR EACH ROW EXECUTE FUNCTION notify_session_change();
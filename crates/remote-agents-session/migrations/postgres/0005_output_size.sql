@@ -0,0 +1,3 @@
+-- Uncompressed size of a flushed output chunk; NULL for chunks stored
+-- inline and uncompressed, whose size is LENGTH(data).
+ALTER TABLE session_output ADD COLUMN size BIGINT;
@@ -0,0 +1,2 @@
+-- Version of each session's status and context, for optimistic concurrency.
+ALTER TABLE sessions ADD COLUMN version BIGINT NOT NULL DEFAULT 0;
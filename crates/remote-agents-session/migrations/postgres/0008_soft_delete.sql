@@ -0,0 +1,2 @@
+-- Soft-deleted sessions are hidden from listings until restored or purged.
+ALTER TABLE sessions ADD COLUMN deleted BOOLEAN NOT NULL DEFAULT FALSE;
@@ -0,0 +1,52 @@
+CREATE TABLE sessions (
+    id TEXT PRIMARY KEY NOT NULL,
+    context TEXT NOT NULL,
+    working_dir TEXT NOT NULL,
+    prompt TEXT,
+    status TEXT NOT NULL,
+    agent_session_id TEXT,
+    parent_session_id TEXT,
+    run_id TEXT,
+    exit_code INTEGER,
+    status_reason TEXT,
+    created_at INTEGER NOT NULL,
+    updated_at INTEGER NOT NULL
+);
+
+CREATE INDEX idx_sessions_status ON sessions (status);
+CREATE INDEX idx_sessions_created_at ON sessions (created_at);
+CREATE INDEX idx_sessions_working_dir ON sessions (working_dir);
+CREATE INDEX idx_sessions_parent_session_id ON sessions (parent_session_id);
+CREATE INDEX idx_sessions_run_id ON sessions (run_id);
+
+-- Output is stored as one chunk per append, concatenated on read.
+CREATE TABLE session_output (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    session_id TEXT NOT NULL,
+    data BLOB NOT NULL
+);
+
+CREATE INDEX idx_session_output_session_id ON session_output (session_id, id);
+
+CREATE TABLE session_events (
+    session_id TEXT NOT NULL,
+    seq INTEGER NOT NULL,
+    timestamp INTEGER NOT NULL,
+    msg TEXT NOT NULL,
+    PRIMARY KEY (session_id, seq)
+);
+
+CREATE TABLE tool_calls (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    session_id TEXT NOT NULL,
+    tool_call_id TEXT NOT NULL,
+    tool_name TEXT NOT NULL,
+    input TEXT NOT NULL,
+    outcome TEXT NOT NULL,
+    decided_by TEXT,
+    reason TEXT,
+    requested_at INTEGER NOT NULL,
+    decided_at INTEGER NOT NULL
+);
+
+CREATE INDEX idx_tool_calls_session_id ON tool_calls (session_id, id);
@@ -0,0 +1,2 @@
+-- Output chunks moved to a blob store keep only their key; `data` is empty.
+ALTER TABLE session_output ADD COLUMN blob_key TEXT;
@@ -0,0 +1,2 @@
+-- Compression applied to a flushed output chunk (e.g. 'zstd'); NULL if none.
+ALTER TABLE session_output ADD COLUMN compression TEXT;
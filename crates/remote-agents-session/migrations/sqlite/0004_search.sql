@@ -0,0 +1,30 @@
+-- Full-text search over prompts, metadata, and output.
+
+-- One row per session, sharing the session's rowid.
+CREATE VIRTUAL TABLE sessions_fts USING fts5 (prompt, metadata);
+
+-- One row per appended output chunk.
+CREATE VIRTUAL TABLE session_output_fts USING fts5 (session_id UNINDEXED, content);
+
+INSERT INTO sessions_fts (rowid, prompt, metadata)
+SELECT rowid, prompt, json_extract(context, '$.metadata') FROM sessions;
+
+INSERT INTO session_output_fts (session_id, content)
+SELECT session_id, CAST(data AS TEXT) FROM session_output
+WHERE blob_key IS NULL AND compression IS NULL;
+
+CREATE TRIGGER sessions_fts_insert AFTER INSERT ON sessions BEGIN
+    INSERT INTO sessions_fts (rowid, prompt, metadata)
+    VALUES (NEW.rowid, NEW.prompt, json_extract(NEW.context, '$.metadata'));
+END;
+
+CREATE TRIGGER sessions_fts_update AFTER UPDATE OF prompt ON sessions BEGIN
+    UPDATE sessions_fts SET prompt = NEW.prompt WHERE rowid = NEW.rowid;
+END;
+
+-- Only raw appends are indexed; flushed chunks were indexed when appended.
+CREATE TRIGGER session_output_fts_insert AFTER INSERT ON session_output
+WHEN NEW.blob_key IS NULL AND NEW.compression IS NULL BEGIN
+    INSERT INTO session_output_fts (session_id, content)
+    VALUES (NEW.session_id, CAST(NEW.data AS TEXT));
+END;
@@ -0,0 +1,4 @@
+-- Tenant owning each session, for multi-tenant deployments.
+ALTER TABLE sessions ADD COLUMN tenant_id TEXT;
+
+CREATE INDEX idx_sessions_tenant_id ON sessions (tenant_id);
@@ -0,0 +1,17 @@
+-- Indexes for querying tool calls across sessions.
+CREATE INDEX idx_tool_calls_tool_name ON tool_calls (tool_name, decided_at);
+CREATE INDEX idx_tool_calls_decided_at ON tool_calls (decided_at);
+
+CREATE TABLE hook_events (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    session_id TEXT NOT NULL,
+    event TEXT NOT NULL,
+    tool_name TEXT,
+    tool_call_id TEXT,
+    input TEXT NOT NULL,
+    output TEXT,
+    timestamp INTEGER NOT NULL
+);
+
+CREATE INDEX idx_hook_events_session_id ON hook_events (session_id, id);
+CREATE INDEX idx_hook_events_timestamp ON hook_events (timestamp);
@@ -0,0 +1,2 @@
+-- Version of each session's status and context, for optimistic concurrency.
+ALTER TABLE sessions ADD COLUMN version INTEGER NOT NULL DEFAULT 0;
@@ -0,0 +1,3 @@
+-- Input hash and decision latency recorded by auditing approval handlers.
+ALTER TABLE tool_calls ADD COLUMN input_hash TEXT;
+ALTER TABLE tool_calls ADD COLUMN latency_ms INTEGER;
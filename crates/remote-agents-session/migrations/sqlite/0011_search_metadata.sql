@@ -0,0 +1,11 @@
+-- Keep searchable metadata in step with context updates.
+
+UPDATE sessions_fts SET metadata = (
+    SELECT json_extract(context, '$.metadata') FROM sessions
+    WHERE sessions.rowid = sessions_fts.rowid
+);
+
+CREATE TRIGGER sessions_fts_update_context AFTER UPDATE OF context ON sessions BEGIN
+    UPDATE sessions_fts SET metadata = json_extract(NEW.context, '$.metadata')
+    WHERE rowid = NEW.rowid;
+END;
@@ -0,0 +1,2 @@
+-- Milliseconds past each event's timestamp, for timing events within a second.
+ALTER TABLE session_events ADD COLUMN subsec_millis INTEGER NOT NULL DEFAULT 0;
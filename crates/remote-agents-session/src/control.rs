@@ -0,0 +1,210 @@
+//! Process control handles for running sessions.
+
+use std::{
+    sync::{
+        Arc,
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+    },
+    time::Duration,
+};
+
+use tokio::{
+    io::AsyncWriteExt,
+    process::ChildStdin,
+    sync::{mpsc, oneshot, watch},
+};
+
+/// Signal escalation step for a session's process group.
+#[derive(Debug, Clone, Copy)]
+pub(crate) enum KillSignal {
+    Terminate,
+    Kill,
+}
+
+/// Cloneable handle for interrupting a running session.
+///
+/// Interrupting asks the agent to stop gracefully, then escalates to SIGTERM
+/// and finally SIGKILL of the process group, waiting `timeout` for the
+/// process to exit between each step. Interrupting again while an earlier
+/// interrupt is waiting takes the next step at once, so a wedged agent can
+/// be stopped without waiting out the timeouts.
+#[derive(Clone)]
+pub struct InterruptHandle {
+    interrupt_tx: Arc<std::sync::Mutex<Option<oneshot::Sender<()>>>>,
+    signal_tx: mpsc::UnboundedSender<KillSignal>,
+    exited_rx: watch::Receiver<bool>,
+    cancelled: Arc<AtomicBool>,
+    /// Escalation steps started so far, shared by every clone.
+    steps: Arc<AtomicUsize>,
+    /// Notice for the session to report once stopped, set by `stop`.
+    stop_notice: Arc<std::sync::Mutex<Option<String>>>,
+    timeout: Duration,
+}
+
+impl InterruptHandle {
+    pub(crate) fn new(
+        interrupt_tx: Option<oneshot::Sender<()>>,
+        signal_tx: mpsc::UnboundedSender<KillSignal>,
+        exited_rx: watch::Receiver<bool>,
+        cancelled: Arc<AtomicBool>,
+        timeout: Duration,
+    ) -> Self {
+        Self {
+            interrupt_tx: Arc::new(std::sync::Mutex::new(interrupt_tx)),
+            signal_tx,
+            exited_rx,
+            cancelled,
+            steps: Arc::default(),
+            stop_notice: Arc::default(),
+            timeout,
+        }
+    }
+
+    /// Interrupt the session, escalating until its process exits.
+    pub async fn interrupt(&self) {
+        self.cancelled.store(true, Ordering::SeqCst);
+        let mut exited_rx = self.exited_rx.clone();
+
+        loop {
+            let signal = match self.steps.fetch_add(1, Ordering::SeqCst) {
+                0 => {
+                    let interrupt_tx = self
+                        .interrupt_tx
+                        .lock()
+                        .unwrap_or_else(std::sync::PoisonError::into_inner)
+                        .take();
+                    // Without a graceful interrupt, go straight to SIGTERM.
+                    if interrupt_tx.is_some_and(|tx| tx.send(()).is_ok())
+                        && wait_for_exit(&mut exited_rx, self.timeout).await
+                    {
+                        return;
+                    }
+                    continue;
+                }
+                1 => KillSignal::Terminate,
+                2 => KillSignal::Kill,
+                _ => return,
+            };
+            if self.signal_tx.send(signal).is_err()
+                || wait_for_exit(&mut exited_rx, self.timeout).await
+            {
+                return;
+            }
+        }
+    }
+
+    /// Interrupt the session, reporting `notice` after its last output once
+    /// its process has exited.
+    pub(crate) async fn stop(&self, notice: String) {
+        *self
+            .stop_notice
+            .lock()
+            .unwrap_or_else(std::sync::PoisonError::into_inner) = Some(notice);
+        self.interrupt().await;
+    }
+
+    /// The notice recorded by `stop`, if the session was stopped.
+    pub(crate) fn take_stop_notice(&self) -> Option<String> {
+        self.stop_notice
+            .lock()
+            .unwrap_or_else(std::sync::PoisonError::into_inner)
+            .take()
+    }
+
+    /// Replace the graceful interrupt sender, for a new process continuing
+    /// the session.
+    pub(crate) fn set_interrupt_tx(&self, interrupt_tx: Option<oneshot::Sender<()>>) {
+        *self
+            .interrupt_tx
+            .lock()
+            .unwrap_or_else(std::sync::PoisonError::into_inner) = interrupt_tx;
+    }
+
+    /// Whether the session's process has exited.
+    #[must_use]
+    pub fn has_exited(&self) -> bool {
+        *self.exited_rx.borrow()
+    }
+}
+
+/// Wait until a session's process has exited, up to `timeout`.
+///
+/// Returns true if the process exited.
+async fn wait_for_exit(exited_rx: &mut watch::Receiver<bool>, timeout: Duration) -> bool {
+    tokio::time::timeout(timeout, exited_rx.wait_for(|exited| *exited))
+        .await
+        .is_ok()
+}
+
+/// Send a kill signal to a child's process group.
+pub(crate) fn send_signal(child: &mut command_group::AsyncGroupChild, signal: KillSignal) {
+    let result = match signal {
+        #[cfg(unix)]
+        KillSignal::Terminate => {
+            use command_group::{Signal, UnixChildExt};
+            child.signal(Signal::SIGTERM)
+        }
+        #[cfg(not(unix))]
+        KillSignal::Terminate => child.start_kill(),
+        KillSignal::Kill => child.start_kill(),
+    };
+    if let Err(e) = result {
+        tracing::debug!("Failed to send {signal:?} to process group: {e}");
+    }
+}
+
+/// Forward input from a channel to a child's stdin.
+///
+/// The writer stops when every sender is dropped or the pipe closes.
+pub(crate) fn spawn_stdin_writer(mut stdin: ChildStdin) -> mpsc::UnboundedSender<Vec<u8>> {
+    let (input_tx, mut input_rx) = mpsc::unbounded_channel::<Vec<u8>>();
+    tokio::spawn(async move {
+        while let Some(data) = input_rx.recv().await {
+            if let Err(e) = stdin.write_all(&data).await {
+                tracing::debug!("Failed to write session input: {e}");
+                break;
+            }
+            if let Err(e) = stdin.flush().await {
+                tracing::debug!("Failed to flush session input: {e}");
+                break;
+            }
+        }
+    });
+    input_tx
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_second_interrupt_escalates_at_once() {
+        let (interrupt_tx, interrupt_rx) = oneshot::channel();
+        let (signal_tx, mut signal_rx) = mpsc::unbounded_channel();
+        let (_exited_tx, exited_rx) = watch::channel(false);
+        let handle = InterruptHandle::new(
+            Some(interrupt_tx),
+            signal_tx,
+            exited_rx,
+            Arc::default(),
+            Duration::from_secs(60),
+        );
+
+        let interrupt = || {
+            let handle = handle.clone();
+            tokio::spawn(async move { handle.interrupt().await })
+        };
+        let first = interrupt();
+        interrupt_rx.await.unwrap();
+        let second = interrupt();
+        assert!(matches!(
+            signal_rx.recv().await,
+            Some(KillSignal::Terminate)
+        ));
+        let third = interrupt();
+        assert!(matches!(signal_rx.recv().await, Some(KillSignal::Kill)));
+        for task in [first, second, third] {
+            task.abort();
+        }
+    }
+}
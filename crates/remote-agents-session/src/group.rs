@@ -0,0 +1,105 @@
+//! Aggregate status for groups of sessions (batches and runs).
+
+use remote_agents_core::traits::{Session, SessionId, SessionStatus};
+use serde::Serialize;
+use uuid::Uuid;
+
+/// Identifier for a batch of sessions started together.
+pub type BatchId = Uuid;
+
+/// Aggregate status of a batch or run of sessions.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct GroupStatus {
+    /// Sessions in the group.
+    pub sessions: Vec<SessionId>,
+    /// Sessions not yet started (e.g. waiting for their working directory).
+    pub pending: usize,
+    /// Sessions currently running.
+    pub running: usize,
+    /// Sessions that completed successfully.
+    pub completed: usize,
+    /// Sessions that failed.
+    pub failed: usize,
+    /// Sessions that were cancelled.
+    pub cancelled: usize,
+}
+
+impl GroupStatus {
+    /// Tally the statuses of a group's sessions.
+    #[must_use]
+    pub fn from_sessions(sessions: &[Session]) -> Self {
+        let mut status = Self {
+            sessions: sessions.iter().map(|s| s.id).collect(),
+            ..Self::default()
+        };
+        for session in sessions {
+            match session.status {
+                SessionStatus::Pending => status.pending += 1,
+                SessionStatus::Running => status.running += 1,
+                SessionStatus::Completed => status.completed += 1,
+                SessionStatus::Failed => status.failed += 1,
+                SessionStatus::Cancelled => status.cancelled += 1,
+            }
+        }
+        status
+    }
+
+    /// Total number of sessions in the group.
+    #[must_use]
+    pub fn total(&self) -> usize {
+        self.sessions.len()
+    }
+
+    /// Number of sessions that reached a terminal status.
+    #[must_use]
+    pub const fn finished(&self) -> usize {
+        self.completed + self.failed + self.cancelled
+    }
+
+    /// Whether every session in the group has finished.
+    #[must_use]
+    pub fn is_finished(&self) -> bool {
+        self.finished() == self.total()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use remote_agents_core::ExecutionContext;
+
+    use super::*;
+
+    fn session(status: SessionStatus) -> Session {
+        Session {
+            id: Uuid::new_v4(),
+            context: ExecutionContext::new(PathBuf::from("/tmp")),
+            prompt: None,
+            status,
+            agent_session_id: None,
+            parent_session_id: None,
+            run_id: None,
+            tenant_id: None,
+            exit_code: None,
+            status_reason: None,
+            deleted: false,
+            created_at: 0,
+            updated_at: 0,
+            version: 0,
+        }
+    }
+
+    #[test]
+    fn test_from_sessions_tallies_statuses() {
+        let status = GroupStatus::from_sessions(&[
+            session(SessionStatus::Running),
+            session(SessionStatus::Completed),
+            session(SessionStatus::Failed),
+        ]);
+        assert_eq!(status.total(), 3);
+        assert_eq!(status.running, 1);
+        assert_eq!(status.finished(), 2);
+        assert!(!status.is_finished());
+    }
+}
@@ -0,0 +1,178 @@
+//! Importing sessions from Claude's on-disk history.
+
+use std::{collections::HashSet, path::Path};
+
+use remote_agents_core::{
+    ExecutionContext,
+    traits::{SessionFilter, SessionId, SessionStatus, SessionStorage, StorageError},
+};
+use remote_agents_executor::claude::{ClaudeTranscript, history::list_transcripts};
+use serde_json::Value;
+
+/// Metadata key recording the Claude session an imported session was read
+/// from, which is how importing again skips it.
+pub const IMPORTED_FROM_METADATA_KEY: &str = "imported_from_claude";
+
+/// Outcome of importing a directory of transcripts.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct HistoryImport {
+    /// Sessions created by this import.
+    pub imported: Vec<SessionId>,
+    /// Transcripts already imported, or with no conversation.
+    pub skipped: usize,
+}
+
+#[allow(clippy::needless_pass_by_value)]
+fn history_error(e: std::io::Error) -> StorageError {
+    StorageError::Internal(format!("Claude history: {e}"))
+}
+
+/// Import every transcript under `projects_dir` (usually
+/// `claude::history::default_projects_dir()`) as a Completed session.
+///
+/// Each session's output holds its conversation as one `NormalizedEntry`
+/// JSON object per line, rather than the CLI's stream-json, and its agent
+/// session ID is set so it can be followed up. Transcripts imported before
+/// are skipped, so the import can be re-run as Claude records more
+/// sessions. Creation timestamps are the time of the import.
+///
+/// # Errors
+/// Returns error if the transcripts cannot be read or writing to storage
+/// fails.
+pub async fn import_claude_history(
+    storage: &dyn SessionStorage,
+    projects_dir: &Path,
+) -> Result<HistoryImport, StorageError> {
+    let all = SessionFilter {
+        include_deleted: true,
+        ..SessionFilter::default()
+    };
+    let mut seen: HashSet<String> = storage
+        .list(all)
+        .await?
+        .iter()
+        .filter_map(|session| session.context.get_metadata(IMPORTED_FROM_METADATA_KEY))
+        .filter_map(Value::as_str)
+        .map(str::to_string)
+        .collect();
+
+    let mut import = HistoryImport::default();
+    for path in list_transcripts(projects_dir)
+        .await
+        .map_err(history_error)?
+    {
+        let transcript = ClaudeTranscript::read(&path).await.map_err(history_error)?;
+        let is_new = transcript
+            .session_id
+            .as_ref()
+            .is_some_and(|id| seen.insert(id.clone()));
+        if !is_new {
+            import.skipped += 1;
+            continue;
+        }
+        match import_transcript(storage, &transcript).await? {
+            Some(id) => import.imported.push(id),
+            None => import.skipped += 1,
+        }
+    }
+    Ok(import)
+}
+
+/// Import one transcript as a Completed session, returning `None` if it has
+/// no conversation to show.
+///
+/// Unlike `import_claude_history`, this does not check whether the
+/// transcript was imported before.
+///
+/// # Errors
+/// Returns error if writing to storage fails.
+pub async fn import_transcript(
+    storage: &dyn SessionStorage,
+    transcript: &ClaudeTranscript,
+) -> Result<Option<SessionId>, StorageError> {
+    let (Some(session_id), Some(cwd)) = (&transcript.session_id, &transcript.cwd) else {
+        return Ok(None);
+    };
+    let entries = transcript.entries();
+    if entries.is_empty() {
+        return Ok(None);
+    }
+
+    let mut ctx = ExecutionContext::new(cwd.clone());
+    ctx.set_metadata(
+        IMPORTED_FROM_METADATA_KEY,
+        Value::String(session_id.clone()),
+    );
+    let id = storage.create(&ctx).await?;
+    if let Some(prompt) = transcript.prompt() {
+        storage.set_prompt(id, prompt.to_string()).await?;
+    }
+    storage.set_agent_session_id(id, session_id.clone()).await?;
+
+    let mut output = Vec::new();
+    for entry in &entries {
+        serde_json::to_writer(&mut output, entry)
+            .map_err(|e| StorageError::Internal(format!("Claude history: {e}")))?;
+        output.push(b'\n');
+    }
+    storage.append_output(id, &output).await?;
+    storage
+        .update_status(id, SessionStatus::Completed, None)
+        .await?;
+    Ok(Some(id))
+}
+
+#[cfg(test)]
+mod tests {
+    use remote_agents_executor::normalized::NormalizedEntry;
+
+    use super::*;
+    use crate::storage::MemoryStorage;
+
+    #[tokio::test]
+    async fn test_import_claude_history() {
+        let projects =
+            std::env::temp_dir().join(format!("claude-history-{}", uuid::Uuid::new_v4()));
+        let project = projects.join("-src-app");
+        tokio::fs::create_dir_all(&project).await.unwrap();
+        let lines = [
+            r#"{"type":"user","sessionId":"s1","cwd":"/src/app","message":{"content":"hi"}}"#,
+            r#"{"type":"assistant","sessionId":"s1","cwd":"/src/app",
+                "message":{"content":[{"type":"text","text":"Hello"}]}}"#,
+        ];
+        let jsonl = lines.map(|line| line.replace('\n', "")).join("\n");
+        tokio::fs::write(project.join("s1.jsonl"), jsonl)
+            .await
+            .unwrap();
+        tokio::fs::write(project.join("empty.jsonl"), "")
+            .await
+            .unwrap();
+
+        let storage = MemoryStorage::new();
+        let import = import_claude_history(&storage, &projects).await.unwrap();
+        assert_eq!(import.imported.len(), 1);
+        assert_eq!(import.skipped, 1);
+
+        let session = storage.get(import.imported[0]).await.unwrap().unwrap();
+        assert_eq!(session.status, SessionStatus::Completed);
+        assert_eq!(session.prompt.as_deref(), Some("hi"));
+        assert_eq!(session.agent_session_id.as_deref(), Some("s1"));
+        let output = storage.get_output(session.id).await.unwrap();
+        let entries: Vec<NormalizedEntry> = output
+            .split(|&b| b == b'\n')
+            .filter(|line| !line.is_empty())
+            .map(|line| serde_json::from_slice(line).unwrap())
+            .collect();
+        assert_eq!(
+            entries[1],
+            NormalizedEntry::AssistantMessage {
+                content: "Hello".into()
+            }
+        );
+
+        let again = import_claude_history(&storage, &projects).await.unwrap();
+        assert!(again.imported.is_empty());
+        assert_eq!(again.skipped, 2);
+        tokio::fs::remove_dir_all(&projects).await.unwrap();
+    }
+}
@@ -0,0 +1,115 @@
+//! Async callbacks for session status changes.
+
+use std::{future::Future, sync::Arc};
+
+use futures::future::{BoxFuture, join_all};
+use remote_agents_core::traits::{Session, SessionId, SessionStatus, SessionStorage};
+
+/// Callback invoked with a session and its new status.
+pub type StatusHook = Arc<dyn Fn(Session, SessionStatus) -> BoxFuture<'static, ()> + Send + Sync>;
+
+/// Registered status-change callbacks.
+#[derive(Clone, Default)]
+pub(crate) struct StatusHooks {
+    on_status_change: Vec<StatusHook>,
+    on_finished: Vec<StatusHook>,
+}
+
+impl StatusHooks {
+    /// Wrap an async closure as a `StatusHook`.
+    pub(crate) fn hook<F, Fut>(hook: F) -> StatusHook
+    where
+        F: Fn(Session, SessionStatus) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = ()> + Send + 'static,
+    {
+        Arc::new(move |session, status| Box::pin(hook(session, status)))
+    }
+
+    /// Register a callback for every status change.
+    pub(crate) fn push_status_change(&mut self, hook: StatusHook) {
+        self.on_status_change.push(hook);
+    }
+
+    /// Register a callback for terminal status changes.
+    pub(crate) fn push_finished(&mut self, hook: StatusHook) {
+        self.on_finished.push(hook);
+    }
+
+    /// Run the callbacks for a status change in the background.
+    ///
+    /// The session is re-read from storage so callbacks see its latest
+    /// state. Failures to load it are logged and the callbacks skipped.
+    pub(crate) async fn notify<S: SessionStorage + ?Sized>(
+        &self,
+        storage: &S,
+        session_id: SessionId,
+        status: SessionStatus,
+    ) {
+        let finished = if status.is_terminal() {
+            self.on_finished.as_slice()
+        } else {
+            &[]
+        };
+        if self.on_status_change.is_empty() && finished.is_empty() {
+            return;
+        }
+
+        let session = match storage.get(session_id).await {
+            Ok(Some(session)) => session,
+            Ok(None) => return,
+            Err(e) => {
+                tracing::error!("Failed to load session {session_id} for hooks: {e}");
+                return;
+            }
+        };
+
+        let calls: Vec<_> = self
+            .on_status_change
+            .iter()
+            .chain(finished)
+            .map(|hook| hook(session.clone(), status))
+            .collect();
+        tokio::spawn(join_all(calls));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use remote_agents_core::ExecutionContext;
+    use tokio::sync::mpsc;
+
+    use super::*;
+    use crate::storage::memory::MemoryStorage;
+
+    #[tokio::test]
+    async fn test_finished_hooks_only_fire_on_terminal_status() {
+        let storage = MemoryStorage::new();
+        let id = storage
+            .create(&ExecutionContext::new(PathBuf::from("/tmp")))
+            .await
+            .unwrap();
+
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        let mut hooks = StatusHooks::default();
+        let changed = tx.clone();
+        hooks.push_status_change(StatusHooks::hook(move |_, status| {
+            let _ = changed.send(("changed", status));
+            async {}
+        }));
+        hooks.push_finished(StatusHooks::hook(move |session, status| {
+            assert_eq!(session.id, id);
+            let _ = tx.send(("finished", status));
+            async {}
+        }));
+
+        hooks.notify(&storage, id, SessionStatus::Running).await;
+        assert_eq!(rx.recv().await, Some(("changed", SessionStatus::Running)));
+
+        hooks.notify(&storage, id, SessionStatus::Completed).await;
+        assert_eq!(rx.recv().await, Some(("changed", SessionStatus::Completed)));
+        assert_eq!(rx.recv().await, Some(("finished", SessionStatus::Completed)));
+        assert!(rx.try_recv().is_err());
+    }
+}
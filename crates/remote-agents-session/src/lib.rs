@@ -2,9 +2,44 @@
 //!
 //! Provides:
 //! - `SessionManager` - Orchestrate agent sessions
-//! - Storage implementations (memory, SQLite)
+//! - `ExecutorRegistry` - Route sessions to one of several executors
+//! - `RetryPolicy` - Backoff for transient spawn failures
+//! - `UsageReport` - Token and cost usage by day, working directory, or tenant
+//! - `WorkspaceProvisioner` - Isolated git worktrees or copies per session
+//! - `import_claude_history` - Import past sessions from Claude's transcripts
+//! - Storage implementations (memory, SQLite, PostgreSQL, redb, `DynamoDB`)
+//! - Output blob stores (local filesystem, S3-compatible)
+//! - `CachedStorage` - Read-through cache for any storage
+//! - `EncryptedStorage` - Encryption at rest for any storage
+//! - `NamespacedStorage` - Per-tenant isolation over a shared storage
+//! - Session export/import archives for backup and migration
+//! - `migrate_storage` - Copy sessions between storage backends
 
+pub mod control;
+pub mod group;
+pub mod history;
+pub mod hooks;
+pub mod limits;
 pub mod manager;
+pub mod metrics;
+pub mod registry;
+pub mod retry;
 pub mod storage;
+pub mod usage;
+pub mod workspace;
 
-pub use manager::SessionManager;
+pub use control::InterruptHandle;
+pub use group::{BatchId, GroupStatus};
+pub use history::import_claude_history;
+pub use hooks::StatusHook;
+pub use limits::{SessionLimits, UsageTotals};
+pub use manager::{
+    ActiveSessionInfo, AttachedSession, DirectoryLocking, INTERACTIVE_METADATA_KEY,
+    LifecycleEvent, OrphanPolicy, OutputQuota, RERUN_OF_METADATA_KEY, RehydrationReport,
+    SessionManager, USAGE_METADATA_KEY,
+};
+pub use metrics::MetricsSnapshot;
+pub use registry::{CLAUDE_EXECUTOR, CURSOR_EXECUTOR, ExecutorRegistry, OPENCODE_EXECUTOR};
+pub use retry::RetryPolicy;
+pub use usage::{UsageGrouping, UsageReport};
+pub use workspace::{WorkspaceProvisioner, WorkspaceStrategy};
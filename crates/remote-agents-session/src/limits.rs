@@ -0,0 +1,114 @@
+//! Per-session turn, tool call, and cost limits.
+
+use remote_agents_core::traits::{TokenUsage, UsageEvent};
+use serde::{Deserialize, Serialize};
+
+/// Limits enforced on every session by `SessionManager`.
+///
+/// Enforced from the executor's `SpawnedProcess::usage` events; sessions
+/// without usage events are not limited.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct SessionLimits {
+    /// Maximum number of agent turns.
+    pub max_turns: Option<u32>,
+    /// Maximum number of tool calls.
+    pub max_tool_calls: Option<u32>,
+    /// Maximum estimated cost, in US dollars.
+    pub max_cost_usd: Option<f64>,
+}
+
+impl SessionLimits {
+    /// Whether no limit is set.
+    #[must_use]
+    pub const fn is_empty(&self) -> bool {
+        self.max_turns.is_none() && self.max_tool_calls.is_none() && self.max_cost_usd.is_none()
+    }
+}
+
+/// Running usage totals for a session.
+///
+/// Stored in session metadata under `USAGE_METADATA_KEY` once the session
+/// finishes.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct UsageTotals {
+    /// Agent turns.
+    pub turns: u32,
+    /// Tool calls.
+    pub tool_calls: u32,
+    /// Tokens used, including prompt cache reads and writes.
+    pub tokens: TokenUsage,
+    /// Estimated cost, in US dollars.
+    pub cost_usd: f64,
+}
+
+impl UsageTotals {
+    /// Apply a usage event.
+    pub const fn record(&mut self, event: &UsageEvent) {
+        match event {
+            UsageEvent::TurnCompleted => self.turns += 1,
+            UsageEvent::ToolCall { .. } => self.tool_calls += 1,
+            UsageEvent::Tokens(tokens) => self.tokens.add(tokens),
+            UsageEvent::Cost { total_usd } => self.cost_usd = *total_usd,
+        }
+    }
+
+    /// Add another session's totals to these.
+    pub const fn merge(&mut self, other: &Self) {
+        self.turns += other.turns;
+        self.tool_calls += other.tool_calls;
+        self.tokens.add(&other.tokens);
+        self.cost_usd += other.cost_usd;
+    }
+
+    /// Describe the first limit these totals exceed, if any.
+    pub(crate) fn exceeded(&self, limits: &SessionLimits) -> Option<String> {
+        if let Some(max) = limits.max_turns.filter(|&max| self.turns > max) {
+            return Some(format!("Turn limit of {max} exceeded"));
+        }
+        if let Some(max) = limits.max_tool_calls.filter(|&max| self.tool_calls > max) {
+            return Some(format!("Tool call limit of {max} exceeded"));
+        }
+        if let Some(max) = limits.max_cost_usd.filter(|&max| self.cost_usd > max) {
+            return Some(format!("Cost limit of ${max:.2} exceeded"));
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_exceeded() {
+        let limits = SessionLimits {
+            max_turns: Some(2),
+            max_cost_usd: Some(1.0),
+            ..Default::default()
+        };
+        let mut totals = UsageTotals::default();
+
+        totals.record(&UsageEvent::TurnCompleted);
+        totals.record(&UsageEvent::TurnCompleted);
+        totals.record(&UsageEvent::Cost { total_usd: 0.5 });
+        totals.record(&UsageEvent::Tokens(TokenUsage {
+            input_tokens: 100,
+            ..Default::default()
+        }));
+        assert_eq!(totals.exceeded(&limits), None);
+        assert_eq!(totals.tokens.input_tokens, 100);
+
+        totals.record(&UsageEvent::Cost { total_usd: 1.25 });
+        assert_eq!(
+            totals.exceeded(&limits).as_deref(),
+            Some("Cost limit of $1.00 exceeded")
+        );
+
+        totals.record(&UsageEvent::TurnCompleted);
+        assert_eq!(
+            totals.exceeded(&limits).as_deref(),
+            Some("Turn limit of 2 exceeded")
+        );
+    }
+}
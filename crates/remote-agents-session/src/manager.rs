@@ -1,12 +1,62 @@
 //! Session manager for orchestrating agent sessions.
 
-use std::sync::Arc;
+use std::{
+    collections::HashMap,
+    future::Future,
+    path::{Path, PathBuf},
+    sync::{
+        Arc,
+        atomic::{AtomicBool, AtomicI64, AtomicU64, Ordering},
+    },
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
 
+use futures::{FutureExt as _, StreamExt, stream::BoxStream};
 use remote_agents_core::{
-    ExecutionContext, MsgStore,
-    traits::{Executor, ExecutorError, SessionId, SessionStatus, SessionStorage, StorageError},
+    ExecutionContext, LogMsg, MsgStore, SessionState,
+    traits::{
+        CommandPreview, EventSeq, EventStorage, EventStream, Executor, ExecutorError, Session, SessionFilter,
+        SessionId, SessionStatus, SessionStorage, SpawnedProcess, StorageError, StoredEvent,
+        UsageEvent, UsageStream, raw_output_events,
+    },
+};
+use remote_agents_pty::{PtyEnv, PtyError, PtyService};
+use tokio::{
+    sync::{Mutex, OwnedMutexGuard, RwLock, broadcast, mpsc, oneshot, watch},
+    task::JoinHandle,
+};
+
+use uuid::Uuid;
+
+use crate::{
+    control::{InterruptHandle, KillSignal, send_signal, spawn_stdin_writer},
+    group::{BatchId, GroupStatus},
+    hooks::StatusHooks,
+    limits::{SessionLimits, UsageTotals},
+    metrics::{Metrics, MetricsSnapshot},
+    retry::RetryPolicy,
+    usage::{UsageGrouping, UsageReport, session_usage},
+    workspace::{Workspace, WorkspaceError, WorkspaceProvisioner},
 };
-use tokio::sync::RwLock;
+
+/// Metadata key linking a re-run session to the session it re-ran.
+pub const RERUN_OF_METADATA_KEY: &str = "rerun_of";
+
+/// Metadata key marking a session as an interactive PTY terminal.
+pub const INTERACTIVE_METADATA_KEY: &str = "interactive";
+
+/// Metadata key holding a session's `UsageTotals`, for executors that
+/// report usage. Updated as usage is reported.
+pub const USAGE_METADATA_KEY: &str = "usage";
+
+/// Default terminal size for interactive sessions (columns, rows).
+const DEFAULT_PTY_SIZE: (u16, u16) = (80, 24);
+
+/// Default time to wait for each interrupt escalation step.
+const DEFAULT_INTERRUPT_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Amount of persisted output read at a time when replaying a session.
+const REPLAY_CHUNK_SIZE: usize = 1024 * 1024;
 
 /// Session manager error.
 #[derive(Debug, thiserror::Error)]
@@ -19,12 +69,290 @@ pub enum ManagerError {
     NotFound(SessionId),
     #[error("Session already running")]
     AlreadyRunning,
+    #[error("PTY error: {0}")]
+    Pty(#[from] PtyError),
+    #[error("Not an interactive session: {0}")]
+    NotInteractive(SessionId),
+    #[error("Session does not support permission mode changes: {0}")]
+    PermissionModeUnsupported(SessionId),
+    #[error("Workspace error: {0}")]
+    Workspace(#[from] WorkspaceError),
+    #[error("Invalid execution context: {0}")]
+    InvalidContext(String),
+    #[error("Batch not found: {0}")]
+    BatchNotFound(BatchId),
+    #[error("Working directory busy: {0}")]
+    DirectoryBusy(PathBuf),
+    #[error("Session {session_id} ended with status {status:?}")]
+    SessionUnsuccessful {
+        session_id: SessionId,
+        status: SessionStatus,
+    },
+}
+
+/// Session lifecycle event broadcast by `SessionManager`.
+#[derive(Debug, Clone)]
+pub enum LifecycleEvent {
+    /// A session's status changed.
+    StatusChanged {
+        session_id: SessionId,
+        status: SessionStatus,
+    },
+}
+
+/// How to handle a session whose working directory is already in use.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum DirectoryLocking {
+    /// Allow concurrent sessions in the same directory.
+    #[default]
+    Disabled,
+    /// Wait until the running session in the directory finishes.
+    Queue,
+    /// Fail with `ManagerError::DirectoryBusy`.
+    Reject,
+}
+
+/// What to do at startup with sessions a previous process left `Running`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum OrphanPolicy {
+    /// Leave them untouched.
+    #[default]
+    Leave,
+    /// Mark them failed.
+    Fail,
+    /// Reattach those the executor can resume; mark the rest failed.
+    Resume,
+}
+
+/// Limit on how much output is persisted per session.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct OutputQuota {
+    /// Maximum output to persist, measured by `LogMsg::approx_bytes`.
+    pub max_bytes: usize,
+    /// Whether to interrupt and fail the session once the quota is exceeded.
+    pub interrupt: bool,
+}
+
+/// Runtime information about an active or queued session.
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct ActiveSessionInfo {
+    /// Session ID.
+    pub session_id: SessionId,
+    /// Time since the process started, or since queueing while waiting.
+    pub uptime: Duration,
+    /// Bytes of events streamed, measured by `LogMsg::approx_bytes`.
+    pub bytes_streamed: u64,
+    /// When the last event was streamed (Unix epoch seconds).
+    pub last_output_at: Option<i64>,
+    /// Process ID of the agent, if known.
+    pub pid: Option<u32>,
+    /// Position in the working directory queue (1 is next), while waiting.
+    pub queue_position: Option<usize>,
+}
+
+/// Outcome of `SessionManager::rehydrate`.
+#[derive(Debug, Clone, Default)]
+pub struct RehydrationReport {
+    /// Sessions reattached through `Executor::resume`.
+    pub resumed: Vec<SessionId>,
+    /// Orphaned sessions marked as failed.
+    pub failed: Vec<SessionId>,
+}
+
+/// Client-facing handles for a running session, from `SessionManager::attach`.
+pub struct AttachedSession {
+    /// Output history followed by live events.
+    pub stream: BoxStream<'static, Result<LogMsg, std::io::Error>>,
+    /// Sender for raw input to the agent, if it accepts input.
+    pub input_tx: Option<mpsc::UnboundedSender<Vec<u8>>>,
+    /// Handle for interrupting the session.
+    pub interrupt: InterruptHandle,
 }
 
 /// Active session state.
 struct ActiveSession {
     msg_store: Arc<MsgStore>,
-    interrupt_tx: Option<tokio::sync::oneshot::Sender<()>>,
+    input_tx: Option<mpsc::UnboundedSender<Vec<u8>>>,
+    permission_mode_tx: Option<mpsc::UnboundedSender<String>>,
+    interrupt: InterruptHandle,
+    started_at: Instant,
+    pid: Option<u32>,
+    pty_id: Option<Uuid>,
+    stats: Arc<RuntimeStats>,
+    _process_task: JoinHandle<()>,
+}
+
+/// Output counters updated by a session's process task.
+#[derive(Default)]
+struct RuntimeStats {
+    bytes_streamed: AtomicU64,
+    /// Unix epoch seconds of the last event, or 0 if none yet.
+    last_output_at: AtomicI64,
+}
+
+impl RuntimeStats {
+    fn record(&self, msg: &LogMsg) {
+        self.bytes_streamed
+            .fetch_add(msg.approx_bytes() as u64, Ordering::Relaxed);
+        self.last_output_at.store(now(), Ordering::Relaxed);
+    }
+
+    fn last_output_at(&self) -> Option<i64> {
+        Some(self.last_output_at.load(Ordering::Relaxed)).filter(|&t| t != 0)
+    }
+}
+
+/// Version and status of each session as of this manager's last write to
+/// its status or context.
+///
+/// Each write expects the version of the previous one, so it fails with
+/// `StorageError::Conflict` if another manager sharing the storage wrote in
+/// between, instead of overwriting that manager's change. Writes to a
+/// session from this manager are serialized with `lock`, so they do not
+/// conflict with each other.
+#[derive(Default)]
+struct SessionVersions(std::sync::Mutex<HashMap<SessionId, Arc<Mutex<Option<SessionVersion>>>>>);
+
+/// Version the next write to a session must expect, and its status.
+type SessionVersion = (u64, SessionStatus);
+
+impl SessionVersions {
+    fn slots(
+        &self,
+    ) -> std::sync::MutexGuard<'_, HashMap<SessionId, Arc<Mutex<Option<SessionVersion>>>>> {
+        self.0
+            .lock()
+            .unwrap_or_else(std::sync::PoisonError::into_inner)
+    }
+
+    /// Lock a session for a write until the returned guard is dropped.
+    ///
+    /// Sessions this manager has not written to yet, e.g. ones it just
+    /// created, which a storage wrapper may already have written to, or
+    /// orphans of a previous process, are read from storage.
+    async fn lock<S: SessionStorage + ?Sized>(
+        &self,
+        storage: &S,
+        session_id: SessionId,
+    ) -> Result<VersionGuard, StorageError> {
+        let slot = Arc::clone(self.slots().entry(session_id).or_default());
+        let mut guard = slot.lock_owned().await;
+        if guard.is_none() {
+            let session = storage
+                .get(session_id)
+                .await?
+                .ok_or(StorageError::NotFound(session_id))?;
+            *guard = Some((session.version, session.status));
+        }
+        Ok(VersionGuard(guard))
+    }
+
+    /// Stop tracking a session that will not be written to again.
+    fn forget(&self, session_id: SessionId) {
+        self.slots().remove(&session_id);
+    }
+}
+
+/// A session locked for a write by `SessionVersions::lock`.
+struct VersionGuard(OwnedMutexGuard<Option<SessionVersion>>);
+
+impl VersionGuard {
+    /// The version the write must expect, and the session's status.
+    fn current(&self) -> SessionVersion {
+        self.0.unwrap_or((0, SessionStatus::Pending))
+    }
+
+    /// Record a successful write leaving the session with `status`.
+    fn written(&mut self, status: SessionStatus) {
+        let (version, _) = self.current();
+        *self.0 = Some((version + 1, status));
+    }
+}
+
+/// A session's workspace and the provisioner that created it.
+type SessionWorkspace = (Arc<WorkspaceProvisioner>, Workspace);
+
+/// Cleans up a session's workspace when dropped, unless the session started
+/// and its process task took the cleanup over.
+struct WorkspaceGuard(Option<SessionWorkspace>);
+
+impl WorkspaceGuard {
+    /// Leave the workspace to the started session.
+    fn started(mut self) {
+        self.0 = None;
+    }
+}
+
+impl Drop for WorkspaceGuard {
+    fn drop(&mut self) {
+        if let Some(workspace) = self.0.take() {
+            if let Ok(runtime) = tokio::runtime::Handle::try_current() {
+                runtime.spawn(cleanup_workspace(Some(workspace)));
+            }
+        }
+    }
+}
+
+/// Lock and wait queue for a working directory.
+#[derive(Default)]
+struct DirectorySlot {
+    lock: Arc<Mutex<()>>,
+    /// Sessions waiting for the lock, in arrival order.
+    queue: Vec<(SessionId, Instant)>,
+}
+
+type DirectorySlots = std::sync::Mutex<HashMap<PathBuf, DirectorySlot>>;
+
+/// Exclusive use of a working directory, released when dropped.
+struct DirectoryGuard {
+    guard: Option<OwnedMutexGuard<()>>,
+    slots: Arc<DirectorySlots>,
+    key: PathBuf,
+}
+
+impl Drop for DirectoryGuard {
+    fn drop(&mut self) {
+        drop(self.guard.take());
+        let slots = self
+            .slots
+            .lock()
+            .unwrap_or_else(std::sync::PoisonError::into_inner);
+        forget_if_unused(slots, &self.key);
+    }
+}
+
+/// Forget directory `key` once nobody holds or waits for its lock.
+fn forget_if_unused(
+    mut slots: std::sync::MutexGuard<'_, HashMap<PathBuf, DirectorySlot>>,
+    key: &Path,
+) {
+    let unused = slots
+        .get(key)
+        .is_some_and(|slot| Arc::strong_count(&slot.lock) == 1 && slot.queue.is_empty());
+    if unused {
+        slots.remove(key);
+    }
+}
+
+/// Removes a session from its directory's wait queue when dropped.
+struct QueueEntry<'a> {
+    slots: &'a DirectorySlots,
+    key: &'a Path,
+    session_id: SessionId,
+}
+
+impl Drop for QueueEntry<'_> {
+    fn drop(&mut self) {
+        let mut slots = self
+            .slots
+            .lock()
+            .unwrap_or_else(std::sync::PoisonError::into_inner);
+        if let Some(slot) = slots.get_mut(self.key) {
+            slot.queue.retain(|(id, _)| *id != self.session_id);
+        }
+        // A start cancelled while waiting may have been the last user.
+        forget_if_unused(slots, self.key);
+    }
 }
 
 /// Session manager for orchestrating agent sessions.
@@ -33,61 +361,464 @@ where
     S: SessionStorage,
     E: Executor,
 {
-    storage: S,
+    storage: Arc<S>,
     executor: E,
-    active_sessions: RwLock<std::collections::HashMap<SessionId, ActiveSession>>,
+    pty: PtyService,
+    workspaces: Option<Arc<WorkspaceProvisioner>>,
+    event_storage: Option<Arc<dyn EventStorage>>,
+    interrupt_timeout: Duration,
+    directory_locking: DirectoryLocking,
+    retry_policy: RetryPolicy,
+    output_quota: Option<OutputQuota>,
+    limits: SessionLimits,
+    orphan_policy: OrphanPolicy,
+    metrics: Arc<Metrics>,
+    lifecycle_tx: broadcast::Sender<LifecycleEvent>,
+    hooks: StatusHooks,
+    versions: Arc<SessionVersions>,
+    directory_locks: Arc<DirectorySlots>,
+    batches: std::sync::Mutex<HashMap<BatchId, Vec<SessionId>>>,
+    /// Executor state of the sessions started here, shared with their
+    /// follow-ups.
+    session_states: std::sync::Mutex<HashMap<SessionId, SessionState>>,
+    active_sessions: Arc<RwLock<HashMap<SessionId, ActiveSession>>>,
 }
 
 impl<S, E> SessionManager<S, E>
 where
-    S: SessionStorage,
+    S: SessionStorage + 'static,
     E: Executor,
 {
     /// Create a new session manager.
     #[must_use]
     pub fn new(storage: S, executor: E) -> Self {
         Self {
-            storage,
+            storage: Arc::new(storage),
             executor,
-            active_sessions: RwLock::new(std::collections::HashMap::new()),
+            pty: PtyService::new(),
+            workspaces: None,
+            event_storage: None,
+            interrupt_timeout: DEFAULT_INTERRUPT_TIMEOUT,
+            directory_locking: DirectoryLocking::Disabled,
+            retry_policy: RetryPolicy::none(),
+            output_quota: None,
+            limits: SessionLimits::default(),
+            orphan_policy: OrphanPolicy::Leave,
+            metrics: Arc::new(Metrics::default()),
+            lifecycle_tx: broadcast::channel(1024).0,
+            hooks: StatusHooks::default(),
+            versions: Arc::default(),
+            directory_locks: Arc::default(),
+            batches: std::sync::Mutex::new(HashMap::new()),
+            session_states: std::sync::Mutex::new(HashMap::new()),
+            active_sessions: Arc::new(RwLock::new(HashMap::new())),
+        }
+    }
+
+    /// Persist structured session events to the given storage.
+    #[must_use]
+    pub fn with_event_storage(mut self, event_storage: Arc<dyn EventStorage>) -> Self {
+        self.event_storage = Some(event_storage);
+        self
+    }
+
+    /// Use the given PTY service for interactive sessions.
+    #[must_use]
+    pub fn with_pty_service(mut self, pty: PtyService) -> Self {
+        self.pty = pty;
+        self
+    }
+
+    /// Run each new session in its own workspace from the given provisioner.
+    ///
+    /// Follow-ups reuse their original session's workspace, re-creating it
+    /// if it was cleaned up.
+    #[must_use]
+    pub fn with_workspace_provisioner(mut self, provisioner: WorkspaceProvisioner) -> Self {
+        self.workspaces = Some(Arc::new(provisioner));
+        self
+    }
+
+    /// Set how long `interrupt_session` waits at each escalation step.
+    #[must_use]
+    pub const fn with_interrupt_timeout(mut self, timeout: Duration) -> Self {
+        self.interrupt_timeout = timeout;
+        self
+    }
+
+    /// Set whether sessions get exclusive use of their working directory.
+    #[must_use]
+    pub const fn with_directory_locking(mut self, locking: DirectoryLocking) -> Self {
+        self.directory_locking = locking;
+        self
+    }
+
+    /// Set the retry policy for spawning agent processes.
+    #[must_use]
+    pub const fn with_retry_policy(mut self, retry_policy: RetryPolicy) -> Self {
+        self.retry_policy = retry_policy;
+        self
+    }
+
+    /// Limit the output persisted for each session.
+    ///
+    /// Once a session exceeds the quota, a warning is pushed and further
+    /// output is only streamed live, not persisted.
+    #[must_use]
+    pub const fn with_output_quota(mut self, quota: OutputQuota) -> Self {
+        self.output_quota = Some(quota);
+        self
+    }
+
+    /// Set turn, tool call, and cost limits for each session.
+    ///
+    /// A session exceeding a limit is interrupted and marked failed, with
+    /// the limit recorded as its status reason.
+    #[must_use]
+    pub const fn with_limits(mut self, limits: SessionLimits) -> Self {
+        self.limits = limits;
+        self
+    }
+
+    /// Set how `build` reconciles sessions orphaned by a previous process.
+    #[must_use]
+    pub const fn with_orphan_policy(mut self, policy: OrphanPolicy) -> Self {
+        self.orphan_policy = policy;
+        self
+    }
+
+    /// Finish construction, reconciling orphaned sessions per the orphan policy.
+    ///
+    /// Call once at startup, after the other builder methods.
+    ///
+    /// # Errors
+    /// Returns error if reconciling against storage fails.
+    pub async fn build(self) -> Result<Self, ManagerError> {
+        let report = self.reconcile_orphans(self.orphan_policy).await?;
+        if !report.resumed.is_empty() || !report.failed.is_empty() {
+            tracing::info!(
+                "Reconciled orphaned sessions: {} resumed, {} failed",
+                report.resumed.len(),
+                report.failed.len()
+            );
+        }
+        Ok(self)
+    }
+
+    /// Register an async callback for every session status change.
+    #[must_use]
+    pub fn on_status_change<F, Fut>(mut self, hook: F) -> Self
+    where
+        F: Fn(Session, SessionStatus) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = ()> + Send + 'static,
+    {
+        self.hooks.push_status_change(StatusHooks::hook(hook));
+        self
+    }
+
+    /// Register an async callback for when a session reaches a terminal status.
+    #[must_use]
+    pub fn on_finished<F, Fut>(mut self, hook: F) -> Self
+    where
+        F: Fn(Session, SessionStatus) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = ()> + Send + 'static,
+    {
+        self.hooks.push_finished(StatusHooks::hook(hook));
+        self
+    }
+
+    /// Check that a session could start, without spawning it.
+    ///
+    /// Verifies the working directory and environment, then asks the
+    /// executor for the exact command it would run. Returns `None` if the
+    /// executor cannot preview commands.
+    ///
+    /// # Errors
+    /// Returns error if the context is invalid or the command cannot be built.
+    pub async fn validate(
+        &self,
+        ctx: &ExecutionContext,
+        prompt: &str,
+    ) -> Result<Option<CommandPreview>, ManagerError> {
+        if !ctx.working_dir.is_dir() {
+            return Err(ManagerError::InvalidContext(format!(
+                "working directory does not exist: {}",
+                ctx.working_dir.display()
+            )));
+        }
+        for (key, value) in ctx.secret_env() {
+            if key.is_empty() || key.contains(['=', '\0']) || value.contains('\0') {
+                return Err(ManagerError::InvalidContext(format!(
+                    "invalid environment variable: {key:?}"
+                )));
+            }
         }
+
+        Ok(self.executor.preview(ctx, prompt).await?)
     }
 
     /// Start a new session.
     ///
     /// # Errors
-    /// Returns error if session creation or spawn fails.
+    /// Returns error if the working directory is busy, or if session
+    /// creation or spawn fails.
     pub async fn start_session(
         &self,
         ctx: ExecutionContext,
         prompt: &str,
     ) -> Result<SessionId, ManagerError> {
-        let session_id = self.storage.create(&ctx).await?;
-        self.storage
-            .update_status(session_id, SessionStatus::Running)
+        let (mut ctx, workspace) = self.provision_workspace(ctx).await?;
+        let session_id = self.create_session(&mut ctx, prompt, None, None).await?;
+        self.launch(session_id, &ctx, || self.executor.spawn(&ctx, prompt))
+            .await?;
+        workspace.started();
+        Ok(session_id)
+    }
+
+    /// Start a new session as part of a named run.
+    ///
+    /// Follow-ups of the session join the same run.
+    ///
+    /// # Errors
+    /// Returns error if the working directory is busy, or if session
+    /// creation or spawn fails.
+    pub async fn start_session_in_run(
+        &self,
+        run_id: &str,
+        ctx: ExecutionContext,
+        prompt: &str,
+    ) -> Result<SessionId, ManagerError> {
+        let (mut ctx, workspace) = self.provision_workspace(ctx).await?;
+        let session_id = self
+            .create_session(&mut ctx, prompt, Some(run_id), None)
             .await?;
+        self.launch(session_id, &ctx, || self.executor.spawn(&ctx, prompt))
+            .await?;
+        workspace.started();
+        Ok(session_id)
+    }
 
-        let msg_store = Arc::new(MsgStore::new());
-        let process = self.executor.spawn(&ctx, prompt).await?;
+    /// Start an interactive terminal session in the context's working directory.
+    ///
+    /// The shell runs in a PTY from the manager's `PtyService`, with the
+    /// context's secrets in its environment. Its output is streamed and
+    /// recorded like an agent's, input sent through `attach` is written to
+    /// the terminal, and interrupting closes the PTY. The session completes
+    /// when the shell exits.
+    ///
+    /// # Errors
+    /// Returns error if session creation or PTY creation fails.
+    pub async fn start_interactive_session(
+        &self,
+        mut ctx: ExecutionContext,
+    ) -> Result<SessionId, ManagerError> {
+        ctx.set_metadata(INTERACTIVE_METADATA_KEY, serde_json::Value::Bool(true));
+        let session_id = self.storage.create(&ctx).await?;
+        self.set_status(session_id, SessionStatus::Running).await?;
 
-        let active = ActiveSession {
-            msg_store: Arc::clone(&msg_store),
-            interrupt_tx: None, // TODO: Wire up interrupt
+        let (cols, rows) = DEFAULT_PTY_SIZE;
+        let env = ctx
+            .secret_env()
+            .fold(PtyEnv::new(), |env, (key, value)| env.with_var(key, value));
+        let (pty_id, output_rx) = match self
+            .pty
+            .create_session_with_env(ctx.working_dir.clone(), cols, rows, env)
+            .await
+        {
+            Ok(pty) => {
+                self.metrics.session_started();
+                pty
+            }
+            Err(e) => {
+                self.metrics.spawn_failed();
+                self.mark_failed(session_id).await;
+                return Err(e.into());
+            }
         };
 
+        let msg_store = Arc::new(MsgStore::with_redactor(ctx.redactor()));
+        let active = self.spawn_pty_task(session_id, msg_store, pty_id, output_rx);
         self.active_sessions.write().await.insert(session_id, active);
 
-        // TODO: Spawn output forwarding task
+        Ok(session_id)
+    }
+
+    /// Resize the terminal of an interactive session.
+    ///
+    /// # Errors
+    /// Returns error if session not active, not interactive, or resize fails.
+    pub async fn resize_session(
+        &self,
+        session_id: SessionId,
+        cols: u16,
+        rows: u16,
+    ) -> Result<(), ManagerError> {
+        let pty_id = self
+            .active_sessions
+            .read()
+            .await
+            .get(&session_id)
+            .ok_or(ManagerError::NotFound(session_id))?
+            .pty_id
+            .ok_or(ManagerError::NotInteractive(session_id))?;
+        self.pty.resize(pty_id, cols, rows).await?;
+        Ok(())
+    }
+
+    /// Change a running session's permission mode, e.g. from `plan` to
+    /// `acceptEdits`, without restarting it.
+    ///
+    /// # Errors
+    /// Returns error if session not active or its executor does not support
+    /// changing the permission mode.
+    pub async fn set_session_permission_mode(
+        &self,
+        session_id: SessionId,
+        mode: &str,
+    ) -> Result<(), ManagerError> {
+        let sessions = self.active_sessions.read().await;
+        sessions
+            .get(&session_id)
+            .ok_or(ManagerError::NotFound(session_id))?
+            .permission_mode_tx
+            .as_ref()
+            .ok_or(ManagerError::PermissionModeUnsupported(session_id))?
+            .send(mode.to_string())
+            .map_err(|_| ManagerError::PermissionModeUnsupported(session_id))
+    }
+
+    /// Start many sessions at once.
+    ///
+    /// Every session is created before any is started, then all are started
+    /// concurrently. Sessions that fail to start are marked failed and count
+    /// towards the batch's status rather than failing the whole batch. In
+    /// `DirectoryLocking::Queue` mode, this returns once the sessions are
+    /// queued, and each starts in the background when its directory is free.
+    ///
+    /// If creating a session fails, those already created are marked failed
+    /// and their workspaces released.
+    ///
+    /// # Errors
+    /// Returns error if creating the sessions fails.
+    pub async fn start_batch(
+        self: &Arc<Self>,
+        items: Vec<(ExecutionContext, String)>,
+    ) -> Result<BatchId, ManagerError>
+    where
+        E: 'static,
+    {
+        let mut sessions = Vec::with_capacity(items.len());
+        for (ctx, prompt) in items {
+            let created = async {
+                let (mut ctx, workspace) = self.provision_workspace(ctx).await?;
+                let session_id = self.create_session(&mut ctx, &prompt, None, None).await?;
+                Ok::<_, ManagerError>((session_id, ctx, prompt, workspace))
+            };
+            match created.await {
+                Ok(session) => sessions.push(session),
+                Err(e) => {
+                    for (session_id, _, _, _) in sessions {
+                        self.mark_failed(session_id).await;
+                    }
+                    return Err(e);
+                }
+            }
+        }
+
+        let batch_id = BatchId::new_v4();
+        self.batches
+            .lock()
+            .unwrap_or_else(std::sync::PoisonError::into_inner)
+            .insert(batch_id, sessions.iter().map(|(id, _, _, _)| *id).collect());
 
-        drop(process); // Placeholder - should be managed
+        let launches = sessions
+            .into_iter()
+            .map(|(session_id, ctx, prompt, workspace)| {
+                let manager = Arc::clone(self);
+                async move {
+                    match manager
+                        .launch(session_id, &ctx, || manager.executor.spawn(&ctx, &prompt))
+                        .await
+                    {
+                        Ok(()) => workspace.started(),
+                        Err(e) => tracing::warn!(
+                            "Failed to start session {session_id} in batch {batch_id}: {e}"
+                        ),
+                    }
+                }
+            });
+        if self.directory_locking == DirectoryLocking::Queue {
+            for launch in launches {
+                tokio::spawn(launch);
+            }
+        } else {
+            futures::future::join_all(launches).await;
+        }
 
-        Ok(session_id)
+        Ok(batch_id)
+    }
+
+    /// Get the aggregate status of a batch started with `start_batch`.
+    ///
+    /// # Errors
+    /// Returns error if the batch is unknown or storage fails.
+    pub async fn batch_status(&self, batch_id: BatchId) -> Result<GroupStatus, ManagerError> {
+        let session_ids = self
+            .batches
+            .lock()
+            .unwrap_or_else(std::sync::PoisonError::into_inner)
+            .get(&batch_id)
+            .cloned()
+            .ok_or(ManagerError::BatchNotFound(batch_id))?;
+
+        let mut sessions = Vec::with_capacity(session_ids.len());
+        for session_id in session_ids {
+            let session = self
+                .storage
+                .get(session_id)
+                .await?
+                .ok_or(ManagerError::NotFound(session_id))?;
+            sessions.push(session);
+        }
+
+        Ok(GroupStatus::from_sessions(&sessions))
+    }
+
+    /// Get the aggregate status of a run, with its sessions oldest first.
+    ///
+    /// # Errors
+    /// Returns error if storage fails.
+    pub async fn run_status(&self, run_id: &str) -> Result<GroupStatus, ManagerError> {
+        let mut sessions = self
+            .storage
+            .list(SessionFilter {
+                run_id: Some(run_id.to_string()),
+                ..Default::default()
+            })
+            .await?;
+        sessions.sort_by_key(|s| s.created_at);
+        Ok(GroupStatus::from_sessions(&sessions))
+    }
+
+    /// Report the usage of the sessions matching `filter`, grouped by each
+    /// of `group_by`, e.g. cost per tenant per day for the last week.
+    ///
+    /// # Errors
+    /// Returns error if storage fails.
+    pub async fn usage_report(
+        &self,
+        filter: SessionFilter,
+        group_by: &[UsageGrouping],
+    ) -> Result<UsageReport, ManagerError> {
+        let sessions = self.storage.list(filter).await?;
+        Ok(UsageReport::from_sessions(&sessions, group_by))
     }
 
     /// Start a follow-up session.
     ///
     /// # Errors
-    /// Returns error if session not found or spawn fails.
+    /// Returns error if session not found, the working directory is busy,
+    /// or spawn fails.
     pub async fn start_follow_up(
         &self,
         original_session_id: SessionId,
@@ -102,31 +833,125 @@ where
         let agent_session_id = session
             .agent_session_id
             .ok_or(ManagerError::NotFound(original_session_id))?;
+        let workspace = self.reopen_workspace(&session.context).await?;
 
-        let new_session_id = self.storage.create(&session.context).await?;
-        self.storage
-            .update_status(new_session_id, SessionStatus::Running)
+        let mut ctx = session.context;
+        let new_session_id = self
+            .create_session(
+                &mut ctx,
+                prompt,
+                session.run_id.as_deref(),
+                Some(original_session_id),
+            )
             .await?;
+        self.launch(new_session_id, &ctx, || {
+            self.executor.spawn_follow_up(&ctx, prompt, &agent_session_id)
+        })
+        .await?;
+        workspace.started();
+
+        Ok(new_session_id)
+    }
+
+    /// Start a fresh session with another session's context and prompt.
+    ///
+    /// Pass `prompt` to replace the original prompt. The new session joins
+    /// the original's run, and its metadata links back to the original under
+    /// `RERUN_OF_METADATA_KEY`.
+    ///
+    /// # Errors
+    /// Returns error if session not found, it has no prompt to reuse, or
+    /// starting the new session fails.
+    pub async fn rerun(
+        &self,
+        session_id: SessionId,
+        prompt: Option<&str>,
+    ) -> Result<SessionId, ManagerError> {
+        let session = self
+            .storage
+            .get(session_id)
+            .await?
+            .ok_or(ManagerError::NotFound(session_id))?;
+        let prompt = prompt
+            .or(session.prompt.as_deref())
+            .ok_or_else(|| {
+                ManagerError::InvalidContext(format!("session {session_id} has no prompt"))
+            })?;
 
-        let msg_store = Arc::new(MsgStore::new());
-        let process = self
-            .executor
-            .spawn_follow_up(&session.context, prompt, &agent_session_id)
+        let mut ctx = session.context.clone();
+        ctx.set_metadata(
+            RERUN_OF_METADATA_KEY,
+            serde_json::Value::String(session_id.to_string()),
+        );
+        let (mut ctx, workspace) = self.provision_workspace(ctx).await?;
+
+        let new_session_id = self
+            .create_session(&mut ctx, prompt, session.run_id.as_deref(), None)
+            .await?;
+        self.launch(new_session_id, &ctx, || self.executor.spawn(&ctx, prompt))
             .await?;
+        workspace.started();
+        Ok(new_session_id)
+    }
 
-        let active = ActiveSession {
-            msg_store: Arc::clone(&msg_store),
-            interrupt_tx: None,
-        };
+    /// Start a follow-up once the original session completes successfully.
+    ///
+    /// Waits on the lifecycle event bus for the original session to reach a
+    /// terminal status. Spawn the returned future to run it in the background.
+    ///
+    /// # Errors
+    /// Returns error if the original session does not complete successfully,
+    /// or if starting the follow-up fails.
+    pub async fn queue_follow_up(
+        &self,
+        original_session_id: SessionId,
+        prompt: &str,
+    ) -> Result<SessionId, ManagerError> {
+        let mut lifecycle_rx = self.subscribe_lifecycle();
 
-        self.active_sessions
-            .write()
-            .await
-            .insert(new_session_id, active);
+        let mut status = self
+            .storage
+            .get(original_session_id)
+            .await?
+            .ok_or(ManagerError::NotFound(original_session_id))?
+            .status;
+
+        while !status.is_terminal() {
+            match lifecycle_rx.recv().await {
+                Ok(LifecycleEvent::StatusChanged { session_id, status: new_status })
+                    if session_id == original_session_id =>
+                {
+                    status = new_status;
+                }
+                Ok(_) => {}
+                Err(broadcast::error::RecvError::Lagged(_)) => {
+                    status = self
+                        .storage
+                        .get(original_session_id)
+                        .await?
+                        .ok_or(ManagerError::NotFound(original_session_id))?
+                        .status;
+                }
+                Err(broadcast::error::RecvError::Closed) => {
+                    return Err(ManagerError::NotFound(original_session_id));
+                }
+            }
+        }
 
-        drop(process); // Placeholder
+        if status != SessionStatus::Completed {
+            return Err(ManagerError::SessionUnsuccessful {
+                session_id: original_session_id,
+                status,
+            });
+        }
 
-        Ok(new_session_id)
+        self.start_follow_up(original_session_id, prompt).await
+    }
+
+    /// Subscribe to session lifecycle events.
+    #[must_use]
+    pub fn subscribe_lifecycle(&self) -> broadcast::Receiver<LifecycleEvent> {
+        self.lifecycle_tx.subscribe()
     }
 
     /// Get the message store for a session.
@@ -138,17 +963,1653 @@ where
             .map(|s| Arc::clone(&s.msg_store))
     }
 
-    /// Interrupt a running session.
+    /// Attach a client to a running session.
+    ///
+    /// Returns the session's output stream (history first), an input sender
+    /// forwarding to the agent when supported, and an interrupt handle.
     ///
     /// # Errors
-    /// Returns error if session not found.
-    pub async fn interrupt_session(&self, session_id: SessionId) -> Result<(), ManagerError> {
-        let mut sessions = self.active_sessions.write().await;
-        if let Some(session) = sessions.get_mut(&session_id) {
-            if let Some(tx) = session.interrupt_tx.take() {
-                let _ = tx.send(());
-            }
+    /// Returns error if session not active.
+    pub async fn attach(&self, session_id: SessionId) -> Result<AttachedSession, ManagerError> {
+        let (msg_store, input_tx, interrupt) = self
+            .active_sessions
+            .read()
+            .await
+            .get(&session_id)
+            .map(|session| {
+                (
+                    Arc::clone(&session.msg_store),
+                    session.input_tx.clone(),
+                    session.interrupt.clone(),
+                )
+            })
+            .ok_or(ManagerError::NotFound(session_id))?;
+        Ok(AttachedSession {
+            stream: msg_store.history_plus_stream(),
+            input_tx,
+            interrupt,
+        })
+    }
+
+    /// Get a message store for any session, rebuilding it from storage if needed.
+    ///
+    /// Active sessions return their live store. For other sessions, the
+    /// store is replayed from persisted events (or raw output when no event
+    /// storage is configured) and ends with `Finished`.
+    ///
+    /// # Errors
+    /// Returns error if session not found or storage fails.
+    pub async fn load_msg_store(&self, session_id: SessionId) -> Result<Arc<MsgStore>, ManagerError> {
+        if let Some(msg_store) = self.get_msg_store(session_id).await {
+            return Ok(msg_store);
         }
-        Ok(())
+
+        let session = self
+            .storage
+            .get(session_id)
+            .await?
+            .ok_or(ManagerError::NotFound(session_id))?;
+
+        let msg_store = self.rebuild_msg_store(&session).await?;
+        msg_store.push_finished();
+        Ok(Arc::new(msg_store))
+    }
+
+    /// Reconcile sessions left `Running` by a previous process.
+    ///
+    /// Call once at startup. Sessions the executor can resume (via their
+    /// `agent_session_id`) are reattached with a `MsgStore` rebuilt from
+    /// storage so clients can reconnect; the rest are marked failed.
+    ///
+    /// # Errors
+    /// Returns error if listing sessions fails.
+    pub async fn rehydrate(&self) -> Result<RehydrationReport, ManagerError> {
+        self.reconcile_orphans(OrphanPolicy::Resume).await
+    }
+
+    /// Apply an orphan policy to `Running` sessions that are not active.
+    async fn reconcile_orphans(
+        &self,
+        policy: OrphanPolicy,
+    ) -> Result<RehydrationReport, ManagerError> {
+        let mut report = RehydrationReport::default();
+        if policy == OrphanPolicy::Leave {
+            return Ok(report);
+        }
+
+        let running = self
+            .storage
+            .list(SessionFilter {
+                status: Some(SessionStatus::Running),
+                ..Default::default()
+            })
+            .await?;
+
+        for session in running {
+            if self.active_sessions.read().await.contains_key(&session.id) {
+                continue;
+            }
+
+            let resumed = policy == OrphanPolicy::Resume
+                && self.reattach(&session).await.unwrap_or_else(|e| {
+                    tracing::warn!("Failed to reattach session {}: {e}", session.id);
+                    false
+                });
+
+            if resumed {
+                report.resumed.push(session.id);
+            } else {
+                self.set_status(session.id, SessionStatus::Failed).await?;
+                report.failed.push(session.id);
+            }
+        }
+
+        Ok(report)
+    }
+
+    /// List running and queued sessions with their runtime statistics.
+    ///
+    /// Sessions waiting for their working directory have a `queue_position`
+    /// and no process yet.
+    pub async fn list_active(&self) -> Vec<ActiveSessionInfo> {
+        let mut infos: Vec<_> = self
+            .active_sessions
+            .read()
+            .await
+            .iter()
+            .map(|(session_id, session)| ActiveSessionInfo {
+                session_id: *session_id,
+                uptime: session.started_at.elapsed(),
+                bytes_streamed: session.stats.bytes_streamed.load(Ordering::Relaxed),
+                last_output_at: session.stats.last_output_at(),
+                pid: session.pid,
+                queue_position: None,
+            })
+            .collect();
+
+        let slots = self
+            .directory_locks
+            .lock()
+            .unwrap_or_else(std::sync::PoisonError::into_inner);
+        for slot in slots.values() {
+            for (position, (session_id, queued_at)) in slot.queue.iter().enumerate() {
+                infos.push(ActiveSessionInfo {
+                    session_id: *session_id,
+                    uptime: queued_at.elapsed(),
+                    bytes_streamed: 0,
+                    last_output_at: None,
+                    pid: None,
+                    queue_position: Some(position + 1),
+                });
+            }
+        }
+        drop(slots);
+
+        infos
+    }
+
+    /// Get a snapshot of the manager's metrics.
+    #[must_use]
+    pub fn metrics(&self) -> MetricsSnapshot {
+        self.metrics.snapshot()
+    }
+
+    /// Interrupt a running session.
+    ///
+    /// Asks the agent to stop gracefully, then escalates to SIGTERM and
+    /// finally SIGKILL of the process group, waiting the configured
+    /// interrupt timeout for the process to exit between each step.
+    /// Interrupting again while a step is waiting takes the next one at once.
+    ///
+    /// # Errors
+    /// Returns error if session not found.
+    pub async fn interrupt_session(&self, session_id: SessionId) -> Result<(), ManagerError> {
+        self.interrupt_handle(session_id).await?.interrupt().await;
+        Ok(())
+    }
+
+    /// Stop a running session.
+    ///
+    /// Interrupts the agent (escalating to a process group kill as in
+    /// `interrupt_session`), marks the session `Cancelled`, and pushes a
+    /// cancellation notice after its last output.
+    ///
+    /// # Errors
+    /// Returns error if session not active or storage fails.
+    pub async fn stop_session(&self, session_id: SessionId) -> Result<(), ManagerError> {
+        self.interrupt_handle(session_id)
+            .await?
+            .stop("Session cancelled\n".to_string())
+            .await;
+
+        let still_running = self
+            .storage
+            .get(session_id)
+            .await?
+            .is_some_and(|s| !s.status.is_terminal());
+        if still_running {
+            self.set_status(session_id, SessionStatus::Cancelled).await?;
+        }
+        Ok(())
+    }
+
+    async fn interrupt_handle(
+        &self,
+        session_id: SessionId,
+    ) -> Result<InterruptHandle, ManagerError> {
+        self.active_sessions
+            .read()
+            .await
+            .get(&session_id)
+            .map(|s| s.interrupt.clone())
+            .ok_or(ManagerError::NotFound(session_id))
+    }
+
+    /// Push an event to a running session.
+    ///
+    /// The event is broadcast through the session's `MsgStore` and, if event
+    /// storage is configured, persisted for later replay.
+    ///
+    /// # Errors
+    /// Returns error if session not active or persisting fails.
+    pub async fn push_event(&self, session_id: SessionId, msg: LogMsg) -> Result<(), ManagerError> {
+        let msg_store = self
+            .get_msg_store(session_id)
+            .await
+            .ok_or(ManagerError::NotFound(session_id))?;
+
+        let msg = msg_store.redactor().redact_msg(msg);
+        persist_msg(&*self.storage, self.event_storage.as_deref(), session_id, &msg).await?;
+        msg_store.push(msg);
+
+        Ok(())
+    }
+
+    /// Get persisted events for a session.
+    ///
+    /// Returns an empty list if no event storage is configured.
+    ///
+    /// # Errors
+    /// Returns error if the event storage fails.
+    pub async fn get_events(
+        &self,
+        session_id: SessionId,
+        from: EventSeq,
+        limit: Option<usize>,
+    ) -> Result<Vec<StoredEvent>, ManagerError> {
+        match &self.event_storage {
+            Some(event_storage) => Ok(event_storage.get_events(session_id, from, limit).await?),
+            None => Ok(Vec::new()),
+        }
+    }
+
+    /// Read up to `len` bytes of a session's persisted output from byte
+    /// `offset`, so clients can replay long transcripts incrementally.
+    ///
+    /// # Errors
+    /// Returns error if the session does not exist or storage fails.
+    pub async fn get_output_range(
+        &self,
+        session_id: SessionId,
+        offset: u64,
+        len: usize,
+    ) -> Result<Vec<u8>, ManagerError> {
+        Ok(self
+            .storage
+            .get_output_range(session_id, offset, len)
+            .await?)
+    }
+
+    /// Get the length in bytes of a session's persisted output.
+    ///
+    /// # Errors
+    /// Returns error if the session does not exist or storage fails.
+    pub async fn output_len(&self, session_id: SessionId) -> Result<u64, ManagerError> {
+        Ok(self.storage.output_len(session_id).await?)
+    }
+
+    /// Update a session's status and broadcast the change.
+    async fn set_status(
+        &self,
+        session_id: SessionId,
+        status: SessionStatus,
+    ) -> Result<(), StorageError> {
+        update_status(
+            &*self.storage,
+            &self.versions,
+            &self.lifecycle_tx,
+            &self.hooks,
+            session_id,
+            status,
+        )
+        .await
+    }
+
+    /// Create a pending session record, optionally as part of a run, or as
+    /// a follow-up of `parent` sharing its session state. A record that
+    /// cannot be completed is marked failed.
+    async fn create_session(
+        &self,
+        ctx: &mut ExecutionContext,
+        prompt: &str,
+        run_id: Option<&str>,
+        parent: Option<SessionId>,
+    ) -> Result<SessionId, ManagerError> {
+        ctx.session_state = parent
+            .map(|parent| self.session_state(parent))
+            .unwrap_or_default();
+        let session_id = self.storage.create(ctx).await?;
+        self.session_states
+            .lock()
+            .unwrap_or_else(std::sync::PoisonError::into_inner)
+            .insert(session_id, ctx.session_state.clone());
+        let described = async {
+            self.storage
+                .set_prompt(session_id, prompt.to_string())
+                .await?;
+            if let Some(run_id) = run_id {
+                self.storage
+                    .set_run_id(session_id, run_id.to_string())
+                    .await?;
+            }
+            if let Some(parent) = parent {
+                self.storage.set_parent_session_id(session_id, parent).await?;
+            }
+            Ok::<_, StorageError>(())
+        };
+        if let Err(e) = described.await {
+            self.mark_failed(session_id).await;
+            return Err(e.into());
+        }
+        Ok(session_id)
+    }
+
+    /// The executor state of a session, e.g. the tool calls its user chose
+    /// to always allow.
+    fn session_state(&self, session_id: SessionId) -> SessionState {
+        self.session_states
+            .lock()
+            .unwrap_or_else(std::sync::PoisonError::into_inner)
+            .entry(session_id)
+            .or_default()
+            .clone()
+    }
+
+    /// Lock the working directory, spawn the agent, and make the session active.
+    async fn launch<F, Fut>(
+        &self,
+        session_id: SessionId,
+        ctx: &ExecutionContext,
+        spawn: F,
+    ) -> Result<(), ManagerError>
+    where
+        F: FnMut() -> Fut,
+        Fut: Future<Output = Result<SpawnedProcess, ExecutorError>>,
+    {
+        let dir_guard = self
+            .acquire_directory(session_id, &ctx.working_dir)
+            .await?;
+        self.launch_locked(session_id, ctx, spawn, dir_guard).await
+    }
+
+    /// Spawn the agent in a working directory locked by `dir_guard`, and
+    /// make the session active.
+    async fn launch_locked<F, Fut>(
+        &self,
+        session_id: SessionId,
+        ctx: &ExecutionContext,
+        spawn: F,
+        dir_guard: Option<DirectoryGuard>,
+    ) -> Result<(), ManagerError>
+    where
+        F: FnMut() -> Fut,
+        Fut: Future<Output = Result<SpawnedProcess, ExecutorError>>,
+    {
+        self.set_status(session_id, SessionStatus::Running).await?;
+
+        let msg_store = Arc::new(MsgStore::with_redactor(ctx.redactor()));
+        let process = self.retry_policy.run(spawn).await;
+        let process = self.fail_on_spawn_error(session_id, process).await?;
+
+        let workspace = self.session_workspace(ctx);
+        let active = self.spawn_process_task(
+            session_id,
+            msg_store,
+            process,
+            dir_guard,
+            workspace,
+            UsageTotals::default(),
+        );
+        self.active_sessions.write().await.insert(session_id, active);
+        Ok(())
+    }
+
+    /// Give a new session its own workspace, if a provisioner is configured.
+    ///
+    /// The workspace is cleaned up again unless the guard is told that the
+    /// session started.
+    async fn provision_workspace(
+        &self,
+        ctx: ExecutionContext,
+    ) -> Result<(ExecutionContext, WorkspaceGuard), ManagerError> {
+        let ctx = match &self.workspaces {
+            Some(provisioner) => provisioner.provision(&ctx).await?,
+            None => ctx,
+        };
+        let guard = WorkspaceGuard(self.session_workspace(&ctx));
+        Ok((ctx, guard))
+    }
+
+    /// Re-create a session's workspace if it was cleaned up, guarded like
+    /// `provision_workspace`.
+    async fn reopen_workspace(&self, ctx: &ExecutionContext) -> Result<WorkspaceGuard, ManagerError> {
+        let workspace = self.session_workspace(ctx);
+        if let Some((provisioner, workspace)) = &workspace {
+            provisioner.reopen(workspace).await?;
+        }
+        Ok(WorkspaceGuard(workspace))
+    }
+
+    /// The provisioned workspace a session runs in, if any.
+    fn session_workspace(&self, ctx: &ExecutionContext) -> Option<SessionWorkspace> {
+        let provisioner = self.workspaces.as_ref()?;
+        Some((Arc::clone(provisioner), Workspace::from_context(ctx)?))
+    }
+
+    /// Mark a session failed if its process could not be spawned.
+    async fn fail_on_spawn_error(
+        &self,
+        session_id: SessionId,
+        process: Result<SpawnedProcess, ExecutorError>,
+    ) -> Result<SpawnedProcess, ManagerError> {
+        match process {
+            Ok(process) => {
+                self.metrics.session_started();
+                Ok(process)
+            }
+            Err(e) => {
+                self.metrics.spawn_failed();
+                self.mark_failed(session_id).await;
+                Err(e.into())
+            }
+        }
+    }
+
+    /// Mark a session failed, logging if storage cannot be updated.
+    async fn mark_failed(&self, session_id: SessionId) {
+        if let Err(e) = self.set_status(session_id, SessionStatus::Failed).await {
+            tracing::error!("Failed to update status for session {session_id}: {e}");
+        }
+    }
+
+    /// Lock a new session's working directory, marking it failed if busy.
+    async fn acquire_directory(
+        &self,
+        session_id: SessionId,
+        dir: &Path,
+    ) -> Result<Option<DirectoryGuard>, ManagerError> {
+        let guard = self.lock_directory(session_id, dir).await;
+        if guard.is_err() {
+            self.mark_failed(session_id).await;
+        }
+        guard
+    }
+
+    /// Try to resume an orphaned session through the executor.
+    ///
+    /// Returns false if the executor cannot resume it.
+    async fn reattach(&self, session: &Session) -> Result<bool, ManagerError> {
+        let Some(agent_session_id) = &session.agent_session_id else {
+            return Ok(false);
+        };
+        let workspace = self.reopen_workspace(&session.context).await?;
+        let mut ctx = session.context.clone();
+        ctx.session_state = self.session_state(session.id);
+        let Some(process) = self.executor.resume(&ctx, agent_session_id).await?
+        else {
+            return Ok(false);
+        };
+
+        let dir_guard = self
+            .lock_directory(session.id, &session.context.working_dir)
+            .await?;
+        self.resume_locked(session, process, workspace, dir_guard)
+            .await
+            .map(|()| true)
+    }
+
+    /// Continue resumed `process` of `session` in a working directory
+    /// locked by `dir_guard`, and make the session active.
+    async fn resume_locked(
+        &self,
+        session: &Session,
+        process: SpawnedProcess,
+        workspace: WorkspaceGuard,
+        dir_guard: Option<DirectoryGuard>,
+    ) -> Result<(), ManagerError> {
+        let msg_store = Arc::new(self.rebuild_msg_store(session).await?);
+        let active = self.spawn_process_task(
+            session.id,
+            msg_store,
+            process,
+            dir_guard,
+            self.session_workspace(&session.context),
+            session_usage(session).unwrap_or_default(),
+        );
+        self.active_sessions.write().await.insert(session.id, active);
+        workspace.started();
+        Ok(())
+    }
+
+    /// Replay a session's persisted output into a new `MsgStore`.
+    ///
+    /// `Finished` markers are skipped so the store can continue live.
+    async fn rebuild_msg_store(&self, session: &Session) -> Result<MsgStore, ManagerError> {
+        let msg_store = MsgStore::with_redactor(session.context.redactor());
+
+        let events = match &self.event_storage {
+            Some(event_storage) => event_storage.get_events(session.id, 0, None).await?,
+            None => Vec::new(),
+        };
+
+        if events.is_empty() {
+            // Read in chunks, carrying any UTF-8 sequence split by a chunk
+            // boundary over to the next one.
+            let mut offset = 0;
+            let mut pending = Vec::new();
+            loop {
+                let chunk = self
+                    .storage
+                    .get_output_range(session.id, offset, REPLAY_CHUNK_SIZE)
+                    .await?;
+                offset += chunk.len() as u64;
+                let done = chunk.len() < REPLAY_CHUNK_SIZE;
+                pending.extend(chunk);
+
+                let complete = if done {
+                    pending.len()
+                } else {
+                    match std::str::from_utf8(&pending) {
+                        Err(e) if e.error_len().is_none() => e.valid_up_to(),
+                        _ => pending.len(),
+                    }
+                };
+                if complete > 0 {
+                    msg_store.push_stdout(String::from_utf8_lossy(&pending[..complete]));
+                    pending.drain(..complete);
+                }
+                if done {
+                    break;
+                }
+            }
+        } else {
+            for event in events {
+                if !matches!(event.msg, LogMsg::Finished) {
+                    msg_store.push(event.msg);
+                }
+            }
+        }
+
+        Ok(msg_store)
+    }
+
+    /// Acquire exclusive use of a working directory, per the locking mode.
+    ///
+    /// In `Queue` mode the session is listed in the directory's wait queue
+    /// until the lock is acquired.
+    async fn lock_directory(
+        &self,
+        session_id: SessionId,
+        dir: &Path,
+    ) -> Result<Option<DirectoryGuard>, ManagerError> {
+        if self.directory_locking == DirectoryLocking::Disabled {
+            return Ok(None);
+        }
+
+        let key = std::fs::canonicalize(dir).unwrap_or_else(|_| dir.to_path_buf());
+        let lock = {
+            let mut slots = self
+                .directory_locks
+                .lock()
+                .unwrap_or_else(std::sync::PoisonError::into_inner);
+            let slot = slots.entry(key.clone()).or_default();
+            if self.directory_locking == DirectoryLocking::Queue {
+                slot.queue.push((session_id, Instant::now()));
+            }
+            Arc::clone(&slot.lock)
+        };
+
+        let guard = match self.directory_locking {
+            DirectoryLocking::Queue => {
+                let _entry = QueueEntry {
+                    slots: &self.directory_locks,
+                    key: &key,
+                    session_id,
+                };
+                lock.lock_owned().await
+            }
+            DirectoryLocking::Reject => lock
+                .try_lock_owned()
+                .map_err(|_| ManagerError::DirectoryBusy(key.clone()))?,
+            DirectoryLocking::Disabled => return Ok(None),
+        };
+        Ok(Some(DirectoryGuard {
+            guard: Some(guard),
+            slots: Arc::clone(&self.directory_locks),
+            key,
+        }))
+    }
+
+    /// Take ownership of a spawned process.
+    ///
+    /// Forwards its output into the `MsgStore` and storage, routes client
+    /// input to the executor or the child's stdin, and applies kill signals
+    /// from the session's `InterruptHandle`. A process with a continuation is
+    /// followed by the process it resolves to, if any. Once the last process
+    /// exits, its status and exit code are recorded, `Finished` is pushed,
+    /// and the session is removed from the active set. The working directory
+    /// lock, if any, is held until then. Usage is counted on from `usage`,
+    /// e.g. what a reattached session used before a restart.
+    fn spawn_process_task(
+        &self,
+        session_id: SessionId,
+        msg_store: Arc<MsgStore>,
+        mut process: SpawnedProcess,
+        dir_guard: Option<DirectoryGuard>,
+        workspace: Option<SessionWorkspace>,
+        usage_so_far: UsageTotals,
+    ) -> ActiveSession {
+        let cancelled = Arc::new(AtomicBool::new(false));
+        let task_cancelled = Arc::clone(&cancelled);
+        let (signal_tx, mut signal_rx) = mpsc::unbounded_channel();
+        let (exited_tx, exited_rx) = watch::channel(false);
+        let events = process
+            .events
+            .take()
+            .unwrap_or_else(|| raw_output_events(&mut process.child));
+        let input_tx = take_input_tx(&mut process);
+        let usage = process.usage.take();
+        let permission_mode_tx = process.permission_mode_tx.take();
+        let abort_rx = process.abort_rx.take();
+        let mut continuation = process.continuation.take();
+        let mut outcome = process.outcome.take();
+        let mut child = process.child;
+        let pid = child.id();
+        let interrupt = InterruptHandle::new(
+            process.interrupt_tx,
+            signal_tx,
+            exited_rx,
+            cancelled,
+            self.interrupt_timeout,
+        );
+        let mut task = self.process_task(session_id, &msg_store, &interrupt);
+        task.workspace = workspace;
+        task.usage = std::sync::Mutex::new(usage_so_far);
+        let (task_started_at, runtime_stats) = (task.started_at, Arc::clone(&task.stats));
+
+        let process_task = tokio::spawn(async move {
+            let mut exit_status = task
+                .run_process(&mut child, events, usage, abort_rx, &mut signal_rx)
+                .await;
+            let mut reported = outcome.take().and_then(|outcome| outcome.now_or_never().flatten());
+            // Continue the session in new processes for as long as the
+            // executor asks to, unless it was stopped meanwhile.
+            while let Some(next) = continuation.take() {
+                if task_cancelled.load(Ordering::SeqCst) || task.failure_reason().is_some() {
+                    break;
+                }
+                let next = tokio::select! {
+                    next = next => next,
+                    Some(_) = signal_rx.recv() => None,
+                };
+                let Some(mut next) = next else {
+                    break;
+                };
+                task.interrupt.set_interrupt_tx(next.interrupt_tx.take());
+                let input_tx = take_input_tx(&mut next);
+                task.set_process_handles(input_tx, next.permission_mode_tx.take(), next.child.id())
+                    .await;
+                continuation = next.continuation.take();
+                outcome = next.outcome.take();
+                let events = next
+                    .events
+                    .take()
+                    .unwrap_or_else(|| raw_output_events(&mut next.child));
+                let (usage, abort_rx) = (next.usage.take(), next.abort_rx.take());
+                child = next.child;
+                exit_status = task
+                    .run_process(&mut child, events, usage, abort_rx, &mut signal_rx)
+                    .await;
+                reported = outcome.take().and_then(|outcome| outcome.now_or_never().flatten());
+            }
+            if let Some(reported) = reported {
+                if !reported.success && !task_cancelled.load(Ordering::SeqCst) {
+                    task.agent_failed(reported.reason);
+                }
+            }
+
+            let status = match &exit_status {
+                _ if task.failure_reason().is_some() => SessionStatus::Failed,
+                _ if task_cancelled.load(Ordering::SeqCst) => SessionStatus::Cancelled,
+                Ok(exit_status) if exit_status.success() => SessionStatus::Completed,
+                Ok(_) => SessionStatus::Failed,
+                Err(e) => {
+                    tracing::error!("Failed to wait for session {session_id}: {e}");
+                    SessionStatus::Failed
+                }
+            };
+            task.finish(status, exit_status.ok().and_then(|s| s.code()))
+                .await;
+            drop(dir_guard);
+            let _ = exited_tx.send(true);
+        });
+
+        ActiveSession {
+            msg_store,
+            input_tx,
+            permission_mode_tx,
+            interrupt,
+            started_at: task_started_at,
+            pid,
+            pty_id: None,
+            stats: runtime_stats,
+            _process_task: process_task,
+        }
+    }
+
+    /// Take ownership of an interactive PTY session.
+    ///
+    /// Like `spawn_process_task`, but output comes from the PTY, input is
+    /// written to it, and any kill signal closes it.
+    fn spawn_pty_task(
+        &self,
+        session_id: SessionId,
+        msg_store: Arc<MsgStore>,
+        pty_id: Uuid,
+        output_rx: mpsc::UnboundedReceiver<Vec<u8>>,
+    ) -> ActiveSession {
+        let cancelled = Arc::new(AtomicBool::new(false));
+        let task_cancelled = Arc::clone(&cancelled);
+        let (signal_tx, mut signal_rx) = mpsc::unbounded_channel();
+        let (exited_tx, exited_rx) = watch::channel(false);
+        let interrupt = InterruptHandle::new(
+            None,
+            signal_tx,
+            exited_rx,
+            cancelled,
+            self.interrupt_timeout,
+        );
+        let task = self.process_task(session_id, &msg_store, &interrupt);
+        let (task_started_at, runtime_stats) = (task.started_at, Arc::clone(&task.stats));
+
+        let (input_tx, mut input_rx) = mpsc::unbounded_channel::<Vec<u8>>();
+        let pty = self.pty.clone();
+        tokio::spawn(async move {
+            while let Some(data) = input_rx.recv().await {
+                if let Err(e) = pty.write(pty_id, &data).await {
+                    tracing::debug!("Failed to write to PTY {pty_id}: {e}");
+                    break;
+                }
+            }
+        });
+
+        let pty = self.pty.clone();
+        let process_task = tokio::spawn(async move {
+            let control = async {
+                while signal_rx.recv().await.is_some() {
+                    if let Err(e) = pty.close_session(pty_id).await {
+                        tracing::debug!("Failed to close PTY {pty_id}: {e}");
+                    }
+                }
+                std::future::pending::<()>().await;
+            };
+            tokio::select! {
+                () = task.forward(pty_output_events(output_rx)) => {}
+                () = control => {}
+            }
+            let _ = pty.close_session(pty_id).await;
+
+            let status = if task.failure_reason().is_some() {
+                SessionStatus::Failed
+            } else if task_cancelled.load(Ordering::SeqCst) {
+                SessionStatus::Cancelled
+            } else {
+                SessionStatus::Completed
+            };
+            task.finish(status, None).await;
+            let _ = exited_tx.send(true);
+        });
+
+        ActiveSession {
+            msg_store,
+            input_tx: Some(input_tx),
+            permission_mode_tx: None,
+            interrupt,
+            started_at: task_started_at,
+            pid: None,
+            pty_id: Some(pty_id),
+            stats: runtime_stats,
+            _process_task: process_task,
+        }
+    }
+
+    /// Build the shared state for a session's background task.
+    fn process_task(
+        &self,
+        session_id: SessionId,
+        msg_store: &Arc<MsgStore>,
+        interrupt: &InterruptHandle,
+    ) -> ProcessTask<S> {
+        self.metrics.session_activated();
+        ProcessTask {
+            session_id,
+            storage: Arc::clone(&self.storage),
+            event_storage: self.event_storage.clone(),
+            msg_store: Arc::clone(msg_store),
+            metrics: Arc::clone(&self.metrics),
+            lifecycle_tx: self.lifecycle_tx.clone(),
+            hooks: self.hooks.clone(),
+            versions: Arc::clone(&self.versions),
+            output_quota: self.output_quota,
+            limits: self.limits,
+            failure_reason: std::sync::Mutex::new(None),
+            usage: std::sync::Mutex::default(),
+            interrupt: interrupt.clone(),
+            stats: Arc::new(RuntimeStats::default()),
+            workspace: None,
+            active_sessions: Arc::clone(&self.active_sessions),
+            started_at: Instant::now(),
+        }
+    }
+}
+
+/// Stream PTY output as `Stdout` events.
+///
+/// Multi-byte UTF-8 characters split across reads are carried over to the
+/// next chunk rather than replaced.
+fn pty_output_events(output_rx: mpsc::UnboundedReceiver<Vec<u8>>) -> EventStream {
+    futures::stream::unfold(
+        (output_rx, Vec::new()),
+        |(mut output_rx, mut pending)| async move {
+            let chunk = output_rx.recv().await?;
+            pending.extend_from_slice(&chunk);
+            // Hold back an incomplete trailing character; invalid bytes are replaced.
+            let valid = match std::str::from_utf8(&pending) {
+                Err(e) if e.error_len().is_none() => e.valid_up_to(),
+                _ => pending.len(),
+            };
+            let rest = pending.split_off(valid);
+            let text = String::from_utf8_lossy(&pending).into_owned();
+            Some((Ok(LogMsg::Stdout(text)), (output_rx, rest)))
+        },
+    )
+    .boxed()
+}
+
+/// Shared state for a session's background process task.
+struct ProcessTask<S: ?Sized> {
+    session_id: SessionId,
+    storage: Arc<S>,
+    event_storage: Option<Arc<dyn EventStorage>>,
+    msg_store: Arc<MsgStore>,
+    metrics: Arc<Metrics>,
+    lifecycle_tx: broadcast::Sender<LifecycleEvent>,
+    hooks: StatusHooks,
+    versions: Arc<SessionVersions>,
+    output_quota: Option<OutputQuota>,
+    limits: SessionLimits,
+    /// Set when the manager stops the session for exceeding a limit, or
+    /// when the agent reports that its run failed.
+    failure_reason: std::sync::Mutex<Option<String>>,
+    usage: std::sync::Mutex<UsageTotals>,
+    interrupt: InterruptHandle,
+    stats: Arc<RuntimeStats>,
+    /// Workspace to clean up once the session finishes.
+    workspace: Option<SessionWorkspace>,
+    active_sessions: Arc<RwLock<HashMap<SessionId, ActiveSession>>>,
+    started_at: Instant,
+}
+
+impl<S: SessionStorage + ?Sized> ProcessTask<S> {
+    /// Replace the session's input and permission mode senders and pid, for
+    /// a new process continuing the session.
+    async fn set_process_handles(
+        &self,
+        input_tx: Option<mpsc::UnboundedSender<Vec<u8>>>,
+        permission_mode_tx: Option<mpsc::UnboundedSender<String>>,
+        pid: Option<u32>,
+    ) {
+        if let Some(session) = self.active_sessions.write().await.get_mut(&self.session_id) {
+            session.input_tx = input_tx;
+            session.permission_mode_tx = permission_mode_tx;
+            session.pid = pid;
+        }
+    }
+
+    /// Forward the agent's events into storage and the `MsgStore`.
+    ///
+    /// `SessionId` events are recorded as the session's `agent_session_id`.
+    ///
+    /// Past the output quota, events are only pushed to the `MsgStore`.
+    ///
+    /// The end of each `AssistantText` delta that could start a secret is
+    /// held back until the next, and sent before any other event.
+    async fn forward(&self, events: EventStream) {
+        let mut seen_output = false;
+        let mut persisted_bytes = 0;
+        let mut over_quota = false;
+        let mut assistant_text = self.msg_store.redactor().streaming();
+        // `None` once the events end, to send any text still held back.
+        let mut events = events.map(Some).chain(futures::stream::iter([None]));
+        while let Some(next) = events.next().await {
+            let msgs = match next {
+                Some(Ok(LogMsg::AssistantText(text))) => {
+                    vec![LogMsg::AssistantText(assistant_text.push(&text))]
+                }
+                next => {
+                    let held = LogMsg::AssistantText(assistant_text.flush());
+                    let msg = next.map(|next| match next {
+                        Ok(msg) => self.msg_store.redactor().redact_msg(msg),
+                        Err(e) => LogMsg::Stderr(format!("stream error: {e}")),
+                    });
+                    std::iter::once(held).chain(msg).collect()
+                }
+            };
+            for msg in msgs {
+                if matches!(&msg, LogMsg::AssistantText(text) if text.is_empty()) {
+                    continue;
+                }
+                self.stats.record(&msg);
+                if let LogMsg::SessionId(agent_session_id) = &msg {
+                    self.record_agent_session_id(agent_session_id).await;
+                }
+                if let LogMsg::Stdout(s) | LogMsg::Stderr(s) = &msg {
+                    if !seen_output {
+                        seen_output = true;
+                        self.metrics.first_output(self.started_at.elapsed());
+                    }
+                    self.metrics.output(s.len());
+                }
+
+                if let Some(quota) = self.output_quota {
+                    persisted_bytes += msg.approx_bytes();
+                    if !over_quota && persisted_bytes > quota.max_bytes {
+                        over_quota = true;
+                        self.exceed_quota(quota).await;
+                    }
+                }
+                if over_quota {
+                    self.msg_store.push(msg);
+                } else {
+                    self.persist_and_push(msg).await;
+                }
+            }
+        }
+    }
+
+    /// Forward a process's events and usage until it has exited, applying
+    /// kill signals to it meanwhile.
+    async fn run_process(
+        &self,
+        child: &mut command_group::AsyncGroupChild,
+        events: EventStream,
+        mut usage: Option<UsageStream>,
+        abort_rx: Option<oneshot::Receiver<String>>,
+        signal_rx: &mut mpsc::UnboundedReceiver<KillSignal>,
+    ) -> std::io::Result<std::process::ExitStatus> {
+        let wait = async {
+            loop {
+                tokio::select! {
+                    status = child.wait() => break status,
+                    Some(signal) = signal_rx.recv() => send_signal(child, signal),
+                }
+            }
+        };
+        let run = async {
+            let run = async { tokio::join!(self.forward(events), wait) };
+            let Some(abort_rx) = abort_rx else {
+                return run.await;
+            };
+            tokio::pin!(run);
+            tokio::select! {
+                result = &mut run => result,
+                Ok(reason) = abort_rx => {
+                    self.abort_requested(reason).await;
+                    run.await
+                }
+            }
+        };
+        let ((), exit_status) = match usage.as_mut() {
+            Some(usage) => tokio::select! {
+                result = run => result,
+                never = self.track_usage(usage) => match never {},
+            },
+            None => run.await,
+        };
+        if let Some(usage) = usage.as_mut() {
+            // Usage reported just before exit, such as the final cost.
+            while let Some(Some(event)) = usage.next().now_or_never() {
+                self.record_usage(&event);
+            }
+        }
+        exit_status
+    }
+
+    /// Store the agent's own session ID so follow-ups can resume it.
+    async fn record_agent_session_id(&self, agent_session_id: &str) {
+        let session_id = self.session_id;
+        if let Err(e) = self
+            .storage
+            .set_agent_session_id(session_id, agent_session_id.to_string())
+            .await
+        {
+            tracing::error!("Failed to record agent session ID for session {session_id}: {e}");
+        }
+    }
+
+    /// Warn that the output quota was exceeded and interrupt if configured.
+    async fn exceed_quota(&self, quota: OutputQuota) {
+        tracing::warn!(
+            "Session {} exceeded its output quota of {} bytes",
+            self.session_id,
+            quota.max_bytes
+        );
+        let warning = format!(
+            "Output quota of {} bytes exceeded; further output will not be saved\n",
+            quota.max_bytes
+        );
+        self.persist_and_push(LogMsg::Stderr(warning)).await;
+
+        if quota.interrupt {
+            self.abort(format!("Output quota of {} bytes exceeded", quota.max_bytes));
+        }
+    }
+
+    /// Track usage events and abort the session once a limit is exceeded.
+    ///
+    /// Never returns, so it can be raced against the process finishing.
+    async fn track_usage(&self, usage: &mut UsageStream) -> std::convert::Infallible {
+        while let Some(event) = usage.next().await {
+            let totals = self.record_usage(&event);
+            // Stored as it goes, so a restart does not lose what was used.
+            if let Err(e) = self.persist_usage().await {
+                let session_id = self.session_id;
+                tracing::warn!("Failed to record usage for session {session_id}: {e}");
+            }
+            if let Some(reason) = totals.exceeded(&self.limits) {
+                tracing::warn!("Session {}: {reason}", self.session_id);
+                self.persist_and_push(LogMsg::Stderr(format!("{reason}; interrupting session\n")))
+                    .await;
+                self.abort(reason);
+                break;
+            }
+        }
+        std::future::pending().await
+    }
+
+    /// Add a usage event to the session's totals, returning the new totals.
+    fn record_usage(&self, event: &UsageEvent) -> UsageTotals {
+        let mut usage = self
+            .usage
+            .lock()
+            .unwrap_or_else(std::sync::PoisonError::into_inner);
+        usage.record(event);
+        *usage
+    }
+
+    /// Store the session's usage totals in its metadata, if any were
+    /// reported.
+    async fn persist_usage(&self) -> Result<(), StorageError> {
+        let totals = *self
+            .usage
+            .lock()
+            .unwrap_or_else(std::sync::PoisonError::into_inner);
+        if totals == UsageTotals::default() {
+            return Ok(());
+        }
+        store_usage(&*self.storage, &self.versions, self.session_id, totals).await
+    }
+
+    /// Stop the session because its executor asked to.
+    async fn abort_requested(&self, reason: String) {
+        tracing::warn!("Session {}: {reason}", self.session_id);
+        self.persist_and_push(LogMsg::Stderr(format!("{reason}; stopping session\n")))
+            .await;
+        self.abort(reason);
+    }
+
+    /// Interrupt the session in the background, failing it with `reason`.
+    fn abort(&self, reason: String) {
+        self.failure_reason
+            .lock()
+            .unwrap_or_else(std::sync::PoisonError::into_inner)
+            .get_or_insert(reason);
+        let interrupt = self.interrupt.clone();
+        tokio::spawn(async move { interrupt.interrupt().await });
+    }
+
+    /// Fail the session as the agent reported its run failed, even if the
+    /// process exits cleanly.
+    fn agent_failed(&self, reason: Option<String>) {
+        let reason = reason.unwrap_or_else(|| "Agent reported failure".to_string());
+        tracing::info!("Session {}: {reason}", self.session_id);
+        self.failure_reason
+            .lock()
+            .unwrap_or_else(std::sync::PoisonError::into_inner)
+            .get_or_insert(reason);
+    }
+
+    /// Why the manager stopped the session, if it did.
+    fn failure_reason(&self) -> Option<String> {
+        self.failure_reason
+            .lock()
+            .unwrap_or_else(std::sync::PoisonError::into_inner)
+            .clone()
+    }
+
+    /// Record the session's final status and remove it from the active set.
+    async fn finish(&self, status: SessionStatus, exit_code: Option<i32>) {
+        let session_id = self.session_id;
+        self.metrics.session_ended(status, self.started_at.elapsed());
+        if let Some(code) = exit_code {
+            if let Err(e) = self.storage.set_exit_code(session_id, code).await {
+                tracing::error!("Failed to record exit code for session {session_id}: {e}");
+            }
+        }
+        if let Some(reason) = self.failure_reason() {
+            if let Err(e) = self.storage.set_status_reason(session_id, reason).await {
+                tracing::error!("Failed to record status reason for session {session_id}: {e}");
+            }
+        }
+        if let Err(e) = self.persist_usage().await {
+            tracing::error!("Failed to record usage for session {session_id}: {e}");
+        }
+        match update_status(
+            &*self.storage,
+            &self.versions,
+            &self.lifecycle_tx,
+            &self.hooks,
+            session_id,
+            status,
+        )
+        .await
+        {
+            Ok(()) => {}
+            // E.g. `stop_session` ended it first.
+            Err(StorageError::Conflict(_)) => {
+                tracing::debug!("Session {session_id} ended before its process finished");
+            }
+            Err(e) => {
+                tracing::error!("Failed to update status for session {session_id}: {e}");
+            }
+        }
+        self.versions.forget(session_id);
+
+        cleanup_workspace(self.workspace.clone()).await;
+        if let Some(notice) = self.interrupt.take_stop_notice() {
+            self.persist_and_push(LogMsg::Stderr(notice)).await;
+        }
+        self.persist_and_push(LogMsg::Finished).await;
+        self.active_sessions.write().await.remove(&session_id);
+    }
+
+    /// Persist a message, logging failures, then push it to the `MsgStore`.
+    async fn persist_and_push(&self, msg: LogMsg) {
+        let session_id = self.session_id;
+        if let Err(e) =
+            persist_msg(&*self.storage, self.event_storage.as_deref(), session_id, &msg).await
+        {
+            tracing::error!("Failed to persist output for session {session_id}: {e}");
+        }
+        self.msg_store.push(msg);
+    }
+}
+
+/// Remove a session's workspace, logging failures.
+async fn cleanup_workspace(workspace: Option<SessionWorkspace>) {
+    if let Some((provisioner, workspace)) = workspace {
+        if let Err(e) = provisioner.cleanup(&workspace).await {
+            tracing::warn!("Failed to clean up workspace {}: {e}", workspace.path.display());
+        }
+    }
+}
+
+/// Update a session's status in storage, broadcast the change, and run hooks.
+///
+/// The update expects the version of this manager's last write, so if
+/// another manager sharing the storage changed the session since, this
+/// fails with `StorageError::Conflict` instead of overwriting the change.
+/// A session that has already ended keeps its status, also failing with
+/// `StorageError::Conflict`. Once it ends, the manager stops tracking its
+/// version.
+async fn update_status<S: SessionStorage + ?Sized>(
+    storage: &S,
+    versions: &SessionVersions,
+    lifecycle_tx: &broadcast::Sender<LifecycleEvent>,
+    hooks: &StatusHooks,
+    session_id: SessionId,
+    status: SessionStatus,
+) -> Result<(), StorageError> {
+    let mut guard = versions.lock(storage, session_id).await?;
+    let (version, current) = guard.current();
+    if current.is_terminal() {
+        return Err(StorageError::Conflict(session_id));
+    }
+    storage
+        .update_status(session_id, status, Some(version))
+        .await?;
+    guard.written(status);
+    drop(guard);
+    if status.is_terminal() {
+        // Nothing but late usage reports writes to it again, and those read
+        // the version back from storage.
+        versions.forget(session_id);
+    }
+    let _ = lifecycle_tx.send(LifecycleEvent::StatusChanged { session_id, status });
+    hooks.notify(storage, session_id, status).await;
+    Ok(())
+}
+
+/// Store a session's usage totals in its metadata.
+///
+/// Like `update_status`, the update expects the version of this manager's
+/// last write.
+async fn store_usage<S: SessionStorage + ?Sized>(
+    storage: &S,
+    versions: &SessionVersions,
+    session_id: SessionId,
+    totals: UsageTotals,
+) -> Result<(), StorageError> {
+    let mut guard = versions.lock(storage, session_id).await?;
+    let (version, status) = guard.current();
+    let mut ctx = storage
+        .get(session_id)
+        .await?
+        .ok_or(StorageError::NotFound(session_id))?
+        .context;
+    let totals =
+        serde_json::to_value(totals).map_err(|e| StorageError::Internal(e.to_string()))?;
+    ctx.set_metadata(USAGE_METADATA_KEY, totals);
+    storage
+        .update_context(session_id, &ctx, Some(version))
+        .await?;
+    guard.written(status);
+    drop(guard);
+    Ok(())
+}
+
+/// Take a process's input sender, falling back to writing its stdin.
+fn take_input_tx(process: &mut SpawnedProcess) -> Option<mpsc::UnboundedSender<Vec<u8>>> {
+    process.input_tx.take().or_else(|| {
+        process
+            .child
+            .inner()
+            .stdin
+            .take()
+            .map(spawn_stdin_writer)
+    })
+}
+
+/// Persist a message as raw output and, if configured, as a structured event.
+///
+/// `AssistantText` deltas are only for live clients; the complete message
+/// follows as output.
+async fn persist_msg<S: SessionStorage + ?Sized>(
+    storage: &S,
+    event_storage: Option<&dyn EventStorage>,
+    session_id: SessionId,
+    msg: &LogMsg,
+) -> Result<(), StorageError> {
+    if let LogMsg::Stdout(s) | LogMsg::Stderr(s) = msg {
+        storage.append_output(session_id, s.as_bytes()).await?;
+    }
+    if matches!(msg, LogMsg::AssistantText(_)) {
+        return Ok(());
+    }
+    if let Some(event_storage) = event_storage {
+        event_storage.append_event(session_id, msg).await?;
+    }
+    Ok(())
+}
+
+/// Current time as Unix epoch seconds.
+fn now() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| i64::try_from(d.as_secs()).unwrap_or(i64::MAX))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{path::PathBuf, process::Stdio};
+
+    use async_trait::async_trait;
+    use command_group::AsyncCommandGroup;
+
+    use super::*;
+    use crate::{
+        registry::ExecutorRegistry,
+        storage::{memory::MemoryStorage, namespaced::NamespacedStorage},
+        workspace::WorkspaceStrategy,
+    };
+
+    fn spawn_shell(script: &str) -> SpawnedProcess {
+        let child = tokio::process::Command::new("sh")
+            .args(["-c", script])
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .group_spawn()
+            .unwrap();
+        SpawnedProcess::from_child(child)
+    }
+
+    /// Runs a process that exits at once, continued by one echoing a line
+    /// of input.
+    struct ContinuingExecutor;
+
+    #[async_trait]
+    impl Executor for ContinuingExecutor {
+        async fn spawn(
+            &self,
+            _ctx: &ExecutionContext,
+            _prompt: &str,
+        ) -> Result<SpawnedProcess, ExecutorError> {
+            let mut process = spawn_shell("echo first");
+            process.continuation = Some(Box::pin(async {
+                Some(spawn_shell("echo ready; read line; echo \"got $line\""))
+            }));
+            Ok(process)
+        }
+
+        async fn spawn_follow_up(
+            &self,
+            ctx: &ExecutionContext,
+            prompt: &str,
+            _session_id: &str,
+        ) -> Result<SpawnedProcess, ExecutorError> {
+            self.spawn(ctx, prompt).await
+        }
+    }
+
+    /// Runs the prompt as a shell script.
+    struct ScriptExecutor;
+
+    #[async_trait]
+    impl Executor for ScriptExecutor {
+        async fn spawn(
+            &self,
+            _ctx: &ExecutionContext,
+            prompt: &str,
+        ) -> Result<SpawnedProcess, ExecutorError> {
+            Ok(spawn_shell(prompt))
+        }
+
+        async fn spawn_follow_up(
+            &self,
+            ctx: &ExecutionContext,
+            prompt: &str,
+            _session_id: &str,
+        ) -> Result<SpawnedProcess, ExecutorError> {
+            self.spawn(ctx, prompt).await
+        }
+    }
+
+    /// Runs a process waiting to be stopped, reporting the usage sent
+    /// through its sender.
+    struct UsageExecutor(std::sync::Mutex<Option<UsageStream>>);
+
+    #[async_trait]
+    impl Executor for UsageExecutor {
+        async fn spawn(
+            &self,
+            _ctx: &ExecutionContext,
+            _prompt: &str,
+        ) -> Result<SpawnedProcess, ExecutorError> {
+            let mut process = spawn_shell("sleep 30");
+            process.usage = self.0.lock().unwrap().take();
+            Ok(process)
+        }
+
+        async fn spawn_follow_up(
+            &self,
+            ctx: &ExecutionContext,
+            prompt: &str,
+            _session_id: &str,
+        ) -> Result<SpawnedProcess, ExecutorError> {
+            self.spawn(ctx, prompt).await
+        }
+    }
+
+    #[tokio::test]
+    async fn test_queued_batch_starts_in_background() {
+        let manager = Arc::new(
+            SessionManager::new(MemoryStorage::new(), ScriptExecutor)
+                .with_directory_locking(DirectoryLocking::Queue),
+        );
+        let ctx = ExecutionContext::new(std::env::temp_dir());
+        let batch_id = manager
+            .start_batch(vec![
+                (ctx.clone(), "sleep 0.2".to_string()),
+                (ctx, "true".to_string()),
+            ])
+            .await
+            .unwrap();
+
+        let status = manager.batch_status(batch_id).await.unwrap();
+        assert_eq!(status.finished(), 0);
+        assert!(status.pending >= 1);
+
+        let mut status = status;
+        for _ in 0..100 {
+            if status.is_finished() {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(20)).await;
+            status = manager.batch_status(batch_id).await.unwrap();
+        }
+        assert_eq!(status.completed, 2);
+    }
+
+    #[tokio::test]
+    async fn test_input_reaches_continued_process() {
+        let manager = SessionManager::new(MemoryStorage::new(), ContinuingExecutor);
+        let id = manager
+            .start_session(ExecutionContext::new(std::env::temp_dir()), "prompt")
+            .await
+            .unwrap();
+
+        let mut stream = manager.attach(id).await.unwrap().stream;
+        while let Some(Ok(msg)) = stream.next().await {
+            if matches!(&msg, LogMsg::Stdout(s) if s.contains("ready")) {
+                break;
+            }
+        }
+        let attached = manager.attach(id).await.unwrap();
+        let active = manager.list_active().await;
+        assert!(active[0].pid.is_some());
+        attached.input_tx.unwrap().send(b"hello\n".to_vec()).unwrap();
+
+        let mut output = String::new();
+        while let Some(Ok(msg)) = stream.next().await {
+            match msg {
+                LogMsg::Stdout(s) => output.push_str(&s),
+                LogMsg::Finished => break,
+                _ => {}
+            }
+        }
+        assert!(output.contains("got hello"), "{output}");
+    }
+
+    #[tokio::test]
+    async fn test_stop_notice_follows_last_output() {
+        let manager = SessionManager::new(MemoryStorage::new(), ScriptExecutor);
+        let script = "trap 'echo bye; exit 1' TERM; echo ready; sleep 30 & wait";
+        let id = manager
+            .start_session(ExecutionContext::new(std::env::temp_dir()), script)
+            .await
+            .unwrap();
+
+        let mut stream = manager.attach(id).await.unwrap().stream;
+        while let Some(Ok(msg)) = stream.next().await {
+            if matches!(&msg, LogMsg::Stdout(s) if s.contains("ready")) {
+                break;
+            }
+        }
+        manager.stop_session(id).await.unwrap();
+
+        let mut output = String::new();
+        while let Some(Ok(msg)) = stream.next().await {
+            match msg {
+                LogMsg::Stdout(s) | LogMsg::Stderr(s) => output.push_str(&s),
+                LogMsg::Finished => break,
+                _ => {}
+            }
+        }
+        assert!(output.ends_with("bye\nSession cancelled\n"), "{output}");
+        let session = manager.storage.get(id).await.unwrap().unwrap();
+        assert_eq!(session.status, SessionStatus::Cancelled);
+        assert!(manager.stop_session(id).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_directory_forgotten_when_released() {
+        let manager = SessionManager::new(MemoryStorage::new(), ScriptExecutor)
+            .with_directory_locking(DirectoryLocking::Reject);
+        let dir = std::env::temp_dir();
+        let held = manager.lock_directory(Uuid::new_v4(), &dir).await.unwrap();
+        assert!(matches!(
+            manager.lock_directory(Uuid::new_v4(), &dir).await,
+            Err(ManagerError::DirectoryBusy(_))
+        ));
+        assert_eq!(manager.directory_locks.lock().unwrap().len(), 1);
+
+        drop(held);
+        assert!(manager.directory_locks.lock().unwrap().is_empty());
+        assert!(
+            manager
+                .lock_directory(Uuid::new_v4(), &dir)
+                .await
+                .unwrap()
+                .is_some()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_directory_forgotten_when_queued_start_cancelled() {
+        let manager = SessionManager::new(MemoryStorage::new(), ScriptExecutor)
+            .with_directory_locking(DirectoryLocking::Queue);
+        let dir = std::env::temp_dir();
+        let held = manager.lock_directory(Uuid::new_v4(), &dir).await.unwrap();
+        let mut queued = Box::pin(manager.lock_directory(Uuid::new_v4(), &dir));
+        assert!(futures::poll!(queued.as_mut()).is_pending());
+        assert_eq!(manager.list_active().await.len(), 1);
+
+        // Released to the queued start, which is cancelled before taking it.
+        drop(held);
+        drop(queued);
+        assert!(manager.directory_locks.lock().unwrap().is_empty());
+        assert!(manager.list_active().await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_usage_stored_while_running() {
+        let (usage_tx, usage_rx) = futures::channel::mpsc::unbounded();
+        let executor = UsageExecutor(std::sync::Mutex::new(Some(usage_rx.boxed())));
+        let manager = SessionManager::new(MemoryStorage::new(), executor);
+        let id = manager
+            .start_session(ExecutionContext::new(std::env::temp_dir()), "prompt")
+            .await
+            .unwrap();
+
+        usage_tx.unbounded_send(UsageEvent::TurnCompleted).unwrap();
+        usage_tx
+            .unbounded_send(UsageEvent::Cost { total_usd: 0.5 })
+            .unwrap();
+        let mut stored = None;
+        for _ in 0..100 {
+            let session = manager.storage.get(id).await.unwrap().unwrap();
+            stored = session_usage(&session).filter(|usage| usage.cost_usd > 0.0);
+            if stored.is_some() {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        assert_eq!(stored.unwrap().turns, 1);
+        assert_eq!(manager.list_active().await.len(), 1);
+        manager.stop_session(id).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_workspace_released_when_session_fails_to_start() {
+        let base = std::env::temp_dir().join(format!("manager-test-{}", Uuid::new_v4()));
+        std::fs::create_dir_all(base.join("repo")).unwrap();
+        let workspaces = base.join("workspaces");
+        let provisioner =
+            WorkspaceProvisioner::new(&workspaces).with_strategy(WorkspaceStrategy::Copy);
+        // No executors are registered, so spawning fails.
+        let manager = SessionManager::new(MemoryStorage::new(), ExecutorRegistry::new())
+            .with_workspace_provisioner(provisioner);
+
+        let started = manager
+            .start_session(ExecutionContext::new(base.join("repo")), "prompt")
+            .await;
+        assert!(started.is_err());
+        for _ in 0..100 {
+            if std::fs::read_dir(&workspaces).unwrap().next().is_none() {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        assert!(std::fs::read_dir(&workspaces).unwrap().next().is_none());
+
+        std::fs::remove_dir_all(base).unwrap();
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+    async fn test_concurrent_writes_do_not_conflict() {
+        let manager = Arc::new(SessionManager::new(
+            MemoryStorage::new(),
+            ExecutorRegistry::new(),
+        ));
+        let mut ctx = ExecutionContext::new(PathBuf::from("/tmp"));
+        let id = manager
+            .create_session(&mut ctx, "prompt", None, None)
+            .await
+            .unwrap();
+
+        let mut writes = Vec::new();
+        for turns in 1..=50 {
+            let usage = Arc::clone(&manager);
+            writes.push(tokio::spawn(async move {
+                let totals = UsageTotals {
+                    turns,
+                    ..UsageTotals::default()
+                };
+                store_usage(&*usage.storage, &usage.versions, id, totals).await
+            }));
+            let status = Arc::clone(&manager);
+            writes.push(tokio::spawn(async move {
+                status.set_status(id, SessionStatus::Running).await
+            }));
+        }
+        for write in writes {
+            write.await.unwrap().unwrap();
+        }
+        let session = manager.storage.get(id).await.unwrap().unwrap();
+        assert_eq!(session.version, 100);
+    }
+
+    #[tokio::test]
+    async fn test_failed_orphans_forgotten() {
+        let storage = MemoryStorage::new();
+        let id = storage
+            .create(&ExecutionContext::new(PathBuf::from("/tmp")))
+            .await
+            .unwrap();
+        storage
+            .update_status(id, SessionStatus::Running, None)
+            .await
+            .unwrap();
+
+        let manager = SessionManager::new(storage, ExecutorRegistry::new())
+            .with_orphan_policy(OrphanPolicy::Fail)
+            .build()
+            .await
+            .unwrap();
+        let session = manager.storage.get(id).await.unwrap().unwrap();
+        assert_eq!(session.status, SessionStatus::Failed);
+        assert!(manager.versions.slots().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_status_write_conflicts_across_managers() {
+        let shared = Arc::new(MemoryStorage::new());
+        let first = SessionManager::new(
+            NamespacedStorage::new(shared.clone(), "tenant"),
+            ExecutorRegistry::new(),
+        );
+        let second = SessionManager::new(
+            NamespacedStorage::new(shared.clone(), "tenant"),
+            ExecutorRegistry::new(),
+        );
+
+        let mut ctx = ExecutionContext::new(PathBuf::from("/tmp"));
+        let id = first
+            .create_session(&mut ctx, "prompt", None, None)
+            .await
+            .unwrap();
+        first.set_status(id, SessionStatus::Running).await.unwrap();
+
+        // The second manager reads the session before its first write.
+        second.set_status(id, SessionStatus::Cancelled).await.unwrap();
+        let stale = first.set_status(id, SessionStatus::Completed).await;
+        assert!(matches!(stale, Err(StorageError::Conflict(conflict)) if conflict == id));
+
+        let reopened = second.set_status(id, SessionStatus::Running).await;
+        assert!(matches!(reopened, Err(StorageError::Conflict(_))));
+        let session = shared.get(id).await.unwrap().unwrap();
+        assert_eq!(session.status, SessionStatus::Cancelled);
+        assert_eq!(session.version, 2);
     }
 }
@@ -0,0 +1,251 @@
+//! Session manager metrics.
+
+use std::{
+    fmt::Write as _,
+    sync::atomic::{AtomicU64, Ordering},
+    time::Duration,
+};
+
+use remote_agents_core::traits::SessionStatus;
+use serde::Serialize;
+
+/// Histogram bucket upper bounds.
+const BUCKETS: [Duration; 10] = [
+    Duration::from_millis(100),
+    Duration::from_millis(500),
+    Duration::from_secs(1),
+    Duration::from_secs(5),
+    Duration::from_secs(10),
+    Duration::from_secs(30),
+    Duration::from_secs(60),
+    Duration::from_secs(300),
+    Duration::from_secs(900),
+    Duration::from_secs(3600),
+];
+
+/// Lock-free duration histogram.
+#[derive(Debug, Default)]
+struct Histogram {
+    buckets: [AtomicU64; BUCKETS.len()],
+    count: AtomicU64,
+    sum_micros: AtomicU64,
+}
+
+impl Histogram {
+    fn observe(&self, value: Duration) {
+        for (bound, bucket) in BUCKETS.iter().zip(&self.buckets) {
+            if value <= *bound {
+                bucket.fetch_add(1, Ordering::Relaxed);
+            }
+        }
+        self.count.fetch_add(1, Ordering::Relaxed);
+        let micros = u64::try_from(value.as_micros()).unwrap_or(u64::MAX);
+        self.sum_micros.fetch_add(micros, Ordering::Relaxed);
+    }
+
+    fn snapshot(&self) -> HistogramSnapshot {
+        HistogramSnapshot {
+            buckets: BUCKETS
+                .iter()
+                .zip(&self.buckets)
+                .map(|(bound, count)| (bound.as_secs_f64(), count.load(Ordering::Relaxed)))
+                .collect(),
+            count: self.count.load(Ordering::Relaxed),
+            sum_seconds: Duration::from_micros(self.sum_micros.load(Ordering::Relaxed))
+                .as_secs_f64(),
+        }
+    }
+}
+
+/// Counters and histograms updated by `SessionManager`.
+#[derive(Debug, Default)]
+pub(crate) struct Metrics {
+    sessions_started: AtomicU64,
+    sessions_completed: AtomicU64,
+    sessions_failed: AtomicU64,
+    sessions_cancelled: AtomicU64,
+    active_sessions: AtomicU64,
+    output_bytes: AtomicU64,
+    time_to_first_output: Histogram,
+    session_duration: Histogram,
+}
+
+impl Metrics {
+    pub(crate) fn session_started(&self) {
+        self.sessions_started.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(crate) fn session_activated(&self) {
+        self.active_sessions.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(crate) fn spawn_failed(&self) {
+        self.sessions_failed.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(crate) fn first_output(&self, elapsed: Duration) {
+        self.time_to_first_output.observe(elapsed);
+    }
+
+    pub(crate) fn output(&self, bytes: usize) {
+        self.output_bytes.fetch_add(bytes as u64, Ordering::Relaxed);
+    }
+
+    pub(crate) fn session_ended(&self, status: SessionStatus, duration: Duration) {
+        let counter = match status {
+            SessionStatus::Completed => &self.sessions_completed,
+            SessionStatus::Cancelled => &self.sessions_cancelled,
+            SessionStatus::Failed | SessionStatus::Pending | SessionStatus::Running => {
+                &self.sessions_failed
+            }
+        };
+        counter.fetch_add(1, Ordering::Relaxed);
+        self.active_sessions.fetch_sub(1, Ordering::Relaxed);
+        self.session_duration.observe(duration);
+    }
+
+    pub(crate) fn snapshot(&self) -> MetricsSnapshot {
+        MetricsSnapshot {
+            sessions_started: self.sessions_started.load(Ordering::Relaxed),
+            sessions_completed: self.sessions_completed.load(Ordering::Relaxed),
+            sessions_failed: self.sessions_failed.load(Ordering::Relaxed),
+            sessions_cancelled: self.sessions_cancelled.load(Ordering::Relaxed),
+            active_sessions: self.active_sessions.load(Ordering::Relaxed),
+            output_bytes: self.output_bytes.load(Ordering::Relaxed),
+            time_to_first_output: self.time_to_first_output.snapshot(),
+            session_duration: self.session_duration.snapshot(),
+        }
+    }
+}
+
+/// Point-in-time histogram values (cumulative buckets, in seconds).
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct HistogramSnapshot {
+    /// `(upper bound, count)` pairs; counts are cumulative.
+    pub buckets: Vec<(f64, u64)>,
+    /// Number of observations.
+    pub count: u64,
+    /// Sum of all observations.
+    pub sum_seconds: f64,
+}
+
+/// Point-in-time snapshot of `SessionManager` metrics.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct MetricsSnapshot {
+    /// Sessions whose process was spawned.
+    pub sessions_started: u64,
+    /// Sessions that exited successfully.
+    pub sessions_completed: u64,
+    /// Sessions that failed to spawn or exited unsuccessfully.
+    pub sessions_failed: u64,
+    /// Sessions that were interrupted or stopped.
+    pub sessions_cancelled: u64,
+    /// Sessions currently running.
+    pub active_sessions: u64,
+    /// Total stdout/stderr bytes forwarded.
+    pub output_bytes: u64,
+    /// Time from spawn to the first output.
+    pub time_to_first_output: HistogramSnapshot,
+    /// Time from spawn to exit.
+    pub session_duration: HistogramSnapshot,
+}
+
+impl MetricsSnapshot {
+    /// Encode in the Prometheus text exposition format.
+    #[must_use]
+    pub fn to_prometheus(&self) -> String {
+        let mut out = String::new();
+
+        for (name, help, value) in [
+            (
+                "sessions_started_total",
+                "Sessions started.",
+                self.sessions_started,
+            ),
+            (
+                "sessions_completed_total",
+                "Sessions completed.",
+                self.sessions_completed,
+            ),
+            (
+                "sessions_failed_total",
+                "Sessions failed.",
+                self.sessions_failed,
+            ),
+            (
+                "sessions_cancelled_total",
+                "Sessions cancelled.",
+                self.sessions_cancelled,
+            ),
+            (
+                "output_bytes_total",
+                "Output bytes forwarded.",
+                self.output_bytes,
+            ),
+        ] {
+            let _ = writeln!(out, "# HELP remote_agents_{name} {help}");
+            let _ = writeln!(out, "# TYPE remote_agents_{name} counter");
+            let _ = writeln!(out, "remote_agents_{name} {value}");
+        }
+
+        let _ = writeln!(
+            out,
+            "# HELP remote_agents_active_sessions Sessions running."
+        );
+        let _ = writeln!(out, "# TYPE remote_agents_active_sessions gauge");
+        let _ = writeln!(
+            out,
+            "remote_agents_active_sessions {}",
+            self.active_sessions
+        );
+
+        for (name, help, histogram) in [
+            (
+                "time_to_first_output_seconds",
+                "Time from spawn to first output.",
+                &self.time_to_first_output,
+            ),
+            (
+                "session_duration_seconds",
+                "Time from spawn to exit.",
+                &self.session_duration,
+            ),
+        ] {
+            let _ = writeln!(out, "# HELP remote_agents_{name} {help}");
+            let _ = writeln!(out, "# TYPE remote_agents_{name} histogram");
+            for (bound, count) in &histogram.buckets {
+                let _ = writeln!(out, "remote_agents_{name}_bucket{{le=\"{bound}\"}} {count}");
+            }
+            let _ = writeln!(
+                out,
+                "remote_agents_{name}_bucket{{le=\"+Inf\"}} {}",
+                histogram.count
+            );
+            let _ = writeln!(out, "remote_agents_{name}_sum {}", histogram.sum_seconds);
+            let _ = writeln!(out, "remote_agents_{name}_count {}", histogram.count);
+        }
+
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_prometheus_encoding() {
+        let metrics = Metrics::default();
+        metrics.session_started();
+        metrics.session_activated();
+        metrics.first_output(Duration::from_millis(250));
+        metrics.session_ended(SessionStatus::Completed, Duration::from_secs(2));
+
+        let text = metrics.snapshot().to_prometheus();
+        assert!(text.contains("remote_agents_sessions_started_total 1"));
+        assert!(text.contains("remote_agents_active_sessions 0"));
+        assert!(text.contains("remote_agents_time_to_first_output_seconds_bucket{le=\"0.1\"} 0"));
+        assert!(text.contains("remote_agents_time_to_first_output_seconds_bucket{le=\"0.5\"} 1"));
+        assert!(text.contains("remote_agents_session_duration_seconds_count 1"));
+    }
+}
@@ -0,0 +1,155 @@
+//! Routing sessions to one of several executors.
+
+use std::{collections::HashMap, sync::Arc};
+
+use async_trait::async_trait;
+use remote_agents_core::{
+    ExecutionContext,
+    traits::{CommandPreview, Executor, ExecutorError, SpawnedProcess},
+};
+use remote_agents_executor::{ClaudeExecutor, CursorAgentExecutor, OpencodeExecutor};
+
+/// Metadata key the default router reads the executor name from.
+pub const EXECUTOR_METADATA_KEY: &str = "executor";
+
+/// Name `with_builtin_executors` registers `ClaudeExecutor` under.
+pub const CLAUDE_EXECUTOR: &str = "claude";
+
+/// Name `with_builtin_executors` registers `OpencodeExecutor` under.
+pub const OPENCODE_EXECUTOR: &str = "opencode";
+
+/// Name `with_builtin_executors` registers `CursorAgentExecutor` under.
+pub const CURSOR_EXECUTOR: &str = "cursor";
+
+type Router = Box<dyn Fn(&ExecutionContext) -> Option<String> + Send + Sync>;
+
+/// Registry of named executors, itself usable as an `Executor`.
+///
+/// Each session is routed by name: by default the `"executor"` metadata
+/// value of its context, falling back to the default executor. Use it as
+/// `SessionManager<S, ExecutorRegistry>` to host several agents behind one
+/// manager.
+pub struct ExecutorRegistry {
+    executors: HashMap<String, Arc<dyn Executor>>,
+    default: Option<String>,
+    router: Router,
+}
+
+impl Default for ExecutorRegistry {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ExecutorRegistry {
+    /// Create an empty registry that routes by context metadata.
+    #[must_use]
+    pub fn new() -> Self {
+        Self {
+            executors: HashMap::new(),
+            default: None,
+            router: Box::new(|ctx| {
+                ctx.get_metadata(EXECUTOR_METADATA_KEY)
+                    .and_then(|v| v.as_str())
+                    .map(str::to_string)
+            }),
+        }
+    }
+
+    /// Create a registry with every executor this workspace provides, each
+    /// with its default settings, and Claude as the default.
+    #[must_use]
+    pub fn with_builtin_executors() -> Self {
+        Self::new()
+            .register(CLAUDE_EXECUTOR, ClaudeExecutor::new())
+            .register(OPENCODE_EXECUTOR, OpencodeExecutor::new())
+            .register(CURSOR_EXECUTOR, CursorAgentExecutor::new())
+    }
+
+    /// Register an executor under a name.
+    ///
+    /// The first executor registered becomes the default.
+    #[must_use]
+    pub fn register(mut self, name: impl Into<String>, executor: impl Executor + 'static) -> Self {
+        let name = name.into();
+        if self.default.is_none() {
+            self.default = Some(name.clone());
+        }
+        self.executors.insert(name, Arc::new(executor));
+        self
+    }
+
+    /// Set the executor used when the router picks none.
+    #[must_use]
+    pub fn with_default(mut self, name: impl Into<String>) -> Self {
+        self.default = Some(name.into());
+        self
+    }
+
+    /// Replace the router that picks an executor name for a context.
+    #[must_use]
+    pub fn with_router<F>(mut self, router: F) -> Self
+    where
+        F: Fn(&ExecutionContext) -> Option<String> + Send + Sync + 'static,
+    {
+        self.router = Box::new(router);
+        self
+    }
+
+    /// Names of the registered executors.
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.executors.keys().map(String::as_str)
+    }
+
+    /// Pick the executor for a context.
+    ///
+    /// # Errors
+    /// Returns error if no registered executor matches.
+    pub fn select(&self, ctx: &ExecutionContext) -> Result<&dyn Executor, ExecutorError> {
+        let name = (self.router)(ctx)
+            .or_else(|| self.default.clone())
+            .ok_or_else(|| ExecutorError::NotRegistered("<default>".to_string()))?;
+        self.executors
+            .get(&name)
+            .map(AsRef::as_ref)
+            .ok_or(ExecutorError::NotRegistered(name))
+    }
+}
+
+#[async_trait]
+impl Executor for ExecutorRegistry {
+    async fn spawn(
+        &self,
+        ctx: &ExecutionContext,
+        prompt: &str,
+    ) -> Result<SpawnedProcess, ExecutorError> {
+        self.select(ctx)?.spawn(ctx, prompt).await
+    }
+
+    async fn spawn_follow_up(
+        &self,
+        ctx: &ExecutionContext,
+        prompt: &str,
+        session_id: &str,
+    ) -> Result<SpawnedProcess, ExecutorError> {
+        self.select(ctx)?
+            .spawn_follow_up(ctx, prompt, session_id)
+            .await
+    }
+
+    async fn resume(
+        &self,
+        ctx: &ExecutionContext,
+        session_id: &str,
+    ) -> Result<Option<SpawnedProcess>, ExecutorError> {
+        self.select(ctx)?.resume(ctx, session_id).await
+    }
+
+    async fn preview(
+        &self,
+        ctx: &ExecutionContext,
+        prompt: &str,
+    ) -> Result<Option<CommandPreview>, ExecutorError> {
+        self.select(ctx)?.preview(ctx, prompt).await
+    }
+}
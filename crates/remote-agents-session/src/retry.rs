@@ -0,0 +1,120 @@
+//! Retry policy for spawning agent processes.
+
+use std::{future::Future, time::Duration};
+
+use remote_agents_core::traits::ExecutorError;
+
+/// Retry policy with exponential backoff.
+#[derive(Debug, Clone)]
+pub struct RetryPolicy {
+    /// Total number of attempts, including the first.
+    pub max_attempts: u32,
+    /// Delay before the first retry.
+    pub initial_backoff: Duration,
+    /// Upper bound on the delay between attempts.
+    pub max_backoff: Duration,
+    /// Factor the delay grows by after each retry.
+    pub multiplier: u32,
+    /// Decides whether an error is worth retrying.
+    pub retry_on: fn(&ExecutorError) -> bool,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self::none()
+    }
+}
+
+impl RetryPolicy {
+    /// Policy that never retries.
+    #[must_use]
+    pub fn none() -> Self {
+        Self {
+            max_attempts: 1,
+            ..Self::exponential(1)
+        }
+    }
+
+    /// Exponential backoff from 100ms up to 10s, retrying transient errors.
+    #[must_use]
+    pub fn exponential(max_attempts: u32) -> Self {
+        Self {
+            max_attempts,
+            initial_backoff: Duration::from_millis(100),
+            max_backoff: Duration::from_secs(10),
+            multiplier: 2,
+            retry_on: ExecutorError::is_transient,
+        }
+    }
+
+    /// Delay before the given retry (1 for the first retry).
+    #[must_use]
+    pub fn backoff(&self, retry: u32) -> Duration {
+        let factor = self.multiplier.saturating_pow(retry.saturating_sub(1));
+        self.initial_backoff
+            .saturating_mul(factor)
+            .min(self.max_backoff)
+    }
+
+    /// Run `op` until it succeeds, fails with a non-retryable error, or
+    /// attempts are exhausted.
+    ///
+    /// # Errors
+    /// Returns the last error from `op`.
+    pub async fn run<T, F, Fut>(&self, mut op: F) -> Result<T, ExecutorError>
+    where
+        F: FnMut() -> Fut,
+        Fut: Future<Output = Result<T, ExecutorError>>,
+    {
+        let mut attempt = 1;
+        loop {
+            match op().await {
+                Ok(value) => return Ok(value),
+                Err(e) if attempt < self.max_attempts && (self.retry_on)(&e) => {
+                    let delay = self.backoff(attempt);
+                    tracing::warn!("Spawn attempt {attempt} failed, retrying in {delay:?}: {e}");
+                    tokio::time::sleep(delay).await;
+                    attempt += 1;
+                }
+                Err(e) => return Err(e),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_backoff_is_capped() {
+        let policy = RetryPolicy::exponential(10);
+        assert_eq!(policy.backoff(1), Duration::from_millis(100));
+        assert_eq!(policy.backoff(3), Duration::from_millis(400));
+        assert_eq!(policy.backoff(20), Duration::from_secs(10));
+    }
+
+    #[tokio::test]
+    async fn test_run_stops_on_permanent_error() {
+        let policy = RetryPolicy {
+            initial_backoff: Duration::ZERO,
+            ..RetryPolicy::exponential(5)
+        };
+
+        let mut calls = 0;
+        let result: Result<(), _> = policy
+            .run(|| {
+                calls += 1;
+                let err = if calls < 3 {
+                    ExecutorError::ExecutableNotFound("claude".to_string())
+                } else {
+                    ExecutorError::SpawnFailed("bad flag".to_string())
+                };
+                async move { Err(err) }
+            })
+            .await;
+
+        assert!(matches!(result, Err(ExecutorError::SpawnFailed(_))));
+        assert_eq!(calls, 3);
+    }
+}
@@ -0,0 +1,243 @@
+//! Portable session archives for backup and migration (feature-gated).
+//!
+//! An archive is a tar file holding a `manifest.json`, then for each session
+//! a `sessions/<id>.json` with the `Session` and a `sessions/<id>.output`
+//! with its output. Archives work with any `SessionStorage`, so they can
+//! move sessions between backends as well as between machines.
+
+use std::{collections::HashMap, io, path::Path};
+
+use futures::StreamExt;
+use remote_agents_core::traits::{Session, SessionFilter, SessionId, SessionStorage, StorageError};
+use serde::{Deserialize, Serialize};
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+use tokio_tar::{Archive, Builder, Header};
+
+use super::migrate::restore;
+
+/// Archive format version written by `export`.
+const FORMAT_VERSION: u32 = 1;
+
+const MANIFEST_PATH: &str = "manifest.json";
+
+#[derive(Debug, Serialize, Deserialize)]
+struct Manifest {
+    version: u32,
+    sessions: usize,
+}
+
+#[allow(clippy::needless_pass_by_value)]
+fn archive_error(e: io::Error) -> StorageError {
+    StorageError::Internal(format!("Archive: {e}"))
+}
+
+#[allow(clippy::needless_pass_by_value)]
+fn json_error(e: serde_json::Error) -> StorageError {
+    StorageError::Internal(format!("Archive: {e}"))
+}
+
+async fn append<W: AsyncWrite + Unpin + Send>(
+    builder: &mut Builder<W>,
+    path: &str,
+    data: &[u8],
+) -> Result<(), StorageError> {
+    let mut header = Header::new_gnu();
+    header.set_size(data.len() as u64);
+    header.set_mode(0o644);
+    header.set_cksum();
+    builder
+        .append_data(&mut header, path, data)
+        .await
+        .map_err(archive_error)
+}
+
+/// Write the sessions matching `filter`, with their output, to an archive.
+///
+/// Returns the number of sessions exported.
+///
+/// # Errors
+/// Returns error if reading from storage or writing the archive fails.
+pub async fn export<W>(
+    storage: &dyn SessionStorage,
+    filter: SessionFilter,
+    writer: W,
+) -> Result<usize, StorageError>
+where
+    W: AsyncWrite + Unpin + Send + 'static,
+{
+    let sessions = storage.list(filter).await?;
+    let mut builder = Builder::new(writer);
+
+    let manifest = Manifest {
+        version: FORMAT_VERSION,
+        sessions: sessions.len(),
+    };
+    let manifest = serde_json::to_vec_pretty(&manifest).map_err(json_error)?;
+    append(&mut builder, MANIFEST_PATH, &manifest).await?;
+
+    for session in &sessions {
+        let json = serde_json::to_vec_pretty(session).map_err(json_error)?;
+        append(
+            &mut builder,
+            &format!("sessions/{}.json", session.id),
+            &json,
+        )
+        .await?;
+        let output = storage.get_output(session.id).await?;
+        append(
+            &mut builder,
+            &format!("sessions/{}.output", session.id),
+            &output,
+        )
+        .await?;
+    }
+
+    let mut writer = builder.into_inner().await.map_err(archive_error)?;
+    writer.flush().await.map_err(archive_error)?;
+    Ok(sessions.len())
+}
+
+/// Restore the sessions in an archive written by `export`.
+///
+/// Sessions are created with new IDs; the returned map goes from each
+/// archived ID to its new one. Parent links between imported sessions are
+/// rewritten to the new IDs. Creation and update timestamps are not
+/// preserved.
+///
+/// # Errors
+/// Returns error if the archive is malformed or writing to storage fails.
+pub async fn import<R>(
+    storage: &dyn SessionStorage,
+    reader: R,
+) -> Result<HashMap<SessionId, SessionId>, StorageError>
+where
+    R: AsyncRead + Unpin + Send,
+{
+    let mut archive = Archive::new(reader);
+    let mut entries = archive.entries().map_err(archive_error)?;
+    let mut ids = HashMap::new();
+    let mut parents = Vec::new();
+    let mut manifest_seen = false;
+
+    while let Some(entry) = entries.next().await {
+        let mut entry = entry.map_err(archive_error)?;
+        let path = entry.path().map_err(archive_error)?.into_owned();
+        let mut data = Vec::new();
+        entry.read_to_end(&mut data).await.map_err(archive_error)?;
+
+        if path == Path::new(MANIFEST_PATH) {
+            let manifest: Manifest = serde_json::from_slice(&data).map_err(json_error)?;
+            if manifest.version != FORMAT_VERSION {
+                return Err(StorageError::Internal(format!(
+                    "Unsupported archive version: {}",
+                    manifest.version
+                )));
+            }
+            manifest_seen = true;
+            continue;
+        }
+        if !manifest_seen {
+            return Err(StorageError::Internal(
+                "Archive does not start with a manifest".to_string(),
+            ));
+        }
+
+        match path.extension().and_then(|ext| ext.to_str()) {
+            Some("json") => {
+                let session: Session = serde_json::from_slice(&data).map_err(json_error)?;
+                let id = restore(storage, &session).await?;
+                ids.insert(session.id, id);
+                if let Some(parent) = session.parent_session_id {
+                    parents.push((id, parent));
+                }
+            }
+            Some("output") => {
+                let archived = path
+                    .file_stem()
+                    .and_then(|stem| stem.to_str())
+                    .and_then(|stem| SessionId::parse_str(stem).ok());
+                let Some(&id) = archived.as_ref().and_then(|archived| ids.get(archived)) else {
+                    return Err(StorageError::Internal(format!(
+                        "Archive output without session: {}",
+                        path.display()
+                    )));
+                };
+                if !data.is_empty() {
+                    storage.append_output(id, &data).await?;
+                }
+            }
+            _ => {}
+        }
+    }
+
+    // Parents may be archived after their children, so link them last.
+    for (id, parent) in parents {
+        let parent = ids.get(&parent).copied().unwrap_or(parent);
+        storage.set_parent_session_id(id, parent).await?;
+    }
+    Ok(ids)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use remote_agents_core::{ExecutionContext, traits::SessionStatus};
+
+    use super::*;
+    use crate::storage::MemoryStorage;
+
+    #[tokio::test]
+    async fn test_export_import_round_trip() {
+        let source = MemoryStorage::new();
+        let parent = source
+            .create(&ExecutionContext::new(PathBuf::from("/tmp/project")))
+            .await
+            .unwrap();
+        source.set_prompt(parent, "plan".into()).await.unwrap();
+        source
+            .update_status(parent, SessionStatus::Completed, None)
+            .await
+            .unwrap();
+        source.append_output(parent, b"hello").await.unwrap();
+
+        let child = source
+            .create(&ExecutionContext::new(PathBuf::from("/tmp/project")))
+            .await
+            .unwrap();
+        source.set_parent_session_id(child, parent).await.unwrap();
+        source.set_exit_code(child, 1).await.unwrap();
+
+        let (writer, reader) = tokio::io::duplex(64 * 1024);
+        let exported = export(&source, SessionFilter::default(), writer)
+            .await
+            .unwrap();
+        assert_eq!(exported, 2);
+
+        let target = MemoryStorage::new();
+        let ids = import(&target, reader).await.unwrap();
+        assert_eq!(ids.len(), 2);
+
+        let restored = target.get(ids[&parent]).await.unwrap().unwrap();
+        assert_eq!(restored.prompt.as_deref(), Some("plan"));
+        assert_eq!(restored.status, SessionStatus::Completed);
+        assert_eq!(restored.context.working_dir, PathBuf::from("/tmp/project"));
+        assert_eq!(target.get_output(ids[&parent]).await.unwrap(), b"hello");
+
+        let restored = target.get(ids[&child]).await.unwrap().unwrap();
+        assert_eq!(restored.parent_session_id, Some(ids[&parent]));
+        assert_eq!(restored.exit_code, Some(1));
+    }
+
+    #[tokio::test]
+    async fn test_import_rejects_missing_manifest() {
+        let mut builder = Builder::new(Vec::new());
+        append(&mut builder, "sessions/x.output", b"data")
+            .await
+            .unwrap();
+        let archive = builder.into_inner().await.unwrap();
+
+        let storage = MemoryStorage::new();
+        assert!(import(&storage, archive.as_slice()).await.is_err());
+    }
+}
@@ -0,0 +1,138 @@
+//! Local-filesystem output blob store.
+
+use std::path::{Component, Path, PathBuf};
+
+use async_trait::async_trait;
+use futures::{StreamExt, stream};
+use remote_agents_core::traits::{BlobStream, OutputBlobStore, StorageError};
+use tokio::io::AsyncReadExt;
+use uuid::Uuid;
+
+/// Size of the chunks `FsBlobStore::stream` yields.
+const STREAM_CHUNK_SIZE: usize = 64 * 1024;
+
+/// Blob store keeping each blob as a file under a root directory.
+///
+/// Keys are relative paths; `/` in a key creates subdirectories.
+#[derive(Debug, Clone)]
+pub struct FsBlobStore {
+    root: PathBuf,
+}
+
+impl FsBlobStore {
+    /// Create a store rooted at `root`, which is created on first write.
+    #[must_use]
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        Self { root: root.into() }
+    }
+
+    /// Resolve a key to a path, rejecting keys that escape the root.
+    fn path(&self, key: &str) -> Result<PathBuf, StorageError> {
+        let relative = Path::new(key);
+        let valid = !key.is_empty()
+            && relative
+                .components()
+                .all(|component| matches!(component, Component::Normal(_)));
+        if !valid {
+            return Err(StorageError::Internal(format!("Invalid blob key: {key}")));
+        }
+        Ok(self.root.join(relative))
+    }
+}
+
+fn blob_error(key: &str, e: &std::io::Error) -> StorageError {
+    StorageError::Internal(format!("Blob {key}: {e}"))
+}
+
+#[async_trait]
+impl OutputBlobStore for FsBlobStore {
+    async fn put(&self, key: &str, data: Vec<u8>) -> Result<(), StorageError> {
+        let path = self.path(key)?;
+        if let Some(parent) = path.parent() {
+            tokio::fs::create_dir_all(parent)
+                .await
+                .map_err(|e| blob_error(key, &e))?;
+        }
+
+        // Write then rename so readers never see a partial blob.
+        let mut tmp = path.clone().into_os_string();
+        tmp.push(format!(".{}.tmp", Uuid::new_v4()));
+        tokio::fs::write(&tmp, data)
+            .await
+            .map_err(|e| blob_error(key, &e))?;
+        tokio::fs::rename(&tmp, &path)
+            .await
+            .map_err(|e| blob_error(key, &e))
+    }
+
+    async fn get(&self, key: &str) -> Result<Vec<u8>, StorageError> {
+        tokio::fs::read(self.path(key)?)
+            .await
+            .map_err(|e| blob_error(key, &e))
+    }
+
+    async fn stream(&self, key: &str) -> Result<BlobStream, StorageError> {
+        let file = tokio::fs::File::open(self.path(key)?)
+            .await
+            .map_err(|e| blob_error(key, &e))?;
+        let key = key.to_string();
+
+        Ok(stream::try_unfold(file, move |mut file| {
+            let key = key.clone();
+            async move {
+                let mut chunk = vec![0; STREAM_CHUNK_SIZE];
+                let read = file
+                    .read(&mut chunk)
+                    .await
+                    .map_err(|e| blob_error(&key, &e))?;
+                if read == 0 {
+                    return Ok(None);
+                }
+                chunk.truncate(read);
+                Ok(Some((chunk, file)))
+            }
+        })
+        .boxed())
+    }
+
+    async fn delete(&self, key: &str) -> Result<(), StorageError> {
+        match tokio::fs::remove_file(self.path(key)?).await {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(blob_error(key, &e)),
+            _ => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use futures::TryStreamExt;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_put_get_stream_delete() {
+        let root = std::env::temp_dir().join(format!("blob-store-{}", Uuid::new_v4()));
+        let store = FsBlobStore::new(&root);
+        let data: Vec<u8> = b"0123456789"
+            .iter()
+            .copied()
+            .cycle()
+            .take(STREAM_CHUNK_SIZE * 2 + 10)
+            .collect();
+
+        store.put("session/0001", data.clone()).await.unwrap();
+        assert_eq!(store.get("session/0001").await.unwrap(), data);
+
+        let stream = store.stream("session/0001").await.unwrap();
+        let chunks: Vec<Vec<u8>> = stream.try_collect().await.unwrap();
+        assert_eq!(chunks.len(), 3);
+        assert_eq!(chunks.concat(), data);
+
+        store.delete("session/0001").await.unwrap();
+        store.delete("session/0001").await.unwrap();
+        assert!(store.get("session/0001").await.is_err());
+        assert!(store.put("../escape", Vec::new()).await.is_err());
+
+        std::fs::remove_dir_all(root).unwrap();
+    }
+}
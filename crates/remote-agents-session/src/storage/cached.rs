@@ -0,0 +1,578 @@
+//! Read-through caching storage wrapper.
+
+use std::{
+    collections::HashMap,
+    future::Future,
+    sync::RwLock,
+    time::{Duration, Instant},
+};
+
+use async_trait::async_trait;
+use remote_agents_core::{
+    ExecutionContext,
+    traits::{
+        RunId, Session, SessionFilter, SessionId, SessionStatus, SessionStorage, StorageError,
+        TenantId,
+    },
+};
+
+/// Default time a cached result is served before it is re-read.
+pub const DEFAULT_CACHE_TTL: Duration = Duration::from_secs(5);
+
+struct Entry<T> {
+    value: T,
+    cached_at: Instant,
+}
+
+/// Cached results, and what it takes to keep a read that races a write
+/// from caching the result from before the write.
+struct Cache<K, T> {
+    entries: HashMap<K, Entry<T>>,
+    /// Times each key was invalidated while reads were in flight.
+    generations: HashMap<K, u64>,
+    /// Times the whole cache was cleared.
+    cleared: u64,
+    /// Reads of the wrapped storage in flight.
+    reading: usize,
+}
+
+/// When a read of the wrapped storage started, in invalidations.
+#[derive(Clone, Copy, PartialEq, Eq)]
+struct Generation {
+    key: u64,
+    cleared: u64,
+}
+
+impl<K: Eq + std::hash::Hash + Clone, T: Clone> Cache<K, T> {
+    fn new() -> Self {
+        Self {
+            entries: HashMap::new(),
+            generations: HashMap::new(),
+            cleared: 0,
+            reading: 0,
+        }
+    }
+
+    fn fresh(&self, key: &K, ttl: Duration) -> Option<T> {
+        self.entries
+            .get(key)
+            .filter(|entry| entry.cached_at.elapsed() < ttl)
+            .map(|entry| entry.value.clone())
+    }
+
+    fn generation(&self, key: &K) -> Generation {
+        Generation {
+            key: self.generations.get(key).copied().unwrap_or_default(),
+            cleared: self.cleared,
+        }
+    }
+
+    fn invalidate(&mut self, key: &K) {
+        self.entries.remove(key);
+        // Only reads already in flight can have missed the write.
+        if self.reading > 0 {
+            *self.generations.entry(key.clone()).or_default() += 1;
+        }
+    }
+
+    fn clear(&mut self) {
+        self.entries.clear();
+        self.cleared += 1;
+    }
+
+    /// Note a read of `key` from the wrapped storage starting.
+    fn start_read(&mut self, key: &K) -> Generation {
+        self.reading += 1;
+        self.generation(key)
+    }
+
+    /// Note a read started at `generation` finishing with `value`, caching
+    /// it unless `key` was invalidated since. Expired entries are evicted.
+    fn finish_read(&mut self, key: K, generation: Generation, value: T, ttl: Duration) {
+        let unchanged = self.generation(&key) == generation;
+        self.end_read();
+        self.entries
+            .retain(|_, entry| entry.cached_at.elapsed() < ttl);
+        if unchanged {
+            self.entries.insert(
+                key,
+                Entry {
+                    value,
+                    cached_at: Instant::now(),
+                },
+            );
+        }
+    }
+
+    /// Note a read finishing, with nothing to cache.
+    fn end_read(&mut self) {
+        self.reading -= 1;
+        if self.reading == 0 {
+            self.generations.clear();
+        }
+    }
+}
+
+/// Storage wrapper caching `get` and `list` results in memory.
+///
+/// Writes through the wrapper invalidate the affected entries immediately;
+/// writes made elsewhere (e.g. another process sharing the database) are
+/// seen once cached entries expire. Output is not cached.
+pub struct CachedStorage<S> {
+    inner: S,
+    ttl: Duration,
+    sessions: RwLock<Cache<SessionId, Option<Session>>>,
+    lists: RwLock<Cache<String, Vec<Session>>>,
+}
+
+impl<S: SessionStorage> CachedStorage<S> {
+    /// Wrap a storage, caching for [`DEFAULT_CACHE_TTL`].
+    #[must_use]
+    pub fn new(inner: S) -> Self {
+        Self {
+            inner,
+            ttl: DEFAULT_CACHE_TTL,
+            sessions: RwLock::new(Cache::new()),
+            lists: RwLock::new(Cache::new()),
+        }
+    }
+
+    /// Set how long cached results are served.
+    #[must_use]
+    pub const fn with_ttl(mut self, ttl: Duration) -> Self {
+        self.ttl = ttl;
+        self
+    }
+
+    /// The wrapped storage.
+    #[must_use]
+    pub const fn inner(&self) -> &S {
+        &self.inner
+    }
+
+    /// Drop all cached results.
+    ///
+    /// # Errors
+    /// Returns error if a cache lock is poisoned.
+    pub fn clear(&self) -> Result<(), StorageError> {
+        self.sessions.write().map_err(lock_error)?.clear();
+        self.lists.write().map_err(lock_error)?.clear();
+        Ok(())
+    }
+
+    /// Drop cached results a write to `id` may have changed.
+    fn invalidate(&self, id: SessionId) -> Result<(), StorageError> {
+        self.sessions.write().map_err(lock_error)?.invalidate(&id);
+        self.lists.write().map_err(lock_error)?.clear();
+        Ok(())
+    }
+
+    /// Serve `key` from `cache`, or read it with `read` and cache the
+    /// result unless the key was invalidated during the read.
+    async fn read_through<K, T, F>(
+        &self,
+        cache: &RwLock<Cache<K, T>>,
+        key: K,
+        read: F,
+    ) -> Result<T, StorageError>
+    where
+        K: Eq + std::hash::Hash + Clone + Send + Sync,
+        T: Clone + Send + Sync,
+        F: Future<Output = Result<T, StorageError>> + Send,
+    {
+        let cached = cache.read().map_err(lock_error)?.fresh(&key, self.ttl);
+        if let Some(value) = cached {
+            return Ok(value);
+        }
+
+        let generation = cache.write().map_err(lock_error)?.start_read(&key);
+        let mut reading = ReadGuard {
+            cache,
+            finished: false,
+        };
+        let value = read.await?;
+        cache
+            .write()
+            .map_err(lock_error)?
+            .finish_read(key, generation, value.clone(), self.ttl);
+        reading.finished = true;
+        Ok(value)
+    }
+}
+
+/// Ends a read of the wrapped storage that did not finish, e.g. because it
+/// failed or the caller gave up on it.
+struct ReadGuard<'a, K: Eq + std::hash::Hash + Clone, T: Clone> {
+    cache: &'a RwLock<Cache<K, T>>,
+    finished: bool,
+}
+
+impl<K: Eq + std::hash::Hash + Clone, T: Clone> Drop for ReadGuard<'_, K, T> {
+    fn drop(&mut self) {
+        if !self.finished {
+            if let Ok(mut cache) = self.cache.write() {
+                cache.end_read();
+            }
+        }
+    }
+}
+
+#[allow(clippy::needless_pass_by_value)]
+fn lock_error<E: std::fmt::Display>(e: E) -> StorageError {
+    StorageError::Internal(e.to_string())
+}
+
+#[async_trait]
+impl<S: SessionStorage> SessionStorage for CachedStorage<S> {
+    async fn create(&self, ctx: &ExecutionContext) -> Result<SessionId, StorageError> {
+        let id = self.inner.create(ctx).await?;
+        self.invalidate(id)?;
+        Ok(id)
+    }
+
+    async fn get(&self, id: SessionId) -> Result<Option<Session>, StorageError> {
+        self.read_through(&self.sessions, id, self.inner.get(id))
+            .await
+    }
+
+    async fn update_status(
+        &self,
+        id: SessionId,
+        status: SessionStatus,
+        expected_version: Option<u64>,
+    ) -> Result<(), StorageError> {
+        let result = self
+            .inner
+            .update_status(id, status, expected_version)
+            .await;
+        self.invalidate(id)?;
+        result
+    }
+
+    async fn update_context(
+        &self,
+        id: SessionId,
+        ctx: &ExecutionContext,
+        expected_version: Option<u64>,
+    ) -> Result<(), StorageError> {
+        let result = self.inner.update_context(id, ctx, expected_version).await;
+        self.invalidate(id)?;
+        result
+    }
+
+    async fn set_agent_session_id(
+        &self,
+        id: SessionId,
+        agent_session_id: String,
+    ) -> Result<(), StorageError> {
+        let result = self.inner.set_agent_session_id(id, agent_session_id).await;
+        self.invalidate(id)?;
+        result
+    }
+
+    async fn set_exit_code(&self, id: SessionId, exit_code: i32) -> Result<(), StorageError> {
+        let result = self.inner.set_exit_code(id, exit_code).await;
+        self.invalidate(id)?;
+        result
+    }
+
+    async fn set_status_reason(&self, id: SessionId, reason: String) -> Result<(), StorageError> {
+        let result = self.inner.set_status_reason(id, reason).await;
+        self.invalidate(id)?;
+        result
+    }
+
+    async fn set_prompt(&self, id: SessionId, prompt: String) -> Result<(), StorageError> {
+        let result = self.inner.set_prompt(id, prompt).await;
+        self.invalidate(id)?;
+        result
+    }
+
+    async fn set_parent_session_id(
+        &self,
+        id: SessionId,
+        parent_session_id: SessionId,
+    ) -> Result<(), StorageError> {
+        let result = self
+            .inner
+            .set_parent_session_id(id, parent_session_id)
+            .await;
+        self.invalidate(id)?;
+        result
+    }
+
+    async fn set_run_id(&self, id: SessionId, run_id: RunId) -> Result<(), StorageError> {
+        let result = self.inner.set_run_id(id, run_id).await;
+        self.invalidate(id)?;
+        result
+    }
+
+    async fn set_tenant_id(&self, id: SessionId, tenant_id: TenantId) -> Result<(), StorageError> {
+        let result = self.inner.set_tenant_id(id, tenant_id).await;
+        self.invalidate(id)?;
+        result
+    }
+
+    async fn soft_delete(&self, id: SessionId) -> Result<(), StorageError> {
+        let result = self.inner.soft_delete(id).await;
+        self.invalidate(id)?;
+        result
+    }
+
+    async fn restore(&self, id: SessionId) -> Result<(), StorageError> {
+        let result = self.inner.restore(id).await;
+        self.invalidate(id)?;
+        result
+    }
+
+    async fn get_children(&self, id: SessionId) -> Result<Vec<Session>, StorageError> {
+        self.inner.get_children(id).await
+    }
+
+    async fn list(&self, filter: SessionFilter) -> Result<Vec<Session>, StorageError> {
+        // Filters hold only plain values, so their debug form is a stable key.
+        let key = format!("{filter:?}");
+        self.read_through(&self.lists, key, self.inner.list(filter))
+            .await
+    }
+
+    async fn append_output(&self, id: SessionId, data: &[u8]) -> Result<(), StorageError> {
+        self.inner.append_output(id, data).await
+    }
+
+    async fn get_output(&self, id: SessionId) -> Result<Vec<u8>, StorageError> {
+        self.inner.get_output(id).await
+    }
+
+    async fn get_output_range(
+        &self,
+        id: SessionId,
+        offset: u64,
+        len: usize,
+    ) -> Result<Vec<u8>, StorageError> {
+        self.inner.get_output_range(id, offset, len).await
+    }
+
+    async fn output_len(&self, id: SessionId) -> Result<u64, StorageError> {
+        self.inner.output_len(id).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{path::PathBuf, sync::Arc};
+
+    use super::*;
+    use crate::storage::MemoryStorage;
+
+    #[tokio::test]
+    async fn test_caches_until_written_through() {
+        let storage = CachedStorage::new(MemoryStorage::new());
+        let id = storage
+            .create(&ExecutionContext::new(PathBuf::from("/tmp")))
+            .await
+            .unwrap();
+        assert_eq!(
+            storage.list(SessionFilter::default()).await.unwrap().len(),
+            1
+        );
+        assert_eq!(
+            storage.get(id).await.unwrap().unwrap().status,
+            SessionStatus::Pending
+        );
+
+        // Writes that bypass the wrapper are not seen while cached.
+        storage
+            .inner()
+            .update_status(id, SessionStatus::Running, None)
+            .await
+            .unwrap();
+        storage
+            .inner()
+            .create(&ExecutionContext::new(PathBuf::from("/tmp")))
+            .await
+            .unwrap();
+        assert_eq!(
+            storage.get(id).await.unwrap().unwrap().status,
+            SessionStatus::Pending
+        );
+        assert_eq!(
+            storage.list(SessionFilter::default()).await.unwrap().len(),
+            1
+        );
+
+        storage.set_exit_code(id, 0).await.unwrap();
+        let session = storage.get(id).await.unwrap().unwrap();
+        assert_eq!(session.status, SessionStatus::Running);
+        assert_eq!(session.exit_code, Some(0));
+        assert_eq!(
+            storage.list(SessionFilter::default()).await.unwrap().len(),
+            2
+        );
+    }
+
+    /// Storage whose `get` waits for a signal after reading.
+    struct SlowGet {
+        inner: MemoryStorage,
+        read: tokio::sync::Notify,
+        resume: tokio::sync::Notify,
+    }
+
+    #[async_trait]
+    impl SessionStorage for SlowGet {
+        async fn create(&self, ctx: &ExecutionContext) -> Result<SessionId, StorageError> {
+            self.inner.create(ctx).await
+        }
+
+        async fn get(&self, id: SessionId) -> Result<Option<Session>, StorageError> {
+            let session = self.inner.get(id).await?;
+            self.read.notify_one();
+            self.resume.notified().await;
+            Ok(session)
+        }
+
+        async fn update_status(
+            &self,
+            id: SessionId,
+            status: SessionStatus,
+            expected_version: Option<u64>,
+        ) -> Result<(), StorageError> {
+            self.inner.update_status(id, status, expected_version).await
+        }
+
+        async fn update_context(
+            &self,
+            id: SessionId,
+            ctx: &ExecutionContext,
+            expected_version: Option<u64>,
+        ) -> Result<(), StorageError> {
+            self.inner.update_context(id, ctx, expected_version).await
+        }
+
+        async fn set_agent_session_id(
+            &self,
+            id: SessionId,
+            agent_session_id: String,
+        ) -> Result<(), StorageError> {
+            self.inner.set_agent_session_id(id, agent_session_id).await
+        }
+
+        async fn set_exit_code(&self, id: SessionId, exit_code: i32) -> Result<(), StorageError> {
+            self.inner.set_exit_code(id, exit_code).await
+        }
+
+        async fn set_status_reason(
+            &self,
+            id: SessionId,
+            reason: String,
+        ) -> Result<(), StorageError> {
+            self.inner.set_status_reason(id, reason).await
+        }
+
+        async fn set_prompt(&self, id: SessionId, prompt: String) -> Result<(), StorageError> {
+            self.inner.set_prompt(id, prompt).await
+        }
+
+        async fn set_parent_session_id(
+            &self,
+            id: SessionId,
+            parent_session_id: SessionId,
+        ) -> Result<(), StorageError> {
+            self.inner
+                .set_parent_session_id(id, parent_session_id)
+                .await
+        }
+
+        async fn set_run_id(&self, id: SessionId, run_id: RunId) -> Result<(), StorageError> {
+            self.inner.set_run_id(id, run_id).await
+        }
+
+        async fn set_tenant_id(
+            &self,
+            id: SessionId,
+            tenant_id: TenantId,
+        ) -> Result<(), StorageError> {
+            self.inner.set_tenant_id(id, tenant_id).await
+        }
+
+        async fn soft_delete(&self, id: SessionId) -> Result<(), StorageError> {
+            self.inner.soft_delete(id).await
+        }
+
+        async fn restore(&self, id: SessionId) -> Result<(), StorageError> {
+            self.inner.restore(id).await
+        }
+
+        async fn get_children(&self, id: SessionId) -> Result<Vec<Session>, StorageError> {
+            self.inner.get_children(id).await
+        }
+
+        async fn list(&self, filter: SessionFilter) -> Result<Vec<Session>, StorageError> {
+            self.inner.list(filter).await
+        }
+
+        async fn append_output(&self, id: SessionId, data: &[u8]) -> Result<(), StorageError> {
+            self.inner.append_output(id, data).await
+        }
+
+        async fn get_output(&self, id: SessionId) -> Result<Vec<u8>, StorageError> {
+            self.inner.get_output(id).await
+        }
+    }
+
+    #[tokio::test]
+    async fn test_read_racing_write_is_not_cached() {
+        let storage = Arc::new(CachedStorage::new(SlowGet {
+            inner: MemoryStorage::new(),
+            read: tokio::sync::Notify::new(),
+            resume: tokio::sync::Notify::new(),
+        }));
+        let id = storage
+            .create(&ExecutionContext::new(PathBuf::from("/tmp")))
+            .await
+            .unwrap();
+
+        // The read sees the session before the write, but returns after it.
+        let reader = tokio::spawn({
+            let storage = Arc::clone(&storage);
+            async move { storage.get(id).await }
+        });
+        storage.inner().read.notified().await;
+        storage
+            .update_status(id, SessionStatus::Running, None)
+            .await
+            .unwrap();
+        storage.inner().resume.notify_one();
+        let stale = reader.await.unwrap().unwrap().unwrap();
+        assert_eq!(stale.status, SessionStatus::Pending);
+
+        let reader = tokio::spawn({
+            let storage = Arc::clone(&storage);
+            async move { storage.get(id).await }
+        });
+        storage.inner().read.notified().await;
+        storage.inner().resume.notify_one();
+        let session = reader.await.unwrap().unwrap().unwrap();
+        assert_eq!(session.status, SessionStatus::Running);
+    }
+
+    #[tokio::test]
+    async fn test_entries_expire() {
+        let storage = CachedStorage::new(MemoryStorage::new()).with_ttl(Duration::ZERO);
+        let id = storage
+            .create(&ExecutionContext::new(PathBuf::from("/tmp")))
+            .await
+            .unwrap();
+        assert!(storage.get(id).await.unwrap().is_some());
+
+        storage
+            .inner()
+            .update_status(id, SessionStatus::Running, None)
+            .await
+            .unwrap();
+        assert_eq!(
+            storage.get(id).await.unwrap().unwrap().status,
+            SessionStatus::Running
+        );
+    }
+}
@@ -0,0 +1,25 @@
+//! Output compression shared by the persistent storage backends.
+
+use remote_agents_core::traits::StorageError;
+
+/// Name recorded for zstd-compressed output.
+pub(super) const ZSTD: &str = "zstd";
+
+/// zstd level used for output; favours speed, as transcripts compress well
+/// at any level.
+const ZSTD_LEVEL: i32 = 3;
+
+/// Compress output with zstd.
+pub(super) fn compress(data: &[u8]) -> Result<Vec<u8>, StorageError> {
+    zstd::encode_all(data, ZSTD_LEVEL).map_err(|e| StorageError::Internal(e.to_string()))
+}
+
+/// Decompress output compressed with the named algorithm.
+pub(super) fn decompress(compression: &str, data: &[u8]) -> Result<Vec<u8>, StorageError> {
+    match compression {
+        ZSTD => zstd::decode_all(data).map_err(|e| StorageError::Internal(e.to_string())),
+        other => Err(StorageError::Internal(format!(
+            "Unknown output compression: {other}"
+        ))),
+    }
+}
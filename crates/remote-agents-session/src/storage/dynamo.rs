@@ -0,0 +1,702 @@
+//! `DynamoDB` session storage (feature-gated).
+//!
+//! Everything lives in one table keyed by `pk`/`sk`. A session is one item
+//! (`pk = SESSION#<id>`, `sk = SESSION`) with an attribute per field, and
+//! its output is a series of chunk items in the same partition
+//! (`sk = OUTPUT#<seq>`), read back in order with a single query. Two
+//! global secondary indexes serve status and working directory lookups,
+//! newest first; other listings scan the table.
+
+use std::{collections::HashMap, fmt::Write as _};
+
+use async_trait::async_trait;
+use aws_sdk_dynamodb::{
+    Client,
+    error::DisplayErrorContext,
+    operation::update_item::UpdateItemError,
+    primitives::Blob,
+    types::{
+        AttributeDefinition, AttributeValue, BillingMode, GlobalSecondaryIndex, KeySchemaElement,
+        KeyType, Projection, ProjectionType, ReturnValue, ScalarAttributeType,
+    },
+};
+use remote_agents_core::{
+    ExecutionContext,
+    traits::{
+        RunId, Session, SessionFilter, SessionId, SessionStatus, SessionStorage, StorageError,
+        TenantId,
+    },
+};
+use serde_json::Value;
+use uuid::Uuid;
+
+/// Index over `status`, sorted by `created_at`.
+pub const STATUS_INDEX: &str = "status-index";
+
+/// Index over `working_dir`, sorted by `created_at`.
+pub const WORKING_DIR_INDEX: &str = "working_dir-index";
+
+/// Largest output chunk stored in one item, well under the 400 KB item
+/// limit.
+const OUTPUT_CHUNK_SIZE: usize = 256 * 1024;
+
+const SESSION_SK: &str = "SESSION";
+const OUTPUT_SK_PREFIX: &str = "OUTPUT#";
+
+type Item = HashMap<String, AttributeValue>;
+
+/// `DynamoDB` storage implementation.
+///
+/// Suits serverless and AWS-native deployments that would rather not run a
+/// database. The table can be created with [`DynamoStorage::create_table`]
+/// or provisioned separately with the same key schema and indexes.
+pub struct DynamoStorage {
+    client: Client,
+    table: String,
+}
+
+impl DynamoStorage {
+    /// Use `table` through a configured client (e.g. from `aws-config`).
+    #[must_use]
+    pub fn new(client: Client, table: impl Into<String>) -> Self {
+        Self {
+            client,
+            table: table.into(),
+        }
+    }
+
+    /// Create the table and its indexes, billed on demand.
+    ///
+    /// # Errors
+    /// Returns error if the table cannot be created (e.g. it already exists).
+    pub async fn create_table(&self) -> Result<(), StorageError> {
+        let index = |name: &str, key: &str| {
+            GlobalSecondaryIndex::builder()
+                .index_name(name)
+                .key_schema(key_schema(key, KeyType::Hash)?)
+                .key_schema(key_schema("created_at", KeyType::Range)?)
+                .projection(
+                    Projection::builder()
+                        .projection_type(ProjectionType::All)
+                        .build(),
+                )
+                .build()
+                .map_err(build_error)
+        };
+
+        self.client
+            .create_table()
+            .table_name(&self.table)
+            .billing_mode(BillingMode::PayPerRequest)
+            .key_schema(key_schema("pk", KeyType::Hash)?)
+            .key_schema(key_schema("sk", KeyType::Range)?)
+            .attribute_definitions(attribute("pk", ScalarAttributeType::S)?)
+            .attribute_definitions(attribute("sk", ScalarAttributeType::S)?)
+            .attribute_definitions(attribute("status", ScalarAttributeType::S)?)
+            .attribute_definitions(attribute("working_dir", ScalarAttributeType::S)?)
+            .attribute_definitions(attribute("created_at", ScalarAttributeType::N)?)
+            .global_secondary_indexes(index(STATUS_INDEX, "status")?)
+            .global_secondary_indexes(index(WORKING_DIR_INDEX, "working_dir")?)
+            .send()
+            .await
+            .map_err(db_error)?;
+        Ok(())
+    }
+
+    async fn get_item(&self, id: SessionId) -> Result<Option<Item>, StorageError> {
+        let output = self
+            .client
+            .get_item()
+            .table_name(&self.table)
+            .key("pk", partition_key(id))
+            .key("sk", s(SESSION_SK))
+            .consistent_read(true)
+            .send()
+            .await
+            .map_err(db_error)?;
+        Ok(output.item)
+    }
+
+    /// Set session attributes, bumping `updated_at`.
+    async fn set_attributes(
+        &self,
+        id: SessionId,
+        attributes: Vec<(&'static str, AttributeValue)>,
+    ) -> Result<(), StorageError> {
+        self.update(id, attributes, "", "attribute_exists(pk)", Vec::new())
+            .await
+    }
+
+    /// Set status or context attributes, bumping the session's version.
+    async fn update_versioned(
+        &self,
+        id: SessionId,
+        attributes: Vec<(&'static str, AttributeValue)>,
+        expected_version: Option<u64>,
+    ) -> Result<(), StorageError> {
+        let mut condition = "attribute_exists(pk)";
+        let mut values = vec![(":one", n(1))];
+        if let Some(version) = expected_version {
+            condition = "attribute_exists(pk) AND version = :expected_version";
+            values.push((":expected_version", n(version)));
+        }
+        let result = self
+            .update(
+                id,
+                attributes,
+                ", version = version + :one",
+                condition,
+                values,
+            )
+            .await;
+
+        match result {
+            Err(StorageError::NotFound(_))
+                if expected_version.is_some() && self.get_item(id).await?.is_some() =>
+            {
+                Err(StorageError::Conflict(id))
+            }
+            result => result,
+        }
+    }
+
+    /// Apply an update to a session item, failing with `NotFound` if
+    /// `condition` does not hold.
+    async fn update(
+        &self,
+        id: SessionId,
+        attributes: Vec<(&'static str, AttributeValue)>,
+        extra: &str,
+        condition: &str,
+        values: Vec<(&'static str, AttributeValue)>,
+    ) -> Result<(), StorageError> {
+        let mut expression = String::from("SET updated_at = :updated_at");
+        let mut request = self
+            .client
+            .update_item()
+            .table_name(&self.table)
+            .key("pk", partition_key(id))
+            .key("sk", s(SESSION_SK))
+            .condition_expression(condition)
+            .expression_attribute_values(":updated_at", n(now()));
+        for (name, value) in attributes {
+            let _ = write!(expression, ", #{name} = :{name}");
+            request = request
+                .expression_attribute_names(format!("#{name}"), name)
+                .expression_attribute_values(format!(":{name}"), value);
+        }
+        expression.push_str(extra);
+        for (name, value) in values {
+            request = request.expression_attribute_values(name, value);
+        }
+
+        match request.update_expression(expression).send().await {
+            Ok(_) => Ok(()),
+            Err(e)
+                if e.as_service_error()
+                    .is_some_and(UpdateItemError::is_conditional_check_failed_exception) =>
+            {
+                Err(StorageError::NotFound(id))
+            }
+            Err(e) => Err(db_error(e)),
+        }
+    }
+
+    /// Run a query or scan page by page, collecting the sessions.
+    async fn collect_sessions<F, Fut>(&self, mut page: F) -> Result<Vec<Session>, StorageError>
+    where
+        F: FnMut(Option<Item>) -> Fut,
+        Fut: Future<Output = Result<(Vec<Item>, Option<Item>), StorageError>>,
+    {
+        let mut sessions = Vec::new();
+        let mut start = None;
+        loop {
+            let (items, next) = page(start).await?;
+            for item in &items {
+                sessions.push(session_from_item(item)?);
+            }
+            match next {
+                Some(next) => start = Some(next),
+                None => return Ok(sessions),
+            }
+        }
+    }
+
+    /// Sessions whose index attribute `key` equals `value`, newest first.
+    async fn query_index(
+        &self,
+        index: &str,
+        key: &'static str,
+        value: String,
+    ) -> Result<Vec<Session>, StorageError> {
+        self.collect_sessions(|start| {
+            let request = self
+                .client
+                .query()
+                .table_name(&self.table)
+                .index_name(index)
+                .key_condition_expression("#key = :value")
+                .expression_attribute_names("#key", key)
+                .expression_attribute_values(":value", s(value.clone()))
+                .scan_index_forward(false)
+                .set_exclusive_start_key(start);
+            async move {
+                let output = request.send().await.map_err(db_error)?;
+                Ok((output.items.unwrap_or_default(), output.last_evaluated_key))
+            }
+        })
+        .await
+    }
+
+    /// Scan for session items, optionally narrowed by an attribute value.
+    async fn scan_sessions(
+        &self,
+        attribute: Option<(&'static str, String)>,
+    ) -> Result<Vec<Session>, StorageError> {
+        self.collect_sessions(|start| {
+            let mut request = self
+                .client
+                .scan()
+                .table_name(&self.table)
+                .expression_attribute_values(":sk", s(SESSION_SK))
+                .set_exclusive_start_key(start);
+            request = match attribute.clone() {
+                Some((name, value)) => request
+                    .filter_expression("sk = :sk AND #attr = :value")
+                    .expression_attribute_names("#attr", name)
+                    .expression_attribute_values(":value", s(value)),
+                None => request.filter_expression("sk = :sk"),
+            };
+            async move {
+                let output = request.send().await.map_err(db_error)?;
+                Ok((output.items.unwrap_or_default(), output.last_evaluated_key))
+            }
+        })
+        .await
+    }
+}
+
+fn now() -> i64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map_or(0, |d| i64::try_from(d.as_secs()).unwrap_or(i64::MAX))
+}
+
+fn db_error<E: std::error::Error + 'static>(e: E) -> StorageError {
+    StorageError::Internal(DisplayErrorContext(e).to_string())
+}
+
+#[allow(clippy::needless_pass_by_value)]
+fn build_error(e: aws_sdk_dynamodb::error::BuildError) -> StorageError {
+    StorageError::Internal(e.to_string())
+}
+
+#[allow(clippy::needless_pass_by_value)]
+fn json_error(e: serde_json::Error) -> StorageError {
+    StorageError::Internal(e.to_string())
+}
+
+fn key_schema(name: &str, key_type: KeyType) -> Result<KeySchemaElement, StorageError> {
+    KeySchemaElement::builder()
+        .attribute_name(name)
+        .key_type(key_type)
+        .build()
+        .map_err(build_error)
+}
+
+fn attribute(
+    name: &str,
+    attribute_type: ScalarAttributeType,
+) -> Result<AttributeDefinition, StorageError> {
+    AttributeDefinition::builder()
+        .attribute_name(name)
+        .attribute_type(attribute_type)
+        .build()
+        .map_err(build_error)
+}
+
+fn partition_key(id: SessionId) -> AttributeValue {
+    s(format!("SESSION#{id}"))
+}
+
+fn s(value: impl Into<String>) -> AttributeValue {
+    AttributeValue::S(value.into())
+}
+
+#[allow(clippy::needless_pass_by_value)]
+fn n(value: impl ToString) -> AttributeValue {
+    AttributeValue::N(value.to_string())
+}
+
+fn status_str(status: SessionStatus) -> Result<String, StorageError> {
+    match serde_json::to_value(status).map_err(json_error)? {
+        Value::String(status) => Ok(status),
+        other => Err(StorageError::Internal(format!(
+            "Expected string, got {other}"
+        ))),
+    }
+}
+
+fn context_attributes(
+    ctx: &ExecutionContext,
+) -> Result<Vec<(&'static str, AttributeValue)>, StorageError> {
+    Ok(vec![
+        (
+            "context",
+            s(serde_json::to_string(ctx).map_err(json_error)?),
+        ),
+        ("working_dir", s(ctx.working_dir.to_string_lossy())),
+    ])
+}
+
+fn get_s<'a>(item: &'a Item, name: &str) -> Option<&'a String> {
+    item.get(name).and_then(|value| value.as_s().ok())
+}
+
+fn get_n<T: std::str::FromStr>(item: &Item, name: &str) -> Result<Option<T>, StorageError> {
+    item.get(name)
+        .and_then(|value| value.as_n().ok())
+        .map(|value| {
+            value
+                .parse()
+                .map_err(|_| StorageError::Internal(format!("Invalid number in {name}: {value}")))
+        })
+        .transpose()
+}
+
+fn required<T>(value: Option<T>, name: &str) -> Result<T, StorageError> {
+    value.ok_or_else(|| StorageError::Internal(format!("Session item is missing {name}")))
+}
+
+fn parse_id(value: &str) -> Result<SessionId, StorageError> {
+    Uuid::parse_str(value).map_err(|e| StorageError::Internal(e.to_string()))
+}
+
+fn session_from_item(item: &Item) -> Result<Session, StorageError> {
+    let context = required(get_s(item, "context"), "context")?;
+    let status = required(get_s(item, "status"), "status")?;
+
+    Ok(Session {
+        id: parse_id(required(get_s(item, "id"), "id")?)?,
+        context: serde_json::from_str(context).map_err(json_error)?,
+        prompt: get_s(item, "prompt").cloned(),
+        status: serde_json::from_value(Value::String(status.clone())).map_err(json_error)?,
+        agent_session_id: get_s(item, "agent_session_id").cloned(),
+        parent_session_id: get_s(item, "parent_session_id")
+            .map(String::as_str)
+            .map(parse_id)
+            .transpose()?,
+        run_id: get_s(item, "run_id").cloned(),
+        tenant_id: get_s(item, "tenant_id").cloned(),
+        exit_code: get_n(item, "exit_code")?,
+        status_reason: get_s(item, "status_reason").cloned(),
+        deleted: item
+            .get("deleted")
+            .and_then(|value| value.as_bool().ok())
+            .copied()
+            .unwrap_or(false),
+        created_at: required(get_n(item, "created_at")?, "created_at")?,
+        updated_at: required(get_n(item, "updated_at")?, "updated_at")?,
+        version: get_n(item, "version")?.unwrap_or(0),
+    })
+}
+
+#[async_trait]
+impl SessionStorage for DynamoStorage {
+    async fn create(&self, ctx: &ExecutionContext) -> Result<SessionId, StorageError> {
+        let id = Uuid::new_v4();
+        let timestamp = now();
+
+        let mut request = self
+            .client
+            .put_item()
+            .table_name(&self.table)
+            .item("pk", partition_key(id))
+            .item("sk", s(SESSION_SK))
+            .item("id", s(id.to_string()))
+            .item("status", s(status_str(SessionStatus::Pending)?))
+            .item("deleted", AttributeValue::Bool(false))
+            .item("created_at", n(timestamp))
+            .item("updated_at", n(timestamp))
+            .item("version", n(0))
+            .item("output_seq", n(0))
+            .item("output_len", n(0));
+        for (name, value) in context_attributes(ctx)? {
+            request = request.item(name, value);
+        }
+        request.send().await.map_err(db_error)?;
+
+        Ok(id)
+    }
+
+    async fn get(&self, id: SessionId) -> Result<Option<Session>, StorageError> {
+        self.get_item(id)
+            .await?
+            .as_ref()
+            .map(session_from_item)
+            .transpose()
+    }
+
+    async fn update_status(
+        &self,
+        id: SessionId,
+        status: SessionStatus,
+        expected_version: Option<u64>,
+    ) -> Result<(), StorageError> {
+        let attributes = vec![("status", s(status_str(status)?))];
+        self.update_versioned(id, attributes, expected_version)
+            .await
+    }
+
+    async fn update_context(
+        &self,
+        id: SessionId,
+        ctx: &ExecutionContext,
+        expected_version: Option<u64>,
+    ) -> Result<(), StorageError> {
+        self.update_versioned(id, context_attributes(ctx)?, expected_version)
+            .await
+    }
+
+    async fn set_agent_session_id(
+        &self,
+        id: SessionId,
+        agent_session_id: String,
+    ) -> Result<(), StorageError> {
+        self.set_attributes(id, vec![("agent_session_id", s(agent_session_id))])
+            .await
+    }
+
+    async fn set_exit_code(&self, id: SessionId, exit_code: i32) -> Result<(), StorageError> {
+        self.set_attributes(id, vec![("exit_code", n(exit_code))])
+            .await
+    }
+
+    async fn set_status_reason(&self, id: SessionId, reason: String) -> Result<(), StorageError> {
+        self.set_attributes(id, vec![("status_reason", s(reason))])
+            .await
+    }
+
+    async fn set_prompt(&self, id: SessionId, prompt: String) -> Result<(), StorageError> {
+        self.set_attributes(id, vec![("prompt", s(prompt))]).await
+    }
+
+    async fn set_parent_session_id(
+        &self,
+        id: SessionId,
+        parent_session_id: SessionId,
+    ) -> Result<(), StorageError> {
+        let parent = s(parent_session_id.to_string());
+        self.set_attributes(id, vec![("parent_session_id", parent)])
+            .await
+    }
+
+    async fn set_run_id(&self, id: SessionId, run_id: RunId) -> Result<(), StorageError> {
+        self.set_attributes(id, vec![("run_id", s(run_id))]).await
+    }
+
+    async fn set_tenant_id(&self, id: SessionId, tenant_id: TenantId) -> Result<(), StorageError> {
+        self.set_attributes(id, vec![("tenant_id", s(tenant_id))])
+            .await
+    }
+
+    async fn soft_delete(&self, id: SessionId) -> Result<(), StorageError> {
+        self.set_attributes(id, vec![("deleted", AttributeValue::Bool(true))])
+            .await
+    }
+
+    async fn restore(&self, id: SessionId) -> Result<(), StorageError> {
+        self.set_attributes(id, vec![("deleted", AttributeValue::Bool(false))])
+            .await
+    }
+
+    async fn get_children(&self, id: SessionId) -> Result<Vec<Session>, StorageError> {
+        let mut children = self
+            .scan_sessions(Some(("parent_session_id", id.to_string())))
+            .await?;
+        children.sort_by_key(|session| session.created_at);
+        Ok(children)
+    }
+
+    async fn list(&self, filter: SessionFilter) -> Result<Vec<Session>, StorageError> {
+        let mut sessions = if let Some(status) = filter.status {
+            self.query_index(STATUS_INDEX, "status", status_str(status)?)
+                .await?
+        } else if let Some(ref working_dir) = filter.working_dir {
+            let working_dir = working_dir.to_string_lossy().into_owned();
+            self.query_index(WORKING_DIR_INDEX, "working_dir", working_dir)
+                .await?
+        } else {
+            let mut sessions = self.scan_sessions(None).await?;
+            sessions.sort_by_key(|session| std::cmp::Reverse(session.created_at));
+            sessions
+        };
+
+        sessions.retain(|session| filter.matches(session));
+        if let Some(limit) = filter.limit {
+            sessions.truncate(limit);
+        }
+        Ok(sessions)
+    }
+
+    async fn append_output(&self, id: SessionId, data: &[u8]) -> Result<(), StorageError> {
+        if data.is_empty() {
+            return match self.get_item(id).await? {
+                Some(_) => Ok(()),
+                None => Err(StorageError::NotFound(id)),
+            };
+        }
+
+        for chunk in data.chunks(OUTPUT_CHUNK_SIZE) {
+            // Reserve the chunk's sequence number on the session item.
+            let reserved = self
+                .client
+                .update_item()
+                .table_name(&self.table)
+                .key("pk", partition_key(id))
+                .key("sk", s(SESSION_SK))
+                .update_expression("ADD output_seq :one, output_len :len")
+                .condition_expression("attribute_exists(pk)")
+                .expression_attribute_values(":one", n(1))
+                .expression_attribute_values(":len", n(chunk.len()))
+                .return_values(ReturnValue::UpdatedNew)
+                .send()
+                .await;
+            let reserved = match reserved {
+                Ok(output) => output,
+                Err(e)
+                    if e.as_service_error()
+                        .is_some_and(UpdateItemError::is_conditional_check_failed_exception) =>
+                {
+                    return Err(StorageError::NotFound(id));
+                }
+                Err(e) => return Err(db_error(e)),
+            };
+            let seq: u64 = reserved
+                .attributes
+                .as_ref()
+                .map(|attributes| get_n(attributes, "output_seq"))
+                .transpose()?
+                .flatten()
+                .ok_or_else(|| StorageError::Internal("Missing output sequence".to_string()))?;
+
+            self.client
+                .put_item()
+                .table_name(&self.table)
+                .item("pk", partition_key(id))
+                .item("sk", s(format!("{OUTPUT_SK_PREFIX}{:020}", seq - 1)))
+                .item("data", AttributeValue::B(Blob::new(chunk)))
+                .send()
+                .await
+                .map_err(db_error)?;
+        }
+        Ok(())
+    }
+
+    async fn get_output(&self, id: SessionId) -> Result<Vec<u8>, StorageError> {
+        let mut output = Vec::new();
+        let mut start = None;
+        loop {
+            let page = self
+                .client
+                .query()
+                .table_name(&self.table)
+                .key_condition_expression("pk = :pk AND begins_with(sk, :prefix)")
+                .expression_attribute_values(":pk", partition_key(id))
+                .expression_attribute_values(":prefix", s(OUTPUT_SK_PREFIX))
+                .consistent_read(true)
+                .set_exclusive_start_key(start)
+                .send()
+                .await
+                .map_err(db_error)?;
+            for item in page.items() {
+                if let Some(data) = item.get("data").and_then(|data| data.as_b().ok()) {
+                    output.extend_from_slice(data.as_ref());
+                }
+            }
+            match page.last_evaluated_key {
+                Some(next) => start = Some(next),
+                None => break,
+            }
+        }
+
+        if output.is_empty() && self.get_item(id).await?.is_none() {
+            return Err(StorageError::NotFound(id));
+        }
+        Ok(output)
+    }
+
+    async fn output_len(&self, id: SessionId) -> Result<u64, StorageError> {
+        let item = self.get_item(id).await?.ok_or(StorageError::NotFound(id))?;
+        Ok(get_n(&item, "output_len")?.unwrap_or(0))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use aws_sdk_dynamodb::config::{BehaviorVersion, Credentials, Region};
+
+    use super::*;
+
+    /// Create a scratch table on the endpoint named by
+    /// `DYNAMODB_TEST_ENDPOINT` (e.g. a local `DynamoDB`).
+    async fn storage() -> DynamoStorage {
+        let endpoint = std::env::var("DYNAMODB_TEST_ENDPOINT")
+            .expect("DYNAMODB_TEST_ENDPOINT must point at a DynamoDB endpoint");
+        let config = aws_sdk_dynamodb::Config::builder()
+            .behavior_version(BehaviorVersion::latest())
+            .endpoint_url(endpoint)
+            .region(Region::new("us-east-1"))
+            .credentials_provider(Credentials::new("test", "test", None, None, "test"))
+            .build();
+        let storage = DynamoStorage::new(
+            Client::from_conf(config),
+            format!("sessions-{}", Uuid::new_v4()),
+        );
+        storage.create_table().await.unwrap();
+        storage
+    }
+
+    #[tokio::test]
+    #[ignore = "requires DynamoDB (set DYNAMODB_TEST_ENDPOINT)"]
+    async fn test_session_round_trip() {
+        let storage = storage().await;
+        let id = storage
+            .create(&ExecutionContext::new(PathBuf::from("/tmp/project")))
+            .await
+            .unwrap();
+
+        storage
+            .update_status(id, SessionStatus::Running, Some(0))
+            .await
+            .unwrap();
+        assert!(matches!(
+            storage
+                .update_status(id, SessionStatus::Failed, Some(0))
+                .await,
+            Err(StorageError::Conflict(_))
+        ));
+        storage.set_prompt(id, "fix it".into()).await.unwrap();
+        storage.append_output(id, b"hello ").await.unwrap();
+        storage.append_output(id, b"world").await.unwrap();
+
+        let session = storage.get(id).await.unwrap().unwrap();
+        assert_eq!(session.status, SessionStatus::Running);
+        assert_eq!(session.prompt.as_deref(), Some("fix it"));
+        assert_eq!(session.version, 1);
+        assert_eq!(storage.get_output(id).await.unwrap(), b"hello world");
+        assert_eq!(storage.output_len(id).await.unwrap(), 11);
+
+        let filter = SessionFilter {
+            status: Some(SessionStatus::Running),
+            ..Default::default()
+        };
+        assert_eq!(storage.list(filter).await.unwrap().len(), 1);
+        assert!(matches!(
+            storage.set_exit_code(Uuid::new_v4(), 0).await,
+            Err(StorageError::NotFound(_))
+        ));
+    }
+}
@@ -0,0 +1,713 @@
+//! Encryption at rest for any session storage (feature-gated).
+
+use std::{collections::HashMap, sync::Arc};
+
+use aes_gcm::{
+    Aes256Gcm, Key, Nonce,
+    aead::{Aead, AeadCore, KeyInit, OsRng, Payload},
+};
+use async_trait::async_trait;
+use base64::{Engine, engine::general_purpose::STANDARD};
+use remote_agents_core::{
+    ExecutionContext, LogMsg,
+    traits::{
+        EventSeq, EventStorage, RunId, Session, SessionFilter, SessionId, SessionStatus,
+        SessionStorage, StorageError, StoredEvent, TenantId,
+    },
+};
+use serde_json::Value;
+
+/// Metadata key holding a session's encrypted metadata in the wrapped storage.
+pub const ENCRYPTED_METADATA_KEY: &str = "encrypted_metadata";
+
+/// Prefix of encrypted strings in the wrapped storage.
+const STRING_PREFIX: &str = "enc:v1:";
+
+/// Marker starting every encrypted frame.
+const FRAME_MAGIC: &[u8; 4] = b"RAE1";
+
+/// Length of an AES-GCM nonce.
+const NONCE_LEN: usize = 12;
+
+/// Length of an AES-GCM authentication tag.
+const TAG_LEN: usize = 16;
+
+/// A 256-bit data encryption key.
+pub type EncryptionKey = [u8; 32];
+
+/// Supplies the keys `EncryptedStorage` encrypts and decrypts with.
+///
+/// Every ciphertext records the ID of the key it was made with, so keys can
+/// be rotated by changing the current key while still serving old ones.
+#[async_trait]
+pub trait KeyProvider: Send + Sync {
+    /// ID of the key new data is encrypted with.
+    fn current_key_id(&self) -> String;
+
+    /// Get a key by ID.
+    async fn key(&self, key_id: &str) -> Result<EncryptionKey, StorageError>;
+}
+
+/// Key provider holding keys in memory.
+#[derive(Clone)]
+pub struct StaticKeyProvider {
+    current: String,
+    keys: HashMap<String, EncryptionKey>,
+}
+
+impl StaticKeyProvider {
+    /// Create a provider encrypting with `key`.
+    #[must_use]
+    pub fn new(key_id: impl Into<String>, key: EncryptionKey) -> Self {
+        let current = key_id.into();
+        Self {
+            keys: HashMap::from([(current.clone(), key)]),
+            current,
+        }
+    }
+
+    /// Add a key that is only used to decrypt (e.g. a rotated-out key).
+    #[must_use]
+    pub fn with_key(mut self, key_id: impl Into<String>, key: EncryptionKey) -> Self {
+        self.keys.insert(key_id.into(), key);
+        self
+    }
+}
+
+#[async_trait]
+impl KeyProvider for StaticKeyProvider {
+    fn current_key_id(&self) -> String {
+        self.current.clone()
+    }
+
+    async fn key(&self, key_id: &str) -> Result<EncryptionKey, StorageError> {
+        self.keys
+            .get(key_id)
+            .copied()
+            .ok_or_else(|| StorageError::Internal(format!("Unknown encryption key: {key_id}")))
+    }
+}
+
+/// Storage wrapper encrypting session metadata, prompts, output, and
+/// events with AES-256-GCM before they reach the wrapped storage.
+///
+/// Working directories, statuses, and other bookkeeping fields stay in
+/// plaintext so the wrapped storage can still filter on them; text queries
+/// are matched after decryption. Output is encrypted per append, so
+/// compression in the wrapped storage has no effect on it. Events keep
+/// their type, with their text and patch values encrypted.
+pub struct EncryptedStorage<S> {
+    inner: S,
+    keys: Arc<dyn KeyProvider>,
+}
+
+impl<S: SessionStorage> EncryptedStorage<S> {
+    /// Wrap a storage, encrypting with keys from `keys`.
+    #[must_use]
+    pub fn new(inner: S, keys: Arc<dyn KeyProvider>) -> Self {
+        Self { inner, keys }
+    }
+
+    /// The wrapped storage.
+    #[must_use]
+    pub const fn inner(&self) -> &S {
+        &self.inner
+    }
+
+    /// Encrypt data into a self-delimiting frame:
+    /// magic, key ID length and key ID, nonce, ciphertext length, ciphertext.
+    ///
+    /// The frame only opens with the same `aad`, e.g. from `Field::aad`.
+    async fn seal(&self, plaintext: &[u8], aad: &[u8]) -> Result<Vec<u8>, StorageError> {
+        let key_id = self.keys.current_key_id();
+        let key_id_len = u8::try_from(key_id.len())
+            .map_err(|_| StorageError::Internal("Encryption key ID too long".to_string()))?;
+        let key = self.keys.key(&key_id).await?;
+
+        let nonce = Aes256Gcm::generate_nonce(&mut OsRng);
+        let ciphertext = Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(&key))
+            .encrypt(
+                &nonce,
+                Payload {
+                    msg: plaintext,
+                    aad,
+                },
+            )
+            .map_err(|e| StorageError::Internal(format!("Encryption failed: {e}")))?;
+        let ciphertext_len = u32::try_from(ciphertext.len())
+            .map_err(|_| StorageError::Internal("Plaintext too large to encrypt".to_string()))?;
+
+        let mut frame = Vec::with_capacity(
+            FRAME_MAGIC.len() + 1 + key_id.len() + NONCE_LEN + 4 + ciphertext.len(),
+        );
+        frame.extend_from_slice(FRAME_MAGIC);
+        frame.push(key_id_len);
+        frame.extend_from_slice(key_id.as_bytes());
+        frame.extend_from_slice(&nonce);
+        frame.extend_from_slice(&ciphertext_len.to_be_bytes());
+        frame.extend_from_slice(&ciphertext);
+        Ok(frame)
+    }
+
+    /// Decrypt a sequence of frames sealed with `aad`, concatenating their
+    /// plaintext.
+    async fn open(&self, mut data: &[u8], aad: &[u8]) -> Result<Vec<u8>, StorageError> {
+        let mut plaintext = Vec::new();
+        while !data.is_empty() {
+            let frame = Frame::parse(&mut data)?;
+            plaintext.extend(self.open_frame(&frame, aad).await?);
+        }
+        Ok(plaintext)
+    }
+
+    async fn open_frame(&self, frame: &Frame<'_>, aad: &[u8]) -> Result<Vec<u8>, StorageError> {
+        let key = self.keys.key(frame.key_id).await?;
+        let payload = Payload {
+            msg: frame.ciphertext,
+            aad,
+        };
+        Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(&key))
+            .decrypt(Nonce::from_slice(frame.nonce), payload)
+            .map_err(|e| StorageError::Internal(format!("Decryption failed: {e}")))
+    }
+
+    async fn seal_str(&self, plaintext: &str, aad: &[u8]) -> Result<String, StorageError> {
+        let frame = self.seal(plaintext.as_bytes(), aad).await?;
+        Ok(format!("{STRING_PREFIX}{}", STANDARD.encode(frame)))
+    }
+
+    async fn open_str(&self, sealed: &str, aad: &[u8]) -> Result<String, StorageError> {
+        let Some(encoded) = sealed.strip_prefix(STRING_PREFIX) else {
+            return Err(StorageError::Internal("Value is not encrypted".to_string()));
+        };
+        let frame = STANDARD
+            .decode(encoded)
+            .map_err(|e| StorageError::Internal(e.to_string()))?;
+        String::from_utf8(self.open(&frame, aad).await?)
+            .map_err(|e| StorageError::Internal(e.to_string()))
+    }
+
+    /// Replace session `id`'s context's metadata with its encrypted form.
+    async fn seal_context(
+        &self,
+        id: SessionId,
+        ctx: &ExecutionContext,
+    ) -> Result<ExecutionContext, StorageError> {
+        let metadata = serde_json::to_string(&ctx.metadata)
+            .map_err(|e| StorageError::Internal(e.to_string()))?;
+        let sealed = self.seal_str(&metadata, &Field::Metadata.aad(id)).await?;
+        let mut ctx = ctx.clone();
+        ctx.metadata = HashMap::from([(ENCRYPTED_METADATA_KEY.to_string(), Value::String(sealed))]);
+        Ok(ctx)
+    }
+
+    /// Decrypt the encrypted fields of a stored session.
+    async fn decrypt_session(&self, mut session: Session) -> Result<Session, StorageError> {
+        if let Some(sealed) = session.context.metadata.remove(ENCRYPTED_METADATA_KEY) {
+            let sealed = sealed.as_str().ok_or_else(|| {
+                StorageError::Internal("Encrypted metadata is not a string".to_string())
+            })?;
+            let metadata = self
+                .open_str(sealed, &Field::Metadata.aad(session.id))
+                .await?;
+            session.context.metadata = serde_json::from_str(&metadata)
+                .map_err(|e| StorageError::Internal(e.to_string()))?;
+        }
+        if let Some(prompt) = session.prompt.take() {
+            let prompt = self
+                .open_str(&prompt, &Field::Prompt.aad(session.id))
+                .await?;
+            session.prompt = Some(prompt);
+        }
+        Ok(session)
+    }
+
+    /// Encrypt the text of session `id`'s event, or the values of its patch
+    /// operations.
+    async fn seal_msg(&self, id: SessionId, msg: &LogMsg) -> Result<LogMsg, StorageError> {
+        let aad = Field::Event.aad(id);
+        Ok(match msg {
+            LogMsg::Stdout(text) => LogMsg::Stdout(self.seal_str(text, &aad).await?),
+            LogMsg::Stderr(text) => LogMsg::Stderr(self.seal_str(text, &aad).await?),
+            LogMsg::AssistantText(text) => LogMsg::AssistantText(self.seal_str(text, &aad).await?),
+            LogMsg::JsonPatch(patch) => {
+                let mut ops = serde_json::to_value(patch)
+                    .map_err(|e| StorageError::Internal(e.to_string()))?;
+                for value in patch_values(&mut ops) {
+                    *value = Value::String(self.seal_str(&value.to_string(), &aad).await?);
+                }
+                LogMsg::JsonPatch(
+                    serde_json::from_value(ops)
+                        .map_err(|e| StorageError::Internal(e.to_string()))?,
+                )
+            }
+            other => other.clone(),
+        })
+    }
+
+    /// Decrypt what `seal_msg` encrypted.
+    async fn open_msg(&self, id: SessionId, msg: LogMsg) -> Result<LogMsg, StorageError> {
+        let aad = Field::Event.aad(id);
+        Ok(match msg {
+            LogMsg::Stdout(text) => LogMsg::Stdout(self.open_str(&text, &aad).await?),
+            LogMsg::Stderr(text) => LogMsg::Stderr(self.open_str(&text, &aad).await?),
+            LogMsg::AssistantText(text) => LogMsg::AssistantText(self.open_str(&text, &aad).await?),
+            LogMsg::JsonPatch(patch) => {
+                let mut ops = serde_json::to_value(patch)
+                    .map_err(|e| StorageError::Internal(e.to_string()))?;
+                for value in patch_values(&mut ops) {
+                    let sealed = value.as_str().ok_or_else(|| {
+                        StorageError::Internal("Patch value is not encrypted".to_string())
+                    })?;
+                    *value = serde_json::from_str(&self.open_str(sealed, &aad).await?)
+                        .map_err(|e| StorageError::Internal(e.to_string()))?;
+                }
+                LogMsg::JsonPatch(
+                    serde_json::from_value(ops)
+                        .map_err(|e| StorageError::Internal(e.to_string()))?,
+                )
+            }
+            other => other,
+        })
+    }
+
+    async fn decrypt_sessions(&self, sessions: Vec<Session>) -> Result<Vec<Session>, StorageError> {
+        let mut decrypted = Vec::with_capacity(sessions.len());
+        for session in sessions {
+            decrypted.push(self.decrypt_session(session).await?);
+        }
+        Ok(decrypted)
+    }
+}
+
+/// The values of a serialized patch's operations.
+fn patch_values(ops: &mut Value) -> Vec<&mut Value> {
+    ops.as_array_mut()
+        .into_iter()
+        .flatten()
+        .filter_map(|op| op.get_mut("value"))
+        .collect()
+}
+
+/// What an encrypted value holds, bound into its frames with the session's
+/// ID so they do not open as another field or in another session.
+#[derive(Clone, Copy)]
+enum Field {
+    Metadata,
+    Prompt,
+    Output,
+    Event,
+}
+
+impl Field {
+    /// Additional authenticated data for this field of session `id`.
+    fn aad(self, id: SessionId) -> Vec<u8> {
+        let name = match self {
+            Self::Metadata => "metadata",
+            Self::Prompt => "prompt",
+            Self::Output => "output",
+            Self::Event => "event",
+        };
+        [id.as_bytes().as_slice(), name.as_bytes()].concat()
+    }
+}
+
+/// A parsed encrypted frame.
+struct Frame<'a> {
+    key_id: &'a str,
+    nonce: &'a [u8],
+    ciphertext: &'a [u8],
+}
+
+impl<'a> Frame<'a> {
+    /// Parse the frame at the start of `data`, advancing past it.
+    fn parse(data: &mut &'a [u8]) -> Result<Self, StorageError> {
+        let magic = take(data, FRAME_MAGIC.len())?;
+        if magic != FRAME_MAGIC {
+            return Err(StorageError::Internal("Data is not encrypted".to_string()));
+        }
+        let key_id_len = usize::from(take(data, 1)?[0]);
+        let key_id = std::str::from_utf8(take(data, key_id_len)?)
+            .map_err(|e| StorageError::Internal(e.to_string()))?;
+        let nonce = take(data, NONCE_LEN)?;
+        let len_bytes: [u8; 4] = take(data, 4)?.try_into().unwrap_or_default();
+        let ciphertext_len = usize::try_from(u32::from_be_bytes(len_bytes))
+            .map_err(|e| StorageError::Internal(e.to_string()))?;
+        let ciphertext = take(data, ciphertext_len)?;
+        Ok(Self {
+            key_id,
+            nonce,
+            ciphertext,
+        })
+    }
+
+    /// Length of the frame's plaintext.
+    const fn plaintext_len(&self) -> usize {
+        self.ciphertext.len().saturating_sub(TAG_LEN)
+    }
+}
+
+/// Split `len` bytes off the front of `data`.
+fn take<'a>(data: &mut &'a [u8], len: usize) -> Result<&'a [u8], StorageError> {
+    if data.len() < len {
+        return Err(StorageError::Internal(
+            "Truncated encrypted data".to_string(),
+        ));
+    }
+    let (head, tail) = data.split_at(len);
+    *data = tail;
+    Ok(head)
+}
+
+#[async_trait]
+impl<S: SessionStorage> SessionStorage for EncryptedStorage<S> {
+    /// The metadata is stored once the session has an ID to bind it to,
+    /// which bumps the session's version.
+    async fn create(&self, ctx: &ExecutionContext) -> Result<SessionId, StorageError> {
+        let mut unsealed = ctx.clone();
+        unsealed.metadata = HashMap::new();
+        let id = self.inner.create(&unsealed).await?;
+        if !ctx.metadata.is_empty() {
+            let ctx = self.seal_context(id, ctx).await?;
+            self.inner.update_context(id, &ctx, None).await?;
+        }
+        Ok(id)
+    }
+
+    async fn get(&self, id: SessionId) -> Result<Option<Session>, StorageError> {
+        match self.inner.get(id).await? {
+            Some(session) => Ok(Some(self.decrypt_session(session).await?)),
+            None => Ok(None),
+        }
+    }
+
+    async fn update_status(
+        &self,
+        id: SessionId,
+        status: SessionStatus,
+        expected_version: Option<u64>,
+    ) -> Result<(), StorageError> {
+        self.inner
+            .update_status(id, status, expected_version)
+            .await
+    }
+
+    async fn update_context(
+        &self,
+        id: SessionId,
+        ctx: &ExecutionContext,
+        expected_version: Option<u64>,
+    ) -> Result<(), StorageError> {
+        let ctx = self.seal_context(id, ctx).await?;
+        self.inner
+            .update_context(id, &ctx, expected_version)
+            .await
+    }
+
+    async fn set_agent_session_id(
+        &self,
+        id: SessionId,
+        agent_session_id: String,
+    ) -> Result<(), StorageError> {
+        self.inner.set_agent_session_id(id, agent_session_id).await
+    }
+
+    async fn set_exit_code(&self, id: SessionId, exit_code: i32) -> Result<(), StorageError> {
+        self.inner.set_exit_code(id, exit_code).await
+    }
+
+    async fn set_status_reason(&self, id: SessionId, reason: String) -> Result<(), StorageError> {
+        self.inner.set_status_reason(id, reason).await
+    }
+
+    async fn set_prompt(&self, id: SessionId, prompt: String) -> Result<(), StorageError> {
+        let prompt = self.seal_str(&prompt, &Field::Prompt.aad(id)).await?;
+        self.inner.set_prompt(id, prompt).await
+    }
+
+    async fn set_parent_session_id(
+        &self,
+        id: SessionId,
+        parent_session_id: SessionId,
+    ) -> Result<(), StorageError> {
+        self.inner
+            .set_parent_session_id(id, parent_session_id)
+            .await
+    }
+
+    async fn set_run_id(&self, id: SessionId, run_id: RunId) -> Result<(), StorageError> {
+        self.inner.set_run_id(id, run_id).await
+    }
+
+    async fn set_tenant_id(&self, id: SessionId, tenant_id: TenantId) -> Result<(), StorageError> {
+        self.inner.set_tenant_id(id, tenant_id).await
+    }
+
+    async fn soft_delete(&self, id: SessionId) -> Result<(), StorageError> {
+        self.inner.soft_delete(id).await
+    }
+
+    async fn restore(&self, id: SessionId) -> Result<(), StorageError> {
+        self.inner.restore(id).await
+    }
+
+    async fn get_children(&self, id: SessionId) -> Result<Vec<Session>, StorageError> {
+        let children = self.inner.get_children(id).await?;
+        self.decrypt_sessions(children).await
+    }
+
+    async fn list(&self, filter: SessionFilter) -> Result<Vec<Session>, StorageError> {
+        if filter.query.is_none() {
+            let sessions = self.inner.list(filter).await?;
+            return self.decrypt_sessions(sessions).await;
+        }
+
+        // Prompts and metadata are only searchable once decrypted.
+        let inner_filter = SessionFilter {
+            query: None,
+            limit: None,
+            ..filter.clone()
+        };
+        let sessions = self.inner.list(inner_filter).await?;
+        let mut result = Vec::new();
+        for session in self.decrypt_sessions(sessions).await? {
+            if filter.limit.is_some_and(|limit| result.len() >= limit) {
+                break;
+            }
+            if filter.matches(&session) {
+                result.push(session);
+            }
+        }
+        Ok(result)
+    }
+
+    async fn append_output(&self, id: SessionId, data: &[u8]) -> Result<(), StorageError> {
+        let frame = self.seal(data, &Field::Output.aad(id)).await?;
+        self.inner.append_output(id, &frame).await
+    }
+
+    async fn get_output(&self, id: SessionId) -> Result<Vec<u8>, StorageError> {
+        let data = self.inner.get_output(id).await?;
+        self.open(&data, &Field::Output.aad(id)).await
+    }
+
+    /// Only the frames overlapping the range are decrypted.
+    async fn get_output_range(
+        &self,
+        id: SessionId,
+        offset: u64,
+        len: usize,
+    ) -> Result<Vec<u8>, StorageError> {
+        let data = self.inner.get_output(id).await?;
+        let start = usize::try_from(offset).unwrap_or(usize::MAX);
+        let end = start.saturating_add(len);
+        let aad = Field::Output.aad(id);
+        let mut data = data.as_slice();
+        let mut position = 0;
+        let mut output = Vec::new();
+        while !data.is_empty() && position < end {
+            let frame = Frame::parse(&mut data)?;
+            let frame_end = position + frame.plaintext_len();
+            if frame_end > start {
+                let plaintext = self.open_frame(&frame, &aad).await?;
+                let from = start.saturating_sub(position);
+                let to = (end - position).min(plaintext.len());
+                output.extend_from_slice(&plaintext[from..to]);
+            }
+            position = frame_end;
+        }
+        Ok(output)
+    }
+
+    /// Computed from the frame headers, without decrypting.
+    async fn output_len(&self, id: SessionId) -> Result<u64, StorageError> {
+        let data = self.inner.get_output(id).await?;
+        let mut data = data.as_slice();
+        let mut len = 0;
+        while !data.is_empty() {
+            len += Frame::parse(&mut data)?.plaintext_len();
+        }
+        Ok(u64::try_from(len).unwrap_or(u64::MAX))
+    }
+}
+
+#[async_trait]
+impl<S: SessionStorage + EventStorage> EventStorage for EncryptedStorage<S> {
+    async fn append_event(&self, id: SessionId, msg: &LogMsg) -> Result<EventSeq, StorageError> {
+        let msg = self.seal_msg(id, msg).await?;
+        self.inner.append_event(id, &msg).await
+    }
+
+    async fn get_events(
+        &self,
+        id: SessionId,
+        from: EventSeq,
+        limit: Option<usize>,
+    ) -> Result<Vec<StoredEvent>, StorageError> {
+        let events = self.inner.get_events(id, from, limit).await?;
+        let mut decrypted = Vec::with_capacity(events.len());
+        for mut event in events {
+            event.msg = self.open_msg(id, event.msg).await?;
+            decrypted.push(event);
+        }
+        Ok(decrypted)
+    }
+
+    async fn event_count(&self, id: SessionId) -> Result<u64, StorageError> {
+        self.inner.event_count(id).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use serde_json::json;
+
+    use super::*;
+    use crate::storage::MemoryStorage;
+
+    fn storage(keys: StaticKeyProvider) -> EncryptedStorage<MemoryStorage> {
+        EncryptedStorage::new(MemoryStorage::new(), Arc::new(keys))
+    }
+
+    #[tokio::test]
+    async fn test_encrypts_metadata_prompt_and_output() {
+        let storage = storage(StaticKeyProvider::new("k1", [7; 32]));
+
+        let mut ctx = ExecutionContext::new(PathBuf::from("/tmp/billing"));
+        ctx.set_metadata("ticket", json!("BILL-42"));
+        let id = storage.create(&ctx).await.unwrap();
+        storage
+            .set_prompt(id, "Fix the billing service".into())
+            .await
+            .unwrap();
+        storage.append_output(id, b"hello ").await.unwrap();
+        storage.append_output(id, b"world").await.unwrap();
+
+        let raw = storage.inner().get(id).await.unwrap().unwrap();
+        assert!(!serde_json::to_string(&raw).unwrap().contains("BILL-42"));
+        assert!(!raw.prompt.unwrap().contains("billing"));
+        let raw_output = storage.inner().get_output(id).await.unwrap();
+        assert!(!raw_output.windows(5).any(|w| w == b"hello"));
+
+        let session = storage.get(id).await.unwrap().unwrap();
+        assert_eq!(
+            session.context.get_metadata("ticket"),
+            Some(&json!("BILL-42"))
+        );
+        assert_eq!(session.prompt.as_deref(), Some("Fix the billing service"));
+        assert_eq!(session.context.working_dir, PathBuf::from("/tmp/billing"));
+        assert_eq!(storage.get_output(id).await.unwrap(), b"hello world");
+        assert_eq!(storage.output_len(id).await.unwrap(), 11);
+        assert_eq!(storage.get_output_range(id, 4, 4).await.unwrap(), b"o wo");
+        assert_eq!(storage.get_output_range(id, 9, 10).await.unwrap(), b"ld");
+        let past_end = storage.get_output_range(id, 20, 4).await.unwrap();
+        assert!(past_end.is_empty());
+
+        let filter = SessionFilter {
+            query: Some("bill-42".into()),
+            ..Default::default()
+        };
+        assert_eq!(storage.list(filter).await.unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_encrypts_events() {
+        let storage = storage(StaticKeyProvider::new("k1", [7; 32]));
+        let id = storage
+            .create(&ExecutionContext::new(PathBuf::from("/tmp")))
+            .await
+            .unwrap();
+        let patch: LogMsg = serde_json::from_value(json!({
+            "JsonPatch": [{ "op": "add", "path": "/entries/0", "value": { "text": "secret" } }],
+        }))
+        .unwrap();
+        let events = [
+            LogMsg::Stdout("secret output".into()),
+            patch,
+            LogMsg::Finished,
+        ];
+        for event in &events {
+            storage.append_event(id, event).await.unwrap();
+        }
+
+        let raw = storage.inner().get_events(id, 0, None).await.unwrap();
+        for event in &raw {
+            let json = serde_json::to_string(&event.msg).unwrap();
+            assert!(!json.contains("secret"));
+        }
+        let stored = storage.get_events(id, 0, None).await.unwrap();
+        let msgs: Vec<_> = stored.into_iter().map(|event| event.msg).collect();
+        assert_eq!(
+            serde_json::to_value(&msgs).unwrap(),
+            serde_json::to_value(&events).unwrap()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_frames_bound_to_session_and_field() {
+        let storage = storage(StaticKeyProvider::new("k1", [7; 32]));
+        let ctx = ExecutionContext::new(PathBuf::from("/tmp"));
+        let from = storage.create(&ctx).await.unwrap();
+        let to = storage.create(&ctx).await.unwrap();
+        storage.set_prompt(from, "secret".into()).await.unwrap();
+        storage.append_output(from, b"secret").await.unwrap();
+        storage
+            .append_event(from, &LogMsg::Stdout("secret".into()))
+            .await
+            .unwrap();
+
+        let raw = storage.inner().get(from).await.unwrap().unwrap();
+        let prompt = raw.prompt.unwrap();
+        let output = storage.inner().get_output(from).await.unwrap();
+        let event = storage.inner().get_events(from, 0, None).await.unwrap();
+        storage.inner().append_output(to, &output).await.unwrap();
+        storage
+            .inner()
+            .append_event(to, &event[0].msg)
+            .await
+            .unwrap();
+        assert!(storage.get_output(to).await.is_err());
+        assert!(storage.get_events(to, 0, None).await.is_err());
+        storage
+            .inner()
+            .set_prompt(to, prompt.clone())
+            .await
+            .unwrap();
+        assert!(storage.get(to).await.is_err());
+
+        // Nor as another field of the same session.
+        let mut ctx = ctx;
+        ctx.metadata
+            .insert(ENCRYPTED_METADATA_KEY.into(), json!(prompt));
+        storage
+            .inner()
+            .update_context(from, &ctx, None)
+            .await
+            .unwrap();
+        assert!(storage.get(from).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_key_rotation() {
+        let inner = MemoryStorage::new();
+        let old = EncryptedStorage::new(inner, Arc::new(StaticKeyProvider::new("k1", [1; 32])));
+        let id = old
+            .create(&ExecutionContext::new(PathBuf::from("/tmp")))
+            .await
+            .unwrap();
+        old.append_output(id, b"before ").await.unwrap();
+
+        let rotated = StaticKeyProvider::new("k2", [2; 32]).with_key("k1", [1; 32]);
+        let storage = EncryptedStorage::new(old.inner, Arc::new(rotated));
+        storage.append_output(id, b"after").await.unwrap();
+        assert_eq!(storage.get_output(id).await.unwrap(), b"before after");
+
+        let without_old = EncryptedStorage::new(
+            storage.inner,
+            Arc::new(StaticKeyProvider::new("k2", [2; 32])),
+        );
+        assert!(without_old.get_output(id).await.is_err());
+    }
+}
@@ -8,8 +8,12 @@ use std::{
 
 use async_trait::async_trait;
 use remote_agents_core::{
-    ExecutionContext,
-    traits::{Session, SessionFilter, SessionId, SessionStatus, SessionStorage, StorageError},
+    ExecutionContext, LogMsg,
+    traits::{
+        AuditStorage, EventSeq, EventStorage, HookEventFilter, HookEventRecord, RunId, Session,
+        SessionFilter, SessionId, SessionStatus, SessionStorage, StorageError, StoredEvent,
+        TenantId, ToolCallFilter, ToolCallRecord,
+    },
 };
 use uuid::Uuid;
 
@@ -20,6 +24,9 @@ use uuid::Uuid;
 pub struct MemoryStorage {
     sessions: RwLock<HashMap<SessionId, Session>>,
     outputs: RwLock<HashMap<SessionId, Vec<u8>>>,
+    events: RwLock<HashMap<SessionId, Vec<StoredEvent>>>,
+    tool_calls: RwLock<HashMap<SessionId, Vec<ToolCallRecord>>>,
+    hook_events: RwLock<HashMap<SessionId, Vec<HookEventRecord>>>,
 }
 
 impl MemoryStorage {
@@ -29,6 +36,9 @@ impl MemoryStorage {
         Self {
             sessions: RwLock::new(HashMap::new()),
             outputs: RwLock::new(HashMap::new()),
+            events: RwLock::new(HashMap::new()),
+            tool_calls: RwLock::new(HashMap::new()),
+            hook_events: RwLock::new(HashMap::new()),
         }
     }
 }
@@ -46,6 +56,18 @@ fn now() -> i64 {
         .unwrap_or(0)
 }
 
+/// The time now as Unix epoch seconds and milliseconds past them, for
+/// timing events.
+fn event_time() -> (i64, u32) {
+    let time = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default();
+    (
+        i64::try_from(time.as_secs()).unwrap_or(i64::MAX),
+        time.subsec_millis(),
+    )
+}
+
 #[async_trait]
 impl SessionStorage for MemoryStorage {
     async fn create(&self, ctx: &ExecutionContext) -> Result<SessionId, StorageError> {
@@ -55,10 +77,18 @@ impl SessionStorage for MemoryStorage {
         let session = Session {
             id,
             context: ctx.clone(),
+            prompt: None,
             status: SessionStatus::Pending,
             agent_session_id: None,
+            parent_session_id: None,
+            run_id: None,
+            tenant_id: None,
+            exit_code: None,
+            status_reason: None,
+            deleted: false,
             created_at: timestamp,
             updated_at: timestamp,
+            version: 0,
         };
 
         self.sessions
@@ -83,15 +113,47 @@ impl SessionStorage for MemoryStorage {
             .cloned())
     }
 
-    async fn update_status(&self, id: SessionId, status: SessionStatus) -> Result<(), StorageError> {
+    async fn update_status(
+        &self,
+        id: SessionId,
+        status: SessionStatus,
+        expected_version: Option<u64>,
+    ) -> Result<(), StorageError> {
         let mut sessions = self
             .sessions
             .write()
             .map_err(|e| StorageError::Internal(e.to_string()))?;
 
         let session = sessions.get_mut(&id).ok_or(StorageError::NotFound(id))?;
+        if expected_version.is_some_and(|version| version != session.version) {
+            return Err(StorageError::Conflict(id));
+        }
 
         session.status = status;
+        session.version += 1;
+        session.updated_at = now();
+
+        Ok(())
+    }
+
+    async fn update_context(
+        &self,
+        id: SessionId,
+        ctx: &ExecutionContext,
+        expected_version: Option<u64>,
+    ) -> Result<(), StorageError> {
+        let mut sessions = self
+            .sessions
+            .write()
+            .map_err(|e| StorageError::Internal(e.to_string()))?;
+
+        let session = sessions.get_mut(&id).ok_or(StorageError::NotFound(id))?;
+        if expected_version.is_some_and(|version| version != session.version) {
+            return Err(StorageError::Conflict(id));
+        }
+
+        session.context = ctx.clone();
+        session.version += 1;
         session.updated_at = now();
 
         Ok(())
@@ -115,6 +177,140 @@ impl SessionStorage for MemoryStorage {
         Ok(())
     }
 
+    async fn set_exit_code(&self, id: SessionId, exit_code: i32) -> Result<(), StorageError> {
+        let mut sessions = self
+            .sessions
+            .write()
+            .map_err(|e| StorageError::Internal(e.to_string()))?;
+
+        let session = sessions.get_mut(&id).ok_or(StorageError::NotFound(id))?;
+
+        session.exit_code = Some(exit_code);
+        session.updated_at = now();
+
+        Ok(())
+    }
+
+    async fn set_status_reason(&self, id: SessionId, reason: String) -> Result<(), StorageError> {
+        let mut sessions = self
+            .sessions
+            .write()
+            .map_err(|e| StorageError::Internal(e.to_string()))?;
+
+        let session = sessions.get_mut(&id).ok_or(StorageError::NotFound(id))?;
+
+        session.status_reason = Some(reason);
+        session.updated_at = now();
+
+        Ok(())
+    }
+
+    async fn set_prompt(&self, id: SessionId, prompt: String) -> Result<(), StorageError> {
+        let mut sessions = self
+            .sessions
+            .write()
+            .map_err(|e| StorageError::Internal(e.to_string()))?;
+
+        let session = sessions.get_mut(&id).ok_or(StorageError::NotFound(id))?;
+
+        session.prompt = Some(prompt);
+        session.updated_at = now();
+
+        Ok(())
+    }
+
+    async fn set_parent_session_id(
+        &self,
+        id: SessionId,
+        parent_session_id: SessionId,
+    ) -> Result<(), StorageError> {
+        let mut sessions = self
+            .sessions
+            .write()
+            .map_err(|e| StorageError::Internal(e.to_string()))?;
+
+        let session = sessions.get_mut(&id).ok_or(StorageError::NotFound(id))?;
+
+        session.parent_session_id = Some(parent_session_id);
+        session.updated_at = now();
+
+        Ok(())
+    }
+
+    async fn set_run_id(&self, id: SessionId, run_id: RunId) -> Result<(), StorageError> {
+        let mut sessions = self
+            .sessions
+            .write()
+            .map_err(|e| StorageError::Internal(e.to_string()))?;
+
+        let session = sessions.get_mut(&id).ok_or(StorageError::NotFound(id))?;
+
+        session.run_id = Some(run_id);
+        session.updated_at = now();
+
+        Ok(())
+    }
+
+    async fn set_tenant_id(&self, id: SessionId, tenant_id: TenantId) -> Result<(), StorageError> {
+        let mut sessions = self
+            .sessions
+            .write()
+            .map_err(|e| StorageError::Internal(e.to_string()))?;
+
+        let session = sessions.get_mut(&id).ok_or(StorageError::NotFound(id))?;
+
+        session.tenant_id = Some(tenant_id);
+        session.updated_at = now();
+
+        Ok(())
+    }
+
+    async fn soft_delete(&self, id: SessionId) -> Result<(), StorageError> {
+        let mut sessions = self
+            .sessions
+            .write()
+            .map_err(|e| StorageError::Internal(e.to_string()))?;
+
+        let session = sessions.get_mut(&id).ok_or(StorageError::NotFound(id))?;
+
+        session.deleted = true;
+        session.updated_at = now();
+
+        Ok(())
+    }
+
+    async fn restore(&self, id: SessionId) -> Result<(), StorageError> {
+        let mut sessions = self
+            .sessions
+            .write()
+            .map_err(|e| StorageError::Internal(e.to_string()))?;
+
+        let session = sessions.get_mut(&id).ok_or(StorageError::NotFound(id))?;
+
+        session.deleted = false;
+        session.updated_at = now();
+
+        Ok(())
+    }
+
+    async fn get_children(&self, id: SessionId) -> Result<Vec<Session>, StorageError> {
+        let sessions = self
+            .sessions
+            .read()
+            .map_err(|e| StorageError::Internal(e.to_string()))?;
+
+        let mut result: Vec<Session> = sessions
+            .values()
+            .filter(|s| s.parent_session_id == Some(id))
+            .cloned()
+            .collect();
+
+        // Sort by created_at ascending
+        result.sort_by_key(|s| s.created_at);
+
+        Ok(result)
+    }
+
     async fn list(&self, filter: SessionFilter) -> Result<Vec<Session>, StorageError> {
         let sessions = self
             .sessions
@@ -123,19 +319,7 @@ impl SessionStorage for MemoryStorage {
 
         let mut result: Vec<Session> = sessions
             .values()
-            .filter(|s| {
-                if let Some(status) = filter.status {
-                    if s.status != status {
-                        return false;
-                    }
-                }
-                if let Some(ref working_dir) = filter.working_dir {
-                    if s.context.working_dir != *working_dir {
-                        return false;
-                    }
-                }
-                true
-            })
+            .filter(|s| filter.matches(s))
             .cloned()
             .collect();
 
@@ -173,4 +357,167 @@ impl SessionStorage for MemoryStorage {
             .cloned()
             .ok_or(StorageError::NotFound(id))
     }
+
+    async fn get_output_range(
+        &self,
+        id: SessionId,
+        offset: u64,
+        len: usize,
+    ) -> Result<Vec<u8>, StorageError> {
+        let outputs = self
+            .outputs
+            .read()
+            .map_err(|e| StorageError::Internal(e.to_string()))?;
+
+        let output = outputs.get(&id).ok_or(StorageError::NotFound(id))?;
+        let start = usize::try_from(offset).map_or(output.len(), |o| o.min(output.len()));
+        let end = start.saturating_add(len).min(output.len());
+        Ok(output[start..end].to_vec())
+    }
+
+    async fn output_len(&self, id: SessionId) -> Result<u64, StorageError> {
+        let outputs = self
+            .outputs
+            .read()
+            .map_err(|e| StorageError::Internal(e.to_string()))?;
+
+        let output = outputs.get(&id).ok_or(StorageError::NotFound(id))?;
+        Ok(u64::try_from(output.len()).unwrap_or(u64::MAX))
+    }
+}
+
+#[async_trait]
+impl EventStorage for MemoryStorage {
+    async fn append_event(&self, id: SessionId, msg: &LogMsg) -> Result<EventSeq, StorageError> {
+        let mut events = self
+            .events
+            .write()
+            .map_err(|e| StorageError::Internal(e.to_string()))?;
+
+        let session_events = events.entry(id).or_default();
+        let seq = session_events.len() as EventSeq;
+
+        let (timestamp, subsec_millis) = event_time();
+        session_events.push(StoredEvent {
+            session_id: id,
+            seq,
+            timestamp,
+            subsec_millis,
+            msg: msg.clone(),
+        });
+
+        Ok(seq)
+    }
+
+    async fn get_events(
+        &self,
+        id: SessionId,
+        from: EventSeq,
+        limit: Option<usize>,
+    ) -> Result<Vec<StoredEvent>, StorageError> {
+        let events = self
+            .events
+            .read()
+            .map_err(|e| StorageError::Internal(e.to_string()))?;
+
+        let Some(session_events) = events.get(&id) else {
+            return Ok(Vec::new());
+        };
+
+        let start = usize::try_from(from).unwrap_or(usize::MAX);
+        Ok(session_events
+            .iter()
+            .skip(start)
+            .take(limit.unwrap_or(usize::MAX))
+            .cloned()
+            .collect())
+    }
+
+    async fn event_count(&self, id: SessionId) -> Result<u64, StorageError> {
+        let events = self
+            .events
+            .read()
+            .map_err(|e| StorageError::Internal(e.to_string()))?;
+
+        Ok(events.get(&id).map_or(0, |e| e.len() as u64))
+    }
+}
+
+#[async_trait]
+impl AuditStorage for MemoryStorage {
+    async fn record_tool_call(&self, record: ToolCallRecord) -> Result<(), StorageError> {
+        self.tool_calls
+            .write()
+            .map_err(|e| StorageError::Internal(e.to_string()))?
+            .entry(record.session_id)
+            .or_default()
+            .push(record);
+
+        Ok(())
+    }
+
+    async fn get_tool_calls(&self, id: SessionId) -> Result<Vec<ToolCallRecord>, StorageError> {
+        Ok(self
+            .tool_calls
+            .read()
+            .map_err(|e| StorageError::Internal(e.to_string()))?
+            .get(&id)
+            .cloned()
+            .unwrap_or_default())
+    }
+
+    async fn query_tool_calls(
+        &self,
+        filter: ToolCallFilter,
+    ) -> Result<Vec<ToolCallRecord>, StorageError> {
+        let mut result: Vec<ToolCallRecord> = self
+            .tool_calls
+            .read()
+            .map_err(|e| StorageError::Internal(e.to_string()))?
+            .values()
+            .flatten()
+            .filter(|record| filter.matches(record))
+            .cloned()
+            .collect();
+
+        result.sort_by_key(|record| record.decided_at);
+        if let Some(limit) = filter.limit {
+            result.truncate(limit);
+        }
+
+        Ok(result)
+    }
+
+    async fn record_hook_event(&self, record: HookEventRecord) -> Result<(), StorageError> {
+        self.hook_events
+            .write()
+            .map_err(|e| StorageError::Internal(e.to_string()))?
+            .entry(record.session_id)
+            .or_default()
+            .push(record);
+
+        Ok(())
+    }
+
+    async fn query_hook_events(
+        &self,
+        filter: HookEventFilter,
+    ) -> Result<Vec<HookEventRecord>, StorageError> {
+        let mut result: Vec<HookEventRecord> = self
+            .hook_events
+            .read()
+            .map_err(|e| StorageError::Internal(e.to_string()))?
+            .values()
+            .flatten()
+            .filter(|record| filter.matches(record))
+            .cloned()
+            .collect();
+
+        result.sort_by_key(|record| record.timestamp);
+        if let Some(limit) = filter.limit {
+            result.truncate(limit);
+        }
+
+        Ok(result)
+    }
 }
@@ -0,0 +1,230 @@
+//! Copying sessions between storage backends.
+
+use std::collections::HashMap;
+
+use remote_agents_core::traits::{Session, SessionFilter, SessionId, SessionStorage, StorageError};
+use serde_json::Value;
+
+/// Metadata key recording the source session a migrated session was copied
+/// from, which is how an interrupted migration resumes.
+pub const MIGRATED_FROM_METADATA_KEY: &str = "migrated_from";
+
+/// Progress of a storage migration.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct MigrationProgress {
+    /// Sessions in the source storage.
+    pub total: usize,
+    /// Sessions copied by this run (including ones a previous run left
+    /// incomplete).
+    pub migrated: usize,
+    /// Sessions a previous run already copied in full.
+    pub skipped: usize,
+}
+
+/// Copy every session, with its output, from one storage to another.
+///
+/// Sessions are copied one at a time, so only one session's output is held
+/// in memory. `on_progress` is called after each session. Copied sessions
+/// get new IDs and are marked with [`MIGRATED_FROM_METADATA_KEY`]; running
+/// the migration again skips sessions that were fully copied and finishes
+/// ones that were not, so an interrupted migration can simply be re-run.
+/// Parent links are rewritten to the copied sessions' IDs. Creation and
+/// update timestamps are not preserved, and events and audit records are
+/// not copied.
+///
+/// # Errors
+/// Returns error if reading from `from` or writing to `to` fails.
+pub async fn migrate_storage<F>(
+    from: &dyn SessionStorage,
+    to: &dyn SessionStorage,
+    mut on_progress: F,
+) -> Result<MigrationProgress, StorageError>
+where
+    F: FnMut(&MigrationProgress) + Send,
+{
+    let all = SessionFilter {
+        include_deleted: true,
+        ..SessionFilter::default()
+    };
+    let sessions = from.list(all.clone()).await?;
+
+    // Sessions copied by an earlier run, by source ID.
+    let mut ids: HashMap<SessionId, SessionId> = HashMap::new();
+    for session in to.list(all).await? {
+        let source = session
+            .context
+            .get_metadata(MIGRATED_FROM_METADATA_KEY)
+            .and_then(Value::as_str)
+            .and_then(|source| SessionId::parse_str(source).ok());
+        if let Some(source) = source {
+            ids.insert(source, session.id);
+        }
+    }
+
+    let mut progress = MigrationProgress {
+        total: sessions.len(),
+        ..MigrationProgress::default()
+    };
+    for session in &sessions {
+        let output = from.get_output(session.id).await?;
+        if let Some(&id) = ids.get(&session.id) {
+            // Output is appended last, so a partial copy has a prefix of it.
+            let copied = to.get_output(id).await?.len().min(output.len());
+            let complete = copied == output.len()
+                && to
+                    .get(id)
+                    .await?
+                    .is_some_and(|copy| fields_match(&copy, session));
+            if complete {
+                progress.skipped += 1;
+            } else {
+                restore_fields(to, id, session).await?;
+                if copied < output.len() {
+                    to.append_output(id, &output[copied..]).await?;
+                }
+                progress.migrated += 1;
+            }
+        } else {
+            let mut ctx = session.context.clone();
+            ctx.set_metadata(
+                MIGRATED_FROM_METADATA_KEY,
+                Value::String(session.id.to_string()),
+            );
+            let id = to.create(&ctx).await?;
+            ids.insert(session.id, id);
+            restore_fields(to, id, session).await?;
+            if !output.is_empty() {
+                to.append_output(id, &output).await?;
+            }
+            progress.migrated += 1;
+        }
+        on_progress(&progress);
+    }
+
+    // Parents may be copied after their children, so link them last.
+    for session in &sessions {
+        if let (Some(parent), Some(&id)) = (session.parent_session_id, ids.get(&session.id)) {
+            let parent = ids.get(&parent).copied().unwrap_or(parent);
+            to.set_parent_session_id(id, parent).await?;
+        }
+    }
+    Ok(progress)
+}
+
+/// Create a copy of a session (without output or parent link), returning
+/// its new ID.
+#[cfg(feature = "archive")]
+pub(super) async fn restore(
+    storage: &dyn SessionStorage,
+    session: &Session,
+) -> Result<SessionId, StorageError> {
+    let id = storage.create(&session.context).await?;
+    restore_fields(storage, id, session).await?;
+    Ok(id)
+}
+
+/// Whether `copy` has the fields `restore_fields` copies from `session`.
+fn fields_match(copy: &Session, session: &Session) -> bool {
+    copy.status == session.status
+        && copy.prompt == session.prompt
+        && copy.agent_session_id == session.agent_session_id
+        && copy.exit_code == session.exit_code
+        && copy.status_reason == session.status_reason
+        && copy.run_id == session.run_id
+        && copy.tenant_id == session.tenant_id
+        && copy.deleted == session.deleted
+}
+
+/// Copy a session's fields (other than its context and parent) onto `id`.
+async fn restore_fields(
+    storage: &dyn SessionStorage,
+    id: SessionId,
+    session: &Session,
+) -> Result<(), StorageError> {
+    storage.update_status(id, session.status, None).await?;
+    if let Some(ref prompt) = session.prompt {
+        storage.set_prompt(id, prompt.clone()).await?;
+    }
+    if let Some(ref agent_session_id) = session.agent_session_id {
+        storage
+            .set_agent_session_id(id, agent_session_id.clone())
+            .await?;
+    }
+    if let Some(exit_code) = session.exit_code {
+        storage.set_exit_code(id, exit_code).await?;
+    }
+    if let Some(ref reason) = session.status_reason {
+        storage.set_status_reason(id, reason.clone()).await?;
+    }
+    if let Some(ref run_id) = session.run_id {
+        storage.set_run_id(id, run_id.clone()).await?;
+    }
+    if let Some(ref tenant_id) = session.tenant_id {
+        storage.set_tenant_id(id, tenant_id.clone()).await?;
+    }
+    if session.deleted {
+        storage.soft_delete(id).await?;
+    } else {
+        storage.restore(id).await?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use remote_agents_core::{ExecutionContext, traits::SessionStatus};
+
+    use super::*;
+    use crate::storage::MemoryStorage;
+
+    #[tokio::test]
+    async fn test_migrate_and_resume() {
+        let from = MemoryStorage::new();
+        let parent = from
+            .create(&ExecutionContext::new(PathBuf::from("/tmp/project")))
+            .await
+            .unwrap();
+        from.set_prompt(parent, "plan".into()).await.unwrap();
+        from.update_status(parent, SessionStatus::Completed, None)
+            .await
+            .unwrap();
+        from.append_output(parent, b"hello world").await.unwrap();
+        let child = from
+            .create(&ExecutionContext::new(PathBuf::from("/tmp/project")))
+            .await
+            .unwrap();
+        from.set_parent_session_id(child, parent).await.unwrap();
+
+        // Simulate a run interrupted partway through copying `parent`.
+        let to = MemoryStorage::new();
+        let mut ctx = ExecutionContext::new(PathBuf::from("/tmp/project"));
+        ctx.set_metadata(
+            MIGRATED_FROM_METADATA_KEY,
+            Value::String(parent.to_string()),
+        );
+        let partial = to.create(&ctx).await.unwrap();
+        to.append_output(partial, b"hello").await.unwrap();
+
+        let mut reported = Vec::new();
+        let progress = migrate_storage(&from, &to, |progress| reported.push(*progress))
+            .await
+            .unwrap();
+        assert_eq!(progress.total, 2);
+        assert_eq!(progress.migrated, 2);
+        assert_eq!(reported.len(), 2);
+
+        assert_eq!(to.get_output(partial).await.unwrap(), b"hello world");
+        let copied = to.get(partial).await.unwrap().unwrap();
+        assert_eq!(copied.prompt.as_deref(), Some("plan"));
+        assert_eq!(copied.status, SessionStatus::Completed);
+        let children = to.get_children(partial).await.unwrap();
+        assert_eq!(children.len(), 1);
+
+        let progress = migrate_storage(&from, &to, |_| {}).await.unwrap();
+        assert_eq!(progress.migrated, 0);
+        assert_eq!(progress.skipped, 2);
+        assert_eq!(to.list(SessionFilter::default()).await.unwrap().len(), 2);
+    }
+}
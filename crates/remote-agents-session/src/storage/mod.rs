@@ -1,10 +1,48 @@
 //! Storage implementations.
 
+pub mod blob;
+pub mod cached;
+pub mod migrate;
+pub mod namespaced;
+
 #[cfg(feature = "memory")]
 pub mod memory;
 
 #[cfg(feature = "sqlite")]
 pub mod sqlite;
 
+#[cfg(feature = "postgres")]
+pub mod postgres;
+
+#[cfg(feature = "redb")]
+pub mod redb;
+
+#[cfg(feature = "dynamodb")]
+pub mod dynamo;
+
+#[cfg(feature = "s3")]
+pub mod s3;
+
+#[cfg(feature = "encryption")]
+pub mod encrypted;
+
+#[cfg(feature = "archive")]
+pub mod archive;
+
+#[cfg(any(feature = "sqlite", feature = "postgres"))]
+mod sql;
+
+#[cfg(any(feature = "sqlite", feature = "postgres", feature = "redb"))]
+mod compression;
+
+pub use blob::FsBlobStore;
+pub use cached::CachedStorage;
+pub use migrate::migrate_storage;
+pub use namespaced::NamespacedStorage;
+
+/// Default amount of output a storage backend collects per session before
+/// flushing it to its blob store and/or compressing it (1 MiB).
+pub const DEFAULT_OUTPUT_FLUSH_SIZE: usize = 1024 * 1024;
+
 #[cfg(feature = "memory")]
 pub use memory::MemoryStorage;
@@ -0,0 +1,417 @@
+//! Tenant isolation over a shared storage.
+
+use std::{
+    collections::HashSet,
+    sync::{Arc, RwLock},
+};
+
+use async_trait::async_trait;
+use remote_agents_core::{
+    ExecutionContext, LogMsg,
+    traits::{
+        AuditStorage, EventSeq, EventStorage, HookEventFilter, HookEventRecord, RunId, Session,
+        SessionFilter, SessionId, SessionStatus, SessionStorage, StorageError, StoredEvent,
+        TenantId, ToolCallFilter, ToolCallRecord,
+    },
+};
+
+/// Storage wrapper confining its caller to one tenant's sessions.
+///
+/// Sessions created through the wrapper are assigned to its tenant, and
+/// sessions of other tenants behave as if they did not exist: reads return
+/// nothing and writes fail with `StorageError::NotFound`. Several wrappers
+/// can share one storage, so a single database serves every tenant.
+pub struct NamespacedStorage<S: ?Sized> {
+    inner: Arc<S>,
+    tenant_id: TenantId,
+    /// Sessions already known to belong to the tenant.
+    owned: RwLock<HashSet<SessionId>>,
+}
+
+impl<S: SessionStorage + ?Sized> NamespacedStorage<S> {
+    /// Confine access to `inner` to the sessions of `tenant_id`.
+    #[must_use]
+    pub fn new(inner: Arc<S>, tenant_id: impl Into<TenantId>) -> Self {
+        Self {
+            inner,
+            tenant_id: tenant_id.into(),
+            owned: RwLock::new(HashSet::new()),
+        }
+    }
+
+    /// The tenant this wrapper is confined to.
+    #[must_use]
+    pub fn tenant_id(&self) -> &str {
+        &self.tenant_id
+    }
+
+    /// The shared storage.
+    #[must_use]
+    pub const fn inner(&self) -> &Arc<S> {
+        &self.inner
+    }
+
+    fn owns(&self, session: &Session) -> bool {
+        session.tenant_id.as_ref() == Some(&self.tenant_id)
+    }
+
+    fn remember(&self, id: SessionId) -> Result<(), StorageError> {
+        self.owned.write().map_err(lock_error)?.insert(id);
+        Ok(())
+    }
+
+    /// Fail with `NotFound` unless the session belongs to the tenant.
+    async fn check(&self, id: SessionId) -> Result<(), StorageError> {
+        if self.owned.read().map_err(lock_error)?.contains(&id) {
+            return Ok(());
+        }
+        match self.inner.get(id).await? {
+            Some(session) if self.owns(&session) => self.remember(id),
+            _ => Err(StorageError::NotFound(id)),
+        }
+    }
+
+    /// Keep the records of the tenant's sessions, up to `limit`.
+    async fn retain_owned<T>(
+        &self,
+        records: Vec<T>,
+        session_id: impl Fn(&T) -> SessionId,
+        limit: Option<usize>,
+    ) -> Result<Vec<T>, StorageError> {
+        let mut owned = Vec::new();
+        for record in records {
+            if limit.is_some_and(|limit| owned.len() >= limit) {
+                break;
+            }
+            match self.check(session_id(&record)).await {
+                Ok(()) => owned.push(record),
+                Err(StorageError::NotFound(_)) => {}
+                Err(e) => return Err(e),
+            }
+        }
+        Ok(owned)
+    }
+}
+
+#[allow(clippy::needless_pass_by_value)]
+fn lock_error<E: std::fmt::Display>(e: E) -> StorageError {
+    StorageError::Internal(e.to_string())
+}
+
+#[async_trait]
+impl<S: SessionStorage + ?Sized> SessionStorage for NamespacedStorage<S> {
+    async fn create(&self, ctx: &ExecutionContext) -> Result<SessionId, StorageError> {
+        let id = self.inner.create(ctx).await?;
+        // A session without a tenant would be visible to none, so do not
+        // leave one behind.
+        if let Err(e) = self.inner.set_tenant_id(id, self.tenant_id.clone()).await {
+            if let Err(delete_error) = self.inner.soft_delete(id).await {
+                tracing::warn!("Failed to delete session {id} without a tenant: {delete_error}");
+            }
+            return Err(e);
+        }
+        self.remember(id)?;
+        Ok(id)
+    }
+
+    async fn get(&self, id: SessionId) -> Result<Option<Session>, StorageError> {
+        Ok(self
+            .inner
+            .get(id)
+            .await?
+            .filter(|session| self.owns(session)))
+    }
+
+    async fn update_status(
+        &self,
+        id: SessionId,
+        status: SessionStatus,
+        expected_version: Option<u64>,
+    ) -> Result<(), StorageError> {
+        self.check(id).await?;
+        self.inner
+            .update_status(id, status, expected_version)
+            .await
+    }
+
+    async fn update_context(
+        &self,
+        id: SessionId,
+        ctx: &ExecutionContext,
+        expected_version: Option<u64>,
+    ) -> Result<(), StorageError> {
+        self.check(id).await?;
+        self.inner.update_context(id, ctx, expected_version).await
+    }
+
+    async fn set_agent_session_id(
+        &self,
+        id: SessionId,
+        agent_session_id: String,
+    ) -> Result<(), StorageError> {
+        self.check(id).await?;
+        self.inner.set_agent_session_id(id, agent_session_id).await
+    }
+
+    async fn set_exit_code(&self, id: SessionId, exit_code: i32) -> Result<(), StorageError> {
+        self.check(id).await?;
+        self.inner.set_exit_code(id, exit_code).await
+    }
+
+    async fn set_status_reason(&self, id: SessionId, reason: String) -> Result<(), StorageError> {
+        self.check(id).await?;
+        self.inner.set_status_reason(id, reason).await
+    }
+
+    async fn set_prompt(&self, id: SessionId, prompt: String) -> Result<(), StorageError> {
+        self.check(id).await?;
+        self.inner.set_prompt(id, prompt).await
+    }
+
+    async fn set_parent_session_id(
+        &self,
+        id: SessionId,
+        parent_session_id: SessionId,
+    ) -> Result<(), StorageError> {
+        self.check(id).await?;
+        self.check(parent_session_id).await?;
+        self.inner
+            .set_parent_session_id(id, parent_session_id)
+            .await
+    }
+
+    async fn set_run_id(&self, id: SessionId, run_id: RunId) -> Result<(), StorageError> {
+        self.check(id).await?;
+        self.inner.set_run_id(id, run_id).await
+    }
+
+    async fn set_tenant_id(&self, id: SessionId, tenant_id: TenantId) -> Result<(), StorageError> {
+        self.check(id).await?;
+        if tenant_id != self.tenant_id {
+            return Err(StorageError::Internal(format!(
+                "Cannot move session {id} to another tenant"
+            )));
+        }
+        Ok(())
+    }
+
+    async fn soft_delete(&self, id: SessionId) -> Result<(), StorageError> {
+        self.check(id).await?;
+        self.inner.soft_delete(id).await
+    }
+
+    async fn restore(&self, id: SessionId) -> Result<(), StorageError> {
+        self.check(id).await?;
+        self.inner.restore(id).await
+    }
+
+    async fn get_children(&self, id: SessionId) -> Result<Vec<Session>, StorageError> {
+        self.check(id).await?;
+        let mut children = self.inner.get_children(id).await?;
+        children.retain(|session| self.owns(session));
+        Ok(children)
+    }
+
+    async fn list(&self, filter: SessionFilter) -> Result<Vec<Session>, StorageError> {
+        let filter = SessionFilter {
+            tenant_id: Some(self.tenant_id.clone()),
+            ..filter
+        };
+        self.inner.list(filter).await
+    }
+
+    async fn append_output(&self, id: SessionId, data: &[u8]) -> Result<(), StorageError> {
+        self.check(id).await?;
+        self.inner.append_output(id, data).await
+    }
+
+    async fn get_output(&self, id: SessionId) -> Result<Vec<u8>, StorageError> {
+        self.check(id).await?;
+        self.inner.get_output(id).await
+    }
+
+    async fn get_output_range(
+        &self,
+        id: SessionId,
+        offset: u64,
+        len: usize,
+    ) -> Result<Vec<u8>, StorageError> {
+        self.check(id).await?;
+        self.inner.get_output_range(id, offset, len).await
+    }
+
+    async fn output_len(&self, id: SessionId) -> Result<u64, StorageError> {
+        self.check(id).await?;
+        self.inner.output_len(id).await
+    }
+}
+
+#[async_trait]
+impl<S: EventStorage + SessionStorage + ?Sized> EventStorage for NamespacedStorage<S> {
+    async fn append_event(&self, id: SessionId, msg: &LogMsg) -> Result<EventSeq, StorageError> {
+        self.check(id).await?;
+        self.inner.append_event(id, msg).await
+    }
+
+    async fn get_events(
+        &self,
+        id: SessionId,
+        from: EventSeq,
+        limit: Option<usize>,
+    ) -> Result<Vec<StoredEvent>, StorageError> {
+        self.check(id).await?;
+        self.inner.get_events(id, from, limit).await
+    }
+
+    async fn event_count(&self, id: SessionId) -> Result<u64, StorageError> {
+        self.check(id).await?;
+        self.inner.event_count(id).await
+    }
+}
+
+/// Queries across sessions only return records of the tenant's sessions.
+#[async_trait]
+impl<S: AuditStorage + SessionStorage + ?Sized> AuditStorage for NamespacedStorage<S> {
+    async fn record_tool_call(&self, record: ToolCallRecord) -> Result<(), StorageError> {
+        self.check(record.session_id).await?;
+        self.inner.record_tool_call(record).await
+    }
+
+    async fn get_tool_calls(&self, id: SessionId) -> Result<Vec<ToolCallRecord>, StorageError> {
+        self.check(id).await?;
+        self.inner.get_tool_calls(id).await
+    }
+
+    async fn query_tool_calls(
+        &self,
+        filter: ToolCallFilter,
+    ) -> Result<Vec<ToolCallRecord>, StorageError> {
+        if let Some(id) = filter.session_id {
+            self.check(id).await?;
+            return self.inner.query_tool_calls(filter).await;
+        }
+        let limit = filter.limit;
+        let filter = ToolCallFilter {
+            limit: None,
+            ..filter
+        };
+        let records = self.inner.query_tool_calls(filter).await?;
+        self.retain_owned(records, |record| record.session_id, limit)
+            .await
+    }
+
+    async fn record_hook_event(&self, record: HookEventRecord) -> Result<(), StorageError> {
+        self.check(record.session_id).await?;
+        self.inner.record_hook_event(record).await
+    }
+
+    async fn query_hook_events(
+        &self,
+        filter: HookEventFilter,
+    ) -> Result<Vec<HookEventRecord>, StorageError> {
+        if let Some(id) = filter.session_id {
+            self.check(id).await?;
+            return self.inner.query_hook_events(filter).await;
+        }
+        let limit = filter.limit;
+        let filter = HookEventFilter {
+            limit: None,
+            ..filter
+        };
+        let records = self.inner.query_hook_events(filter).await?;
+        self.retain_owned(records, |record| record.session_id, limit)
+            .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use remote_agents_core::traits::ApprovalOutcome;
+    use serde_json::json;
+
+    use super::*;
+    use crate::storage::MemoryStorage;
+
+    fn tool_call(session_id: SessionId) -> ToolCallRecord {
+        ToolCallRecord {
+            session_id,
+            tool_call_id: "call-1".into(),
+            tool_name: "Bash".into(),
+            input: json!({ "command": "ls" }),
+            outcome: ApprovalOutcome::Approved,
+            decided_by: None,
+            reason: None,
+            requested_at: 0,
+            decided_at: 0,
+            input_hash: None,
+            latency_ms: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_tenants_are_isolated() {
+        let shared = Arc::new(MemoryStorage::new());
+        let acme = NamespacedStorage::new(shared.clone(), "acme");
+        let globex = NamespacedStorage::new(shared.clone(), "globex");
+
+        let ctx = ExecutionContext::new(PathBuf::from("/tmp"));
+        let id = acme.create(&ctx).await.unwrap();
+        acme.append_output(id, b"secret plans").await.unwrap();
+        globex.create(&ctx).await.unwrap();
+
+        let session = acme.get(id).await.unwrap().unwrap();
+        assert_eq!(session.tenant_id.as_deref(), Some("acme"));
+        assert_eq!(acme.list(SessionFilter::default()).await.unwrap().len(), 1);
+        assert_eq!(
+            shared.list(SessionFilter::default()).await.unwrap().len(),
+            2
+        );
+
+        assert!(globex.get(id).await.unwrap().is_none());
+        assert!(matches!(
+            globex.get_output(id).await,
+            Err(StorageError::NotFound(_))
+        ));
+        assert!(matches!(
+            globex
+                .update_status(id, SessionStatus::Cancelled, None)
+                .await,
+            Err(StorageError::NotFound(_))
+        ));
+        assert!(acme.set_tenant_id(id, "globex".into()).await.is_err());
+
+        acme.append_event(id, &LogMsg::Stdout("secret plans".into()))
+            .await
+            .unwrap();
+        assert_eq!(acme.event_count(id).await.unwrap(), 1);
+        assert!(matches!(
+            globex.get_events(id, 0, None).await,
+            Err(StorageError::NotFound(_))
+        ));
+        acme.record_tool_call(tool_call(id)).await.unwrap();
+        assert!(matches!(
+            globex.get_tool_calls(id).await,
+            Err(StorageError::NotFound(_))
+        ));
+        let calls = globex
+            .query_tool_calls(ToolCallFilter::default())
+            .await
+            .unwrap();
+        assert!(calls.is_empty());
+        let calls = acme
+            .query_tool_calls(ToolCallFilter::default())
+            .await
+            .unwrap();
+        assert_eq!(calls.len(), 1);
+
+        let filter = SessionFilter {
+            tenant_id: Some("acme".into()),
+            ..Default::default()
+        };
+        let sessions = globex.list(filter).await.unwrap();
+        assert_eq!(sessions.len(), 1);
+        assert_eq!(sessions[0].tenant_id.as_deref(), Some("globex"));
+    }
+}
@@ -0,0 +1,881 @@
+//! Postgres session storage (feature-gated).
+
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use futures::{StreamExt, stream::BoxStream};
+use remote_agents_core::{
+    ExecutionContext, LogMsg,
+    traits::{
+        AuditStorage, EventSeq, EventStorage, HookEventFilter, HookEventRecord, OutputBlobStore,
+        RunId, Session, SessionFilter, SessionId, SessionStatus, SessionStorage, StorageError,
+        StoredEvent, TenantId, ToolCallFilter, ToolCallRecord,
+    },
+};
+use serde::Deserialize;
+use serde_json::Value;
+use sqlx::{
+    Encode, PgPool, Postgres, QueryBuilder, Row, Type,
+    postgres::{PgListener, PgRow},
+    types::Json,
+};
+use uuid::Uuid;
+
+use super::{
+    DEFAULT_OUTPUT_FLUSH_SIZE,
+    compression::{ZSTD, compress},
+    sql::{
+        OutputChunk, OutputSpan, assemble_output, assemble_output_range, blob_key, db_error,
+        enum_from_str, enum_to_str, event_time, json_error, now, range_bounds, version_to_i64,
+    },
+};
+
+/// Channel the `sessions` table trigger notifies on.
+const CHANGES_CHANNEL: &str = "remote_agents_sessions";
+
+/// A session was created or updated.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct SessionChange {
+    /// The changed session.
+    pub session_id: SessionId,
+    /// Its status after the change.
+    pub status: SessionStatus,
+}
+
+/// Stream of session changes from `PostgresStorage::watch`.
+pub type SessionChangeStream = BoxStream<'static, Result<SessionChange, StorageError>>;
+
+/// Postgres storage implementation.
+///
+/// Lets several instances share one durable session store; use
+/// [`PostgresStorage::watch`] to observe sessions changed by other
+/// instances. Secrets in a session's `ExecutionContext` are redacted before
+/// they are written.
+pub struct PostgresStorage {
+    pool: PgPool,
+    blobs: Option<Arc<dyn OutputBlobStore>>,
+    output_flush_size: usize,
+    compress_output: bool,
+}
+
+impl PostgresStorage {
+    /// Connect to a database and run migrations.
+    ///
+    /// # Errors
+    /// Returns error if database connection or migration fails.
+    pub async fn new(database_url: &str) -> Result<Self, StorageError> {
+        let pool = PgPool::connect(database_url).await.map_err(db_error)?;
+        Self::from_pool(pool).await
+    }
+
+    /// Use an existing connection pool, running migrations on it.
+    ///
+    /// # Errors
+    /// Returns error if migration fails.
+    pub async fn from_pool(pool: PgPool) -> Result<Self, StorageError> {
+        sqlx::migrate!("./migrations/postgres")
+            .run(&pool)
+            .await
+            .map_err(|e| StorageError::Internal(e.to_string()))?;
+        Ok(Self {
+            pool,
+            blobs: None,
+            output_flush_size: DEFAULT_OUTPUT_FLUSH_SIZE,
+            compress_output: false,
+        })
+    }
+
+    /// Move output to a blob store, keeping only blob keys in the database.
+    ///
+    /// Output is appended inline and moved to the store in one blob per
+    /// flush (see [`Self::with_output_flush_size`]).
+    #[must_use]
+    pub fn with_blob_store(mut self, blobs: Arc<dyn OutputBlobStore>) -> Self {
+        self.blobs = Some(blobs);
+        self
+    }
+
+    /// Compress output with zstd at rest.
+    ///
+    /// Output is appended uncompressed and compressed one flush at a time
+    /// (see [`Self::with_output_flush_size`]). Output compressed earlier is
+    /// still read back if this is later turned off.
+    #[must_use]
+    pub const fn with_output_compression(mut self, enabled: bool) -> Self {
+        self.compress_output = enabled;
+        self
+    }
+
+    /// Set how much appended output is collected per session before it is
+    /// flushed to the blob store and/or compressed
+    /// (default [`DEFAULT_OUTPUT_FLUSH_SIZE`]).
+    #[must_use]
+    pub const fn with_output_flush_size(mut self, bytes: usize) -> Self {
+        self.output_flush_size = bytes;
+        self
+    }
+
+    /// Subscribe to session creations and updates from any instance.
+    ///
+    /// Backed by `LISTEN`/`NOTIFY`, so only changes made after the call are
+    /// seen. The listener reconnects on connection loss; changes made while
+    /// disconnected are missed.
+    ///
+    /// # Errors
+    /// Returns error if the listener cannot connect.
+    pub async fn watch(&self) -> Result<SessionChangeStream, StorageError> {
+        let mut listener = PgListener::connect_with(&self.pool)
+            .await
+            .map_err(db_error)?;
+        listener.listen(CHANGES_CHANNEL).await.map_err(db_error)?;
+
+        Ok(listener
+            .into_stream()
+            .map(|notification| {
+                let notification = notification.map_err(db_error)?;
+                serde_json::from_str(notification.payload()).map_err(json_error)
+            })
+            .boxed())
+    }
+
+    /// Set a single session column, bumping `updated_at`.
+    async fn set_column<T>(
+        &self,
+        id: SessionId,
+        column: &'static str,
+        value: T,
+    ) -> Result<(), StorageError>
+    where
+        T: 'static + Send + for<'q> Encode<'q, Postgres> + Type<Postgres>,
+    {
+        let sql = format!("UPDATE sessions SET {column} = $1, updated_at = $2 WHERE id = $3");
+        let result = sqlx::query(&sql)
+            .bind(value)
+            .bind(now())
+            .bind(id)
+            .execute(&self.pool)
+            .await
+            .map_err(db_error)?;
+
+        if result.rows_affected() == 0 {
+            return Err(StorageError::NotFound(id));
+        }
+        Ok(())
+    }
+
+    /// Update a session's status or context, bumping its version.
+    ///
+    /// `set` pushes the `column = value` assignments to update.
+    async fn update_versioned<F>(
+        &self,
+        id: SessionId,
+        expected_version: Option<u64>,
+        set: F,
+    ) -> Result<(), StorageError>
+    where
+        F: FnOnce(&mut QueryBuilder<'_, Postgres>),
+    {
+        let mut query = QueryBuilder::<Postgres>::new("UPDATE sessions SET ");
+        set(&mut query);
+        query
+            .push(", version = version + 1, updated_at = ")
+            .push_bind(now())
+            .push(" WHERE id = ")
+            .push_bind(id);
+        if let Some(version) = expected_version {
+            query.push(" AND version = ").push_bind(version_to_i64(version)?);
+        }
+        let result = query.build().execute(&self.pool).await.map_err(db_error)?;
+
+        if result.rows_affected() == 0 {
+            if self.session_exists(id).await? {
+                return Err(StorageError::Conflict(id));
+            }
+            return Err(StorageError::NotFound(id));
+        }
+        Ok(())
+    }
+
+    /// Flush a session's appended output once it reaches the flush size,
+    /// merging it into one chunk that is compressed and/or moved to the
+    /// blob store.
+    async fn flush_output(&self, id: SessionId) -> Result<(), StorageError> {
+        let pending: i64 = sqlx::query_scalar(
+            "SELECT COALESCE(SUM(LENGTH(data)), 0) FROM session_output
+             WHERE session_id = $1 AND blob_key IS NULL AND compression IS NULL",
+        )
+        .bind(id)
+        .fetch_one(&self.pool)
+        .await
+        .map_err(db_error)?;
+        if usize::try_from(pending).unwrap_or(usize::MAX) < self.output_flush_size {
+            return Ok(());
+        }
+
+        let chunks: Vec<(i64, Vec<u8>)> = sqlx::query_as(
+            "SELECT id, data FROM session_output
+             WHERE session_id = $1 AND blob_key IS NULL AND compression IS NULL
+             ORDER BY id",
+        )
+        .bind(id)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(db_error)?;
+        let (Some(&(first, _)), Some(&(last, _))) = (chunks.first(), chunks.last()) else {
+            return Ok(());
+        };
+
+        let data: Vec<u8> = chunks.into_iter().flat_map(|(_, data)| data).collect();
+        let size = i64::try_from(data.len()).unwrap_or(i64::MAX);
+        let (data, compression) = if self.compress_output {
+            (compress(&data)?, Some(ZSTD))
+        } else {
+            (data, None)
+        };
+        let (data, key) = match &self.blobs {
+            Some(blobs) => {
+                let key = blob_key(id, first);
+                blobs.put(&key, data).await?;
+                (Vec::new(), Some(key))
+            }
+            None => (data, None),
+        };
+
+        // Replace the chunks with the merged one in the first chunk's place.
+        let mut tx = self.pool.begin().await.map_err(db_error)?;
+        sqlx::query(
+            "DELETE FROM session_output
+             WHERE session_id = $1 AND blob_key IS NULL AND compression IS NULL
+             AND id BETWEEN $2 AND $3",
+        )
+        .bind(id)
+        .bind(first)
+        .bind(last)
+        .execute(&mut *tx)
+        .await
+        .map_err(db_error)?;
+        sqlx::query(
+            "INSERT INTO session_output (id, session_id, data, blob_key, compression, size)
+             VALUES ($1, $2, $3, $4, $5, $6)",
+        )
+        .bind(first)
+        .bind(id)
+        .bind(data)
+        .bind(key)
+        .bind(compression)
+        .bind(size)
+        .execute(&mut *tx)
+        .await
+        .map_err(db_error)?;
+        tx.commit().await.map_err(db_error)
+    }
+
+    async fn session_exists(&self, id: SessionId) -> Result<bool, StorageError> {
+        let row = sqlx::query("SELECT 1 FROM sessions WHERE id = $1")
+            .bind(id)
+            .fetch_optional(&self.pool)
+            .await
+            .map_err(db_error)?;
+        Ok(row.is_some())
+    }
+}
+
+fn session_from_row(row: &PgRow) -> Result<Session, StorageError> {
+    let Json(context): Json<ExecutionContext> = row.try_get("context").map_err(db_error)?;
+    let version: i64 = row.try_get("version").map_err(db_error)?;
+
+    Ok(Session {
+        id: row.try_get("id").map_err(db_error)?,
+        context,
+        prompt: row.try_get("prompt").map_err(db_error)?,
+        status: enum_from_str(row.try_get("status").map_err(db_error)?)?,
+        agent_session_id: row.try_get("agent_session_id").map_err(db_error)?,
+        parent_session_id: row.try_get("parent_session_id").map_err(db_error)?,
+        run_id: row.try_get("run_id").map_err(db_error)?,
+        tenant_id: row.try_get("tenant_id").map_err(db_error)?,
+        exit_code: row.try_get("exit_code").map_err(db_error)?,
+        status_reason: row.try_get("status_reason").map_err(db_error)?,
+        deleted: row.try_get("deleted").map_err(db_error)?,
+        created_at: row.try_get("created_at").map_err(db_error)?,
+        updated_at: row.try_get("updated_at").map_err(db_error)?,
+        version: u64::try_from(version).unwrap_or_default(),
+    })
+}
+
+fn event_from_row(row: &PgRow) -> Result<StoredEvent, StorageError> {
+    let seq: i64 = row.try_get("seq").map_err(db_error)?;
+    let subsec_millis: i64 = row.try_get("subsec_millis").map_err(db_error)?;
+    let Json(msg): Json<LogMsg> = row.try_get("msg").map_err(db_error)?;
+
+    Ok(StoredEvent {
+        session_id: row.try_get("session_id").map_err(db_error)?,
+        seq: EventSeq::try_from(seq).unwrap_or_default(),
+        timestamp: row.try_get("timestamp").map_err(db_error)?,
+        subsec_millis: u32::try_from(subsec_millis).unwrap_or_default(),
+        msg,
+    })
+}
+
+fn tool_call_from_row(row: &PgRow) -> Result<ToolCallRecord, StorageError> {
+    let Json(input): Json<Value> = row.try_get("input").map_err(db_error)?;
+    let latency_ms: Option<i64> = row.try_get("latency_ms").map_err(db_error)?;
+
+    Ok(ToolCallRecord {
+        session_id: row.try_get("session_id").map_err(db_error)?,
+        tool_call_id: row.try_get("tool_call_id").map_err(db_error)?,
+        tool_name: row.try_get("tool_name").map_err(db_error)?,
+        input,
+        outcome: enum_from_str(row.try_get("outcome").map_err(db_error)?)?,
+        decided_by: row.try_get("decided_by").map_err(db_error)?,
+        reason: row.try_get("reason").map_err(db_error)?,
+        requested_at: row.try_get("requested_at").map_err(db_error)?,
+        decided_at: row.try_get("decided_at").map_err(db_error)?,
+        input_hash: row.try_get("input_hash").map_err(db_error)?,
+        latency_ms: latency_ms.and_then(|ms| u64::try_from(ms).ok()),
+    })
+}
+
+fn hook_event_from_row(row: &PgRow) -> Result<HookEventRecord, StorageError> {
+    let Json(input): Json<Value> = row.try_get("input").map_err(db_error)?;
+    let output: Option<Json<Value>> = row.try_get("output").map_err(db_error)?;
+
+    Ok(HookEventRecord {
+        session_id: row.try_get("session_id").map_err(db_error)?,
+        event: row.try_get("event").map_err(db_error)?,
+        tool_name: row.try_get("tool_name").map_err(db_error)?,
+        tool_call_id: row.try_get("tool_call_id").map_err(db_error)?,
+        input,
+        output: output.map(|Json(output)| output),
+        timestamp: row.try_get("timestamp").map_err(db_error)?,
+    })
+}
+
+#[async_trait]
+impl SessionStorage for PostgresStorage {
+    async fn create(&self, ctx: &ExecutionContext) -> Result<SessionId, StorageError> {
+        let id = Uuid::new_v4();
+        let timestamp = now();
+
+        sqlx::query(
+            "INSERT INTO sessions (id, context, working_dir, status, created_at, updated_at)
+             VALUES ($1, $2, $3, $4, $5, $6)",
+        )
+        .bind(id)
+        .bind(Json(ctx))
+        .bind(ctx.working_dir.to_string_lossy())
+        .bind(enum_to_str(&SessionStatus::Pending)?)
+        .bind(timestamp)
+        .bind(timestamp)
+        .execute(&self.pool)
+        .await
+        .map_err(db_error)?;
+
+        Ok(id)
+    }
+
+    async fn get(&self, id: SessionId) -> Result<Option<Session>, StorageError> {
+        sqlx::query("SELECT * FROM sessions WHERE id = $1")
+            .bind(id)
+            .fetch_optional(&self.pool)
+            .await
+            .map_err(db_error)?
+            .as_ref()
+            .map(session_from_row)
+            .transpose()
+    }
+
+    async fn update_status(
+        &self,
+        id: SessionId,
+        status: SessionStatus,
+        expected_version: Option<u64>,
+    ) -> Result<(), StorageError> {
+        let status = enum_to_str(&status)?;
+        self.update_versioned(id, expected_version, |query| {
+            query.push("status = ").push_bind(status);
+        })
+        .await
+    }
+
+    async fn update_context(
+        &self,
+        id: SessionId,
+        ctx: &ExecutionContext,
+        expected_version: Option<u64>,
+    ) -> Result<(), StorageError> {
+        let context = Json(ctx.clone());
+        let working_dir = ctx.working_dir.to_string_lossy().into_owned();
+        self.update_versioned(id, expected_version, |query| {
+            query
+                .push("context = ")
+                .push_bind(context)
+                .push(", working_dir = ")
+                .push_bind(working_dir);
+        })
+        .await
+    }
+
+    async fn set_agent_session_id(
+        &self,
+        id: SessionId,
+        agent_session_id: String,
+    ) -> Result<(), StorageError> {
+        self.set_column(id, "agent_session_id", agent_session_id).await
+    }
+
+    async fn set_exit_code(&self, id: SessionId, exit_code: i32) -> Result<(), StorageError> {
+        self.set_column(id, "exit_code", exit_code).await
+    }
+
+    async fn set_status_reason(&self, id: SessionId, reason: String) -> Result<(), StorageError> {
+        self.set_column(id, "status_reason", reason).await
+    }
+
+    async fn set_prompt(&self, id: SessionId, prompt: String) -> Result<(), StorageError> {
+        self.set_column(id, "prompt", prompt).await
+    }
+
+    async fn set_parent_session_id(
+        &self,
+        id: SessionId,
+        parent_session_id: SessionId,
+    ) -> Result<(), StorageError> {
+        self.set_column(id, "parent_session_id", parent_session_id).await
+    }
+
+    async fn set_run_id(&self, id: SessionId, run_id: RunId) -> Result<(), StorageError> {
+        self.set_column(id, "run_id", run_id).await
+    }
+
+    async fn set_tenant_id(&self, id: SessionId, tenant_id: TenantId) -> Result<(), StorageError> {
+        self.set_column(id, "tenant_id", tenant_id).await
+    }
+
+    async fn soft_delete(&self, id: SessionId) -> Result<(), StorageError> {
+        self.set_column(id, "deleted", true).await
+    }
+
+    async fn restore(&self, id: SessionId) -> Result<(), StorageError> {
+        self.set_column(id, "deleted", false).await
+    }
+
+    async fn get_children(&self, id: SessionId) -> Result<Vec<Session>, StorageError> {
+        sqlx::query("SELECT * FROM sessions WHERE parent_session_id = $1 ORDER BY created_at, id")
+            .bind(id)
+            .fetch_all(&self.pool)
+            .await
+            .map_err(db_error)?
+            .iter()
+            .map(session_from_row)
+            .collect()
+    }
+
+    async fn list(&self, filter: SessionFilter) -> Result<Vec<Session>, StorageError> {
+        let mut query = QueryBuilder::<Postgres>::new("SELECT * FROM sessions WHERE TRUE");
+        if !filter.include_deleted {
+            query.push(" AND NOT deleted");
+        }
+        if let Some(status) = filter.status {
+            query.push(" AND status = ").push_bind(enum_to_str(&status)?);
+        }
+        if let Some(ref working_dir) = filter.working_dir {
+            query
+                .push(" AND working_dir = ")
+                .push_bind(working_dir.to_string_lossy().into_owned());
+        }
+        if let Some(ref run_id) = filter.run_id {
+            query.push(" AND run_id = ").push_bind(run_id.clone());
+        }
+        if let Some(ref tenant_id) = filter.tenant_id {
+            query.push(" AND tenant_id = ").push_bind(tenant_id.clone());
+        }
+        if let Some(created_after) = filter.created_after {
+            query.push(" AND created_at >= ").push_bind(created_after);
+        }
+        if let Some(created_before) = filter.created_before {
+            query.push(" AND created_at < ").push_bind(created_before);
+        }
+        query.push(" ORDER BY created_at DESC");
+
+        // Text queries match against JSON metadata, so they are applied
+        // after loading and the limit with them.
+        if filter.query.is_none() {
+            if let Some(limit) = filter.limit {
+                query
+                    .push(" LIMIT ")
+                    .push_bind(i64::try_from(limit).unwrap_or(i64::MAX));
+            }
+        }
+
+        let rows = query
+            .build()
+            .fetch_all(&self.pool)
+            .await
+            .map_err(db_error)?;
+
+        let mut result = Vec::with_capacity(rows.len());
+        for row in &rows {
+            let session = session_from_row(row)?;
+            if filter.matches(&session) {
+                result.push(session);
+            }
+        }
+
+        if let Some(limit) = filter.limit {
+            result.truncate(limit);
+        }
+
+        Ok(result)
+    }
+
+    async fn append_output(&self, id: SessionId, data: &[u8]) -> Result<(), StorageError> {
+        let result = sqlx::query(
+            "INSERT INTO session_output (session_id, data)
+             SELECT id, $1 FROM sessions WHERE id = $2",
+        )
+        .bind(data)
+        .bind(id)
+        .execute(&self.pool)
+        .await
+        .map_err(db_error)?;
+
+        if result.rows_affected() == 0 {
+            return Err(StorageError::NotFound(id));
+        }
+        if self.blobs.is_some() || self.compress_output {
+            self.flush_output(id).await?;
+        }
+        Ok(())
+    }
+
+    async fn get_output(&self, id: SessionId) -> Result<Vec<u8>, StorageError> {
+        let chunks: Vec<OutputChunk> = sqlx::query_as(
+            "SELECT data, blob_key, compression FROM session_output
+             WHERE session_id = $1 ORDER BY id",
+        )
+        .bind(id)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(db_error)?;
+
+        if chunks.is_empty() && !self.session_exists(id).await? {
+            return Err(StorageError::NotFound(id));
+        }
+        assemble_output(chunks, self.blobs.as_deref()).await
+    }
+
+    async fn get_output_range(
+        &self,
+        id: SessionId,
+        offset: u64,
+        len: usize,
+    ) -> Result<Vec<u8>, StorageError> {
+        // One statement, so a concurrent flush cannot shift the chunks.
+        let (start, end) = range_bounds(offset, len);
+        let spans: Vec<OutputSpan> = sqlx::query_as(
+            "WITH spans AS (
+                 SELECT id,
+                        SUM(COALESCE(size, LENGTH(data)))
+                            OVER (ORDER BY id)::BIGINT AS end_offset,
+                        COALESCE(size, LENGTH(data))::BIGINT AS size
+                 FROM session_output WHERE session_id = $1
+             )
+             SELECT o.data, o.blob_key, o.compression, spans.end_offset - spans.size
+             FROM spans JOIN session_output o ON o.id = spans.id
+             WHERE spans.end_offset > $2 AND spans.end_offset - spans.size < $3
+             ORDER BY o.id",
+        )
+        .bind(id)
+        .bind(start)
+        .bind(end)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(db_error)?;
+
+        if spans.is_empty() && !self.session_exists(id).await? {
+            return Err(StorageError::NotFound(id));
+        }
+        assemble_output_range(spans, self.blobs.as_deref(), offset, len).await
+    }
+
+    async fn output_len(&self, id: SessionId) -> Result<u64, StorageError> {
+        let len: i64 = sqlx::query_scalar(
+            "SELECT COALESCE(SUM(COALESCE(size, LENGTH(data))), 0)::BIGINT
+             FROM session_output WHERE session_id = $1",
+        )
+        .bind(id)
+        .fetch_one(&self.pool)
+        .await
+        .map_err(db_error)?;
+
+        if len == 0 && !self.session_exists(id).await? {
+            return Err(StorageError::NotFound(id));
+        }
+        Ok(u64::try_from(len).unwrap_or_default())
+    }
+}
+
+#[async_trait]
+impl EventStorage for PostgresStorage {
+    async fn append_event(&self, id: SessionId, msg: &LogMsg) -> Result<EventSeq, StorageError> {
+        let mut tx = self.pool.begin().await.map_err(db_error)?;
+
+        // Serialize appends per session so concurrent writers from other
+        // instances get consecutive sequence numbers.
+        sqlx::query("SELECT pg_advisory_xact_lock(hashtextextended($1::text, 0))")
+            .bind(id)
+            .execute(&mut *tx)
+            .await
+            .map_err(db_error)?;
+
+        let (timestamp, subsec_millis) = event_time();
+        let seq: i64 = sqlx::query_scalar(
+            "INSERT INTO session_events (session_id, seq, timestamp, subsec_millis, msg)
+             SELECT $1, COALESCE(MAX(seq) + 1, 0), $2, $3, $4
+             FROM session_events WHERE session_id = $1
+             RETURNING seq",
+        )
+        .bind(id)
+        .bind(timestamp)
+        .bind(i64::from(subsec_millis))
+        .bind(Json(msg))
+        .fetch_one(&mut *tx)
+        .await
+        .map_err(db_error)?;
+
+        tx.commit().await.map_err(db_error)?;
+        Ok(EventSeq::try_from(seq).unwrap_or_default())
+    }
+
+    async fn get_events(
+        &self,
+        id: SessionId,
+        from: EventSeq,
+        limit: Option<usize>,
+    ) -> Result<Vec<StoredEvent>, StorageError> {
+        sqlx::query(
+            "SELECT * FROM session_events WHERE session_id = $1 AND seq >= $2
+             ORDER BY seq LIMIT $3",
+        )
+        .bind(id)
+        .bind(i64::try_from(from).unwrap_or(i64::MAX))
+        // A NULL limit means no limit.
+        .bind(limit.map(|limit| i64::try_from(limit).unwrap_or(i64::MAX)))
+        .fetch_all(&self.pool)
+        .await
+        .map_err(db_error)?
+        .iter()
+        .map(event_from_row)
+        .collect()
+    }
+
+    async fn event_count(&self, id: SessionId) -> Result<u64, StorageError> {
+        let count: i64 =
+            sqlx::query_scalar("SELECT COUNT(*) FROM session_events WHERE session_id = $1")
+                .bind(id)
+                .fetch_one(&self.pool)
+                .await
+                .map_err(db_error)?;
+
+        Ok(u64::try_from(count).unwrap_or_default())
+    }
+}
+
+#[async_trait]
+impl AuditStorage for PostgresStorage {
+    async fn record_tool_call(&self, record: ToolCallRecord) -> Result<(), StorageError> {
+        sqlx::query(
+            "INSERT INTO tool_calls (
+                session_id, tool_call_id, tool_name, input, outcome,
+                decided_by, reason, requested_at, decided_at, input_hash, latency_ms
+             ) VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11)",
+        )
+        .bind(record.session_id)
+        .bind(record.tool_call_id)
+        .bind(record.tool_name)
+        .bind(Json(record.input))
+        .bind(enum_to_str(&record.outcome)?)
+        .bind(record.decided_by)
+        .bind(record.reason)
+        .bind(record.requested_at)
+        .bind(record.decided_at)
+        .bind(record.input_hash)
+        .bind(record.latency_ms.map(|ms| i64::try_from(ms).unwrap_or(i64::MAX)))
+        .execute(&self.pool)
+        .await
+        .map_err(db_error)?;
+
+        Ok(())
+    }
+
+    async fn get_tool_calls(&self, id: SessionId) -> Result<Vec<ToolCallRecord>, StorageError> {
+        sqlx::query("SELECT * FROM tool_calls WHERE session_id = $1 ORDER BY id")
+            .bind(id)
+            .fetch_all(&self.pool)
+            .await
+            .map_err(db_error)?
+            .iter()
+            .map(tool_call_from_row)
+            .collect()
+    }
+
+    async fn query_tool_calls(
+        &self,
+        filter: ToolCallFilter,
+    ) -> Result<Vec<ToolCallRecord>, StorageError> {
+        let mut query = QueryBuilder::<Postgres>::new("SELECT * FROM tool_calls WHERE TRUE");
+        if let Some(session_id) = filter.session_id {
+            query.push(" AND session_id = ").push_bind(session_id);
+        }
+        if let Some(tool_name) = filter.tool_name {
+            query.push(" AND tool_name = ").push_bind(tool_name);
+        }
+        if let Some(outcome) = filter.outcome {
+            query.push(" AND outcome = ").push_bind(enum_to_str(&outcome)?);
+        }
+        if let Some(decided_after) = filter.decided_after {
+            query.push(" AND decided_at >= ").push_bind(decided_after);
+        }
+        if let Some(decided_before) = filter.decided_before {
+            query.push(" AND decided_at < ").push_bind(decided_before);
+        }
+        query.push(" ORDER BY decided_at, id");
+        if let Some(limit) = filter.limit {
+            query
+                .push(" LIMIT ")
+                .push_bind(i64::try_from(limit).unwrap_or(i64::MAX));
+        }
+
+        query
+            .build()
+            .fetch_all(&self.pool)
+            .await
+            .map_err(db_error)?
+            .iter()
+            .map(tool_call_from_row)
+            .collect()
+    }
+
+    async fn record_hook_event(&self, record: HookEventRecord) -> Result<(), StorageError> {
+        sqlx::query(
+            "INSERT INTO hook_events (
+                session_id, event, tool_name, tool_call_id, input, output, timestamp
+             ) VALUES ($1, $2, $3, $4, $5, $6, $7)",
+        )
+        .bind(record.session_id)
+        .bind(record.event)
+        .bind(record.tool_name)
+        .bind(record.tool_call_id)
+        .bind(Json(record.input))
+        .bind(record.output.map(Json))
+        .bind(record.timestamp)
+        .execute(&self.pool)
+        .await
+        .map_err(db_error)?;
+
+        Ok(())
+    }
+
+    async fn query_hook_events(
+        &self,
+        filter: HookEventFilter,
+    ) -> Result<Vec<HookEventRecord>, StorageError> {
+        let mut query = QueryBuilder::<Postgres>::new("SELECT * FROM hook_events WHERE TRUE");
+        if let Some(session_id) = filter.session_id {
+            query.push(" AND session_id = ").push_bind(session_id);
+        }
+        if let Some(event) = filter.event {
+            query.push(" AND event = ").push_bind(event);
+        }
+        if let Some(tool_name) = filter.tool_name {
+            query.push(" AND tool_name = ").push_bind(tool_name);
+        }
+        if let Some(after) = filter.after {
+            query.push(" AND timestamp >= ").push_bind(after);
+        }
+        if let Some(before) = filter.before {
+            query.push(" AND timestamp < ").push_bind(before);
+        }
+        query.push(" ORDER BY timestamp, id");
+        if let Some(limit) = filter.limit {
+            query
+                .push(" LIMIT ")
+                .push_bind(i64::try_from(limit).unwrap_or(i64::MAX));
+        }
+
+        query
+            .build()
+            .fetch_all(&self.pool)
+            .await
+            .map_err(db_error)?
+            .iter()
+            .map(hook_event_from_row)
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use super::*;
+
+    /// Connect to the test database named by `POSTGRES_TEST_URL`.
+    async fn storage() -> PostgresStorage {
+        let url = std::env::var("POSTGRES_TEST_URL")
+            .expect("POSTGRES_TEST_URL must point at a scratch database");
+        PostgresStorage::new(&url).await.unwrap()
+    }
+
+    #[tokio::test]
+    #[ignore = "requires a PostgreSQL server (set POSTGRES_TEST_URL)"]
+    async fn test_session_round_trip() {
+        let storage = storage().await;
+        let id = storage
+            .create(&ExecutionContext::new(PathBuf::from("/tmp/project")))
+            .await
+            .unwrap();
+
+        storage
+            .update_status(id, SessionStatus::Running, None)
+            .await
+            .unwrap();
+        storage.set_prompt(id, "fix it".into()).await.unwrap();
+        storage.append_output(id, b"hello ").await.unwrap();
+        storage.append_output(id, b"world").await.unwrap();
+        assert_eq!(storage.append_event(id, &LogMsg::Ready).await.unwrap(), 0);
+        assert_eq!(storage.append_event(id, &LogMsg::Finished).await.unwrap(), 1);
+
+        let session = storage.get(id).await.unwrap().unwrap();
+        assert_eq!(session.status, SessionStatus::Running);
+        assert_eq!(session.prompt.as_deref(), Some("fix it"));
+        assert_eq!(session.context.working_dir, PathBuf::from("/tmp/project"));
+        assert_eq!(storage.get_output(id).await.unwrap(), b"hello world");
+        assert_eq!(storage.get_events(id, 1, None).await.unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    #[ignore = "requires a PostgreSQL server (set POSTGRES_TEST_URL)"]
+    async fn test_watch_sees_changes() {
+        let storage = storage().await;
+        let mut changes = storage.watch().await.unwrap();
+
+        let id = storage
+            .create(&ExecutionContext::new(PathBuf::from("/tmp")))
+            .await
+            .unwrap();
+        storage
+            .update_status(id, SessionStatus::Completed, None)
+            .await
+            .unwrap();
+
+        let mut seen = Vec::new();
+        while seen.len() < 2 {
+            let change = changes.next().await.unwrap().unwrap();
+            if change.session_id == id {
+                seen.push(change.status);
+            }
+        }
+        assert_eq!(seen, vec![SessionStatus::Pending, SessionStatus::Completed]);
+    }
+}
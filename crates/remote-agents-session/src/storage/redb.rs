@@ -0,0 +1,726 @@
+//! Embedded key-value session storage backed by redb (feature-gated).
+
+use std::{
+    path::Path,
+    sync::Arc,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use ::redb::{
+    Database, Durability, ReadableTable, TableDefinition, WriteTransaction,
+};
+use async_trait::async_trait;
+use remote_agents_core::{
+    ExecutionContext, LogMsg,
+    traits::{
+        AuditStorage, EventSeq, EventStorage, RunId, Session, SessionFilter, SessionId,
+        HookEventFilter, HookEventRecord, SessionStatus, SessionStorage, StorageError, StoredEvent,
+        TenantId, ToolCallFilter, ToolCallRecord,
+    },
+};
+use uuid::Uuid;
+
+use super::{
+    DEFAULT_OUTPUT_FLUSH_SIZE,
+    compression::{ZSTD, compress, decompress},
+};
+
+/// Sessions as JSON, keyed by session ID.
+const SESSIONS: TableDefinition<u128, &str> = TableDefinition::new("sessions");
+/// Output chunks, keyed by session ID and append order.
+const OUTPUT: TableDefinition<(u128, u64), &[u8]> = TableDefinition::new("session_output");
+/// zstd-compressed runs of output chunks, keyed by session ID and the
+/// append order of the last chunk in the run.
+const COMPRESSED_OUTPUT: TableDefinition<(u128, u64), &[u8]> =
+    TableDefinition::new("session_output_zstd");
+/// Events as JSON, keyed by session ID and sequence number.
+const EVENTS: TableDefinition<(u128, u64), &str> = TableDefinition::new("session_events");
+/// Tool call records as JSON, keyed by session ID and record order.
+const TOOL_CALLS: TableDefinition<(u128, u64), &str> = TableDefinition::new("tool_calls");
+/// Hook event records as JSON, keyed by session ID and record order.
+const HOOK_EVENTS: TableDefinition<(u128, u64), &str> = TableDefinition::new("hook_events");
+
+/// Embedded storage implementation for single-binary deployments.
+///
+/// Everything lives in one redb file. Output is stored as one entry per
+/// append, and output appends commit with eventual durability so frequent
+/// small writes don't each wait for an fsync; they become durable with the
+/// next write of any other kind. Secrets in a session's `ExecutionContext`
+/// are redacted before they are written.
+pub struct RedbStorage {
+    db: Arc<Database>,
+    output_flush_size: usize,
+    compress_output: bool,
+}
+
+impl RedbStorage {
+    /// Open (creating if missing) a database file.
+    ///
+    /// # Errors
+    /// Returns error if the file cannot be opened or is not a redb database.
+    pub async fn new(path: impl AsRef<Path>) -> Result<Self, StorageError> {
+        let path = path.as_ref().to_path_buf();
+        let db = tokio::task::spawn_blocking(move || {
+            let db = Database::create(path).map_err(db_error)?;
+            let txn = db.begin_write().map_err(db_error)?;
+            txn.open_table(SESSIONS).map_err(db_error)?;
+            txn.open_table(OUTPUT).map_err(db_error)?;
+            txn.open_table(COMPRESSED_OUTPUT).map_err(db_error)?;
+            txn.open_table(EVENTS).map_err(db_error)?;
+            txn.open_table(TOOL_CALLS).map_err(db_error)?;
+            txn.open_table(HOOK_EVENTS).map_err(db_error)?;
+            txn.commit().map_err(db_error)?;
+            Ok::<_, StorageError>(db)
+        })
+        .await
+        .map_err(|e| StorageError::Internal(e.to_string()))??;
+
+        Ok(Self {
+            db: Arc::new(db),
+            output_flush_size: DEFAULT_OUTPUT_FLUSH_SIZE,
+            compress_output: false,
+        })
+    }
+
+    /// Compress output with zstd at rest.
+    ///
+    /// Output is appended uncompressed and compressed one flush at a time
+    /// (see [`Self::with_output_flush_size`]). Output compressed earlier is
+    /// still read back if this is later turned off.
+    #[must_use]
+    pub const fn with_output_compression(mut self, enabled: bool) -> Self {
+        self.compress_output = enabled;
+        self
+    }
+
+    /// Set how much appended output is collected per session before it is
+    /// compressed (default [`DEFAULT_OUTPUT_FLUSH_SIZE`]).
+    #[must_use]
+    pub const fn with_output_flush_size(mut self, bytes: usize) -> Self {
+        self.output_flush_size = bytes;
+        self
+    }
+
+    /// Run a database operation on the blocking thread pool.
+    async fn blocking<T, F>(&self, f: F) -> Result<T, StorageError>
+    where
+        F: FnOnce(&Database) -> Result<T, StorageError> + Send + 'static,
+        T: Send + 'static,
+    {
+        let db = Arc::clone(&self.db);
+        tokio::task::spawn_blocking(move || f(&db))
+            .await
+            .map_err(|e| StorageError::Internal(e.to_string()))?
+    }
+
+    /// Apply a change to a stored session, bumping `updated_at`.
+    async fn update<F>(&self, id: SessionId, f: F) -> Result<(), StorageError>
+    where
+        F: FnOnce(&mut Session) + Send + 'static,
+    {
+        self.try_update(id, move |session| {
+            f(session);
+            Ok(())
+        })
+        .await
+    }
+
+    /// Apply a versioned change to a stored session, bumping its version.
+    async fn update_versioned<F>(
+        &self,
+        id: SessionId,
+        expected_version: Option<u64>,
+        f: F,
+    ) -> Result<(), StorageError>
+    where
+        F: FnOnce(&mut Session) + Send + 'static,
+    {
+        self.try_update(id, move |session| {
+            if expected_version.is_some_and(|version| version != session.version) {
+                return Err(StorageError::Conflict(id));
+            }
+            f(session);
+            session.version += 1;
+            Ok(())
+        })
+        .await
+    }
+
+    /// Apply a fallible change to a stored session, bumping `updated_at`.
+    async fn try_update<F>(&self, id: SessionId, f: F) -> Result<(), StorageError>
+    where
+        F: FnOnce(&mut Session) -> Result<(), StorageError> + Send + 'static,
+    {
+        self.blocking(move |db| {
+            let txn = db.begin_write().map_err(db_error)?;
+            {
+                let mut table = txn.open_table(SESSIONS).map_err(db_error)?;
+                let mut session = match table.get(id.as_u128()).map_err(db_error)? {
+                    Some(json) => decode::<Session>(json.value())?,
+                    None => return Err(StorageError::NotFound(id)),
+                };
+                f(&mut session)?;
+                session.updated_at = now();
+                table
+                    .insert(id.as_u128(), encode(&session)?.as_str())
+                    .map_err(db_error)?;
+            }
+            txn.commit().map_err(db_error)
+        })
+        .await
+    }
+
+    /// Load every session matching `keep`.
+    async fn scan<F>(&self, keep: F) -> Result<Vec<Session>, StorageError>
+    where
+        F: Fn(&Session) -> bool + Send + 'static,
+    {
+        self.blocking(move |db| {
+            let txn = db.begin_read().map_err(db_error)?;
+            let table = txn.open_table(SESSIONS).map_err(db_error)?;
+            let mut sessions = Vec::new();
+            for entry in table.iter().map_err(db_error)? {
+                let (_, json) = entry.map_err(db_error)?;
+                let session = decode::<Session>(json.value())?;
+                if keep(&session) {
+                    sessions.push(session);
+                }
+            }
+            Ok(sessions)
+        })
+        .await
+    }
+}
+
+fn now() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| i64::try_from(d.as_secs()).unwrap_or(i64::MAX))
+}
+
+/// The time now as Unix epoch seconds and milliseconds past them, for
+/// timing events.
+fn event_time() -> (i64, u32) {
+    let time = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default();
+    (
+        i64::try_from(time.as_secs()).unwrap_or(i64::MAX),
+        time.subsec_millis(),
+    )
+}
+
+fn db_error(e: impl Into<::redb::Error>) -> StorageError {
+    StorageError::Internal(e.into().to_string())
+}
+
+fn encode<T: serde::Serialize>(value: &T) -> Result<String, StorageError> {
+    serde_json::to_string(value).map_err(|e| StorageError::Internal(e.to_string()))
+}
+
+fn decode<T: serde::de::DeserializeOwned>(json: &str) -> Result<T, StorageError> {
+    serde_json::from_str(json).map_err(|e| StorageError::Internal(e.to_string()))
+}
+
+/// Next free sequence number for a session in a `(session, seq)` table.
+fn next_seq<V: ::redb::Value + 'static>(
+    txn: &WriteTransaction,
+    table: TableDefinition<(u128, u64), V>,
+    id: SessionId,
+) -> Result<u64, StorageError> {
+    let table = txn.open_table(table).map_err(db_error)?;
+    let last = table
+        .range((id.as_u128(), 0)..=(id.as_u128(), u64::MAX))
+        .map_err(db_error)?
+        .next_back()
+        .transpose()
+        .map_err(db_error)?;
+    Ok(last.map_or(0, |(key, _)| key.value().1 + 1))
+}
+
+/// Compress a session's uncompressed output into one run once it reaches
+/// `flush_size`.
+fn flush_output(
+    txn: &WriteTransaction,
+    id: SessionId,
+    flush_size: usize,
+) -> Result<(), StorageError> {
+    let mut output = txn.open_table(OUTPUT).map_err(db_error)?;
+    let mut pending = Vec::new();
+    let mut seqs = Vec::new();
+    for entry in output
+        .range((id.as_u128(), 0)..=(id.as_u128(), u64::MAX))
+        .map_err(db_error)?
+    {
+        let (key, chunk) = entry.map_err(db_error)?;
+        seqs.push(key.value().1);
+        pending.extend_from_slice(chunk.value());
+    }
+    let Some(&last) = seqs.last() else {
+        return Ok(());
+    };
+    if pending.len() < flush_size {
+        return Ok(());
+    }
+
+    txn.open_table(COMPRESSED_OUTPUT)
+        .map_err(db_error)?
+        .insert((id.as_u128(), last), compress(&pending)?.as_slice())
+        .map_err(db_error)?;
+    for seq in seqs {
+        output.remove((id.as_u128(), seq)).map_err(db_error)?;
+    }
+    Ok(())
+}
+
+#[async_trait]
+impl SessionStorage for RedbStorage {
+    async fn create(&self, ctx: &ExecutionContext) -> Result<SessionId, StorageError> {
+        let id = Uuid::new_v4();
+        let timestamp = now();
+
+        let session = Session {
+            id,
+            context: ctx.clone(),
+            prompt: None,
+            status: SessionStatus::Pending,
+            agent_session_id: None,
+            parent_session_id: None,
+            run_id: None,
+            tenant_id: None,
+            exit_code: None,
+            status_reason: None,
+            deleted: false,
+            created_at: timestamp,
+            updated_at: timestamp,
+            version: 0,
+        };
+        let json = encode(&session)?;
+
+        self.blocking(move |db| {
+            let txn = db.begin_write().map_err(db_error)?;
+            txn.open_table(SESSIONS)
+                .map_err(db_error)?
+                .insert(id.as_u128(), json.as_str())
+                .map_err(db_error)?;
+            txn.commit().map_err(db_error)
+        })
+        .await?;
+
+        Ok(id)
+    }
+
+    async fn get(&self, id: SessionId) -> Result<Option<Session>, StorageError> {
+        self.blocking(move |db| {
+            let txn = db.begin_read().map_err(db_error)?;
+            let table = txn.open_table(SESSIONS).map_err(db_error)?;
+            table
+                .get(id.as_u128())
+                .map_err(db_error)?
+                .map(|json| decode(json.value()))
+                .transpose()
+        })
+        .await
+    }
+
+    async fn update_status(
+        &self,
+        id: SessionId,
+        status: SessionStatus,
+        expected_version: Option<u64>,
+    ) -> Result<(), StorageError> {
+        self.update_versioned(id, expected_version, move |session| {
+            session.status = status;
+        })
+        .await
+    }
+
+    async fn update_context(
+        &self,
+        id: SessionId,
+        ctx: &ExecutionContext,
+        expected_version: Option<u64>,
+    ) -> Result<(), StorageError> {
+        let ctx = ctx.clone();
+        self.update_versioned(id, expected_version, move |session| session.context = ctx)
+            .await
+    }
+
+    async fn set_agent_session_id(
+        &self,
+        id: SessionId,
+        agent_session_id: String,
+    ) -> Result<(), StorageError> {
+        self.update(id, move |session| session.agent_session_id = Some(agent_session_id))
+            .await
+    }
+
+    async fn set_exit_code(&self, id: SessionId, exit_code: i32) -> Result<(), StorageError> {
+        self.update(id, move |session| session.exit_code = Some(exit_code))
+            .await
+    }
+
+    async fn set_status_reason(&self, id: SessionId, reason: String) -> Result<(), StorageError> {
+        self.update(id, move |session| session.status_reason = Some(reason))
+            .await
+    }
+
+    async fn set_prompt(&self, id: SessionId, prompt: String) -> Result<(), StorageError> {
+        self.update(id, move |session| session.prompt = Some(prompt)).await
+    }
+
+    async fn set_parent_session_id(
+        &self,
+        id: SessionId,
+        parent_session_id: SessionId,
+    ) -> Result<(), StorageError> {
+        self.update(id, move |session| {
+            session.parent_session_id = Some(parent_session_id);
+        })
+        .await
+    }
+
+    async fn set_run_id(&self, id: SessionId, run_id: RunId) -> Result<(), StorageError> {
+        self.update(id, move |session| session.run_id = Some(run_id)).await
+    }
+
+    async fn set_tenant_id(&self, id: SessionId, tenant_id: TenantId) -> Result<(), StorageError> {
+        self.update(id, move |session| session.tenant_id = Some(tenant_id))
+            .await
+    }
+
+    async fn soft_delete(&self, id: SessionId) -> Result<(), StorageError> {
+        self.update(id, |session| session.deleted = true).await
+    }
+
+    async fn restore(&self, id: SessionId) -> Result<(), StorageError> {
+        self.update(id, |session| session.deleted = false).await
+    }
+
+    async fn get_children(&self, id: SessionId) -> Result<Vec<Session>, StorageError> {
+        let mut result = self
+            .scan(move |session| session.parent_session_id == Some(id))
+            .await?;
+
+        // Sort by created_at ascending
+        result.sort_by_key(|s| s.created_at);
+
+        Ok(result)
+    }
+
+    async fn list(&self, filter: SessionFilter) -> Result<Vec<Session>, StorageError> {
+        let limit = filter.limit;
+        let mut result = self.scan(move |session| filter.matches(session)).await?;
+
+        // Sort by created_at descending
+        result.sort_by_key(|s| std::cmp::Reverse(s.created_at));
+
+        if let Some(limit) = limit {
+            result.truncate(limit);
+        }
+
+        Ok(result)
+    }
+
+    async fn append_output(&self, id: SessionId, data: &[u8]) -> Result<(), StorageError> {
+        let data = data.to_vec();
+        let (compress_output, flush_size) = (self.compress_output, self.output_flush_size);
+        self.blocking(move |db| {
+            let mut txn = db.begin_write().map_err(db_error)?;
+            txn.set_durability(Durability::Eventual);
+
+            let exists = txn
+                .open_table(SESSIONS)
+                .map_err(db_error)?
+                .get(id.as_u128())
+                .map_err(db_error)?
+                .is_some();
+            if !exists {
+                return Err(StorageError::NotFound(id));
+            }
+
+            let seq = next_seq(&txn, OUTPUT, id)?.max(next_seq(&txn, COMPRESSED_OUTPUT, id)?);
+            txn.open_table(OUTPUT)
+                .map_err(db_error)?
+                .insert((id.as_u128(), seq), data.as_slice())
+                .map_err(db_error)?;
+            if compress_output {
+                flush_output(&txn, id, flush_size)?;
+            }
+            txn.commit().map_err(db_error)
+        })
+        .await
+    }
+
+    async fn get_output(&self, id: SessionId) -> Result<Vec<u8>, StorageError> {
+        self.blocking(move |db| {
+            let txn = db.begin_read().map_err(db_error)?;
+            let sessions = txn.open_table(SESSIONS).map_err(db_error)?;
+            if sessions.get(id.as_u128()).map_err(db_error)?.is_none() {
+                return Err(StorageError::NotFound(id));
+            }
+
+            // Compressed runs and uncompressed chunks never interleave, so
+            // ordering both by sequence number restores append order.
+            let mut chunks = Vec::new();
+            for (table, compressed) in [(OUTPUT, false), (COMPRESSED_OUTPUT, true)] {
+                let table = txn.open_table(table).map_err(db_error)?;
+                for entry in table
+                    .range((id.as_u128(), 0)..=(id.as_u128(), u64::MAX))
+                    .map_err(db_error)?
+                {
+                    let (key, chunk) = entry.map_err(db_error)?;
+                    let chunk = if compressed {
+                        decompress(ZSTD, chunk.value())?
+                    } else {
+                        chunk.value().to_vec()
+                    };
+                    chunks.push((key.value().1, chunk));
+                }
+            }
+            chunks.sort_by_key(|(seq, _)| *seq);
+            Ok(chunks.into_iter().flat_map(|(_, chunk)| chunk).collect())
+        })
+        .await
+    }
+}
+
+#[async_trait]
+impl EventStorage for RedbStorage {
+    async fn append_event(&self, id: SessionId, msg: &LogMsg) -> Result<EventSeq, StorageError> {
+        let msg = msg.clone();
+        self.blocking(move |db| {
+            let txn = db.begin_write().map_err(db_error)?;
+            let seq = next_seq(&txn, EVENTS, id)?;
+            let (timestamp, subsec_millis) = event_time();
+            let event = StoredEvent {
+                session_id: id,
+                seq,
+                timestamp,
+                subsec_millis,
+                msg,
+            };
+            txn.open_table(EVENTS)
+                .map_err(db_error)?
+                .insert((id.as_u128(), seq), encode(&event)?.as_str())
+                .map_err(db_error)?;
+            txn.commit().map_err(db_error)?;
+            Ok(seq)
+        })
+        .await
+    }
+
+    async fn get_events(
+        &self,
+        id: SessionId,
+        from: EventSeq,
+        limit: Option<usize>,
+    ) -> Result<Vec<StoredEvent>, StorageError> {
+        self.blocking(move |db| {
+            let txn = db.begin_read().map_err(db_error)?;
+            let table = txn.open_table(EVENTS).map_err(db_error)?;
+            table
+                .range((id.as_u128(), from)..=(id.as_u128(), u64::MAX))
+                .map_err(db_error)?
+                .take(limit.unwrap_or(usize::MAX))
+                .map(|entry| {
+                    let (_, json) = entry.map_err(db_error)?;
+                    decode(json.value())
+                })
+                .collect()
+        })
+        .await
+    }
+
+    async fn event_count(&self, id: SessionId) -> Result<u64, StorageError> {
+        self.blocking(move |db| {
+            let txn = db.begin_read().map_err(db_error)?;
+            let table = txn.open_table(EVENTS).map_err(db_error)?;
+            let count = table
+                .range((id.as_u128(), 0)..=(id.as_u128(), u64::MAX))
+                .map_err(db_error)?
+                .count();
+            Ok(count as u64)
+        })
+        .await
+    }
+}
+
+impl RedbStorage {
+    /// Append a JSON record to a per-session record table.
+    async fn append_record(
+        &self,
+        table: TableDefinition<'static, (u128, u64), &'static str>,
+        id: SessionId,
+        json: String,
+    ) -> Result<(), StorageError> {
+        self.blocking(move |db| {
+            let txn = db.begin_write().map_err(db_error)?;
+            let seq = next_seq(&txn, table, id)?;
+            txn.open_table(table)
+                .map_err(db_error)?
+                .insert((id.as_u128(), seq), json.as_str())
+                .map_err(db_error)?;
+            txn.commit().map_err(db_error)
+        })
+        .await
+    }
+
+    /// Read the records of one session, or of all sessions, from a
+    /// per-session record table.
+    async fn read_records<T>(
+        &self,
+        table: TableDefinition<'static, (u128, u64), &'static str>,
+        id: Option<SessionId>,
+    ) -> Result<Vec<T>, StorageError>
+    where
+        T: serde::de::DeserializeOwned + Send + 'static,
+    {
+        self.blocking(move |db| {
+            let txn = db.begin_read().map_err(db_error)?;
+            let table = txn.open_table(table).map_err(db_error)?;
+            let entries = id
+                .map_or_else(
+                    || table.iter(),
+                    |id| table.range((id.as_u128(), 0)..=(id.as_u128(), u64::MAX)),
+                )
+                .map_err(db_error)?;
+            entries
+                .map(|entry| {
+                    let (_, json) = entry.map_err(db_error)?;
+                    decode(json.value())
+                })
+                .collect()
+        })
+        .await
+    }
+}
+
+#[async_trait]
+impl AuditStorage for RedbStorage {
+    async fn record_tool_call(&self, record: ToolCallRecord) -> Result<(), StorageError> {
+        self.append_record(TOOL_CALLS, record.session_id, encode(&record)?)
+            .await
+    }
+
+    async fn get_tool_calls(&self, id: SessionId) -> Result<Vec<ToolCallRecord>, StorageError> {
+        self.read_records(TOOL_CALLS, Some(id)).await
+    }
+
+    async fn query_tool_calls(
+        &self,
+        filter: ToolCallFilter,
+    ) -> Result<Vec<ToolCallRecord>, StorageError> {
+        let mut result: Vec<ToolCallRecord> =
+            self.read_records(TOOL_CALLS, filter.session_id).await?;
+        result.retain(|record| filter.matches(record));
+        result.sort_by_key(|record| record.decided_at);
+        if let Some(limit) = filter.limit {
+            result.truncate(limit);
+        }
+        Ok(result)
+    }
+
+    async fn record_hook_event(&self, record: HookEventRecord) -> Result<(), StorageError> {
+        self.append_record(HOOK_EVENTS, record.session_id, encode(&record)?)
+            .await
+    }
+
+    async fn query_hook_events(
+        &self,
+        filter: HookEventFilter,
+    ) -> Result<Vec<HookEventRecord>, StorageError> {
+        let mut result: Vec<HookEventRecord> =
+            self.read_records(HOOK_EVENTS, filter.session_id).await?;
+        result.retain(|record| filter.matches(record));
+        result.sort_by_key(|record| record.timestamp);
+        if let Some(limit) = filter.limit {
+            result.truncate(limit);
+        }
+        Ok(result)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_persists_sessions_output_and_events() {
+        let path = std::env::temp_dir().join(format!("redb-storage-{}.redb", Uuid::new_v4()));
+
+        let storage = RedbStorage::new(&path).await.unwrap();
+        let id = storage
+            .create(&ExecutionContext::new(PathBuf::from("/tmp/project")))
+            .await
+            .unwrap();
+        storage
+            .update_status(id, SessionStatus::Completed, None)
+            .await
+            .unwrap();
+        storage.set_prompt(id, "fix it".into()).await.unwrap();
+        storage.append_output(id, b"hello ").await.unwrap();
+        storage.append_output(id, b"world").await.unwrap();
+        assert_eq!(storage.append_event(id, &LogMsg::Ready).await.unwrap(), 0);
+        assert_eq!(storage.append_event(id, &LogMsg::Finished).await.unwrap(), 1);
+        assert!(matches!(
+            storage.append_output(Uuid::new_v4(), b"x").await,
+            Err(StorageError::NotFound(_))
+        ));
+        drop(storage);
+
+        let storage = RedbStorage::new(&path).await.unwrap();
+        let session = storage.get(id).await.unwrap().unwrap();
+        assert_eq!(session.status, SessionStatus::Completed);
+        assert_eq!(session.prompt.as_deref(), Some("fix it"));
+        assert_eq!(storage.get_output(id).await.unwrap(), b"hello world");
+        assert_eq!(storage.event_count(id).await.unwrap(), 2);
+
+        let events = storage.get_events(id, 1, None).await.unwrap();
+        assert_eq!(events.len(), 1);
+        assert!(matches!(events[0].msg, LogMsg::Finished));
+
+        let completed = SessionFilter {
+            status: Some(SessionStatus::Completed),
+            ..Default::default()
+        };
+        assert_eq!(storage.list(completed).await.unwrap().len(), 1);
+
+        drop(storage);
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_output_compression() {
+        let path = std::env::temp_dir().join(format!("redb-storage-{}.redb", Uuid::new_v4()));
+        let storage = RedbStorage::new(&path)
+            .await
+            .unwrap()
+            .with_output_compression(true)
+            .with_output_flush_size(16);
+
+        let id = storage
+            .create(&ExecutionContext::new(PathBuf::from("/tmp")))
+            .await
+            .unwrap();
+        let mut expected = Vec::new();
+        for i in 0..10 {
+            let line = format!("line {i}\n");
+            storage.append_output(id, line.as_bytes()).await.unwrap();
+            expected.extend_from_slice(line.as_bytes());
+        }
+        assert_eq!(storage.get_output(id).await.unwrap(), expected);
+
+        // Output compressed earlier is still readable without compression.
+        drop(storage);
+        let storage = RedbStorage::new(&path).await.unwrap();
+        storage.append_output(id, b"tail").await.unwrap();
+        expected.extend_from_slice(b"tail");
+        assert_eq!(storage.get_output(id).await.unwrap(), expected);
+
+        drop(storage);
+        std::fs::remove_file(path).unwrap();
+    }
+}
@@ -0,0 +1,115 @@
+//! S3-compatible output blob store (feature-gated).
+
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use futures::{StreamExt, TryStreamExt};
+use object_store::{ObjectStore, aws::AmazonS3Builder, path::Path};
+use remote_agents_core::traits::{BlobStream, OutputBlobStore, StorageError};
+
+/// Blob store backed by an S3-compatible bucket (AWS S3, `MinIO`, R2, ...).
+#[derive(Debug, Clone)]
+pub struct S3BlobStore {
+    store: Arc<dyn ObjectStore>,
+    prefix: Option<String>,
+}
+
+impl S3BlobStore {
+    /// Connect to a bucket, configured from the standard `AWS_*` environment
+    /// variables (`AWS_ENDPOINT` selects a non-AWS service).
+    ///
+    /// # Errors
+    /// Returns error if the configuration is invalid.
+    pub fn new(bucket: &str) -> Result<Self, StorageError> {
+        let store = AmazonS3Builder::from_env()
+            .with_bucket_name(bucket)
+            .build()
+            .map_err(store_error)?;
+        Ok(Self::from_store(Arc::new(store)))
+    }
+
+    /// Use a preconfigured object store.
+    #[must_use]
+    pub fn from_store(store: Arc<dyn ObjectStore>) -> Self {
+        Self {
+            store,
+            prefix: None,
+        }
+    }
+
+    /// Store blobs under a key prefix within the bucket.
+    #[must_use]
+    pub fn with_prefix(mut self, prefix: impl Into<String>) -> Self {
+        self.prefix = Some(prefix.into());
+        self
+    }
+
+    fn path(&self, key: &str) -> Path {
+        self.prefix.as_ref().map_or_else(
+            || Path::from(key),
+            |prefix| Path::from(format!("{prefix}/{key}")),
+        )
+    }
+}
+
+#[allow(clippy::needless_pass_by_value)]
+fn store_error(e: object_store::Error) -> StorageError {
+    StorageError::Internal(e.to_string())
+}
+
+#[async_trait]
+impl OutputBlobStore for S3BlobStore {
+    async fn put(&self, key: &str, data: Vec<u8>) -> Result<(), StorageError> {
+        self.store
+            .put(&self.path(key), data.into())
+            .await
+            .map_err(store_error)?;
+        Ok(())
+    }
+
+    async fn get(&self, key: &str) -> Result<Vec<u8>, StorageError> {
+        let result = self.store.get(&self.path(key)).await.map_err(store_error)?;
+        Ok(result.bytes().await.map_err(store_error)?.to_vec())
+    }
+
+    async fn stream(&self, key: &str) -> Result<BlobStream, StorageError> {
+        let result = self.store.get(&self.path(key)).await.map_err(store_error)?;
+        Ok(result
+            .into_stream()
+            .map_ok(|chunk| chunk.to_vec())
+            .map_err(store_error)
+            .boxed())
+    }
+
+    async fn delete(&self, key: &str) -> Result<(), StorageError> {
+        match self.store.delete(&self.path(key)).await {
+            Err(object_store::Error::NotFound { .. }) | Ok(()) => Ok(()),
+            Err(e) => Err(store_error(e)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use object_store::memory::InMemory;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_prefixed_round_trip() {
+        let backing = Arc::new(InMemory::new());
+        let store = S3BlobStore::from_store(backing.clone()).with_prefix("outputs");
+
+        store.put("session/0001", b"hello".to_vec()).await.unwrap();
+        assert_eq!(store.get("session/0001").await.unwrap(), b"hello");
+        assert!(backing.head(&Path::from("outputs/session/0001")).await.is_ok());
+
+        let stream = store.stream("session/0001").await.unwrap();
+        let chunks: Vec<Vec<u8>> = stream.try_collect().await.unwrap();
+        assert_eq!(chunks.concat(), b"hello");
+
+        store.delete("session/0001").await.unwrap();
+        store.delete("session/0001").await.unwrap();
+        assert!(store.get("session/0001").await.is_err());
+    }
+}
@@ -0,0 +1,121 @@
+//! Helpers shared by the SQL storage backends.
+
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use remote_agents_core::traits::{OutputBlobStore, SessionId, StorageError};
+use serde::{Serialize, de::DeserializeOwned};
+use serde_json::Value;
+
+use super::compression::decompress;
+
+pub(super) fn now() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| i64::try_from(d.as_secs()).unwrap_or(i64::MAX))
+}
+
+/// The time now as Unix epoch seconds and milliseconds past them, for
+/// timing events.
+pub(super) fn event_time() -> (i64, u32) {
+    let time = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default();
+    (
+        i64::try_from(time.as_secs()).unwrap_or(i64::MAX),
+        time.subsec_millis(),
+    )
+}
+
+#[allow(clippy::needless_pass_by_value)]
+pub(super) fn db_error(e: sqlx::Error) -> StorageError {
+    StorageError::Internal(e.to_string())
+}
+
+#[allow(clippy::needless_pass_by_value)]
+pub(super) fn json_error(e: serde_json::Error) -> StorageError {
+    StorageError::Internal(e.to_string())
+}
+
+/// Encode a unit enum as its serde name (e.g. `"running"`).
+pub(super) fn enum_to_str<T: Serialize>(value: &T) -> Result<String, StorageError> {
+    match serde_json::to_value(value).map_err(json_error)? {
+        Value::String(s) => Ok(s),
+        other => Err(StorageError::Internal(format!("Expected string, got {other}"))),
+    }
+}
+
+/// Convert a session version to the signed integer SQL stores it as.
+pub(super) fn version_to_i64(version: u64) -> Result<i64, StorageError> {
+    i64::try_from(version).map_err(|e| StorageError::Internal(e.to_string()))
+}
+
+pub(super) fn enum_from_str<T: DeserializeOwned>(value: String) -> Result<T, StorageError> {
+    serde_json::from_value(Value::String(value)).map_err(json_error)
+}
+
+/// Key of the blob holding a session's output from chunk `chunk_id` on.
+pub(super) fn blob_key(session_id: SessionId, chunk_id: i64) -> String {
+    format!("{session_id}/{chunk_id:020}")
+}
+
+/// A stored output chunk: inline data, blob key, and compression.
+pub(super) type OutputChunk = (Vec<u8>, Option<String>, Option<String>);
+
+/// Concatenate output chunks, fetching those moved to a blob store and
+/// decompressing compressed ones.
+pub(super) async fn assemble_output(
+    chunks: Vec<OutputChunk>,
+    blobs: Option<&dyn OutputBlobStore>,
+) -> Result<Vec<u8>, StorageError> {
+    let mut output = Vec::new();
+    for (data, blob_key, compression) in chunks {
+        let data = match (blob_key, blobs) {
+            (None, _) => data,
+            (Some(key), Some(blobs)) => blobs.get(&key).await?,
+            (Some(key), None) => {
+                return Err(StorageError::Internal(format!(
+                    "Output blob {key} requires a blob store"
+                )));
+            }
+        };
+        match compression {
+            Some(compression) => output.extend(decompress(&compression, &data)?),
+            None => output.extend(data),
+        }
+    }
+    Ok(output)
+}
+
+/// A stored output chunk with the offset of its first byte in the output.
+pub(super) type OutputSpan = (Vec<u8>, Option<String>, Option<String>, i64);
+
+/// Assemble the chunks overlapping a range of output (in order) and cut the
+/// range out of them.
+pub(super) async fn assemble_output_range(
+    spans: Vec<OutputSpan>,
+    blobs: Option<&dyn OutputBlobStore>,
+    offset: u64,
+    len: usize,
+) -> Result<Vec<u8>, StorageError> {
+    let Some(&(.., start)) = spans.first() else {
+        return Ok(Vec::new());
+    };
+    let chunks = spans
+        .into_iter()
+        .map(|(data, blob_key, compression, _)| (data, blob_key, compression))
+        .collect();
+    let mut output = assemble_output(chunks, blobs).await?;
+
+    let skip = offset.saturating_sub(u64::try_from(start).unwrap_or_default());
+    let skip = usize::try_from(skip).map_or(output.len(), |s| s.min(output.len()));
+    output.truncate(skip.saturating_add(len));
+    output.drain(..skip);
+    Ok(output)
+}
+
+/// Bounds of a byte range as SQL integers.
+pub(super) fn range_bounds(offset: u64, len: usize) -> (i64, i64) {
+    let start = i64::try_from(offset).unwrap_or(i64::MAX);
+    let len = i64::try_from(len).unwrap_or(i64::MAX);
+    (start, start.saturating_add(len))
+}
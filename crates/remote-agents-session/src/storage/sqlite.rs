@@ -1,61 +1,1396 @@
 //! SQLite session storage (feature-gated).
 
-// TODO: Implement SQLite storage using sqlx.
-// This is a placeholder for the feature-gated implementation.
+use std::{str::FromStr, sync::Arc};
 
 use async_trait::async_trait;
 use remote_agents_core::{
-    ExecutionContext,
-    traits::{Session, SessionFilter, SessionId, SessionStatus, SessionStorage, StorageError},
+    ExecutionContext, LogMsg,
+    traits::{
+        AuditStorage, EventSeq, EventStorage, HookEventFilter, HookEventRecord, OutputBlobStore,
+        RunId, Session, SessionFilter, SessionId, SessionStatus, SessionStorage, StorageError,
+        StoredEvent, TenantId, ToolCallFilter, ToolCallRecord,
+    },
 };
+use sqlx::{
+    Encode, QueryBuilder, Row, Sqlite, SqlitePool, Type,
+    sqlite::{SqliteConnectOptions, SqliteJournalMode, SqlitePoolOptions, SqliteRow},
+};
+use uuid::Uuid;
+
+use super::{
+    DEFAULT_OUTPUT_FLUSH_SIZE,
+    compression::{ZSTD, compress},
+    sql::{
+        OutputChunk, OutputSpan, assemble_output, assemble_output_range, blob_key, db_error,
+        enum_from_str, enum_to_str, event_time, json_error, now, range_bounds, version_to_i64,
+    },
+};
+
+/// Number of tokens around a match included in a search snippet.
+const SNIPPET_TOKENS: i64 = 16;
+
+/// A session matching a full-text search.
+#[derive(Debug, Clone)]
+pub struct SearchHit {
+    /// The matching session.
+    pub session: Session,
+    /// Excerpt of the best match, with matched terms wrapped in `[` `]`.
+    pub snippet: String,
+    /// BM25 rank of the best match; lower is more relevant.
+    pub rank: f64,
+}
 
 /// SQLite storage implementation.
+///
+/// Sessions, output, events, and tool call audit records persist across
+/// restarts. Secrets in a session's `ExecutionContext` are redacted before
+/// they are written, so they are not restored when the session is read.
 pub struct SqliteStorage {
-    // pool: sqlx::SqlitePool,
+    pool: SqlitePool,
+    blobs: Option<Arc<dyn OutputBlobStore>>,
+    output_flush_size: usize,
+    compress_output: bool,
 }
 
 impl SqliteStorage {
-    /// Create a new SQLite storage.
+    /// Open (creating if missing) a database file and run migrations.
+    ///
+    /// `database_url` is a URL such as `sqlite://sessions.db`.
     ///
     /// # Errors
-    /// Returns error if database connection fails.
-    pub async fn new(_database_url: &str) -> Result<Self, StorageError> {
-        // TODO: Implement
-        Err(StorageError::Internal("SQLite storage not yet implemented".to_string()))
+    /// Returns error if database connection or migration fails.
+    pub async fn new(database_url: &str) -> Result<Self, StorageError> {
+        let options = SqliteConnectOptions::from_str(database_url)
+            .map_err(db_error)?
+            .create_if_missing(true)
+            .journal_mode(SqliteJournalMode::Wal);
+        let pool = SqlitePoolOptions::new()
+            .connect_with(options)
+            .await
+            .map_err(db_error)?;
+        Self::from_pool(pool).await
+    }
+
+    /// Use an existing connection pool, running migrations on it.
+    ///
+    /// # Errors
+    /// Returns error if migration fails.
+    pub async fn from_pool(pool: SqlitePool) -> Result<Self, StorageError> {
+        sqlx::migrate!("./migrations/sqlite")
+            .run(&pool)
+            .await
+            .map_err(|e| StorageError::Internal(e.to_string()))?;
+        Ok(Self {
+            pool,
+            blobs: None,
+            output_flush_size: DEFAULT_OUTPUT_FLUSH_SIZE,
+            compress_output: false,
+        })
+    }
+
+    /// Move output to a blob store, keeping only blob keys in the database.
+    ///
+    /// Output is appended inline and moved to the store in one blob per
+    /// flush (see [`Self::with_output_flush_size`]).
+    #[must_use]
+    pub fn with_blob_store(mut self, blobs: Arc<dyn OutputBlobStore>) -> Self {
+        self.blobs = Some(blobs);
+        self
+    }
+
+    /// Compress output with zstd at rest.
+    ///
+    /// Output is appended uncompressed and compressed one flush at a time
+    /// (see [`Self::with_output_flush_size`]). Output compressed earlier is
+    /// still read back if this is later turned off.
+    #[must_use]
+    pub const fn with_output_compression(mut self, enabled: bool) -> Self {
+        self.compress_output = enabled;
+        self
+    }
+
+    /// Set how much appended output is collected per session before it is
+    /// flushed to the blob store and/or compressed
+    /// (default [`DEFAULT_OUTPUT_FLUSH_SIZE`]).
+    #[must_use]
+    pub const fn with_output_flush_size(mut self, bytes: usize) -> Self {
+        self.output_flush_size = bytes;
+        self
+    }
+
+    /// Set a single session column, bumping `updated_at`.
+    async fn set_column<T>(
+        &self,
+        id: SessionId,
+        column: &'static str,
+        value: T,
+    ) -> Result<(), StorageError>
+    where
+        T: 'static + Send + for<'q> Encode<'q, Sqlite> + Type<Sqlite>,
+    {
+        let sql = format!("UPDATE sessions SET {column} = ?, updated_at = ? WHERE id = ?");
+        let result = sqlx::query(&sql)
+            .bind(value)
+            .bind(now())
+            .bind(id.to_string())
+            .execute(&self.pool)
+            .await
+            .map_err(db_error)?;
+
+        if result.rows_affected() == 0 {
+            return Err(StorageError::NotFound(id));
+        }
+        Ok(())
+    }
+
+    /// Update a session's status or context, bumping its version.
+    ///
+    /// `set` pushes the `column = value` assignments to update.
+    async fn update_versioned<F>(
+        &self,
+        id: SessionId,
+        expected_version: Option<u64>,
+        set: F,
+    ) -> Result<(), StorageError>
+    where
+        F: FnOnce(&mut QueryBuilder<'_, Sqlite>),
+    {
+        let mut query = QueryBuilder::<Sqlite>::new("UPDATE sessions SET ");
+        set(&mut query);
+        query
+            .push(", version = version + 1, updated_at = ")
+            .push_bind(now())
+            .push(" WHERE id = ")
+            .push_bind(id.to_string());
+        if let Some(version) = expected_version {
+            query.push(" AND version = ").push_bind(version_to_i64(version)?);
+        }
+        let result = query.build().execute(&self.pool).await.map_err(db_error)?;
+
+        if result.rows_affected() == 0 {
+            if self.session_exists(id).await? {
+                return Err(StorageError::Conflict(id));
+            }
+            return Err(StorageError::NotFound(id));
+        }
+        Ok(())
     }
+
+    /// Flush a session's appended output once it reaches the flush size,
+    /// merging it into one chunk that is compressed and/or moved to the
+    /// blob store.
+    async fn flush_output(&self, id: SessionId) -> Result<(), StorageError> {
+        let pending: i64 = sqlx::query_scalar(
+            "SELECT COALESCE(SUM(LENGTH(data)), 0) FROM session_output
+             WHERE session_id = ? AND blob_key IS NULL AND compression IS NULL",
+        )
+        .bind(id.to_string())
+        .fetch_one(&self.pool)
+        .await
+        .map_err(db_error)?;
+        if usize::try_from(pending).unwrap_or(usize::MAX) < self.output_flush_size {
+            return Ok(());
+        }
+
+        let chunks: Vec<(i64, Vec<u8>)> = sqlx::query_as(
+            "SELECT id, data FROM session_output
+             WHERE session_id = ? AND blob_key IS NULL AND compression IS NULL
+             ORDER BY id",
+        )
+        .bind(id.to_string())
+        .fetch_all(&self.pool)
+        .await
+        .map_err(db_error)?;
+        let (Some(&(first, _)), Some(&(last, _))) = (chunks.first(), chunks.last()) else {
+            return Ok(());
+        };
+
+        let data: Vec<u8> = chunks.into_iter().flat_map(|(_, data)| data).collect();
+        let size = i64::try_from(data.len()).unwrap_or(i64::MAX);
+        let (data, compression) = if self.compress_output {
+            (compress(&data)?, Some(ZSTD))
+        } else {
+            (data, None)
+        };
+        let (data, key) = match &self.blobs {
+            Some(blobs) => {
+                let key = blob_key(id, first);
+                blobs.put(&key, data).await?;
+                (Vec::new(), Some(key))
+            }
+            None => (data, None),
+        };
+
+        // Replace the chunks with the merged one in the first chunk's place.
+        let mut tx = self.pool.begin().await.map_err(db_error)?;
+        sqlx::query(
+            "DELETE FROM session_output
+             WHERE session_id = ? AND blob_key IS NULL AND compression IS NULL
+             AND id BETWEEN ? AND ?",
+        )
+        .bind(id.to_string())
+        .bind(first)
+        .bind(last)
+        .execute(&mut *tx)
+        .await
+        .map_err(db_error)?;
+        sqlx::query(
+            "INSERT INTO session_output (id, session_id, data, blob_key, compression, size)
+             VALUES (?, ?, ?, ?, ?, ?)",
+        )
+        .bind(first)
+        .bind(id.to_string())
+        .bind(data)
+        .bind(key)
+        .bind(compression)
+        .bind(size)
+        .execute(&mut *tx)
+        .await
+        .map_err(db_error)?;
+        tx.commit().await.map_err(db_error)
+    }
+
+    /// Search prompts, metadata, and output, most relevant sessions first.
+    ///
+    /// Every word of `query` must appear in the same prompt, metadata, or
+    /// output chunk. Output stored before search was available is only
+    /// indexed if it had not yet been flushed. Soft-deleted sessions are
+    /// left out.
+    ///
+    /// # Errors
+    /// Returns error if the query fails.
+    pub async fn search(&self, query: &str, limit: usize) -> Result<Vec<SearchHit>, StorageError> {
+        let query = fts_query(query);
+        if query.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        // SQLite takes the bare snippet column from the row with the MIN rank.
+        let rows = sqlx::query(
+            "WITH hits AS (
+                 SELECT sessions.id AS session_id,
+                        snippet(sessions_fts, -1, '[', ']', '...', ?) AS snippet,
+                        bm25(sessions_fts) AS rank
+                 FROM sessions_fts JOIN sessions ON sessions.rowid = sessions_fts.rowid
+                 WHERE sessions_fts MATCH ?
+                 UNION ALL
+                 SELECT session_id,
+                        snippet(session_output_fts, 1, '[', ']', '...', ?),
+                        bm25(session_output_fts)
+                 FROM session_output_fts
+                 WHERE session_output_fts MATCH ?
+             )
+             SELECT sessions.*, hits.snippet, MIN(hits.rank) AS rank
+             FROM hits JOIN sessions ON sessions.id = hits.session_id
+             WHERE sessions.deleted = 0
+             GROUP BY sessions.id
+             ORDER BY rank
+             LIMIT ?",
+        )
+        .bind(SNIPPET_TOKENS)
+        .bind(&query)
+        .bind(SNIPPET_TOKENS)
+        .bind(&query)
+        .bind(i64::try_from(limit).unwrap_or(i64::MAX))
+        .fetch_all(&self.pool)
+        .await
+        .map_err(db_error)?;
+
+        rows.iter()
+            .map(|row| {
+                Ok(SearchHit {
+                    session: session_from_row(row)?,
+                    snippet: row.try_get("snippet").map_err(db_error)?,
+                    rank: row.try_get("rank").map_err(db_error)?,
+                })
+            })
+            .collect()
+    }
+
+    async fn session_exists(&self, id: SessionId) -> Result<bool, StorageError> {
+        let row = sqlx::query("SELECT 1 FROM sessions WHERE id = ?")
+            .bind(id.to_string())
+            .fetch_optional(&self.pool)
+            .await
+            .map_err(db_error)?;
+        Ok(row.is_some())
+    }
+}
+
+fn parse_id(value: &str) -> Result<SessionId, StorageError> {
+    Uuid::parse_str(value).map_err(|e| StorageError::Internal(e.to_string()))
+}
+
+/// Turn free text into an FTS5 query matching every word, quoting each
+/// word so punctuation is not parsed as query syntax.
+fn fts_query(text: &str) -> String {
+    text.split_whitespace()
+        .map(|word| format!("\"{}\"", word.replace('"', "\"\"")))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+fn session_from_row(row: &SqliteRow) -> Result<Session, StorageError> {
+    let id: String = row.try_get("id").map_err(db_error)?;
+    let context: String = row.try_get("context").map_err(db_error)?;
+    let parent_session_id: Option<String> = row.try_get("parent_session_id").map_err(db_error)?;
+    let version: i64 = row.try_get("version").map_err(db_error)?;
+
+    Ok(Session {
+        id: parse_id(&id)?,
+        context: serde_json::from_str(&context).map_err(json_error)?,
+        prompt: row.try_get("prompt").map_err(db_error)?,
+        status: enum_from_str(row.try_get("status").map_err(db_error)?)?,
+        agent_session_id: row.try_get("agent_session_id").map_err(db_error)?,
+        parent_session_id: parent_session_id.as_deref().map(parse_id).transpose()?,
+        run_id: row.try_get("run_id").map_err(db_error)?,
+        tenant_id: row.try_get("tenant_id").map_err(db_error)?,
+        exit_code: row.try_get("exit_code").map_err(db_error)?,
+        status_reason: row.try_get("status_reason").map_err(db_error)?,
+        deleted: row.try_get("deleted").map_err(db_error)?,
+        created_at: row.try_get("created_at").map_err(db_error)?,
+        updated_at: row.try_get("updated_at").map_err(db_error)?,
+        version: u64::try_from(version).unwrap_or_default(),
+    })
+}
+
+fn event_from_row(row: &SqliteRow) -> Result<StoredEvent, StorageError> {
+    let session_id: String = row.try_get("session_id").map_err(db_error)?;
+    let seq: i64 = row.try_get("seq").map_err(db_error)?;
+    let subsec_millis: i64 = row.try_get("subsec_millis").map_err(db_error)?;
+    let msg: String = row.try_get("msg").map_err(db_error)?;
+
+    Ok(StoredEvent {
+        session_id: parse_id(&session_id)?,
+        seq: EventSeq::try_from(seq).unwrap_or_default(),
+        timestamp: row.try_get("timestamp").map_err(db_error)?,
+        subsec_millis: u32::try_from(subsec_millis).unwrap_or_default(),
+        msg: serde_json::from_str(&msg).map_err(json_error)?,
+    })
+}
+
+fn tool_call_from_row(row: &SqliteRow) -> Result<ToolCallRecord, StorageError> {
+    let session_id: String = row.try_get("session_id").map_err(db_error)?;
+    let input: String = row.try_get("input").map_err(db_error)?;
+    let latency_ms: Option<i64> = row.try_get("latency_ms").map_err(db_error)?;
+
+    Ok(ToolCallRecord {
+        session_id: parse_id(&session_id)?,
+        tool_call_id: row.try_get("tool_call_id").map_err(db_error)?,
+        tool_name: row.try_get("tool_name").map_err(db_error)?,
+        input: serde_json::from_str(&input).map_err(json_error)?,
+        outcome: enum_from_str(row.try_get("outcome").map_err(db_error)?)?,
+        decided_by: row.try_get("decided_by").map_err(db_error)?,
+        reason: row.try_get("reason").map_err(db_error)?,
+        requested_at: row.try_get("requested_at").map_err(db_error)?,
+        decided_at: row.try_get("decided_at").map_err(db_error)?,
+        input_hash: row.try_get("input_hash").map_err(db_error)?,
+        latency_ms: latency_ms.and_then(|ms| u64::try_from(ms).ok()),
+    })
+}
+
+fn hook_event_from_row(row: &SqliteRow) -> Result<HookEventRecord, StorageError> {
+    let session_id: String = row.try_get("session_id").map_err(db_error)?;
+    let input: String = row.try_get("input").map_err(db_error)?;
+    let output: Option<String> = row.try_get("output").map_err(db_error)?;
+
+    Ok(HookEventRecord {
+        session_id: parse_id(&session_id)?,
+        event: row.try_get("event").map_err(db_error)?,
+        tool_name: row.try_get("tool_name").map_err(db_error)?,
+        tool_call_id: row.try_get("tool_call_id").map_err(db_error)?,
+        input: serde_json::from_str(&input).map_err(json_error)?,
+        output: output
+            .as_deref()
+            .map(serde_json::from_str)
+            .transpose()
+            .map_err(json_error)?,
+        timestamp: row.try_get("timestamp").map_err(db_error)?,
+    })
 }
 
 #[async_trait]
 impl SessionStorage for SqliteStorage {
-    async fn create(&self, _ctx: &ExecutionContext) -> Result<SessionId, StorageError> {
-        Err(StorageError::Internal("Not implemented".to_string()))
+    async fn create(&self, ctx: &ExecutionContext) -> Result<SessionId, StorageError> {
+        let id = Uuid::new_v4();
+        let timestamp = now();
+
+        sqlx::query(
+            "INSERT INTO sessions (id, context, working_dir, status, created_at, updated_at)
+             VALUES (?, ?, ?, ?, ?, ?)",
+        )
+        .bind(id.to_string())
+        .bind(serde_json::to_string(ctx).map_err(json_error)?)
+        .bind(ctx.working_dir.to_string_lossy())
+        .bind(enum_to_str(&SessionStatus::Pending)?)
+        .bind(timestamp)
+        .bind(timestamp)
+        .execute(&self.pool)
+        .await
+        .map_err(db_error)?;
+
+        Ok(id)
+    }
+
+    async fn get(&self, id: SessionId) -> Result<Option<Session>, StorageError> {
+        sqlx::query("SELECT * FROM sessions WHERE id = ?")
+            .bind(id.to_string())
+            .fetch_optional(&self.pool)
+            .await
+            .map_err(db_error)?
+            .as_ref()
+            .map(session_from_row)
+            .transpose()
     }
 
-    async fn get(&self, _id: SessionId) -> Result<Option<Session>, StorageError> {
-        Err(StorageError::Internal("Not implemented".to_string()))
+    async fn update_status(
+        &self,
+        id: SessionId,
+        status: SessionStatus,
+        expected_version: Option<u64>,
+    ) -> Result<(), StorageError> {
+        let status = enum_to_str(&status)?;
+        self.update_versioned(id, expected_version, |query| {
+            query.push("status = ").push_bind(status);
+        })
+        .await
     }
 
-    async fn update_status(&self, _id: SessionId, _status: SessionStatus) -> Result<(), StorageError> {
-        Err(StorageError::Internal("Not implemented".to_string()))
+    async fn update_context(
+        &self,
+        id: SessionId,
+        ctx: &ExecutionContext,
+        expected_version: Option<u64>,
+    ) -> Result<(), StorageError> {
+        let context = serde_json::to_string(ctx).map_err(json_error)?;
+        let working_dir = ctx.working_dir.to_string_lossy().into_owned();
+        self.update_versioned(id, expected_version, |query| {
+            query
+                .push("context = ")
+                .push_bind(context)
+                .push(", working_dir = ")
+                .push_bind(working_dir);
+        })
+        .await
     }
 
     async fn set_agent_session_id(
         &self,
-        _id: SessionId,
-        _agent_session_id: String,
+        id: SessionId,
+        agent_session_id: String,
     ) -> Result<(), StorageError> {
-        Err(StorageError::Internal("Not implemented".to_string()))
+        self.set_column(id, "agent_session_id", agent_session_id).await
+    }
+
+    async fn set_exit_code(&self, id: SessionId, exit_code: i32) -> Result<(), StorageError> {
+        self.set_column(id, "exit_code", exit_code).await
+    }
+
+    async fn set_status_reason(&self, id: SessionId, reason: String) -> Result<(), StorageError> {
+        self.set_column(id, "status_reason", reason).await
+    }
+
+    async fn set_prompt(&self, id: SessionId, prompt: String) -> Result<(), StorageError> {
+        self.set_column(id, "prompt", prompt).await
+    }
+
+    async fn set_parent_session_id(
+        &self,
+        id: SessionId,
+        parent_session_id: SessionId,
+    ) -> Result<(), StorageError> {
+        self.set_column(id, "parent_session_id", parent_session_id.to_string())
+            .await
+    }
+
+    async fn set_run_id(&self, id: SessionId, run_id: RunId) -> Result<(), StorageError> {
+        self.set_column(id, "run_id", run_id).await
+    }
+
+    async fn set_tenant_id(&self, id: SessionId, tenant_id: TenantId) -> Result<(), StorageError> {
+        self.set_column(id, "tenant_id", tenant_id).await
+    }
+
+    async fn soft_delete(&self, id: SessionId) -> Result<(), StorageError> {
+        self.set_column(id, "deleted", true).await
+    }
+
+    async fn restore(&self, id: SessionId) -> Result<(), StorageError> {
+        self.set_column(id, "deleted", false).await
+    }
+
+    async fn get_children(&self, id: SessionId) -> Result<Vec<Session>, StorageError> {
+        sqlx::query(
+            "SELECT * FROM sessions WHERE parent_session_id = ? ORDER BY created_at, rowid",
+        )
+        .bind(id.to_string())
+        .fetch_all(&self.pool)
+        .await
+        .map_err(db_error)?
+        .iter()
+        .map(session_from_row)
+        .collect()
+    }
+
+    async fn list(&self, filter: SessionFilter) -> Result<Vec<Session>, StorageError> {
+        let mut query = QueryBuilder::<Sqlite>::new("SELECT * FROM sessions WHERE 1 = 1");
+        if !filter.include_deleted {
+            query.push(" AND deleted = 0");
+        }
+        if let Some(status) = filter.status {
+            query.push(" AND status = ").push_bind(enum_to_str(&status)?);
+        }
+        if let Some(ref working_dir) = filter.working_dir {
+            query
+                .push(" AND working_dir = ")
+                .push_bind(working_dir.to_string_lossy().into_owned());
+        }
+        if let Some(ref run_id) = filter.run_id {
+            query.push(" AND run_id = ").push_bind(run_id.clone());
+        }
+        if let Some(ref tenant_id) = filter.tenant_id {
+            query.push(" AND tenant_id = ").push_bind(tenant_id.clone());
+        }
+        if let Some(created_after) = filter.created_after {
+            query.push(" AND created_at >= ").push_bind(created_after);
+        }
+        if let Some(created_before) = filter.created_before {
+            query.push(" AND created_at < ").push_bind(created_before);
+        }
+        query.push(" ORDER BY created_at DESC, rowid DESC");
+
+        // Text queries match against JSON metadata, so they are applied
+        // after loading and the limit with them.
+        if filter.query.is_none() {
+            if let Some(limit) = filter.limit {
+                query
+                    .push(" LIMIT ")
+                    .push_bind(i64::try_from(limit).unwrap_or(i64::MAX));
+            }
+        }
+
+        let rows = query
+            .build()
+            .fetch_all(&self.pool)
+            .await
+            .map_err(db_error)?;
+
+        let mut result = Vec::with_capacity(rows.len());
+        for row in &rows {
+            let session = session_from_row(row)?;
+            if filter.matches(&session) {
+                result.push(session);
+            }
+        }
+
+        if let Some(limit) = filter.limit {
+            result.truncate(limit);
+        }
+
+        Ok(result)
+    }
+
+    async fn append_output(&self, id: SessionId, data: &[u8]) -> Result<(), StorageError> {
+        let result = sqlx::query(
+            "INSERT INTO session_output (session_id, data)
+             SELECT id, ? FROM sessions WHERE id = ?",
+        )
+        .bind(data)
+        .bind(id.to_string())
+        .execute(&self.pool)
+        .await
+        .map_err(db_error)?;
+
+        if result.rows_affected() == 0 {
+            return Err(StorageError::NotFound(id));
+        }
+        if self.blobs.is_some() || self.compress_output {
+            self.flush_output(id).await?;
+        }
+        Ok(())
+    }
+
+    async fn get_output(&self, id: SessionId) -> Result<Vec<u8>, StorageError> {
+        let chunks: Vec<OutputChunk> = sqlx::query_as(
+            "SELECT data, blob_key, compression FROM session_output
+             WHERE session_id = ? ORDER BY id",
+        )
+        .bind(id.to_string())
+        .fetch_all(&self.pool)
+        .await
+        .map_err(db_error)?;
+
+        if chunks.is_empty() && !self.session_exists(id).await? {
+            return Err(StorageError::NotFound(id));
+        }
+        assemble_output(chunks, self.blobs.as_deref()).await
+    }
+
+    async fn get_output_range(
+        &self,
+        id: SessionId,
+        offset: u64,
+        len: usize,
+    ) -> Result<Vec<u8>, StorageError> {
+        // One statement, so a concurrent flush cannot shift the chunks.
+        let (start, end) = range_bounds(offset, len);
+        let spans: Vec<OutputSpan> = sqlx::query_as(
+            "WITH spans AS (
+                 SELECT id,
+                        SUM(COALESCE(size, LENGTH(data))) OVER (ORDER BY id) AS end_offset,
+                        COALESCE(size, LENGTH(data)) AS size
+                 FROM session_output WHERE session_id = ?
+             )
+             SELECT o.data, o.blob_key, o.compression, spans.end_offset - spans.size
+             FROM spans JOIN session_output o ON o.id = spans.id
+             WHERE spans.end_offset > ? AND spans.end_offset - spans.size < ?
+             ORDER BY o.id",
+        )
+        .bind(id.to_string())
+        .bind(start)
+        .bind(end)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(db_error)?;
+
+        if spans.is_empty() && !self.session_exists(id).await? {
+            return Err(StorageError::NotFound(id));
+        }
+        assemble_output_range(spans, self.blobs.as_deref(), offset, len).await
+    }
+
+    async fn output_len(&self, id: SessionId) -> Result<u64, StorageError> {
+        let len: i64 = sqlx::query_scalar(
+            "SELECT COALESCE(SUM(COALESCE(size, LENGTH(data))), 0)
+             FROM session_output WHERE session_id = ?",
+        )
+        .bind(id.to_string())
+        .fetch_one(&self.pool)
+        .await
+        .map_err(db_error)?;
+
+        if len == 0 && !self.session_exists(id).await? {
+            return Err(StorageError::NotFound(id));
+        }
+        Ok(u64::try_from(len).unwrap_or_default())
+    }
+}
+
+#[async_trait]
+impl EventStorage for SqliteStorage {
+    async fn append_event(&self, id: SessionId, msg: &LogMsg) -> Result<EventSeq, StorageError> {
+        let (timestamp, subsec_millis) = event_time();
+        let seq: i64 = sqlx::query_scalar(
+            "INSERT INTO session_events (session_id, seq, timestamp, subsec_millis, msg)
+             SELECT ?1, COALESCE(MAX(seq) + 1, 0), ?2, ?3, ?4
+             FROM session_events WHERE session_id = ?1
+             RETURNING seq",
+        )
+        .bind(id.to_string())
+        .bind(timestamp)
+        .bind(i64::from(subsec_millis))
+        .bind(serde_json::to_string(msg).map_err(json_error)?)
+        .fetch_one(&self.pool)
+        .await
+        .map_err(db_error)?;
+
+        Ok(EventSeq::try_from(seq).unwrap_or_default())
+    }
+
+    async fn get_events(
+        &self,
+        id: SessionId,
+        from: EventSeq,
+        limit: Option<usize>,
+    ) -> Result<Vec<StoredEvent>, StorageError> {
+        sqlx::query(
+            "SELECT * FROM session_events WHERE session_id = ? AND seq >= ?
+             ORDER BY seq LIMIT ?",
+        )
+        .bind(id.to_string())
+        .bind(i64::try_from(from).unwrap_or(i64::MAX))
+        // A negative limit means no limit in SQLite.
+        .bind(limit.map_or(-1, |limit| i64::try_from(limit).unwrap_or(i64::MAX)))
+        .fetch_all(&self.pool)
+        .await
+        .map_err(db_error)?
+        .iter()
+        .map(event_from_row)
+        .collect()
+    }
+
+    async fn event_count(&self, id: SessionId) -> Result<u64, StorageError> {
+        let count: i64 =
+            sqlx::query_scalar("SELECT COUNT(*) FROM session_events WHERE session_id = ?")
+                .bind(id.to_string())
+                .fetch_one(&self.pool)
+                .await
+                .map_err(db_error)?;
+
+        Ok(u64::try_from(count).unwrap_or_default())
+    }
+}
+
+#[async_trait]
+impl AuditStorage for SqliteStorage {
+    async fn record_tool_call(&self, record: ToolCallRecord) -> Result<(), StorageError> {
+        sqlx::query(
+            "INSERT INTO tool_calls (
+                session_id, tool_call_id, tool_name, input, outcome,
+                decided_by, reason, requested_at, decided_at, input_hash, latency_ms
+             ) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)",
+        )
+        .bind(record.session_id.to_string())
+        .bind(record.tool_call_id)
+        .bind(record.tool_name)
+        .bind(serde_json::to_string(&record.input).map_err(json_error)?)
+        .bind(enum_to_str(&record.outcome)?)
+        .bind(record.decided_by)
+        .bind(record.reason)
+        .bind(record.requested_at)
+        .bind(record.decided_at)
+        .bind(record.input_hash)
+        .bind(record.latency_ms.map(|ms| i64::try_from(ms).unwrap_or(i64::MAX)))
+        .execute(&self.pool)
+        .await
+        .map_err(db_error)?;
+
+        Ok(())
+    }
+
+    async fn get_tool_calls(&self, id: SessionId) -> Result<Vec<ToolCallRecord>, StorageError> {
+        sqlx::query("SELECT * FROM tool_calls WHERE session_id = ? ORDER BY id")
+            .bind(id.to_string())
+            .fetch_all(&self.pool)
+            .await
+            .map_err(db_error)?
+            .iter()
+            .map(tool_call_from_row)
+            .collect()
+    }
+
+    async fn query_tool_calls(
+        &self,
+        filter: ToolCallFilter,
+    ) -> Result<Vec<ToolCallRecord>, StorageError> {
+        let mut query = QueryBuilder::<Sqlite>::new("SELECT * FROM tool_calls WHERE 1 = 1");
+        if let Some(session_id) = filter.session_id {
+            query.push(" AND session_id = ").push_bind(session_id.to_string());
+        }
+        if let Some(tool_name) = filter.tool_name {
+            query.push(" AND tool_name = ").push_bind(tool_name);
+        }
+        if let Some(outcome) = filter.outcome {
+            query.push(" AND outcome = ").push_bind(enum_to_str(&outcome)?);
+        }
+        if let Some(decided_after) = filter.decided_after {
+            query.push(" AND decided_at >= ").push_bind(decided_after);
+        }
+        if let Some(decided_before) = filter.decided_before {
+            query.push(" AND decided_at < ").push_bind(decided_before);
+        }
+        query.push(" ORDER BY decided_at, id");
+        if let Some(limit) = filter.limit {
+            query
+                .push(" LIMIT ")
+                .push_bind(i64::try_from(limit).unwrap_or(i64::MAX));
+        }
+
+        query
+            .build()
+            .fetch_all(&self.pool)
+            .await
+            .map_err(db_error)?
+            .iter()
+            .map(tool_call_from_row)
+            .collect()
+    }
+
+    async fn record_hook_event(&self, record: HookEventRecord) -> Result<(), StorageError> {
+        sqlx::query(
+            "INSERT INTO hook_events (
+                session_id, event, tool_name, tool_call_id, input, output, timestamp
+             ) VALUES (?, ?, ?, ?, ?, ?, ?)",
+        )
+        .bind(record.session_id.to_string())
+        .bind(record.event)
+        .bind(record.tool_name)
+        .bind(record.tool_call_id)
+        .bind(serde_json::to_string(&record.input).map_err(json_error)?)
+        .bind(record.output.as_ref().map(serde_json::to_string).transpose().map_err(json_error)?)
+        .bind(record.timestamp)
+        .execute(&self.pool)
+        .await
+        .map_err(db_error)?;
+
+        Ok(())
     }
 
-    async fn list(&self, _filter: SessionFilter) -> Result<Vec<Session>, StorageError> {
-        Err(StorageError::Internal("Not implemented".to_string()))
+    async fn query_hook_events(
+        &self,
+        filter: HookEventFilter,
+    ) -> Result<Vec<HookEventRecord>, StorageError> {
+        let mut query = QueryBuilder::<Sqlite>::new("SELECT * FROM hook_events WHERE 1 = 1");
+        if let Some(session_id) = filter.session_id {
+            query.push(" AND session_id = ").push_bind(session_id.to_string());
+        }
+        if let Some(event) = filter.event {
+            query.push(" AND event = ").push_bind(event);
+        }
+        if let Some(tool_name) = filter.tool_name {
+            query.push(" AND tool_name = ").push_bind(tool_name);
+        }
+        if let Some(after) = filter.after {
+            query.push(" AND timestamp >= ").push_bind(after);
+        }
+        if let Some(before) = filter.before {
+            query.push(" AND timestamp < ").push_bind(before);
+        }
+        query.push(" ORDER BY timestamp, id");
+        if let Some(limit) = filter.limit {
+            query
+                .push(" LIMIT ")
+                .push_bind(i64::try_from(limit).unwrap_or(i64::MAX));
+        }
+
+        query
+            .build()
+            .fetch_all(&self.pool)
+            .await
+            .map_err(db_error)?
+            .iter()
+            .map(hook_event_from_row)
+            .collect()
     }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::{Path, PathBuf};
+
+    use remote_agents_core::traits::ApprovalOutcome;
+    use serde_json::json;
+
+    use super::*;
+    use crate::storage::FsBlobStore;
 
-    async fn append_output(&self, _id: SessionId, _data: &[u8]) -> Result<(), StorageError> {
-        Err(StorageError::Internal("Not implemented".to_string()))
+    /// A storage backed by a fresh database file, removed on drop.
+    struct TempDb {
+        storage: SqliteStorage,
+        path: PathBuf,
     }
 
-    async fn get_output(&self, _id: SessionId) -> Result<Vec<u8>, StorageError> {
-        Err(StorageError::Internal("Not implemented".to_string()))
+    impl TempDb {
+        async fn new() -> Self {
+            let path = std::env::temp_dir().join(format!("sqlite-storage-{}.db", Uuid::new_v4()));
+            let storage = SqliteStorage::new(&format!("sqlite://{}", path.display()))
+                .await
+                .unwrap();
+            Self { storage, path }
+        }
+    }
+
+    impl Drop for TempDb {
+        fn drop(&mut self) {
+            for suffix in ["", "-wal", "-shm"] {
+                let mut path = self.path.clone().into_os_string();
+                path.push(suffix);
+                let _ = std::fs::remove_file(path);
+            }
+        }
+    }
+
+    fn context(dir: &str) -> ExecutionContext {
+        ExecutionContext::new(Path::new(dir).to_path_buf())
+    }
+
+    #[tokio::test]
+    async fn test_session_round_trip() {
+        let db = TempDb::new().await;
+        let storage = &db.storage;
+
+        let mut ctx = context("/tmp/project");
+        ctx.set_metadata("ticket", json!("ABC-1"));
+        let id = storage.create(&ctx).await.unwrap();
+
+        let session = storage.get(id).await.unwrap().unwrap();
+        assert_eq!(session.status, SessionStatus::Pending);
+        assert_eq!(session.context.working_dir, ctx.working_dir);
+        assert_eq!(session.context.get_metadata("ticket"), Some(&json!("ABC-1")));
+
+        storage
+            .update_status(id, SessionStatus::Failed, None)
+            .await
+            .unwrap();
+        storage.set_prompt(id, "fix it".into()).await.unwrap();
+        storage.set_agent_session_id(id, "agent-1".into()).await.unwrap();
+        storage.set_exit_code(id, 2).await.unwrap();
+        storage.set_status_reason(id, "Turn limit of 3 exceeded".into()).await.unwrap();
+        storage.set_run_id(id, "nightly".into()).await.unwrap();
+
+        let session = storage.get(id).await.unwrap().unwrap();
+        assert_eq!(session.status, SessionStatus::Failed);
+        assert_eq!(session.prompt.as_deref(), Some("fix it"));
+        assert_eq!(session.agent_session_id.as_deref(), Some("agent-1"));
+        assert_eq!(session.exit_code, Some(2));
+        assert_eq!(session.status_reason.as_deref(), Some("Turn limit of 3 exceeded"));
+        assert_eq!(session.run_id.as_deref(), Some("nightly"));
+
+        assert!(storage.get(Uuid::new_v4()).await.unwrap().is_none());
+        assert!(matches!(
+            storage
+                .update_status(Uuid::new_v4(), SessionStatus::Running, None)
+                .await,
+            Err(StorageError::NotFound(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_versioned_updates() {
+        let db = TempDb::new().await;
+        let storage = &db.storage;
+        let id = storage.create(&context("/tmp/project")).await.unwrap();
+        assert_eq!(storage.get(id).await.unwrap().unwrap().version, 0);
+
+        storage
+            .update_status(id, SessionStatus::Running, Some(0))
+            .await
+            .unwrap();
+        // A replica still holding version 0 loses the race.
+        assert!(matches!(
+            storage
+                .update_status(id, SessionStatus::Cancelled, Some(0))
+                .await,
+            Err(StorageError::Conflict(_))
+        ));
+
+        let mut ctx = context("/tmp/other");
+        ctx.set_metadata("ticket", json!("ABC-2"));
+        storage.update_context(id, &ctx, Some(1)).await.unwrap();
+        assert!(matches!(
+            storage.update_context(id, &ctx, Some(1)).await,
+            Err(StorageError::Conflict(_))
+        ));
+        storage.set_exit_code(id, 0).await.unwrap();
+
+        let session = storage.get(id).await.unwrap().unwrap();
+        assert_eq!(session.version, 2);
+        assert_eq!(session.status, SessionStatus::Running);
+        assert_eq!(session.context.working_dir, ctx.working_dir);
+        assert_eq!(session.context.get_metadata("ticket"), Some(&json!("ABC-2")));
+        assert!(matches!(
+            storage
+                .update_context(Uuid::new_v4(), &ctx, Some(0))
+                .await,
+            Err(StorageError::NotFound(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_persists_across_reopen() {
+        let db = TempDb::new().await;
+        let id = db.storage.create(&context("/tmp/project")).await.unwrap();
+        db.storage.append_output(id, b"hello").await.unwrap();
+
+        let url = format!("sqlite://{}", db.path.display());
+        let reopened = SqliteStorage::new(&url).await.unwrap();
+        assert!(reopened.get(id).await.unwrap().is_some());
+        assert_eq!(reopened.get_output(id).await.unwrap(), b"hello");
+    }
+
+    #[tokio::test]
+    async fn test_list_filters() {
+        let db = TempDb::new().await;
+        let storage = &db.storage;
+
+        let a = storage.create(&context("/tmp/a")).await.unwrap();
+        let b = storage.create(&context("/tmp/b")).await.unwrap();
+        let c = storage.create(&context("/tmp/a")).await.unwrap();
+        storage
+            .update_status(b, SessionStatus::Running, None)
+            .await
+            .unwrap();
+        storage.set_prompt(c, "Refactor the parser".into()).await.unwrap();
+        storage.set_run_id(a, "run-1".into()).await.unwrap();
+        storage.set_tenant_id(b, "acme".into()).await.unwrap();
+
+        let ids = |sessions: Vec<Session>| sessions.into_iter().map(|s| s.id).collect::<Vec<_>>();
+
+        let all = storage.list(SessionFilter::default()).await.unwrap();
+        assert_eq!(ids(all), vec![c, b, a]);
+
+        let running = SessionFilter {
+            status: Some(SessionStatus::Running),
+            ..Default::default()
+        };
+        assert_eq!(ids(storage.list(running).await.unwrap()), vec![b]);
+
+        let in_a = SessionFilter {
+            working_dir: Some(PathBuf::from("/tmp/a")),
+            limit: Some(1),
+            ..Default::default()
+        };
+        assert_eq!(ids(storage.list(in_a).await.unwrap()), vec![c]);
+
+        let in_run = SessionFilter {
+            run_id: Some("run-1".into()),
+            ..Default::default()
+        };
+        assert_eq!(ids(storage.list(in_run).await.unwrap()), vec![a]);
+
+        let in_tenant = SessionFilter {
+            tenant_id: Some("acme".into()),
+            ..Default::default()
+        };
+        assert_eq!(ids(storage.list(in_tenant).await.unwrap()), vec![b]);
+
+        let query = SessionFilter {
+            query: Some("parser".into()),
+            ..Default::default()
+        };
+        assert_eq!(ids(storage.list(query).await.unwrap()), vec![c]);
+    }
+
+    #[tokio::test]
+    async fn test_soft_delete_and_restore() {
+        let db = TempDb::new().await;
+        let storage = &db.storage;
+        let kept = storage.create(&context("/tmp/project")).await.unwrap();
+        let deleted = storage.create(&context("/tmp/project")).await.unwrap();
+        storage.set_prompt(deleted, "deploy billing".into()).await.unwrap();
+
+        storage.soft_delete(deleted).await.unwrap();
+        let sessions = storage.list(SessionFilter::default()).await.unwrap();
+        assert_eq!(sessions.len(), 1);
+        assert_eq!(sessions[0].id, kept);
+        assert!(storage.search("billing", 10).await.unwrap().is_empty());
+        assert!(storage.get(deleted).await.unwrap().unwrap().deleted);
+
+        let filter = SessionFilter {
+            include_deleted: true,
+            ..Default::default()
+        };
+        assert_eq!(storage.list(filter).await.unwrap().len(), 2);
+
+        storage.restore(deleted).await.unwrap();
+        assert_eq!(
+            storage.list(SessionFilter::default()).await.unwrap().len(),
+            2
+        );
+        assert_eq!(storage.search("billing", 10).await.unwrap().len(), 1);
+        assert!(matches!(
+            storage.soft_delete(Uuid::new_v4()).await,
+            Err(StorageError::NotFound(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_children_and_chain() {
+        let db = TempDb::new().await;
+        let storage = &db.storage;
+
+        let root = storage.create(&context("/tmp")).await.unwrap();
+        let child = storage.create(&context("/tmp")).await.unwrap();
+        let grandchild = storage.create(&context("/tmp")).await.unwrap();
+        storage.set_parent_session_id(child, root).await.unwrap();
+        storage.set_parent_session_id(grandchild, child).await.unwrap();
+
+        let children = storage.get_children(root).await.unwrap();
+        assert_eq!(children.len(), 1);
+        assert_eq!(children[0].id, child);
+
+        let chain = storage.get_chain(grandchild).await.unwrap();
+        let chain: Vec<_> = chain.into_iter().map(|s| s.id).collect();
+        assert_eq!(chain, vec![root, child, grandchild]);
+    }
+
+    #[tokio::test]
+    async fn test_output_chunks() {
+        let db = TempDb::new().await;
+        let storage = &db.storage;
+
+        let id = storage.create(&context("/tmp")).await.unwrap();
+        assert!(storage.get_output(id).await.unwrap().is_empty());
+
+        storage.append_output(id, b"hello ").await.unwrap();
+        storage.append_output(id, b"world").await.unwrap();
+        assert_eq!(storage.get_output(id).await.unwrap(), b"hello world");
+
+        let missing = Uuid::new_v4();
+        assert!(matches!(
+            storage.append_output(missing, b"x").await,
+            Err(StorageError::NotFound(_))
+        ));
+        assert!(matches!(
+            storage.get_output(missing).await,
+            Err(StorageError::NotFound(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_output_moves_to_blob_store() {
+        let db = TempDb::new().await;
+        let root = db.path.with_extension("blobs");
+        let storage = SqliteStorage::from_pool(db.storage.pool.clone())
+            .await
+            .unwrap()
+            .with_blob_store(Arc::new(FsBlobStore::new(&root)))
+            .with_output_flush_size(8);
+
+        let id = storage.create(&context("/tmp")).await.unwrap();
+        for chunk in [&b"hello "[..], b"world", b"!"] {
+            storage.append_output(id, chunk).await.unwrap();
+        }
+        assert_eq!(storage.get_output(id).await.unwrap(), b"hello world!");
+
+        let blob_keys: Vec<Option<String>> =
+            sqlx::query_scalar("SELECT blob_key FROM session_output ORDER BY id")
+                .fetch_all(&storage.pool)
+                .await
+                .unwrap();
+        assert_eq!(blob_keys.len(), 2);
+        assert!(blob_keys[0].is_some());
+        assert!(blob_keys[1].is_none());
+
+        // Without the blob store the output can't be assembled.
+        assert!(db.storage.get_output(id).await.is_err());
+
+        std::fs::remove_dir_all(root).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_output_compression() {
+        let db = TempDb::new().await;
+        let storage = SqliteStorage::from_pool(db.storage.pool.clone())
+            .await
+            .unwrap()
+            .with_output_compression(true)
+            .with_output_flush_size(64);
+
+        let id = storage.create(&context("/tmp")).await.unwrap();
+        let line = "the same transcript line, over and over\n";
+        for _ in 0..10 {
+            storage.append_output(id, line.as_bytes()).await.unwrap();
+        }
+        storage.append_output(id, b"tail").await.unwrap();
+
+        let expected = format!("{}tail", line.repeat(10));
+        assert_eq!(storage.get_output(id).await.unwrap(), expected.as_bytes());
+
+        let stored: i64 = sqlx::query_scalar("SELECT SUM(LENGTH(data)) FROM session_output")
+            .fetch_one(&storage.pool)
+            .await
+            .unwrap();
+        assert!(usize::try_from(stored).unwrap() < expected.len());
+
+        // Turning compression off keeps compressed output readable.
+        assert_eq!(db.storage.get_output(id).await.unwrap(), expected.as_bytes());
+
+        // Ranges span compressed chunks and the uncompressed tail.
+        let len = u64::try_from(expected.len()).unwrap();
+        assert_eq!(storage.output_len(id).await.unwrap(), len);
+        for (offset, size) in [(0, 5), (35, 20), (390, 100), (len, 10)] {
+            let start = usize::try_from(offset).unwrap();
+            let end = (start + size).min(expected.len());
+            assert_eq!(
+                storage.get_output_range(id, offset, size).await.unwrap(),
+                &expected.as_bytes()[start..end]
+            );
+        }
+        assert!(matches!(
+            storage.output_len(Uuid::new_v4()).await,
+            Err(StorageError::NotFound(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_events() {
+        let db = TempDb::new().await;
+        let storage = &db.storage;
+        let id = storage.create(&context("/tmp")).await.unwrap();
+
+        for i in 0..3 {
+            let seq = storage
+                .append_event(id, &LogMsg::Stdout(format!("line {i}")))
+                .await
+                .unwrap();
+            assert_eq!(seq, i);
+        }
+        assert_eq!(storage.event_count(id).await.unwrap(), 3);
+        assert_eq!(storage.event_count(Uuid::new_v4()).await.unwrap(), 0);
+
+        let events = storage.get_events(id, 1, Some(1)).await.unwrap();
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].seq, 1);
+        assert!(matches!(&events[0].msg, LogMsg::Stdout(line) if line == "line 1"));
+
+        let events = storage.get_events(id, 0, None).await.unwrap();
+        assert_eq!(events.len(), 3);
+        let times: Vec<_> = events
+            .iter()
+            .map(|event| (event.timestamp, event.subsec_millis))
+            .collect();
+        assert!(times.is_sorted());
+        assert!(times.iter().all(|(_, millis)| *millis < 1000));
+    }
+
+    #[tokio::test]
+    async fn test_search() {
+        let db = TempDb::new().await;
+        let storage = &db.storage;
+
+        let mut ctx = context("/tmp/a");
+        ctx.set_metadata("ticket", json!("PAY-7"));
+        let billing = storage.create(&ctx).await.unwrap();
+        storage
+            .set_prompt(billing, "Fix the flaky checkout test".into())
+            .await
+            .unwrap();
+        storage
+            .append_output(billing, b"Editing services/billing-service/src/main.rs")
+            .await
+            .unwrap();
+
+        let other = storage.create(&context("/tmp/b")).await.unwrap();
+        storage.set_prompt(other, "Update the docs".into()).await.unwrap();
+
+        let hits = storage.search("billing-service", 10).await.unwrap();
+        assert_eq!(hits.len(), 1);
+        assert_eq!(hits[0].session.id, billing);
+        assert!(hits[0].snippet.contains("[billing-service]"));
+
+        let hits = storage.search("pay-7", 10).await.unwrap();
+        assert_eq!(hits.len(), 1);
+        assert_eq!(hits[0].session.id, billing);
+
+        let mut ctx = context("/tmp/b");
+        ctx.set_metadata("ticket", json!("DOC-3"));
+        storage.update_context(other, &ctx, None).await.unwrap();
+        let hits = storage.search("doc-3", 10).await.unwrap();
+        assert_eq!(hits.len(), 1);
+        assert_eq!(hits[0].session.id, other);
+
+        storage.set_prompt(other, "Fix the checkout docs".into()).await.unwrap();
+        assert_eq!(storage.search("checkout", 10).await.unwrap().len(), 2);
+        assert_eq!(storage.search("checkout", 1).await.unwrap().len(), 1);
+        assert!(storage.search("\"unbalanced", 10).await.unwrap().is_empty());
+        assert!(storage.search("  ", 10).await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_tool_calls() {
+        let db = TempDb::new().await;
+        let storage = &db.storage;
+        let id = Uuid::new_v4();
+
+        let decisions = [("1", ApprovalOutcome::Approved), ("2", ApprovalOutcome::Denied)];
+        for (tool_call_id, outcome) in decisions {
+            storage
+                .record_tool_call(ToolCallRecord {
+                    session_id: id,
+                    tool_call_id: tool_call_id.into(),
+                    tool_name: "Bash".into(),
+                    input: json!({ "command": "ls" }),
+                    outcome,
+                    decided_by: Some("alice".into()),
+                    reason: None,
+                    requested_at: 10,
+                    decided_at: 11,
+                    input_hash: None,
+                    latency_ms: None,
+                })
+                .await
+                .unwrap();
+        }
+
+        let calls = storage.get_tool_calls(id).await.unwrap();
+        assert_eq!(calls.len(), 2);
+        assert_eq!(calls[0].tool_call_id, "1");
+        assert_eq!(calls[1].outcome, ApprovalOutcome::Denied);
+        assert_eq!(calls[0].input, json!({ "command": "ls" }));
+        assert_eq!(calls[0].decided_by.as_deref(), Some("alice"));
+    }
+
+    #[tokio::test]
+    async fn test_audit_queries() {
+        let db = TempDb::new().await;
+        let storage = &db.storage;
+        let (first, second) = (Uuid::new_v4(), Uuid::new_v4());
+
+        let calls = [
+            (first, "Bash", ApprovalOutcome::Approved, 30),
+            (first, "Write", ApprovalOutcome::Denied, 10),
+            (second, "Bash", ApprovalOutcome::Denied, 20),
+        ];
+        for (session_id, tool_name, outcome, decided_at) in calls {
+            storage
+                .record_tool_call(ToolCallRecord {
+                    session_id,
+                    tool_call_id: format!("{tool_name}-{decided_at}"),
+                    tool_name: tool_name.into(),
+                    input: json!({}),
+                    outcome,
+                    decided_by: None,
+                    reason: None,
+                    requested_at: decided_at,
+                    decided_at,
+                    input_hash: None,
+                    latency_ms: None,
+                })
+                .await
+                .unwrap();
+        }
+
+        let all = storage
+            .query_tool_calls(ToolCallFilter::default())
+            .await
+            .unwrap();
+        let order: Vec<_> = all.iter().map(|call| call.decided_at).collect();
+        assert_eq!(order, [10, 20, 30]);
+
+        let filter = ToolCallFilter {
+            tool_name: Some("Bash".into()),
+            outcome: Some(ApprovalOutcome::Denied),
+            ..Default::default()
+        };
+        let denied = storage.query_tool_calls(filter).await.unwrap();
+        assert_eq!(denied.len(), 1);
+        assert_eq!(denied[0].session_id, second);
+
+        let filter = ToolCallFilter {
+            decided_after: Some(15),
+            decided_before: Some(30),
+            ..Default::default()
+        };
+        assert_eq!(storage.query_tool_calls(filter).await.unwrap().len(), 1);
+
+        for (event, timestamp) in [("PreToolUse", 5), ("PostToolUse", 6)] {
+            storage
+                .record_hook_event(HookEventRecord {
+                    session_id: first,
+                    event: event.into(),
+                    tool_name: Some("Bash".into()),
+                    tool_call_id: Some("1".into()),
+                    input: json!({ "command": "ls" }),
+                    output: (event == "PostToolUse").then(|| json!({ "ok": true })),
+                    timestamp,
+                })
+                .await
+                .unwrap();
+        }
+        let filter = HookEventFilter {
+            session_id: Some(first),
+            event: Some("PostToolUse".into()),
+            ..Default::default()
+        };
+        let events = storage.query_hook_events(filter).await.unwrap();
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].output, Some(json!({ "ok": true })));
+        let filter = HookEventFilter {
+            limit: Some(1),
+            ..Default::default()
+        };
+        let events = storage.query_hook_events(filter).await.unwrap();
+        assert_eq!(events[0].event, "PreToolUse");
     }
 }
@@ -0,0 +1,178 @@
+//! Usage reports aggregated across sessions.
+
+use std::{collections::BTreeMap, path::PathBuf};
+
+use remote_agents_core::traits::{Session, TenantId};
+use serde::Serialize;
+
+use crate::limits::UsageTotals;
+use crate::manager::USAGE_METADATA_KEY;
+
+/// Seconds in a day, for grouping by UTC day.
+const DAY_SECS: i64 = 24 * 60 * 60;
+
+/// A session field usage can be grouped by.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UsageGrouping {
+    /// The UTC day the session was created.
+    Day,
+    /// The session's working directory.
+    WorkingDir,
+    /// The tenant owning the session.
+    Tenant,
+}
+
+/// The group a row of a usage report covers. Fields not grouped by are
+/// `None`.
+#[derive(Debug, Clone, Default, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+pub struct UsageKey {
+    /// Start of the UTC day (Unix epoch seconds).
+    pub day: Option<i64>,
+    /// Working directory of the sessions.
+    pub working_dir: Option<PathBuf>,
+    /// Tenant owning the sessions; `None` also for sessions without one.
+    pub tenant_id: Option<TenantId>,
+}
+
+/// Usage summed over a group of sessions.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct UsageAggregate {
+    /// The group summed.
+    pub key: UsageKey,
+    /// Sessions in the group that reported usage.
+    pub sessions: usize,
+    /// Usage summed over the group's sessions.
+    pub totals: UsageTotals,
+}
+
+/// Usage of a set of sessions, one row per group, ordered by key.
+///
+/// Built from the `UsageTotals` each session stores under
+/// `USAGE_METADATA_KEY` as it runs, so the report always agrees with
+/// storage and survives restarts; sessions without usage (not reported yet,
+/// or whose executor does not report it) are left out.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct UsageReport {
+    /// One row per group, ordered by key.
+    pub rows: Vec<UsageAggregate>,
+}
+
+impl UsageReport {
+    /// Sum the usage of `sessions`, grouped by each of `group_by`.
+    ///
+    /// With no groupings, the report has a single row for all sessions.
+    #[must_use]
+    pub fn from_sessions(sessions: &[Session], group_by: &[UsageGrouping]) -> Self {
+        let mut groups: BTreeMap<UsageKey, (usize, UsageTotals)> = BTreeMap::new();
+        for session in sessions {
+            let Some(totals) = session_usage(session) else {
+                continue;
+            };
+            let (count, sum) = groups.entry(usage_key(session, group_by)).or_default();
+            *count += 1;
+            sum.merge(&totals);
+        }
+        Self {
+            rows: groups
+                .into_iter()
+                .map(|(key, (sessions, totals))| UsageAggregate {
+                    key,
+                    sessions,
+                    totals,
+                })
+                .collect(),
+        }
+    }
+
+    /// Usage summed over every row.
+    #[must_use]
+    pub fn total(&self) -> UsageTotals {
+        let mut total = UsageTotals::default();
+        for row in &self.rows {
+            total.merge(&row.totals);
+        }
+        total
+    }
+}
+
+/// The usage a session stored, if any.
+pub(crate) fn session_usage(session: &Session) -> Option<UsageTotals> {
+    let usage = session.context.get_metadata(USAGE_METADATA_KEY)?;
+    serde_json::from_value(usage.clone()).ok()
+}
+
+fn usage_key(session: &Session, group_by: &[UsageGrouping]) -> UsageKey {
+    let mut key = UsageKey::default();
+    for grouping in group_by {
+        match grouping {
+            UsageGrouping::Day => {
+                key.day = Some(session.created_at.div_euclid(DAY_SECS) * DAY_SECS);
+            }
+            UsageGrouping::WorkingDir => {
+                key.working_dir = Some(session.context.working_dir.clone());
+            }
+            UsageGrouping::Tenant => key.tenant_id.clone_from(&session.tenant_id),
+        }
+    }
+    key
+}
+
+#[cfg(test)]
+mod tests {
+    use remote_agents_core::{ExecutionContext, traits::SessionStatus};
+    use uuid::Uuid;
+
+    use super::*;
+
+    fn session(dir: &str, created_at: i64, cost_usd: Option<f64>) -> Session {
+        let mut context = ExecutionContext::new(PathBuf::from(dir));
+        if let Some(cost_usd) = cost_usd {
+            let totals = UsageTotals {
+                turns: 1,
+                cost_usd,
+                ..UsageTotals::default()
+            };
+            context.set_metadata(USAGE_METADATA_KEY, serde_json::to_value(totals).unwrap());
+        }
+        Session {
+            id: Uuid::new_v4(),
+            context,
+            prompt: None,
+            status: SessionStatus::Completed,
+            agent_session_id: None,
+            parent_session_id: None,
+            run_id: None,
+            tenant_id: None,
+            exit_code: None,
+            status_reason: None,
+            deleted: false,
+            created_at,
+            updated_at: created_at,
+            version: 0,
+        }
+    }
+
+    #[test]
+    fn test_group_by_day_and_working_dir() {
+        let sessions = [
+            session("/a", DAY_SECS + 10, Some(0.5)),
+            session("/a", DAY_SECS + 20, Some(0.25)),
+            session("/b", DAY_SECS + 30, Some(1.0)),
+            session("/a", 2 * DAY_SECS, Some(2.0)),
+            session("/a", 2 * DAY_SECS, None),
+        ];
+        let report =
+            UsageReport::from_sessions(&sessions, &[UsageGrouping::Day, UsageGrouping::WorkingDir]);
+        assert_eq!(report.rows.len(), 3);
+        assert_eq!(report.rows[0].key.day, Some(DAY_SECS));
+        assert_eq!(report.rows[0].key.working_dir, Some(PathBuf::from("/a")));
+        assert_eq!(report.rows[0].sessions, 2);
+        assert!((report.rows[0].totals.cost_usd - 0.75).abs() < f64::EPSILON);
+        assert_eq!(report.rows[0].totals.turns, 2);
+        assert_eq!(report.total().turns, 4);
+
+        let report = UsageReport::from_sessions(&sessions, &[]);
+        assert_eq!(report.rows.len(), 1);
+        assert_eq!(report.rows[0].sessions, 4);
+    }
+}
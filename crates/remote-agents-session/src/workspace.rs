@@ -0,0 +1,278 @@
+//! Isolated per-session workspaces (git worktrees or copies).
+
+use std::path::{Path, PathBuf};
+
+use remote_agents_core::ExecutionContext;
+use serde_json::Value;
+use thiserror::Error;
+use uuid::Uuid;
+
+/// Metadata key holding the directory a workspace was created from.
+pub const WORKSPACE_SOURCE_METADATA_KEY: &str = "workspace_source";
+
+/// Metadata key holding the git branch a worktree was created on.
+pub const WORKSPACE_BRANCH_METADATA_KEY: &str = "workspace_branch";
+
+/// Workspace provisioning error.
+#[derive(Debug, Error)]
+pub enum WorkspaceError {
+    #[error("Git command failed: {0}")]
+    Git(String),
+    #[error("I/O error: {0}")]
+    Io(#[from] std::io::Error),
+}
+
+/// How a session's workspace is created from the source directory.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum WorkspaceStrategy {
+    /// A git worktree on a dedicated branch.
+    #[default]
+    Worktree,
+    /// A plain recursive copy.
+    Copy,
+}
+
+/// A provisioned workspace, as recorded in a session's context.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Workspace {
+    /// Directory the session runs in.
+    pub path: PathBuf,
+    /// Directory the workspace was created from.
+    pub source: PathBuf,
+    /// Branch of the worktree, if it is one.
+    pub branch: Option<String>,
+}
+
+impl Workspace {
+    /// Read the workspace a context was provisioned with, if any.
+    #[must_use]
+    pub fn from_context(ctx: &ExecutionContext) -> Option<Self> {
+        let source = ctx.get_metadata(WORKSPACE_SOURCE_METADATA_KEY)?.as_str()?;
+        let branch = ctx
+            .get_metadata(WORKSPACE_BRANCH_METADATA_KEY)
+            .and_then(Value::as_str);
+        Some(Self {
+            path: ctx.working_dir.clone(),
+            source: PathBuf::from(source),
+            branch: branch.map(str::to_string),
+        })
+    }
+
+    /// Point a context at this workspace.
+    fn apply(&self, ctx: &mut ExecutionContext) {
+        ctx.working_dir.clone_from(&self.path);
+        ctx.set_metadata(
+            WORKSPACE_SOURCE_METADATA_KEY,
+            Value::String(self.source.to_string_lossy().into_owned()),
+        );
+        if let Some(branch) = &self.branch {
+            ctx.set_metadata(WORKSPACE_BRANCH_METADATA_KEY, Value::String(branch.clone()));
+        }
+    }
+}
+
+/// Creates an isolated workspace for each session so concurrent agents
+/// don't share a checkout.
+#[derive(Debug, Clone)]
+pub struct WorkspaceProvisioner {
+    root: PathBuf,
+    strategy: WorkspaceStrategy,
+    branch_prefix: String,
+    keep: bool,
+}
+
+impl WorkspaceProvisioner {
+    /// Create a provisioner that places git worktrees under `root`.
+    #[must_use]
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        Self {
+            root: root.into(),
+            strategy: WorkspaceStrategy::Worktree,
+            branch_prefix: "agent/".to_string(),
+            keep: false,
+        }
+    }
+
+    /// Set how workspaces are created.
+    #[must_use]
+    pub const fn with_strategy(mut self, strategy: WorkspaceStrategy) -> Self {
+        self.strategy = strategy;
+        self
+    }
+
+    /// Set the prefix for worktree branch names (default `agent/`).
+    #[must_use]
+    pub fn with_branch_prefix(mut self, prefix: impl Into<String>) -> Self {
+        self.branch_prefix = prefix.into();
+        self
+    }
+
+    /// Keep workspaces after their session finishes instead of removing them.
+    #[must_use]
+    pub const fn keep_workspaces(mut self, keep: bool) -> Self {
+        self.keep = keep;
+        self
+    }
+
+    /// Create a fresh workspace from the context's working directory.
+    ///
+    /// If the context already points at a workspace, the new one is created
+    /// from that workspace's source. Returns the context pointed at it.
+    ///
+    /// # Errors
+    /// Returns error if creating the worktree or copy fails.
+    pub async fn provision(
+        &self,
+        ctx: &ExecutionContext,
+    ) -> Result<ExecutionContext, WorkspaceError> {
+        let source =
+            Workspace::from_context(ctx).map_or_else(|| ctx.working_dir.clone(), |w| w.source);
+        let name = Uuid::new_v4().to_string();
+        let workspace = Workspace {
+            path: self.root.join(&name),
+            source,
+            branch: (self.strategy == WorkspaceStrategy::Worktree)
+                .then(|| format!("{}{name}", self.branch_prefix)),
+        };
+
+        tokio::fs::create_dir_all(&self.root).await?;
+        match &workspace.branch {
+            Some(branch) => {
+                git(
+                    &workspace.source,
+                    &["worktree", "add", "-b", branch, &path_arg(&workspace.path), "HEAD"],
+                )
+                .await?;
+            }
+            None => copy_dir(&workspace.source, &workspace.path).await?,
+        }
+
+        let mut ctx = ctx.clone();
+        workspace.apply(&mut ctx);
+        Ok(ctx)
+    }
+
+    /// Make sure a previously provisioned workspace exists.
+    ///
+    /// Worktrees that were cleaned up are re-created on their branch, so a
+    /// follow-up sees the earlier session's commits. Removed copies are
+    /// copied afresh from the source.
+    ///
+    /// # Errors
+    /// Returns error if re-creating the workspace fails.
+    pub async fn reopen(&self, workspace: &Workspace) -> Result<(), WorkspaceError> {
+        if tokio::fs::try_exists(&workspace.path).await? {
+            return Ok(());
+        }
+        match &workspace.branch {
+            Some(branch) => {
+                git(
+                    &workspace.source,
+                    &["worktree", "add", &path_arg(&workspace.path), branch],
+                )
+                .await
+            }
+            None => Ok(copy_dir(&workspace.source, &workspace.path).await?),
+        }
+    }
+
+    /// Remove a workspace once its session has finished, unless kept.
+    ///
+    /// Worktree branches are left in place so the agent's commits survive.
+    ///
+    /// # Errors
+    /// Returns error if removing the worktree or copy fails.
+    pub async fn cleanup(&self, workspace: &Workspace) -> Result<(), WorkspaceError> {
+        if self.keep || !tokio::fs::try_exists(&workspace.path).await? {
+            return Ok(());
+        }
+        if workspace.branch.is_some() {
+            git(
+                &workspace.source,
+                &["worktree", "remove", "--force", &path_arg(&workspace.path)],
+            )
+            .await
+        } else {
+            Ok(tokio::fs::remove_dir_all(&workspace.path).await?)
+        }
+    }
+}
+
+fn path_arg(path: &Path) -> String {
+    path.to_string_lossy().into_owned()
+}
+
+/// Run a git command in `dir`, failing with its stderr.
+async fn git(dir: &Path, args: &[&str]) -> Result<(), WorkspaceError> {
+    let output = tokio::process::Command::new("git")
+        .arg("-C")
+        .arg(dir)
+        .args(args)
+        .output()
+        .await?;
+    if output.status.success() {
+        Ok(())
+    } else {
+        Err(WorkspaceError::Git(
+            String::from_utf8_lossy(&output.stderr).trim().to_string(),
+        ))
+    }
+}
+
+/// Recursively copy a directory.
+async fn copy_dir(src: &Path, dst: &Path) -> std::io::Result<()> {
+    let (src, dst) = (src.to_path_buf(), dst.to_path_buf());
+    tokio::task::spawn_blocking(move || copy_dir_blocking(&src, &dst))
+        .await
+        .map_err(std::io::Error::other)?
+}
+
+fn copy_dir_blocking(src: &Path, dst: &Path) -> std::io::Result<()> {
+    std::fs::create_dir_all(dst)?;
+    for entry in std::fs::read_dir(src)? {
+        let entry = entry?;
+        let file_type = entry.file_type()?;
+        let target = dst.join(entry.file_name());
+        if file_type.is_dir() {
+            copy_dir_blocking(&entry.path(), &target)?;
+        } else if file_type.is_symlink() {
+            #[cfg(unix)]
+            std::os::unix::fs::symlink(std::fs::read_link(entry.path())?, &target)?;
+            #[cfg(not(unix))]
+            std::fs::copy(entry.path(), &target).map(|_| ())?;
+        } else {
+            std::fs::copy(entry.path(), &target)?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_copy_provision_and_cleanup() {
+        let base = std::env::temp_dir().join(format!("workspace-test-{}", Uuid::new_v4()));
+        let source = base.join("repo");
+        std::fs::create_dir_all(source.join("src")).unwrap();
+        std::fs::write(source.join("src/main.rs"), "fn main() {}").unwrap();
+
+        let provisioner = WorkspaceProvisioner::new(base.join("workspaces"))
+            .with_strategy(WorkspaceStrategy::Copy);
+        let ctx = provisioner
+            .provision(&ExecutionContext::new(source.clone()))
+            .await
+            .unwrap();
+
+        let workspace = Workspace::from_context(&ctx).unwrap();
+        assert_eq!(workspace.source, source);
+        assert_eq!(workspace.branch, None);
+        assert!(ctx.working_dir.join("src/main.rs").is_file());
+
+        provisioner.cleanup(&workspace).await.unwrap();
+        assert!(!ctx.working_dir.exists());
+
+        std::fs::remove_dir_all(base).unwrap();
+    }
+}
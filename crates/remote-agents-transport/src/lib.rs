@@ -4,7 +4,11 @@
 //! - Wire protocol (JSON + base64)
 //! - WebSocket transport (feature: websocket)
 //! - TUI transport bridge (feature: tui)
+//! - Webhook approval handler (feature: webhook)
+//! - Slack approval handler (feature: slack)
+//! - Playback of recorded sessions
 
+pub mod playback;
 pub mod protocol;
 
 #[cfg(feature = "websocket")]
@@ -13,4 +17,13 @@ pub mod websocket;
 #[cfg(feature = "tui")]
 pub mod tui;
 
-pub use protocol::{ClientMessage, ServerMessage};
+#[cfg(any(feature = "webhook", feature = "slack"))]
+mod signing;
+
+#[cfg(feature = "slack")]
+pub mod slack;
+
+#[cfg(feature = "webhook")]
+pub mod webhook;
+
+pub use protocol::{ApprovalDecision, ClientMessage, ServerMessage};
@@ -0,0 +1,285 @@
+//! Playback of recorded terminal sessions.
+
+use std::{path::Path, time::Duration};
+
+use remote_agents_core::{
+    EventStorage, LogMsg,
+    traits::{SessionId, StorageError, StoredEvent},
+};
+use serde_json::Value;
+use thiserror::Error;
+use tokio::sync::mpsc;
+
+use crate::protocol::ServerMessage;
+
+/// Playback error.
+#[derive(Debug, Error)]
+pub enum PlaybackError {
+    /// Reading a cast file failed.
+    #[error("I/O error: {0}")]
+    Io(#[from] std::io::Error),
+    /// A cast file is not valid asciinema v2.
+    #[error("Invalid cast file: {0}")]
+    InvalidCast(String),
+    /// Reading a session's stored events failed.
+    #[error("Storage error: {0}")]
+    Storage(#[from] StorageError),
+}
+
+/// A chunk of recorded output.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RecordingFrame {
+    /// Time from the start of the recording.
+    pub offset: Duration,
+    /// Output, as written to the terminal.
+    pub data: Vec<u8>,
+}
+
+/// A recorded session's output, for reviewing what happened in it.
+///
+/// Read from an asciinema v2 cast, e.g. one written by
+/// `PtyService::with_recording`, or from a session's stored events, then
+/// `play` it back at real-time or accelerated speed.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Recording {
+    frames: Vec<RecordingFrame>,
+}
+
+impl Recording {
+    /// Parse the contents of an asciinema v2 cast file. Events other than
+    /// output, e.g. resizes, are skipped.
+    ///
+    /// # Errors
+    /// Returns error if the header or an event is malformed.
+    pub fn from_cast(cast: &str) -> Result<Self, PlaybackError> {
+        let mut lines = cast
+            .lines()
+            .enumerate()
+            .filter(|(_, line)| !line.trim().is_empty());
+        let (_, header) = lines
+            .next()
+            .ok_or_else(|| PlaybackError::InvalidCast("missing header".to_string()))?;
+        let header: Value = serde_json::from_str(header)
+            .map_err(|e| PlaybackError::InvalidCast(format!("header: {e}")))?;
+        if header.get("version").and_then(Value::as_u64) != Some(2) {
+            return Err(PlaybackError::InvalidCast(
+                "only version 2 is supported".to_string(),
+            ));
+        }
+
+        let mut frames = Vec::new();
+        for (index, line) in lines {
+            let invalid = || PlaybackError::InvalidCast(format!("line {}", index + 1));
+            let event: (f64, String, String) = serde_json::from_str(line).map_err(|_| invalid())?;
+            let (time, code, data) = event;
+            if code != "o" {
+                continue;
+            }
+            frames.push(RecordingFrame {
+                offset: Duration::try_from_secs_f64(time).map_err(|_| invalid())?,
+                data: data.into_bytes(),
+            });
+        }
+        Ok(Self { frames })
+    }
+
+    /// Read the asciinema v2 cast file at `path`.
+    ///
+    /// # Errors
+    /// Returns error if the file cannot be read or is malformed.
+    pub async fn read_cast(path: impl AsRef<Path>) -> Result<Self, PlaybackError> {
+        Self::from_cast(&tokio::fs::read_to_string(path).await?)
+    }
+
+    /// The stdout and stderr of session `id`'s stored events, timed to the
+    /// millisecond, or to the second for events stored without
+    /// milliseconds.
+    ///
+    /// # Errors
+    /// Returns error if the events cannot be read.
+    pub async fn from_events(
+        storage: &dyn EventStorage,
+        id: SessionId,
+    ) -> Result<Self, PlaybackError> {
+        let events = storage.get_events(id, 0, None).await?;
+        let millis = |event: &StoredEvent| {
+            event
+                .timestamp
+                .saturating_mul(1000)
+                .saturating_add(i64::from(event.subsec_millis))
+        };
+        let start = events.first().map_or(0, millis);
+        let frames = events
+            .into_iter()
+            .filter_map(|event| {
+                let offset = u64::try_from(millis(&event).saturating_sub(start));
+                let (LogMsg::Stdout(text) | LogMsg::Stderr(text)) = event.msg else {
+                    return None;
+                };
+                Some(RecordingFrame {
+                    offset: Duration::from_millis(offset.unwrap_or_default()),
+                    data: text.into_bytes(),
+                })
+            })
+            .collect();
+        Ok(Self { frames })
+    }
+
+    /// Shorten pauses between frames to at most `max`, e.g. to skip time
+    /// spent waiting for approvals.
+    #[must_use]
+    pub fn with_idle_limit(mut self, max: Duration) -> Self {
+        let mut previous = Duration::ZERO;
+        let mut skipped = Duration::ZERO;
+        for frame in &mut self.frames {
+            let pause = frame.offset.saturating_sub(previous);
+            previous = frame.offset;
+            skipped += pause.saturating_sub(max);
+            frame.offset = frame.offset.saturating_sub(skipped);
+        }
+        self
+    }
+
+    /// The recorded output.
+    #[must_use]
+    pub fn frames(&self) -> &[RecordingFrame] {
+        &self.frames
+    }
+
+    /// Time from the start to the last frame.
+    #[must_use]
+    pub fn duration(&self) -> Duration {
+        self.frames
+            .last()
+            .map_or(Duration::ZERO, |frame| frame.offset)
+    }
+
+    /// Play back on a channel like `PtyService`'s output receiver, `speed`
+    /// times as fast as recorded, e.g. 2.0 for double speed. A speed that
+    /// is not positive plays everything at once.
+    ///
+    /// Stops when the receiver is dropped.
+    #[must_use]
+    pub fn play(self, speed: f64) -> mpsc::UnboundedReceiver<Vec<u8>> {
+        self.play_with(speed, |data| data)
+    }
+
+    /// Play back as `ServerMessage::Output` frames, e.g. to send to a web
+    /// client, like `play`.
+    #[must_use]
+    pub fn play_messages(self, speed: f64) -> mpsc::UnboundedReceiver<ServerMessage> {
+        self.play_with(speed, |data| ServerMessage::output(&data))
+    }
+
+    fn play_with<T: Send + 'static>(
+        self,
+        speed: f64,
+        map: fn(Vec<u8>) -> T,
+    ) -> mpsc::UnboundedReceiver<T> {
+        let (tx, rx) = mpsc::unbounded_channel();
+        tokio::spawn(async move {
+            let started = tokio::time::Instant::now();
+            for frame in self.frames {
+                let at = Duration::try_from_secs_f64(frame.offset.as_secs_f64() / speed)
+                    .unwrap_or_default();
+                tokio::time::sleep(at.saturating_sub(started.elapsed())).await;
+                if tx.send(map(frame.data)).is_err() {
+                    return;
+                }
+            }
+        });
+        rx
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Instant;
+
+    use super::*;
+
+    const CAST: &str = r#"{"version": 2, "width": 80, "height": 24}
+[0.5, "o", "$ ls\r\n"]
+[1.0, "r", "100x30"]
+[1.5, "o", "Cargo.toml\r\n"]
+[61.5, "o", "$ "]
+"#;
+
+    #[tokio::test]
+    async fn test_cast_played_back_in_order() {
+        let recording = Recording::from_cast(CAST).unwrap();
+        assert_eq!(recording.frames().len(), 3);
+        assert_eq!(recording.duration(), Duration::from_secs_f64(61.5));
+        let recording = recording.with_idle_limit(Duration::from_secs(2));
+        assert_eq!(recording.duration(), Duration::from_secs_f64(3.5));
+
+        let started = Instant::now();
+        let mut output = recording.play(100.0);
+        let mut played = Vec::new();
+        while let Some(data) = output.recv().await {
+            played.extend(data);
+        }
+        assert_eq!(played, b"$ ls\r\nCargo.toml\r\n$ ");
+        assert!(started.elapsed() >= Duration::from_millis(35));
+        assert!(started.elapsed() < Duration::from_secs(1));
+
+        let invalid = Recording::from_cast("{\"version\": 1}");
+        assert!(matches!(invalid, Err(PlaybackError::InvalidCast(_))));
+    }
+
+    /// Events stored at fixed times.
+    struct FixedEvents(Vec<StoredEvent>);
+
+    #[async_trait::async_trait]
+    impl EventStorage for FixedEvents {
+        async fn append_event(&self, _: SessionId, _: &LogMsg) -> Result<u64, StorageError> {
+            Err(StorageError::Internal("read only".to_string()))
+        }
+
+        async fn get_events(
+            &self,
+            _: SessionId,
+            _: u64,
+            _: Option<usize>,
+        ) -> Result<Vec<StoredEvent>, StorageError> {
+            Ok(self.0.clone())
+        }
+
+        async fn event_count(&self, _: SessionId) -> Result<u64, StorageError> {
+            Ok(self.0.len() as u64)
+        }
+    }
+
+    #[tokio::test]
+    async fn test_events_timed_to_the_millisecond() {
+        let id = SessionId::new_v4();
+        let event = |seq, timestamp, subsec_millis, msg| StoredEvent {
+            session_id: id,
+            seq,
+            timestamp,
+            subsec_millis,
+            msg,
+        };
+        let storage = FixedEvents(vec![
+            event(0, 100, 900, LogMsg::Stdout("$ ls\r\n".into())),
+            event(1, 101, 50, LogMsg::Stderr("warning\r\n".into())),
+            event(2, 101, 70, LogMsg::Finished),
+            event(3, 101, 250, LogMsg::Stdout("$ ".into())),
+        ]);
+
+        let recording = Recording::from_events(&storage, id).await.unwrap();
+        let offsets: Vec<_> = recording
+            .frames()
+            .iter()
+            .map(|frame| frame.offset)
+            .collect();
+        assert_eq!(
+            offsets,
+            [
+                Duration::ZERO,
+                Duration::from_millis(150),
+                Duration::from_millis(350),
+            ]
+        );
+    }
+}
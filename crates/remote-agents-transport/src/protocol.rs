@@ -1,7 +1,10 @@
 //! Wire protocol for client-server communication.
 
 use base64::{Engine as _, engine::general_purpose::STANDARD as BASE64};
+use json_patch::Patch;
+use remote_agents_executor::approvals::{ApprovalResult, ApprovalRisk, ApprovalScope};
 use serde::{Deserialize, Serialize};
+use serde_json::Value;
 
 /// Message from client to server.
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -17,10 +20,64 @@ pub enum ClientMessage {
     ContinueSession { session_id: String, prompt: String },
     /// Interrupt current session.
     Interrupt,
+    /// Change a running session's permission mode, e.g. to `acceptEdits`.
+    SetPermissionMode { session_id: String, mode: String },
+    /// Read part of a session's stored output, for incremental replay.
+    ReadOutput {
+        session_id: String,
+        offset: u64,
+        len: usize,
+    },
+    /// Decide an `ApprovalRequest`.
+    ApprovalResponse {
+        id: String,
+        decision: ApprovalDecision,
+    },
     /// Ping for keepalive.
     Ping,
 }
 
+/// A client's decision on a tool call.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "behavior", rename_all = "snake_case")]
+pub enum ApprovalDecision {
+    /// Run the tool, with edited input if given, and approve calls like it
+    /// per `scope`.
+    Allow {
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        updated_input: Option<Value>,
+        #[serde(default, skip_serializing_if = "ApprovalScope::is_once")]
+        scope: ApprovalScope,
+    },
+    /// Refuse the tool call, telling the agent why.
+    Deny {
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        message: Option<String>,
+    },
+}
+
+impl ApprovalDecision {
+    /// The result for a call with `input`.
+    #[must_use]
+    pub fn into_result(self, input: Value) -> ApprovalResult {
+        match self {
+            Self::Allow {
+                updated_input,
+                scope,
+            } => ApprovalResult::Allow {
+                updated_input: updated_input.unwrap_or(input),
+                scope,
+                decided_by: None,
+            },
+            Self::Deny { message } => ApprovalResult::Deny {
+                message: message.unwrap_or_else(|| "Denied by the user".to_string()),
+                interrupt: None,
+                decided_by: None,
+            },
+        }
+    }
+}
+
 impl ClientMessage {
     /// Create an input message from raw bytes.
     #[must_use]
@@ -47,10 +104,30 @@ impl ClientMessage {
 pub enum ServerMessage {
     /// Terminal output data (base64 encoded).
     Output { data: String },
+    /// Part of a session's stored output (base64 encoded), in reply to
+    /// `ReadOutput`.
+    OutputRange {
+        session_id: String,
+        offset: u64,
+        data: String,
+        /// Offset to read the rest of the output from.
+        next_offset: u64,
+        total_len: u64,
+    },
     /// Session started.
     SessionStarted { session_id: String },
     /// Session ended.
     SessionEnded { session_id: String, success: bool },
+    /// A tool call awaiting a decision, answered with `ApprovalResponse`.
+    ApprovalRequest {
+        id: String,
+        tool_name: String,
+        input: Value,
+        risk: ApprovalRisk,
+        /// Changes made to the input since the model wrote it, if any.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        changes: Option<Patch>,
+    },
     /// Error message.
     Error { message: String },
     /// Pong response.
@@ -66,10 +143,23 @@ impl ServerMessage {
         }
     }
 
-    /// Decode output data from base64.
+    /// Create an output range message from raw bytes.
+    #[must_use]
+    pub fn output_range(session_id: String, offset: u64, data: &[u8], total_len: u64) -> Self {
+        let read = u64::try_from(data.len()).unwrap_or(u64::MAX);
+        Self::OutputRange {
+            session_id,
+            offset,
+            data: BASE64.encode(data),
+            next_offset: offset.saturating_add(read),
+            total_len,
+        }
+    }
+
+    /// Decode output data (of an `Output` or `OutputRange`) from base64.
     #[must_use]
     pub fn decode_output(&self) -> Option<Vec<u8>> {
-        if let Self::Output { data } = self {
+        if let Self::Output { data } | Self::OutputRange { data, .. } = self {
             BASE64.decode(data).ok()
         } else {
             None
@@ -97,6 +187,34 @@ mod tests {
         assert_eq!(decoded, original);
     }
 
+    #[test]
+    fn test_output_range_roundtrip() {
+        let msg = ServerMessage::output_range("abc".into(), 1024, b"more", 2048);
+        let json = serde_json::to_string(&msg).unwrap();
+        assert!(json.contains("output_range"));
+        assert!(json.contains("\"next_offset\":1028"));
+
+        let parsed: ServerMessage = serde_json::from_str(&json).unwrap();
+        assert_eq!(parsed.decode_output().unwrap(), b"more");
+    }
+
+    #[test]
+    fn test_approval_response_parses() {
+        let json = r#"{"type":"approval_response","id":"a1",
+            "decision":{"behavior":"allow","scope":{"type":"tool"}}}"#;
+        let ClientMessage::ApprovalResponse { id, decision } = serde_json::from_str(json).unwrap()
+        else {
+            panic!("Wrong message type");
+        };
+        assert_eq!(id, "a1");
+        let result = decision.into_result(serde_json::json!({ "command": "ls" }));
+        assert!(matches!(
+            result,
+            ApprovalResult::Allow { updated_input, scope: ApprovalScope::Tool, .. }
+                if updated_input["command"] == "ls"
+        ));
+    }
+
     #[test]
     fn test_message_serialization() {
         let msg = ClientMessage::Resize { cols: 80, rows: 24 };
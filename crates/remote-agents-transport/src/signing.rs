@@ -0,0 +1,39 @@
+//! HMAC-SHA256 signatures of HTTP bodies.
+
+use std::fmt::Write as _;
+
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+
+/// Hex HMAC-SHA256 of `parts`, concatenated, under `secret`.
+pub fn sign(secret: &[u8], parts: &[&[u8]]) -> String {
+    let tag = mac(secret, parts).finalize().into_bytes();
+    tag.iter().fold(String::new(), |mut hex, byte| {
+        let _ = write!(hex, "{byte:02x}");
+        hex
+    })
+}
+
+/// Whether `hex` is the HMAC-SHA256 of `parts` under `secret`, compared in
+/// constant time.
+pub fn verify(secret: &[u8], parts: &[&[u8]], hex: &str) -> bool {
+    decode_hex(hex).is_some_and(|tag| mac(secret, parts).verify_slice(&tag).is_ok())
+}
+
+fn mac(secret: &[u8], parts: &[&[u8]]) -> Hmac<Sha256> {
+    let mut mac = Hmac::<Sha256>::new_from_slice(secret).expect("HMAC accepts keys of any length");
+    for part in parts {
+        mac.update(part);
+    }
+    mac
+}
+
+fn decode_hex(hex: &str) -> Option<Vec<u8>> {
+    if hex.len() % 2 != 0 {
+        return None;
+    }
+    (0..hex.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(hex.get(i..i + 2)?, 16).ok())
+        .collect()
+}
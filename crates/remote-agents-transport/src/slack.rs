@@ -0,0 +1,368 @@
+//! Approvals decided from Slack messages.
+
+use std::{
+    collections::HashMap,
+    sync::{Mutex, PoisonError},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use remote_agents_executor::{
+    RiskClassifier,
+    approvals::{ApprovalError, ApprovalHandler, ApprovalResult, ApprovalRisk, ApprovalScope},
+};
+use serde_json::{Value, json};
+use tokio::sync::oneshot;
+use uuid::Uuid;
+
+use crate::{protocol::ApprovalDecision, signing};
+
+/// Slack Web API base URL.
+const SLACK_API_URL: &str = "https://slack.com/api";
+
+/// Start of the `response_url`s Slack sends, the only URLs messages are
+/// updated through.
+const SLACK_RESPONSE_URL: &str = "https://hooks.slack.com/";
+
+/// Longest input shown in a message, in bytes.
+const MAX_INPUT_LEN: usize = 2000;
+
+/// Oldest request timestamp accepted by `verify`, in seconds, as Slack
+/// recommends to stop replayed requests.
+const MAX_REQUEST_AGE_SECS: i64 = 5 * 60;
+
+/// Approval handler asking on Slack, e.g. an on-call channel.
+///
+/// Each request is posted to the channel as a message with Approve and
+/// Deny buttons. Route the app's interactivity requests to
+/// `handle_interaction`, which checks them against the app's signing
+/// secret: the first button pressed decides the request, and the message
+/// is updated to say who decided it.
+pub struct SlackApprovalHandler {
+    client: reqwest::Client,
+    api_url: String,
+    token: String,
+    channel: String,
+    signing_secret: Option<Vec<u8>>,
+    classifier: RiskClassifier,
+    /// Waiting requests, sent their decision and who made it.
+    pending: Mutex<HashMap<String, oneshot::Sender<(ApprovalDecision, String)>>>,
+}
+
+impl SlackApprovalHandler {
+    /// Post requests to `channel` as the bot with `token`.
+    #[must_use]
+    pub fn new(token: impl Into<String>, channel: impl Into<String>) -> Self {
+        Self {
+            client: reqwest::Client::new(),
+            api_url: SLACK_API_URL.to_string(),
+            token: token.into(),
+            channel: channel.into(),
+            signing_secret: None,
+            classifier: RiskClassifier::new(),
+            pending: Mutex::default(),
+        }
+    }
+
+    /// Check interactivity requests against the app's `signing_secret`.
+    #[must_use]
+    pub fn with_signing_secret(mut self, signing_secret: impl Into<Vec<u8>>) -> Self {
+        self.signing_secret = Some(signing_secret.into());
+        self
+    }
+
+    /// Judge the risk shown with requests with `classifier`.
+    #[must_use]
+    pub fn with_risk_classifier(mut self, classifier: RiskClassifier) -> Self {
+        self.classifier = classifier;
+        self
+    }
+
+    /// Call the Web API at `api_url` instead of Slack's.
+    #[must_use]
+    pub fn with_api_url(mut self, api_url: impl Into<String>) -> Self {
+        self.api_url = api_url.into();
+        self
+    }
+
+    /// Whether an interactivity request is from Slack: `signature` and
+    /// `timestamp` are its `X-Slack-Signature` and
+    /// `X-Slack-Request-Timestamp` headers. Always false without a signing
+    /// secret.
+    #[must_use]
+    pub fn verify(&self, timestamp: &str, body: &[u8], signature: &str) -> bool {
+        let Some(secret) = &self.signing_secret else {
+            return false;
+        };
+        let recent = timestamp
+            .parse::<i64>()
+            .is_ok_and(|sent| (now() - sent).abs() <= MAX_REQUEST_AGE_SECS);
+        let Some(hex) = signature.strip_prefix("v0=") else {
+            return false;
+        };
+        recent && signing::verify(secret, &[b"v0:", timestamp.as_bytes(), b":", body], hex)
+    }
+
+    /// Decide the request whose button was pressed in an interactivity
+    /// request, given its form-encoded `body` and headers as for `verify`,
+    /// returning whether it is from Slack and the request was still
+    /// waiting.
+    pub async fn handle_interaction(&self, timestamp: &str, body: &[u8], signature: &str) -> bool {
+        if !self.verify(timestamp, body, signature) {
+            tracing::warn!("Rejected Slack interaction with an invalid signature");
+            return false;
+        }
+        let payload = form_urlencoded::parse(body)
+            .find(|(key, _)| key == "payload")
+            .and_then(|(_, payload)| serde_json::from_str::<Value>(&payload).ok());
+        let Some(payload) = payload else {
+            return false;
+        };
+        let Some(action) = payload
+            .get("actions")
+            .and_then(Value::as_array)
+            .and_then(|actions| actions.first())
+        else {
+            return false;
+        };
+        let Some(id) = action.get("value").and_then(Value::as_str) else {
+            return false;
+        };
+        let user = ["username", "name", "id"]
+            .iter()
+            .find_map(|key| payload.get("user")?.get(key)?.as_str())
+            .unwrap_or("someone");
+        let (decision, outcome) = match action.get("action_id").and_then(Value::as_str) {
+            Some("approve") => (
+                ApprovalDecision::Allow {
+                    updated_input: None,
+                    scope: ApprovalScope::Once,
+                },
+                "Approved",
+            ),
+            Some("deny") => (
+                ApprovalDecision::Deny {
+                    message: Some(format!("Denied by {user} in Slack")),
+                },
+                "Denied",
+            ),
+            _ => return false,
+        };
+
+        let waiter = self
+            .pending
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .remove(id);
+        let decided =
+            waiter.is_some_and(|waiter| waiter.send((decision, user.to_string())).is_ok());
+        if decided {
+            let url = payload
+                .get("response_url")
+                .and_then(Value::as_str)
+                .filter(|url| url.starts_with(SLACK_RESPONSE_URL));
+            if let Some(url) = url {
+                let update = json!({
+                    "replace_original": true,
+                    "text": format!("{outcome} by {user}"),
+                });
+                if let Err(e) = self.client.post(url).json(&update).send().await {
+                    tracing::warn!("Failed to update Slack approval message: {e}");
+                }
+            }
+        }
+        decided
+    }
+
+    /// Post the message asking about request `id`.
+    async fn post_request(
+        &self,
+        id: &str,
+        tool_name: &str,
+        tool_input: &Value,
+    ) -> Result<(), ApprovalError> {
+        let risk = match self.classifier.classify(tool_name, tool_input) {
+            ApprovalRisk::Low => "low",
+            ApprovalRisk::Medium => "medium",
+            ApprovalRisk::High => "high",
+        };
+        let text = format!("Approve *{tool_name}* ({risk} risk)?");
+        let button = |label: &str, action_id: &str, style: &str| {
+            json!({
+                "type": "button",
+                "text": { "type": "plain_text", "text": label },
+                "style": style,
+                "action_id": action_id,
+                "value": id,
+            })
+        };
+        let message = json!({
+            "channel": self.channel,
+            "text": text,
+            "blocks": [
+                {
+                    "type": "section",
+                    "text": {
+                        "type": "mrkdwn",
+                        "text": format!("{text}\n```{}```", summary(tool_input)),
+                    },
+                },
+                {
+                    "type": "actions",
+                    "elements": [
+                        button("Approve", "approve", "primary"),
+                        button("Deny", "deny", "danger"),
+                    ],
+                },
+            ],
+        });
+        let response: Value = self
+            .client
+            .post(format!("{}/chat.postMessage", self.api_url))
+            .bearer_auth(&self.token)
+            .json(&message)
+            .send()
+            .await
+            .map_err(request_failed)?
+            .json()
+            .await
+            .map_err(request_failed)?;
+        if response.get("ok").and_then(Value::as_bool) == Some(true) {
+            return Ok(());
+        }
+        let error = response
+            .get("error")
+            .and_then(Value::as_str)
+            .unwrap_or("unknown error");
+        Err(ApprovalError::RequestFailed(format!("Slack: {error}")))
+    }
+}
+
+#[async_trait::async_trait]
+impl ApprovalHandler for SlackApprovalHandler {
+    async fn request_approval(
+        &self,
+        tool_name: &str,
+        tool_input: Value,
+        _tool_call_id: &str,
+    ) -> Result<ApprovalResult, ApprovalError> {
+        let id = Uuid::new_v4().to_string();
+        let (decision_tx, decision_rx) = oneshot::channel();
+        {
+            let mut pending = self.pending.lock().unwrap_or_else(PoisonError::into_inner);
+            // Requests whose caller stopped waiting, e.g. on a timeout.
+            pending.retain(|_, waiter| !waiter.is_closed());
+            pending.insert(id.clone(), decision_tx);
+        }
+        if let Err(e) = self.post_request(&id, tool_name, &tool_input).await {
+            self.pending
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner)
+                .remove(&id);
+            return Err(e);
+        }
+        let (decision, user) = decision_rx
+            .await
+            .map_err(|_| ApprovalError::ServiceUnavailable)?;
+        Ok(decision.into_result(tool_input).with_decided_by(user))
+    }
+}
+
+/// The command or path of a tool call, or else its input as JSON,
+/// shortened to fit a message.
+fn summary(tool_input: &Value) -> String {
+    let mut summary = ["command", "file_path"]
+        .iter()
+        .find_map(|key| tool_input.get(key).and_then(Value::as_str))
+        .map_or_else(|| tool_input.to_string(), str::to_string);
+    if summary.len() > MAX_INPUT_LEN {
+        let mut end = MAX_INPUT_LEN;
+        while !summary.is_char_boundary(end) {
+            end -= 1;
+        }
+        summary.truncate(end);
+        summary.push('…');
+    }
+    // Keep the input from closing the code block.
+    summary.replace("```", "'''")
+}
+
+/// Current time as Unix epoch seconds.
+fn now() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| i64::try_from(d.as_secs()).unwrap_or(i64::MAX))
+}
+
+fn request_failed(e: impl std::fmt::Display) -> ApprovalError {
+    ApprovalError::RequestFailed(e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+
+    #[test]
+    fn test_verify_signed_request() {
+        let handler = SlackApprovalHandler::new("xoxb-token", "C123").with_signing_secret("s3cret");
+        let body = b"payload=%7B%7D";
+        let timestamp = now().to_string();
+        let signature = format!(
+            "v0={}",
+            signing::sign(b"s3cret", &[b"v0:", timestamp.as_bytes(), b":", body])
+        );
+        assert!(handler.verify(&timestamp, body, &signature));
+        assert!(!handler.verify(&timestamp, b"payload=other", &signature));
+        assert!(!handler.verify("1531420618", body, &signature));
+    }
+
+    #[tokio::test]
+    async fn test_button_decides_request() {
+        let handler = SlackApprovalHandler::new("xoxb-token", "C123").with_signing_secret("s3cret");
+        let (decision_tx, decision_rx) = oneshot::channel();
+        handler
+            .pending
+            .lock()
+            .unwrap()
+            .insert("r1".into(), decision_tx);
+        // Messages are only updated through Slack.
+        let elsewhere = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+
+        let payload = json!({
+            "type": "block_actions",
+            "user": { "id": "U1", "username": "oncall" },
+            "actions": [{ "action_id": "deny", "value": "r1" }],
+            "response_url": format!("http://{}/", elsewhere.local_addr().unwrap()),
+        });
+        let body = form_urlencoded::Serializer::new(String::new())
+            .append_pair("payload", &payload.to_string())
+            .finish()
+            .into_bytes();
+        let timestamp = now().to_string();
+        let signature = format!(
+            "v0={}",
+            signing::sign(b"s3cret", &[b"v0:", timestamp.as_bytes(), b":", &body])
+        );
+        let forged = handler.handle_interaction(&timestamp, &body, "v0=00").await;
+        assert!(!forged);
+        let decided = handler
+            .handle_interaction(&timestamp, &body, &signature)
+            .await;
+        assert!(decided);
+        let (decision, user) = decision_rx.await.unwrap();
+        assert_eq!(
+            decision,
+            ApprovalDecision::Deny {
+                message: Some("Denied by oncall in Slack".into()),
+            }
+        );
+        assert_eq!(user, "oncall");
+        let posted = tokio::time::timeout(Duration::from_millis(50), elsewhere.accept()).await;
+        assert!(posted.is_err());
+        let again = handler
+            .handle_interaction(&timestamp, &body, &signature)
+            .await;
+        assert!(!again);
+    }
+}
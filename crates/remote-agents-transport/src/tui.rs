@@ -1,5 +1,7 @@
 //! TUI transport bridge for ratatui applications.
 
+pub mod approval;
+
 use std::sync::Arc;
 
 use crossterm::event::{Event, KeyCode, KeyEvent, KeyModifiers};
@@ -7,6 +9,8 @@ use tokio::sync::mpsc;
 
 use crate::protocol::{ClientMessage, ServerMessage};
 
+pub use approval::{ApprovalChoice, ApprovalPrompt, TuiApprovalHandler};
+
 /// TUI bridge for connecting terminal UI to session.
 pub struct TuiBridge {
     /// Sender for client messages.
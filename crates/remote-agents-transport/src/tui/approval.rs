@@ -0,0 +1,329 @@
+//! Approval prompts for terminal UIs.
+
+use crossterm::event::{KeyCode, KeyEvent};
+use ratatui::{
+    buffer::Buffer,
+    layout::{Constraint, Layout, Rect},
+    style::{Color, Modifier, Style},
+    text::{Line, Span},
+    widgets::{Block, Clear, Paragraph, Widget, Wrap},
+};
+use remote_agents_executor::{
+    RiskClassifier,
+    approvals::{ApprovalError, ApprovalHandler, ApprovalResult, ApprovalRisk, ApprovalScope},
+    normalized::ToolKind,
+};
+use serde_json::Value;
+use tokio::sync::{mpsc, oneshot};
+
+/// Answer to an approval prompt.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ApprovalChoice {
+    /// Allow this call.
+    Allow,
+    /// Deny this call.
+    Deny,
+    /// Allow this call and every later call of the same tool.
+    AlwaysAllow,
+}
+
+impl ApprovalChoice {
+    const ALL: [Self; 3] = [Self::Allow, Self::Deny, Self::AlwaysAllow];
+
+    const fn label(self) -> &'static str {
+        match self {
+            Self::Allow => "Allow (y)",
+            Self::Deny => "Deny (n)",
+            Self::AlwaysAllow => "Always allow (a)",
+        }
+    }
+}
+
+/// Approval handler asking the user of a terminal UI.
+///
+/// Each request arrives on the receiver returned by `new` as an
+/// `ApprovalPrompt`, for the application to show, e.g. as a modal over
+/// its other widgets, and to pass key events to until it is answered.
+pub struct TuiApprovalHandler {
+    prompts: mpsc::UnboundedSender<ApprovalPrompt>,
+    classifier: RiskClassifier,
+}
+
+impl TuiApprovalHandler {
+    /// Create a handler and the receiver of its prompts.
+    #[must_use]
+    pub fn new() -> (Self, mpsc::UnboundedReceiver<ApprovalPrompt>) {
+        let (prompts, prompts_rx) = mpsc::unbounded_channel();
+        let handler = Self {
+            prompts,
+            classifier: RiskClassifier::new(),
+        };
+        (handler, prompts_rx)
+    }
+
+    /// Judge the risk shown with prompts with `classifier`.
+    #[must_use]
+    pub fn with_risk_classifier(mut self, classifier: RiskClassifier) -> Self {
+        self.classifier = classifier;
+        self
+    }
+}
+
+#[async_trait::async_trait]
+impl ApprovalHandler for TuiApprovalHandler {
+    async fn request_approval(
+        &self,
+        tool_name: &str,
+        tool_input: Value,
+        _tool_call_id: &str,
+    ) -> Result<ApprovalResult, ApprovalError> {
+        let (responder, response) = oneshot::channel();
+        let prompt = ApprovalPrompt {
+            risk: self.classifier.classify(tool_name, &tool_input),
+            tool_name: tool_name.to_string(),
+            input: tool_input,
+            selected: ApprovalChoice::Allow,
+            responder: Some(responder),
+        };
+        self.prompts
+            .send(prompt)
+            .map_err(|_| ApprovalError::ServiceUnavailable)?;
+        response
+            .await
+            .map_err(|_| ApprovalError::ServiceUnavailable)
+    }
+}
+
+/// A tool call awaiting the user's answer.
+///
+/// Render it with `Widget` and pass it key events with `handle_key`.
+/// Dropping it unanswered fails the request.
+pub struct ApprovalPrompt {
+    pub tool_name: String,
+    pub input: Value,
+    pub risk: ApprovalRisk,
+    selected: ApprovalChoice,
+    responder: Option<oneshot::Sender<ApprovalResult>>,
+}
+
+impl ApprovalPrompt {
+    /// The highlighted choice, taken on Enter.
+    #[must_use]
+    pub const fn selected(&self) -> ApprovalChoice {
+        self.selected
+    }
+
+    /// Whether the prompt has been answered, and can be dismissed.
+    #[must_use]
+    pub const fn is_answered(&self) -> bool {
+        self.responder.is_none()
+    }
+
+    /// Handle a key: Left/Right/Tab move between choices and Enter takes
+    /// the highlighted one; `y`, `n`, and `a` take a choice directly, and
+    /// Esc denies. Returns whether the key answered the prompt.
+    pub fn handle_key(&mut self, key: &KeyEvent) -> bool {
+        let position = ApprovalChoice::ALL
+            .iter()
+            .position(|choice| *choice == self.selected)
+            .unwrap_or_default();
+        let count = ApprovalChoice::ALL.len();
+        let choice = match key.code {
+            KeyCode::Left | KeyCode::BackTab => {
+                self.selected = ApprovalChoice::ALL[(position + count - 1) % count];
+                return false;
+            }
+            KeyCode::Right | KeyCode::Tab => {
+                self.selected = ApprovalChoice::ALL[(position + 1) % count];
+                return false;
+            }
+            KeyCode::Enter => self.selected,
+            KeyCode::Char('y') => ApprovalChoice::Allow,
+            KeyCode::Char('n') | KeyCode::Esc => ApprovalChoice::Deny,
+            KeyCode::Char('a') => ApprovalChoice::AlwaysAllow,
+            _ => return false,
+        };
+        self.answer(choice)
+    }
+
+    /// Answer with `choice`, returning whether the prompt was unanswered.
+    pub fn answer(&mut self, choice: ApprovalChoice) -> bool {
+        let Some(responder) = self.responder.take() else {
+            return false;
+        };
+        self.selected = choice;
+        let updated_input = self.input.clone();
+        let result = match choice {
+            ApprovalChoice::Allow => ApprovalResult::allow(updated_input),
+            ApprovalChoice::AlwaysAllow => ApprovalResult::Allow {
+                updated_input,
+                scope: ApprovalScope::Tool,
+                decided_by: None,
+            },
+            ApprovalChoice::Deny => ApprovalResult::Deny {
+                message: "Denied by the user".to_string(),
+                interrupt: None,
+                decided_by: None,
+            },
+        };
+        // The agent may have stopped waiting.
+        let _ = responder.send(result);
+        true
+    }
+
+    /// The input as lines: a command highlighted, anything else as
+    /// pretty-printed JSON.
+    fn input_lines(&self) -> Vec<Line<'static>> {
+        let command = self.input.get("command").and_then(Value::as_str);
+        match command {
+            Some(command) if ToolKind::from_name(&self.tool_name) == ToolKind::Execute => {
+                command.lines().map(highlight_command).collect()
+            }
+            _ => serde_json::to_string_pretty(&self.input)
+                .unwrap_or_default()
+                .lines()
+                .map(highlight_json)
+                .collect(),
+        }
+    }
+}
+
+impl Widget for &ApprovalPrompt {
+    /// Render as a modal centred in `area`.
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        let width = (area.width / 5 * 4).max(area.width.min(40));
+        let height = (area.height / 5 * 3).max(area.height.min(8));
+        let modal = Rect {
+            x: area.x + (area.width - width) / 2,
+            y: area.y + (area.height - height) / 2,
+            width,
+            height,
+        };
+        let (risk, color) = match self.risk {
+            ApprovalRisk::Low => ("low risk", Color::Green),
+            ApprovalRisk::Medium => ("medium risk", Color::Yellow),
+            ApprovalRisk::High => ("high risk", Color::Red),
+        };
+        let block = Block::bordered()
+            .title(format!(" Allow {}? ({risk}) ", self.tool_name))
+            .border_style(Style::default().fg(color));
+        let inner = block.inner(modal);
+        Clear.render(modal, buf);
+        block.render(modal, buf);
+
+        let [input, choices] =
+            Layout::vertical([Constraint::Min(1), Constraint::Length(1)]).areas(inner);
+        Paragraph::new(self.input_lines())
+            .wrap(Wrap { trim: false })
+            .render(input, buf);
+        let spans: Vec<Span> = ApprovalChoice::ALL
+            .iter()
+            .flat_map(|choice| {
+                let style = if *choice == self.selected {
+                    Style::default().add_modifier(Modifier::REVERSED)
+                } else {
+                    Style::default()
+                };
+                [
+                    Span::styled(format!(" {} ", choice.label()), style),
+                    Span::raw("  "),
+                ]
+            })
+            .collect();
+        Line::from(spans).render(choices, buf);
+    }
+}
+
+/// A line of a shell command, with the program and its flags highlighted.
+fn highlight_command(line: &str) -> Line<'static> {
+    let mut expect_program = true;
+    let spans = line
+        .split_inclusive(' ')
+        .map(|word| {
+            let trimmed = word.trim();
+            let style = if ["&&", "||", "|", ";"].contains(&trimmed) {
+                expect_program = true;
+                Style::default().fg(Color::Magenta)
+            } else if expect_program && !trimmed.is_empty() {
+                expect_program = false;
+                Style::default()
+                    .fg(Color::Yellow)
+                    .add_modifier(Modifier::BOLD)
+            } else if trimmed.starts_with('-') {
+                Style::default().fg(Color::Cyan)
+            } else {
+                Style::default()
+            };
+            Span::styled(word.to_string(), style)
+        })
+        .collect::<Vec<_>>();
+    Line::from(spans)
+}
+
+/// A line of pretty-printed JSON, with its key highlighted.
+fn highlight_json(line: &str) -> Line<'static> {
+    let trimmed = line.trim_start();
+    let indent = &line[..line.len() - trimmed.len()];
+    let key_end = trimmed
+        .strip_prefix('"')
+        .and_then(|rest| rest.find("\": "))
+        // Past the key's closing quote.
+        .map(|end| end + 2);
+    key_end.map_or_else(
+        || Line::raw(line.to_string()),
+        |end| {
+            Line::from(vec![
+                Span::raw(indent.to_string()),
+                Span::styled(trimmed[..end].to_string(), Style::default().fg(Color::Blue)),
+                Span::raw(trimmed[end..].to_string()),
+            ])
+        },
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use crossterm::event::KeyModifiers;
+    use serde_json::json;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_prompt_answered_from_keys() {
+        let (handler, mut prompts) = TuiApprovalHandler::new();
+        let request = tokio::spawn(async move {
+            handler
+                .request_approval("Bash", json!({ "command": "rm -rf target" }), "t1")
+                .await
+        });
+        let mut prompt = prompts.recv().await.unwrap();
+        assert_eq!(prompt.risk, ApprovalRisk::High);
+
+        let mut buf = Buffer::empty(Rect::new(0, 0, 60, 12));
+        (&prompt).render(buf.area, &mut buf);
+        let text: String = buf
+            .content()
+            .iter()
+            .map(ratatui::buffer::Cell::symbol)
+            .collect();
+        assert!(text.contains("Allow Bash?"));
+        assert!(text.contains("rm -rf target"));
+
+        let key = |code| KeyEvent::new(code, KeyModifiers::NONE);
+        assert!(!prompt.handle_key(&key(KeyCode::Right)));
+        assert!(!prompt.handle_key(&key(KeyCode::Right)));
+        assert_eq!(prompt.selected(), ApprovalChoice::AlwaysAllow);
+        assert!(prompt.handle_key(&key(KeyCode::Enter)));
+        assert!(prompt.is_answered());
+        assert!(!prompt.handle_key(&key(KeyCode::Char('n'))));
+
+        let result = request.await.unwrap().unwrap();
+        assert!(matches!(
+            result,
+            ApprovalResult::Allow {
+                scope: ApprovalScope::Tool,
+                ..
+            }
+        ));
+    }
+}
@@ -0,0 +1,296 @@
+//! Approvals decided by an external HTTP service.
+
+use std::{
+    collections::HashMap,
+    sync::{Mutex, PoisonError},
+};
+
+use json_patch::Patch;
+use remote_agents_executor::{
+    RiskClassifier,
+    approvals::{ApprovalError, ApprovalHandler, ApprovalResult, ApprovalRisk},
+};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tokio::sync::oneshot;
+use uuid::Uuid;
+
+use crate::{protocol::ApprovalDecision, signing};
+
+/// Header carrying `sha256=<hex HMAC-SHA256 of the body>`.
+pub const SIGNATURE_HEADER: &str = "X-Signature-256";
+
+/// Body posted to the webhook for each tool call.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WebhookApprovalRequest {
+    /// ID to decide the request by in a callback.
+    pub id: String,
+    /// Name of the tool the agent wants to call, e.g. `Bash`.
+    pub tool_name: String,
+    /// Agent-assigned tool call identifier.
+    pub tool_call_id: String,
+    /// Input the tool would be called with, after any changes.
+    pub input: Value,
+    /// How much harm the call could do, e.g. to decide how to route it.
+    pub risk: ApprovalRisk,
+    /// Changes made to the input since the model wrote it, if any.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub changes: Option<Patch>,
+}
+
+/// Approval handler asking an HTTP endpoint, e.g. an existing workflow
+/// system.
+///
+/// Each request is posted as a `WebhookApprovalRequest`, signed with
+/// `SIGNATURE_HEADER` if a secret is set. The endpoint either decides at
+/// once, answering with an `ApprovalDecision`, or accepts the request with
+/// an empty body (e.g. `202 Accepted`) and decides later through a
+/// callback the application routes to `respond`.
+pub struct WebhookApprovalHandler {
+    client: reqwest::Client,
+    url: String,
+    secret: Option<Vec<u8>>,
+    classifier: RiskClassifier,
+    pending: Mutex<HashMap<String, oneshot::Sender<ApprovalDecision>>>,
+}
+
+impl WebhookApprovalHandler {
+    /// POST requests to `url`.
+    #[must_use]
+    pub fn new(url: impl Into<String>) -> Self {
+        Self {
+            client: reqwest::Client::new(),
+            url: url.into(),
+            secret: None,
+            classifier: RiskClassifier::new(),
+            pending: Mutex::default(),
+        }
+    }
+
+    /// Sign requests with `secret`.
+    #[must_use]
+    pub fn with_secret(mut self, secret: impl Into<Vec<u8>>) -> Self {
+        self.secret = Some(secret.into());
+        self
+    }
+
+    /// Judge the risk sent with requests with `classifier`.
+    #[must_use]
+    pub fn with_risk_classifier(mut self, classifier: RiskClassifier) -> Self {
+        self.classifier = classifier;
+        self
+    }
+
+    /// Send requests with `client`, e.g. one with timeouts or a proxy.
+    #[must_use]
+    pub fn with_client(mut self, client: reqwest::Client) -> Self {
+        self.client = client;
+        self
+    }
+
+    /// Decide request `id`, returning whether it was still waiting.
+    pub fn respond(&self, id: &str, decision: ApprovalDecision) -> bool {
+        let waiter = self
+            .pending
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .remove(id);
+        waiter.is_some_and(|waiter| waiter.send(decision).is_ok())
+    }
+
+    /// Whether `signature`, a `SIGNATURE_HEADER` value, signs `body` with
+    /// the secret, e.g. to authenticate callbacks. Always false without a
+    /// secret.
+    #[must_use]
+    pub fn verify(&self, body: &[u8], signature: &str) -> bool {
+        let Some(secret) = &self.secret else {
+            return false;
+        };
+        signature
+            .strip_prefix("sha256=")
+            .is_some_and(|hex| signing::verify(secret, &[body], hex))
+    }
+
+    /// `SIGNATURE_HEADER` value for `body`, if requests are signed.
+    fn sign(&self, body: &[u8]) -> Option<String> {
+        let secret = self.secret.as_ref()?;
+        Some(format!("sha256={}", signing::sign(secret, &[body])))
+    }
+
+    /// POST `request`, returning the decision if the endpoint made one.
+    async fn post(
+        &self,
+        request: &WebhookApprovalRequest,
+    ) -> Result<Option<ApprovalDecision>, ApprovalError> {
+        let body = serde_json::to_vec(request).map_err(request_failed)?;
+        let mut post = self
+            .client
+            .post(&self.url)
+            .header(reqwest::header::CONTENT_TYPE, "application/json");
+        if let Some(signature) = self.sign(&body) {
+            post = post.header(SIGNATURE_HEADER, signature);
+        }
+        let response = post.body(body).send().await.map_err(request_failed)?;
+        let status = response.status();
+        if !status.is_success() {
+            return Err(ApprovalError::RequestFailed(format!(
+                "Webhook returned {status}"
+            )));
+        }
+        let body = response.bytes().await.map_err(request_failed)?;
+        if body.iter().all(u8::is_ascii_whitespace) {
+            return Ok(None);
+        }
+        serde_json::from_slice(&body)
+            .map(Some)
+            .map_err(request_failed)
+    }
+
+    async fn request(
+        &self,
+        tool_name: &str,
+        tool_input: Value,
+        tool_call_id: &str,
+        changes: Option<&Patch>,
+    ) -> Result<ApprovalResult, ApprovalError> {
+        let request = WebhookApprovalRequest {
+            id: Uuid::new_v4().to_string(),
+            tool_name: tool_name.to_string(),
+            tool_call_id: tool_call_id.to_string(),
+            risk: self.classifier.classify(tool_name, &tool_input),
+            input: tool_input,
+            changes: changes.cloned(),
+        };
+        // Registered first, as the callback may come before the response.
+        let (decision_tx, decision_rx) = oneshot::channel();
+        {
+            let mut pending = self.pending.lock().unwrap_or_else(PoisonError::into_inner);
+            // Requests whose caller stopped waiting, e.g. on a timeout.
+            pending.retain(|_, waiter| !waiter.is_closed());
+            pending.insert(request.id.clone(), decision_tx);
+        }
+        let posted = self.post(&request).await;
+        if !matches!(posted, Ok(None)) {
+            self.pending
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner)
+                .remove(&request.id);
+        }
+        let decision = match posted? {
+            Some(decision) => decision,
+            None => decision_rx
+                .await
+                .map_err(|_| ApprovalError::ServiceUnavailable)?,
+        };
+        Ok(decision.into_result(request.input))
+    }
+}
+
+#[async_trait::async_trait]
+impl ApprovalHandler for WebhookApprovalHandler {
+    async fn request_approval(
+        &self,
+        tool_name: &str,
+        tool_input: Value,
+        tool_call_id: &str,
+    ) -> Result<ApprovalResult, ApprovalError> {
+        self.request(tool_name, tool_input, tool_call_id, None)
+            .await
+    }
+
+    async fn request_modified_approval(
+        &self,
+        tool_name: &str,
+        tool_input: Value,
+        tool_call_id: &str,
+        changes: &Patch,
+    ) -> Result<ApprovalResult, ApprovalError> {
+        self.request(tool_name, tool_input, tool_call_id, Some(changes))
+            .await
+    }
+}
+
+fn request_failed(e: impl std::fmt::Display) -> ApprovalError {
+    ApprovalError::RequestFailed(e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use serde_json::json;
+    use tokio::{
+        io::{AsyncReadExt, AsyncWriteExt},
+        net::TcpListener,
+    };
+
+    use super::*;
+
+    /// Accept one request, answer it with `202 Accepted`, and return its
+    /// signature header and body.
+    async fn accept_one(listener: TcpListener) -> (String, Vec<u8>) {
+        let (mut stream, _) = listener.accept().await.unwrap();
+        let mut received = Vec::new();
+        let mut buf = [0; 4096];
+        let (head, body) = loop {
+            let n = stream.read(&mut buf).await.unwrap();
+            received.extend_from_slice(&buf[..n]);
+            let text = String::from_utf8_lossy(&received).to_string();
+            let Some((head, body)) = text.split_once("\r\n\r\n") else {
+                continue;
+            };
+            let len: usize = head
+                .lines()
+                .find_map(|line| {
+                    line.to_lowercase()
+                        .strip_prefix("content-length: ")?
+                        .parse()
+                        .ok()
+                })
+                .unwrap();
+            if body.len() >= len {
+                break (head.to_string(), body.as_bytes().to_vec());
+            }
+        };
+        stream
+            .write_all(b"HTTP/1.1 202 Accepted\r\ncontent-length: 0\r\n\r\n")
+            .await
+            .unwrap();
+        let signature = head
+            .lines()
+            .find_map(|line| line.strip_prefix("x-signature-256: "))
+            .unwrap()
+            .to_string();
+        (signature, body)
+    }
+
+    #[tokio::test]
+    async fn test_callback_decides_signed_request() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/approvals", listener.local_addr().unwrap());
+        let server = tokio::spawn(accept_one(listener));
+        let handler = Arc::new(WebhookApprovalHandler::new(url).with_secret("s3cret"));
+
+        let request = tokio::spawn({
+            let handler = Arc::clone(&handler);
+            async move {
+                handler
+                    .request_approval("Bash", json!({ "command": "ls" }), "t1")
+                    .await
+            }
+        });
+        let (signature, body) = server.await.unwrap();
+        assert!(handler.verify(&body, &signature));
+        assert!(!handler.verify(b"tampered", &signature));
+        let sent: WebhookApprovalRequest = serde_json::from_slice(&body).unwrap();
+        assert_eq!(sent.tool_call_id, "t1");
+        assert_eq!(sent.risk, ApprovalRisk::Low);
+
+        let deny = ApprovalDecision::Deny {
+            message: Some("not today".into()),
+        };
+        assert!(handler.respond(&sent.id, deny));
+        let result = request.await.unwrap().unwrap();
+        assert!(matches!(result, ApprovalResult::Deny { message, .. } if message == "not today"));
+    }
+}
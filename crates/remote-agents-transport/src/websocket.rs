@@ -1,6 +1,7 @@
 //! WebSocket transport for web terminals.
 
-use std::sync::Arc;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, PoisonError};
 
 use axum::{
     extract::{
@@ -10,22 +11,221 @@ use axum::{
     response::IntoResponse,
 };
 use futures::{SinkExt, StreamExt};
-use tokio::sync::mpsc;
+use json_patch::Patch;
+use remote_agents_core::{Executor, SessionStorage, traits::SessionId};
+use remote_agents_executor::{
+    RiskClassifier,
+    approvals::{ApprovalError, ApprovalHandler, ApprovalResult},
+};
+use remote_agents_session::{SessionManager, manager::ManagerError};
+use serde_json::Value;
+use tokio::sync::{mpsc, oneshot};
+use uuid::Uuid;
+
+use crate::protocol::{ApprovalDecision, ClientMessage, ServerMessage};
+
+/// Most output bytes sent in reply to one `ReadOutput`.
+const MAX_OUTPUT_READ: usize = 1024 * 1024;
 
-use crate::protocol::{ClientMessage, ServerMessage};
+/// Sessions clients can read and control, e.g. a `SessionManager`.
+#[async_trait::async_trait]
+pub trait WsSessions: Send + Sync {
+    /// Read up to `len` bytes of a session's stored output from byte
+    /// `offset`, with the output's total length.
+    async fn read_output(
+        &self,
+        session_id: SessionId,
+        offset: u64,
+        len: usize,
+    ) -> Result<(Vec<u8>, u64), ManagerError>;
+
+    /// Change a running session's permission mode, e.g. to `acceptEdits`.
+    async fn set_permission_mode(
+        &self,
+        session_id: SessionId,
+        mode: &str,
+    ) -> Result<(), ManagerError>;
+}
+
+#[async_trait::async_trait]
+impl<S, E> WsSessions for SessionManager<S, E>
+where
+    S: SessionStorage + 'static,
+    E: Executor,
+{
+    async fn read_output(
+        &self,
+        session_id: SessionId,
+        offset: u64,
+        len: usize,
+    ) -> Result<(Vec<u8>, u64), ManagerError> {
+        let data = self.get_output_range(session_id, offset, len).await?;
+        Ok((data, self.output_len(session_id).await?))
+    }
+
+    async fn set_permission_mode(
+        &self,
+        session_id: SessionId,
+        mode: &str,
+    ) -> Result<(), ManagerError> {
+        self.set_session_permission_mode(session_id, mode).await
+    }
+}
 
 /// WebSocket handler state.
 #[derive(Clone)]
 pub struct WsState<S> {
     /// Application state.
     pub app_state: Arc<S>,
+    /// Asks connected clients about tool calls, if set.
+    pub approvals: Option<Arc<WsApprovalHandler>>,
+    /// Serves clients' requests about sessions, if set.
+    pub sessions: Option<Arc<dyn WsSessions>>,
 }
 
 impl<S> WsState<S> {
     /// Create new WebSocket state.
     #[must_use]
     pub fn new(app_state: Arc<S>) -> Self {
-        Self { app_state }
+        Self {
+            app_state,
+            approvals: None,
+            sessions: None,
+        }
+    }
+
+    /// Send `approvals`' requests to connected clients and route their
+    /// responses back to it.
+    #[must_use]
+    pub fn with_approvals(mut self, approvals: Arc<WsApprovalHandler>) -> Self {
+        self.approvals = Some(approvals);
+        self
+    }
+
+    /// Serve clients' `ReadOutput` and `SetPermissionMode` requests from
+    /// `sessions`.
+    #[must_use]
+    pub fn with_sessions(mut self, sessions: Arc<dyn WsSessions>) -> Self {
+        self.sessions = Some(sessions);
+        self
+    }
+}
+
+/// A request sent to clients, awaiting their decision.
+struct WsPendingApproval {
+    request: ServerMessage,
+    decision_tx: oneshot::Sender<ApprovalDecision>,
+}
+
+/// Approval handler asking the web clients connected over WebSocket.
+///
+/// Give it to the executor, and to the router with `WsState::with_approvals`.
+/// Each request is sent as an `ApprovalRequest` to every connected client,
+/// and to clients connecting while it waits, e.g. after a page refresh; the
+/// first `ApprovalResponse` decides it.
+#[derive(Default)]
+pub struct WsApprovalHandler {
+    clients: Mutex<Vec<mpsc::UnboundedSender<ServerMessage>>>,
+    pending: Mutex<HashMap<String, WsPendingApproval>>,
+    classifier: RiskClassifier,
+}
+
+impl WsApprovalHandler {
+    /// Create a handler with no clients connected.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Judge the risk shown with requests with `classifier`.
+    #[must_use]
+    pub fn with_risk_classifier(mut self, classifier: RiskClassifier) -> Self {
+        self.classifier = classifier;
+        self
+    }
+
+    /// Send requests to a newly connected client, starting with those
+    /// already waiting.
+    fn connect(&self, client: mpsc::UnboundedSender<ServerMessage>) {
+        {
+            let mut pending = self.pending.lock().unwrap_or_else(PoisonError::into_inner);
+            // Requests whose caller stopped waiting, e.g. on a timeout.
+            pending.retain(|_, item| !item.decision_tx.is_closed());
+            for item in pending.values() {
+                let _ = client.send(item.request.clone());
+            }
+        }
+        self.clients
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .push(client);
+    }
+
+    /// Decide request `id`, returning whether it was still waiting.
+    pub fn respond(&self, id: &str, decision: ApprovalDecision) -> bool {
+        let item = self
+            .pending
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .remove(id);
+        item.is_some_and(|item| item.decision_tx.send(decision).is_ok())
+    }
+
+    async fn request(
+        &self,
+        tool_name: &str,
+        tool_input: Value,
+        changes: Option<&Patch>,
+    ) -> Result<ApprovalResult, ApprovalError> {
+        let id = Uuid::new_v4().to_string();
+        let request = ServerMessage::ApprovalRequest {
+            id: id.clone(),
+            tool_name: tool_name.to_string(),
+            input: tool_input.clone(),
+            risk: self.classifier.classify(tool_name, &tool_input),
+            changes: changes.cloned(),
+        };
+        let (decision_tx, decision_rx) = oneshot::channel();
+        self.pending
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .insert(
+                id,
+                WsPendingApproval {
+                    request: request.clone(),
+                    decision_tx,
+                },
+            );
+        self.clients
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .retain(|client| client.send(request.clone()).is_ok());
+        let decision = decision_rx
+            .await
+            .map_err(|_| ApprovalError::ServiceUnavailable)?;
+        Ok(decision.into_result(tool_input))
+    }
+}
+
+#[async_trait::async_trait]
+impl ApprovalHandler for WsApprovalHandler {
+    async fn request_approval(
+        &self,
+        tool_name: &str,
+        tool_input: Value,
+        _tool_call_id: &str,
+    ) -> Result<ApprovalResult, ApprovalError> {
+        self.request(tool_name, tool_input, None).await
+    }
+
+    async fn request_modified_approval(
+        &self,
+        tool_name: &str,
+        tool_input: Value,
+        _tool_call_id: &str,
+        changes: &Patch,
+    ) -> Result<ApprovalResult, ApprovalError> {
+        self.request(tool_name, tool_input, Some(changes)).await
     }
 }
 
@@ -42,7 +242,7 @@ where
     ws.on_upgrade(|socket| handle_socket(socket, state))
 }
 
-async fn handle_socket<S>(socket: WebSocket, _state: WsState<S>)
+async fn handle_socket<S>(socket: WebSocket, state: WsState<S>)
 where
     S: Send + Sync + 'static,
 {
@@ -50,6 +250,9 @@ where
 
     // Channel for sending messages to the client
     let (tx, mut rx) = mpsc::unbounded_channel::<ServerMessage>();
+    if let Some(approvals) = &state.approvals {
+        approvals.connect(tx.clone());
+    }
 
     // Spawn task to forward messages to WebSocket
     let send_task = tokio::spawn(async move {
@@ -118,12 +321,84 @@ where
             ClientMessage::Interrupt => {
                 // TODO: Interrupt session
             }
+            ClientMessage::SetPermissionMode { session_id, mode } => {
+                let changed =
+                    set_permission_mode(state.sessions.as_deref(), &session_id, &mode).await;
+                if let Err(message) = changed {
+                    let _ = tx.send(ServerMessage::Error { message });
+                }
+            }
+            ClientMessage::ReadOutput {
+                session_id,
+                offset,
+                len,
+            } => {
+                let reply = read_output(state.sessions.as_deref(), session_id, offset, len).await;
+                let _ = tx.send(reply);
+            }
+            ClientMessage::ApprovalResponse { id, decision } => {
+                let answered = state
+                    .approvals
+                    .as_ref()
+                    .is_some_and(|approvals| approvals.respond(&id, decision));
+                if !answered {
+                    let _ = tx.send(ServerMessage::Error {
+                        message: format!("No pending approval {id}"),
+                    });
+                }
+            }
         }
     }
 
     send_task.abort();
 }
 
+/// The sessions to serve a request about `session_id` from, and its ID.
+fn session_target<'a>(
+    sessions: Option<&'a dyn WsSessions>,
+    session_id: &str,
+) -> Result<(&'a dyn WsSessions, SessionId), String> {
+    let sessions = sessions.ok_or_else(|| "Sessions are not available".to_string())?;
+    let id =
+        Uuid::parse_str(session_id).map_err(|e| format!("Invalid session ID {session_id}: {e}"))?;
+    Ok((sessions, id))
+}
+
+/// Reply to a `ReadOutput` request.
+async fn read_output(
+    sessions: Option<&dyn WsSessions>,
+    session_id: String,
+    offset: u64,
+    len: usize,
+) -> ServerMessage {
+    let (sessions, id) = match session_target(sessions, &session_id) {
+        Ok(target) => target,
+        Err(message) => return ServerMessage::Error { message },
+    };
+    match sessions
+        .read_output(id, offset, len.min(MAX_OUTPUT_READ))
+        .await
+    {
+        Ok((data, total_len)) => ServerMessage::output_range(session_id, offset, &data, total_len),
+        Err(e) => ServerMessage::Error {
+            message: format!("Cannot read output of session {session_id}: {e}"),
+        },
+    }
+}
+
+/// Handle a `SetPermissionMode` request.
+async fn set_permission_mode(
+    sessions: Option<&dyn WsSessions>,
+    session_id: &str,
+    mode: &str,
+) -> Result<(), String> {
+    let (sessions, id) = session_target(sessions, session_id)?;
+    sessions
+        .set_permission_mode(id, mode)
+        .await
+        .map_err(|e| format!("Cannot set permission mode of session {session_id}: {e}"))
+}
+
 /// Create WebSocket router.
 ///
 /// # Example
@@ -140,3 +415,16 @@ where
         .route("/ws", axum::routing::get(ws_handler::<S>))
         .with_state(WsState::new(state))
 }
+
+/// Create WebSocket router whose clients answer `approvals`' requests.
+pub fn create_ws_router_with_approvals<S>(
+    state: Arc<S>,
+    approvals: Arc<WsApprovalHandler>,
+) -> axum::Router
+where
+    S: Send + Sync + 'static + Clone,
+{
+    axum::Router::new()
+        .route("/ws", axum::routing::get(ws_handler::<S>))
+        .with_state(WsState::new(state).with_approvals(approvals))
+}